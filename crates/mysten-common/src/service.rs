@@ -0,0 +1,534 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Startup and shutdown of the long running components of a service.
+//!
+//! Components are started in the order they are added to a [`ServiceBuilder`], each one only
+//! once the components it depends on report being ready, so that e.g. a pipeline is not fed
+//! before the task committing its output runs. They are stopped in the reverse order, so that
+//! components are stopped before the components they depend on.
+//!
+//! A panicking component fails with an error carrying the panic message, which stops the service,
+//! unless it was added with [`ServiceBuilder::supervised_component`], in which case it is
+//! restarted after a backoff.
+
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use prometheus::IntCounterVec;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+pub const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+type RunFn = Box<dyn FnOnce(ComponentContext) -> BoxFuture<'static, Result<()>> + Send>;
+
+struct ComponentSpec {
+    name: &'static str,
+    depends_on: Vec<&'static str>,
+    run: RunFn,
+}
+
+/// How a supervised component is restarted after panicking.
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    /// Delay before the first restart, doubled on every consecutive restart.
+    pub initial_backoff: Duration,
+    /// Largest delay between restarts. The delay is reset once the component ran for this long
+    /// without panicking.
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+pub struct ServiceBuilder {
+    name: &'static str,
+    components: Vec<ComponentSpec>,
+    startup_timeout: Duration,
+    shutdown_timeout: Duration,
+    restart_counter: Option<IntCounterVec>,
+}
+
+impl ServiceBuilder {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            components: vec![],
+            startup_timeout: DEFAULT_STARTUP_TIMEOUT,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            restart_counter: None,
+        }
+    }
+
+    /// How long to wait for each component to become ready before giving up on starting.
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// How long to wait for each component to stop before aborting it.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Count the restarts of supervised components in `counter`, labelled by component name.
+    pub fn with_restart_counter(mut self, counter: IntCounterVec) -> Self {
+        self.restart_counter = Some(counter);
+        self
+    }
+
+    /// Add a component, started once all the components in `depends_on`, which must have been
+    /// added before, are ready. The component is ready once it calls
+    /// [`ComponentContext::set_ready`], and should return once [`ComponentContext::stopped`]
+    /// resolves.
+    pub fn component<F, Fut>(
+        mut self,
+        name: &'static str,
+        depends_on: &[&'static str],
+        run: F,
+    ) -> Self
+    where
+        F: FnOnce(ComponentContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.components.push(ComponentSpec {
+            name,
+            depends_on: depends_on.to_vec(),
+            run: Box::new(move |context| {
+                async move {
+                    catch_panic(run(context))
+                        .await
+                        .unwrap_or_else(|message| Err(anyhow!("{name} panicked: {message}")))
+                }
+                .boxed()
+            }),
+        });
+        self
+    }
+
+    /// Add a component like [`Self::component`], which is run again by calling `run` with a new
+    /// context whenever it panics, after a backoff set by `policy`. Components returning an
+    /// error are not restarted. For components holding no state that a panic could leave
+    /// inconsistent, like caches and background scans.
+    pub fn supervised_component<F, Fut>(
+        self,
+        name: &'static str,
+        depends_on: &[&'static str],
+        policy: RestartPolicy,
+        mut run: F,
+    ) -> Self
+    where
+        F: FnMut(ComponentContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.component(name, depends_on, move |mut context| async move {
+            let mut backoff = policy.initial_backoff;
+            loop {
+                let started = Instant::now();
+                let message = match catch_panic(run(context.restarted())).await {
+                    Ok(result) => return result,
+                    Err(message) => message,
+                };
+                if started.elapsed() >= policy.max_backoff {
+                    backoff = policy.initial_backoff;
+                }
+                error!("{name} panicked, restarting it in {backoff:?}: {message}");
+                if let Some(counter) = &context.restart_counter {
+                    counter.with_label_values(&[name]).inc();
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = context.stopped() => return Ok(()),
+                }
+                backoff = (backoff * 2).min(policy.max_backoff);
+            }
+        })
+    }
+
+    /// Start all components. If any of them fails to become ready, the ones already started are
+    /// stopped and the error is returned.
+    pub async fn start(self) -> Result<Service> {
+        let mut service = Service {
+            name: self.name,
+            components: vec![],
+            shutdown_timeout: self.shutdown_timeout,
+            restart_counter: self.restart_counter,
+        };
+        for spec in self.components {
+            if let Err(e) = service.start_component(spec, self.startup_timeout).await {
+                error!("Failed to start {}: {e:?}", self.name);
+                if let Err(e) = service.shutdown().await {
+                    warn!("Failed to stop {} after failing to start: {e:?}", self.name);
+                }
+                return Err(e);
+            }
+        }
+        info!("Started {}", self.name);
+        Ok(service)
+    }
+}
+
+/// Handle through which a component follows its lifecycle.
+pub struct ComponentContext {
+    name: &'static str,
+    /// Shared by the runs of a supervised component.
+    ready: Arc<watch::Sender<bool>>,
+    stop: watch::Receiver<bool>,
+    restart_counter: Option<IntCounterVec>,
+}
+
+impl ComponentContext {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Context of the next run of a supervised component.
+    fn restarted(&self) -> Self {
+        Self {
+            name: self.name,
+            ready: self.ready.clone(),
+            stop: self.stop.clone(),
+            restart_counter: self.restart_counter.clone(),
+        }
+    }
+
+    /// Report the component as ready, letting the components that depend on it start.
+    pub fn set_ready(&self) {
+        self.ready.send_replace(true);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        *self.stop.borrow()
+    }
+
+    /// Resolves once the component is asked to stop.
+    pub async fn stopped(&mut self) {
+        while !*self.stop.borrow() {
+            // The service was dropped without being shut down.
+            if self.stop.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Report the component as ready and run `future` until it completes, or until the component
+    /// is asked to stop and `future` is dropped. For components with no state to flush.
+    pub async fn run_until_stopped<F>(mut self, future: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        self.set_ready();
+        tokio::select! {
+            _ = future => Ok(()),
+            _ = self.stopped() => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentHealth {
+    pub name: &'static str,
+    pub ready: bool,
+    pub running: bool,
+}
+
+struct RunningComponent {
+    name: &'static str,
+    ready: watch::Receiver<bool>,
+    stop: watch::Sender<bool>,
+    /// Taken once the component's task is joined.
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+pub struct Service {
+    name: &'static str,
+    /// In the order they were started.
+    components: Vec<RunningComponent>,
+    shutdown_timeout: Duration,
+    restart_counter: Option<IntCounterVec>,
+}
+
+impl Service {
+    async fn start_component(&mut self, spec: ComponentSpec, timeout: Duration) -> Result<()> {
+        for dependency in &spec.depends_on {
+            let component = self
+                .components
+                .iter_mut()
+                .find(|component| component.name == *dependency)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "{} depends on {dependency}, which must be added before it",
+                        spec.name
+                    )
+                })?;
+            tokio::time::timeout(timeout, component.wait_ready())
+                .await
+                .with_context(|| format!("Timed out waiting for {dependency} to be ready"))??;
+        }
+
+        let (ready_sender, ready) = watch::channel(false);
+        let (stop, stop_receiver) = watch::channel(false);
+        let context = ComponentContext {
+            name: spec.name,
+            ready: Arc::new(ready_sender),
+            stop: stop_receiver,
+            restart_counter: self.restart_counter.clone(),
+        };
+        info!("Starting {}", spec.name);
+        let handle = tokio::spawn((spec.run)(context));
+        self.components.push(RunningComponent {
+            name: spec.name,
+            ready,
+            stop,
+            handle: Some(handle),
+        });
+        Ok(())
+    }
+
+    pub fn health(&self) -> Vec<ComponentHealth> {
+        self.components
+            .iter()
+            .map(|component| ComponentHealth {
+                name: component.name,
+                ready: *component.ready.borrow(),
+                running: component
+                    .handle
+                    .as_ref()
+                    .map_or(false, |handle| !handle.is_finished()),
+            })
+            .collect()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.health()
+            .iter()
+            .all(|health| health.ready && health.running)
+    }
+
+    /// Run until `shutdown` resolves or any component exits, then stop all components. Returns
+    /// the error of the component that exited, if it failed.
+    pub async fn run_until<F>(mut self, shutdown: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        let exited = {
+            let running = self
+                .components
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(i, component)| Some((i, component.handle.as_mut()?)))
+                .map(|(i, handle)| async move { (i, handle.await) }.boxed())
+                .collect::<Vec<_>>();
+            if running.is_empty() {
+                bail!("{} has no running component", self.name);
+            }
+            tokio::select! {
+                _ = shutdown => None,
+                ((i, result), _, _) = futures::future::select_all(running) => Some((i, result)),
+            }
+        };
+
+        let result = match exited {
+            None => Ok(()),
+            Some((i, result)) => {
+                let component = &mut self.components[i];
+                component.handle = None;
+                warn!("{} exited, stopping {}", component.name, self.name);
+                flatten(component.name, result)
+            }
+        };
+        let shutdown_result = self.shutdown().await;
+        result.and(shutdown_result)
+    }
+
+    /// Stop all components, in the reverse order they were started. Components that don't stop
+    /// within the shutdown timeout are aborted.
+    pub async fn shutdown(mut self) -> Result<()> {
+        info!("Stopping {}", self.name);
+        let mut result = Ok(());
+        while let Some(mut component) = self.components.pop() {
+            let Some(mut handle) = component.handle.take() else {
+                continue;
+            };
+            component.stop.send_replace(true);
+            let component_result =
+                match tokio::time::timeout(self.shutdown_timeout, &mut handle).await {
+                    Ok(joined) => flatten(component.name, joined),
+                    Err(_) => {
+                        warn!(
+                            "{} did not stop within {:?}, aborting it",
+                            component.name, self.shutdown_timeout
+                        );
+                        handle.abort();
+                        Ok(())
+                    }
+                };
+            if let Err(e) = &component_result {
+                error!("{} failed: {e:?}", component.name);
+            }
+            result = result.and(component_result);
+        }
+        info!("Stopped {}", self.name);
+        result
+    }
+}
+
+impl RunningComponent {
+    async fn wait_ready(&mut self) -> Result<()> {
+        while !*self.ready.borrow() {
+            // The context is dropped when the component exits.
+            if self.ready.changed().await.is_err() && !*self.ready.borrow() {
+                bail!("{} exited before being ready", self.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `future`, returning the message of its panic if it panics.
+async fn catch_panic<F, T>(future: F) -> Result<T, String>
+where
+    F: Future<Output = T>,
+{
+    AssertUnwindSafe(future)
+        .catch_unwind()
+        .await
+        .map_err(|payload| panic_message(payload.as_ref()))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+fn flatten(name: &'static str, joined: Result<Result<()>, tokio::task::JoinError>) -> Result<()> {
+    joined.with_context(|| format!("{name} panicked or was cancelled"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::{RestartPolicy, ServiceBuilder};
+    use parking_lot::Mutex;
+    use prometheus::{IntCounterVec, Opts};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_dependency_order() -> anyhow::Result<()> {
+        let events = Arc::new(Mutex::new(vec![]));
+        let (commit_events, pipeline_events) = (events.clone(), events.clone());
+        let service = ServiceBuilder::new("test")
+            .component("commit", &[], move |mut context| async move {
+                commit_events.lock().push("commit started");
+                context.set_ready();
+                context.stopped().await;
+                commit_events.lock().push("commit stopped");
+                Ok(())
+            })
+            .component("pipeline", &["commit"], move |mut context| async move {
+                pipeline_events.lock().push("pipeline started");
+                context.set_ready();
+                context.stopped().await;
+                pipeline_events.lock().push("pipeline stopped");
+                Ok(())
+            })
+            .start()
+            .await?;
+
+        while !service.is_healthy() {
+            tokio::task::yield_now().await;
+        }
+        service.shutdown().await?;
+        assert_eq!(
+            *events.lock(),
+            vec![
+                "commit started",
+                "pipeline started",
+                "pipeline stopped",
+                "commit stopped"
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_dependency() {
+        let result = ServiceBuilder::new("test")
+            .component("commit", &[], |_| async { anyhow::bail!("no database") })
+            .component("pipeline", &["commit"], |context| {
+                context.run_until_stopped(futures::future::pending())
+            })
+            .start()
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_panic_fails_component() {
+        let service = ServiceBuilder::new("test")
+            .component("commit", &[], |context| {
+                context.run_until_stopped(async { panic!("lost database connection") })
+            })
+            .start()
+            .await
+            .unwrap();
+        let error = service
+            .run_until(futures::future::pending())
+            .await
+            .unwrap_err();
+        assert!(format!("{error:#}").contains("commit panicked: lost database connection"));
+    }
+
+    #[tokio::test]
+    async fn test_supervised_component_restarts() -> anyhow::Result<()> {
+        let restarts = IntCounterVec::new(Opts::new("restarts", "restarts"), &["component"])?;
+        let runs = Arc::new(AtomicUsize::new(0));
+        let component_runs = runs.clone();
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        };
+        let service = ServiceBuilder::new("test")
+            .with_restart_counter(restarts.clone())
+            .supervised_component("gc", &[], policy, move |context| {
+                let runs = component_runs.clone();
+                context.run_until_stopped(async move {
+                    if runs.fetch_add(1, Ordering::SeqCst) < 2 {
+                        panic!("corrupted cache entry");
+                    }
+                    futures::future::pending::<()>().await
+                })
+            })
+            .start()
+            .await?;
+
+        while runs.load(Ordering::SeqCst) < 3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert!(service.is_healthy());
+        assert_eq!(restarts.with_label_values(&["gc"]).get(), 2);
+        service.shutdown().await
+    }
+}
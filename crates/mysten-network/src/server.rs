@@ -38,6 +38,10 @@ use tower_http::trace::{DefaultMakeSpan, DefaultOnBodyChunk, DefaultOnEos, Trace
 pub struct ServerBuilder<M: MetricsCallbackProvider = DefaultMetricsCallbackProvider> {
     router: Router<WrapperService<M>>,
     health_reporter: tonic_health::server::HealthReporter,
+    /// Names of the services added, reported as serving by the health service once bound.
+    services: Vec<&'static str>,
+    /// Encoded file descriptor sets served by the reflection service.
+    file_descriptor_sets: Vec<&'static [u8]>,
 }
 
 type AddPathToHeaderFunction = fn(&Request<Body>) -> Option<HeaderValue>;
@@ -133,6 +137,11 @@ impl<M: MetricsCallbackProvider> ServerBuilder<M> {
         Self {
             router,
             health_reporter,
+            services: vec![],
+            file_descriptor_sets: vec![
+                tonic_health::pb::FILE_DESCRIPTOR_SET,
+                tonic_reflection::pb::FILE_DESCRIPTOR_SET,
+            ],
         }
     }
 
@@ -151,10 +160,37 @@ impl<M: MetricsCallbackProvider> ServerBuilder<M> {
         S::Future: Send + 'static,
     {
         self.router = self.router.add_service(svc);
+        self.services.push(S::NAME);
         self
     }
 
-    pub async fn bind(self, addr: &Multiaddr) -> Result<Server> {
+    /// Register the encoded file descriptor set of services added with protobuf definitions, so
+    /// that the reflection service can describe them to clients such as grpcurl. Services using
+    /// other codecs can still be called, but not described.
+    pub fn register_file_descriptor_set(mut self, file_descriptor_set: &'static [u8]) -> Self {
+        self.file_descriptor_sets.push(file_descriptor_set);
+        self
+    }
+
+    pub async fn bind(mut self, addr: &Multiaddr) -> Result<Server> {
+        let reflection_service = self
+            .file_descriptor_sets
+            .iter()
+            .fold(
+                tonic_reflection::server::Builder::configure(),
+                |builder, file_descriptor_set| {
+                    builder.register_encoded_file_descriptor_set(file_descriptor_set)
+                },
+            )
+            .build()
+            .map_err(|e| eyre!("failed to build reflection service: {e}"))?;
+        self.router = self.router.add_service(reflection_service);
+        for service in &self.services {
+            self.health_reporter
+                .set_service_status(*service, tonic_health::ServingStatus::Serving)
+                .await;
+        }
+
         let mut iter = addr.iter();
 
         let (tx_cancellation, rx_cancellation) = tokio::sync::oneshot::channel();
@@ -280,6 +316,10 @@ mod test {
     use tonic::Code;
     use tonic_health::pb::health_client::HealthClient;
     use tonic_health::pb::HealthCheckRequest;
+    use tonic_reflection::pb::server_reflection_client::ServerReflectionClient;
+    use tonic_reflection::pb::server_reflection_request::MessageRequest;
+    use tonic_reflection::pb::server_reflection_response::MessageResponse;
+    use tonic_reflection::pb::ServerReflectionRequest;
 
     #[test]
     fn document_multiaddr_limitation_for_unix_protocol() {
@@ -418,6 +458,47 @@ mod test {
         assert!(metrics.metrics_called.lock().unwrap().deref());
     }
 
+    #[tokio::test]
+    async fn test_reflection_lists_services() {
+        let address: Multiaddr = "/ip4/127.0.0.1/tcp/0/http".parse().unwrap();
+        let config = Config::new();
+        let mut server = config.server_builder().bind(&address).await.unwrap();
+        let address = server.local_addr().to_owned();
+        let cancel_handle = server.take_cancel_handle().unwrap();
+        let server_handle = tokio::spawn(server.serve());
+        let channel = config.connect(&address).await.unwrap();
+        let mut client = ServerReflectionClient::new(channel);
+
+        let request = ServerReflectionRequest {
+            host: "".to_owned(),
+            message_request: Some(MessageRequest::ListServices("".to_owned())),
+        };
+        let response = client
+            .server_reflection_info(tokio_stream::once(request))
+            .await
+            .unwrap()
+            .into_inner()
+            .message()
+            .await
+            .unwrap()
+            .unwrap();
+        let Some(MessageResponse::ListServicesResponse(list)) = response.message_response else {
+            panic!("unexpected reflection response {response:?}");
+        };
+        let mut services: Vec<_> = list.service.into_iter().map(|s| s.name).collect();
+        services.sort();
+        assert_eq!(
+            services,
+            vec![
+                "grpc.health.v1.Health",
+                "grpc.reflection.v1alpha.ServerReflection"
+            ]
+        );
+
+        cancel_handle.send(()).unwrap();
+        server_handle.await.unwrap().unwrap();
+    }
+
     async fn test_multiaddr(address: Multiaddr) {
         let config = Config::new();
         let mut server = config.server_builder().bind(&address).await.unwrap();
@@ -367,8 +367,8 @@ pub async fn read_store_for_checkpoint(
         find_all_files_with_epoch_prefix(&remote_object_store, Some(&epoch_prefix)).await?;
     let next_checkpoint_seq_num = checkpoints
         .iter()
-        .max_by(|x, y| x.end.cmp(&y.end))
-        .map(|r| r.end)
+        .map(|range| range.end())
+        .max()
         .unwrap_or(0);
     Ok(next_checkpoint_seq_num)
 }
@@ -0,0 +1,218 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In place conversion of the checkpoint and summary files of an archive to another compression
+//! codec, so that existing archives don't have to be regenerated from a node to change it.
+//!
+//! Files are converted in batches. Every file is verified against the checksum in the MANIFEST
+//! before it is converted, and converted files are decoded again and compared with the original
+//! before they are uploaded. The checksums of the files of a batch are recorded in a journal
+//! before the files are overwritten, and the MANIFEST is updated once they are all uploaded, so
+//! that an interrupted conversion can be started again: files already overwritten are recognized
+//! by the checksums in the journal.
+//!
+//! The archive writer must be stopped while the archive is converted, as it overwrites the
+//! MANIFEST with its own copy.
+
+use crate::{read_manifest, write_manifest, FileMetadata, Manifest};
+use anyhow::{anyhow, Result};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+use sui_storage::blob::{Blob, BlobEncoding};
+use sui_storage::object_store::util::{get, put};
+use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreDeleteExt};
+use sui_storage::{compute_sha3_checksum_for_bytes, FileCompression, SHA3_BYTES};
+use tracing::info;
+
+const CONVERSION_JOURNAL_FILENAME: &str = "CONVERSION_JOURNAL";
+/// Magic, storage format and file compression
+const FILE_HEADER_BYTES: usize = 6;
+
+/// Checksums of the files being overwritten by the current batch, by path.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Eq, PartialEq)]
+struct ConversionJournal {
+    converted: BTreeMap<String, [u8; SHA3_BYTES]>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct ConversionSummary {
+    /// Files rewritten with the target compression
+    pub converted: usize,
+    /// Files already compressed with the target compression
+    pub skipped: usize,
+}
+
+/// Convert all files of the archive at `remote_store_config` to `compression`, `concurrency`
+/// files at a time. See the [module documentation](self).
+pub async fn convert_archive_compression(
+    remote_store_config: ObjectStoreConfig,
+    compression: FileCompression,
+    concurrency: usize,
+) -> Result<ConversionSummary> {
+    let store = remote_store_config.make()?;
+    let mut manifest = read_manifest(store.clone()).await?;
+    let journal = read_journal(&store).await?;
+    let mut summary = ConversionSummary::default();
+    let mut journal_written = !journal.converted.is_empty();
+    let files = manifest.files();
+    for batch in files.chunks(concurrency.max(1)) {
+        let results: Vec<(FileMetadata, Option<(Bytes, [u8; SHA3_BYTES])>)> =
+            futures::stream::iter(batch)
+                .map(|file| convert_file(&store, &journal, file, compression))
+                .buffer_unordered(concurrency.max(1))
+                .try_collect()
+                .await?;
+        let mut batch_journal = ConversionJournal::default();
+        let mut uploads = vec![];
+        for (file, converted) in results {
+            match converted {
+                Some((bytes, digest)) => {
+                    batch_journal
+                        .converted
+                        .insert(file.file_path().to_string(), digest);
+                    uploads.push((file.file_path(), bytes));
+                    summary.converted += 1;
+                }
+                None => {
+                    // Files overwritten by an interrupted run may still have their previous
+                    // checksum in the MANIFEST
+                    let path = file.file_path().to_string();
+                    if let Some(digest) = journal.converted.get(&path) {
+                        batch_journal.converted.insert(path, *digest);
+                    }
+                    summary.skipped += 1;
+                }
+            }
+        }
+        if batch_journal.converted.is_empty() {
+            continue;
+        }
+        write_journal(&store, &batch_journal).await?;
+        journal_written = true;
+        futures::stream::iter(uploads)
+            .map(|(path, bytes)| {
+                let store = store.clone();
+                async move { put(&store, &path, bytes).await }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_collect::<Vec<()>>()
+            .await?;
+        update_checksums(&mut manifest, &batch_journal);
+        write_manifest(manifest.clone(), store.clone()).await?;
+        info!(
+            "Converted {} files, {} already {:?}",
+            summary.converted, summary.skipped, compression
+        );
+    }
+    if journal_written {
+        store
+            .delete_object(&Path::from(CONVERSION_JOURNAL_FILENAME))
+            .await?;
+    }
+    Ok(summary)
+}
+
+/// Download `file`, check it against the MANIFEST or the journal, and return it converted to
+/// `compression` with its new checksum, or `None` if it is already compressed with it.
+async fn convert_file(
+    store: &Arc<DynObjectStore>,
+    journal: &ConversionJournal,
+    file: &FileMetadata,
+    compression: FileCompression,
+) -> Result<(FileMetadata, Option<(Bytes, [u8; SHA3_BYTES])>)> {
+    let path = file.file_path();
+    let bytes = get(store, &path).await?;
+    let digest = compute_sha3_checksum_for_bytes(bytes.clone())?;
+    if digest != file.sha3_digest && journal.converted.get(path.as_ref()) != Some(&digest) {
+        return Err(anyhow!("Checksum doesn't match for file: {}", path));
+    }
+    if file_compression(&bytes)? == compression {
+        return Ok((file.clone(), None));
+    }
+    let converted = tokio::task::spawn_blocking(move || recompress(bytes, compression)).await??;
+    let converted_digest = compute_sha3_checksum_for_bytes(converted.clone())?;
+    Ok((file.clone(), Some((converted, converted_digest))))
+}
+
+fn file_compression(bytes: &[u8]) -> Result<FileCompression> {
+    let compression = bytes
+        .get(FILE_HEADER_BYTES - 1)
+        .ok_or_else(|| anyhow!("Truncated archive file"))?;
+    Ok(FileCompression::try_from(*compression)?)
+}
+
+/// Blobs of the file, decompressed, with its magic and storage format.
+fn decompress(bytes: Bytes) -> Result<(u32, u8, Vec<u8>)> {
+    let mut reader = Cursor::new(bytes.clone());
+    let magic = reader.read_u32::<BigEndian>()?;
+    let storage_format = reader.read_u8()?;
+    let compression = FileCompression::try_from(reader.read_u8()?)?;
+    let mut blobs = vec![];
+    compression
+        .bytes_decompress(bytes.slice(FILE_HEADER_BYTES..))?
+        .read_to_end(&mut blobs)?;
+    Ok((magic, storage_format, blobs))
+}
+
+/// Rewrite a file with `compression`, checking that the rewritten file holds the same blobs.
+fn recompress(bytes: Bytes, compression: FileCompression) -> Result<Bytes> {
+    let (magic, storage_format, blobs) = decompress(bytes)?;
+    let mut converted = vec![];
+    converted.write_u32::<BigEndian>(magic)?;
+    converted.write_u8(storage_format)?;
+    converted.write_u8(compression.into())?;
+    match compression {
+        FileCompression::Zstd => {
+            FileCompression::zstd_compress(&mut blobs.as_slice(), &mut converted)?
+        }
+        FileCompression::None => converted.extend_from_slice(&blobs),
+    }
+    let converted = Bytes::from(converted);
+    let (_, _, converted_blobs) = decompress(converted.clone())?;
+    if converted_blobs != blobs {
+        return Err(anyhow!("Converted file doesn't match the original"));
+    }
+    Ok(converted)
+}
+
+fn update_checksums(manifest: &mut Manifest, journal: &ConversionJournal) {
+    match manifest {
+        Manifest::V1(manifest) => {
+            for file in manifest.file_metadata.iter_mut() {
+                if let Some(digest) = journal.converted.get(file.file_path().as_ref()) {
+                    file.sha3_digest = *digest;
+                }
+            }
+        }
+    }
+}
+
+async fn read_journal(store: &Arc<DynObjectStore>) -> Result<ConversionJournal> {
+    let path = Path::from(CONVERSION_JOURNAL_FILENAME);
+    match store.get(&path).await {
+        Ok(result) => {
+            let bytes = result.bytes().await?;
+            Blob::read(&mut bytes.as_ref())?.decode()
+        }
+        Err(object_store::Error::NotFound { .. }) => Ok(ConversionJournal::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn write_journal(store: &Arc<DynObjectStore>, journal: &ConversionJournal) -> Result<()> {
+    let mut buf = vec![];
+    Blob::encode(journal, BlobEncoding::Bcs)?.write(&mut buf)?;
+    put(
+        store,
+        &Path::from(CONVERSION_JOURNAL_FILENAME),
+        Bytes::from(buf),
+    )
+    .await
+}
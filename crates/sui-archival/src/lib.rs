@@ -2,13 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 #![allow(dead_code)]
 
+pub mod converter;
+pub mod publish;
 pub mod reader;
+pub mod summary;
 pub mod writer;
 
 #[cfg(test)]
 mod tests;
 
 use crate::reader::{ArchiveReader, ArchiveReaderMetrics};
+use crate::summary::EpochSummary;
 use anyhow::{anyhow, Result};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use bytes::Bytes;
@@ -212,6 +216,8 @@ pub struct CheckpointUpdates {
     checkpoint_file_metadata: FileMetadata,
     summary_file_metadata: FileMetadata,
     manifest: Manifest,
+    /// Summary of the epoch ended by the last checkpoint of these files, if any
+    epoch_summary: Option<EpochSummary>,
 }
 
 impl CheckpointUpdates {
@@ -232,6 +238,7 @@ impl CheckpointUpdates {
             checkpoint_file_metadata,
             summary_file_metadata,
             manifest: manifest.clone(),
+            epoch_summary: None,
         }
     }
     pub fn content_file_path(&self) -> Path {
@@ -263,7 +270,21 @@ pub fn create_file_metadata(
 
 pub async fn read_manifest<S: ObjectStoreGetExt>(remote_store: S) -> Result<Manifest> {
     let manifest_file_path = Path::from(MANIFEST_FILENAME);
-    let vec = get(&remote_store, &manifest_file_path).await?.to_vec();
+    decode_manifest(get(&remote_store, &manifest_file_path).await?)
+}
+
+pub async fn write_manifest<S: ObjectStorePutExt>(
+    manifest: Manifest,
+    remote_store: S,
+) -> Result<()> {
+    let path = Path::from(MANIFEST_FILENAME);
+    put(&remote_store, &path, encode_manifest(&manifest)?).await?;
+    Ok(())
+}
+
+/// Decode a MANIFEST file, checking its magic and checksum.
+pub fn decode_manifest(bytes: Bytes) -> Result<Manifest> {
+    let vec = bytes.to_vec();
     let manifest_file_size = vec.len();
     let mut manifest_reader = Cursor::new(vec);
     manifest_reader.rewind()?;
@@ -292,23 +313,18 @@ pub async fn read_manifest<S: ObjectStoreGetExt>(remote_store: S) -> Result<Mani
     Blob::read(&mut manifest_reader)?.decode()
 }
 
-pub async fn write_manifest<S: ObjectStorePutExt>(
-    manifest: Manifest,
-    remote_store: S,
-) -> Result<()> {
-    let path = Path::from(MANIFEST_FILENAME);
+/// Encode `manifest` in the MANIFEST file format.
+pub fn encode_manifest(manifest: &Manifest) -> Result<Bytes> {
     let mut buf = BufWriter::new(vec![]);
     buf.write_u32::<BigEndian>(MANIFEST_FILE_MAGIC)?;
-    let blob = Blob::encode(&manifest, BlobEncoding::Bcs)?;
+    let blob = Blob::encode(manifest, BlobEncoding::Bcs)?;
     blob.write(&mut buf)?;
     buf.flush()?;
     let mut hasher = Sha3_256::default();
     hasher.update(buf.get_ref());
     let computed_digest = hasher.finalize().digest;
     buf.write_all(&computed_digest)?;
-    let bytes = Bytes::from(buf.into_inner()?);
-    put(&remote_store, &path, bytes).await?;
-    Ok(())
+    Ok(Bytes::from(buf.into_inner()?))
 }
 
 pub async fn verify_archive_with_genesis_config(
@@ -366,6 +382,7 @@ pub async fn verify_archive_with_checksums(
     let metrics = ArchiveReaderMetrics::new(&Registry::default());
     let config = ArchiveReaderConfig {
         remote_store_config,
+        mirror_store_configs: vec![],
         download_concurrency: NonZeroUsize::new(concurrency).unwrap(),
         use_for_pruning_watermark: false,
     };
@@ -400,6 +417,7 @@ where
     let metrics = ArchiveReaderMetrics::new(&Registry::default());
     let config = ArchiveReaderConfig {
         remote_store_config,
+        mirror_store_configs: vec![],
         download_concurrency: NonZeroUsize::new(concurrency).unwrap(),
         use_for_pruning_watermark: false,
     };
@@ -0,0 +1,368 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Two-phase publishing of the files cut by the archive writer, so that readers never see a
+//! MANIFEST referring to files that aren't uploaded yet, and a publish interrupted by a crash is
+//! completed or discarded when the writer starts again.
+//!
+//! A publish is first recorded in a journal in the local staging directory, written to a
+//! temporary file which is fsynced and renamed in place. In the first phase, the checkpoint and
+//! summary files are uploaded and, if the remote store isn't read-after-write consistent, waited
+//! for until they are visible. In the second phase, the MANIFEST is replaced with a conditional
+//! write, only if the remote MANIFEST still ends at the first checkpoint of the published files
+//! and hasn't changed since it was read. Local files and the journal are removed last.
+//!
+//! On start, [`ManifestPublisher::recover`] reads the journal of an interrupted publish, if any,
+//! and completes it: files still in the staging directory are uploaded again, and the ones
+//! already removed are checked against their checksums. A publish already included in the remote
+//! MANIFEST is only cleaned up, and one the remote MANIFEST moved past is discarded.
+//!
+//! The summary of the epoch ended by the published files, if any, is written after the MANIFEST,
+//! so that it only exists once the whole epoch is archived.
+//!
+//! Conditional writes to S3, GCS and Azure are sent to presigned URLs, which need the remote store
+//! to be configured with static credentials, see `ObjectStoreConfig::make_conditional_put`.
+
+use crate::summary::{write_epoch_summary, EpochSummary};
+use crate::{
+    decode_manifest, encode_manifest, CheckpointUpdates, FileMetadata, Manifest, MANIFEST_FILENAME,
+};
+use anyhow::{anyhow, Result};
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::fs::File;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_storage::blob::{Blob, BlobEncoding};
+use sui_storage::compute_sha3_checksum_for_bytes;
+use sui_storage::object_store::conditional::ObjectStoreConditionalPutExt;
+use sui_storage::object_store::consistency::{
+    wait_until_visible, ExpectedObject, ReadAfterWriteMetrics,
+};
+use sui_storage::object_store::mirror::MirroredObjectStore;
+use sui_storage::object_store::util::{
+    copy_file_with_threshold, get, path_to_filesystem, DEFAULT_STREAMING_THRESHOLD,
+};
+use tracing::{debug, info, warn};
+
+pub const PUBLISH_JOURNAL_FILENAME: &str = "PUBLISH_JOURNAL";
+
+/// Files of a publish and the MANIFEST referring to them, as recorded in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct PendingPublish {
+    /// Next checkpoint of the MANIFEST the publish appends to
+    pub previous_checkpoint_seq_num: u64,
+    pub files: Vec<FileMetadata>,
+    pub manifest: Manifest,
+    pub epoch_summary: Option<EpochSummary>,
+}
+
+impl PendingPublish {
+    pub fn new(updates: &CheckpointUpdates) -> Self {
+        PendingPublish {
+            previous_checkpoint_seq_num: updates
+                .checkpoint_file_metadata
+                .checkpoint_seq_range
+                .start,
+            files: vec![
+                updates.summary_file_metadata.clone(),
+                updates.checkpoint_file_metadata.clone(),
+            ],
+            manifest: updates.manifest.clone(),
+            epoch_summary: updates.epoch_summary.clone(),
+        }
+    }
+
+    fn is_included_in(&self, manifest: &Manifest) -> bool {
+        let files = manifest.files();
+        manifest.next_checkpoint_seq_num() >= self.manifest.next_checkpoint_seq_num()
+            && self.files.iter().all(|file| files.contains(file))
+    }
+}
+
+/// Returned when the remote MANIFEST doesn't end where a publish starts, as another writer
+/// updated it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestSequenceMismatch {
+    pub expected: u64,
+    pub found: u64,
+}
+
+impl Display for ManifestSequenceMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Remote MANIFEST ends at checkpoint {} instead of {}, it was updated by another writer",
+            self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ManifestSequenceMismatch {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryOutcome {
+    /// No publish was interrupted
+    NothingPending,
+    /// The interrupted publish was completed
+    Completed,
+    /// The remote MANIFEST already included the interrupted publish
+    AlreadyCommitted,
+    /// The remote MANIFEST moved past the start of the interrupted publish, which was dropped
+    Discarded,
+}
+
+/// Publishes the files of the archive writer and the MANIFEST referring to them, see the
+/// [module documentation](self).
+pub struct ManifestPublisher {
+    local_staging_root: PathBuf,
+    local_store: Arc<DynObjectStore>,
+    remote_store: Arc<DynObjectStore>,
+    conditional: Arc<dyn ObjectStoreConditionalPutExt>,
+    /// Time to wait for uploaded files to become visible before updating the MANIFEST
+    read_after_write_timeout: Option<Duration>,
+    read_after_write_metrics: Arc<ReadAfterWriteMetrics>,
+    /// Mirrors the writes of the remote store, if any, see [`Self::with_mirrors`]
+    mirrored: Option<Arc<MirroredObjectStore<Arc<DynObjectStore>>>>,
+    /// Size above which files are streamed to the remote store
+    streaming_threshold: usize,
+}
+
+impl ManifestPublisher {
+    pub fn new(
+        local_staging_root: PathBuf,
+        local_store: Arc<DynObjectStore>,
+        remote_store: Arc<DynObjectStore>,
+        conditional: Arc<dyn ObjectStoreConditionalPutExt>,
+        read_after_write_timeout: Option<Duration>,
+        read_after_write_metrics: Arc<ReadAfterWriteMetrics>,
+    ) -> Self {
+        ManifestPublisher {
+            local_staging_root,
+            local_store,
+            remote_store,
+            conditional,
+            read_after_write_timeout,
+            read_after_write_metrics,
+            mirrored: None,
+            streaming_threshold: DEFAULT_STREAMING_THRESHOLD,
+        }
+    }
+
+    /// Stream files larger than `streaming_threshold` bytes to the remote store, rather than
+    /// [`DEFAULT_STREAMING_THRESHOLD`], e.g. the one of the remote store config.
+    pub fn with_streaming_threshold(mut self, streaming_threshold: usize) -> Self {
+        self.streaming_threshold = streaming_threshold;
+        self
+    }
+
+    /// Copy the committed MANIFEST to the mirrors of `mirrored`, for a remote store mirroring
+    /// its writes with `mirrored`. Conditional writes of the MANIFEST only go to its primary.
+    pub fn with_mirrors(mut self, mirrored: Arc<MirroredObjectStore<Arc<DynObjectStore>>>) -> Self {
+        self.mirrored = Some(mirrored);
+        self
+    }
+
+    /// Upload the files of `updates`, then append them to the remote MANIFEST.
+    pub async fn publish(&self, updates: &CheckpointUpdates) -> Result<()> {
+        let pending = PendingPublish::new(updates);
+        self.write_journal(&pending)?;
+        self.complete(&pending).await
+    }
+
+    /// Complete or discard the publish interrupted by the last run of the writer, if any. Must
+    /// be called before the staging directory is cleared and the remote MANIFEST is read.
+    pub async fn recover(&self) -> Result<RecoveryOutcome> {
+        let Some(pending) = self.read_journal()? else {
+            return Ok(RecoveryOutcome::NothingPending);
+        };
+        let remote_manifest = self
+            .read_remote_manifest()
+            .await?
+            .map(|(manifest, _)| manifest);
+        if let Some(manifest) = &remote_manifest {
+            if pending.is_included_in(manifest) {
+                info!(
+                    "Interrupted publish up to checkpoint {} was already committed",
+                    pending.manifest.next_checkpoint_seq_num()
+                );
+                self.write_epoch_summary(&pending).await?;
+                self.clear(&pending)?;
+                return Ok(RecoveryOutcome::AlreadyCommitted);
+            }
+        }
+        let next_checkpoint_seq_num = remote_manifest
+            .as_ref()
+            .map_or(0, |manifest| manifest.next_checkpoint_seq_num());
+        if next_checkpoint_seq_num != pending.previous_checkpoint_seq_num {
+            warn!(
+                "Discarding interrupted publish from checkpoint {}, remote MANIFEST ends at {}",
+                pending.previous_checkpoint_seq_num, next_checkpoint_seq_num
+            );
+            self.clear(&pending)?;
+            return Ok(RecoveryOutcome::Discarded);
+        }
+        info!(
+            "Completing interrupted publish from checkpoint {}",
+            pending.previous_checkpoint_seq_num
+        );
+        self.complete(&pending).await?;
+        Ok(RecoveryOutcome::Completed)
+    }
+
+    async fn complete(&self, pending: &PendingPublish) -> Result<()> {
+        for file in pending.files.iter() {
+            self.upload(file).await?;
+        }
+        self.commit_manifest(pending).await?;
+        self.write_epoch_summary(pending).await?;
+        self.clear(pending)
+    }
+
+    async fn write_epoch_summary(&self, pending: &PendingPublish) -> Result<()> {
+        if let Some(epoch_summary) = &pending.epoch_summary {
+            info!("Writing summary of epoch {}", epoch_summary.epoch);
+            write_epoch_summary(epoch_summary, self.remote_store.clone()).await?;
+        }
+        Ok(())
+    }
+
+    async fn upload(&self, file: &FileMetadata) -> Result<()> {
+        let path = file.file_path();
+        let local_path = path_to_filesystem(self.local_staging_root.clone(), &path)?;
+        if !local_path.exists() {
+            // Only removed once the publish was committed, or by hand
+            let bytes = get(&self.remote_store, &path).await?;
+            if compute_sha3_checksum_for_bytes(bytes)? != file.sha3_digest {
+                return Err(anyhow!(
+                    "Checksum doesn't match for uploaded file: {}",
+                    path
+                ));
+            }
+            return Ok(());
+        }
+        debug!("Syncing archive file to remote: {:?}", path);
+        let size = fs::metadata(&local_path)?.len() as usize;
+        copy_file_with_threshold(
+            &path,
+            &path,
+            &self.local_store,
+            &self.remote_store,
+            self.streaming_threshold,
+        )
+        .await?;
+        if let Some(timeout) = self.read_after_write_timeout {
+            let expected = ExpectedObject {
+                size,
+                replaced: None,
+            };
+            wait_until_visible(
+                &self.remote_store,
+                &path,
+                &expected,
+                timeout,
+                Some(self.read_after_write_metrics.as_ref()),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Replace the remote MANIFEST with the one of `pending`, if it still ends where `pending`
+    /// starts and isn't replaced concurrently.
+    async fn commit_manifest(&self, pending: &PendingPublish) -> Result<()> {
+        let path = Path::from(MANIFEST_FILENAME);
+        let bytes = encode_manifest(&pending.manifest)?;
+        // The MANIFEST the remote store ends up with, to copy to its mirrors
+        let bytes = match self.read_remote_manifest().await? {
+            Some((manifest, _)) if pending.is_included_in(&manifest) => encode_manifest(&manifest)?,
+            Some((manifest, etag)) => {
+                if manifest.next_checkpoint_seq_num() != pending.previous_checkpoint_seq_num {
+                    return Err(ManifestSequenceMismatch {
+                        expected: pending.previous_checkpoint_seq_num,
+                        found: manifest.next_checkpoint_seq_num(),
+                    }
+                    .into());
+                }
+                let etag = etag.ok_or_else(|| anyhow!("Store returned no ETag for {path}"))?;
+                self.conditional
+                    .put_bytes_if_match(&path, bytes.clone(), &etag)
+                    .await?;
+                bytes
+            }
+            None => {
+                if pending.previous_checkpoint_seq_num != 0 {
+                    return Err(ManifestSequenceMismatch {
+                        expected: pending.previous_checkpoint_seq_num,
+                        found: 0,
+                    }
+                    .into());
+                }
+                self.conditional
+                    .put_bytes_if_not_exists(&path, bytes.clone())
+                    .await?;
+                bytes
+            }
+        };
+        if let Some(mirrored) = &self.mirrored {
+            mirrored.put_to_mirrors(&path, bytes).await?;
+        }
+        Ok(())
+    }
+
+    /// The remote MANIFEST and its ETag, `None` if there is no MANIFEST yet.
+    async fn read_remote_manifest(&self) -> Result<Option<(Manifest, Option<String>)>> {
+        match self.remote_store.get(&Path::from(MANIFEST_FILENAME)).await {
+            Ok(result) => {
+                let etag = result.meta.e_tag.clone();
+                Ok(Some((decode_manifest(result.bytes().await?)?, etag)))
+            }
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.local_staging_root.join(PUBLISH_JOURNAL_FILENAME)
+    }
+
+    fn write_journal(&self, pending: &PendingPublish) -> Result<()> {
+        let path = self.journal_path();
+        let tmp_path = path.with_extension("tmp");
+        let mut buf = vec![];
+        Blob::encode(pending, BlobEncoding::Bcs)?.write(&mut buf)?;
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&buf)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        // Persist the rename
+        File::open(&self.local_staging_root)?.sync_all()?;
+        Ok(())
+    }
+
+    fn read_journal(&self) -> Result<Option<PendingPublish>> {
+        match fs::read(self.journal_path()) {
+            Ok(bytes) => Ok(Some(Blob::read(&mut bytes.as_slice())?.decode()?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Remove the local files of `pending`, then its journal.
+    fn clear(&self, pending: &PendingPublish) -> Result<()> {
+        for file in pending.files.iter() {
+            let local_path =
+                path_to_filesystem(self.local_staging_root.clone(), &file.file_path())?;
+            if local_path.exists() {
+                fs::remove_file(local_path)?;
+            }
+        }
+        fs::remove_file(self.journal_path())?;
+        Ok(())
+    }
+}
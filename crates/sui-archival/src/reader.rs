@@ -2,25 +2,31 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    read_manifest, FileMetadata, FileType, Manifest, CHECKPOINT_FILE_MAGIC, SUMMARY_FILE_MAGIC,
+    read_manifest, FileMetadata, FileType, Manifest, CHECKPOINT_FILE_MAGIC, MANIFEST_FILENAME,
+    SUMMARY_FILE_MAGIC,
 };
 use anyhow::{anyhow, Context, Result};
 use bytes::buf::Reader;
 use bytes::{Buf, Bytes};
 use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path;
 use prometheus::{register_int_counter_vec_with_registry, IntCounterVec, Registry};
 use rand::seq::SliceRandom;
 use std::borrow::Borrow;
 use std::future;
-use std::ops::Range;
+use std::num::NonZeroUsize;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use sui_config::node::ArchiveReaderConfig;
+use sui_storage::object_store::fallback::{
+    FallbackObjectStore, FallbackObjectStoreMetrics, StoreRanking,
+};
 use sui_storage::object_store::http::HttpDownloaderBuilder;
-use sui_storage::object_store::util::get;
-use sui_storage::object_store::ObjectStoreGetExt;
+use sui_storage::object_store::util::{find_missing_files, get};
+use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreGetExt, ObjectStoreHeadExt};
 use sui_storage::{compute_sha3_checksum_for_bytes, make_iterator, verify_checkpoint};
+use sui_types::checkpoint_range::CheckpointRange;
 use sui_types::messages_checkpoint::{
     CertifiedCheckpointSummary, CheckpointSequenceNumber,
     FullCheckpointContents as CheckpointContents, VerifiedCheckpoint, VerifiedCheckpointContents,
@@ -31,9 +37,13 @@ use tokio::sync::{oneshot, Mutex};
 use tracing::info;
 
 #[derive(Debug)]
+/// Interval between probes of the latency of the mirrors of an archive.
+const MIRROR_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub struct ArchiveReaderMetrics {
     pub archive_txns_read: IntCounterVec,
     pub archive_checkpoints_read: IntCounterVec,
+    pub failover: Arc<FallbackObjectStoreMetrics>,
 }
 
 impl ArchiveReaderMetrics {
@@ -53,6 +63,7 @@ impl ArchiveReaderMetrics {
                 registry
             )
             .unwrap(),
+            failover: FallbackObjectStoreMetrics::new(registry),
         };
         Arc::new(this)
     }
@@ -93,12 +104,13 @@ impl ArchiveReaderBalancer {
     }
     pub async fn pick_one_random(
         &self,
-        checkpoint_range: Range<CheckpointSequenceNumber>,
+        checkpoint_range: impl Into<CheckpointRange>,
     ) -> Option<Arc<ArchiveReader>> {
+        let checkpoint_range = checkpoint_range.into();
         let mut archives_with_complete_range = vec![];
         for reader in self.readers.iter() {
             let latest_checkpoint = reader.latest_available_checkpoint().await.unwrap_or(0);
-            if latest_checkpoint >= checkpoint_range.end {
+            if latest_checkpoint >= checkpoint_range.end() {
                 archives_with_complete_range.push(reader.clone());
             }
         }
@@ -113,7 +125,7 @@ impl ArchiveReaderBalancer {
         let mut archives_with_partial_range = vec![];
         for reader in self.readers.iter() {
             let latest_checkpoint = reader.latest_available_checkpoint().await.unwrap_or(0);
-            if latest_checkpoint >= checkpoint_range.start {
+            if latest_checkpoint >= checkpoint_range.start() {
                 archives_with_partial_range.push(reader.clone());
             }
         }
@@ -137,6 +149,7 @@ pub struct ArchiveReader {
     manifest: Arc<Mutex<Manifest>>,
     use_for_pruning_watermark: bool,
     remote_object_store: Arc<dyn ObjectStoreGetExt>,
+    remote_head_store: Arc<dyn ObjectStoreHeadExt>,
     archive_reader_metrics: Arc<ArchiveReaderMetrics>,
 }
 
@@ -147,10 +160,34 @@ impl ArchiveReader {
             .bucket
             .clone()
             .unwrap_or("unknown".to_string());
-        let remote_object_store = if config.remote_store_config.no_sign_request {
-            config.remote_store_config.make_http()?
+        let (remote_object_store, remote_head_store) =
+            Self::make_remote_stores(&config.remote_store_config)?;
+        let (remote_object_store, remote_head_store) = if config.mirror_store_configs.is_empty() {
+            (remote_object_store, remote_head_store)
         } else {
-            config.remote_store_config.make().map(Arc::new)?
+            let mut object_stores = vec![];
+            let mut head_stores = vec![];
+            for mirror_config in &config.mirror_store_configs {
+                let (object_store, head_store) = Self::make_remote_stores(mirror_config)?;
+                object_stores.push(object_store);
+                head_stores.push(head_store);
+            }
+            // Reads go to the fastest of the archive and its mirrors, ranked by the latency of
+            // reading the metadata of the MANIFEST
+            let ranking = StoreRanking::new(head_stores.len() + 1);
+            let object_store = FallbackObjectStore::new(
+                remote_object_store,
+                object_stores,
+                metrics.failover.clone(),
+            )
+            .with_ranking(ranking.clone())?;
+            let head_store =
+                FallbackObjectStore::new(remote_head_store, head_stores, metrics.failover.clone())
+                    .with_ranking(ranking)?;
+            head_store.spawn_latency_probe(Path::from(MANIFEST_FILENAME), MIRROR_PROBE_INTERVAL);
+            let object_store: Arc<dyn ObjectStoreGetExt> = Arc::new(object_store);
+            let head_store: Arc<dyn ObjectStoreHeadExt> = Arc::new(head_store);
+            (object_store, head_store)
         };
         let (sender, recv) = oneshot::channel();
         let manifest = Arc::new(Mutex::new(Manifest::new(0, 0)));
@@ -161,12 +198,24 @@ impl ArchiveReader {
             manifest,
             sender: Arc::new(sender),
             remote_object_store,
+            remote_head_store,
             use_for_pruning_watermark: config.use_for_pruning_watermark,
             concurrency: config.download_concurrency.get(),
             archive_reader_metrics: metrics.clone(),
         })
     }
 
+    fn make_remote_stores(
+        config: &ObjectStoreConfig,
+    ) -> Result<(Arc<dyn ObjectStoreGetExt>, Arc<dyn ObjectStoreHeadExt>)> {
+        if config.no_sign_request {
+            Ok((config.make_http()?, config.make_http_head()?))
+        } else {
+            let store = config.make()?;
+            Ok((Arc::new(store.clone()), Arc::new(store)))
+        }
+    }
+
     /// This function verifies that the files in archive cover the entire range of checkpoints from
     /// sequence number 0 until the latest available checkpoint with no missing checkpoint
     pub async fn verify_manifest(
@@ -268,7 +317,7 @@ impl ArchiveReader {
     pub async fn read_summaries<S>(
         &self,
         store: S,
-        checkpoint_range: Range<CheckpointSequenceNumber>,
+        checkpoint_range: impl Into<CheckpointRange>,
         checkpoint_counter: Arc<AtomicU64>,
         verify: bool,
     ) -> Result<()>
@@ -276,8 +325,9 @@ impl ArchiveReader {
         S: WriteStore + Clone,
         <S as ReadStore>::Error: std::error::Error,
     {
+        let checkpoint_range = checkpoint_range.into();
         let (summary_files, start_index, end_index) =
-            self.get_summary_files(checkpoint_range.clone()).await?;
+            self.get_summary_files(checkpoint_range).await?;
         let remote_object_store = self.remote_object_store.clone();
         let stream = futures::stream::iter(summary_files.iter())
             .enumerate()
@@ -304,10 +354,7 @@ impl ArchiveReader {
                     )
                     .and_then(|summary_iter| {
                         summary_iter
-                            .filter(|s| {
-                                s.sequence_number >= checkpoint_range.start
-                                    && s.sequence_number < checkpoint_range.end
-                            })
+                            .filter(|s| checkpoint_range.contains(s.sequence_number))
                             .try_for_each(|summary| {
                                 let verified_checkpoint = Self::get_or_insert_verified_checkpoint(
                                     &store,
@@ -342,10 +389,7 @@ impl ArchiveReader {
                         )
                         .and_then(|summary_iter| {
                             summary_iter
-                                .filter(|s| {
-                                    s.sequence_number >= checkpoint_range.start
-                                        && s.sequence_number < checkpoint_range.end
-                                })
+                                .filter(|s| checkpoint_range.contains(s.sequence_number))
                                 .try_for_each(|summary| {
                                     Self::insert_certified_checkpoint(&store, summary)?;
                                     checkpoint_counter.fetch_add(1, Ordering::Relaxed);
@@ -365,7 +409,7 @@ impl ArchiveReader {
     pub async fn read<S>(
         &self,
         store: S,
-        checkpoint_range: Range<CheckpointSequenceNumber>,
+        checkpoint_range: impl Into<CheckpointRange>,
         txn_counter: Arc<AtomicU64>,
         checkpoint_counter: Arc<AtomicU64>,
         verify: bool,
@@ -374,6 +418,7 @@ impl ArchiveReader {
         S: WriteStore + Clone,
         <S as ReadStore>::Error: std::error::Error,
     {
+        let checkpoint_range = checkpoint_range.into();
         let manifest = self.manifest.lock().await.clone();
 
         let latest_available_checkpoint = manifest
@@ -381,7 +426,7 @@ impl ArchiveReader {
             .checked_sub(1)
             .context("Checkpoint seq num underflow")?;
 
-        if checkpoint_range.start > latest_available_checkpoint {
+        if checkpoint_range.start() > latest_available_checkpoint {
             return Err(anyhow!(
                 "Latest available checkpoint is: {}",
                 latest_available_checkpoint
@@ -390,20 +435,40 @@ impl ArchiveReader {
 
         let files: Vec<(FileMetadata, FileMetadata)> = self.verify_manifest(manifest).await?;
 
-        let start_index = match files.binary_search_by_key(&checkpoint_range.start, |(s, _c)| {
+        let start_index = match files.binary_search_by_key(&checkpoint_range.start(), |(s, _c)| {
             s.checkpoint_seq_range.start
         }) {
             Ok(index) => index,
             Err(index) => index - 1,
         };
 
-        let end_index = match files.binary_search_by_key(&checkpoint_range.end, |(s, _c)| {
+        let end_index = match files.binary_search_by_key(&checkpoint_range.end(), |(s, _c)| {
             s.checkpoint_seq_range.start
         }) {
             Ok(index) => index,
             Err(index) => index,
         };
 
+        // Fail before downloading anything if files of the range are missing, e.g. as they were
+        // pruned from the remote store after the manifest was synced
+        let expected_files: Vec<_> = files[start_index..end_index.max(start_index)]
+            .iter()
+            .flat_map(|(s, c)| [s.file_path(), c.file_path()])
+            .collect();
+        let missing_files = find_missing_files(
+            &expected_files,
+            &self.remote_head_store,
+            NonZeroUsize::new(self.concurrency).unwrap_or(NonZeroUsize::MIN),
+        )
+        .await?;
+        if !missing_files.is_empty() {
+            return Err(anyhow!(
+                "Missing files in archive for checkpoints {}: {:?}",
+                checkpoint_range,
+                missing_files
+            ));
+        }
+
         let remote_object_store = self.remote_object_store.clone();
         futures::stream::iter(files.iter())
             .enumerate()
@@ -437,10 +502,7 @@ impl ArchiveReader {
                 .and_then(|(summary_iter, content_iter)| {
                     summary_iter
                         .zip(content_iter)
-                        .filter(|(s, _c)| {
-                            s.sequence_number >= checkpoint_range.start
-                                && s.sequence_number < checkpoint_range.end
-                        })
+                        .filter(|(s, _c)| checkpoint_range.contains(s.sequence_number))
                         .try_for_each(|(summary, contents)| {
                             let verified_checkpoint =
                                 Self::get_or_insert_verified_checkpoint(&store, summary, verify)?;
@@ -574,7 +636,7 @@ impl ArchiveReader {
 
     async fn get_summary_files(
         &self,
-        checkpoint_range: Range<CheckpointSequenceNumber>,
+        checkpoint_range: CheckpointRange,
     ) -> Result<(Vec<FileMetadata>, usize, usize)> {
         let manifest = self.manifest.lock().await.clone();
 
@@ -583,7 +645,7 @@ impl ArchiveReader {
             .checked_sub(1)
             .context("Checkpoint seq num underflow")?;
 
-        if checkpoint_range.start > latest_available_checkpoint {
+        if checkpoint_range.start() > latest_available_checkpoint {
             return Err(anyhow!(
                 "Latest available checkpoint is: {}",
                 latest_available_checkpoint
@@ -598,14 +660,14 @@ impl ArchiveReader {
             .collect();
 
         let start_index = match summary_files
-            .binary_search_by_key(&checkpoint_range.start, |s| s.checkpoint_seq_range.start)
+            .binary_search_by_key(&checkpoint_range.start(), |s| s.checkpoint_seq_range.start)
         {
             Ok(index) => index,
             Err(index) => index - 1,
         };
 
         let end_index = match summary_files
-            .binary_search_by_key(&checkpoint_range.end, |s| s.checkpoint_seq_range.start)
+            .binary_search_by_key(&checkpoint_range.end(), |s| s.checkpoint_seq_range.start)
         {
             Ok(index) => index,
             Err(index) => index,
@@ -0,0 +1,154 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Summaries of the archived epochs, so that tooling and dashboards can fetch an epoch's totals
+//! rather than scan its checkpoint files.
+//!
+//! The archive writer tallies the checkpoints of an epoch as it tails them, and once the last
+//! checkpoint of the epoch is published, writes the summary as JSON at
+//! `epoch_<N>/EPOCH_SUMMARY`, next to the epoch's checkpoint and summary files. A writer started
+//! in the middle of an epoch replays the epoch's first checkpoints from the node's store, and
+//! skips the epoch's summary if they were pruned already.
+
+use crate::EPOCH_DIR_PREFIX;
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{HashFunction, Sha3_256};
+use object_store::path::Path;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use sui_storage::object_store::util::{get, put};
+use sui_storage::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
+use sui_types::committee::Committee;
+use sui_types::effects::TransactionEffectsAPI;
+use sui_types::messages_checkpoint::{CheckpointSummary, FullCheckpointContents};
+
+pub const EPOCH_SUMMARY_FILENAME: &str = "EPOCH_SUMMARY";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct EpochSummary {
+    pub epoch: u64,
+    pub checkpoint_seq_range: Range<u64>,
+    pub start_timestamp_ms: u64,
+    pub end_timestamp_ms: u64,
+    pub transaction_count: u64,
+    pub event_count: u64,
+    pub objects_created: u64,
+    pub objects_mutated: u64,
+    /// Objects deleted, including those unwrapped and deleted in the same transaction
+    pub objects_deleted: u64,
+    pub objects_wrapped: u64,
+    pub objects_unwrapped: u64,
+    pub computation_cost: u64,
+    pub storage_cost: u64,
+    pub storage_rebate: u64,
+    pub non_refundable_storage_fee: u64,
+    /// Hex encoded sha3 digest of the validators and stakes of the epoch's committee, if the
+    /// node had it
+    pub validator_set_hash: Option<String>,
+}
+
+impl EpochSummary {
+    pub fn file_path(epoch: u64) -> Path {
+        Path::from(format!("{EPOCH_DIR_PREFIX}{epoch}")).child(EPOCH_SUMMARY_FILENAME)
+    }
+}
+
+/// Tallies the checkpoints of an epoch, in order.
+#[derive(Debug)]
+pub struct EpochSummaryBuilder {
+    summary: Option<EpochSummary>,
+    /// Whether the first checkpoint added is the first of its epoch
+    complete: bool,
+}
+
+impl EpochSummaryBuilder {
+    /// Builder of the summary of the epoch starting with the next checkpoint added.
+    pub fn new() -> Self {
+        EpochSummaryBuilder {
+            summary: None,
+            complete: true,
+        }
+    }
+
+    /// Builder of an epoch whose first checkpoints are missing, which builds no summary.
+    pub fn incomplete() -> Self {
+        EpochSummaryBuilder {
+            summary: None,
+            complete: false,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        checkpoint: &CheckpointSummary,
+        contents: &FullCheckpointContents,
+        event_count: u64,
+    ) {
+        let summary = self.summary.get_or_insert_with(|| EpochSummary {
+            epoch: checkpoint.epoch,
+            checkpoint_seq_range: checkpoint.sequence_number..checkpoint.sequence_number,
+            start_timestamp_ms: checkpoint.timestamp_ms,
+            ..Default::default()
+        });
+        summary.checkpoint_seq_range.end = checkpoint.sequence_number + 1;
+        summary.end_timestamp_ms = checkpoint.timestamp_ms;
+        summary.event_count += event_count;
+        for execution_data in contents.iter() {
+            let effects = &execution_data.effects;
+            summary.transaction_count += 1;
+            summary.objects_created += effects.created().len() as u64;
+            summary.objects_mutated += effects.mutated().len() as u64;
+            summary.objects_deleted +=
+                (effects.deleted().len() + effects.unwrapped_then_deleted().len()) as u64;
+            summary.objects_wrapped += effects.wrapped().len() as u64;
+            summary.objects_unwrapped += effects.unwrapped().len() as u64;
+        }
+        // Gas costs are rolled up across the epoch by every checkpoint
+        let gas = &checkpoint.epoch_rolling_gas_cost_summary;
+        summary.computation_cost = gas.computation_cost;
+        summary.storage_cost = gas.storage_cost;
+        summary.storage_rebate = gas.storage_rebate;
+        summary.non_refundable_storage_fee = gas.non_refundable_storage_fee;
+    }
+
+    /// The summary of the epoch, once its last checkpoint was added, or `None` if the builder is
+    /// missing the epoch's first checkpoints.
+    pub fn finish(self, committee: Option<&Committee>) -> Result<Option<EpochSummary>> {
+        if !self.complete {
+            return Ok(None);
+        }
+        let Some(mut summary) = self.summary else {
+            return Ok(None);
+        };
+        if let Some(committee) = committee {
+            let voting_rights = bcs::to_bytes(&committee.voting_rights)?;
+            summary.validator_set_hash = Some(Hex::encode(Sha3_256::digest(voting_rights).digest));
+        }
+        Ok(Some(summary))
+    }
+}
+
+impl Default for EpochSummaryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub async fn read_epoch_summary<S: ObjectStoreGetExt>(
+    store: S,
+    epoch: u64,
+) -> Result<EpochSummary> {
+    let path = EpochSummary::file_path(epoch);
+    let bytes = get(&store, &path).await?;
+    serde_json::from_slice(&bytes).with_context(|| format!("Invalid epoch summary {path}"))
+}
+
+pub async fn write_epoch_summary<S: ObjectStorePutExt>(
+    summary: &EpochSummary,
+    store: S,
+) -> Result<()> {
+    let bytes = Bytes::from(serde_json::to_vec(summary)?);
+    put(&store, &EpochSummary::file_path(summary.epoch), bytes).await
+}
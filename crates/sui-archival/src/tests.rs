@@ -1,9 +1,18 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::converter::convert_archive_compression;
+use crate::publish::{
+    ManifestPublisher, ManifestSequenceMismatch, PendingPublish, RecoveryOutcome,
+    PUBLISH_JOURNAL_FILENAME,
+};
 use crate::reader::{ArchiveReader, ArchiveReaderMetrics};
+use crate::summary::{read_epoch_summary, EpochSummaryBuilder};
 use crate::writer::ArchiveWriter;
-use crate::{read_manifest, verify_archive_with_local_store, write_manifest, Manifest};
+use crate::{
+    create_file_metadata, read_manifest, verify_archive_with_checksums,
+    verify_archive_with_local_store, write_manifest, CheckpointUpdates, FileType, Manifest,
+};
 use anyhow::{anyhow, Context, Result};
 use more_asserts as ma;
 use object_store::DynObjectStore;
@@ -17,7 +26,9 @@ use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::time::Duration;
 use sui_config::node::ArchiveReaderConfig;
-use sui_storage::object_store::util::path_to_filesystem;
+use sui_storage::blob::{Blob, BlobEncoding};
+use sui_storage::object_store::consistency::ReadAfterWriteMetrics;
+use sui_storage::object_store::util::{copy_file, path_to_filesystem};
 use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreType};
 use sui_storage::{FileCompression, StorageFormat};
 use sui_swarm_config::test_utils::{empty_contents, CommitteeFixture};
@@ -81,7 +92,7 @@ async fn setup_test_state(temp_dir: PathBuf) -> anyhow::Result<TestState> {
     let committee = CommitteeFixture::generate(rand::rngs::OsRng, 0, 4);
     let archive_writer = ArchiveWriter::new(
         local_store_config.clone(),
-        remote_store_config.clone(),
+        remote_store_config.clone().into(),
         FileCompression::Zstd,
         StorageFormat::Blob,
         Duration::from_secs(10),
@@ -91,6 +102,7 @@ async fn setup_test_state(temp_dir: PathBuf) -> anyhow::Result<TestState> {
     .await?;
     let archive_reader_config = ArchiveReaderConfig {
         remote_store_config: remote_store_config.clone(),
+        mirror_store_configs: vec![],
         download_concurrency: NonZeroUsize::new(2).unwrap(),
         use_for_pruning_watermark: false,
     };
@@ -372,3 +384,210 @@ async fn test_verify_archive_with_oneshot_store_bad_data() -> Result<(), anyhow:
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_convert_archive_compression() -> Result<(), anyhow::Error> {
+    let test_store = SharedInMemoryStore::default();
+    let test_state = setup_test_state(temp_dir()).await?;
+    let kill = test_state.archive_writer.start(test_store.clone()).await?;
+    insert_checkpoints_and_verify_manifest(&test_state, test_store.clone(), None).await?;
+    kill.send(())?;
+    // Wait for the writer to stop before rewriting the archive
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let manifest = read_manifest(test_state.remote_store.clone()).await?;
+    let num_files = manifest.files().len();
+    let summary = convert_archive_compression(
+        test_state.remote_store_config.clone(),
+        FileCompression::None,
+        3,
+    )
+    .await?;
+    assert_eq!(summary.converted, num_files);
+    let converted_manifest = read_manifest(test_state.remote_store.clone()).await?;
+    assert_eq!(
+        converted_manifest.next_checkpoint_seq_num(),
+        manifest.next_checkpoint_seq_num()
+    );
+    assert_ne!(converted_manifest.files(), manifest.files());
+    verify_archive_with_checksums(test_state.remote_store_config.clone(), 2).await?;
+
+    // Converting again is a no-op
+    let summary = convert_archive_compression(
+        test_state.remote_store_config.clone(),
+        FileCompression::None,
+        3,
+    )
+    .await?;
+    assert_eq!(summary.skipped, num_files);
+    assert!(!test_state.remote_path.join("CONVERSION_JOURNAL").exists());
+
+    // Converted files are still read and verified
+    let genesis_checkpoint = test_store
+        .get_checkpoint_by_sequence_number(0)?
+        .context("Missing genesis checkpoint")?;
+    let genesis_checkpoint_content = test_store
+        .get_full_checkpoint_contents_by_sequence_number(0)?
+        .context("Missing genesis checkpoint")?;
+    let mut read_store = SingleCheckpointSharedInMemoryStore::default();
+    read_store.insert_genesis_state(
+        genesis_checkpoint,
+        VerifiedCheckpointContents::new_unchecked(genesis_checkpoint_content),
+        test_state.committee.committee().to_owned(),
+    );
+    verify_archive_with_local_store(read_store, test_state.remote_store_config.clone(), 1, false)
+        .await?;
+    Ok(())
+}
+
+/// Write a checkpoint and a summary file of checkpoints 0 to 10 in the local staging dir, as if
+/// they were just cut by the checkpoint writer.
+fn stage_checkpoint_files(test_state: &TestState) -> Result<CheckpointUpdates> {
+    let epoch_dir = test_state.local_path.join("epoch_0");
+    fs::create_dir_all(&epoch_dir)?;
+    fs::write(epoch_dir.join("0.chk"), b"checkpoint contents")?;
+    fs::write(epoch_dir.join("0.sum"), b"checkpoint summaries")?;
+    let checkpoint_file_metadata = create_file_metadata(
+        &epoch_dir.join("0.chk"),
+        FileType::CheckpointContent,
+        0,
+        0..10,
+    )?;
+    let summary_file_metadata = create_file_metadata(
+        &epoch_dir.join("0.sum"),
+        FileType::CheckpointSummary,
+        0,
+        0..10,
+    )?;
+    Ok(CheckpointUpdates::new(
+        0,
+        10,
+        checkpoint_file_metadata,
+        summary_file_metadata,
+        &mut Manifest::new(0, 0),
+    ))
+}
+
+fn make_publisher(test_state: &TestState) -> Result<ManifestPublisher> {
+    Ok(ManifestPublisher::new(
+        test_state.local_path.clone(),
+        test_state.local_store.clone(),
+        test_state.remote_store.clone(),
+        test_state.remote_store_config.make_conditional_put()?,
+        None,
+        ReadAfterWriteMetrics::new_for_tests(),
+    ))
+}
+
+#[tokio::test]
+async fn test_publish_recovers_interrupted_publish() -> Result<(), anyhow::Error> {
+    let test_state = setup_test_state(temp_dir()).await?;
+    let updates = stage_checkpoint_files(&test_state)?;
+    let pending = PendingPublish::new(&updates);
+
+    // Crash after the journal was written and the summary file uploaded, but before the content
+    // file and the MANIFEST were
+    let mut journal = vec![];
+    Blob::encode(&pending, BlobEncoding::Bcs)?.write(&mut journal)?;
+    fs::write(
+        test_state.local_path.join(PUBLISH_JOURNAL_FILENAME),
+        journal,
+    )?;
+    let summary_file_path = updates.summary_file_path();
+    copy_file(
+        &summary_file_path,
+        &summary_file_path,
+        &test_state.local_store,
+        &test_state.remote_store,
+    )
+    .await?;
+    assert!(!test_state.remote_path.join("MANIFEST").exists());
+
+    let publisher = make_publisher(&test_state)?;
+    assert_eq!(publisher.recover().await?, RecoveryOutcome::Completed);
+    assert_eq!(
+        read_manifest(test_state.remote_store.clone()).await?,
+        pending.manifest
+    );
+    for file in pending.files.iter() {
+        let path = file.file_path();
+        assert!(path_to_filesystem(test_state.remote_path.clone(), &path)?.exists());
+        assert!(!path_to_filesystem(test_state.local_path.clone(), &path)?.exists());
+    }
+    assert!(!test_state
+        .local_path
+        .join(PUBLISH_JOURNAL_FILENAME)
+        .exists());
+    assert_eq!(publisher.recover().await?, RecoveryOutcome::NothingPending);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_publish_checks_manifest_sequence() -> Result<(), anyhow::Error> {
+    let test_state = setup_test_state(temp_dir()).await?;
+    let updates = stage_checkpoint_files(&test_state)?;
+    // Another writer already archived checkpoints 0 to 5
+    let other_manifest = Manifest::new(0, 5);
+    write_manifest(other_manifest.clone(), test_state.remote_store.clone()).await?;
+
+    let publisher = make_publisher(&test_state)?;
+    let error = publisher.publish(&updates).await.unwrap_err();
+    assert_eq!(
+        error.downcast::<ManifestSequenceMismatch>()?,
+        ManifestSequenceMismatch {
+            expected: 0,
+            found: 5
+        }
+    );
+    assert_eq!(
+        read_manifest(test_state.remote_store.clone()).await?,
+        other_manifest
+    );
+
+    // The publish is discarded on restart, as the remote MANIFEST moved past it
+    assert_eq!(publisher.recover().await?, RecoveryOutcome::Discarded);
+    assert!(!test_state
+        .local_path
+        .join(PUBLISH_JOURNAL_FILENAME)
+        .exists());
+    assert_eq!(
+        read_manifest(test_state.remote_store.clone()).await?,
+        other_manifest
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_epoch_summary() -> Result<(), anyhow::Error> {
+    let test_state = setup_test_state(temp_dir()).await?;
+    let (checkpoints, _, _, _) = test_state.committee.make_empty_checkpoints(3, None);
+    let contents = empty_contents().into_inner();
+
+    let mut builder = EpochSummaryBuilder::new();
+    for checkpoint in checkpoints.iter() {
+        builder.add(checkpoint, &contents, 2);
+    }
+    let summary = builder
+        .finish(Some(test_state.committee.committee()))?
+        .context("Missing epoch summary")?;
+    assert_eq!(summary.epoch, 0);
+    assert_eq!(summary.checkpoint_seq_range, 0..3);
+    assert_eq!(summary.transaction_count, 0);
+    assert_eq!(summary.event_count, 6);
+    assert!(summary.validator_set_hash.is_some());
+
+    // No summary is built for an epoch whose first checkpoints weren't added
+    let mut builder = EpochSummaryBuilder::incomplete();
+    builder.add(checkpoints.last().unwrap(), &contents, 0);
+    assert!(builder.finish(None)?.is_none());
+
+    // The summary is written once the files ending the epoch are published
+    let mut updates = stage_checkpoint_files(&test_state)?;
+    updates.epoch_summary = Some(summary.clone());
+    make_publisher(&test_state)?.publish(&updates).await?;
+    assert_eq!(
+        read_epoch_summary(test_state.remote_store.clone(), 0).await?,
+        summary
+    );
+    Ok(())
+}
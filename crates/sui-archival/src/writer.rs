@@ -2,9 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 #![allow(dead_code)]
 
+use crate::publish::ManifestPublisher;
+use crate::summary::{EpochSummary, EpochSummaryBuilder};
 use crate::{
-    create_file_metadata, read_manifest, write_manifest, CheckpointUpdates, FileMetadata, FileType,
-    Manifest, CHECKPOINT_FILE_MAGIC, CHECKPOINT_FILE_SUFFIX, EPOCH_DIR_PREFIX, MAGIC_BYTES,
+    create_file_metadata, read_manifest, CheckpointUpdates, FileMetadata, FileType, Manifest,
+    CHECKPOINT_FILE_MAGIC, CHECKPOINT_FILE_SUFFIX, EPOCH_DIR_PREFIX, MAGIC_BYTES,
     SUMMARY_FILE_MAGIC, SUMMARY_FILE_SUFFIX,
 };
 use anyhow::Result;
@@ -21,9 +23,12 @@ use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration;
 use sui_storage::blob::{Blob, BlobEncoding};
-use sui_storage::object_store::util::{copy_file, path_to_filesystem};
+use sui_storage::object_store::circuit::CircuitBreakerMetrics;
+use sui_storage::object_store::consistency::ReadAfterWriteMetrics;
+use sui_storage::object_store::mirror::MirroredObjectStoreConfig;
 use sui_storage::object_store::ObjectStoreConfig;
 use sui_storage::{compress, FileCompression, StorageFormat};
+use sui_types::effects::TransactionEffectsAPI;
 use sui_types::messages_checkpoint::{
     CertifiedCheckpointSummary as Checkpoint, CheckpointSequenceNumber,
     FullCheckpointContents as CheckpointContents,
@@ -32,10 +37,12 @@ use sui_types::storage::{ReadStore, WriteStore};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time::Instant;
-use tracing::{debug, info};
+use tracing::{info, warn};
 
 pub struct ArchiveMetrics {
     pub latest_checkpoint_archived: IntGauge,
+    pub read_after_write: Arc<ReadAfterWriteMetrics>,
+    pub circuit_breaker: Arc<CircuitBreakerMetrics>,
 }
 
 impl ArchiveMetrics {
@@ -47,6 +54,8 @@ impl ArchiveMetrics {
                 registry
             )
             .unwrap(),
+            read_after_write: ReadAfterWriteMetrics::new(registry),
+            circuit_breaker: CircuitBreakerMetrics::new(registry),
         };
         Arc::new(this)
     }
@@ -67,6 +76,8 @@ struct CheckpointWriter {
     last_commit_instant: Instant,
     commit_duration: Duration,
     commit_file_size: usize,
+    /// Summary of the epoch ended by the last checkpoint written, published with the next cut
+    epoch_summary: Option<EpochSummary>,
 }
 
 impl CheckpointWriter {
@@ -116,6 +127,7 @@ impl CheckpointWriter {
             last_commit_instant: Instant::now(),
             commit_duration,
             commit_file_size,
+            epoch_summary: None,
         })
     }
 
@@ -223,18 +235,22 @@ impl CheckpointWriter {
         if !self.checkpoint_range.is_empty() {
             let checkpoint_file_metadata = self.finalize()?;
             let summary_file_metadata = self.finalize_summary()?;
-            let checkpoint_updates = CheckpointUpdates::new(
+            let mut checkpoint_updates = CheckpointUpdates::new(
                 self.epoch_num,
                 self.checkpoint_range.end,
                 checkpoint_file_metadata,
                 summary_file_metadata,
                 &mut self.manifest,
             );
+            checkpoint_updates.epoch_summary = self.epoch_summary.take();
             info!("Checkpoint file cut for: {:?}", checkpoint_updates);
             self.sender.blocking_send(checkpoint_updates)?;
         }
         Ok(())
     }
+    fn set_epoch_summary(&mut self, epoch_summary: EpochSummary) {
+        self.epoch_summary = Some(epoch_summary);
+    }
     fn compress(&self, source: &Path) -> Result<()> {
         if self.file_compression == FileCompression::None {
             return Ok(());
@@ -315,8 +331,8 @@ pub struct ArchiveWriter {
     file_compression: FileCompression,
     storage_format: StorageFormat,
     local_staging_dir_root: PathBuf,
-    local_object_store: Arc<DynObjectStore>,
     remote_object_store: Arc<DynObjectStore>,
+    publisher: Arc<ManifestPublisher>,
     commit_duration: Duration,
     commit_file_size: usize,
     archive_metrics: Arc<ArchiveMetrics>,
@@ -325,22 +341,42 @@ pub struct ArchiveWriter {
 impl ArchiveWriter {
     pub async fn new(
         local_store_config: ObjectStoreConfig,
-        remote_store_config: ObjectStoreConfig,
+        remote_store_config: MirroredObjectStoreConfig,
         file_compression: FileCompression,
         storage_format: StorageFormat,
         commit_duration: Duration,
         commit_file_size: usize,
         registry: &Registry,
     ) -> Result<Self> {
+        let local_staging_dir_root = local_store_config
+            .directory
+            .clone()
+            .context("Missing local dir")?;
+        let archive_metrics = ArchiveMetrics::new(registry);
+        let mirrored = Arc::new(remote_store_config.make_with(|config| {
+            config.make_with_circuit_breaker_metrics(archive_metrics.circuit_breaker.clone())
+        })?);
+        let remote_object_store: Arc<DynObjectStore> = mirrored.clone();
+        let primary_store_config = remote_store_config.primary()?;
+        let publisher = ManifestPublisher::new(
+            local_staging_dir_root.clone(),
+            local_store_config.make()?,
+            remote_object_store.clone(),
+            primary_store_config.make_conditional_put()?,
+            primary_store_config.read_after_write_timeout(),
+            archive_metrics.read_after_write.clone(),
+        )
+        .with_mirrors(mirrored)
+        .with_streaming_threshold(primary_store_config.streaming_threshold());
         Ok(ArchiveWriter {
             file_compression,
             storage_format,
-            remote_object_store: remote_store_config.make()?,
-            local_object_store: local_store_config.make()?,
-            local_staging_dir_root: local_store_config.directory.context("Missing local dir")?,
+            local_staging_dir_root,
+            remote_object_store,
+            publisher: Arc::new(publisher),
             commit_duration,
             commit_file_size,
-            archive_metrics: ArchiveMetrics::new(registry),
+            archive_metrics,
         })
     }
 
@@ -349,6 +385,11 @@ impl ArchiveWriter {
         S: WriteStore + Send + Sync + 'static,
         <S as ReadStore>::Error: Send,
     {
+        // Before the staging dir is cleared by the checkpoint writer
+        self.publisher
+            .recover()
+            .await
+            .expect("Failed to recover interrupted publish");
         let remote_archive_is_empty = self
             .remote_object_store
             .list_with_delimiter(None)
@@ -365,6 +406,13 @@ impl ArchiveWriter {
                 .expect("Failed to read manifest")
         };
         let start_checkpoint_sequence_number = manifest.next_checkpoint_seq_num();
+        let epoch_start_checkpoint_sequence_number = manifest
+            .files()
+            .iter()
+            .filter(|file| file.epoch_num == manifest.epoch_num())
+            .map(|file| file.checkpoint_seq_range.start)
+            .min()
+            .unwrap_or(start_checkpoint_sequence_number);
         let (sender, receiver) = mpsc::channel::<CheckpointUpdates>(100);
         let checkpoint_writer = CheckpointWriter::new(
             self.local_staging_dir_root.clone(),
@@ -378,9 +426,7 @@ impl ArchiveWriter {
         .expect("Failed to create checkpoint writer");
         let (kill_sender, kill_receiver) = tokio::sync::broadcast::channel::<()>(1);
         tokio::spawn(Self::start_syncing_with_remote(
-            self.remote_object_store.clone(),
-            self.local_object_store.clone(),
-            self.local_staging_dir_root.clone(),
+            self.publisher.clone(),
             receiver,
             kill_sender.subscribe(),
             self.archive_metrics.clone(),
@@ -388,6 +434,7 @@ impl ArchiveWriter {
         tokio::task::spawn_blocking(move || {
             Self::start_tailing_checkpoints(
                 start_checkpoint_sequence_number,
+                epoch_start_checkpoint_sequence_number,
                 checkpoint_writer,
                 store,
                 kill_receiver,
@@ -398,6 +445,7 @@ impl ArchiveWriter {
 
     fn start_tailing_checkpoints<S>(
         start_checkpoint_sequence_number: CheckpointSequenceNumber,
+        epoch_start_checkpoint_sequence_number: CheckpointSequenceNumber,
         mut checkpoint_writer: CheckpointWriter,
         store: S,
         mut kill: tokio::sync::broadcast::Receiver<()>,
@@ -408,6 +456,11 @@ impl ArchiveWriter {
     {
         let mut checkpoint_sequence_number = start_checkpoint_sequence_number;
         info!("Starting checkpoint tailing from sequence number: {checkpoint_sequence_number}");
+        let mut epoch_summary = Self::resume_epoch_summary(
+            &store,
+            epoch_start_checkpoint_sequence_number,
+            checkpoint_sequence_number,
+        );
 
         while kill.try_recv().is_err() {
             if let Some(checkpoint_summary) = store
@@ -418,8 +471,21 @@ impl ArchiveWriter {
                     .get_full_checkpoint_contents(&checkpoint_summary.content_digest)
                     .map_err(|_| anyhow!("Failed to read checkpoint content from store"))?
                 {
+                    let event_count = Self::count_events(&store, &checkpoint_contents)?;
+                    epoch_summary.add(&checkpoint_summary, &checkpoint_contents, event_count);
+                    let epoch = checkpoint_summary.epoch;
+                    let end_of_epoch = checkpoint_summary.end_of_epoch_data.is_some();
                     checkpoint_writer
                         .write(checkpoint_contents, checkpoint_summary.into_inner())?;
+                    if end_of_epoch {
+                        let committee = store
+                            .get_committee(epoch)
+                            .map_err(|_| anyhow!("Failed to read committee from store"))?;
+                        match std::mem::take(&mut epoch_summary).finish(committee.as_deref())? {
+                            Some(summary) => checkpoint_writer.set_epoch_summary(summary),
+                            None => warn!("Skipping summary of incomplete epoch {epoch}"),
+                        }
+                    }
                     checkpoint_sequence_number = checkpoint_sequence_number
                         .checked_add(1)
                         .context("checkpoint seq number overflow")?;
@@ -434,10 +500,77 @@ impl ArchiveWriter {
         Ok(())
     }
 
+    /// Tally the checkpoints of the epoch being tailed from its first checkpoint, at
+    /// `epoch_start_checkpoint_sequence_number`, up to where tailing starts.
+    fn resume_epoch_summary<S: ReadStore>(
+        store: &S,
+        epoch_start_checkpoint_sequence_number: CheckpointSequenceNumber,
+        start_checkpoint_sequence_number: CheckpointSequenceNumber,
+    ) -> EpochSummaryBuilder {
+        let previous_ends_epoch = match start_checkpoint_sequence_number.checked_sub(1) {
+            Some(previous) => matches!(
+                store.get_checkpoint_by_sequence_number(previous),
+                Ok(Some(checkpoint)) if checkpoint.end_of_epoch_data.is_some()
+            ),
+            None => true,
+        };
+        let mut epoch_summary = EpochSummaryBuilder::new();
+        if previous_ends_epoch {
+            return epoch_summary;
+        }
+        for checkpoint_sequence_number in
+            epoch_start_checkpoint_sequence_number..start_checkpoint_sequence_number
+        {
+            if let Err(e) =
+                Self::replay_checkpoint(store, checkpoint_sequence_number, &mut epoch_summary)
+            {
+                warn!("Failed to replay checkpoint {checkpoint_sequence_number}: {e}");
+                return EpochSummaryBuilder::incomplete();
+            }
+        }
+        epoch_summary
+    }
+
+    fn replay_checkpoint<S: ReadStore>(
+        store: &S,
+        checkpoint_sequence_number: CheckpointSequenceNumber,
+        epoch_summary: &mut EpochSummaryBuilder,
+    ) -> Result<()> {
+        let checkpoint_summary = store
+            .get_checkpoint_by_sequence_number(checkpoint_sequence_number)
+            .map_err(|_| anyhow!("Failed to read checkpoint summary from store"))?
+            .context("Checkpoint summary is missing from store")?;
+        let checkpoint_contents = store
+            .get_full_checkpoint_contents(&checkpoint_summary.content_digest)
+            .map_err(|_| anyhow!("Failed to read checkpoint content from store"))?
+            .context("Checkpoint content is missing from store")?;
+        let event_count = Self::count_events(store, &checkpoint_contents)?;
+        epoch_summary.add(&checkpoint_summary, &checkpoint_contents, event_count);
+        if checkpoint_summary.end_of_epoch_data.is_some() {
+            *epoch_summary = EpochSummaryBuilder::new();
+        }
+        Ok(())
+    }
+
+    fn count_events<S: ReadStore>(
+        store: &S,
+        checkpoint_contents: &CheckpointContents,
+    ) -> Result<u64> {
+        let mut event_count = 0;
+        for execution_data in checkpoint_contents.iter() {
+            if let Some(events_digest) = execution_data.effects.events_digest() {
+                let events = store
+                    .get_transaction_events(events_digest)
+                    .map_err(|_| anyhow!("Failed to read transaction events from store"))?
+                    .context("Transaction events are missing from store")?;
+                event_count += events.data.len() as u64;
+            }
+        }
+        Ok(event_count)
+    }
+
     async fn start_syncing_with_remote(
-        remote_object_store: Arc<DynObjectStore>,
-        local_object_store: Arc<DynObjectStore>,
-        local_staging_root_dir: PathBuf,
+        publisher: Arc<ManifestPublisher>,
         mut update_receiver: Receiver<CheckpointUpdates>,
         mut kill: tokio::sync::broadcast::Receiver<()>,
         metrics: Arc<ArchiveMetrics>,
@@ -449,32 +582,10 @@ impl ArchiveWriter {
                     if let Some(checkpoint_updates) = updates {
                         info!("Received checkpoint update: {:?}", checkpoint_updates);
                         let latest_checkpoint_seq_num = checkpoint_updates.manifest.next_checkpoint_seq_num();
-                        let summary_file_path = checkpoint_updates.summary_file_path();
-                        Self::sync_file_to_remote(
-                            local_staging_root_dir.clone(),
-                            summary_file_path,
-                            local_object_store.clone(),
-                            remote_object_store.clone()
-                        )
-                        .await
-                        .expect("Syncing checkpoint summary should not fail");
-
-                        let content_file_path = checkpoint_updates.content_file_path();
-                        Self::sync_file_to_remote(
-                            local_staging_root_dir.clone(),
-                            content_file_path,
-                            local_object_store.clone(),
-                            remote_object_store.clone()
-                        )
-                        .await
-                        .expect("Syncing checkpoint content should not fail");
-
-                        write_manifest(
-                            checkpoint_updates.manifest,
-                            remote_object_store.clone()
-                        )
-                        .await
-                        .expect("Updating manifest should not fail");
+                        publisher
+                            .publish(&checkpoint_updates)
+                            .await
+                            .expect("Publishing checkpoint files should not fail");
                         metrics.latest_checkpoint_archived.set(latest_checkpoint_seq_num as i64)
                     } else {
                         info!("Terminating archive sync loop");
@@ -485,16 +596,4 @@ impl ArchiveWriter {
         }
         Ok(())
     }
-
-    async fn sync_file_to_remote(
-        dir: PathBuf,
-        path: object_store::path::Path,
-        from: Arc<DynObjectStore>,
-        to: Arc<DynObjectStore>,
-    ) -> Result<()> {
-        debug!("Syncing archive file to remote: {:?}", path);
-        copy_file(&path, &path, &from, &to).await?;
-        fs::remove_file(path_to_filesystem(dir, &path)?)?;
-        Ok(())
-    }
 }
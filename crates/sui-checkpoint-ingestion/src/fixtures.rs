@@ -0,0 +1,391 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Corpus of recorded checkpoints, for testing checkpoint handlers against real chain data rather
+//! than hand-constructed checkpoints.
+//!
+//! A corpus is a set of BCS encoded [`CheckpointData`] files, laid out like any checkpoint store
+//! (see [`checkpoint_path`]), so that it can be recorded from a full node with
+//! [`CheckpointCorpus::record`] and [`CheckpointCorpus::save`], kept in a directory or bucket, and
+//! loaded with [`CheckpointCorpus::load`]. Small corpora can be embedded in the test binary with
+//! `include_bytes!` and [`CheckpointCorpus::from_encoded`].
+//!
+//! Tests pick the checkpoints exercising an [`EdgeCase`] with [`CheckpointCorpus::require`], and
+//! check the rows produced by a handler against the checkpoint with the `assert_*` helpers.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path as FsPath;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use object_store::local::LocalFileSystem;
+use object_store::{DynObjectStore, ObjectStore};
+use sui_storage::object_store::ObjectStoreListExt;
+use sui_types::base_types::{ObjectID, SequenceNumber};
+use sui_types::digests::TransactionDigest;
+use sui_types::effects::TransactionEffectsAPI;
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+use sui_types::object::OBJECT_START_VERSION;
+use sui_types::transaction::TransactionDataAPI;
+
+use crate::{
+    checkpoint_path, CheckpointData, CheckpointReader, CheckpointStream,
+    ObjectStoreCheckpointReader, CHECKPOINT_FILE_SUFFIX,
+};
+
+/// Objects written or deleted by the transactions of a checkpoint for it to count as
+/// [`EdgeCase::ManyObjects`].
+pub const MANY_OBJECTS_THRESHOLD: usize = 1000;
+
+/// Situations handlers get wrong most often, which a corpus should hold checkpoints of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeCase {
+    /// Last checkpoint of an epoch, holding the change epoch transaction
+    EpochBoundary,
+    /// Transaction whose gas is paid by another address than its sender
+    SponsoredTransaction,
+    /// At least [`MANY_OBJECTS_THRESHOLD`] objects written or deleted in a checkpoint
+    ManyObjects,
+    PackagePublish,
+    PackageUpgrade,
+}
+
+impl EdgeCase {
+    pub const ALL: [EdgeCase; 5] = [
+        EdgeCase::EpochBoundary,
+        EdgeCase::SponsoredTransaction,
+        EdgeCase::ManyObjects,
+        EdgeCase::PackagePublish,
+        EdgeCase::PackageUpgrade,
+    ];
+
+    pub fn matches(&self, checkpoint: &CheckpointData) -> bool {
+        match self {
+            EdgeCase::EpochBoundary => checkpoint.checkpoint_summary.end_of_epoch_data.is_some(),
+            EdgeCase::SponsoredTransaction => checkpoint.transactions.iter().any(|tx| {
+                let data = tx.transaction.data().transaction_data();
+                data.gas_owner() != data.sender()
+            }),
+            EdgeCase::ManyObjects => {
+                checkpoint
+                    .transactions
+                    .iter()
+                    .map(|tx| tx.output_objects.len() + tx.effects.deleted().len())
+                    .sum::<usize>()
+                    >= MANY_OBJECTS_THRESHOLD
+            }
+            // Packages are immutable, so the packages written by a transaction are new ones, and
+            // upgrades are the versions after the first one.
+            EdgeCase::PackagePublish => checkpoint
+                .output_objects()
+                .iter()
+                .any(|object| object.is_package() && object.version() == OBJECT_START_VERSION),
+            EdgeCase::PackageUpgrade => checkpoint
+                .output_objects()
+                .iter()
+                .any(|object| object.is_package() && object.version() > OBJECT_START_VERSION),
+        }
+    }
+}
+
+impl Display for EdgeCase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EdgeCase::EpochBoundary => "epoch boundary",
+            EdgeCase::SponsoredTransaction => "sponsored transaction",
+            EdgeCase::ManyObjects => "many objects",
+            EdgeCase::PackagePublish => "package publish",
+            EdgeCase::PackageUpgrade => "package upgrade",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Checkpoints of a corpus, by sequence number.
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointCorpus {
+    checkpoints: Arc<BTreeMap<CheckpointSequenceNumber, CheckpointData>>,
+}
+
+impl CheckpointCorpus {
+    pub fn new(checkpoints: impl IntoIterator<Item = CheckpointData>) -> Self {
+        Self {
+            checkpoints: Arc::new(
+                checkpoints
+                    .into_iter()
+                    .map(|checkpoint| {
+                        (*checkpoint.checkpoint_summary.sequence_number(), checkpoint)
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Corpus of BCS encoded checkpoints, e.g. embedded with `include_bytes!`.
+    pub fn from_encoded<B: AsRef<[u8]>>(files: impl IntoIterator<Item = B>) -> Result<Self> {
+        let checkpoints = files
+            .into_iter()
+            .enumerate()
+            .map(|(idx, bytes)| {
+                bcs::from_bytes(bytes.as_ref())
+                    .with_context(|| format!("Failed to decode checkpoint file {idx}"))
+            })
+            .collect::<Result<Vec<CheckpointData>>>()?;
+        Ok(Self::new(checkpoints))
+    }
+
+    /// Load every checkpoint file at the root of `store`.
+    pub async fn load(store: Arc<DynObjectStore>) -> Result<Self> {
+        let sequence_numbers: Vec<CheckpointSequenceNumber> = store
+            .list_objects(None)
+            .await?
+            .try_filter_map(|meta| async move {
+                Ok(meta
+                    .location
+                    .filename()
+                    .and_then(|name| name.strip_suffix(&format!(".{CHECKPOINT_FILE_SUFFIX}")))
+                    .and_then(|seq| seq.parse().ok()))
+            })
+            .try_collect()
+            .await?;
+        let reader = ObjectStoreCheckpointReader::new(store);
+        let mut checkpoints = Vec::with_capacity(sequence_numbers.len());
+        for sequence_number in sequence_numbers {
+            let checkpoint = reader
+                .get_checkpoint(sequence_number)
+                .await?
+                .with_context(|| format!("Checkpoint {sequence_number} removed while loading"))?;
+            checkpoints.push(checkpoint);
+        }
+        Ok(Self::new(checkpoints))
+    }
+
+    /// Load every checkpoint file in the directory `path`.
+    pub async fn load_local(path: impl AsRef<FsPath>) -> Result<Self> {
+        let store = LocalFileSystem::new_with_prefix(path.as_ref())?;
+        Self::load(Arc::new(store)).await
+    }
+
+    /// Record the checkpoints of `stream`, e.g. a range of checkpoints of a full node holding the
+    /// edge cases to test, built with [`crate::CheckpointIngestionBuilder`].
+    pub async fn record(stream: CheckpointStream) -> Result<Self> {
+        let checkpoints: Vec<_> = stream.try_collect().await?;
+        Ok(Self::new(checkpoints))
+    }
+
+    /// Write the checkpoints of the corpus to `store`, to be loaded with [`Self::load`].
+    pub async fn save(&self, store: &DynObjectStore) -> Result<()> {
+        for (sequence_number, checkpoint) in self.checkpoints.iter() {
+            let bytes = bcs::to_bytes(checkpoint)?;
+            store
+                .put(&checkpoint_path(*sequence_number), bytes.into())
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+
+    pub fn get(&self, sequence_number: CheckpointSequenceNumber) -> Option<&CheckpointData> {
+        self.checkpoints.get(&sequence_number)
+    }
+
+    /// Checkpoints of the corpus, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &CheckpointData> {
+        self.checkpoints.values()
+    }
+
+    /// Checkpoints exercising `edge_case`, in order.
+    pub fn with_edge_case(&self, edge_case: EdgeCase) -> impl Iterator<Item = &CheckpointData> {
+        self.iter()
+            .filter(move |checkpoint| edge_case.matches(checkpoint))
+    }
+
+    /// The first checkpoint exercising `edge_case`, panicking if the corpus has none, so that
+    /// tests don't pass vacuously on a corpus missing their case.
+    pub fn require(&self, edge_case: EdgeCase) -> &CheckpointData {
+        self.with_edge_case(edge_case)
+            .next()
+            .unwrap_or_else(|| panic!("Checkpoint corpus has no checkpoint of a {edge_case}"))
+    }
+
+    /// Edge cases the corpus has no checkpoint of.
+    pub fn missing_edge_cases(&self) -> Vec<EdgeCase> {
+        EdgeCase::ALL
+            .into_iter()
+            .filter(|edge_case| self.with_edge_case(*edge_case).next().is_none())
+            .collect()
+    }
+
+    /// Reader serving the corpus, to feed it to a [`crate::CheckpointIngestionBuilder`].
+    pub fn reader(&self) -> CorpusCheckpointReader {
+        CorpusCheckpointReader {
+            checkpoints: self.checkpoints.clone(),
+        }
+    }
+}
+
+/// Serves the checkpoints of a [`CheckpointCorpus`].
+pub struct CorpusCheckpointReader {
+    checkpoints: Arc<BTreeMap<CheckpointSequenceNumber, CheckpointData>>,
+}
+
+#[async_trait]
+impl CheckpointReader for CorpusCheckpointReader {
+    async fn get_checkpoint(
+        &self,
+        sequence_number: CheckpointSequenceNumber,
+    ) -> Result<Option<CheckpointData>> {
+        Ok(self.checkpoints.get(&sequence_number).cloned())
+    }
+}
+
+/// Assert that `rows` hold one row per transaction of `checkpoint`, in order, given the digest of
+/// the transaction of each row.
+pub fn assert_transactions<T>(
+    checkpoint: &CheckpointData,
+    rows: &[T],
+    digest: impl Fn(&T) -> TransactionDigest,
+) {
+    let expected: Vec<_> = checkpoint
+        .transactions
+        .iter()
+        .map(|tx| *tx.effects.transaction_digest())
+        .collect();
+    let actual: Vec<_> = rows.iter().map(digest).collect();
+    assert_eq!(
+        actual,
+        expected,
+        "Transactions of checkpoint {}",
+        checkpoint.checkpoint_summary.sequence_number()
+    );
+}
+
+/// Assert that `rows` hold one row per event of `checkpoint`, in order, given the digest of the
+/// transaction emitting the event of each row, and its index among the transaction's events.
+pub fn assert_events<T>(
+    checkpoint: &CheckpointData,
+    rows: &[T],
+    key: impl Fn(&T) -> (TransactionDigest, usize),
+) {
+    let expected: Vec<_> = checkpoint
+        .transactions
+        .iter()
+        .flat_map(|tx| {
+            let digest = *tx.effects.transaction_digest();
+            let events = tx.events.as_ref().map_or(0, |events| events.data.len());
+            (0..events).map(move |idx| (digest, idx))
+        })
+        .collect();
+    let actual: Vec<_> = rows.iter().map(key).collect();
+    assert_eq!(
+        actual,
+        expected,
+        "Events of checkpoint {}",
+        checkpoint.checkpoint_summary.sequence_number()
+    );
+}
+
+/// Assert that `rows` hold one row per object version written by `checkpoint`, in any order, given
+/// the ID and version of the object of each row.
+pub fn assert_output_objects<T>(
+    checkpoint: &CheckpointData,
+    rows: &[T],
+    key: impl Fn(&T) -> (ObjectID, SequenceNumber),
+) {
+    let mut expected: Vec<_> = checkpoint
+        .output_objects()
+        .into_iter()
+        .map(|object| (object.id(), object.version()))
+        .collect();
+    expected.sort();
+    let mut actual: Vec<_> = rows.iter().map(key).collect();
+    actual.sort();
+    assert_eq!(
+        actual,
+        expected,
+        "Objects written by checkpoint {}",
+        checkpoint.checkpoint_summary.sequence_number()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{assert_transactions, CheckpointCorpus, EdgeCase};
+    use crate::{CheckpointData, CheckpointIngestionBuilder};
+    use futures::TryStreamExt;
+    use object_store::memory::InMemory;
+    use std::sync::Arc;
+    use sui_protocol_config::ProtocolVersion;
+    use sui_types::committee::Committee;
+    use sui_types::digests::TransactionDigest;
+    use sui_types::gas::GasCostSummary;
+    use sui_types::messages_checkpoint::{
+        CertifiedCheckpointSummary, CheckpointContents, CheckpointSequenceNumber,
+        CheckpointSummary, EndOfEpochData,
+    };
+
+    fn checkpoint(sequence_number: CheckpointSequenceNumber, end_of_epoch: bool) -> CheckpointData {
+        let (committee, keypairs) = Committee::new_simple_test_committee();
+        let contents = CheckpointContents::new_with_digests_only_for_tests(vec![]);
+        let end_of_epoch_data = end_of_epoch.then(|| EndOfEpochData {
+            next_epoch_committee: committee.voting_rights.clone(),
+            next_epoch_protocol_version: ProtocolVersion::MIN,
+            epoch_commitments: vec![],
+        });
+        let summary = CheckpointSummary::new(
+            0,
+            sequence_number,
+            0,
+            &contents,
+            None,
+            GasCostSummary::default(),
+            end_of_epoch_data,
+            0,
+        );
+        CheckpointData {
+            checkpoint_summary: CertifiedCheckpointSummary::new_from_keypairs_for_testing(
+                summary, &keypairs, &committee,
+            ),
+            checkpoint_contents: contents,
+            transactions: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corpus_saved_and_loaded() -> anyhow::Result<()> {
+        let corpus = CheckpointCorpus::new([checkpoint(1, false), checkpoint(2, true)]);
+        let store = Arc::new(InMemory::new());
+        corpus.save(store.as_ref()).await?;
+
+        let loaded = CheckpointCorpus::load(store).await?;
+        assert_eq!(loaded.len(), 2);
+        let boundary = loaded.require(EdgeCase::EpochBoundary);
+        assert_eq!(*boundary.checkpoint_summary.sequence_number(), 2);
+        assert_transactions(boundary, &Vec::<TransactionDigest>::new(), |digest| *digest);
+        assert!(loaded
+            .missing_edge_cases()
+            .contains(&EdgeCase::PackageUpgrade));
+
+        // The corpus can be fed to ingestion like any other source of checkpoints
+        let sequence_numbers: Vec<_> = CheckpointIngestionBuilder::new()
+            .reader(loaded.reader())
+            .start_checkpoint(1)
+            .end_checkpoint(2)
+            .build()?
+            .map_ok(|checkpoint| *checkpoint.checkpoint_summary.sequence_number())
+            .try_collect()
+            .await?;
+        assert_eq!(sequence_numbers, vec![1, 2]);
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ingestion of checkpoints, for indexers and other services processing the chain's history.
+//!
+//! [`CheckpointIngestionBuilder`] builds a stream of [`CheckpointData`], read from a full node's
+//! REST API, from an object store holding checkpoint files, or from any [`CheckpointReader`].
+//! Checkpoints are fetched concurrently but yielded in order, starting from a watermark, e.g.
+//! the checkpoint after the last one the consumer processed. Failed reads are retried, and the
+//! stream waits for checkpoints that are not available yet, until it reaches its end watermark,
+//! if any.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! use futures::TryStreamExt;
+//! use sui_checkpoint_ingestion::CheckpointIngestionBuilder;
+//!
+//! let mut checkpoints = CheckpointIngestionBuilder::new()
+//!     .rest_url("http://127.0.0.1:9000/rest")
+//!     .start_checkpoint(1000)
+//!     .build()?;
+//! while let Some(checkpoint) = checkpoints.try_next().await? {
+//!     println!("{}", checkpoint.checkpoint_summary.sequence_number());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use backoff::backoff::Backoff;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use sui_storage::object_store::retry::RetryConfig;
+use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreType};
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+use tracing::{debug, warn};
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod fixtures;
+mod reader;
+
+pub use reader::{
+    checkpoint_path, CheckpointReader, ObjectStoreCheckpointReader, RestCheckpointReader,
+    CHECKPOINT_FILE_SUFFIX,
+};
+pub use sui_rest_api::CheckpointData;
+
+pub const DEFAULT_CONCURRENCY: usize = 100;
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub type CheckpointStream = BoxStream<'static, Result<CheckpointData>>;
+
+enum CheckpointSource {
+    Rest(String),
+    ObjectStore(ObjectStoreConfig),
+    Reader(Arc<dyn CheckpointReader>),
+}
+
+pub struct CheckpointIngestionBuilder {
+    source: Option<CheckpointSource>,
+    start_checkpoint: CheckpointSequenceNumber,
+    end_checkpoint: Option<CheckpointSequenceNumber>,
+    concurrency: usize,
+    poll_interval: Duration,
+    retry: RetryConfig,
+}
+
+impl CheckpointIngestionBuilder {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            source: None,
+            start_checkpoint: 0,
+            end_checkpoint: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            retry: RetryConfig {
+                max_retries: 10,
+                base_delay: Duration::from_millis(200),
+                jitter: 0.5,
+                retry_not_found: false,
+                budget: None,
+            },
+        }
+    }
+
+    /// Read checkpoints from the REST API of a full node, e.g. `http://127.0.0.1:9000/rest`.
+    pub fn rest_url(mut self, url: impl Into<String>) -> Self {
+        self.source = Some(CheckpointSource::Rest(url.into()));
+        self
+    }
+
+    /// Read checkpoint files from an object store, see [`ObjectStoreCheckpointReader`].
+    pub fn object_store(mut self, config: ObjectStoreConfig) -> Self {
+        self.source = Some(CheckpointSource::ObjectStore(config));
+        self
+    }
+
+    /// Read checkpoint files from a local directory, see [`ObjectStoreCheckpointReader`].
+    pub fn local_path(self, path: impl Into<PathBuf>) -> Self {
+        self.object_store(ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(path.into()),
+            ..Default::default()
+        })
+    }
+
+    pub fn reader(mut self, reader: impl CheckpointReader + 'static) -> Self {
+        self.source = Some(CheckpointSource::Reader(Arc::new(reader)));
+        self
+    }
+
+    /// First checkpoint of the stream.
+    pub fn start_checkpoint(mut self, start_checkpoint: CheckpointSequenceNumber) -> Self {
+        self.start_checkpoint = start_checkpoint;
+        self
+    }
+
+    /// Last checkpoint of the stream, which otherwise never ends.
+    pub fn end_checkpoint(mut self, end_checkpoint: CheckpointSequenceNumber) -> Self {
+        self.end_checkpoint = Some(end_checkpoint);
+        self
+    }
+
+    /// Number of checkpoints fetched at the same time.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// How long to wait before asking again for checkpoints that are not available yet.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// How reads failing with an error are retried. Once a read fails after all its retries, the
+    /// stream yields the error and ends.
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn build(self) -> Result<CheckpointStream> {
+        let reader: Arc<dyn CheckpointReader> = match self.source {
+            Some(CheckpointSource::Rest(url)) => Arc::new(RestCheckpointReader::new(url)),
+            Some(CheckpointSource::ObjectStore(config)) => {
+                Arc::new(ObjectStoreCheckpointReader::new(config.make()?))
+            }
+            Some(CheckpointSource::Reader(reader)) => reader,
+            None => bail!("No checkpoint source configured"),
+        };
+        if self.concurrency == 0 {
+            bail!("Checkpoint ingestion concurrency must be positive");
+        }
+        let ingestion = Ingestion {
+            reader,
+            next_checkpoint: self.start_checkpoint,
+            end_checkpoint: self.end_checkpoint,
+            concurrency: self.concurrency,
+            poll_interval: self.poll_interval,
+            retry: self.retry,
+            fetched: VecDeque::new(),
+            failed: false,
+        };
+        Ok(
+            futures::stream::unfold(ingestion, |mut ingestion| async move {
+                let checkpoint = ingestion.next().await?;
+                Some((checkpoint, ingestion))
+            })
+            .boxed(),
+        )
+    }
+}
+
+struct Ingestion {
+    reader: Arc<dyn CheckpointReader>,
+    /// First checkpoint not fetched yet.
+    next_checkpoint: CheckpointSequenceNumber,
+    end_checkpoint: Option<CheckpointSequenceNumber>,
+    concurrency: usize,
+    poll_interval: Duration,
+    retry: RetryConfig,
+    /// Checkpoints fetched but not yielded yet, in order.
+    fetched: VecDeque<CheckpointData>,
+    failed: bool,
+}
+
+impl Ingestion {
+    async fn next(&mut self) -> Option<Result<CheckpointData>> {
+        loop {
+            if let Some(checkpoint) = self.fetched.pop_front() {
+                return Some(Ok(checkpoint));
+            }
+            if self.failed
+                || self
+                    .end_checkpoint
+                    .map_or(false, |end| self.next_checkpoint > end)
+            {
+                return None;
+            }
+            match self.fetch_batch().await {
+                Ok(0) => tokio::time::sleep(self.poll_interval).await,
+                Ok(_) => {}
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+
+    /// Fetch the next checkpoints, up to the first one not available yet. Returns how many were
+    /// fetched.
+    async fn fetch_batch(&mut self) -> Result<usize> {
+        let mut last = self
+            .next_checkpoint
+            .saturating_add(self.concurrency as u64 - 1);
+        if let Some(end) = self.end_checkpoint {
+            last = last.min(end);
+        }
+        let (reader, retry) = (self.reader.clone(), self.retry.clone());
+        let mut checkpoints = futures::stream::iter(self.next_checkpoint..=last)
+            .map(|sequence_number| get_with_retries(reader.clone(), retry.clone(), sequence_number))
+            .buffered(self.concurrency);
+
+        let mut count = 0;
+        while let Some(checkpoint) = checkpoints.next().await {
+            let Some(checkpoint) = checkpoint? else {
+                break;
+            };
+            self.fetched.push_back(checkpoint);
+            self.next_checkpoint += 1;
+            count += 1;
+        }
+        debug!(
+            "Fetched {count} checkpoints, next is {}",
+            self.next_checkpoint
+        );
+        Ok(count)
+    }
+}
+
+async fn get_with_retries(
+    reader: Arc<dyn CheckpointReader>,
+    retry: RetryConfig,
+    sequence_number: CheckpointSequenceNumber,
+) -> Result<Option<CheckpointData>> {
+    let mut backoff = retry.backoff();
+    let mut retries = 0;
+    loop {
+        let result = reader
+            .get_checkpoint(sequence_number)
+            .await
+            .and_then(|checkpoint| check_sequence_number(checkpoint, sequence_number));
+        match result {
+            Ok(checkpoint) => return Ok(checkpoint),
+            Err(e) if retries < retry.max_retries && retry.spend_budget() => {
+                let Some(delay) = backoff.next_backoff() else {
+                    return Err(e);
+                };
+                retries += 1;
+                warn!(
+                    "Retrying checkpoint {sequence_number} in {delay:?} ({retries}/{}) after error: {e}",
+                    retry.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read checkpoint {sequence_number}"))
+            }
+        }
+    }
+}
+
+fn check_sequence_number(
+    checkpoint: Option<CheckpointData>,
+    sequence_number: CheckpointSequenceNumber,
+) -> Result<Option<CheckpointData>> {
+    if let Some(checkpoint) = &checkpoint {
+        let actual = *checkpoint.checkpoint_summary.sequence_number();
+        if actual != sequence_number {
+            return Err(anyhow!(
+                "Asked for checkpoint {sequence_number}, got checkpoint {actual}"
+            ));
+        }
+    }
+    Ok(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{checkpoint_path, CheckpointIngestionBuilder};
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    use object_store::local::LocalFileSystem;
+    use object_store::ObjectStore;
+    use sui_rest_api::CheckpointData;
+    use sui_types::committee::Committee;
+    use sui_types::gas::GasCostSummary;
+    use sui_types::messages_checkpoint::{
+        CertifiedCheckpointSummary, CheckpointContents, CheckpointSequenceNumber, CheckpointSummary,
+    };
+
+    fn checkpoint(sequence_number: CheckpointSequenceNumber) -> CheckpointData {
+        let (committee, keypairs) = Committee::new_simple_test_committee();
+        let contents = CheckpointContents::new_with_digests_only_for_tests(vec![]);
+        let summary = CheckpointSummary::new(
+            0,
+            sequence_number,
+            0,
+            &contents,
+            None,
+            GasCostSummary::default(),
+            None,
+            0,
+        );
+        CheckpointData {
+            checkpoint_summary: CertifiedCheckpointSummary::new_from_keypairs_for_testing(
+                summary, &keypairs, &committee,
+            ),
+            checkpoint_contents: contents,
+            transactions: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_local_checkpoints_in_order() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = LocalFileSystem::new_with_prefix(dir.path())?;
+        for sequence_number in 0..10 {
+            let bytes = bcs::to_bytes(&checkpoint(sequence_number))?;
+            store
+                .put(&checkpoint_path(sequence_number), Bytes::from(bytes))
+                .await?;
+        }
+
+        let checkpoints: Vec<_> = CheckpointIngestionBuilder::new()
+            .local_path(dir.path())
+            .start_checkpoint(2)
+            .end_checkpoint(8)
+            .concurrency(3)
+            .build()?
+            .map_ok(|checkpoint| *checkpoint.checkpoint_summary.sequence_number())
+            .try_collect()
+            .await?;
+        assert_eq!(checkpoints, (2..=8).collect::<Vec<_>>());
+        Ok(())
+    }
+}
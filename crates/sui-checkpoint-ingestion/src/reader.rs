@@ -0,0 +1,105 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use sui_rest_api::{CheckpointData, Client};
+use sui_storage::object_store::ObjectStoreGetExt;
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+
+pub const CHECKPOINT_FILE_SUFFIX: &str = "chk";
+
+/// Source of checkpoints for [`crate::CheckpointIngestionBuilder`]. Implement it to ingest
+/// checkpoints from a source not supported out of the box.
+#[async_trait]
+pub trait CheckpointReader: Send + Sync {
+    /// Returns `None` if the checkpoint is not available from the source yet, in which case it
+    /// is asked for again later. Errors are retried.
+    async fn get_checkpoint(
+        &self,
+        sequence_number: CheckpointSequenceNumber,
+    ) -> Result<Option<CheckpointData>>;
+}
+
+/// Reads checkpoints from the REST API of a full node.
+pub struct RestCheckpointReader {
+    client: Client,
+    highest_known_checkpoint: AtomicU64,
+}
+
+impl RestCheckpointReader {
+    /// `url` is the base URL of the REST API, e.g. `http://127.0.0.1:9000/rest`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::from_client(Client::new(url))
+    }
+
+    pub fn from_client(client: Client) -> Self {
+        Self {
+            client,
+            highest_known_checkpoint: AtomicU64::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl CheckpointReader for RestCheckpointReader {
+    async fn get_checkpoint(
+        &self,
+        sequence_number: CheckpointSequenceNumber,
+    ) -> Result<Option<CheckpointData>> {
+        // The node is only asked for its latest checkpoint once all the ones known are read.
+        if sequence_number > self.highest_known_checkpoint.load(Ordering::Relaxed) {
+            let latest = self.client.get_latest_checkpoint().await?;
+            let highest = self
+                .highest_known_checkpoint
+                .fetch_max(*latest.sequence_number(), Ordering::Relaxed)
+                .max(*latest.sequence_number());
+            if sequence_number > highest {
+                return Ok(None);
+            }
+        }
+        Ok(Some(
+            self.client.get_full_checkpoint(sequence_number).await?,
+        ))
+    }
+}
+
+/// Path of the file holding checkpoint `sequence_number` in an object store.
+pub fn checkpoint_path(sequence_number: CheckpointSequenceNumber) -> Path {
+    Path::from(format!("{sequence_number}.{CHECKPOINT_FILE_SUFFIX}"))
+}
+
+/// Reads checkpoints from an object store, remote or on the local file system, holding every
+/// checkpoint as a BCS encoded [`CheckpointData`] at [`checkpoint_path`].
+pub struct ObjectStoreCheckpointReader {
+    store: Arc<DynObjectStore>,
+}
+
+impl ObjectStoreCheckpointReader {
+    pub fn new(store: Arc<DynObjectStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl CheckpointReader for ObjectStoreCheckpointReader {
+    async fn get_checkpoint(
+        &self,
+        sequence_number: CheckpointSequenceNumber,
+    ) -> Result<Option<CheckpointData>> {
+        let path = checkpoint_path(sequence_number);
+        let bytes = match self.store.get_bytes(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.is_not_found() => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let checkpoint =
+            bcs::from_bytes(&bytes).with_context(|| format!("Failed to decode {path}"))?;
+        Ok(Some(checkpoint))
+    }
+}
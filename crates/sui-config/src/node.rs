@@ -21,6 +21,7 @@ use std::time::Duration;
 use std::usize;
 use sui_keys::keypair_file::{read_authority_keypair_from_file, read_keypair_from_file};
 use sui_protocol_config::{Chain, SupportedProtocolVersions};
+use sui_storage::object_store::mirror::MirroredObjectStoreConfig;
 use sui_storage::object_store::ObjectStoreConfig;
 use sui_types::base_types::{ObjectID, SuiAddress};
 use sui_types::crypto::AuthorityPublicKeyBytes;
@@ -162,12 +163,24 @@ pub struct NodeConfig {
 
     #[serde(default = "default_overload_threshold_config")]
     pub overload_threshold_config: OverloadThresholdConfig,
+
+    /// Upper bound, in bytes, on the memory used by in-memory caches together. If not set, each
+    /// cache is only bounded by its own limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_budget_bytes: Option<usize>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub struct TransactionKeyValueStoreReadConfig {
     pub base_url: String,
+    /// zstd dictionary the uploader of the store compresses values with, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_dictionary_path: Option<PathBuf>,
+    /// Store of the packs the uploader packs small values into, if any. Packed values are read
+    /// from their pack with ranged gets, and the others from `base_url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack_store_config: Option<ObjectStoreConfig>,
 }
 
 fn default_jwk_fetch_interval_seconds() -> u64 {
@@ -198,6 +211,8 @@ pub fn default_zklogin_oauth_providers() -> BTreeMap<Chain, BTreeSet<String>> {
 fn default_transaction_kv_store_config() -> TransactionKeyValueStoreReadConfig {
     TransactionKeyValueStoreReadConfig {
         base_url: "https://transactions.sui.io/".to_string(),
+        compression_dictionary_path: None,
+        pack_store_config: None,
     }
 }
 
@@ -252,6 +267,8 @@ pub fn bool_true() -> bool {
 
 impl Config for NodeConfig {}
 
+impl Config for ObjectStoreConfig {}
+
 impl NodeConfig {
     pub fn protocol_key_pair(&self) -> &AuthorityKeyPair {
         self.protocol_key_pair.authority_keypair()
@@ -322,6 +339,7 @@ impl NodeConfig {
                     .as_ref()
                     .map(|remote_store_config| ArchiveReaderConfig {
                         remote_store_config: remote_store_config.clone(),
+                        mirror_store_configs: config.mirror_object_store_configs.clone(),
                         download_concurrency: NonZeroUsize::new(config.concurrency)
                             .unwrap_or(NonZeroUsize::new(5).unwrap()),
                         use_for_pruning_watermark: config.use_for_pruning_watermark,
@@ -664,11 +682,18 @@ pub struct DBCheckpointConfig {
     pub perform_index_db_checkpoints_at_epoch_end: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prune_and_compact_before_upload: Option<bool>,
+    /// Upload db checkpoints under `cas/`, named by the digest of each file, with a manifest of
+    /// the files of each epoch, so that files unchanged since a previous epoch aren't uploaded
+    /// again.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_addressed_upload: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ArchiveReaderConfig {
     pub remote_store_config: ObjectStoreConfig,
+    /// Mirrors of the archive at `remote_store_config`
+    pub mirror_store_configs: Vec<ObjectStoreConfig>,
     pub download_concurrency: NonZeroUsize,
     pub use_for_pruning_watermark: bool,
 }
@@ -680,6 +705,23 @@ pub struct StateArchiveConfig {
     pub object_store_config: Option<ObjectStoreConfig>,
     pub concurrency: usize,
     pub use_for_pruning_watermark: bool,
+    /// Mirrors of the archive at `object_store_config`, e.g. community mirrors of a public
+    /// archive. Reads go to the mirror with the lowest latency, failing over to the others.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirror_object_store_configs: Vec<ObjectStoreConfig>,
+    /// Stores the archive is written to instead of `object_store_config`, e.g. an S3 and a GCS
+    /// bucket for disaster recovery. The MANIFEST is only conditionally written to the first one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrored_write_config: Option<MirroredObjectStoreConfig>,
+}
+
+impl StateArchiveConfig {
+    /// Stores the archive is written to, if any.
+    pub fn write_store_config(&self) -> Option<MirroredObjectStoreConfig> {
+        self.mirrored_write_config
+            .clone()
+            .or_else(|| self.object_store_config.clone().map(Into::into))
+    }
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -688,6 +730,19 @@ pub struct StateSnapshotConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub object_store_config: Option<ObjectStoreConfig>,
     pub concurrency: usize,
+    /// Stores snapshots are written to instead of `object_store_config`, e.g. an S3 and a GCS
+    /// bucket for disaster recovery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrored_write_config: Option<MirroredObjectStoreConfig>,
+}
+
+impl StateSnapshotConfig {
+    /// Stores snapshots are written to, if any.
+    pub fn write_store_config(&self) -> Option<MirroredObjectStoreConfig> {
+        self.mirrored_write_config
+            .clone()
+            .or_else(|| self.object_store_config.clone().map(Into::into))
+    }
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
@@ -699,6 +754,10 @@ pub struct TransactionKeyValueStoreWriteConfig {
     pub table_name: String,
     pub bucket_name: String,
     pub concurrency: usize,
+    /// zstd dictionary to compress values with, e.g. trained with `zstd_dict_tool`. Readers of
+    /// the store need the same dictionary.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression_dictionary_path: Option<PathBuf>,
 }
 
 /// Configuration for the threshold(s) at which we consider the system
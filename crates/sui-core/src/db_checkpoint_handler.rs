@@ -19,6 +19,8 @@ use std::sync::Arc;
 use std::time::Duration;
 use sui_config::node::AuthorityStorePruningConfig;
 use sui_storage::mutex_table::RwLockTable;
+use sui_storage::object_store::cas::{ContentAddressedStore, CAS_MANIFEST_FILENAME};
+use sui_storage::object_store::checksum::ChecksummedStore;
 use sui_storage::object_store::util::{
     copy_recursively, find_all_dirs_with_epoch_prefix, find_missing_epochs_dirs,
     path_to_filesystem, put, write_snapshot_manifest,
@@ -63,6 +65,9 @@ pub struct DBCheckpointHandler {
     gc_markers: Vec<String>,
     /// Boolean flag to enable/disable object pruning and manual compaction before upload
     prune_and_compact_before_upload: bool,
+    /// Whether files are uploaded to a content addressed layout, in which files identical to the
+    /// ones of a previous epoch aren't uploaded again
+    content_addressed: bool,
     /// Indirect object config for pruner
     indirect_objects_threshold: usize,
     /// Pruning objects
@@ -76,6 +81,7 @@ impl DBCheckpointHandler {
         output_object_store_config: Option<&ObjectStoreConfig>,
         interval_s: u64,
         prune_and_compact_before_upload: bool,
+        content_addressed: bool,
         indirect_objects_threshold: usize,
         pruning_config: AuthorityStorePruningConfig,
         registry: &Registry,
@@ -98,6 +104,7 @@ impl DBCheckpointHandler {
             interval: Duration::from_secs(interval_s),
             gc_markers,
             prune_and_compact_before_upload,
+            content_addressed,
             indirect_objects_threshold,
             pruning_config,
             metrics: DBCheckpointMetrics::new(registry),
@@ -121,6 +128,7 @@ impl DBCheckpointHandler {
             interval: Duration::from_secs(interval_s),
             gc_markers: vec![UPLOAD_COMPLETED_MARKER.to_string(), TEST_MARKER.to_string()],
             prune_and_compact_before_upload,
+            content_addressed: false,
             indirect_objects_threshold: 0,
             pruning_config: AuthorityStorePruningConfig::default(),
             metrics: DBCheckpointMetrics::new(&Registry::default()),
@@ -287,13 +295,32 @@ impl DBCheckpointHandler {
                     self.prune_and_compact(local_db_path, *epoch).await?;
                 }
                 info!("Copying db checkpoint for epoch: {epoch} to remote storage");
-                copy_recursively(
-                    db_path,
-                    &self.input_object_store,
-                    &object_store,
-                    NonZeroUsize::new(20).unwrap(),
-                )
-                .await?;
+                if self.content_addressed {
+                    // Files are checked against their digest on restore.
+                    let cas = ContentAddressedStore::new(object_store.clone());
+                    copy_recursively(
+                        db_path,
+                        &self.input_object_store,
+                        &cas,
+                        NonZeroUsize::new(20).unwrap(),
+                    )
+                    .await?;
+                    cas.write_manifest(&db_path.child(CAS_MANIFEST_FILENAME))
+                        .await?;
+                    let (uploaded_bytes, deduplicated_bytes) = cas.upload_stats();
+                    info!(
+                        "Uploaded {uploaded_bytes} bytes of db checkpoint for epoch: {epoch}, skipped {deduplicated_bytes} bytes already in remote storage"
+                    );
+                } else {
+                    // Checksums are written along with every file, for restores to verify.
+                    copy_recursively(
+                        db_path,
+                        &self.input_object_store,
+                        &ChecksummedStore::new(object_store.clone()),
+                        NonZeroUsize::new(20).unwrap(),
+                    )
+                    .await?;
+                }
                 // Drop marker in the output directory that upload completed successfully
                 let bytes = Bytes::from_static(b"success");
                 let success_marker = db_path.child(SUCCESS_MARKER);
@@ -417,6 +444,7 @@ mod tests {
         assert!(remote_epoch0_checkpoint.join("file1").exists());
         assert!(remote_epoch0_checkpoint.join("file2").exists());
         assert!(remote_epoch0_checkpoint.join("data").join("file3").exists());
+        assert!(remote_epoch0_checkpoint.join("file1.sha256").exists());
         assert!(remote_epoch0_checkpoint.join(SUCCESS_MARKER).exists());
         assert!(local_epoch0_checkpoint
             .join(UPLOAD_COMPLETED_MARKER)
@@ -3,7 +3,7 @@
 
 use std::{
     cmp::max,
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -209,6 +209,61 @@ impl AvailableObjectsCache {
     }
 }
 
+/// Weight of the latest interval in the moving average of release intervals.
+const RELEASE_INTERVAL_WEIGHT: f64 = 0.2;
+
+struct ReleaseInterval {
+    last_release: Instant,
+    average: Option<Duration>,
+}
+
+impl ReleaseInterval {
+    fn new() -> Self {
+        Self {
+            last_release: Instant::now(),
+            average: None,
+        }
+    }
+
+    fn record_release(&mut self) {
+        let interval = self.last_release.elapsed();
+        self.last_release = Instant::now();
+        self.average = Some(match self.average {
+            Some(average) => {
+                average.mul_f64(1.0 - RELEASE_INTERVAL_WEIGHT)
+                    + interval.mul_f64(RELEASE_INTERVAL_WEIGHT)
+            }
+            None => interval,
+        });
+    }
+}
+
+/// Transactions waiting on an object, and an estimate of how long a transaction depending on it
+/// would wait for them to be executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectQueueEstimate {
+    pub object_id: ObjectID,
+    /// Number of transactions waiting on the object
+    pub queue_len: usize,
+    /// How long the oldest transaction waiting on the object has been waiting
+    pub oldest_age: Option<Duration>,
+    /// Average time between two transactions waiting on the object being released, once two of
+    /// them were released
+    pub release_interval: Option<Duration>,
+}
+
+impl ObjectQueueEstimate {
+    /// Time until every transaction waiting on the object is released: the queue length times the
+    /// release interval if it is known, or the age of the oldest transaction otherwise, as it is
+    /// likely to wait as long again.
+    pub fn estimated_wait(&self) -> Duration {
+        match self.release_interval {
+            Some(interval) => interval * self.queue_len as u32,
+            None => self.oldest_age.unwrap_or_default(),
+        }
+    }
+}
+
 struct Inner {
     // Current epoch of TransactionManager.
     epoch: EpochId,
@@ -221,6 +276,11 @@ struct Inner {
     // An `IndexMap` is used to ensure that the insertion order is preserved.
     input_objects: HashMap<ObjectID, IndexMap<TransactionDigest, Instant>>,
 
+    // Moving average of the time between two transactions waiting on each object being released,
+    // while transactions are waiting on it. Used to estimate how long new transactions depending
+    // on hot objects will wait.
+    release_intervals: HashMap<ObjectID, ReleaseInterval>,
+
     // Maps object IDs to the highest observed sequence number of the object. When the value is
     // None, indicates that the object is immutable, corresponding to an InputKey with no sequence
     // number.
@@ -242,6 +302,7 @@ impl Inner {
             epoch,
             missing_inputs: HashMap::with_capacity(MIN_HASHMAP_CAPACITY),
             input_objects: HashMap::with_capacity(MIN_HASHMAP_CAPACITY),
+            release_intervals: HashMap::with_capacity(MIN_HASHMAP_CAPACITY),
             available_objects_cache: AvailableObjectsCache::new(metrics),
             pending_certificates: HashMap::with_capacity(MIN_HASHMAP_CAPACITY),
             executing_certificates: HashSet::with_capacity(MIN_HASHMAP_CAPACITY),
@@ -288,6 +349,15 @@ impl Inner {
 
         if input_txns.is_empty() {
             self.input_objects.remove(&input_key.id());
+            self.release_intervals.remove(&input_key.id());
+        } else {
+            // The first release only starts the interval to the next one.
+            match self.release_intervals.entry(input_key.id()) {
+                Entry::Occupied(mut interval) => interval.get_mut().record_release(),
+                Entry::Vacant(entry) => {
+                    entry.insert(ReleaseInterval::new());
+                }
+            }
         }
 
         for digest in digests {
@@ -311,6 +381,7 @@ impl Inner {
     fn maybe_reserve_capacity(&mut self) {
         self.missing_inputs.maybe_reserve_capacity();
         self.input_objects.maybe_reserve_capacity();
+        self.release_intervals.maybe_reserve_capacity();
         self.pending_certificates.maybe_reserve_capacity();
         self.executing_certificates.maybe_reserve_capacity();
     }
@@ -319,6 +390,7 @@ impl Inner {
     fn maybe_shrink_capacity(&mut self) {
         self.missing_inputs.maybe_shrink_capacity();
         self.input_objects.maybe_shrink_capacity();
+        self.release_intervals.maybe_shrink_capacity();
         self.pending_certificates.maybe_shrink_capacity();
         self.executing_certificates.maybe_shrink_capacity();
     }
@@ -769,6 +841,27 @@ impl TransactionManager {
             .collect()
     }
 
+    /// Returns the transactions waiting on each object ID, and how fast they are being released.
+    pub fn objects_queue_estimates(&self, keys: Vec<ObjectID>) -> Vec<ObjectQueueEstimate> {
+        let inner = self.inner.read();
+        keys.into_iter()
+            .map(|object_id| {
+                let txns = inner.input_objects.get(&object_id);
+                ObjectQueueEstimate {
+                    object_id,
+                    queue_len: txns.map_or(0, |txns| txns.len()),
+                    oldest_age: txns
+                        .and_then(|txns| txns.first())
+                        .map(|(_, time)| time.elapsed()),
+                    release_interval: inner
+                        .release_intervals
+                        .get(&object_id)
+                        .and_then(|interval| interval.average),
+                }
+            })
+            .collect()
+    }
+
     // Returns the number of transactions pending or being executed right now.
     pub(crate) fn inflight_queue_len(&self) -> usize {
         let inner = self.inner.read();
@@ -843,6 +936,7 @@ impl TransactionManager {
             "Input objects: {:?}",
             inner.input_objects
         );
+        assert!(inner.release_intervals.is_empty());
         assert!(
             inner.pending_certificates.is_empty(),
             "Pending certificates: {:?}",
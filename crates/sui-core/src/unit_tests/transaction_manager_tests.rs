@@ -353,6 +353,83 @@ async fn transaction_manager_object_dependency() {
     transaction_manager.check_empty_for_testing();
 }
 
+// Tests the estimates of the queue of transactions waiting on a shared object, as its versions
+// become available one after the other.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn transaction_manager_queue_estimates() {
+    let (owner, _keypair) = deterministic_random_account_key();
+    let gas_objects: Vec<Object> = (0..3)
+        .map(|_| Object::with_id_owner_for_testing(ObjectID::random(), owner))
+        .collect();
+    let shared_object = Object::shared_for_testing();
+    let state =
+        init_state_with_objects([gas_objects.clone(), vec![shared_object.clone()]].concat()).await;
+    let (transaction_manager, mut rx_ready_certificates) = make_transaction_manager(&state);
+
+    // Enqueue three transactions mutating the shared object, at consecutive versions.
+    let shared_object_arg = ObjectArg::SharedObject {
+        id: shared_object.id(),
+        initial_shared_version: 0.into(),
+        mutable: true,
+    };
+    let mut transactions = vec![];
+    for (i, gas_object) in gas_objects.iter().enumerate() {
+        let transaction =
+            make_transaction(gas_object.clone(), vec![CallArg::Object(shared_object_arg)]);
+        state
+            .epoch_store_for_testing()
+            .set_shared_object_versions_for_testing(
+                transaction.digest(),
+                &vec![(shared_object.id(), (1000 + i as u64).into())],
+            )
+            .unwrap();
+        transactions.push(transaction);
+    }
+    transaction_manager
+        .enqueue(transactions.clone(), &state.epoch_store_for_testing())
+        .unwrap();
+
+    let estimate = |transaction_manager: &TransactionManager| {
+        transaction_manager
+            .objects_queue_estimates(vec![shared_object.id()])
+            .pop()
+            .unwrap()
+    };
+    let queued = estimate(&transaction_manager);
+    assert_eq!(queued.queue_len, 3);
+    assert!(queued.oldest_age.is_some());
+    assert_eq!(queued.release_interval, None);
+    assert_eq!(queued.estimated_wait(), queued.oldest_age.unwrap());
+
+    // The release interval is known once two transactions were released.
+    for (i, transaction) in transactions.iter().enumerate() {
+        transaction_manager.objects_available(
+            vec![InputKey::VersionedObject {
+                id: shared_object.id(),
+                version: (1000 + i as u64).into(),
+            }],
+            &state.epoch_store_for_testing(),
+        );
+        let ready = rx_ready_certificates.recv().await.unwrap().0;
+        assert_eq!(ready.digest(), transaction.digest());
+        let queued = estimate(&transaction_manager);
+        assert_eq!(queued.queue_len, 2 - i);
+        assert_eq!(queued.release_interval.is_some(), i == 1);
+    }
+    let queued = estimate(&transaction_manager);
+    assert_eq!(queued.oldest_age, None);
+    assert_eq!(queued.estimated_wait(), Duration::ZERO);
+
+    for transaction in &transactions {
+        transaction_manager.notify_commit(
+            transaction.digest(),
+            vec![],
+            &state.epoch_store_for_testing(),
+        );
+    }
+    transaction_manager.check_empty_for_testing();
+}
+
 #[tokio::test(flavor = "current_thread", start_paused = true)]
 async fn transaction_manager_receiving_notify_commit() {
     telemetry_subscribers::init_for_testing();
@@ -45,6 +45,7 @@ pub(crate) trait GenericQueryBuilder<DB: Backend> {
         filter: Option<TransactionBlockFilter>,
         after_tx_seq_num: Option<i64>,
         before_tx_seq_num: Option<i64>,
+        watermark: i64,
     ) -> Result<transactions::BoxedQuery<'static, DB>, Error>;
     fn multi_get_coins(
         cursor: Option<Vec<u8>>,
@@ -67,6 +68,7 @@ pub(crate) trait GenericQueryBuilder<DB: Backend> {
         descending_order: bool,
         limit: i64,
         epoch: Option<i64>,
+        watermark: i64,
     ) -> checkpoints::BoxedQuery<'static, DB>;
 }
 
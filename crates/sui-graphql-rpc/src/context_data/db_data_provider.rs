@@ -1,6 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use super::snapshot_cursor::{encode_cursor, SnapshotCursor};
 use crate::{
     config::Limits,
     error::Error,
@@ -55,7 +56,7 @@ use sui_indexer::{
         checkpoints::StoredCheckpoint, epoch::StoredEpochInfo, objects::StoredObject,
         transactions::StoredTransaction,
     },
-    schema_v2::transactions,
+    schema_v2::{checkpoints, transactions},
     types_v2::OwnerType,
     PgConnectionPoolConfig,
 };
@@ -302,12 +303,19 @@ impl PgManager {
         last: Option<u64>,
         before: Option<String>,
         filter: Option<TransactionBlockFilter>,
-    ) -> Result<Option<(Vec<StoredTransaction>, bool)>, Error> {
+    ) -> Result<Option<(Vec<StoredTransaction>, bool, i64)>, Error> {
         let descending_order = last.is_some();
         let cursor = after
             .or(before)
-            .map(|cursor| self.parse_tx_cursor(&cursor))
+            .map(|cursor| SnapshotCursor::parse(&cursor, |key| self.parse_tx_cursor(key)))
             .transpose()?;
+        let Some(watermark) = self
+            .get_watermark(cursor.as_ref().and_then(|c| c.watermark))
+            .await?
+        else {
+            return Ok(None);
+        };
+        let cursor = cursor.map(|c| c.key);
         let limit = first.or(last).unwrap_or(DEFAULT_PAGE_SIZE) as i64;
         let mut after_tx_seq_num: Option<i64> = None;
         let mut before_tx_seq_num: Option<i64> = None;
@@ -356,6 +364,7 @@ impl PgManager {
                 filter.clone(),
                 after_tx_seq_num,
                 before_tx_seq_num,
+                watermark,
             )
         };
 
@@ -370,7 +379,7 @@ impl PgManager {
                     stored_txs.pop();
                 }
 
-                Ok((stored_txs, has_next_page))
+                Ok((stored_txs, has_next_page, watermark))
             })
             .transpose()
     }
@@ -382,12 +391,19 @@ impl PgManager {
         last: Option<u64>,
         before: Option<String>,
         epoch: Option<u64>,
-    ) -> Result<Option<(Vec<StoredCheckpoint>, bool)>, Error> {
+    ) -> Result<Option<(Vec<StoredCheckpoint>, bool, i64)>, Error> {
         let descending_order = last.is_some();
         let cursor = after
             .or(before)
-            .map(|cursor| self.parse_checkpoint_cursor(&cursor))
+            .map(|cursor| SnapshotCursor::parse(&cursor, |key| self.parse_checkpoint_cursor(key)))
             .transpose()?;
+        let Some(watermark) = self
+            .get_watermark(cursor.as_ref().and_then(|c| c.watermark))
+            .await?
+        else {
+            return Ok(None);
+        };
+        let cursor = cursor.map(|c| c.key);
         let limit = first.or(last).unwrap_or(DEFAULT_PAGE_SIZE) as i64;
 
         let result: Option<Vec<StoredCheckpoint>> = self
@@ -398,6 +414,7 @@ impl PgManager {
                         descending_order,
                         limit,
                         epoch.map(|e| e as i64),
+                        watermark,
                     ))
                 },
                 |query| move |conn| query.load(conn).optional(),
@@ -411,11 +428,30 @@ impl PgManager {
                     stored_checkpoints.pop();
                 }
 
-                Ok((stored_checkpoints, has_next_page))
+                Ok((stored_checkpoints, has_next_page, watermark))
             })
             .transpose()
     }
 
+    /// Resolve the checkpoint watermark that a paginated query is pinned to: the one carried by
+    /// its cursor, or the latest committed checkpoint when fetching the first page. Returns `None`
+    /// if no checkpoint has been indexed yet.
+    async fn get_watermark(&self, watermark: Option<i64>) -> Result<Option<i64>, Error> {
+        if watermark.is_some() {
+            return Ok(watermark);
+        }
+
+        self.run_query_async(|conn| {
+            checkpoints::dsl::checkpoints
+                .select(diesel::dsl::max(checkpoints::dsl::sequence_number))
+                .first::<Option<i64>>(conn)
+        })
+        .await
+    }
+
+    /// Objects are paginated by keyset only, not pinned to a watermark like transactions and
+    /// checkpoints: the objects table holds only the latest version of each object, so bounding
+    /// it by a watermark would drop objects modified after the first page from later pages.
     async fn multi_get_objs(
         &self,
         first: Option<u64>,
@@ -647,12 +683,12 @@ impl PgManager {
             .multi_get_txs(first, after, last, before, filter)
             .await?;
 
-        if let Some((stored_txs, has_next_page)) = transactions {
+        if let Some((stored_txs, has_next_page, watermark)) = transactions {
             let mut connection = Connection::new(false, has_next_page);
             connection
                 .edges
                 .extend(stored_txs.into_iter().filter_map(|stored_tx| {
-                    let cursor = stored_tx.tx_sequence_number.to_string();
+                    let cursor = encode_cursor(watermark, stored_tx.tx_sequence_number);
                     TransactionBlock::try_from(stored_tx)
                         .map_err(|e| eprintln!("Error converting transaction: {:?}", e))
                         .ok()
@@ -802,7 +838,7 @@ impl PgManager {
             .multi_get_checkpoints(first, after, last, before, epoch)
             .await?;
 
-        if let Some((stored_checkpoints, has_next_page)) = checkpoints {
+        if let Some((stored_checkpoints, has_next_page, watermark)) = checkpoints {
             let mut connection = Connection::new(false, has_next_page);
             connection
                 .edges
@@ -810,7 +846,8 @@ impl PgManager {
                     stored_checkpoints
                         .into_iter()
                         .filter_map(|stored_checkpoint| {
-                            let cursor = stored_checkpoint.sequence_number.to_string();
+                            let cursor =
+                                encode_cursor(watermark, stored_checkpoint.sequence_number);
                             Checkpoint::try_from(stored_checkpoint)
                                 .map_err(|e| eprintln!("Error converting checkpoint: {:?}", e))
                                 .ok()
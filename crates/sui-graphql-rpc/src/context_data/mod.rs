@@ -7,5 +7,6 @@ pub mod db_query_cost;
 pub(crate) mod package_cache;
 #[cfg(feature = "pg_backend")]
 pub(crate) mod pg_backend;
+pub(crate) mod snapshot_cursor;
 
 pub const DEFAULT_PAGE_SIZE: u64 = 10;
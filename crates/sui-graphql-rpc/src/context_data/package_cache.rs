@@ -7,6 +7,7 @@ use async_trait::async_trait;
 use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
 use move_core_types::account_address::AccountAddress;
 use sui_indexer::errors::IndexerError;
+use sui_indexer::models_v2::bcs_codec::BcsCodec;
 use sui_indexer::{indexer_reader::IndexerReader, schema_v2::objects};
 use sui_package_resolver::{
     error::Error as PackageResolverError, Package, PackageStore, PackageStoreWithLruCache, Result,
@@ -71,17 +72,20 @@ async fn get_package_version_from_db(
 
 async fn get_package_from_db(id: AccountAddress, sui_indexer: &IndexerReader) -> Result<Package> {
     let query = objects::dsl::objects
-        .select(objects::dsl::serialized_object)
+        .select((objects::dsl::serialized_object, objects::dsl::bcs_codec))
         .filter(objects::dsl::object_id.eq(id.to_vec()));
 
-    let Some(bcs) = sui_indexer
-        .run_query_async(move |conn| query.get_result::<Vec<u8>>(conn).optional())
+    let Some((bcs, codec)) = sui_indexer
+        .run_query_async(move |conn| query.get_result::<(Vec<u8>, i16)>(conn).optional())
         .await
         .map_err(Error::Indexer)?
     else {
         return Err(PackageResolverError::PackageNotFound(id));
     };
 
+    let bcs = BcsCodec::try_from(codec)
+        .and_then(|codec| codec.decompress(bcs))
+        .map_err(Error::Indexer)?;
     let object = bcs::from_bytes::<Object>(&bcs)?;
     Package::read(&object)
 }
@@ -78,8 +78,11 @@ impl GenericQueryBuilder<Pg> for PgQueryBuilder {
         filter: Option<TransactionBlockFilter>,
         after_tx_seq_num: Option<i64>,
         before_tx_seq_num: Option<i64>,
+        watermark: i64,
     ) -> Result<transactions::BoxedQuery<'static, Pg>, Error> {
-        let mut query = transactions::dsl::transactions.into_boxed();
+        let mut query = transactions::dsl::transactions
+            .filter(transactions::dsl::checkpoint_sequence_number.le(watermark))
+            .into_boxed();
 
         if let Some(cursor_val) = cursor {
             if descending_order {
@@ -331,8 +334,11 @@ impl GenericQueryBuilder<Pg> for PgQueryBuilder {
         descending_order: bool,
         limit: i64,
         epoch: Option<i64>,
+        watermark: i64,
     ) -> checkpoints::BoxedQuery<'static, Pg> {
-        let mut query = checkpoints::dsl::checkpoints.into_boxed();
+        let mut query = checkpoints::dsl::checkpoints
+            .filter(checkpoints::dsl::sequence_number.le(watermark))
+            .into_boxed();
 
         if let Some(cursor) = cursor {
             if descending_order {
@@ -0,0 +1,15 @@
+-- Maps checkpoint timestamps to checkpoint sequence numbers, so that time range
+-- queries can be resolved to checkpoint ranges with a single index lookup.
+CREATE TABLE checkpoint_timestamps
+(
+    sequence_number                     BIGINT  PRIMARY KEY,
+    epoch                               BIGINT  NOT NULL,
+    timestamp_ms                        BIGINT  NOT NULL,
+    -- total transactions in the network at the end of this checkpoint (including itself)
+    network_total_transactions          BIGINT  NOT NULL
+);
+-- multiple checkpoints can share the same timestamp
+CREATE INDEX checkpoint_timestamps_timestamp_ms ON checkpoint_timestamps (timestamp_ms, sequence_number);
+
+INSERT INTO checkpoint_timestamps (sequence_number, epoch, timestamp_ms, network_total_transactions)
+SELECT sequence_number, epoch, timestamp_ms, network_total_transactions FROM checkpoints;
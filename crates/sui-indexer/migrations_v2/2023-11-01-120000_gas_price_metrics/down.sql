@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS gas_price_metrics;
+ALTER TABLE transactions DROP COLUMN IF EXISTS gas_price;
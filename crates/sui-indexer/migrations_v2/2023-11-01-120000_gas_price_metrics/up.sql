@@ -0,0 +1,24 @@
+-- gas price the sender set in the transaction, NULL for transactions indexed before
+-- this column was added.
+ALTER TABLE transactions ADD COLUMN gas_price BIGINT;
+
+-- Reference gas price and gas prices paid by user transactions, summarized per epoch
+-- and per hour. Hours spanning an epoch change are split at the change, so that each
+-- row has a single reference gas price.
+CREATE TABLE gas_price_metrics
+(
+    -- 0 for epochs, 1 for hours. See models_v2/gas_price_metrics.rs
+    granularity                 SMALLINT     NOT NULL,
+    -- start of the epoch or hour, or of the epoch if it started during the hour
+    start_timestamp_ms          BIGINT       NOT NULL,
+    epoch                       BIGINT       NOT NULL,
+    reference_gas_price         BIGINT       NOT NULL,
+    total_transactions          BIGINT       NOT NULL,
+    -- percentiles are NULL if there were no transactions
+    p25                         BIGINT,
+    p50                         BIGINT,
+    p75                         BIGINT,
+    p90                         BIGINT,
+    p99                         BIGINT,
+    PRIMARY KEY (granularity, start_timestamp_ms)
+);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS epoch_fee_metrics;
+ALTER TABLE transactions DROP COLUMN IF EXISTS computation_cost;
+ALTER TABLE transactions DROP COLUMN IF EXISTS storage_cost;
+ALTER TABLE transactions DROP COLUMN IF EXISTS storage_rebate;
+ALTER TABLE transactions DROP COLUMN IF EXISTS non_refundable_storage_fee;
@@ -0,0 +1,18 @@
+-- gas cost summary from the transaction effects, NULL for transactions indexed before
+-- these columns were added.
+ALTER TABLE transactions ADD COLUMN computation_cost BIGINT;
+ALTER TABLE transactions ADD COLUMN storage_cost BIGINT;
+ALTER TABLE transactions ADD COLUMN storage_rebate BIGINT;
+ALTER TABLE transactions ADD COLUMN non_refundable_storage_fee BIGINT;
+
+-- Fees of the transactions of an epoch, summed once the epoch is over. Transactions
+-- indexed without a fee breakdown are not counted.
+CREATE TABLE epoch_fee_metrics
+(
+    epoch                       BIGINT       PRIMARY KEY,
+    total_transactions          BIGINT       NOT NULL,
+    computation_cost            BIGINT       NOT NULL,
+    storage_cost                BIGINT       NOT NULL,
+    storage_rebate              BIGINT       NOT NULL,
+    non_refundable_storage_fee  BIGINT       NOT NULL
+);
@@ -0,0 +1,25 @@
+-- Addresses registered for a backfill of their history from the checkpoint archive,
+-- covering the checkpoints from genesis until the earliest one in this DB.
+CREATE TABLE watchlist_addresses
+(
+    address                     BYTEA        PRIMARY KEY,
+    -- earliest checkpoint in the DB when the address was registered, the backfill
+    -- covers the checkpoints before it
+    end_checkpoint              BIGINT       NOT NULL,
+    -- next checkpoint to scan, equal to end_checkpoint once the backfill is done
+    next_checkpoint             BIGINT       NOT NULL,
+    transactions_found          BIGINT       NOT NULL,
+    registered_at_ms            BIGINT       NOT NULL
+);
+
+-- Transactions sent or received by watchlist addresses, read from the archive.
+-- Archives hold no objects, so object and balance changes are not available.
+CREATE TABLE watchlist_transactions
+(
+    address                     BYTEA        NOT NULL,
+    checkpoint_sequence_number  BIGINT       NOT NULL,
+    transaction_digest          BYTEA        NOT NULL,
+    raw_transaction             BYTEA        NOT NULL,
+    raw_effects                 BYTEA        NOT NULL,
+    PRIMARY KEY (address, checkpoint_sequence_number, transaction_digest)
+);
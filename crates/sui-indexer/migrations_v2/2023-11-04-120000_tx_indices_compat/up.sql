@@ -0,0 +1,15 @@
+-- Per-transaction index rows in the form read before the tx_* tables, only written while the
+-- writer runs with `--schema-write-mode dual`. Drop it once no reader of the old form is left.
+CREATE TABLE IF NOT EXISTS tx_indices (
+    tx_sequence_number          BIGINT       PRIMARY KEY,
+    checkpoint_sequence_number  BIGINT       NOT NULL,
+    transaction_digest          BYTEA        NOT NULL,
+    input_objects               BYTEA[]      NOT NULL,
+    changed_objects             BYTEA[]      NOT NULL,
+    senders                     BYTEA[]      NOT NULL,
+    payers                      BYTEA[]      NOT NULL,
+    recipients                  BYTEA[]      NOT NULL,
+    packages                    BYTEA[]      NOT NULL,
+    package_modules             TEXT[]       NOT NULL,
+    package_module_functions    TEXT[]       NOT NULL
+);
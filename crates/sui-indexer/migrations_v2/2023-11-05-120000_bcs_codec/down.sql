@@ -0,0 +1,2 @@
+ALTER TABLE transactions DROP COLUMN IF EXISTS bcs_codec;
+ALTER TABLE objects DROP COLUMN IF EXISTS bcs_codec;
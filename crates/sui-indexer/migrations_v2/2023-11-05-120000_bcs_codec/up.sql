@@ -0,0 +1,4 @@
+-- Codec the BCS columns of a row are stored with: 0 for uncompressed, 1 for zstd. Covers
+-- raw_transaction and raw_effects of transactions, and serialized_object of objects.
+ALTER TABLE transactions ADD COLUMN bcs_codec SMALLINT NOT NULL DEFAULT 0;
+ALTER TABLE objects ADD COLUMN bcs_codec SMALLINT NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Checkpoint ranges of each table exported to the warehouse staging area, recorded
+-- once all of their files are written.
+CREATE TABLE warehouse_exports
+(
+    table_name                  TEXT         NOT NULL,
+    first_checkpoint            BIGINT       NOT NULL,
+    -- inclusive
+    last_checkpoint             BIGINT       NOT NULL,
+    file_path                   TEXT         NOT NULL,
+    row_count                   BIGINT       NOT NULL,
+    exported_at_ms              BIGINT       NOT NULL,
+    PRIMARY KEY (table_name, first_checkpoint)
+);
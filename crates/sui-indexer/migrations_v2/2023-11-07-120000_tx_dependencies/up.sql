@@ -0,0 +1,11 @@
+-- Dependency edges between transactions, from the dependencies listed in their effects: the
+-- transactions that last wrote the objects a transaction reads or writes.
+CREATE TABLE tx_dependencies (
+    tx_sequence_number          BIGINT       NOT NULL,
+    transaction_digest          BYTEA        NOT NULL,
+    -- digest of the transaction `transaction_digest` depends on.
+    dependency                  BYTEA        NOT NULL,
+    PRIMARY KEY(transaction_digest, dependency)
+);
+CREATE INDEX tx_dependencies_dependency_index ON tx_dependencies (dependency, transaction_digest);
+CREATE INDEX tx_dependencies_tx_sequence_number_index ON tx_dependencies (tx_sequence_number ASC);
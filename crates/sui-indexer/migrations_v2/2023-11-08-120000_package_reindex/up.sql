@@ -0,0 +1,19 @@
+-- Reindex jobs of the rows of a package over a range of indexed checkpoints, re-derived from
+-- the checkpoint files to repair the rows of an indexing bug without a global backfill.
+CREATE TABLE package_reindex_jobs
+(
+    id                          BIGSERIAL    PRIMARY KEY,
+    package                     BYTEA        NOT NULL,
+    start_checkpoint            BIGINT       NOT NULL,
+    -- checkpoint the reindex stops at, exclusive
+    end_checkpoint              BIGINT       NOT NULL,
+    -- next checkpoint to reindex, equal to end_checkpoint once the job is done
+    next_checkpoint             BIGINT       NOT NULL,
+    transactions_reindexed      BIGINT       NOT NULL,
+    objects_reindexed           BIGINT       NOT NULL,
+    requested_at_ms             BIGINT       NOT NULL
+);
+CREATE INDEX package_reindex_jobs_package ON package_reindex_jobs (package);
+-- rows of the reindexed transactions are replaced by their tx_sequence_number
+CREATE INDEX IF NOT EXISTS tx_input_objects_tx_sequence_number_index ON tx_input_objects (tx_sequence_number ASC);
+CREATE INDEX IF NOT EXISTS tx_changed_objects_tx_sequence_number_index ON tx_changed_objects (tx_sequence_number ASC);
@@ -0,0 +1,12 @@
+-- Audit records of the purges of the rows of an address from the tables and columns tagged with
+-- a retention class, see `retention.rs`.
+CREATE TABLE purge_reports
+(
+    id                          BIGSERIAL    PRIMARY KEY,
+    address                     BYTEA        NOT NULL,
+    dry_run                     BOOLEAN      NOT NULL,
+    requested_at_ms             BIGINT       NOT NULL,
+    -- JSON array of the rows matched in each tagged column, and what was done with them
+    columns                     TEXT         NOT NULL
+);
+CREATE INDEX purge_reports_address ON purge_reports (address);
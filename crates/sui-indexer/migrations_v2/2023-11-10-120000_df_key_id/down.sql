@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS objects_df_key_id;
+ALTER TABLE objects DROP COLUMN IF EXISTS df_key_id;
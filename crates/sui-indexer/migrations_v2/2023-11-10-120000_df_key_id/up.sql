@@ -0,0 +1,5 @@
+-- ID derived from the parent and the name of a dynamic field, the same for dynamic fields and
+-- dynamic object fields, see `dynamic_field_key_id` in types_v2.rs. Non-null for dynamic fields
+-- indexed since the column was added; older ones are found by their object_id.
+ALTER TABLE objects ADD COLUMN df_key_id bytea;
+CREATE INDEX objects_df_key_id ON objects (df_key_id) WHERE df_key_id IS NOT NULL;
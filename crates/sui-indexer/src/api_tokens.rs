@@ -0,0 +1,454 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Tokens scoping what the clients of a reader can see, so that app teams can be given access to
+//! the data of their own protocol without exposing the whole index.
+//!
+//! Clients send their token as `Authorization: Bearer <token>`. A token is either given full
+//! access, or restricted to packages and addresses, in which case its clients only see:
+//!
+//! - objects and coins owned by its addresses, objects of types defined by its packages, and the
+//!   packages themselves, and the dynamic fields of these objects;
+//! - events emitted by its packages, of types defined by them, or sent by its addresses;
+//! - transactions sent by or to its addresses, or calling its packages, and the dependencies
+//!   between them.
+//!
+//! Queries must be narrowed to the scope of the token by their filter, e.g. to the events of one
+//! of its packages: queries over the whole chain are rejected, rather than served with pages
+//! filtered down to nothing. Chain-wide data that isn't specific to any protocol, like
+//! checkpoints, epochs and network metrics, is visible to all tokens.
+//!
+//! Tokens are configured by the SHA-256 digest of their value, so that the config doesn't hold
+//! them. Once tokens are configured, scoped reads without a known token are rejected.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{HashFunction, Sha256};
+use serde::Deserialize;
+use sui_json_rpc_types::{EventFilter, SuiEvent, TransactionDependencyGraph, TransactionFilter};
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::digests::TransactionDigest;
+use sui_types::object::{Object, Owner};
+
+use crate::errors::IndexerError;
+use crate::models_v2::objects::StoredObject;
+
+/// Config of the tokens, read from the YAML file given by `--api-tokens-config`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiTokensConfig {
+    pub tokens: Vec<ApiTokenConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiTokenConfig {
+    /// Name of the client holding the token.
+    pub name: String,
+    /// Hex encoded SHA-256 digest of the token.
+    pub token_sha256: String,
+    /// Whether the token sees everything, in which case its packages and addresses are ignored.
+    #[serde(default)]
+    pub full_access: bool,
+    #[serde(default)]
+    pub packages: Vec<ObjectID>,
+    #[serde(default)]
+    pub addresses: Vec<SuiAddress>,
+}
+
+/// Packages and addresses a token is restricted to.
+#[derive(Debug, Default)]
+pub struct TokenScope {
+    pub name: String,
+    packages: HashSet<ObjectID>,
+    addresses: HashSet<SuiAddress>,
+}
+
+impl TokenScope {
+    pub fn packages(&self) -> impl Iterator<Item = &ObjectID> {
+        self.packages.iter()
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = &SuiAddress> {
+        self.addresses.iter()
+    }
+
+    fn covers_package(&self, package: impl Into<ObjectID>) -> bool {
+        self.packages.contains(&package.into())
+    }
+
+    fn covers_address(&self, address: &SuiAddress) -> bool {
+        self.addresses.contains(address)
+    }
+
+    /// Whether all the events matched by `filter` are visible. Events of a transaction are
+    /// filtered after they are read instead.
+    fn covers_event_filter(&self, filter: &EventFilter) -> bool {
+        match filter {
+            EventFilter::Sender(sender) => self.covers_address(sender),
+            EventFilter::Transaction(_) => true,
+            EventFilter::Package(package)
+            | EventFilter::MoveModule { package, .. }
+            | EventFilter::MoveEventModule { package, .. } => self.covers_package(*package),
+            EventFilter::MoveEventType(event_type) => self.covers_package(event_type.address),
+            EventFilter::MoveEventField { .. } | EventFilter::TimeRange { .. } => false,
+            EventFilter::All(filters) => filters.iter().any(|f| self.covers_event_filter(f)),
+            EventFilter::Any(filters) => filters.iter().all(|f| self.covers_event_filter(f)),
+            EventFilter::And(f1, f2) => {
+                self.covers_event_filter(f1) || self.covers_event_filter(f2)
+            }
+            EventFilter::Or(f1, f2) => self.covers_event_filter(f1) && self.covers_event_filter(f2),
+        }
+    }
+
+    fn covers_transaction_filter(&self, filter: &TransactionFilter) -> bool {
+        match filter {
+            TransactionFilter::MoveFunction { package, .. } => self.covers_package(*package),
+            TransactionFilter::FromAddress(address)
+            | TransactionFilter::ToAddress(address)
+            | TransactionFilter::FromOrToAddress { addr: address } => self.covers_address(address),
+            TransactionFilter::FromAndToAddress { from, to } => {
+                self.covers_address(from) || self.covers_address(to)
+            }
+            TransactionFilter::Checkpoint(_)
+            | TransactionFilter::InputObject(_)
+            | TransactionFilter::ChangedObject(_)
+            | TransactionFilter::TransactionKind(_)
+            | TransactionFilter::TransactionKindIn(_) => false,
+        }
+    }
+
+    fn event_visible(&self, event: &SuiEvent) -> bool {
+        self.covers_package(event.package_id)
+            || self.covers_package(event.type_.address)
+            || self.covers_address(&event.sender)
+    }
+
+    fn object_visible(&self, object: &Object) -> bool {
+        if object.is_package() {
+            return self.covers_package(object.id());
+        }
+        matches!(object.owner, Owner::AddressOwner(owner) if self.covers_address(&owner))
+            || object
+                .type_()
+                .map_or(false, |type_| self.covers_package(type_.address()))
+    }
+}
+
+/// What the client of the current request can see.
+#[derive(Clone, Debug)]
+pub enum Visibility {
+    All,
+    Scoped(Arc<TokenScope>),
+}
+
+impl Visibility {
+    pub fn check_address(&self, address: &SuiAddress) -> Result<(), IndexerError> {
+        match self {
+            Visibility::Scoped(scope) if !scope.covers_address(address) => {
+                Err(out_of_scope(scope, format_args!("address {address}")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_event_filter(&self, filter: &EventFilter) -> Result<(), IndexerError> {
+        match self {
+            Visibility::Scoped(scope) if !scope.covers_event_filter(filter) => Err(out_of_scope(
+                scope,
+                "events matching the filter, narrow it to its packages or addresses",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_transaction_filter(
+        &self,
+        filter: Option<&TransactionFilter>,
+    ) -> Result<(), IndexerError> {
+        match self {
+            Visibility::Scoped(scope)
+                if !filter.map_or(false, |f| scope.covers_transaction_filter(f)) =>
+            {
+                Err(out_of_scope(
+                    scope,
+                    "transactions matching the filter, narrow it to its packages or addresses",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn event_visible(&self, event: &SuiEvent) -> bool {
+        match self {
+            Visibility::All => true,
+            Visibility::Scoped(scope) => scope.event_visible(event),
+        }
+    }
+
+    pub fn object_visible(&self, object: &Object) -> bool {
+        match self {
+            Visibility::All => true,
+            Visibility::Scoped(scope) => scope.object_visible(object),
+        }
+    }
+
+    /// Whether the client can see the dynamic fields of `parent`, which are visible along with it.
+    pub fn dynamic_fields_visible(&self, parent: Option<&Object>) -> bool {
+        parent.map_or(false, |parent| self.object_visible(parent))
+    }
+
+    /// The objects of `objects` the client can see.
+    pub fn retain_visible_objects(
+        &self,
+        objects: Vec<StoredObject>,
+    ) -> Result<Vec<StoredObject>, IndexerError> {
+        let Visibility::Scoped(scope) = self else {
+            return Ok(objects);
+        };
+        let mut visible = Vec::with_capacity(objects.len());
+        for stored_object in objects {
+            if scope.object_visible(&Object::try_from(stored_object.clone())?) {
+                visible.push(stored_object);
+            }
+        }
+        Ok(visible)
+    }
+}
+
+/// Drop the edges of `graph` to or from transactions outside of `visible`, the transactions of
+/// the graph the client can see. All edges are dropped if the root isn't visible.
+pub fn retain_visible_dependencies(
+    graph: &mut TransactionDependencyGraph,
+    visible: &HashSet<TransactionDigest>,
+) {
+    if !visible.contains(&graph.root) {
+        graph.edges.clear();
+        return;
+    }
+    graph
+        .edges
+        .retain(|edge| visible.contains(&edge.transaction) && visible.contains(&edge.dependency));
+}
+
+fn out_of_scope(scope: &TokenScope, what: impl std::fmt::Display) -> IndexerError {
+    IndexerError::Unauthorized(format!(
+        "API token of {} is not allowed to read {what}",
+        scope.name
+    ))
+}
+
+/// Configured tokens, by the digest of their value.
+#[derive(Debug)]
+pub struct ApiTokens {
+    tokens: HashMap<Vec<u8>, Visibility>,
+}
+
+impl ApiTokens {
+    pub fn new(config: ApiTokensConfig) -> Result<Self, IndexerError> {
+        let mut tokens = HashMap::new();
+        for token in config.tokens {
+            let digest = Hex::decode(&token.token_sha256).map_err(|e| {
+                IndexerError::InvalidArgumentError(format!(
+                    "Invalid token-sha256 of API token {}: {e}",
+                    token.name
+                ))
+            })?;
+            let visibility = if token.full_access {
+                Visibility::All
+            } else {
+                Visibility::Scoped(Arc::new(TokenScope {
+                    name: token.name,
+                    packages: token.packages.into_iter().collect(),
+                    addresses: token.addresses.into_iter().collect(),
+                }))
+            };
+            tokens.insert(digest, visibility);
+        }
+        Ok(Self { tokens })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, IndexerError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            IndexerError::InvalidArgumentError(format!(
+                "Failed to read API tokens config {}: {e}",
+                path.display()
+            ))
+        })?;
+        let config = serde_yaml::from_str(&contents).map_err(|e| {
+            IndexerError::InvalidArgumentError(format!(
+                "Failed to parse API tokens config {}: {e}",
+                path.display()
+            ))
+        })?;
+        Self::new(config)
+    }
+
+    /// What the holder of `token` can see.
+    pub fn visibility(&self, token: Option<&str>) -> Result<Visibility, IndexerError> {
+        let token = token.ok_or_else(|| {
+            IndexerError::Unauthorized("An API token is required to read this data".to_string())
+        })?;
+        self.tokens
+            .get(Sha256::digest(token.as_bytes()).digest.as_slice())
+            .cloned()
+            .ok_or_else(|| IndexerError::Unauthorized("Unknown API token".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types_v2::IndexedObject;
+    use move_core_types::identifier::Identifier;
+    use sui_json_rpc_types::TransactionDependencyEdge;
+
+    fn api_tokens() -> ApiTokens {
+        let token_sha256 = |token: &str| Hex::encode(Sha256::digest(token.as_bytes()).digest);
+        ApiTokens::new(ApiTokensConfig {
+            tokens: vec![
+                ApiTokenConfig {
+                    name: "dex".to_string(),
+                    token_sha256: token_sha256("dex-token"),
+                    full_access: false,
+                    packages: vec![ObjectID::from_single_byte(0xde)],
+                    addresses: vec![SuiAddress::from(ObjectID::from_single_byte(0xaa))],
+                },
+                ApiTokenConfig {
+                    name: "ops".to_string(),
+                    token_sha256: token_sha256("ops-token"),
+                    full_access: true,
+                    packages: vec![],
+                    addresses: vec![],
+                },
+            ],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_token_visibility() {
+        let tokens = api_tokens();
+        assert!(matches!(
+            tokens.visibility(None),
+            Err(IndexerError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            tokens.visibility(Some("unknown")),
+            Err(IndexerError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            tokens.visibility(Some("ops-token")),
+            Ok(Visibility::All)
+        ));
+
+        let dex = tokens.visibility(Some("dex-token")).unwrap();
+        let package = ObjectID::from_single_byte(0xde);
+        let other = ObjectID::from_single_byte(0x2);
+        let address = SuiAddress::from(ObjectID::from_single_byte(0xaa));
+        assert!(dex.check_address(&address).is_ok());
+        assert!(dex.check_address(&SuiAddress::ZERO).is_err());
+
+        assert!(dex
+            .check_event_filter(&EventFilter::Package(package))
+            .is_ok());
+        assert!(dex
+            .check_event_filter(&EventFilter::Package(other))
+            .is_err());
+        let module = EventFilter::MoveModule {
+            package: other,
+            module: Identifier::new("pool").unwrap(),
+        };
+        assert!(dex
+            .check_event_filter(&EventFilter::And(
+                Box::new(EventFilter::Sender(address)),
+                Box::new(module.clone()),
+            ))
+            .is_ok());
+        assert!(dex
+            .check_event_filter(&EventFilter::Or(
+                Box::new(EventFilter::Sender(address)),
+                Box::new(module),
+            ))
+            .is_err());
+
+        assert!(dex
+            .check_transaction_filter(Some(&TransactionFilter::FromOrToAddress { addr: address }))
+            .is_ok());
+        assert!(dex
+            .check_transaction_filter(Some(&TransactionFilter::Checkpoint(1)))
+            .is_err());
+        assert!(dex.check_transaction_filter(None).is_err());
+        assert!(Visibility::All.check_transaction_filter(None).is_ok());
+    }
+
+    #[test]
+    fn test_visible_objects() {
+        let dex = api_tokens().visibility(Some("dex-token")).unwrap();
+        let address = SuiAddress::from(ObjectID::from_single_byte(0xaa));
+        let owned = Object::with_id_owner_for_testing(ObjectID::from_single_byte(1), address);
+        let other =
+            Object::with_id_owner_for_testing(ObjectID::from_single_byte(2), SuiAddress::ZERO);
+        let stored = |objects: &[&Object]| {
+            objects
+                .iter()
+                .map(|o| StoredObject::from(IndexedObject::from_object(1, (*o).clone(), None)))
+                .collect::<Vec<_>>()
+        };
+
+        let visible = dex
+            .retain_visible_objects(stored(&[&owned, &other]))
+            .unwrap();
+        assert_eq!(
+            visible.into_iter().map(|o| o.object_id).collect::<Vec<_>>(),
+            vec![owned.id().to_vec()]
+        );
+        let visible = Visibility::All
+            .retain_visible_objects(stored(&[&owned, &other]))
+            .unwrap();
+        assert_eq!(visible.len(), 2);
+    }
+
+    #[test]
+    fn test_dynamic_fields_visible() {
+        let dex = api_tokens().visibility(Some("dex-token")).unwrap();
+        let address = SuiAddress::from(ObjectID::from_single_byte(0xaa));
+        let parent = Object::with_id_owner_for_testing(ObjectID::from_single_byte(1), address);
+        let other =
+            Object::with_id_owner_for_testing(ObjectID::from_single_byte(2), SuiAddress::ZERO);
+
+        assert!(dex.dynamic_fields_visible(Some(&parent)));
+        assert!(!dex.dynamic_fields_visible(Some(&other)));
+        assert!(!dex.dynamic_fields_visible(None));
+        assert!(Visibility::All.dynamic_fields_visible(Some(&other)));
+    }
+
+    #[test]
+    fn test_visible_dependencies() {
+        let [root, visible, hidden] = [1, 2, 3].map(|b| TransactionDigest::new([b; 32]));
+        let edge = |transaction, dependency, depth| TransactionDependencyEdge {
+            transaction,
+            dependency,
+            depth,
+        };
+        let graph = TransactionDependencyGraph {
+            root,
+            edges: vec![
+                edge(root, visible, 1),
+                edge(root, hidden, 1),
+                edge(hidden, visible, 2),
+            ],
+            truncated: false,
+        };
+
+        let mut scoped = graph.clone();
+        retain_visible_dependencies(&mut scoped, &HashSet::from([root, visible]));
+        assert_eq!(scoped.edges, vec![edge(root, visible, 1)]);
+
+        let mut hidden_root = graph;
+        retain_visible_dependencies(&mut hidden_root, &HashSet::from([visible, hidden]));
+        assert!(hidden_root.edges.is_empty());
+    }
+}
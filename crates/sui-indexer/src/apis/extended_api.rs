@@ -10,11 +10,15 @@ use sui_json_rpc::api::{
 };
 use sui_json_rpc::SuiRpcModule;
 use sui_json_rpc_types::{
-    AddressMetrics, CheckpointedObjectID, EpochInfo, EpochMetricsPage, EpochPage, MoveCallMetrics,
-    NetworkMetrics, Page, QueryObjectsPage, SuiObjectDataFilter, SuiObjectResponse,
-    SuiObjectResponseQuery,
+    AddressMetrics, CheckpointedObjectID, EpochInfo, EpochMetricsPage, EpochPage,
+    GasPriceHistoryPage, GasPriceInterval, MoveCallMetrics, NetworkMetrics, PackageReindexJob,
+    Page, PurgeReport, QueryObjectsPage, SuiObjectDataFilter, SuiObjectResponse,
+    SuiObjectResponseQuery, TransactionDependencyDirection, TransactionDependencyGraph,
+    WatchlistBackfillProgress,
 };
 use sui_open_rpc::Module;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::digests::TransactionDigest;
 use sui_types::sui_serde::BigInt;
 
 use crate::errors::IndexerError;
@@ -119,6 +123,16 @@ impl<S: IndexerStore + Sync + Send + 'static> ExtendedApiServer for ExtendedApi<
         unimplemented!();
     }
 
+    async fn get_gas_price_history(
+        &self,
+        _interval: Option<GasPriceInterval>,
+        _cursor: Option<BigInt<u64>>,
+        _limit: Option<usize>,
+        _descending_order: Option<bool>,
+    ) -> RpcResult<GasPriceHistoryPage> {
+        unimplemented!();
+    }
+
     async fn get_current_epoch(&self) -> RpcResult<EpochInfo> {
         Ok(self.state.get_current_epoch().await?)
     }
@@ -179,6 +193,57 @@ impl<S: IndexerStore + Sync + Send + 'static> ExtendedApiServer for ExtendedApi<
         // rolling_total_successful_transaction_blocks.
         Ok((total_txes as u64).into())
     }
+
+    async fn register_watchlist_addresses(
+        &self,
+        _addresses: Vec<SuiAddress>,
+    ) -> RpcResult<Vec<WatchlistBackfillProgress>> {
+        unimplemented!();
+    }
+
+    async fn get_watchlist_backfill_progress(
+        &self,
+        _addresses: Vec<SuiAddress>,
+    ) -> RpcResult<Vec<WatchlistBackfillProgress>> {
+        unimplemented!();
+    }
+
+    async fn get_transaction_dependency_graph(
+        &self,
+        _digest: TransactionDigest,
+        _direction: Option<TransactionDependencyDirection>,
+        _depth: Option<u32>,
+    ) -> RpcResult<TransactionDependencyGraph> {
+        unimplemented!();
+    }
+
+    async fn request_package_reindex(
+        &self,
+        _package: ObjectID,
+        _start_checkpoint: BigInt<u64>,
+        _end_checkpoint: BigInt<u64>,
+    ) -> RpcResult<PackageReindexJob> {
+        unimplemented!();
+    }
+
+    async fn get_package_reindex_jobs(
+        &self,
+        _package: ObjectID,
+    ) -> RpcResult<Vec<PackageReindexJob>> {
+        unimplemented!();
+    }
+
+    async fn purge_address_data(
+        &self,
+        _address: SuiAddress,
+        _dry_run: Option<bool>,
+    ) -> RpcResult<PurgeReport> {
+        unimplemented!();
+    }
+
+    async fn get_purge_reports(&self, _address: SuiAddress) -> RpcResult<Vec<PurgeReport>> {
+        unimplemented!();
+    }
 }
 
 impl<S> SuiRpcModule for ExtendedApi<S>
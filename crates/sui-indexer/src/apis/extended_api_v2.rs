@@ -1,6 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::errors::IndexerError;
 use crate::indexer_reader::IndexerReader;
 use jsonrpsee::{core::RpcResult, RpcModule};
 use sui_json_rpc::{
@@ -9,15 +10,29 @@ use sui_json_rpc::{
 };
 use sui_json_rpc_types::{
     AddressMetrics, CheckpointedObjectID, EpochInfo, EpochMetrics, EpochMetricsPage, EpochPage,
-    MoveCallMetrics, NetworkMetrics, Page, QueryObjectsPage, SuiObjectResponseQuery,
+    GasPriceHistoryPage, GasPriceInterval, MoveCallMetrics, NetworkMetrics, PackageReindexJob,
+    Page, PurgeReport, QueryObjectsPage, SuiObjectResponseQuery, TransactionDependencyDirection,
+    TransactionDependencyGraph, WatchlistBackfillProgress,
 };
 use sui_open_rpc::Module;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::digests::TransactionDigest;
 use sui_types::sui_serde::BigInt;
 
 pub(crate) struct ExtendedApiV2 {
     inner: IndexerReader,
 }
 
+/// Maximum number of addresses registered or looked up in a single watchlist request.
+const MAX_WATCHLIST_ADDRESSES: usize = 1000;
+
+/// Depth of the dependency graphs of transactions when none is requested, and the maximum one.
+const DEFAULT_DEPENDENCY_GRAPH_DEPTH: u32 = 3;
+const MAX_DEPENDENCY_GRAPH_DEPTH: u32 = 10;
+/// Maximum number of edges of a dependency graph, past which it is truncated: the descendants
+/// of a transaction writing a busy shared object grow quickly.
+const MAX_DEPENDENCY_GRAPH_EDGES: usize = 10_000;
+
 impl ExtendedApiV2 {
     pub fn new(inner: IndexerReader) -> Self {
         Self { inner }
@@ -93,6 +108,36 @@ impl ExtendedApiServer for ExtendedApiV2 {
         })
     }
 
+    async fn get_gas_price_history(
+        &self,
+        interval: Option<GasPriceInterval>,
+        cursor: Option<BigInt<u64>>,
+        limit: Option<usize>,
+        descending_order: Option<bool>,
+    ) -> RpcResult<GasPriceHistoryPage> {
+        let limit = validate_limit(limit, QUERY_MAX_RESULT_LIMIT_CHECKPOINTS)?;
+        let mut history = self
+            .inner
+            .spawn_blocking(move |this| {
+                this.get_gas_price_history(
+                    interval.unwrap_or_default(),
+                    cursor.map(|x| *x),
+                    limit + 1,
+                    descending_order.unwrap_or(false),
+                )
+            })
+            .await?;
+
+        let has_next_page = history.len() > limit;
+        history.truncate(limit);
+        let next_cursor = history.last().map(|h| h.start_timestamp_ms);
+        Ok(Page {
+            data: history,
+            next_cursor: next_cursor.map(|t| t.into()),
+            has_next_page,
+        })
+    }
+
     async fn get_current_epoch(&self) -> RpcResult<EpochInfo> {
         let stored_epoch = self
             .inner
@@ -163,6 +208,120 @@ impl ExtendedApiServer for ExtendedApiV2 {
             .await?;
         Ok(latest_checkpoint.network_total_transactions.into())
     }
+
+    async fn register_watchlist_addresses(
+        &self,
+        addresses: Vec<SuiAddress>,
+    ) -> RpcResult<Vec<WatchlistBackfillProgress>> {
+        self.inner.check_admin()?;
+        validate_watchlist_addresses(&addresses)?;
+        let progress = self
+            .inner
+            .spawn_blocking(move |this| this.register_watchlist_addresses(addresses))
+            .await?;
+        Ok(progress)
+    }
+
+    async fn get_watchlist_backfill_progress(
+        &self,
+        addresses: Vec<SuiAddress>,
+    ) -> RpcResult<Vec<WatchlistBackfillProgress>> {
+        validate_watchlist_addresses(&addresses)?;
+        let progress = self
+            .inner
+            .spawn_blocking(move |this| this.get_watchlist_backfill_progress(addresses))
+            .await?;
+        Ok(progress)
+    }
+
+    async fn get_transaction_dependency_graph(
+        &self,
+        digest: TransactionDigest,
+        direction: Option<TransactionDependencyDirection>,
+        depth: Option<u32>,
+    ) -> RpcResult<TransactionDependencyGraph> {
+        let depth = depth.unwrap_or(DEFAULT_DEPENDENCY_GRAPH_DEPTH);
+        if depth == 0 || depth > MAX_DEPENDENCY_GRAPH_DEPTH {
+            return Err(IndexerError::InvalidArgumentError(format!(
+                "Depth {depth} must be between 1 and {MAX_DEPENDENCY_GRAPH_DEPTH}"
+            ))
+            .into());
+        }
+        let direction = direction.unwrap_or_default();
+        let graph = self
+            .inner
+            .get_transaction_dependency_graph_in_blocking_task(
+                digest,
+                direction,
+                depth,
+                MAX_DEPENDENCY_GRAPH_EDGES,
+            )
+            .await?;
+        Ok(graph)
+    }
+
+    async fn request_package_reindex(
+        &self,
+        package: ObjectID,
+        start_checkpoint: BigInt<u64>,
+        end_checkpoint: BigInt<u64>,
+    ) -> RpcResult<PackageReindexJob> {
+        self.inner.check_admin()?;
+        let job = self
+            .inner
+            .spawn_blocking(move |this| {
+                this.request_package_reindex(package, *start_checkpoint, *end_checkpoint)
+            })
+            .await?;
+        Ok(job)
+    }
+
+    async fn get_package_reindex_jobs(
+        &self,
+        package: ObjectID,
+    ) -> RpcResult<Vec<PackageReindexJob>> {
+        self.inner.check_admin()?;
+        let jobs = self
+            .inner
+            .spawn_blocking(move |this| this.get_package_reindex_jobs(package))
+            .await?;
+        Ok(jobs)
+    }
+
+    async fn purge_address_data(
+        &self,
+        address: SuiAddress,
+        dry_run: Option<bool>,
+    ) -> RpcResult<PurgeReport> {
+        self.inner.check_admin()?;
+        let report = self
+            .inner
+            .spawn_blocking(move |this| {
+                this.purge_address_data(address, dry_run.unwrap_or_default())
+            })
+            .await?;
+        Ok(report)
+    }
+
+    async fn get_purge_reports(&self, address: SuiAddress) -> RpcResult<Vec<PurgeReport>> {
+        self.inner.check_admin()?;
+        let reports = self
+            .inner
+            .spawn_blocking(move |this| this.get_purge_reports(address))
+            .await?;
+        Ok(reports)
+    }
+}
+
+fn validate_watchlist_addresses(addresses: &[SuiAddress]) -> Result<(), IndexerError> {
+    if addresses.len() > MAX_WATCHLIST_ADDRESSES {
+        return Err(IndexerError::InvalidArgumentError(format!(
+            "Number of addresses {} exceeds max {}",
+            addresses.len(),
+            MAX_WATCHLIST_ADDRESSES
+        )));
+    }
+    Ok(())
 }
 
 impl SuiRpcModule for ExtendedApiV2 {
@@ -21,7 +21,15 @@ use sui_types::base_types::{ObjectID, SuiAddress};
 use sui_types::digests::TransactionDigest;
 use sui_types::dynamic_field::{DynamicFieldName, Field};
 use sui_types::event::EventID;
-use sui_types::TypeTag;
+
+/// Smaller page size to serve a query over its cost budget with, if one is estimated to fit.
+fn downgraded_limit(e: &IndexerError) -> Option<usize> {
+    match e {
+        // One more row than the page is queried, to tell whether there is a next page.
+        IndexerError::QueryCostExceeded { max_limit, .. } if *max_limit >= 2 => Some(max_limit - 1),
+        _ => None,
+    }
+}
 
 pub(crate) struct IndexerApiV2 {
     inner: IndexerReader,
@@ -101,21 +109,29 @@ impl IndexerApiServer for IndexerApiV2 {
         limit: Option<usize>,
         descending_order: Option<bool>,
     ) -> RpcResult<TransactionBlocksPage> {
-        let limit = cap_page_limit(limit);
+        let mut limit = cap_page_limit(limit);
         if limit == 0 {
             return Ok(TransactionBlocksPage::empty());
         }
-        let mut results = self
-            .inner
-            .query_transaction_blocks_in_blocking_task(
-                query.filter,
-                query.options.unwrap_or_default(),
+        let query_page = |limit: usize| {
+            self.inner.query_transaction_blocks_in_blocking_task(
+                query.filter.clone(),
+                query.options.clone().unwrap_or_default(),
                 cursor,
                 limit + 1,
                 descending_order.unwrap_or(false),
             )
-            .await
-            .map_err(|e: IndexerError| anyhow::anyhow!(e))?;
+        };
+        let mut results = match query_page(limit).await {
+            Err(e) => match downgraded_limit(&e) {
+                Some(downgraded) => {
+                    limit = downgraded;
+                    query_page(limit).await?
+                }
+                None => return Err(e.into()),
+            },
+            results => results?,
+        };
 
         let has_next_page = results.len() > limit;
         results.truncate(limit);
@@ -135,15 +151,29 @@ impl IndexerApiServer for IndexerApiV2 {
         limit: Option<usize>,
         descending_order: Option<bool>,
     ) -> RpcResult<EventPage> {
-        let limit = cap_page_limit(limit);
+        let mut limit = cap_page_limit(limit);
         if limit == 0 {
             return Ok(EventPage::empty());
         }
         let descending_order = descending_order.unwrap_or(false);
-        let mut results = self
-            .inner
-            .query_events_in_blocking_task(query, cursor, limit + 1, descending_order)
-            .await?;
+        let query_page = |limit: usize| {
+            self.inner.query_events_in_blocking_task(
+                query.clone(),
+                cursor.clone(),
+                limit + 1,
+                descending_order,
+            )
+        };
+        let mut results = match query_page(limit).await {
+            Err(e) => match downgraded_limit(&e) {
+                Some(downgraded) => {
+                    limit = downgraded;
+                    query_page(limit).await?
+                }
+                None => return Err(e.into()),
+            },
+            results => results?,
+        };
 
         let has_next_page = results.len() > limit;
         results.truncate(limit);
@@ -187,38 +217,16 @@ impl IndexerApiServer for IndexerApiV2 {
     ) -> RpcResult<SuiObjectResponse> {
         let name_bcs_value = self.inner.bcs_name_from_dynamic_field_name(&name)?;
 
-        // Try as Dynamic Field
-        let id = sui_types::dynamic_field::derive_dynamic_field_id(
-            parent_object_id,
-            &name.type_,
-            &name_bcs_value,
-        )
-        .expect("deriving dynamic field id can't fail");
-
+        // Dynamic fields and dynamic object fields are both found by the IDs derived from the
+        // parent and the name, with a single query.
         let options = sui_json_rpc_types::SuiObjectDataOptions::full_content();
-        match self.inner.get_object_read_in_blocking_task(id).await? {
-            sui_types::object::ObjectRead::NotExists(_)
-            | sui_types::object::ObjectRead::Deleted(_) => {}
-            sui_types::object::ObjectRead::Exists(object_ref, o, layout) => {
-                return Ok(SuiObjectResponse::new_with_data(
-                    (object_ref, o, layout, options, None).try_into()?,
-                ));
-            }
-        }
-
-        // Try as Dynamic Field Object
-        let dynamic_object_field_struct =
-            sui_types::dynamic_field::DynamicFieldInfo::dynamic_object_field_wrapper(name.type_);
-        let dynamic_object_field_type = TypeTag::Struct(Box::new(dynamic_object_field_struct));
-        let dynamic_object_field_id = sui_types::dynamic_field::derive_dynamic_field_id(
-            parent_object_id,
-            &dynamic_object_field_type,
-            &name_bcs_value,
-        )
-        .expect("deriving dynamic field id can't fail");
         match self
             .inner
-            .get_object_read_in_blocking_task(dynamic_object_field_id)
+            .get_dynamic_field_object_read_in_blocking_task(
+                parent_object_id,
+                name.type_,
+                name_bcs_value,
+            )
             .await?
         {
             sui_types::object::ObjectRead::NotExists(_)
@@ -10,7 +10,7 @@ use jsonrpsee::RpcModule;
 use sui_json_rpc::api::{ReadApiClient, ReadApiServer};
 use sui_json_rpc::SuiRpcModule;
 use sui_json_rpc_types::{
-    Checkpoint, CheckpointId, CheckpointPage, ProtocolConfigResponse, SuiEvent,
+    Checkpoint, CheckpointId, CheckpointPage, EventFilter, ProtocolConfigResponse, SuiEvent,
     SuiGetPastObjectRequest, SuiObjectDataOptions, SuiObjectResponse, SuiPastObjectResponse,
     SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
 };
@@ -340,6 +340,34 @@ where
         events_guard.stop_and_record();
         events_resp
     }
+
+    async fn multi_get_events(
+        &self,
+        transaction_digests: Vec<TransactionDigest>,
+    ) -> RpcResult<Vec<Vec<SuiEvent>>> {
+        if !self
+            .migrated_methods
+            .contains(&"multi_get_events".to_string())
+        {
+            let multi_events_guard = self
+                .state
+                .indexer_metrics()
+                .multi_get_events_latency
+                .start_timer();
+            let multi_events_resp = self.fullnode.multi_get_events(transaction_digests).await;
+            multi_events_guard.stop_and_record();
+            return multi_events_resp;
+        }
+        let event_pages = join_all(transaction_digests.into_iter().map(|digest| {
+            self.state
+                .get_events(EventFilter::Transaction(digest), None, None, false)
+        }))
+        .await;
+        Ok(event_pages
+            .into_iter()
+            .map(|page| page.map(|page| page.data))
+            .collect::<Result<Vec<_>, _>>()?)
+    }
     async fn get_loaded_child_objects(
         &self,
         digest: TransactionDigest,
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use async_trait::async_trait;
+use itertools::Itertools;
 use jsonrpsee::core::RpcResult;
 use jsonrpsee::RpcModule;
 use move_core_types::annotated_value::MoveStructLayout;
@@ -37,11 +38,7 @@ impl ReadApiV2 {
     }
 
     async fn get_checkpoint(&self, id: CheckpointId) -> Result<Checkpoint, IndexerError> {
-        match self
-            .inner
-            .spawn_blocking(move |this| this.get_checkpoint(id))
-            .await
-        {
+        match self.inner.get_checkpoint_in_blocking_task(id).await {
             Ok(Some(epoch_info)) => Ok(epoch_info),
             Ok(None) => Err(IndexerError::InvalidArgumentError(format!(
                 "Checkpoint {id:?} not found"
@@ -285,6 +282,24 @@ impl ReadApiServer for ReadApiV2 {
             .map_err(Into::into)
     }
 
+    async fn multi_get_events(
+        &self,
+        transaction_digests: Vec<TransactionDigest>,
+    ) -> RpcResult<Vec<Vec<SuiEvent>>> {
+        if transaction_digests.len() > *QUERY_MAX_RESULT_LIMIT {
+            Err(SuiRpcInputError::SizeLimitExceeded(
+                QUERY_MAX_RESULT_LIMIT.to_string(),
+            ))?
+        }
+        if transaction_digests.iter().unique().count() < transaction_digests.len() {
+            Err(SuiRpcInputError::ContainsDuplicates)?
+        }
+        self.inner
+            .multi_get_transaction_events_in_blocking_task(transaction_digests)
+            .await
+            .map_err(Into::into)
+    }
+
     async fn get_loaded_child_objects(
         &self,
         _digest: TransactionDigest,
@@ -10,8 +10,8 @@ use jsonrpsee::RpcModule;
 use sui_json_rpc::api::{WriteApiClient, WriteApiServer};
 use sui_json_rpc::SuiRpcModule;
 use sui_json_rpc_types::{
-    DevInspectResults, DryRunTransactionBlockResponse, SuiTransactionBlockResponse,
-    SuiTransactionBlockResponseOptions,
+    DevInspectResults, DryRunTransactionBlockResponse, SharedObjectContentionEstimate,
+    SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
 };
 use sui_open_rpc::Module;
 use sui_types::base_types::SuiAddress;
@@ -72,6 +72,15 @@ impl WriteApiServer for WriteApi {
     ) -> RpcResult<DryRunTransactionBlockResponse> {
         self.fullnode.dry_run_transaction_block(tx_bytes).await
     }
+
+    async fn estimate_shared_object_contention(
+        &self,
+        tx_bytes: Base64,
+    ) -> RpcResult<SharedObjectContentionEstimate> {
+        self.fullnode
+            .estimate_shared_object_contention(tx_bytes)
+            .await
+    }
 }
 
 impl SuiRpcModule for WriteApi {
@@ -14,8 +14,8 @@ use jsonrpsee::RpcModule;
 use sui_json_rpc::api::WriteApiServer;
 use sui_json_rpc::SuiRpcModule;
 use sui_json_rpc_types::{
-    DevInspectResults, DryRunTransactionBlockResponse, SuiTransactionBlockResponse,
-    SuiTransactionBlockResponseOptions,
+    DevInspectResults, DryRunTransactionBlockResponse, SharedObjectContentionEstimate,
+    SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
 };
 use sui_open_rpc::Module;
 use sui_types::base_types::SuiAddress;
@@ -60,6 +60,13 @@ impl WriteApiServer for WriteApiV2 {
     ) -> RpcResult<DryRunTransactionBlockResponse> {
         unimplemented!()
     }
+
+    async fn estimate_shared_object_contention(
+        &self,
+        tx_bytes: Base64,
+    ) -> RpcResult<SharedObjectContentionEstimate> {
+        unimplemented!()
+    }
 }
 
 impl SuiRpcModule for WriteApiV2 {
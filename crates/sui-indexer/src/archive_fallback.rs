@@ -0,0 +1,303 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reads of checkpoints older than the lowest one in the DB, served from the checkpoint archive.
+//!
+//! Such checkpoints are fetched from the archive on demand, in slices of [`SLICE_CHECKPOINTS`]
+//! aligned checkpoints, and the checkpoint and transaction rows derived from them are cached in
+//! temporary tables of a dedicated connection. The temporary tables are named `checkpoints` and
+//! `transactions`, and the connection's `search_path` only holds `pg_temp`, so that cached rows
+//! are read with the same queries and models as indexed ones, and nothing else can be written.
+//!
+//! Archives hold no objects or events, so transactions served from the archive have no object
+//! changes, balance changes or events. Archives aren't indexed by transaction digest either, so
+//! archived transactions are only found through their checkpoint.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use diesel::pg::PgConnection;
+use diesel::r2d2::ConnectionManager;
+use diesel::{sql_query, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use prometheus::Registry;
+use tokio::sync::Mutex;
+use tracing::info;
+
+use sui_archival::reader::{ArchiveReader, ArchiveReaderMetrics};
+use sui_config::node::ArchiveReaderConfig;
+use sui_storage::object_store::ObjectStoreConfig;
+use sui_types::digests::TransactionDigest;
+use sui_types::effects::TransactionEffectsAPI;
+use sui_types::message_envelope::Message;
+use sui_types::storage::{ReadStore, SharedInMemoryStore};
+use sui_types::transaction::TransactionDataAPI;
+
+use crate::errors::IndexerError;
+use crate::models_v2::checkpoints::StoredCheckpoint;
+use crate::models_v2::transactions::StoredTransaction;
+use crate::schema_v2::{checkpoints, transactions};
+use crate::types_v2::{IndexedCheckpoint, IndexedTransaction, IndexerResult, TransactionKind};
+
+/// Checkpoints fetched from the archive at once.
+const SLICE_CHECKPOINTS: u64 = 100;
+/// Checkpoints cached before the cache is cleared.
+const MAX_CACHED_CHECKPOINTS: i64 = 10_000;
+/// Transactions inserted per statement, within the limit of bind parameters.
+const INSERT_CHUNK_SIZE: usize = 1000;
+const DOWNLOAD_CONCURRENCY: usize = 5;
+
+type CachePool = diesel::r2d2::Pool<ConnectionManager<PgConnection>>;
+
+pub struct ArchiveFallback {
+    archive_reader: ArchiveReader,
+    /// Pool of the single connection holding the cache
+    cache: CachePool,
+    /// Held while fetching a slice, so that concurrent misses of the same slice fetch it once
+    fetch_lock: Mutex<()>,
+}
+
+impl ArchiveFallback {
+    pub fn new(db_url: &str, archive_store_config: ObjectStoreConfig) -> anyhow::Result<Self> {
+        let archive_reader = ArchiveReader::new(
+            ArchiveReaderConfig {
+                remote_store_config: archive_store_config,
+                mirror_store_configs: vec![],
+                download_concurrency: NonZeroUsize::new(DOWNLOAD_CONCURRENCY).unwrap(),
+                use_for_pruning_watermark: false,
+            },
+            &ArchiveReaderMetrics::new(&Registry::default()),
+        )?;
+        let cache = diesel::r2d2::Pool::builder()
+            .max_size(1)
+            .connection_customizer(Box::new(CacheTables))
+            .build(ConnectionManager::<PgConnection>::new(db_url))?;
+        Ok(Self {
+            archive_reader,
+            cache,
+            fetch_lock: Mutex::new(()),
+        })
+    }
+
+    /// The checkpoint `sequence_number` from the archive, or `None` if it isn't archived yet.
+    pub async fn get_checkpoint(
+        &self,
+        sequence_number: u64,
+    ) -> IndexerResult<Option<StoredCheckpoint>> {
+        self.ensure_cached(sequence_number).await?;
+        self.run_query(move |conn| {
+            checkpoints::table
+                .filter(checkpoints::sequence_number.eq(sequence_number as i64))
+                .first::<StoredCheckpoint>(conn)
+                .optional()
+        })
+        .await
+    }
+
+    /// Up to `limit` transactions of the checkpoint `sequence_number` from the archive, after
+    /// the transaction `cursor` in the order of their sequence numbers.
+    pub async fn get_checkpoint_transactions(
+        &self,
+        sequence_number: u64,
+        cursor: Option<TransactionDigest>,
+        limit: usize,
+        is_descending: bool,
+    ) -> IndexerResult<Vec<StoredTransaction>> {
+        self.ensure_cached(sequence_number).await?;
+        self.run_query(move |conn| {
+            let cursor_tx_seq = cursor
+                .map(|cursor| {
+                    transactions::table
+                        .select(transactions::tx_sequence_number)
+                        .filter(transactions::transaction_digest.eq(cursor.into_inner().to_vec()))
+                        .first::<i64>(conn)
+                })
+                .transpose()?;
+            let mut query = transactions::table
+                .filter(transactions::checkpoint_sequence_number.eq(sequence_number as i64))
+                .into_boxed();
+            if let Some(cursor_tx_seq) = cursor_tx_seq {
+                if is_descending {
+                    query = query.filter(transactions::tx_sequence_number.lt(cursor_tx_seq));
+                } else {
+                    query = query.filter(transactions::tx_sequence_number.gt(cursor_tx_seq));
+                }
+            }
+            if is_descending {
+                query = query.order(transactions::tx_sequence_number.desc());
+            } else {
+                query = query.order(transactions::tx_sequence_number.asc());
+            }
+            query.limit(limit as i64).load::<StoredTransaction>(conn)
+        })
+        .await
+    }
+
+    /// Fetch the slice of checkpoints holding `sequence_number` from the archive, unless it is
+    /// cached already.
+    async fn ensure_cached(&self, sequence_number: u64) -> IndexerResult<()> {
+        if self.is_cached(sequence_number).await? {
+            return Ok(());
+        }
+        let _guard = self.fetch_lock.lock().await;
+        if self.is_cached(sequence_number).await? {
+            return Ok(());
+        }
+        let (stored_checkpoints, stored_transactions) = self.fetch_slice(sequence_number).await?;
+        if stored_checkpoints.is_empty() {
+            return Ok(());
+        }
+        self.run_query(move |conn| {
+            conn.transaction(|conn| {
+                let cached: i64 = checkpoints::table.count().get_result(conn)?;
+                if cached + stored_checkpoints.len() as i64 > MAX_CACHED_CHECKPOINTS {
+                    sql_query("TRUNCATE checkpoints, transactions").execute(conn)?;
+                }
+                diesel::insert_into(checkpoints::table)
+                    .values(&stored_checkpoints)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                for chunk in stored_transactions.chunks(INSERT_CHUNK_SIZE) {
+                    diesel::insert_into(transactions::table)
+                        .values(chunk)
+                        .on_conflict_do_nothing()
+                        .execute(conn)?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    async fn is_cached(&self, sequence_number: u64) -> IndexerResult<bool> {
+        self.run_query(move |conn| {
+            diesel::select(diesel::dsl::exists(
+                checkpoints::table.filter(checkpoints::sequence_number.eq(sequence_number as i64)),
+            ))
+            .get_result::<bool>(conn)
+        })
+        .await
+    }
+
+    /// Read the slice of checkpoints holding `sequence_number` from the archive, and derive their
+    /// rows. Nothing is read if the checkpoint isn't archived yet.
+    async fn fetch_slice(
+        &self,
+        sequence_number: u64,
+    ) -> anyhow::Result<(Vec<StoredCheckpoint>, Vec<StoredTransaction>)> {
+        self.archive_reader.sync_manifest_once().await?;
+        let latest_archived = self.archive_reader.latest_available_checkpoint().await?;
+        if sequence_number > latest_archived {
+            return Ok((vec![], vec![]));
+        }
+        let start = sequence_number - sequence_number % SLICE_CHECKPOINTS;
+        let end = (start + SLICE_CHECKPOINTS).min(latest_archived + 1);
+
+        let store = SharedInMemoryStore::default();
+        self.archive_reader
+            .read(
+                store.clone(),
+                start..end,
+                Arc::new(AtomicU64::new(0)),
+                Arc::new(AtomicU64::new(0)),
+                false,
+            )
+            .await?;
+
+        let mut stored_checkpoints = vec![];
+        let mut stored_transactions = vec![];
+        for checkpoint_seq in start..end {
+            let checkpoint = store
+                .get_checkpoint_by_sequence_number(checkpoint_seq)?
+                .ok_or_else(|| {
+                    anyhow!("Checkpoint {checkpoint_seq} is missing from the archive")
+                })?;
+            let contents = store
+                .get_full_checkpoint_contents_by_sequence_number(checkpoint_seq)?
+                .ok_or_else(|| {
+                    anyhow!("Contents of checkpoint {checkpoint_seq} are missing from the archive")
+                })?;
+            let first_tx_sequence_number =
+                checkpoint.network_total_transactions - contents.size() as u64;
+            let mut successful_tx_num = 0;
+            for (idx, data) in contents.iter().enumerate() {
+                let tx = data.transaction.data().transaction_data();
+                let indexed = IndexedTransaction {
+                    tx_sequence_number: first_tx_sequence_number + idx as u64,
+                    tx_digest: *data.transaction.digest(),
+                    sender_signed_data: data.transaction.data().clone(),
+                    effects: data.effects.clone(),
+                    checkpoint_sequence_number: checkpoint_seq,
+                    timestamp_ms: checkpoint.timestamp_ms,
+                    object_changes: vec![],
+                    balance_change: vec![],
+                    events: vec![],
+                    transaction_kind: if tx.is_system_tx() {
+                        TransactionKind::SystemTransaction
+                    } else {
+                        TransactionKind::ProgrammableTransaction
+                    },
+                    successful_tx_num: if data.effects.status().is_ok() {
+                        tx.kind().tx_count() as u64
+                    } else {
+                        0
+                    },
+                };
+                successful_tx_num += indexed.successful_tx_num;
+                stored_transactions.push(StoredTransaction::from(&indexed));
+            }
+            let indexed = IndexedCheckpoint::from_sui_checkpoint(
+                checkpoint.inner(),
+                &contents.checkpoint_contents(),
+                successful_tx_num as usize,
+            );
+            stored_checkpoints.push(StoredCheckpoint::from(&indexed));
+        }
+        info!(
+            "Fetched checkpoints {start} to {} from the archive, with {} transactions",
+            end - 1,
+            stored_transactions.len()
+        );
+        Ok((stored_checkpoints, stored_transactions))
+    }
+
+    async fn run_query<T, F>(&self, query: F) -> IndexerResult<T>
+    where
+        F: FnOnce(&mut PgConnection) -> Result<T, diesel::result::Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let cache = self.cache.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = cache.get().map_err(|e| {
+                IndexerError::PgPoolConnectionError(format!(
+                    "Failed to get connection to the archive cache with error: {:?}",
+                    e
+                ))
+            })?;
+            query(&mut connection).map_err(|e| IndexerError::PostgresReadError(e.to_string()))
+        })
+        .await
+        .expect("propagate any panics")
+    }
+}
+
+/// Creates the temporary tables of the cache, and hides every other table, on connections of
+/// the cache.
+#[derive(Debug, Clone, Copy)]
+struct CacheTables;
+
+impl diesel::r2d2::CustomizeConnection<PgConnection, diesel::r2d2::Error> for CacheTables {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), diesel::r2d2::Error> {
+        for statement in [
+            "CREATE TEMPORARY TABLE checkpoints (LIKE public.checkpoints INCLUDING ALL)",
+            "CREATE TEMPORARY TABLE transactions (LIKE public.transactions INCLUDING ALL)",
+            "SET search_path TO pg_temp",
+        ] {
+            sql_query(statement)
+                .execute(conn)
+                .map_err(diesel::r2d2::Error::QueryError)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Continuous reconciliation of the balances materialized by the indexer against the balances
+//! computed by its fullnode, for randomly sampled (owner, coin type) pairs.
+//!
+//! The fullnode only serves balances at its latest state, so the two sides cannot be read at the
+//! same checkpoint directly. Instead, a sample is only compared if the owner sent or received no
+//! transaction from the checkpoint the fullnode was at when queried until the checkpoint the
+//! indexer is at when queried: the balance is then the same at both checkpoints. Other samples
+//! are counted as inconclusive.
+
+use std::time::Duration;
+
+use diesel::dsl::{exists, max};
+use diesel::sql_types::{BigInt, Bytea, SmallInt, Text};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, QueryableByName, RunQueryDsl};
+use jsonrpsee::http_client::HttpClient;
+use prometheus::{register_int_counter_with_registry, IntCounter, Registry};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use sui_json_rpc::api::{CoinReadApiClient, ReadApiClient};
+use sui_types::base_types::SuiAddress;
+
+use crate::errors::IndexerError;
+use crate::indexer_reader::IndexerReader;
+use crate::schema_v2::{checkpoints, tx_recipients, tx_senders};
+use crate::types_v2::OwnerType;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+const SAMPLES_PER_ROUND: i64 = 10;
+/// Checkpoints the indexer must be past the fullnode's checkpoint at query time before a sample
+/// is compared, so that transactions the fullnode had executed but not yet checkpointed when
+/// queried are indexed, and seen as activity of the owner.
+const SETTLE_CHECKPOINTS: i64 = 10;
+const CATCH_UP_TIMEOUT: Duration = Duration::from_secs(120);
+const CATCH_UP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone)]
+pub struct BalanceWatchdogMetrics {
+    pub balance_watchdog_matches: IntCounter,
+    pub balance_watchdog_mismatches: IntCounter,
+    pub balance_watchdog_inconclusive: IntCounter,
+    pub balance_watchdog_errors: IntCounter,
+}
+
+impl BalanceWatchdogMetrics {
+    pub fn new(registry: &Registry) -> Self {
+        Self {
+            balance_watchdog_matches: register_int_counter_with_registry!(
+                "balance_watchdog_matches",
+                "Sampled balances on which the indexer and the fullnode agree",
+                registry,
+            )
+            .unwrap(),
+            balance_watchdog_mismatches: register_int_counter_with_registry!(
+                "balance_watchdog_mismatches",
+                "Sampled balances on which the indexer and the fullnode disagree",
+                registry,
+            )
+            .unwrap(),
+            balance_watchdog_inconclusive: register_int_counter_with_registry!(
+                "balance_watchdog_inconclusive",
+                "Sampled balances that changed between the fullnode and indexer reads",
+                registry,
+            )
+            .unwrap(),
+            balance_watchdog_errors: register_int_counter_with_registry!(
+                "balance_watchdog_errors",
+                "Sampled balances that could not be read from the indexer or the fullnode",
+                registry,
+            )
+            .unwrap(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CheckOutcome {
+    Match,
+    Mismatch,
+    Inconclusive,
+}
+
+#[derive(QueryableByName)]
+struct SampledCoinOwner {
+    #[diesel(sql_type = Bytea)]
+    owner_id: Vec<u8>,
+    #[diesel(sql_type = Text)]
+    coin_type: String,
+}
+
+#[derive(QueryableByName)]
+struct CoinBalance {
+    #[diesel(sql_type = BigInt)]
+    balance: i64,
+}
+
+struct IndexerBalance {
+    /// Latest checkpoint indexed when the balance was read.
+    checkpoint: i64,
+    balance: i64,
+    /// Whether the owner sent or received a transaction since the checkpoint the activity check
+    /// started from. `None` if that checkpoint is not indexed.
+    active: Option<bool>,
+}
+
+pub struct BalanceWatchdog {
+    reader: IndexerReader,
+    fullnode: HttpClient,
+    metrics: BalanceWatchdogMetrics,
+}
+
+impl BalanceWatchdog {
+    pub fn new(
+        reader: IndexerReader,
+        fullnode: HttpClient,
+        metrics: BalanceWatchdogMetrics,
+    ) -> Self {
+        Self {
+            reader,
+            fullnode,
+            metrics,
+        }
+    }
+
+    pub async fn run_forever(self) {
+        info!("Balance watchdog started");
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+            let samples = match self
+                .reader
+                .spawn_blocking(|this| sample_coin_owners(&this, SAMPLES_PER_ROUND))
+                .await
+            {
+                Ok(samples) => samples,
+                Err(e) => {
+                    warn!("Balance watchdog failed to sample coin owners: {e}");
+                    continue;
+                }
+            };
+            for (owner, coin_type) in samples {
+                match self.check(owner, coin_type.clone()).await {
+                    Ok(CheckOutcome::Match) => self.metrics.balance_watchdog_matches.inc(),
+                    Ok(CheckOutcome::Mismatch) => self.metrics.balance_watchdog_mismatches.inc(),
+                    Ok(CheckOutcome::Inconclusive) => {
+                        self.metrics.balance_watchdog_inconclusive.inc()
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Balance watchdog failed to check {coin_type} balance of {owner}: {e}"
+                        );
+                        self.metrics.balance_watchdog_errors.inc();
+                    }
+                }
+            }
+        }
+    }
+
+    async fn check(&self, owner: SuiAddress, coin_type: String) -> anyhow::Result<CheckOutcome> {
+        let since_checkpoint = *self
+            .fullnode
+            .get_latest_checkpoint_sequence_number()
+            .await? as i64;
+        let fullnode_balance = self
+            .fullnode
+            .get_balance(owner, Some(coin_type.clone()))
+            .await?
+            .total_balance;
+        let fullnode_checkpoint = *self
+            .fullnode
+            .get_latest_checkpoint_sequence_number()
+            .await? as i64;
+
+        let deadline = Instant::now() + CATCH_UP_TIMEOUT;
+        let indexer_balance = loop {
+            let coin_type = coin_type.clone();
+            let indexer_balance = self
+                .reader
+                .spawn_blocking(move |this| {
+                    get_indexer_balance(&this, owner, coin_type, since_checkpoint)
+                })
+                .await?;
+            if indexer_balance.checkpoint >= fullnode_checkpoint + SETTLE_CHECKPOINTS {
+                break indexer_balance;
+            }
+            if Instant::now() > deadline {
+                warn!(
+                    "Balance watchdog timed out waiting for the indexer to reach checkpoint {}",
+                    fullnode_checkpoint + SETTLE_CHECKPOINTS
+                );
+                return Ok(CheckOutcome::Inconclusive);
+            }
+            tokio::time::sleep(CATCH_UP_POLL_INTERVAL).await;
+        };
+
+        if indexer_balance.active != Some(false) {
+            return Ok(CheckOutcome::Inconclusive);
+        }
+        if indexer_balance.balance as u128 == fullnode_balance {
+            return Ok(CheckOutcome::Match);
+        }
+        error!(
+            "Balance mismatch for {coin_type} owned by {owner}: indexer has {} at checkpoint {}, \
+            fullnode has {fullnode_balance} at checkpoint {fullnode_checkpoint}",
+            indexer_balance.balance, indexer_balance.checkpoint
+        );
+        Ok(CheckOutcome::Mismatch)
+    }
+}
+
+/// Pick up to `limit` (owner, coin type) pairs of address-owned coins, from a random sample of
+/// the pages of the objects table.
+fn sample_coin_owners(
+    reader: &IndexerReader,
+    limit: i64,
+) -> Result<Vec<(SuiAddress, String)>, IndexerError> {
+    let samples = reader.run_query(|conn| {
+        diesel::sql_query(
+            "SELECT DISTINCT owner_id, coin_type FROM objects TABLESAMPLE SYSTEM (0.1) \
+            WHERE owner_type = $1 AND owner_id IS NOT NULL AND coin_type IS NOT NULL \
+            LIMIT $2",
+        )
+        .bind::<SmallInt, _>(OwnerType::Address as i16)
+        .bind::<BigInt, _>(limit)
+        .load::<SampledCoinOwner>(conn)
+    })?;
+    samples
+        .into_iter()
+        .map(|sample| {
+            let owner = SuiAddress::from_bytes(&sample.owner_id).map_err(|e| {
+                IndexerError::PersistentStorageDataCorruptionError(format!(
+                    "Failed to parse owner address {:?}: {e}",
+                    sample.owner_id
+                ))
+            })?;
+            Ok((owner, sample.coin_type))
+        })
+        .collect()
+}
+
+fn get_indexer_balance(
+    reader: &IndexerReader,
+    owner: SuiAddress,
+    coin_type: String,
+    since_checkpoint: i64,
+) -> Result<IndexerBalance, IndexerError> {
+    reader.run_query(|conn| {
+        let checkpoint = checkpoints::table
+            .select(max(checkpoints::sequence_number))
+            .first::<Option<i64>>(conn)?
+            .unwrap_or(-1);
+        let balance = diesel::sql_query(
+            "SELECT CAST(COALESCE(SUM(coin_balance), 0) AS BIGINT) AS balance FROM objects \
+            WHERE owner_type = $1 AND owner_id = $2 AND coin_type = $3",
+        )
+        .bind::<SmallInt, _>(OwnerType::Address as i16)
+        .bind::<Bytea, _>(owner.to_vec())
+        .bind::<Text, _>(coin_type)
+        .get_result::<CoinBalance>(conn)?
+        .balance;
+
+        // Transactions after `since_checkpoint` have sequence numbers from the network total of
+        // transactions at that checkpoint.
+        let Some(first_tx) = checkpoints::table
+            .filter(checkpoints::sequence_number.eq(since_checkpoint))
+            .select(checkpoints::network_total_transactions)
+            .first::<i64>(conn)
+            .optional()?
+        else {
+            return Ok::<_, diesel::result::Error>(IndexerBalance {
+                checkpoint,
+                balance,
+                active: None,
+            });
+        };
+        let sent = diesel::select(exists(
+            tx_senders::table
+                .filter(tx_senders::sender.eq(owner.to_vec()))
+                .filter(tx_senders::tx_sequence_number.ge(first_tx)),
+        ))
+        .get_result::<bool>(conn)?;
+        let received = diesel::select(exists(
+            tx_recipients::table
+                .filter(tx_recipients::recipient.eq(owner.to_vec()))
+                .filter(tx_recipients::tx_sequence_number.ge(first_tx)),
+        ))
+        .get_result::<bool>(conn)?;
+        Ok(IndexerBalance {
+            checkpoint,
+            balance,
+            active: Some(sent || received),
+        })
+    })
+}
@@ -0,0 +1,82 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use clap::Parser;
+use tracing::warn;
+
+use sui_indexer::doctor::{diagnose, DoctorOptions, Severity};
+use sui_indexer::new_rpc_client;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // NOTE: this is to print out tracing like info, warn & error.
+    let _guard = telemetry_subscribers::TelemetryConfig::new()
+        .with_env()
+        .init();
+    let config = DoctorConfig::parse();
+
+    let fullnode_checkpoint = match &config.rpc_client_url {
+        Some(url) => match new_rpc_client(url).await {
+            Ok(client) => client
+                .read_api()
+                .get_latest_checkpoint_sequence_number()
+                .await
+                .map_err(|e| warn!("Failed to get latest checkpoint from fullnode: {e}"))
+                .ok(),
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    let options = DoctorOptions {
+        use_v2: config.use_v2,
+        max_checkpoint_lag: config.max_checkpoint_lag,
+    };
+    let db_url = config.db_url.clone();
+    let findings =
+        tokio::task::spawn_blocking(move || diagnose(&db_url, &options, fullnode_checkpoint))
+            .await?;
+
+    if findings.is_empty() {
+        println!("No problems found.");
+        return Ok(());
+    }
+    for (i, finding) in findings.iter().enumerate() {
+        println!(
+            "{}. [{}] {}: {}",
+            i + 1,
+            finding.severity,
+            finding.check,
+            finding.problem
+        );
+        println!("   -> {}", finding.remediation);
+    }
+    if findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Critical)
+    {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[derive(Parser)]
+#[clap(
+    name = "Indexer Doctor",
+    about = "Check the health of an indexer database and suggest fixes",
+    rename_all = "kebab-case"
+)]
+pub struct DoctorConfig {
+    #[clap(long)]
+    pub db_url: String,
+    /// Fullnode the indexer reads from, to measure how far behind it the indexer is
+    #[clap(long)]
+    pub rpc_client_url: Option<String>,
+    #[clap(long)]
+    pub use_v2: bool,
+    /// Checkpoints a pipeline may trail the fullnode or the checkpoints table before it is
+    /// reported as lagging
+    #[clap(long, default_value_t = 100)]
+    pub max_checkpoint_lag: u64,
+}
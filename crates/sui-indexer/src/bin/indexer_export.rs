@@ -0,0 +1,80 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use object_store::path::Path;
+use tracing::info;
+
+use sui_indexer::indexer_reader::IndexerReader;
+use sui_indexer::sqlite_export::{export_to_sqlite, ExportFilter};
+use sui_storage::object_store::util::put;
+use sui_storage::object_store::ObjectStoreConfig;
+use sui_types::base_types::ObjectID;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // NOTE: this is to print out tracing like info, warn & error.
+    let _guard = telemetry_subscribers::TelemetryConfig::new()
+        .with_env()
+        .init();
+    let config = ExportConfig::parse();
+
+    let reader = IndexerReader::new(config.db_url.clone())?;
+    let filter = ExportFilter {
+        package: config.package,
+        start_timestamp_ms: config.start.timestamp_millis() as u64,
+        end_timestamp_ms: config.end.timestamp_millis() as u64,
+    };
+    let output = config.output.clone();
+    let summary = reader
+        .spawn_blocking(move |reader| export_to_sqlite(&reader, &filter, &output))
+        .await?;
+    println!(
+        "Exported {} transactions and {} events to {}",
+        summary.transactions,
+        summary.events,
+        config.output.display()
+    );
+
+    if let Some(remote_path) = &config.remote_path {
+        let store = config.object_store_config.make()?;
+        let bytes = tokio::fs::read(&config.output).await?;
+        put(&store, &Path::from(remote_path.as_str()), bytes.into()).await?;
+        info!("Uploaded {} to {remote_path}", config.output.display());
+        println!("Uploaded to {remote_path}");
+    }
+    Ok(())
+}
+
+#[derive(Parser)]
+#[clap(
+    name = "Indexer Export",
+    about = "Export indexed transactions and events into a SQLite file for offline analysis",
+    rename_all = "kebab-case"
+)]
+pub struct ExportConfig {
+    #[clap(long)]
+    pub db_url: String,
+    /// Only export transactions calling this package, and events it emitted
+    #[clap(long)]
+    pub package: Option<ObjectID>,
+    /// Start of the time range to export, inclusive, e.g. 2023-10-01T00:00:00Z
+    #[clap(long)]
+    pub start: DateTime<Utc>,
+    /// End of the time range to export, exclusive
+    #[clap(long)]
+    pub end: DateTime<Utc>,
+    /// Local path of the SQLite file to create
+    #[clap(long)]
+    pub output: PathBuf,
+    /// Path in the object store to upload the SQLite file to. The file is only
+    /// written locally if not set.
+    #[clap(long)]
+    pub remote_path: Option<String>,
+    #[clap(flatten)]
+    pub object_store_config: ObjectStoreConfig,
+}
@@ -0,0 +1,58 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+use sui_indexer::warehouse_export::WarehouseExportConfig;
+use sui_indexer::warehouse_restore::WarehouseRestorer;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // NOTE: this is to print out tracing like info, warn & error.
+    let _guard = telemetry_subscribers::TelemetryConfig::new()
+        .with_env()
+        .init();
+    let config = RestoreConfig::parse();
+
+    let export_config = std::fs::read_to_string(&config.warehouse_export_config).map_err(|e| {
+        anyhow!(
+            "Failed to read warehouse export config {}: {e}",
+            config.warehouse_export_config.display()
+        )
+    })?;
+    let export_config: WarehouseExportConfig =
+        serde_yaml::from_str(&export_config).map_err(|e| {
+            anyhow!(
+                "Failed to parse warehouse export config {}: {e}",
+                config.warehouse_export_config.display()
+            )
+        })?;
+    let restorer = WarehouseRestorer::new(config.db_url, &export_config, config.parallelism)?;
+    let summary = restorer.restore().await?;
+    println!(
+        "Restored {} ranges, {} transactions and {} events",
+        summary.ranges, summary.transactions, summary.events
+    );
+    Ok(())
+}
+
+#[derive(Parser)]
+#[clap(
+    name = "Indexer Restore",
+    about = "Restore the indexed transactions and events from the Parquet files of the warehouse export",
+    rename_all = "kebab-case"
+)]
+pub struct RestoreConfig {
+    /// Database to restore into, with its migrations already run
+    #[clap(long)]
+    pub db_url: String,
+    /// Config the files were exported with, the YAML file of `--warehouse-export-config`
+    #[clap(long)]
+    pub warehouse_export_config: PathBuf,
+    /// Number of ranges loaded at a time, each over its own connection
+    #[clap(long, default_value_t = 8)]
+    pub parallelism: usize,
+}
@@ -0,0 +1,99 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Time as seen by the time-based logic of the writer, e.g. the GC of the package cache and the
+//! deadlines of commit batches.
+//!
+//! Live, it is the wall clock. In backfill mode it is the timestamp of the last committed
+//! checkpoint, so that reprocessing history makes the same decisions however fast the machine
+//! gets through it. Rollups of the analytical worker are already computed from checkpoint
+//! timestamps, e.g. hourly gas prices, and don't depend on either.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::watch;
+
+#[derive(Clone)]
+pub enum IndexerClock {
+    Wall,
+    /// Timestamp in milliseconds of the last committed checkpoint, 0 until the first commit.
+    Checkpoint(Arc<watch::Sender<u64>>),
+}
+
+impl IndexerClock {
+    pub fn new(backfill_mode: bool) -> Self {
+        if backfill_mode {
+            IndexerClock::Checkpoint(Arc::new(watch::channel(0).0))
+        } else {
+            IndexerClock::Wall
+        }
+    }
+
+    pub fn is_checkpoint_time(&self) -> bool {
+        matches!(self, IndexerClock::Checkpoint(_))
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        match self {
+            IndexerClock::Wall => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards")
+                .as_millis() as u64,
+            IndexerClock::Checkpoint(timestamp_ms) => *timestamp_ms.borrow(),
+        }
+    }
+
+    /// Move checkpoint time forward to the timestamp of a committed checkpoint. Has no effect on
+    /// the wall clock.
+    pub fn advance(&self, checkpoint_timestamp_ms: u64) {
+        if let IndexerClock::Checkpoint(timestamp_ms) = self {
+            timestamp_ms.send_if_modified(|current| {
+                let advanced = checkpoint_timestamp_ms > *current;
+                if advanced {
+                    *current = checkpoint_timestamp_ms;
+                }
+                advanced
+            });
+        }
+    }
+
+    /// Wait until `duration` has passed on this clock. In checkpoint time, that is until a
+    /// checkpoint at least `duration` later than the current one is committed.
+    pub async fn sleep(&self, duration: Duration) {
+        match self {
+            IndexerClock::Wall => tokio::time::sleep(duration).await,
+            IndexerClock::Checkpoint(timestamp_ms) => {
+                let mut receiver = timestamp_ms.subscribe();
+                let deadline = *receiver.borrow() + duration.as_millis() as u64;
+                // The sender can't be dropped while the clock is borrowed
+                let _ = receiver.wait_for(|now| *now >= deadline).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::IndexerClock;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_checkpoint_clock_sleep() {
+        let clock = IndexerClock::new(true);
+        clock.advance(1_000);
+        let sleeping = tokio::spawn({
+            let clock = clock.clone();
+            async move { clock.sleep(Duration::from_secs(10)).await }
+        });
+
+        // Only checkpoints past the deadline end the sleep, however long it takes to reach them
+        clock.advance(10_999);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!sleeping.is_finished());
+        clock.advance(5_000);
+        assert_eq!(clock.now_ms(), 10_999);
+        clock.advance(11_000);
+        sleeping.await.unwrap();
+    }
+}
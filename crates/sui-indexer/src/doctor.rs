@@ -0,0 +1,478 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Health checks for an indexer deployment: whether its database is reachable and migrated,
+//! whether the tables written by each pipeline agree on how far they have indexed, how far the
+//! indexer trails its fullnode, and whether Postgres itself needs attention.
+
+use std::fmt::{Display, Formatter};
+
+use diesel::sql_types::{BigInt, Nullable, Text};
+use diesel::{Connection, PgConnection, QueryableByName, RunQueryDsl};
+
+use crate::utils::migration_drift;
+
+/// Tables with at least this many dead tuples, making up at least `BLOAT_RATIO` of their live
+/// tuples, are reported as bloated.
+const BLOAT_MIN_DEAD_TUPLES: i64 = 10_000;
+const BLOAT_RATIO: f64 = 0.2;
+/// Tables with at least this many rows that are mostly read with sequential scans are reported as
+/// likely missing an index.
+const SEQ_SCAN_MIN_ROWS: i64 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The indexer is not working, or is serving wrong data.
+    Critical,
+    /// The indexer works, but is degraded or will be soon.
+    Warning,
+    Info,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Critical => write!(f, "CRITICAL"),
+            Severity::Warning => write!(f, "WARNING"),
+            Severity::Info => write!(f, "INFO"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub severity: Severity,
+    pub check: &'static str,
+    pub problem: String,
+    pub remediation: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct DoctorOptions {
+    pub use_v2: bool,
+    /// How many checkpoints a pipeline may trail the fullnode, or the checkpoints table, before
+    /// it is reported as lagging.
+    pub max_checkpoint_lag: u64,
+}
+
+/// Run every check against the database at `db_url`, returning findings ordered from most to
+/// least severe. `fullnode_checkpoint` is the latest checkpoint of the fullnode the indexer reads
+/// from, if it could be reached.
+pub fn diagnose(
+    db_url: &str,
+    options: &DoctorOptions,
+    fullnode_checkpoint: Option<u64>,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut conn = match PgConnection::establish(db_url) {
+        Ok(conn) => conn,
+        Err(e) => {
+            findings.push(Finding {
+                severity: Severity::Critical,
+                check: "connectivity",
+                problem: format!("Cannot connect to the database: {e}"),
+                remediation: "Check that Postgres is running and reachable from this host, and \
+                    that the database url and credentials are correct."
+                    .to_string(),
+            });
+            return findings;
+        }
+    };
+
+    let checks: [(&'static str, CheckFn); 5] = [
+        ("schema", check_schema),
+        ("watermarks", check_watermarks),
+        ("lag", check_lag),
+        ("bloat", check_bloat),
+        ("indexes", check_indexes),
+    ];
+    for (check, f) in checks {
+        if let Err(e) = f(&mut conn, options, fullnode_checkpoint, &mut findings) {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                check,
+                problem: format!("Check failed to run: {e}"),
+                remediation: "Make sure the database user can read the indexer tables and the \
+                    pg_stat_user_tables and pg_index catalogs."
+                    .to_string(),
+            });
+        }
+    }
+
+    findings.sort_by_key(|finding| finding.severity);
+    findings
+}
+
+type CheckFn = fn(
+    &mut PgConnection,
+    &DoctorOptions,
+    Option<u64>,
+    &mut Vec<Finding>,
+) -> Result<(), anyhow::Error>;
+
+fn check_schema(
+    conn: &mut PgConnection,
+    options: &DoctorOptions,
+    _fullnode_checkpoint: Option<u64>,
+    findings: &mut Vec<Finding>,
+) -> Result<(), anyhow::Error> {
+    let (pending, unknown) = migration_drift(conn, options.use_v2)?;
+    if !pending.is_empty() {
+        findings.push(Finding {
+            severity: Severity::Critical,
+            check: "schema",
+            problem: format!(
+                "{} migration(s) have not been applied: {}",
+                pending.len(),
+                pending.join(", ")
+            ),
+            remediation: "Apply them with `diesel migration run`, or restart the indexer with \
+                `--reset-db` if the data can be re-indexed (this drops all tables)."
+                .to_string(),
+        });
+    }
+    if !unknown.is_empty() {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            check: "schema",
+            problem: format!(
+                "The database has migration(s) applied that this binary does not know about: {}",
+                unknown.join(", ")
+            ),
+            remediation: "The database was migrated by a newer indexer. Upgrade this binary, or \
+                check that `--use-v2` matches the schema the database was created with."
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// A table written by an indexer pipeline, and the column recording the checkpoint each row was
+/// written for.
+struct Watermark {
+    pipeline: &'static str,
+    table: &'static str,
+    column: &'static str,
+}
+
+/// Written by the checkpoint committer no later than the checkpoints they belong to. Every
+/// checkpoint has at least one transaction, and updates the clock object.
+const CHECKPOINT_PIPELINE_WATERMARKS: [Watermark; 3] = [
+    Watermark {
+        pipeline: "checkpoint committer",
+        table: "transactions",
+        column: "checkpoint_sequence_number",
+    },
+    Watermark {
+        pipeline: "checkpoint committer",
+        table: "objects",
+        column: "checkpoint_sequence_number",
+    },
+    Watermark {
+        pipeline: "checkpoint committer",
+        table: "checkpoint_timestamps",
+        column: "sequence_number",
+    },
+];
+
+/// Written by the analytical worker from rows already committed by the checkpoint committer.
+const ANALYTICAL_PIPELINE_WATERMARKS: [Watermark; 3] = [
+    Watermark {
+        pipeline: "analytical worker",
+        table: "tx_count_metrics",
+        column: "checkpoint_sequence_number",
+    },
+    Watermark {
+        pipeline: "analytical worker",
+        table: "address_metrics",
+        column: "checkpoint",
+    },
+    Watermark {
+        pipeline: "analytical worker",
+        table: "move_call_metrics",
+        column: "checkpoint_sequence_number",
+    },
+];
+
+#[derive(QueryableByName)]
+struct MaxValue {
+    #[diesel(sql_type = Nullable<BigInt>)]
+    value: Option<i64>,
+}
+
+fn max_value(
+    conn: &mut PgConnection,
+    table: &str,
+    column: &str,
+) -> Result<Option<i64>, anyhow::Error> {
+    let max = diesel::sql_query(format!("SELECT MAX({column}) AS value FROM {table}"))
+        .get_result::<MaxValue>(conn)?;
+    Ok(max.value)
+}
+
+fn check_watermarks(
+    conn: &mut PgConnection,
+    options: &DoctorOptions,
+    _fullnode_checkpoint: Option<u64>,
+    findings: &mut Vec<Finding>,
+) -> Result<(), anyhow::Error> {
+    if !options.use_v2 {
+        findings.push(Finding {
+            severity: Severity::Info,
+            check: "watermarks",
+            problem: "Watermark consistency is only checked for the v2 schema.".to_string(),
+            remediation: "Run with `--use-v2` against a v2 database.".to_string(),
+        });
+        return Ok(());
+    }
+
+    let Some(checkpoint) = max_value(conn, "checkpoints", "sequence_number")? else {
+        findings.push(Finding {
+            severity: Severity::Info,
+            check: "watermarks",
+            problem: "No checkpoints have been indexed yet.".to_string(),
+            remediation: "Start a writer with `--fullnode-sync-worker` if one is not running."
+                .to_string(),
+        });
+        return Ok(());
+    };
+
+    for watermark in &CHECKPOINT_PIPELINE_WATERMARKS {
+        let value = max_value(conn, watermark.table, watermark.column)?.unwrap_or(-1);
+        if value < checkpoint {
+            findings.push(Finding {
+                severity: Severity::Critical,
+                check: "watermarks",
+                problem: format!(
+                    "{} is indexed up to checkpoint {value}, behind checkpoints at {checkpoint}. \
+                     Checkpoints were committed without all of their data.",
+                    watermark.table
+                ),
+                remediation: format!(
+                    "Data served for checkpoints after {value} is incomplete. Re-index from \
+                     checkpoint {} with the {}, or reset the database.",
+                    value + 1,
+                    watermark.pipeline
+                ),
+            });
+        } else if value - checkpoint > options.max_checkpoint_lag as i64 {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                check: "watermarks",
+                problem: format!(
+                    "{} is indexed up to checkpoint {value}, but checkpoints only up to \
+                     {checkpoint}.",
+                    watermark.table
+                ),
+                remediation: "Checkpoints are committed last; the checkpoint commit is stalled \
+                    or failing. Check the writer logs for errors persisting checkpoints."
+                    .to_string(),
+            });
+        }
+    }
+
+    for watermark in &ANALYTICAL_PIPELINE_WATERMARKS {
+        let value = max_value(conn, watermark.table, watermark.column)?;
+        let lag = checkpoint - value.unwrap_or(-1);
+        if lag > options.max_checkpoint_lag as i64 {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                check: "watermarks",
+                problem: format!(
+                    "{} is {lag} checkpoint(s) behind the checkpoints table.",
+                    watermark.table
+                ),
+                remediation: format!(
+                    "Make sure the {} is running (`--analytical-worker`) and check its logs.",
+                    watermark.pipeline
+                ),
+            });
+        }
+    }
+
+    let indexed_epoch = max_value(conn, "epochs", "epoch")?.unwrap_or(-1);
+    let checkpoint_epoch = max_value(conn, "checkpoints", "epoch")?.unwrap_or(-1);
+    if indexed_epoch < checkpoint_epoch {
+        findings.push(Finding {
+            severity: Severity::Critical,
+            check: "watermarks",
+            problem: format!(
+                "Checkpoints are indexed up to epoch {checkpoint_epoch}, but epochs only up to \
+                 {indexed_epoch}."
+            ),
+            remediation: "An epoch change was not persisted. Re-index from the last checkpoint \
+                of the last indexed epoch."
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn check_lag(
+    conn: &mut PgConnection,
+    options: &DoctorOptions,
+    fullnode_checkpoint: Option<u64>,
+    findings: &mut Vec<Finding>,
+) -> Result<(), anyhow::Error> {
+    let Some(fullnode_checkpoint) = fullnode_checkpoint else {
+        findings.push(Finding {
+            severity: Severity::Info,
+            check: "lag",
+            problem: "Lag was not checked, as no fullnode was reachable.".to_string(),
+            remediation: "Pass `--rpc-client-url` pointing at the fullnode the indexer reads \
+                from."
+                .to_string(),
+        });
+        return Ok(());
+    };
+    let checkpoint = max_value(conn, "checkpoints", "sequence_number")?.unwrap_or(-1);
+    let lag = fullnode_checkpoint as i64 - checkpoint;
+    if lag < 0 {
+        findings.push(Finding {
+            severity: Severity::Critical,
+            check: "lag",
+            problem: format!(
+                "The indexer is at checkpoint {checkpoint}, ahead of the fullnode at \
+                 {fullnode_checkpoint}."
+            ),
+            remediation: "The indexer and fullnode are likely on different networks, or the \
+                fullnode was restored from an older snapshot. Check `--rpc-client-url`."
+                .to_string(),
+        });
+    } else if lag > options.max_checkpoint_lag as i64 {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            check: "lag",
+            problem: format!(
+                "The indexer is {lag} checkpoint(s) behind the fullnode (at {checkpoint} vs \
+                 {fullnode_checkpoint})."
+            ),
+            remediation: "Make sure a writer is running (`--fullnode-sync-worker`), and check \
+                its logs and the database's write throughput."
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct TableBloat {
+    #[diesel(sql_type = Text)]
+    relname: String,
+    #[diesel(sql_type = BigInt)]
+    n_live_tup: i64,
+    #[diesel(sql_type = BigInt)]
+    n_dead_tup: i64,
+}
+
+fn check_bloat(
+    conn: &mut PgConnection,
+    _options: &DoctorOptions,
+    _fullnode_checkpoint: Option<u64>,
+    findings: &mut Vec<Finding>,
+) -> Result<(), anyhow::Error> {
+    let tables = diesel::sql_query(format!(
+        "SELECT relname::TEXT, n_live_tup, n_dead_tup FROM pg_stat_user_tables \
+         WHERE n_dead_tup >= {BLOAT_MIN_DEAD_TUPLES} \
+         AND n_dead_tup >= n_live_tup * {BLOAT_RATIO} \
+         ORDER BY n_dead_tup DESC"
+    ))
+    .load::<TableBloat>(conn)?;
+
+    for table in tables {
+        let severe = table.n_dead_tup > table.n_live_tup;
+        findings.push(Finding {
+            severity: Severity::Warning,
+            check: "bloat",
+            problem: format!(
+                "{} has {} dead tuple(s) for {} live one(s).",
+                table.relname, table.n_dead_tup, table.n_live_tup
+            ),
+            remediation: if severe {
+                format!(
+                    "Run `VACUUM (ANALYZE) {}`, and consider `pg_repack` to reclaim space. \
+                     Autovacuum is not keeping up; lower autovacuum_vacuum_scale_factor for \
+                     this table.",
+                    table.relname
+                )
+            } else {
+                format!("Run `VACUUM (ANALYZE) {}`.", table.relname)
+            },
+        });
+    }
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct InvalidIndex {
+    #[diesel(sql_type = Text)]
+    index_name: String,
+    #[diesel(sql_type = Text)]
+    table_name: String,
+}
+
+#[derive(QueryableByName)]
+struct ScanCounts {
+    #[diesel(sql_type = Text)]
+    relname: String,
+    #[diesel(sql_type = BigInt)]
+    seq_scan: i64,
+    #[diesel(sql_type = BigInt)]
+    idx_scan: i64,
+    #[diesel(sql_type = BigInt)]
+    n_live_tup: i64,
+}
+
+fn check_indexes(
+    conn: &mut PgConnection,
+    _options: &DoctorOptions,
+    _fullnode_checkpoint: Option<u64>,
+    findings: &mut Vec<Finding>,
+) -> Result<(), anyhow::Error> {
+    let invalid = diesel::sql_query(
+        "SELECT i.relname::TEXT AS index_name, t.relname::TEXT AS table_name \
+         FROM pg_index x \
+         JOIN pg_class i ON i.oid = x.indexrelid \
+         JOIN pg_class t ON t.oid = x.indrelid \
+         WHERE NOT x.indisvalid",
+    )
+    .load::<InvalidIndex>(conn)?;
+    for index in invalid {
+        findings.push(Finding {
+            severity: Severity::Critical,
+            check: "indexes",
+            problem: format!(
+                "Index {} on {} is invalid, so queries cannot use it.",
+                index.index_name, index.table_name
+            ),
+            remediation: format!(
+                "A concurrent index build likely failed. Run `REINDEX INDEX CONCURRENTLY {}`.",
+                index.index_name
+            ),
+        });
+    }
+
+    let scans = diesel::sql_query(format!(
+        "SELECT relname::TEXT, seq_scan, COALESCE(idx_scan, 0) AS idx_scan, n_live_tup \
+         FROM pg_stat_user_tables \
+         WHERE n_live_tup >= {SEQ_SCAN_MIN_ROWS} AND seq_scan > COALESCE(idx_scan, 0) \
+         ORDER BY seq_scan DESC"
+    ))
+    .load::<ScanCounts>(conn)?;
+    for table in scans {
+        findings.push(Finding {
+            severity: Severity::Warning,
+            check: "indexes",
+            problem: format!(
+                "{} ({} rows) was read with {} sequential scan(s) but only {} index scan(s).",
+                table.relname, table.n_live_tup, table.seq_scan, table.idx_scan
+            ),
+            remediation: format!(
+                "Queries on {} are likely missing an index. Find them in pg_stat_statements and \
+                 check that the indexes created by the migrations have not been dropped.",
+                table.relname
+            ),
+        });
+    }
+    Ok(())
+}
@@ -3,7 +3,8 @@
 
 use fastcrypto::error::FastCryptoError;
 use jsonrpsee::core::Error as RpcError;
-use jsonrpsee::types::error::CallError;
+use jsonrpsee::types::error::{CallError, INVALID_PARAMS_CODE};
+use jsonrpsee::types::ErrorObject;
 use thiserror::Error;
 
 use sui_types::base_types::ObjectIDParseError;
@@ -125,6 +126,61 @@ pub enum IndexerError {
 
     #[error("Indexer failed to send item to channel with error: `{0}`")]
     MpscChannelError(String),
+
+    #[error(
+        "Estimated query cost {cost:.0} exceeds the budget of {budget:.0}, narrow your query \
+        with a more selective filter or a limit of at most {max_limit}"
+    )]
+    QueryCostExceeded {
+        cost: f64,
+        budget: f64,
+        max_limit: usize,
+    },
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+}
+
+/// Errors caused by losing the connection to the database, e.g. on a primary failover or a
+/// connection reset, rather than by the statement itself. Statements that failed with one of
+/// those can be retried as is once a new connection is established.
+pub trait ConnectionFailure {
+    fn is_connection_failure(&self) -> bool;
+}
+
+impl ConnectionFailure for diesel::result::Error {
+    fn is_connection_failure(&self) -> bool {
+        use diesel::result::{DatabaseErrorKind, Error};
+
+        match self {
+            Error::DatabaseError(
+                DatabaseErrorKind::ClosedConnection | DatabaseErrorKind::UnableToSendCommand,
+                _,
+            ) => true,
+            // Postgres reports administrative shutdowns and recovery in progress with plain
+            // errors, see SQLSTATE classes 57P and 08.
+            Error::DatabaseError(_, info) => {
+                let message = info.message();
+                message.starts_with("terminating connection")
+                    || message.starts_with("the database system is")
+                    || message.starts_with("server closed the connection")
+            }
+            Error::BrokenTransactionManager => true,
+            _ => false,
+        }
+    }
+}
+
+impl ConnectionFailure for IndexerError {
+    fn is_connection_failure(&self) -> bool {
+        match self {
+            // No connection could be checked out of the pool before the connection timeout.
+            IndexerError::PgPoolConnectionError(_) => true,
+            IndexerError::PostgresError(e) => e.is_connection_failure(),
+            IndexerError::ErrorWithContext(_, e) => e.is_connection_failure(),
+            _ => false,
+        }
+    }
 }
 
 pub trait Context<T> {
@@ -139,7 +195,22 @@ impl<T> Context<T> for Result<T, IndexerError> {
 
 impl From<IndexerError> for RpcError {
     fn from(e: IndexerError) -> Self {
-        RpcError::Call(CallError::Failed(e.into()))
+        match e {
+            IndexerError::QueryCostExceeded {
+                cost,
+                budget,
+                max_limit,
+            } => RpcError::Call(CallError::Custom(ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                e.to_string(),
+                Some(serde_json::json!({
+                    "cost": cost,
+                    "budget": budget,
+                    "maxLimit": max_limit,
+                })),
+            ))),
+            e => RpcError::Call(CallError::Failed(e.into())),
+        }
     }
 }
 
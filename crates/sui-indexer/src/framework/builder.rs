@@ -1,7 +1,9 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use sui_storage::object_store::ObjectStoreConfig;
 use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+use tracing::error;
 
 use super::fetcher::CheckpointFetcher;
 use super::Handler;
@@ -11,6 +13,9 @@ pub struct IndexerBuilder {
     handlers: Vec<Box<dyn Handler>>,
     last_downloaded_checkpoint: Option<CheckpointSequenceNumber>,
     checkpoint_buffer_size: usize,
+    /// Number of checkpoints to replay at startup, and the store of the checkpoint files to
+    /// read them from
+    replay: Option<(u64, ObjectStoreConfig)>,
 }
 
 impl IndexerBuilder {
@@ -23,6 +28,7 @@ impl IndexerBuilder {
             handlers: Vec::new(),
             last_downloaded_checkpoint: None,
             checkpoint_buffer_size: Self::DEFAULT_CHECKPOINT_BUFFER_SIZE,
+            replay: None,
         }
     }
 
@@ -49,7 +55,18 @@ impl IndexerBuilder {
         self
     }
 
-    pub async fn run(self) {
+    /// Replay the last `replay_checkpoints` downloaded checkpoints to the handlers at startup,
+    /// read from the checkpoint files of `store_config`, see [`Handler::replay_checkpoints`].
+    pub fn replay_checkpoints(
+        mut self,
+        replay_checkpoints: u64,
+        store_config: ObjectStoreConfig,
+    ) -> Self {
+        self.replay = Some((replay_checkpoints, store_config));
+        self
+    }
+
+    pub async fn run(mut self) {
         let (downloaded_checkpoint_data_sender, downloaded_checkpoint_data_receiver) =
             mysten_metrics::metered_channel::channel(
                 self.checkpoint_buffer_size,
@@ -70,6 +87,19 @@ impl IndexerBuilder {
         mysten_metrics::spawn_monitored_task!(fetcher.run());
 
         assert!(!self.handlers.is_empty());
+        if let Some((replay_checkpoints, store_config)) = self.replay.take() {
+            // Handlers only start from a colder state without the replay.
+            if let Err(e) = super::replay::replay_committed_checkpoints(
+                store_config,
+                self.last_downloaded_checkpoint,
+                replay_checkpoints,
+                &mut self.handlers,
+            )
+            .await
+            {
+                error!("Failed to replay committed checkpoints, starting without them: {e:?}");
+            }
+        }
 
         super::runner::run(
             mysten_metrics::metered_channel::ReceiverStream::new(
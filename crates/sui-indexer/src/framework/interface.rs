@@ -17,6 +17,12 @@ pub trait Handler: Send {
         }
         Ok(())
     }
+    /// Rebuild the in-memory state derived from checkpoints that were processed and committed
+    /// before a restart. Called at startup with the last committed checkpoints, in order, before
+    /// new ones are processed; nothing must be written for them again.
+    async fn replay_checkpoints(&mut self, _checkpoints: &[CheckpointData]) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub trait BackfillHandler: Handler {
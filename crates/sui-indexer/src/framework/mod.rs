@@ -6,6 +6,7 @@ pub mod interface;
 
 // TODO remove the pub(crater) once indexer_v2.rs is renamed to lib.rs
 pub(crate) mod fetcher;
+pub(crate) mod replay;
 pub(crate) mod runner;
 
 pub use builder::IndexerBuilder;
@@ -0,0 +1,68 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use sui_checkpoint_ingestion::CheckpointIngestionBuilder;
+use sui_storage::object_store::ObjectStoreConfig;
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+use tracing::info;
+
+use super::interface::Handler;
+
+const REPLAY_DOWNLOAD_CONCURRENCY: usize = 100;
+const REPLAY_BATCH_SIZE: usize = 25;
+
+/// Replay the last `count` checkpoints up to `last_committed` to `handlers`, in order, for them to
+/// rebuild the in-memory state derived from the checkpoints they already processed before a
+/// restart. Runs before the handlers process new checkpoints, so that they don't start from state
+/// that is only correct again once the next checkpoint has been processed.
+///
+/// Checkpoints are read from the checkpoint files of `store_config`, rather than from the full
+/// node, which may have pruned them.
+pub async fn replay_committed_checkpoints(
+    store_config: ObjectStoreConfig,
+    last_committed: Option<CheckpointSequenceNumber>,
+    count: u64,
+    handlers: &mut [Box<dyn Handler>],
+) -> Result<()> {
+    let Some(last_committed) = last_committed else {
+        return Ok(());
+    };
+    if count == 0 {
+        return Ok(());
+    }
+    let first = last_committed.saturating_sub(count - 1);
+    info!(
+        first,
+        last = last_committed,
+        "Replaying committed checkpoints"
+    );
+
+    let mut checkpoints = CheckpointIngestionBuilder::new()
+        .object_store(store_config)
+        .start_checkpoint(first)
+        .end_checkpoint(last_committed)
+        .concurrency(REPLAY_DOWNLOAD_CONCURRENCY)
+        .build()?
+        .chunks(REPLAY_BATCH_SIZE);
+    while let Some(batch) = checkpoints.next().await {
+        let batch = batch
+            .into_iter()
+            .collect::<Result<Vec<_>>>()
+            .context("Failed to read checkpoints to replay")?;
+        for handler in handlers.iter_mut() {
+            let name = handler.name().to_string();
+            handler
+                .replay_checkpoints(&batch)
+                .await
+                .with_context(|| format!("Handler {name} failed to replay checkpoints"))?;
+        }
+    }
+    info!(
+        first,
+        last = last_committed,
+        "Replayed committed checkpoints"
+    );
+    Ok(())
+}
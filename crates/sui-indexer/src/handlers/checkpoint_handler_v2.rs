@@ -1,13 +1,14 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::handlers::committer::start_tx_checkpoint_commit_task;
 use crate::handlers::tx_processor::IndexingPackageCache;
 use crate::models_v2::display::StoredDisplay;
 use async_trait::async_trait;
 use itertools::Itertools;
 use move_bytecode_utils::module_cache::GetModule;
-use mysten_metrics::{get_metrics, spawn_monitored_task};
+use move_core_types::identifier::Identifier;
+use move_core_types::language_storage::ModuleId;
+use mysten_metrics::get_metrics;
 use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 use sui_rest_api::CheckpointData;
@@ -19,8 +20,6 @@ use sui_types::dynamic_field::DynamicFieldType;
 use sui_types::messages_checkpoint::{CertifiedCheckpointSummary, CheckpointContents};
 use sui_types::object::Object;
 
-use tokio::sync::watch;
-
 use std::collections::hash_map::Entry;
 use std::collections::HashSet;
 use sui_json_rpc_types::SuiMoveValue;
@@ -47,7 +46,6 @@ use crate::types_v2::{
     IndexedCheckpoint, IndexedEvent, IndexedTransaction, IndexerResult, TransactionKind, TxIndex,
 };
 use crate::types_v2::{IndexedObject, IndexedPackage};
-use crate::IndexerConfig;
 
 use super::tx_processor::EpochEndIndexingObjectStore;
 use super::tx_processor::TxChangesProcessor;
@@ -57,11 +55,19 @@ use super::TransactionObjectChangesToCommit;
 
 const CHECKPOINT_QUEUE_SIZE: usize = 1000;
 
+/// Returns the handler along with the receiving end of the checkpoints it indexes, to be
+/// consumed by the commit task.
 pub async fn new_handlers<S>(
     state: S,
     metrics: IndexerMetrics,
-    config: &IndexerConfig,
-) -> Result<CheckpointHandler<S>, IndexerError>
+    package_cache: Arc<Mutex<IndexingPackageCache>>,
+) -> Result<
+    (
+        CheckpointHandler<S>,
+        mysten_metrics::metered_channel::Receiver<CheckpointDataToCommit>,
+    ),
+    IndexerError,
+>
 where
     S: IndexerStoreV2 + Clone + Sync + Send + 'static,
 {
@@ -78,26 +84,14 @@ where
                 .with_label_values(&["checkpoint_indexing"]),
         );
 
-    let state_clone = state.clone();
-    let metrics_clone = metrics.clone();
-    let config_clone = config.clone();
-    let (tx, rx) = watch::channel(None);
-    spawn_monitored_task!(start_tx_checkpoint_commit_task(
-        state_clone,
-        metrics_clone,
-        config_clone,
-        indexed_checkpoint_receiver,
-        tx,
-    ));
-
     let checkpoint_handler = CheckpointHandler {
         state,
         metrics,
         indexed_checkpoint_sender,
-        package_cache: IndexingPackageCache::start(rx),
+        package_cache,
     };
 
-    Ok(checkpoint_handler)
+    Ok((checkpoint_handler, indexed_checkpoint_receiver))
 }
 
 pub struct CheckpointHandler<S> {
@@ -210,6 +204,43 @@ where
         }
         Ok(())
     }
+
+    /// Rebuild the package cache with the packages published in the replayed checkpoints, until
+    /// its GC finds them committed, and load their modules in the module cache of the store, as
+    /// the checkpoints following them are the most likely to use them. No other state is kept
+    /// across checkpoints: the latest versions of objects are derived from each checkpoint alone.
+    async fn replay_checkpoints(&mut self, checkpoints: &[CheckpointData]) -> anyhow::Result<()> {
+        // Replayed packages aren't indexed again, so aren't measured as indexed either.
+        let packages = Self::published_packages(checkpoints);
+        self.package_cache
+            .lock()
+            .unwrap()
+            .insert_packages(&packages);
+        let module_ids = packages
+            .into_iter()
+            .flat_map(|package| {
+                package
+                    .move_package
+                    .serialized_module_map()
+                    .keys()
+                    .map(|name| {
+                        Ok(ModuleId::new(
+                            package.package_id.into(),
+                            Identifier::new(name.as_str())?,
+                        ))
+                    })
+                    .collect::<Vec<anyhow::Result<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let module_cache = self.state.module_cache();
+        tokio::task::spawn_blocking(move || {
+            for id in module_ids {
+                module_cache.get_module_by_id(&id)?;
+            }
+            Ok(())
+        })
+        .await?
+    }
 }
 
 impl<S> CheckpointHandler<S>
@@ -348,7 +379,7 @@ where
         })
     }
 
-    async fn index_transactions(
+    pub(crate) async fn index_transactions(
         transactions: Vec<CheckpointTransaction>,
         checkpoint_summary: &CertifiedCheckpointSummary,
         checkpoint_contents: &CheckpointContents,
@@ -494,6 +525,9 @@ where
                 .map(|(p, m, f)| (*<&ObjectID>::clone(p), m.to_string(), f.to_string()))
                 .collect();
 
+            // Dependencies
+            let dependencies = fx.dependencies().to_vec();
+
             db_indices.push(TxIndex {
                 tx_sequence_number,
                 transaction_digest: tx_digest,
@@ -504,12 +538,13 @@ where
                 payers,
                 recipients,
                 move_calls,
+                dependencies,
             });
         }
         Ok((db_transactions, db_events, db_indices, db_displays))
     }
 
-    fn index_objects(
+    pub(crate) fn index_objects(
         data: CheckpointData,
         metrics: &IndexerMetrics,
         module_resolver: &impl GetModule,
@@ -580,11 +615,16 @@ where
         }
     }
 
-    fn index_packages(
+    pub(crate) fn index_packages(
         checkpoint_data: &[CheckpointData],
         metrics: &IndexerMetrics,
     ) -> Vec<IndexedPackage> {
         let _timer = metrics.indexing_packages_latency.start_timer();
+        Self::published_packages(checkpoint_data)
+    }
+
+    /// Packages published in the checkpoints, in order.
+    fn published_packages(checkpoint_data: &[CheckpointData]) -> Vec<IndexedPackage> {
         checkpoint_data
             .iter()
             .flat_map(|data| {
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::collections::BTreeMap;
+use std::time::Duration;
 
 use tokio::sync::watch;
 use tracing::instrument;
@@ -11,6 +12,7 @@ use tracing::{error, info};
 
 use sui_types::messages_checkpoint::CheckpointSequenceNumber;
 
+use crate::clock::IndexerClock;
 use crate::metrics::IndexerMetrics;
 
 use crate::store::IndexerStoreV2;
@@ -19,10 +21,14 @@ use crate::IndexerConfig;
 
 use super::CheckpointDataToCommit;
 
+/// Longest span of checkpoint time committed in one batch in backfill mode.
+const CHECKPOINT_COMMIT_BATCH_DEADLINE: Duration = Duration::from_secs(10);
+
 pub async fn start_tx_checkpoint_commit_task<S>(
     state: S,
     metrics: IndexerMetrics,
     config: IndexerConfig,
+    clock: IndexerClock,
     tx_indexing_receiver: mysten_metrics::metered_channel::Receiver<CheckpointDataToCommit>,
     commit_notifier: watch::Sender<Option<CheckpointSequenceNumber>>,
 ) where
@@ -37,8 +43,37 @@ pub async fn start_tx_checkpoint_commit_task<S>(
         .unwrap();
     info!("Using checkpoint commit batch size {checkpoint_commit_batch_size}");
 
-    let mut stream = mysten_metrics::metered_channel::ReceiverStream::new(tx_indexing_receiver)
-        .ready_chunks(checkpoint_commit_batch_size);
+    let checkpoints = mysten_metrics::metered_channel::ReceiverStream::new(tx_indexing_receiver);
+    // Live, whatever has been indexed is committed as soon as possible. In backfill mode, batches
+    // only depend on the checkpoints, not on how fast they were indexed.
+    let mut stream = if clock.is_checkpoint_time() {
+        let batcher = CheckpointBatcher::new(
+            checkpoint_commit_batch_size,
+            CHECKPOINT_COMMIT_BATCH_DEADLINE,
+        );
+        checkpoints
+            .map(Some)
+            .chain(futures::stream::once(async { None }))
+            .scan(batcher, |batcher, checkpoint| {
+                let batches = match checkpoint {
+                    Some(checkpoint) => {
+                        let (epoch, timestamp_ms) = (
+                            checkpoint.checkpoint.epoch,
+                            checkpoint.checkpoint.timestamp_ms,
+                        );
+                        batcher.push(checkpoint, epoch, timestamp_ms)
+                    }
+                    None => batcher.finish(),
+                };
+                futures::future::ready(Some(futures::stream::iter(batches)))
+            })
+            .flatten()
+            .boxed()
+    } else {
+        checkpoints
+            .ready_chunks(checkpoint_commit_batch_size)
+            .boxed()
+    };
 
     while let Some(indexed_checkpoint_batch) = stream.next().await {
         // TODO: don't batch checkpoints across epoch boundary (for partitioning management)
@@ -54,7 +89,64 @@ pub async fn start_tx_checkpoint_commit_task<S>(
             );
             continue;
         }
-        commit_checkpoints(&state, indexed_checkpoint_batch, &metrics, &commit_notifier).await;
+        commit_checkpoints(
+            &state,
+            indexed_checkpoint_batch,
+            &metrics,
+            &clock,
+            &commit_notifier,
+        )
+        .await;
+    }
+}
+
+/// Cuts checkpoints into commit batches on their contents alone: a batch is closed once it holds
+/// `batch_size` checkpoints, or before a checkpoint of another epoch or past its deadline.
+struct CheckpointBatcher<T> {
+    batch_size: usize,
+    deadline_ms: u64,
+    batch: Vec<T>,
+    batch_epoch: u64,
+    batch_start_ms: u64,
+}
+
+impl<T> CheckpointBatcher<T> {
+    fn new(batch_size: usize, deadline: Duration) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            deadline_ms: deadline.as_millis() as u64,
+            batch: vec![],
+            batch_epoch: 0,
+            batch_start_ms: 0,
+        }
+    }
+
+    /// Add the next checkpoint, returning the batches it closed.
+    fn push(&mut self, checkpoint: T, epoch: u64, timestamp_ms: u64) -> Vec<Vec<T>> {
+        let mut batches = vec![];
+        if !self.batch.is_empty()
+            && (epoch != self.batch_epoch || timestamp_ms >= self.batch_start_ms + self.deadline_ms)
+        {
+            batches.push(std::mem::take(&mut self.batch));
+        }
+        if self.batch.is_empty() {
+            self.batch_epoch = epoch;
+            self.batch_start_ms = timestamp_ms;
+        }
+        self.batch.push(checkpoint);
+        if self.batch.len() >= self.batch_size {
+            batches.push(std::mem::take(&mut self.batch));
+        }
+        batches
+    }
+
+    /// Close the last batch, once there are no more checkpoints.
+    fn finish(&mut self) -> Vec<Vec<T>> {
+        if self.batch.is_empty() {
+            vec![]
+        } else {
+            vec![std::mem::take(&mut self.batch)]
+        }
     }
 }
 
@@ -67,6 +159,7 @@ async fn commit_checkpoints<S>(
     state: &S,
     indexed_checkpoint_batch: Vec<CheckpointDataToCommit>,
     metrics: &IndexerMetrics,
+    clock: &IndexerClock,
     commit_notifier: &watch::Sender<Option<CheckpointSequenceNumber>>,
 ) where
     S: IndexerStoreV2 + Clone + Sync + Send + 'static,
@@ -105,6 +198,7 @@ async fn commit_checkpoints<S>(
 
     let first_checkpoint_seq = checkpoint_batch.first().as_ref().unwrap().sequence_number;
     let last_checkpoint_seq = checkpoint_batch.last().as_ref().unwrap().sequence_number;
+    let last_checkpoint_timestamp_ms = checkpoint_batch.last().as_ref().unwrap().timestamp_ms;
 
     let guard = metrics.checkpoint_db_commit_latency.start_timer();
     let tx_batch = tx_batch.into_iter().flatten().collect::<Vec<_>>();
@@ -150,6 +244,7 @@ async fn commit_checkpoints<S>(
         .expect("Persisting data into DB should not fail.");
     let elapsed = guard.stop_and_record();
 
+    clock.advance(last_checkpoint_timestamp_ms);
     commit_notifier
         .send(Some(last_checkpoint_seq))
         .expect("Commit watcher should not be closed");
@@ -179,3 +274,30 @@ async fn commit_checkpoints<S>(
         .thousand_transaction_avg_db_commit_latency
         .observe(elapsed * 1000.0 / tx_count as f64);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CheckpointBatcher;
+    use std::time::Duration;
+
+    #[test]
+    fn test_checkpoint_batches() {
+        let mut batcher = CheckpointBatcher::new(3, Duration::from_secs(10));
+        let mut batches = vec![];
+        // (sequence number, epoch, timestamp)
+        for (seq, epoch, timestamp_ms) in [
+            (0, 0, 0),
+            (1, 0, 1_000),
+            (2, 0, 2_000),
+            (3, 0, 3_000),
+            (4, 0, 14_000),
+            (5, 1, 15_000),
+            (6, 1, 16_000),
+        ] {
+            batches.extend(batcher.push(seq, epoch, timestamp_ms));
+        }
+        batches.extend(batcher.finish());
+        // Cut when full, past the deadline, and at the epoch boundary
+        assert_eq!(batches, vec![vec![0, 1, 2], vec![3], vec![4], vec![5, 6]]);
+    }
+}
@@ -8,15 +8,13 @@ use async_trait::async_trait;
 use move_binary_format::CompiledModule;
 use move_core_types::language_storage::ModuleId;
 use mysten_metrics::monitored_scope;
-use mysten_metrics::spawn_monitored_task;
 use sui_rest_api::CheckpointData;
 use tokio::sync::watch;
 
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 use sui_types::object::Object;
 use tokio::time::Duration;
-use tokio::time::Instant;
 
 use sui_json_rpc::get_balance_changes_from_effect;
 use sui_json_rpc::get_object_changes;
@@ -30,13 +28,16 @@ use tracing::debug;
 use sui_types::base_types::ObjectID;
 use sui_types::messages_checkpoint::CheckpointSequenceNumber;
 
+use crate::clock::IndexerClock;
 use crate::errors::IndexerError;
 use crate::metrics::IndexerMetrics;
 
 use crate::types_v2::IndexedPackage;
-use crate::types_v2::{IndexedObjectChange, IndexerResult};
+use crate::types_v2::{
+    sort_balance_changes, sort_object_changes, IndexedObjectChange, IndexerResult,
+};
 
-// GC the cache every 10 minutes
+// GC the cache every 10 minutes, of checkpoint time in backfill mode
 pub const PACKAGE_CACHE_GC_INTERVAL: Duration = Duration::from_secs(600);
 
 /// An in-mem cache for packages during writer path indexing.
@@ -50,32 +51,29 @@ pub struct IndexingPackageCache {
 }
 
 impl IndexingPackageCache {
-    pub fn start(
-        commit_watcher: watch::Receiver<Option<CheckpointSequenceNumber>>,
-    ) -> Arc<Mutex<Self>> {
-        let cache = Arc::new(Mutex::new(Self {
+    /// The cache is only GCed while [`Self::remove_committed`] runs.
+    pub fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
             packages: HashMap::new(),
-        }));
-        let cache_clone = cache.clone();
-        spawn_monitored_task!(Self::remove_committed(cache_clone, commit_watcher));
-        cache
+        }))
     }
 
     pub async fn remove_committed(
         cache: Arc<Mutex<Self>>,
         commit_watcher: watch::Receiver<Option<CheckpointSequenceNumber>>,
+        clock: IndexerClock,
     ) {
-        let mut interval = tokio::time::interval_at(Instant::now(), PACKAGE_CACHE_GC_INTERVAL);
-        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         loop {
-            interval.tick().await;
+            clock.sleep(PACKAGE_CACHE_GC_INTERVAL).await;
             let _scope = monitored_scope("InMemObjectCache::remove_committed");
             let Some(committed_checkpoint) = *commit_watcher.borrow() else {
                 continue;
             };
             debug!("About to GC packages older than: {committed_checkpoint}");
 
-            let mut cache = cache.lock().unwrap();
+            // Only entries are removed here, so the cache is still usable after a panic while it
+            // was locked, which restarts this task.
+            let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
             let mut to_remove = vec![];
             for (id, (_, checkpoint_seq)) in cache.packages.iter() {
                 if *checkpoint_seq <= committed_checkpoint {
@@ -178,7 +176,7 @@ impl TxChangesProcessor {
             .metrics
             .indexing_tx_object_changes_latency
             .start_timer();
-        let object_change: Vec<_> = get_object_changes(
+        let mut object_change: Vec<_> = get_object_changes(
             self,
             tx.sender(),
             effects.modified_at_versions(),
@@ -189,7 +187,8 @@ impl TxChangesProcessor {
         .into_iter()
         .map(IndexedObjectChange::from)
         .collect();
-        let balance_change = get_balance_changes_from_effect(
+        sort_object_changes(&mut object_change);
+        let mut balance_change = get_balance_changes_from_effect(
             self,
             effects,
             tx.input_objects().unwrap_or_else(|e| {
@@ -201,6 +200,7 @@ impl TxChangesProcessor {
             None,
         )
         .await?;
+        sort_balance_changes(&mut balance_change);
         Ok((balance_change, object_change))
     }
 }
@@ -2,6 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    api_tokens::{retain_visible_dependencies, ApiTokens, TokenScope, Visibility},
+    archive_fallback::ArchiveFallback,
     errors::IndexerError,
     models_v2::{
         address_metrics::StoredAddressMetrics,
@@ -9,49 +11,66 @@ use crate::{
         display::StoredDisplay,
         epoch::StoredEpochInfo,
         events::StoredEvent,
+        gas_price_metrics::{granularity, StoredGasPriceMetrics},
         move_call_metrics::QueriedMoveCallMetrics,
         network_metrics::StoredNetworkMetrics,
         objects::{CoinBalance, ObjectRefColumn, StoredObject},
+        package_reindex::StoredPackageReindexJob,
         packages::StoredPackage,
+        purge_reports::StoredPurgeReport,
         transactions::StoredTransaction,
         tx_indices::TxSequenceNumber,
+        watchlist::StoredWatchlistAddress,
     },
+    query_budget::QueryTier,
+    retention,
     schema_v2::{
-        address_metrics, checkpoints, display, epochs, events, move_call_metrics, objects,
-        packages, transactions,
+        address_metrics, checkpoint_timestamps, checkpoints, display, epochs, events,
+        gas_price_metrics, move_call_metrics, objects, package_reindex_jobs, packages,
+        purge_reports, query_cost, transactions, tx_calls, tx_dependencies, tx_recipients,
+        tx_senders, watchlist_addresses,
     },
-    types_v2::{IndexerResult, OwnerType},
+    types_v2::{dynamic_field_key_id, IndexerResult, OwnerType},
     PgConnectionConfig, PgConnectionPoolConfig, PgPoolConnection,
 };
 use anyhow::{anyhow, Result};
 use cached::proc_macro::cached;
 use cached::SizedCache;
 use diesel::{
-    r2d2::ConnectionManager, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    dsl::{max, min},
+    r2d2::ConnectionManager,
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
     RunQueryDsl,
 };
 use fastcrypto::encoding::Encoding;
 use fastcrypto::encoding::Hex;
 use itertools::{any, Itertools};
-use move_core_types::language_storage::StructTag;
+use move_core_types::language_storage::{StructTag, TypeTag};
 use std::{
-    collections::{BTreeMap, HashMap},
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, RwLock, Weak},
+    time::{SystemTime, UNIX_EPOCH},
 };
+use sui_json_rpc::api_token::current_api_token;
 use sui_json_rpc_types::{
-    AddressMetrics, CheckpointId, EpochInfo, EventFilter, MoveCallMetrics, MoveFunctionName,
-    NetworkMetrics, SuiEvent, SuiObjectDataFilter, SuiTransactionBlockResponse, TransactionFilter,
+    AddressMetrics, CheckpointId, EpochInfo, EventFilter, GasPriceHistory, GasPriceInterval,
+    MoveCallMetrics, MoveFunctionName, NetworkMetrics, PackageReindexJob, PurgeReport, SuiEvent,
+    SuiObjectDataFilter, SuiTransactionBlockResponse, TransactionDependencyDirection,
+    TransactionDependencyEdge, TransactionDependencyGraph, TransactionFilter,
+    WatchlistBackfillProgress,
 };
 use sui_json_rpc_types::{
     Balance, Coin as SuiCoin, SuiCoinMetadata, SuiTransactionBlockEffects,
     SuiTransactionBlockEffectsAPI,
 };
+use sui_storage::memory_budget::{register_cache, EvictableCache, MemoryAccount};
 use sui_types::{balance::Supply, coin::TreasuryCap, dynamic_field::DynamicFieldName};
 use sui_types::{
     base_types::{ObjectID, ObjectRef, SequenceNumber, SuiAddress, VersionNumber},
+    checkpoint_range::CheckpointRange,
     committee::EpochId,
     digests::{ObjectDigest, TransactionDigest},
-    dynamic_field::DynamicFieldInfo,
+    dynamic_field::{derive_dynamic_object_field_id, DynamicFieldInfo},
     is_system_package,
     move_package::MovePackage,
     object::{Object, ObjectRead},
@@ -67,6 +86,9 @@ pub const EVENT_SEQUENCE_NUMBER_STR: &str = "event_sequence_number";
 pub struct IndexerReader {
     pool: crate::PgConnectionPool,
     package_cache: PackageCache,
+    query_tier: QueryTier,
+    api_tokens: Option<Arc<ApiTokens>>,
+    archive_fallback: Option<Arc<ArchiveFallback>>,
 }
 
 // Impl for common initialization and utilities
@@ -90,6 +112,8 @@ impl IndexerReader {
         let pool = diesel::r2d2::Pool::builder()
             .max_size(config.pool_size)
             .connection_timeout(config.connection_timeout)
+            .test_on_check_out(true)
+            .max_lifetime(Some(config.max_lifetime))
             .connection_customizer(Box::new(connection_config))
             .build(manager)
             .map_err(|e| anyhow!("Failed to initialize connection pool. Error: {:?}. If Error is None, please check whether the configured pool size (currently {}) exceeds the maximum number of connections allowed by the database.", e, config.pool_size))?;
@@ -97,9 +121,69 @@ impl IndexerReader {
         Ok(Self {
             pool,
             package_cache: Default::default(),
+            query_tier: QueryTier::default(),
+            api_tokens: None,
+            archive_fallback: None,
         })
     }
 
+    /// Reject filtered queries estimated to cost more than the budget of `query_tier`.
+    pub fn with_query_tier(mut self, query_tier: QueryTier) -> Self {
+        self.query_tier = query_tier;
+        self
+    }
+
+    /// Only serve clients holding one of `api_tokens` the data their token is scoped to.
+    pub fn with_api_tokens(mut self, api_tokens: ApiTokens) -> Self {
+        self.api_tokens = Some(Arc::new(api_tokens));
+        self
+    }
+
+    /// Serve checkpoints older than the lowest one in the DB, and their transactions, from the
+    /// archive of `archive_fallback`.
+    pub fn with_archive_fallback(mut self, archive_fallback: ArchiveFallback) -> Self {
+        self.archive_fallback = Some(Arc::new(archive_fallback));
+        self
+    }
+
+    /// What the client of the current request can see. Only known on the task serving the
+    /// request, so it must be called before moving to a blocking task.
+    fn visibility(&self) -> Result<Visibility, IndexerError> {
+        match &self.api_tokens {
+            None => Ok(Visibility::All),
+            Some(api_tokens) => api_tokens.visibility(current_api_token().as_deref()),
+        }
+    }
+
+    /// Check that the client of the current request may use admin methods, i.e. holds a full
+    /// access API token. Admin methods are disabled when no API tokens are configured. Like
+    /// [`Self::visibility`], it must be called before moving to a blocking task.
+    pub fn check_admin(&self) -> Result<(), IndexerError> {
+        if self.api_tokens.is_none() {
+            return Err(IndexerError::NotSupportedError(
+                "Admin methods require API tokens to be configured".to_string(),
+            ));
+        }
+        match self.visibility()? {
+            Visibility::All => Ok(()),
+            Visibility::Scoped(scope) => Err(IndexerError::Unauthorized(format!(
+                "API token of {} is not allowed to use admin methods",
+                scope.name
+            ))),
+        }
+    }
+
+    /// Check the estimated cost of `query`, returning up to `limit` rows, against the budget.
+    fn check_query_cost(&self, query: &str, limit: usize) -> Result<(), IndexerError> {
+        if self.query_tier.max_cost().is_none() {
+            return Ok(());
+        }
+        let cost = self.run_query(|conn| {
+            diesel::select(query_cost(query.to_string())).get_result::<f64>(conn)
+        })?;
+        self.query_tier.check(cost, limit)
+    }
+
     fn get_connection(&self) -> Result<PgPoolConnection, IndexerError> {
         self.pool.get().map_err(|e| {
             IndexerError::PgPoolConnectionError(format!(
@@ -219,16 +303,27 @@ impl IndexerReader {
         &self,
         object_id: ObjectID,
     ) -> Result<Option<Object>, IndexerError> {
-        self.spawn_blocking(move |this| this.get_object(&object_id, None))
-            .await
+        let visibility = self.visibility()?;
+        let object = self
+            .spawn_blocking(move |this| this.get_object(&object_id, None))
+            .await?;
+        Ok(object.filter(|object| visibility.object_visible(object)))
     }
 
     pub async fn get_object_read_in_blocking_task(
         &self,
         object_id: ObjectID,
     ) -> Result<ObjectRead, IndexerError> {
-        self.spawn_blocking(move |this| this.get_object_read(&object_id))
-            .await
+        let visibility = self.visibility()?;
+        match self
+            .spawn_blocking(move |this| this.get_object_read(&object_id))
+            .await?
+        {
+            ObjectRead::Exists(_, object, _) if !visibility.object_visible(&object) => {
+                Ok(ObjectRead::NotExists(object_id))
+            }
+            object_read => Ok(object_read),
+        }
     }
 
     fn get_object_read(&self, object_id: &ObjectID) -> Result<ObjectRead, IndexerError> {
@@ -248,6 +343,55 @@ impl IndexerReader {
         }
     }
 
+    /// The `Field` object of the dynamic field or dynamic object field of `parent_object_id`
+    /// named by the BCS bytes `name_bcs` of type `name_type`.
+    pub async fn get_dynamic_field_object_read_in_blocking_task(
+        &self,
+        parent_object_id: ObjectID,
+        name_type: TypeTag,
+        name_bcs: Vec<u8>,
+    ) -> Result<ObjectRead, IndexerError> {
+        let visibility = self.visibility()?;
+        match self
+            .spawn_blocking(move |this| {
+                this.get_dynamic_field_object_read(parent_object_id, &name_type, &name_bcs)
+            })
+            .await?
+        {
+            ObjectRead::Exists(object_ref, object, _) if !visibility.object_visible(&object) => {
+                Ok(ObjectRead::NotExists(object_ref.0))
+            }
+            object_read => Ok(object_read),
+        }
+    }
+
+    fn get_dynamic_field_object_read(
+        &self,
+        parent_object_id: ObjectID,
+        name_type: &TypeTag,
+        name_bcs: &[u8],
+    ) -> Result<ObjectRead, IndexerError> {
+        let key_id = dynamic_field_key_id(parent_object_id, name_type, name_bcs);
+        // Dynamic fields indexed before `df_key_id` was added are found by their own ID, which
+        // is the key ID for dynamic fields and derived from the wrapped name for object fields.
+        let object_field_id = derive_dynamic_object_field_id(parent_object_id, name_type, name_bcs)
+            .expect("deriving dynamic field id can't fail");
+        let stored_object = self.run_query(|conn| {
+            objects::dsl::objects
+                .filter(objects::dsl::df_key_id.eq(key_id.to_vec()).or(
+                    objects::dsl::object_id.eq_any(vec![key_id.to_vec(), object_field_id.to_vec()]),
+                ))
+                .first::<StoredObject>(conn)
+                .optional()
+        })?;
+
+        if let Some(object) = stored_object {
+            object.try_into_object_read(self)
+        } else {
+            Ok(ObjectRead::NotExists(key_id))
+        }
+    }
+
     fn get_package_from_db(
         &self,
         package_id: &ObjectID,
@@ -431,6 +575,48 @@ impl IndexerReader {
         Ok(Some(checkpoint))
     }
 
+    /// The checkpoint `checkpoint_id`, from the archive if it was pruned from the DB and the
+    /// reader has an archive fallback.
+    pub async fn get_checkpoint_in_blocking_task(
+        &self,
+        checkpoint_id: CheckpointId,
+    ) -> Result<Option<sui_json_rpc_types::Checkpoint>, IndexerError> {
+        let (checkpoint, archived_only) = self
+            .spawn_blocking(move |this| {
+                let checkpoint = this.get_checkpoint(checkpoint_id)?;
+                let archived_only = match (&checkpoint, checkpoint_id) {
+                    (None, CheckpointId::SequenceNumber(seq)) => this.is_archived_only(seq)?,
+                    _ => false,
+                };
+                Ok::<_, IndexerError>((checkpoint, archived_only))
+            })
+            .await?;
+        match (&self.archive_fallback, checkpoint_id) {
+            (Some(archive_fallback), CheckpointId::SequenceNumber(seq)) if archived_only => {
+                archive_fallback
+                    .get_checkpoint(seq)
+                    .await?
+                    .map(sui_json_rpc_types::Checkpoint::try_from)
+                    .transpose()
+            }
+            _ => Ok(checkpoint),
+        }
+    }
+
+    /// Whether the checkpoint `checkpoint_seq` is older than the lowest one in the DB, and can be
+    /// read from the archive fallback instead.
+    fn is_archived_only(&self, checkpoint_seq: u64) -> Result<bool, IndexerError> {
+        if self.archive_fallback.is_none() {
+            return Ok(false);
+        }
+        let lowest = self.run_query(|conn| {
+            checkpoints::table
+                .select(min(checkpoints::sequence_number))
+                .first::<Option<i64>>(conn)
+        })?;
+        Ok(lowest.map_or(false, |lowest| (checkpoint_seq as i64) < lowest))
+    }
+
     pub fn get_latest_checkpoint(&self) -> Result<sui_json_rpc_types::Checkpoint, IndexerError> {
         let stored_checkpoint = self.get_latest_checkpoint_from_db()?;
 
@@ -478,6 +664,87 @@ impl IndexerReader {
             .collect()
     }
 
+    /// Returns the sequence number of the latest checkpoint with a timestamp at or before
+    /// `timestamp_ms`, or `None` if every indexed checkpoint is later than that.
+    pub fn get_checkpoint_at_timestamp(
+        &self,
+        timestamp_ms: u64,
+    ) -> Result<Option<u64>, IndexerError> {
+        let sequence_number = self.run_query(|conn| {
+            checkpoint_timestamps::dsl::checkpoint_timestamps
+                .filter(checkpoint_timestamps::timestamp_ms.le(timestamp_ms as i64))
+                .order_by((
+                    checkpoint_timestamps::timestamp_ms.desc(),
+                    checkpoint_timestamps::sequence_number.desc(),
+                ))
+                .select(checkpoint_timestamps::sequence_number)
+                .first::<i64>(conn)
+                .optional()
+        })?;
+
+        Ok(sequence_number.map(|s| s as u64))
+    }
+
+    pub async fn get_checkpoint_at_timestamp_in_blocking_task(
+        &self,
+        timestamp_ms: u64,
+    ) -> Result<Option<u64>, IndexerError> {
+        self.spawn_blocking(move |this| this.get_checkpoint_at_timestamp(timestamp_ms))
+            .await
+    }
+
+    /// Returns the range of checkpoint sequence numbers whose timestamps fall within
+    /// `[start_timestamp_ms, end_timestamp_ms)`, or `None` if no checkpoint does.
+    pub fn get_checkpoint_range_for_timestamps(
+        &self,
+        start_timestamp_ms: u64,
+        end_timestamp_ms: u64,
+    ) -> Result<Option<CheckpointRange>, IndexerError> {
+        if start_timestamp_ms >= end_timestamp_ms {
+            return Ok(None);
+        }
+
+        let (first, last) = self.run_query(|conn| {
+            let first = checkpoint_timestamps::dsl::checkpoint_timestamps
+                .filter(checkpoint_timestamps::timestamp_ms.ge(start_timestamp_ms as i64))
+                .order_by((
+                    checkpoint_timestamps::timestamp_ms.asc(),
+                    checkpoint_timestamps::sequence_number.asc(),
+                ))
+                .select(checkpoint_timestamps::sequence_number)
+                .first::<i64>(conn)
+                .optional()?;
+            let last = checkpoint_timestamps::dsl::checkpoint_timestamps
+                .filter(checkpoint_timestamps::timestamp_ms.lt(end_timestamp_ms as i64))
+                .order_by((
+                    checkpoint_timestamps::timestamp_ms.desc(),
+                    checkpoint_timestamps::sequence_number.desc(),
+                ))
+                .select(checkpoint_timestamps::sequence_number)
+                .first::<i64>(conn)
+                .optional()?;
+            Ok::<_, diesel::result::Error>((first, last))
+        })?;
+
+        Ok(match (first, last) {
+            (Some(first), Some(last)) if first <= last => {
+                Some(CheckpointRange::inclusive(first as u64, last as u64))
+            }
+            _ => None,
+        })
+    }
+
+    pub async fn get_checkpoint_range_for_timestamps_in_blocking_task(
+        &self,
+        start_timestamp_ms: u64,
+        end_timestamp_ms: u64,
+    ) -> Result<Option<CheckpointRange>, IndexerError> {
+        self.spawn_blocking(move |this| {
+            this.get_checkpoint_range_for_timestamps(start_timestamp_ms, end_timestamp_ms)
+        })
+        .await
+    }
+
     fn get_transaction_effects_with_digest(
         &self,
         digest: TransactionDigest,
@@ -558,6 +825,7 @@ impl IndexerReader {
         cursor: Option<ObjectID>,
         limit: usize,
     ) -> Result<Vec<StoredObject>, IndexerError> {
+        self.visibility()?.check_address(&address)?;
         let object_types = Self::extract_struct_filters(filter)?;
         self.spawn_blocking(move |this| {
             this.get_owned_objects_impl(address, object_types, cursor, limit)
@@ -600,6 +868,7 @@ impl IndexerReader {
             let mut query = objects::dsl::objects
                 .filter(objects::dsl::owner_type.eq(OwnerType::Address as i16))
                 .filter(objects::dsl::owner_id.eq(address.to_vec()))
+                .order(objects::dsl::object_id.asc())
                 .limit(limit as i64)
                 .into_boxed();
             if let Some(object_types) = object_types {
@@ -620,10 +889,15 @@ impl IndexerReader {
         limit: usize,
         descending_order: bool,
     ) -> IndexerResult<Vec<SuiEvent>> {
-        self.spawn_blocking(move |this| {
-            this.query_events_impl(filter, cursor, limit, descending_order)
-        })
-        .await
+        let visibility = self.visibility()?;
+        visibility.check_event_filter(&filter)?;
+        let mut events = self
+            .spawn_blocking(move |this| {
+                this.query_events_impl(filter, cursor, limit, descending_order)
+            })
+            .await?;
+        events.retain(|event| visibility.event_visible(event));
+        Ok(events)
     }
 
     fn filter_object_id_with_type(
@@ -656,8 +930,11 @@ impl IndexerReader {
         &self,
         object_ids: Vec<ObjectID>,
     ) -> Result<Vec<StoredObject>, IndexerError> {
-        self.spawn_blocking(move |this| this.multi_get_objects_impl(object_ids))
-            .await
+        let visibility = self.visibility()?;
+        let objects = self
+            .spawn_blocking(move |this| this.multi_get_objects_impl(object_ids))
+            .await?;
+        visibility.retain_visible_objects(objects)
     }
 
     fn multi_get_objects_impl(
@@ -713,6 +990,26 @@ impl IndexerReader {
         limit: usize,
         is_descending: bool,
     ) -> IndexerResult<Vec<SuiTransactionBlockResponse>> {
+        self.visibility()?
+            .check_transaction_filter(filter.as_ref())?;
+        if let (Some(TransactionFilter::Checkpoint(seq)), Some(archive_fallback)) =
+            (&filter, &self.archive_fallback)
+        {
+            let seq = *seq;
+            if self
+                .spawn_blocking(move |this| this.is_archived_only(seq))
+                .await?
+            {
+                let stored_txes = archive_fallback
+                    .get_checkpoint_transactions(seq, cursor, limit, is_descending)
+                    .await?;
+                return self
+                    .spawn_blocking(move |this| {
+                        this.stored_transaction_to_transaction_block(stored_txes, options)
+                    })
+                    .await;
+            }
+        }
         self.spawn_blocking(move |this| {
             this.query_transaction_blocks_impl(filter, options, cursor, limit, is_descending)
         })
@@ -909,6 +1206,7 @@ impl IndexerReader {
         );
 
         tracing::debug!("query transaction blocks: {}", query);
+        self.check_query_cost(&query, limit)?;
 
         let tx_sequence_numbers = self
             .run_query(|conn| diesel::sql_query(query.clone()).load::<TxSequenceNumber>(conn))?
@@ -949,12 +1247,79 @@ impl IndexerReader {
         digests: Vec<TransactionDigest>,
         options: sui_json_rpc_types::SuiTransactionBlockResponseOptions,
     ) -> Result<Vec<sui_json_rpc_types::SuiTransactionBlockResponse>, IndexerError> {
+        let visibility = self.visibility()?;
         self.spawn_blocking(move |this| {
+            let digests = match visibility {
+                Visibility::All => digests,
+                Visibility::Scoped(scope) => {
+                    let visible = this.visible_transactions(&scope, &digests)?;
+                    digests
+                        .into_iter()
+                        .filter(|digest| visible.contains(digest))
+                        .collect()
+                }
+            };
             this.multi_get_transaction_block_response_impl(&digests, options)
         })
         .await
     }
 
+    /// The transactions of `digests` sent by or to the addresses of `scope`, or calling its
+    /// packages.
+    fn visible_transactions(
+        &self,
+        scope: &TokenScope,
+        digests: &[TransactionDigest],
+    ) -> Result<HashSet<TransactionDigest>, IndexerError> {
+        let digests = digests.iter().map(|d| d.inner().to_vec()).collect_vec();
+        let sequence_numbers: HashMap<i64, Vec<u8>> = self
+            .run_query(|conn| {
+                transactions::table
+                    .filter(transactions::transaction_digest.eq_any(digests))
+                    .select((
+                        transactions::tx_sequence_number,
+                        transactions::transaction_digest,
+                    ))
+                    .load::<(i64, Vec<u8>)>(conn)
+            })?
+            .into_iter()
+            .collect();
+        let tx_sequence_numbers = sequence_numbers.keys().copied().collect_vec();
+        let addresses = scope.addresses().map(|a| a.to_vec()).collect_vec();
+        let packages = scope.packages().map(|p| p.to_vec()).collect_vec();
+
+        let mut visible = self.run_query(|conn| {
+            tx_senders::table
+                .filter(tx_senders::tx_sequence_number.eq_any(tx_sequence_numbers.clone()))
+                .filter(tx_senders::sender.eq_any(addresses.clone()))
+                .select(tx_senders::tx_sequence_number)
+                .load::<i64>(conn)
+        })?;
+        visible.extend(self.run_query(|conn| {
+            tx_recipients::table
+                .filter(tx_recipients::tx_sequence_number.eq_any(tx_sequence_numbers.clone()))
+                .filter(tx_recipients::recipient.eq_any(addresses))
+                .select(tx_recipients::tx_sequence_number)
+                .load::<i64>(conn)
+        })?);
+        visible.extend(self.run_query(|conn| {
+            tx_calls::table
+                .filter(tx_calls::tx_sequence_number.eq_any(tx_sequence_numbers))
+                .filter(tx_calls::package.eq_any(packages))
+                .select(tx_calls::tx_sequence_number)
+                .load::<i64>(conn)
+        })?);
+
+        visible
+            .into_iter()
+            .filter_map(|seq| sequence_numbers.get(&seq))
+            .map(|digest| {
+                TransactionDigest::try_from(digest.as_slice())
+                    .map_err(|e| IndexerError::PersistentStorageDataCorruptionError(e.to_string()))
+            })
+            .collect()
+    }
+
     fn get_transaction_events_impl(
         &self,
         digest: TransactionDigest,
@@ -966,6 +1331,59 @@ impl IndexerReader {
                 .first::<(i64, Vec<Option<Vec<u8>>>)>(conn)
         })?;
 
+        self.stored_events_to_sui_events(digest, timestamp_ms, serialized_events)
+    }
+
+    fn multi_get_transaction_events_impl(
+        &self,
+        digests: &[TransactionDigest],
+    ) -> Result<Vec<Vec<sui_json_rpc_types::SuiEvent>>, IndexerError> {
+        let digest_bytes = digests
+            .iter()
+            .map(|digest| digest.into_inner().to_vec())
+            .collect::<Vec<_>>();
+        let rows = self.run_query(|conn| {
+            transactions::table
+                .filter(transactions::transaction_digest.eq_any(digest_bytes))
+                .select((
+                    transactions::transaction_digest,
+                    transactions::timestamp_ms,
+                    transactions::events,
+                ))
+                .load::<(Vec<u8>, i64, Vec<Option<Vec<u8>>>)>(conn)
+        })?;
+
+        let mut events_by_digest = HashMap::new();
+        for (digest, timestamp_ms, serialized_events) in rows {
+            let digest = TransactionDigest::try_from(digest.as_slice()).map_err(|e| {
+                IndexerError::PersistentStorageDataCorruptionError(format!(
+                    "Can't convert {:?} as tx_digest. Error: {e}",
+                    digest
+                ))
+            })?;
+            events_by_digest.insert(digest, (timestamp_ms, serialized_events));
+        }
+
+        digests
+            .iter()
+            .map(|digest| {
+                let (timestamp_ms, serialized_events) =
+                    events_by_digest.remove(digest).ok_or_else(|| {
+                        IndexerError::InvalidArgumentError(format!(
+                            "Transaction {digest} not found"
+                        ))
+                    })?;
+                self.stored_events_to_sui_events(*digest, timestamp_ms, serialized_events)
+            })
+            .collect()
+    }
+
+    fn stored_events_to_sui_events(
+        &self,
+        digest: TransactionDigest,
+        timestamp_ms: i64,
+        serialized_events: Vec<Option<Vec<u8>>>,
+    ) -> Result<Vec<sui_json_rpc_types::SuiEvent>, IndexerError> {
         let events = serialized_events
             .into_iter()
             .flatten()
@@ -1154,6 +1572,7 @@ impl IndexerReader {
             )
         };
         tracing::debug!("query events: {}", query);
+        self.check_query_cost(&query, limit)?;
         let stored_events =
             self.run_query(|conn| diesel::sql_query(query).load::<StoredEvent>(conn))?;
         stored_events
@@ -1166,8 +1585,26 @@ impl IndexerReader {
         &self,
         digest: TransactionDigest,
     ) -> Result<Vec<sui_json_rpc_types::SuiEvent>, IndexerError> {
-        self.spawn_blocking(move |this| this.get_transaction_events_impl(digest))
-            .await
+        let visibility = self.visibility()?;
+        let mut events = self
+            .spawn_blocking(move |this| this.get_transaction_events_impl(digest))
+            .await?;
+        events.retain(|event| visibility.event_visible(event));
+        Ok(events)
+    }
+
+    pub async fn multi_get_transaction_events_in_blocking_task(
+        &self,
+        digests: Vec<TransactionDigest>,
+    ) -> Result<Vec<Vec<sui_json_rpc_types::SuiEvent>>, IndexerError> {
+        let visibility = self.visibility()?;
+        let mut events = self
+            .spawn_blocking(move |this| this.multi_get_transaction_events_impl(&digests))
+            .await?;
+        for events in events.iter_mut() {
+            events.retain(|event| visibility.event_visible(event));
+        }
+        Ok(events)
     }
 
     pub async fn get_dynamic_fields_in_blocking_task(
@@ -1176,12 +1613,30 @@ impl IndexerReader {
         cursor: Option<ObjectID>,
         limit: usize,
     ) -> Result<Vec<DynamicFieldInfo>, IndexerError> {
+        let visibility = self.visibility()?;
         self.spawn_blocking(move |this| {
+            if !this.dynamic_fields_visible(&visibility, parent_object_id)? {
+                return Ok(vec![]);
+            }
             this.get_dynamic_fields_impl(parent_object_id, cursor, limit)
         })
         .await
     }
 
+    /// Whether the client can see the dynamic fields of `parent_object_id`, without reading the
+    /// parent if it can see everything.
+    fn dynamic_fields_visible(
+        &self,
+        visibility: &Visibility,
+        parent_object_id: ObjectID,
+    ) -> Result<bool, IndexerError> {
+        if let Visibility::All = visibility {
+            return Ok(true);
+        }
+        let parent = self.get_object(&parent_object_id, None)?;
+        Ok(visibility.dynamic_fields_visible(parent.as_ref()))
+    }
+
     fn get_dynamic_fields_impl(
         &self,
         parent_object_id: ObjectID,
@@ -1234,7 +1689,11 @@ impl IndexerReader {
         cursor: Option<ObjectID>,
         limit: usize,
     ) -> Result<Vec<StoredObject>, IndexerError> {
+        let visibility = self.visibility()?;
         self.spawn_blocking(move |this| {
+            if !this.dynamic_fields_visible(&visibility, parent_object_id)? {
+                return Ok(vec![]);
+            }
             this.get_dynamic_fields_raw(parent_object_id, cursor, limit)
         })
         .await
@@ -1254,7 +1713,7 @@ impl IndexerReader {
                 .limit(limit as i64)
                 .into_boxed();
             if let Some(object_cursor) = cursor {
-                query = query.filter(objects::dsl::object_id.ge(object_cursor.to_vec()));
+                query = query.filter(objects::dsl::object_id.gt(object_cursor.to_vec()));
             }
             query.load::<StoredObject>(conn)
         })?;
@@ -1346,6 +1805,7 @@ impl IndexerReader {
         cursor: ObjectID,
         limit: usize,
     ) -> Result<Vec<SuiCoin>, IndexerError> {
+        self.visibility()?.check_address(&owner)?;
         self.spawn_blocking(move |this| this.get_owned_coins(owner, coin_type, cursor, limit))
             .await
     }
@@ -1386,6 +1846,7 @@ impl IndexerReader {
         // If coin_type is None, look for all coins.
         coin_type: Option<String>,
     ) -> Result<Vec<Balance>, IndexerError> {
+        self.visibility()?.check_address(&owner)?;
         self.spawn_blocking(move |this| this.get_coin_balances(owner, coin_type))
             .await
     }
@@ -1509,6 +1970,356 @@ impl IndexerReader {
         Ok(stored_address_metrics.into())
     }
 
+    pub fn get_gas_price_history(
+        &self,
+        interval: GasPriceInterval,
+        cursor: Option<u64>,
+        limit: usize,
+        descending_order: bool,
+    ) -> IndexerResult<Vec<GasPriceHistory>> {
+        let stored_gas_price_metrics = self.run_query(|conn| {
+            let mut boxed_query = gas_price_metrics::table
+                .filter(gas_price_metrics::granularity.eq(granularity(interval)))
+                .into_boxed();
+            if let Some(cursor) = cursor {
+                if descending_order {
+                    boxed_query =
+                        boxed_query.filter(gas_price_metrics::start_timestamp_ms.lt(cursor as i64));
+                } else {
+                    boxed_query =
+                        boxed_query.filter(gas_price_metrics::start_timestamp_ms.gt(cursor as i64));
+                }
+            }
+            if descending_order {
+                boxed_query = boxed_query.order_by(gas_price_metrics::start_timestamp_ms.desc());
+            } else {
+                boxed_query = boxed_query.order_by(gas_price_metrics::start_timestamp_ms.asc());
+            }
+
+            boxed_query
+                .limit(limit as i64)
+                .load::<StoredGasPriceMetrics>(conn)
+        })?;
+        Ok(stored_gas_price_metrics
+            .into_iter()
+            .map(GasPriceHistory::from)
+            .collect())
+    }
+
+    /// Register `addresses` for a backfill of their history before the earliest indexed
+    /// checkpoint. This is the only write made through the reader.
+    pub fn register_watchlist_addresses(
+        &self,
+        addresses: Vec<SuiAddress>,
+    ) -> IndexerResult<Vec<WatchlistBackfillProgress>> {
+        blocking_call_is_ok_or_panic();
+
+        let registered_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after the epoch")
+            .as_millis() as i64;
+        let mut connection = self.get_connection()?;
+        connection
+            .build_transaction()
+            .run(|conn| {
+                // Nothing to backfill if the DB is empty: the history will be indexed as usual.
+                let end_checkpoint = checkpoints::table
+                    .select(min(checkpoints::sequence_number))
+                    .first::<Option<i64>>(conn)?
+                    .unwrap_or(0);
+                let stored_addresses = addresses
+                    .iter()
+                    .map(|address| StoredWatchlistAddress {
+                        address: address.to_vec(),
+                        end_checkpoint,
+                        next_checkpoint: 0,
+                        transactions_found: 0,
+                        registered_at_ms,
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(watchlist_addresses::table)
+                    .values(&stored_addresses)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+            })
+            .map_err(|e| IndexerError::PostgresWriteError(e.to_string()))?;
+        self.get_watchlist_backfill_progress(addresses)
+    }
+
+    pub fn get_watchlist_backfill_progress(
+        &self,
+        addresses: Vec<SuiAddress>,
+    ) -> IndexerResult<Vec<WatchlistBackfillProgress>> {
+        let addresses = addresses
+            .into_iter()
+            .map(|address| address.to_vec())
+            .collect::<Vec<_>>();
+        let stored_addresses = self.run_query(|conn| {
+            watchlist_addresses::table
+                .filter(watchlist_addresses::address.eq_any(addresses))
+                .order_by(watchlist_addresses::address.asc())
+                .load::<StoredWatchlistAddress>(conn)
+        })?;
+        stored_addresses
+            .into_iter()
+            .map(WatchlistBackfillProgress::try_from)
+            .collect()
+    }
+
+    /// Request a reindex of the rows of `package` in the checkpoints from `start_checkpoint` to
+    /// `end_checkpoint` exclusive, which must all be indexed already.
+    pub fn request_package_reindex(
+        &self,
+        package: ObjectID,
+        start_checkpoint: u64,
+        end_checkpoint: u64,
+    ) -> IndexerResult<PackageReindexJob> {
+        blocking_call_is_ok_or_panic();
+
+        let latest_checkpoint = self
+            .run_query(|conn| {
+                checkpoints::table
+                    .select(max(checkpoints::sequence_number))
+                    .first::<Option<i64>>(conn)
+            })?
+            .ok_or_else(|| {
+                IndexerError::InvalidArgumentError("No checkpoint is indexed yet".to_string())
+            })? as u64;
+        if start_checkpoint >= end_checkpoint || end_checkpoint > latest_checkpoint + 1 {
+            return Err(IndexerError::InvalidArgumentError(format!(
+                "Invalid checkpoint range {start_checkpoint}..{end_checkpoint} to reindex, the latest indexed checkpoint is {latest_checkpoint}"
+            )));
+        }
+
+        let requested_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after the epoch")
+            .as_millis() as i64;
+        let mut connection = self.get_connection()?;
+        let stored_job = connection
+            .build_transaction()
+            .run(|conn| {
+                diesel::insert_into(package_reindex_jobs::table)
+                    .values((
+                        package_reindex_jobs::package.eq(package.to_vec()),
+                        package_reindex_jobs::start_checkpoint.eq(start_checkpoint as i64),
+                        package_reindex_jobs::end_checkpoint.eq(end_checkpoint as i64),
+                        package_reindex_jobs::next_checkpoint.eq(start_checkpoint as i64),
+                        package_reindex_jobs::transactions_reindexed.eq(0),
+                        package_reindex_jobs::objects_reindexed.eq(0),
+                        package_reindex_jobs::requested_at_ms.eq(requested_at_ms),
+                    ))
+                    .get_result::<StoredPackageReindexJob>(conn)
+            })
+            .map_err(|e| IndexerError::PostgresWriteError(e.to_string()))?;
+        stored_job.try_into()
+    }
+
+    /// Reindex jobs of `package`, latest first.
+    pub fn get_package_reindex_jobs(
+        &self,
+        package: ObjectID,
+    ) -> IndexerResult<Vec<PackageReindexJob>> {
+        let stored_jobs = self.run_query(|conn| {
+            package_reindex_jobs::table
+                .filter(package_reindex_jobs::package.eq(package.to_vec()))
+                .order_by(package_reindex_jobs::id.desc())
+                .load::<StoredPackageReindexJob>(conn)
+        })?;
+        stored_jobs
+            .into_iter()
+            .map(PackageReindexJob::try_from)
+            .collect()
+    }
+
+    /// Purge `address` from every column tagged with a retention class, or only count its rows
+    /// if `dry_run`, and record the report of the purge in the same transaction.
+    pub fn purge_address_data(
+        &self,
+        address: SuiAddress,
+        dry_run: bool,
+    ) -> IndexerResult<PurgeReport> {
+        blocking_call_is_ok_or_panic();
+
+        let requested_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after the epoch")
+            .as_millis() as i64;
+        let mut connection = self.get_connection()?;
+        let stored_report = connection
+            .build_transaction()
+            .run(|conn| {
+                let columns = retention::purge_address(conn, address, dry_run)?;
+                let columns = serde_json::to_string(&columns)
+                    .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+                diesel::insert_into(purge_reports::table)
+                    .values((
+                        purge_reports::address.eq(address.to_vec()),
+                        purge_reports::dry_run.eq(dry_run),
+                        purge_reports::requested_at_ms.eq(requested_at_ms),
+                        purge_reports::columns.eq(columns),
+                    ))
+                    .get_result::<StoredPurgeReport>(conn)
+            })
+            .map_err(|e| IndexerError::PostgresWriteError(e.to_string()))?;
+        stored_report.try_into()
+    }
+
+    /// Reports of the purges of `address`, latest first.
+    pub fn get_purge_reports(&self, address: SuiAddress) -> IndexerResult<Vec<PurgeReport>> {
+        let stored_reports = self.run_query(|conn| {
+            purge_reports::table
+                .filter(purge_reports::address.eq(address.to_vec()))
+                .order_by(purge_reports::id.desc())
+                .load::<StoredPurgeReport>(conn)
+        })?;
+        stored_reports
+            .into_iter()
+            .map(PurgeReport::try_from)
+            .collect()
+    }
+
+    pub async fn get_transaction_dependency_graph_in_blocking_task(
+        &self,
+        root: TransactionDigest,
+        direction: TransactionDependencyDirection,
+        max_depth: u32,
+        max_edges: usize,
+    ) -> IndexerResult<TransactionDependencyGraph> {
+        let visibility = self.visibility()?;
+        self.spawn_blocking(move |this| {
+            let mut graph =
+                this.get_transaction_dependency_graph(root, direction, max_depth, max_edges)?;
+            if let Visibility::Scoped(scope) = visibility {
+                let transactions = std::iter::once(graph.root)
+                    .chain(
+                        graph
+                            .edges
+                            .iter()
+                            .flat_map(|edge| [edge.transaction, edge.dependency]),
+                    )
+                    .unique()
+                    .collect_vec();
+                let visible = this.visible_transactions(&scope, &transactions)?;
+                retain_visible_dependencies(&mut graph, &visible);
+            }
+            Ok(graph)
+        })
+        .await
+    }
+
+    /// Dependency graph of the transaction `root`, walked breadth first up to `max_depth` edges
+    /// away from it and `max_edges` edges in total.
+    fn get_transaction_dependency_graph(
+        &self,
+        root: TransactionDigest,
+        direction: TransactionDependencyDirection,
+        max_depth: u32,
+        max_edges: usize,
+    ) -> IndexerResult<TransactionDependencyGraph> {
+        let mut edges = vec![];
+        let mut truncated = false;
+        if direction != TransactionDependencyDirection::Descendants {
+            truncated |=
+                self.collect_dependency_edges(root, true, max_depth, max_edges, &mut edges)?;
+        }
+        if direction != TransactionDependencyDirection::Ancestors {
+            truncated |=
+                self.collect_dependency_edges(root, false, max_depth, max_edges, &mut edges)?;
+        }
+        Ok(TransactionDependencyGraph {
+            root,
+            edges,
+            truncated,
+        })
+    }
+
+    /// Add the edges to the ancestors or the descendants of `root` to `edges`, returning whether
+    /// some were left out by the limits.
+    fn collect_dependency_edges(
+        &self,
+        root: TransactionDigest,
+        ancestors: bool,
+        max_depth: u32,
+        max_edges: usize,
+        edges: &mut Vec<TransactionDependencyEdge>,
+    ) -> IndexerResult<bool> {
+        let mut visited = HashSet::from([root]);
+        let mut frontier = vec![root];
+        for depth in 1..=max_depth {
+            if frontier.is_empty() {
+                return Ok(false);
+            }
+            let remaining = max_edges.saturating_sub(edges.len());
+            // One more edge than fits, to tell whether any is left out.
+            let rows = self.load_dependency_edges(&frontier, ancestors, remaining + 1)?;
+            let mut next = vec![];
+            for (transaction, dependency) in rows.iter().copied().take(remaining) {
+                let reached = if ancestors { dependency } else { transaction };
+                if visited.insert(reached) {
+                    next.push(reached);
+                }
+                edges.push(TransactionDependencyEdge {
+                    transaction,
+                    dependency,
+                    depth,
+                });
+            }
+            if rows.len() > remaining {
+                return Ok(true);
+            }
+            frontier = next;
+        }
+        Ok(!frontier.is_empty()
+            && !self
+                .load_dependency_edges(&frontier, ancestors, 1)?
+                .is_empty())
+    }
+
+    /// Edges from the transactions of `frontier` to their dependencies if `ancestors`, otherwise
+    /// from the transactions depending on them, as `(transaction, dependency)` pairs.
+    fn load_dependency_edges(
+        &self,
+        frontier: &[TransactionDigest],
+        ancestors: bool,
+        limit: usize,
+    ) -> IndexerResult<Vec<(TransactionDigest, TransactionDigest)>> {
+        let frontier = frontier
+            .iter()
+            .map(|digest| digest.into_inner().to_vec())
+            .collect::<Vec<_>>();
+        let rows = self.run_query(|conn| {
+            let query = tx_dependencies::table
+                .select((
+                    tx_dependencies::transaction_digest,
+                    tx_dependencies::dependency,
+                ))
+                .order_by((
+                    tx_dependencies::transaction_digest.asc(),
+                    tx_dependencies::dependency.asc(),
+                ))
+                .limit(limit as i64)
+                .into_boxed();
+            let query = if ancestors {
+                query.filter(tx_dependencies::transaction_digest.eq_any(frontier))
+            } else {
+                query.filter(tx_dependencies::dependency.eq_any(frontier))
+            };
+            query.load::<(Vec<u8>, Vec<u8>)>(conn)
+        })?;
+        let to_digest = |digest: Vec<u8>| {
+            TransactionDigest::try_from(digest.as_slice()).map_err(|e| {
+                IndexerError::PersistentStorageDataCorruptionError(format!(
+                    "Can't convert {:?} as tx_digest. Error: {e}",
+                    digest
+                ))
+            })
+        };
+        rows.into_iter()
+            .map(|(transaction, dependency)| Ok((to_digest(transaction)?, to_digest(dependency)?)))
+            .collect()
+    }
+
     pub fn get_all_epoch_address_metrics(
         &self,
         descending_order: Option<bool>,
@@ -1591,18 +2402,60 @@ impl IndexerReader {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 struct PackageCache {
-    inner: Arc<RwLock<BTreeMap<ObjectID, MovePackage>>>,
+    inner: Arc<PackageCacheInner>,
+}
+
+struct PackageCacheInner {
+    packages: RwLock<BTreeMap<ObjectID, MovePackage>>,
+    memory_account: Option<MemoryAccount>,
+}
+
+impl Default for PackageCache {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new_cyclic(|inner: &Weak<PackageCacheInner>| PackageCacheInner {
+                packages: Default::default(),
+                memory_account: register_cache("indexer_package_cache", 1, inner.clone()),
+            }),
+        }
+    }
 }
 
 impl PackageCache {
     fn insert(&self, object_id: ObjectID, package: MovePackage) {
-        self.inner.write().unwrap().insert(object_id, package);
+        let size = package.size();
+        let replaced = self
+            .inner
+            .packages
+            .write()
+            .unwrap()
+            .insert(object_id, package);
+        if let Some(account) = &self.inner.memory_account {
+            if let Some(replaced) = replaced {
+                account.release(replaced.size());
+            }
+            account.charge(size);
+        }
     }
 
     fn get(&self, object_id: &ObjectID) -> Option<MovePackage> {
-        self.inner.read().unwrap().get(object_id).cloned()
+        self.inner.packages.read().unwrap().get(object_id).cloned()
+    }
+}
+
+impl EvictableCache for PackageCacheInner {
+    fn evict(&self, bytes: usize) -> usize {
+        let mut packages = self.packages.write().unwrap();
+        let mut freed = 0;
+        while freed < bytes {
+            let Some((_, package)) = packages.pop_first() else {
+                break;
+            };
+            freed += package.size();
+        }
+        freed
     }
 }
 
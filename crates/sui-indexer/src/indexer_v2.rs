@@ -1,28 +1,41 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::api_tokens::ApiTokens;
 use crate::apis::{
     CoinReadApiV2, ExtendedApiV2, GovernanceReadApiV2, IndexerApiV2, MoveUtilsApiV2, ReadApiV2,
     TransactionBuilderApiV2, WriteApi,
 };
+use crate::archive_fallback::ArchiveFallback;
+use crate::balance_watchdog::{BalanceWatchdog, BalanceWatchdogMetrics};
+use crate::clock::IndexerClock;
 use crate::errors::IndexerError;
 use crate::indexer_reader::IndexerReader;
 use crate::metrics::IndexerMetrics;
-use crate::IndexerConfig;
+use crate::package_reindex::PackageReindexer;
+use crate::{read_object_store_config, IndexerConfig};
 use anyhow::Result;
-use mysten_metrics::spawn_monitored_task;
+use mysten_common::service::{RestartPolicy, ServiceBuilder};
 use prometheus::Registry;
 use std::env;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use sui_json_rpc::ServerType;
 use sui_json_rpc::{JsonRpcServerBuilder, ServerHandle};
 use tokio::runtime::Handle;
-use tracing::info;
+use tokio::sync::watch;
+use tracing::{info, warn};
 
 use crate::framework::fetcher::CheckpointFetcher;
+use crate::framework::replay::replay_committed_checkpoints;
+use crate::framework::Handler;
 use crate::handlers::checkpoint_handler_v2::new_handlers;
+use crate::handlers::committer::start_tx_checkpoint_commit_task;
+use crate::handlers::tx_processor::IndexingPackageCache;
 use crate::processors_v2::processor_orchestrator_v2::ProcessorOrchestratorV2;
 use crate::store::{IndexerStoreV2, PgIndexerAnalyticalStore};
+use crate::warehouse_export::{WarehouseExportConfig, WarehouseExporter};
+use crate::watchlist_backfill::WatchlistBackfill;
 
 pub struct IndexerV2;
 
@@ -60,18 +73,174 @@ impl IndexerV2 {
             last_seq_from_db,
             downloaded_checkpoint_data_sender,
         );
-        spawn_monitored_task!(fetcher.run());
 
-        let checkpoint_handler = new_handlers(store, metrics, config).await?;
+        let watchlist_backfill = match &config.watchlist_archive_config {
+            Some(path) => {
+                let archive_store_config = read_object_store_config(path, "watchlist archive")?;
+                let pool = crate::new_pg_connection_pool(&config.get_db_url()?)?;
+                Some(Arc::new(WatchlistBackfill::new(
+                    pool,
+                    archive_store_config,
+                    metrics.clone(),
+                )?))
+            }
+            None => None,
+        };
 
-        crate::framework::runner::run(
-            mysten_metrics::metered_channel::ReceiverStream::new(
-                downloaded_checkpoint_data_receiver,
-            ),
-            vec![Box::new(checkpoint_handler)],
-        )
-        .await;
+        let warehouse_exporter = match &config.warehouse_export_config {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path).map_err(|e| {
+                    IndexerError::InvalidArgumentError(format!(
+                        "Failed to read warehouse export config {}: {e}",
+                        path.display()
+                    ))
+                })?;
+                let export_config: WarehouseExportConfig = serde_yaml::from_str(&contents)
+                    .map_err(|e| {
+                        IndexerError::InvalidArgumentError(format!(
+                            "Failed to parse warehouse export config {}: {e}",
+                            path.display()
+                        ))
+                    })?;
+                let pool = crate::new_pg_connection_pool(&config.get_db_url()?)?;
+                Some(Arc::new(WarehouseExporter::new(
+                    pool,
+                    export_config,
+                    metrics.clone(),
+                )?))
+            }
+            None => None,
+        };
 
+        let package_reindexer = match &config.package_reindex_store_config {
+            Some(path) => {
+                let checkpoint_store_config =
+                    read_object_store_config(path, "package reindex store")?;
+                let pool = crate::new_pg_connection_pool(&config.get_db_url()?)?;
+                Some(Arc::new(
+                    PackageReindexer::new(
+                        pool,
+                        store.clone(),
+                        checkpoint_store_config,
+                        metrics.clone(),
+                    )
+                    .with_schema_write_mode(config.schema_write_mode)
+                    .with_bcs_compression(config.bcs_compression()?),
+                ))
+            }
+            None => None,
+        };
+
+        let (commit_notifier, commit_watcher) = watch::channel(None);
+        let clock = IndexerClock::new(config.backfill_mode);
+        let package_cache = IndexingPackageCache::new();
+        let (checkpoint_handler, indexed_checkpoint_receiver) =
+            new_handlers(store.clone(), metrics.clone(), package_cache.clone()).await?;
+
+        // Components are stopped in reverse order: once the fetcher stops, the pipeline and then
+        // the commit task drain what was already downloaded, as their input channels close.
+        // Only the components without in-flight checkpoints are restarted when they panic, a
+        // panic in the others stops the writer, to resume from the last committed checkpoint.
+        let replay_checkpoints = config.startup_replay_checkpoints;
+        let replay_store_config = config.startup_replay_store_config()?;
+        let config = config.clone();
+        let gc_clock = clock.clone();
+        let mut service = ServiceBuilder::new("indexer-writer")
+            .with_restart_counter(metrics.task_restarts.clone());
+        if let Some(watchlist_backfill) = watchlist_backfill {
+            service = service.supervised_component(
+                "watchlist-backfill",
+                &[],
+                RestartPolicy::default(),
+                move |context| {
+                    let watchlist_backfill = watchlist_backfill.clone();
+                    context.run_until_stopped(async move { watchlist_backfill.run_forever().await })
+                },
+            );
+        }
+        if let Some(warehouse_exporter) = warehouse_exporter {
+            service = service.supervised_component(
+                "warehouse-export",
+                &[],
+                RestartPolicy::default(),
+                move |context| {
+                    let warehouse_exporter = warehouse_exporter.clone();
+                    context.run_until_stopped(async move { warehouse_exporter.run_forever().await })
+                },
+            );
+        }
+        if let Some(package_reindexer) = package_reindexer {
+            service = service.supervised_component(
+                "package-reindex",
+                &[],
+                RestartPolicy::default(),
+                move |context| {
+                    let package_reindexer = package_reindexer.clone();
+                    context.run_until_stopped(async move { package_reindexer.run_forever().await })
+                },
+            );
+        }
+        let service = service
+            .component("checkpoint-commit", &[], move |context| async move {
+                context.set_ready();
+                start_tx_checkpoint_commit_task(
+                    store,
+                    metrics,
+                    config,
+                    clock,
+                    indexed_checkpoint_receiver,
+                    commit_notifier,
+                )
+                .await;
+                Ok(())
+            })
+            .supervised_component(
+                "package-cache-gc",
+                &["checkpoint-commit"],
+                RestartPolicy::default(),
+                move |context| {
+                    context.run_until_stopped(IndexingPackageCache::remove_committed(
+                        package_cache.clone(),
+                        commit_watcher.clone(),
+                        gc_clock.clone(),
+                    ))
+                },
+            )
+            .component(
+                "checkpoint-pipeline",
+                &["checkpoint-commit", "package-cache-gc"],
+                move |context| async move {
+                    let mut handlers: Vec<Box<dyn Handler>> = vec![Box::new(checkpoint_handler)];
+                    // The fetcher only starts once the committed checkpoints are replayed.
+                    if let Some(store_config) = replay_store_config {
+                        replay_committed_checkpoints(
+                            store_config,
+                            last_seq_from_db,
+                            replay_checkpoints,
+                            &mut handlers,
+                        )
+                        .await?;
+                    }
+                    context.set_ready();
+                    crate::framework::runner::run(
+                        mysten_metrics::metered_channel::ReceiverStream::new(
+                            downloaded_checkpoint_data_receiver,
+                        ),
+                        handlers,
+                    )
+                    .await;
+                    Ok(())
+                },
+            )
+            .component(
+                "checkpoint-fetcher",
+                &["checkpoint-pipeline"],
+                move |context| context.run_until_stopped(fetcher.run()),
+            )
+            .start()
+            .await?;
+
+        service.run_until(shutdown_signal()).await?;
         Ok(())
     }
 
@@ -84,14 +253,38 @@ impl IndexerV2 {
             "Sui indexerV2 Reader (version {:?}) started...",
             env!("CARGO_PKG_VERSION")
         );
-        let indexer_reader = IndexerReader::new(db_url)?;
+        let mut indexer_reader =
+            IndexerReader::new(db_url.clone())?.with_query_tier(config.query_tier);
+        if let Some(path) = &config.api_tokens_config {
+            indexer_reader = indexer_reader.with_api_tokens(ApiTokens::from_file(path)?);
+        }
+        if let Some(path) = &config.archive_fallback_config {
+            let archive_store_config = read_object_store_config(path, "archive fallback")?;
+            indexer_reader = indexer_reader
+                .with_archive_fallback(ArchiveFallback::new(&db_url, archive_store_config)?);
+        }
+        let mut service = ServiceBuilder::new("indexer-reader");
+        if config.balance_watchdog {
+            let watchdog = BalanceWatchdog::new(
+                indexer_reader.clone(),
+                crate::get_http_client(config.rpc_client_url.as_str())?,
+                BalanceWatchdogMetrics::new(registry),
+            );
+            service = service.component("balance-watchdog", &[], move |context| {
+                context.run_until_stopped(watchdog.run_forever())
+            });
+        }
         let handle = build_json_rpc_server(registry, indexer_reader, config, None)
             .await
             .expect("Json rpc server should not run into errors upon start.");
-        tokio::spawn(async move { handle.stopped().await })
-            .await
-            .expect("Rpc server task failed");
+        let service = service
+            .component("json-rpc-server", &[], move |context| {
+                context.run_until_stopped(handle.stopped())
+            })
+            .start()
+            .await?;
 
+        service.run_until(shutdown_signal()).await?;
         Ok(())
     }
 
@@ -108,6 +301,13 @@ impl IndexerV2 {
     }
 }
 
+async fn shutdown_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Failed to listen for the shutdown signal: {e}");
+        futures::future::pending::<()>().await;
+    }
+}
+
 pub async fn build_json_rpc_server(
     prometheus_registry: &Registry,
     reader: IndexerReader,
@@ -4,10 +4,11 @@
 
 use std::env;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::{collections::HashMap, time::Duration};
 
 use anyhow::{anyhow, Result};
-use axum::{extract::Extension, http::StatusCode, routing::get, Router};
+use axum::{extract::Extension, http::StatusCode, routing::get, Json, Router};
 use clap::Parser;
 use diesel::pg::PgConnection;
 use diesel::r2d2::ConnectionManager;
@@ -15,6 +16,7 @@ use jsonrpsee::http_client::{HeaderMap, HeaderValue, HttpClient, HttpClientBuild
 use metrics::IndexerMetrics;
 use prometheus::{Registry, TextEncoder};
 use regex::Regex;
+use serde::Serialize;
 use tokio::runtime::Handle;
 use tracing::{info, warn};
 use url::Url;
@@ -26,15 +28,23 @@ use apis::{
 use errors::IndexerError;
 use mysten_metrics::{spawn_monitored_task, RegistryService};
 use processors::processor_orchestrator::ProcessorOrchestrator;
-use store::IndexerStore;
+use query_budget::QueryTier;
+use store::{IndexerStore, SchemaWriteMode};
 use sui_json_rpc::{JsonRpcServerBuilder, ServerHandle, ServerType, CLIENT_SDK_TYPE_HEADER};
+use sui_protocol_config::ProtocolVersion;
 use sui_sdk::{SuiClient, SuiClientBuilder};
+use sui_storage::object_store::ObjectStoreConfig;
 
 use crate::apis::MoveUtilsApi;
 use crate::framework::IndexerBuilder;
 use crate::handlers::checkpoint_handler::new_handlers;
 
+pub mod api_tokens;
 pub mod apis;
+pub mod archive_fallback;
+pub mod balance_watchdog;
+pub mod clock;
+pub mod doctor;
 pub mod errors;
 pub mod framework;
 mod handlers;
@@ -43,20 +53,43 @@ pub mod indexer_v2;
 pub mod metrics;
 pub mod models;
 pub mod models_v2;
+pub mod package_reindex;
 pub mod processors;
 pub mod processors_v2;
+pub mod query_budget;
+pub mod retention;
 pub mod schema;
 pub mod schema_v2;
+pub mod sqlite_export;
 pub mod store;
 pub mod test_utils;
 pub mod types;
 pub mod types_v2;
 pub mod utils;
+pub mod warehouse_export;
+pub mod warehouse_restore;
+pub mod watchlist_backfill;
 
 pub type PgConnectionPool = diesel::r2d2::Pool<ConnectionManager<PgConnection>>;
 pub type PgPoolConnection = diesel::r2d2::PooledConnection<ConnectionManager<PgConnection>>;
 
 const METRICS_ROUTE: &str = "/metrics";
+const BUILD_INFO_ROUTE: &str = "/build-info";
+const GIT_REVISION: &str = {
+    if let Some(revision) = option_env!("GIT_REVISION") {
+        revision
+    } else {
+        let version = git_version::git_version!(
+            args = ["--always", "--abbrev=12", "--dirty", "--exclude", "*"],
+            fallback = ""
+        );
+
+        if version.is_empty() {
+            panic!("unable to query git revision");
+        }
+        version
+    }
+};
 /// Returns all endpoints for which we have implemented on the indexer,
 /// some of them are not validated yet.
 /// NOTE: we only use this for integration testing
@@ -118,6 +151,64 @@ pub struct IndexerConfig {
     pub skip_db_commit: bool,
     #[clap(long)]
     pub use_v2: bool,
+    /// Continuously compare balances of sampled addresses against the fullnode's, reporting
+    /// divergences in metrics. Only used by the v2 rpc server worker.
+    #[clap(long)]
+    pub balance_watchdog: bool,
+    /// Upper bound, in bytes, on the memory used by in-memory caches together.
+    #[clap(long)]
+    pub memory_budget_bytes: Option<usize>,
+    /// Path of a YAML object store config of the checkpoint archive, to backfill the history of
+    /// watchlist addresses from. Only used by the v2 writer.
+    #[clap(long)]
+    pub watchlist_archive_config: Option<PathBuf>,
+    /// Path of a YAML config of the export of transactions and events to files staged in an
+    /// object store for a warehouse to load. Only used by the v2 writer.
+    #[clap(long)]
+    pub warehouse_export_config: Option<PathBuf>,
+    /// Also write the old form of tables and columns changed in this release, while readers of
+    /// the previous release are still running. Only used by the v2 writer.
+    #[clap(long, value_enum, default_value_t = SchemaWriteMode::Current)]
+    pub schema_write_mode: SchemaWriteMode,
+    /// Compress large BCS columns, i.e. the transaction data, effects and object contents, with
+    /// zstd as they are written. Rows written either way can be read by this release, but not by
+    /// the previous one, so it can't be combined with `--schema-write-mode dual`. Only used by
+    /// the v2 writer.
+    #[clap(long)]
+    pub compress_bcs_columns: bool,
+    /// Tier of the clients of the rpc server, setting the budget of the estimated cost of
+    /// filtered queries. Only used by the v2 rpc server worker.
+    #[clap(long, value_enum, default_value_t = QueryTier::Unlimited)]
+    pub query_tier: QueryTier,
+    /// Path of a YAML config of the API tokens allowed to read from the rpc server, each scoped
+    /// to packages and addresses. All data is public when unset. Only used by the v2 rpc server
+    /// worker.
+    #[clap(long)]
+    pub api_tokens_config: Option<PathBuf>,
+    /// Path of a YAML object store config of the checkpoint archive, to serve checkpoints older
+    /// than the lowest one in the DB, and their transactions, from. Only used by the v2 rpc
+    /// server worker.
+    #[clap(long)]
+    pub archive_fallback_config: Option<PathBuf>,
+    /// Path of a YAML object store config of the checkpoint files to reindex packages from, on
+    /// request of the `suix_requestPackageReindex` method. Only used by the v2 writer.
+    #[clap(long)]
+    pub package_reindex_store_config: Option<PathBuf>,
+    /// Drive the time-based logic of the writer, i.e. the deadlines of commit batches and the GC
+    /// of the package cache, off checkpoint timestamps rather than the wall clock, so that
+    /// reprocessing history commits the same batches however fast it goes. Only used by the v2
+    /// writer.
+    #[clap(long)]
+    pub backfill_mode: bool,
+    /// Number of the last committed checkpoints to replay at startup, before new checkpoints are
+    /// processed, for the handlers to rebuild the in-memory state derived from them.
+    #[clap(long, default_value_t = 0)]
+    pub startup_replay_checkpoints: u64,
+    /// Path of a YAML object store config of the checkpoint files to replay the last committed
+    /// checkpoints from, required with `--startup-replay-checkpoints`, as the full node may have
+    /// pruned them.
+    #[clap(long)]
+    pub startup_replay_store_config: Option<PathBuf>,
 }
 
 impl IndexerConfig {
@@ -151,6 +242,51 @@ impl IndexerConfig {
             _ => Err(anyhow!("Invalid db connection config, either db_url or (db_user_name, db_password, db_host, db_port, db_name) must be provided")),
         }
     }
+
+    /// Whether to compress large BCS columns. Rejected in `Dual` schema write mode, since readers
+    /// of the previous release may still be running and can't read compressed rows.
+    pub fn bcs_compression(&self) -> Result<bool, IndexerError> {
+        if self.compress_bcs_columns && self.schema_write_mode == SchemaWriteMode::Dual {
+            return Err(IndexerError::InvalidArgumentError(
+                "--compress-bcs-columns can't be combined with --schema-write-mode dual"
+                    .to_string(),
+            ));
+        }
+        Ok(self.compress_bcs_columns)
+    }
+
+    /// Store of the checkpoint files replayed at startup, if any checkpoints are replayed.
+    pub fn startup_replay_store_config(&self) -> Result<Option<ObjectStoreConfig>, IndexerError> {
+        if self.startup_replay_checkpoints == 0 {
+            return Ok(None);
+        }
+        let Some(path) = &self.startup_replay_store_config else {
+            return Err(IndexerError::InvalidArgumentError(
+                "--startup-replay-store-config is required with --startup-replay-checkpoints"
+                    .to_string(),
+            ));
+        };
+        read_object_store_config(path, "startup replay store").map(Some)
+    }
+}
+
+/// Read the YAML object store config at `path`, of the `name` store in errors.
+pub(crate) fn read_object_store_config(
+    path: &Path,
+    name: &str,
+) -> Result<ObjectStoreConfig, IndexerError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        IndexerError::InvalidArgumentError(format!(
+            "Failed to read {name} config {}: {e}",
+            path.display()
+        ))
+    })?;
+    serde_yaml::from_str(&contents).map_err(|e| {
+        IndexerError::InvalidArgumentError(format!(
+            "Failed to parse {name} config {}: {e}",
+            path.display()
+        ))
+    })
 }
 
 impl Default for IndexerConfig {
@@ -174,6 +310,19 @@ impl Default for IndexerConfig {
             analytical_worker: false,
             skip_db_commit: false,
             use_v2: false,
+            balance_watchdog: false,
+            memory_budget_bytes: None,
+            watchlist_archive_config: None,
+            warehouse_export_config: None,
+            schema_write_mode: SchemaWriteMode::Current,
+            compress_bcs_columns: false,
+            query_tier: QueryTier::Unlimited,
+            api_tokens_config: None,
+            archive_fallback_config: None,
+            package_reindex_store_config: None,
+            backfill_mode: false,
+            startup_replay_checkpoints: 0,
+            startup_replay_store_config: None,
         }
     }
 }
@@ -218,13 +367,16 @@ impl Indexer {
 
             let (checkpoint_handler, object_handler) = new_handlers(store, metrics, config);
 
-            IndexerBuilder::new()
+            let mut builder = IndexerBuilder::new()
                 .last_downloaded_checkpoint(last_downloaded_checkpoint)
                 .rest_url(&config.rpc_client_url)
                 .handler(checkpoint_handler)
-                .handler(object_handler)
-                .run()
-                .await;
+                .handler(object_handler);
+            if let Some(store_config) = config.startup_replay_store_config()? {
+                builder =
+                    builder.replay_checkpoints(config.startup_replay_checkpoints, store_config);
+            }
+            builder.run().await;
         }
 
         Ok(())
@@ -280,6 +432,10 @@ pub fn new_pg_connection_pool_impl(
     diesel::r2d2::Pool::builder()
         .max_size(pool_size)
         .connection_timeout(pool_config.connection_timeout)
+        // Connections are checked before being handed out, so that the ones broken by a failover
+        // are replaced, and recycled periodically, so that they follow DNS changes of the primary.
+        .test_on_check_out(true)
+        .max_lifetime(Some(pool_config.max_lifetime))
         .connection_customizer(Box::new(pool_config.connection_config()))
         .build(manager)
         .map_err(|e| {
@@ -295,12 +451,14 @@ pub struct PgConnectionPoolConfig {
     pool_size: u32,
     connection_timeout: Duration,
     statement_timeout: Duration,
+    max_lifetime: Duration,
 }
 
 impl PgConnectionPoolConfig {
     const DEFAULT_POOL_SIZE: u32 = 100;
     const DEFAULT_CONNECTION_TIMEOUT: u64 = 30;
     const DEFAULT_STATEMENT_TIMEOUT: u64 = 30;
+    const DEFAULT_MAX_LIFETIME: u64 = 300;
 
     fn connection_config(&self) -> PgConnectionConfig {
         PgConnectionConfig {
@@ -320,6 +478,10 @@ impl PgConnectionPoolConfig {
     pub fn set_statement_timeout(&mut self, timeout: Duration) {
         self.statement_timeout = timeout;
     }
+
+    pub fn set_max_lifetime(&mut self, max_lifetime: Duration) {
+        self.max_lifetime = max_lifetime;
+    }
 }
 
 impl Default for PgConnectionPoolConfig {
@@ -336,11 +498,16 @@ impl Default for PgConnectionPoolConfig {
             .ok()
             .and_then(|s| s.parse::<u64>().ok())
             .unwrap_or(Self::DEFAULT_STATEMENT_TIMEOUT);
+        let max_lifetime_secs = std::env::var("DB_CONNECTION_MAX_LIFETIME")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(Self::DEFAULT_MAX_LIFETIME);
 
         Self {
             pool_size: db_pool_size,
             connection_timeout: Duration::from_secs(conn_timeout_secs),
             statement_timeout: Duration::from_secs(statement_timeout_secs),
+            max_lifetime: Duration::from_secs(max_lifetime_secs),
         }
     }
 }
@@ -427,6 +594,7 @@ fn convert_url(url_str: &str) -> Option<String> {
 pub fn start_prometheus_server(
     addr: SocketAddr,
     fn_url: &str,
+    use_v2: bool,
 ) -> Result<(RegistryService, Registry), anyhow::Error> {
     let converted_fn_url = convert_url(fn_url);
     if converted_fn_url.is_none() {
@@ -441,9 +609,11 @@ pub fn start_prometheus_server(
     info!("Starting prometheus server with labels: {:?}", labels);
     let registry = Registry::new_custom(Some("indexer".to_string()), Some(labels))?;
     let registry_service = RegistryService::new(registry.clone());
+    let build_info = BuildInfo::new(use_v2)?;
 
     let app = Router::new()
         .route(METRICS_ROUTE, get(metrics))
+        .route(BUILD_INFO_ROUTE, get(|| async move { Json(build_info) }))
         .layer(Extension(registry_service.clone()));
 
     tokio::spawn(async move {
@@ -455,6 +625,43 @@ pub fn start_prometheus_server(
     Ok((registry_service, registry))
 }
 
+/// Build of this indexer, served for fleet tooling to spot instances that drifted from the rest.
+#[derive(Clone, Serialize)]
+struct BuildInfo {
+    version: &'static str,
+    git_revision: &'static str,
+    /// `debug` or `release`
+    profile: &'static str,
+    /// Cargo features the indexer was built with
+    features: Vec<&'static str>,
+    min_supported_protocol_version: u64,
+    max_supported_protocol_version: u64,
+    /// Version of the latest migration of the schema the indexer writes
+    schema_version: String,
+}
+
+impl BuildInfo {
+    fn new(use_v2: bool) -> Result<Self, anyhow::Error> {
+        let mut features = vec![];
+        if cfg!(feature = "pg_integration") {
+            features.push("pg_integration");
+        }
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_revision: GIT_REVISION,
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+            features,
+            min_supported_protocol_version: ProtocolVersion::MIN.as_u64(),
+            max_supported_protocol_version: ProtocolVersion::MAX.as_u64(),
+            schema_version: utils::schema_version(use_v2)?,
+        })
+    }
+}
+
 async fn metrics(Extension(registry_service): Extension<RegistryService>) -> (StatusCode, String) {
     let metrics_families = registry_service.gather_all();
     match TextEncoder.encode_to_string(&metrics_families) {
@@ -13,6 +13,7 @@ use sui_indexer::store::PgIndexerStore;
 use sui_indexer::store::PgIndexerStoreV2;
 use sui_indexer::utils::reset_database;
 use sui_indexer::{get_pg_pool_connection, new_pg_connection_pool, Indexer, IndexerConfig};
+use sui_storage::memory_budget::init_memory_budget;
 
 #[tokio::main]
 async fn main() -> Result<(), IndexerError> {
@@ -33,10 +34,14 @@ async fn main() -> Result<(), IndexerError> {
         .parse()
         .unwrap(),
         indexer_config.rpc_client_url.as_str(),
+        indexer_config.use_v2,
     )?;
     let indexer_metrics = IndexerMetrics::new(&registry);
 
     mysten_metrics::init_metrics(&registry);
+    if let Some(memory_budget_bytes) = indexer_config.memory_budget_bytes {
+        init_memory_budget(memory_budget_bytes);
+    }
 
     let db_url = indexer_config.get_db_url().map_err(|e| {
         IndexerError::PgPoolConnectionError(format!(
@@ -91,7 +96,9 @@ async fn main() -> Result<(), IndexerError> {
     if indexer_config.use_v2 {
         info!("Use v2");
         if indexer_config.fullnode_sync_worker {
-            let store = PgIndexerStoreV2::new(blocking_cp, indexer_metrics.clone());
+            let store = PgIndexerStoreV2::new(blocking_cp, indexer_metrics.clone())
+                .with_schema_write_mode(indexer_config.schema_write_mode)
+                .with_bcs_compression(indexer_config.bcs_compression()?);
             return IndexerV2::start_writer(&indexer_config, store, indexer_metrics).await;
         } else if indexer_config.rpc_server_worker {
             return IndexerV2::start_reader(&indexer_config, &registry, db_url).await;
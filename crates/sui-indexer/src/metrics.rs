@@ -2,8 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use prometheus::{
-    register_histogram_with_registry, register_int_counter_with_registry,
-    register_int_gauge_with_registry, Histogram, IntCounter, IntGauge, Registry,
+    register_histogram_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry, register_int_gauge_with_registry, Histogram, IntCounter,
+    IntCounterVec, IntGauge, Registry,
 };
 
 /// Prometheus metrics for sui-indexer.
@@ -82,6 +83,7 @@ pub struct IndexerMetrics {
     pub get_checkpoint_latency: Histogram,
     pub get_checkpoints_latency: Histogram,
     pub get_events_latency: Histogram,
+    pub multi_get_events_latency: Histogram,
     pub get_loaded_child_objects_latency: Histogram,
     pub get_total_transaction_blocks_latency: Histogram,
     pub get_latest_checkpoint_sequence_number_latency: Histogram,
@@ -98,6 +100,14 @@ pub struct IndexerMetrics {
 
     pub address_processor_failure: IntCounter,
     pub checkpoint_metrics_processor_failure: IntCounter,
+    pub watchlist_backfill_checkpoints_scanned: IntCounter,
+    pub watchlist_backfill_transactions_found: IntCounter,
+    pub package_reindex_checkpoints_scanned: IntCounter,
+    pub package_reindex_transactions_reindexed: IntCounter,
+    pub task_restarts: IntCounterVec,
+    pub warehouse_export_rows: IntCounterVec,
+    pub warehouse_export_retries: IntCounter,
+    pub warehouse_export_retry_budget_exhausted: IntCounter,
 }
 
 impl IndexerMetrics {
@@ -469,6 +479,13 @@ impl IndexerMetrics {
                 registry
             )
             .unwrap(),
+            multi_get_events_latency: register_histogram_with_registry!(
+                "multi_get_events_latency",
+                "Time spent in multi_get_events on the fullnode behind.",
+                LATENCY_SEC_BUCKETS.to_vec(),
+                registry
+            )
+            .unwrap(),
             get_total_transaction_blocks_latency: register_histogram_with_registry!(
                 "get_total_transaction_blocks_latency",
                 "Time spent in get_total_transaction_blocks on the fullnode behind.",
@@ -554,6 +571,56 @@ impl IndexerMetrics {
                 registry,
             )
             .unwrap(),
+            watchlist_backfill_checkpoints_scanned: register_int_counter_with_registry!(
+                "watchlist_backfill_checkpoints_scanned",
+                "Total number of archived checkpoints scanned for watchlist addresses",
+                registry,
+            )
+            .unwrap(),
+            watchlist_backfill_transactions_found: register_int_counter_with_registry!(
+                "watchlist_backfill_transactions_found",
+                "Total number of archived transactions found for watchlist addresses",
+                registry,
+            )
+            .unwrap(),
+            package_reindex_checkpoints_scanned: register_int_counter_with_registry!(
+                "package_reindex_checkpoints_scanned",
+                "Total number of checkpoint files scanned to reindex packages",
+                registry,
+            )
+            .unwrap(),
+            package_reindex_transactions_reindexed: register_int_counter_with_registry!(
+                "package_reindex_transactions_reindexed",
+                "Total number of transactions rewritten by the reindex of packages",
+                registry,
+            )
+            .unwrap(),
+            task_restarts: register_int_counter_vec_with_registry!(
+                "task_restarts",
+                "Total number of restarts of background tasks after panicking",
+                &["task"],
+                registry,
+            )
+            .unwrap(),
+            warehouse_export_rows: register_int_counter_vec_with_registry!(
+                "warehouse_export_rows",
+                "Total number of rows exported to the warehouse staging area",
+                &["table"],
+                registry,
+            )
+            .unwrap(),
+            warehouse_export_retries: register_int_counter_with_registry!(
+                "warehouse_export_retries",
+                "Total number of storage and DB requests retried by the warehouse export",
+                registry,
+            )
+            .unwrap(),
+            warehouse_export_retry_budget_exhausted: register_int_counter_with_registry!(
+                "warehouse_export_retry_budget_exhausted",
+                "Total number of ranges whose export failed after exhausting their retry budget",
+                registry,
+            )
+            .unwrap(),
         }
     }
 }
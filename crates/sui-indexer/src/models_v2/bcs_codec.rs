@@ -0,0 +1,86 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compression of the large BCS columns, i.e. the transaction data and effects of transactions
+//! and the contents of objects. The codec is tagged per row in `bcs_codec`, so compressed and
+//! uncompressed rows can be mixed, and rows are decompressed as they are read.
+
+use crate::errors::IndexerError;
+use crate::types_v2::IndexerResult;
+
+/// BCS columns smaller than this in total are not worth compressing, and stay uncompressed.
+pub const MIN_COMPRESSED_BCS_BYTES: usize = 1024;
+
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(i16)]
+pub enum BcsCodec {
+    #[default]
+    Uncompressed = 0,
+    Zstd = 1,
+}
+
+impl TryFrom<i16> for BcsCodec {
+    type Error = IndexerError;
+
+    fn try_from(codec: i16) -> Result<Self, Self::Error> {
+        match codec {
+            0 => Ok(BcsCodec::Uncompressed),
+            1 => Ok(BcsCodec::Zstd),
+            _ => Err(IndexerError::PersistentStorageDataCorruptionError(format!(
+                "Unknown bcs codec {codec}"
+            ))),
+        }
+    }
+}
+
+impl BcsCodec {
+    /// Codec to compress BCS columns of `len` bytes in total with.
+    pub fn for_len(len: usize) -> Self {
+        if len >= MIN_COMPRESSED_BCS_BYTES {
+            BcsCodec::Zstd
+        } else {
+            BcsCodec::Uncompressed
+        }
+    }
+
+    pub fn compress(self, bytes: Vec<u8>) -> IndexerResult<Vec<u8>> {
+        match self {
+            BcsCodec::Uncompressed => Ok(bytes),
+            BcsCodec::Zstd => zstd::encode_all(bytes.as_slice(), ZSTD_LEVEL).map_err(|e| {
+                IndexerError::SerdeError(format!("Failed to compress bcs column: {e}"))
+            }),
+        }
+    }
+
+    pub fn decompress(self, bytes: Vec<u8>) -> IndexerResult<Vec<u8>> {
+        match self {
+            BcsCodec::Uncompressed => Ok(bytes),
+            BcsCodec::Zstd => zstd::decode_all(bytes.as_slice()).map_err(|e| {
+                IndexerError::PersistentStorageDataCorruptionError(format!(
+                    "Failed to decompress bcs column: {e}"
+                ))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bcs_codec_round_trip() {
+        let bytes = vec![7u8; 4 * MIN_COMPRESSED_BCS_BYTES];
+        for codec in [BcsCodec::Uncompressed, BcsCodec::Zstd] {
+            let compressed = codec.compress(bytes.clone()).unwrap();
+            assert_eq!(codec.decompress(compressed).unwrap(), bytes);
+            assert_eq!(BcsCodec::try_from(codec as i16).unwrap(), codec);
+        }
+        assert!(BcsCodec::Zstd.compress(bytes.clone()).unwrap().len() < bytes.len());
+        assert_eq!(BcsCodec::for_len(bytes.len()), BcsCodec::Zstd);
+        assert_eq!(BcsCodec::for_len(16), BcsCodec::Uncompressed);
+        assert!(BcsCodec::try_from(2).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use diesel::prelude::*;
+
+use crate::schema_v2::checkpoint_timestamps;
+use crate::types_v2::IndexedCheckpoint;
+
+#[derive(Queryable, Insertable, Debug, Clone, Default)]
+#[diesel(table_name = checkpoint_timestamps)]
+pub struct StoredCheckpointTimestamp {
+    pub sequence_number: i64,
+    pub epoch: i64,
+    pub timestamp_ms: i64,
+    pub network_total_transactions: i64,
+}
+
+impl From<&IndexedCheckpoint> for StoredCheckpointTimestamp {
+    fn from(c: &IndexedCheckpoint) -> Self {
+        Self {
+            sequence_number: c.sequence_number as i64,
+            epoch: c.epoch as i64,
+            timestamp_ms: c.timestamp_ms as i64,
+            network_total_transactions: c.network_total_transactions as i64,
+        }
+    }
+}
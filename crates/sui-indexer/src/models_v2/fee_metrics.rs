@@ -0,0 +1,18 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use diesel::prelude::*;
+
+use crate::schema_v2::epoch_fee_metrics;
+
+/// Fees and rebates of all the transactions of an epoch.
+#[derive(Clone, Debug, Default, Queryable, Insertable)]
+#[diesel(table_name = epoch_fee_metrics)]
+pub struct StoredEpochFeeMetrics {
+    pub epoch: i64,
+    pub total_transactions: i64,
+    pub computation_cost: i64,
+    pub storage_cost: i64,
+    pub storage_rebate: i64,
+    pub non_refundable_storage_fee: i64,
+}
@@ -0,0 +1,61 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use diesel::prelude::*;
+
+use sui_json_rpc_types::{GasPriceHistory, GasPriceInterval, GasPricePercentiles};
+
+use crate::schema_v2::gas_price_metrics;
+
+pub const HOUR_MS: i64 = 60 * 60 * 1000;
+
+#[derive(Clone, Debug, Queryable, Insertable)]
+#[diesel(table_name = gas_price_metrics)]
+pub struct StoredGasPriceMetrics {
+    pub granularity: i16,
+    pub start_timestamp_ms: i64,
+    pub epoch: i64,
+    pub reference_gas_price: i64,
+    pub total_transactions: i64,
+    pub p25: Option<i64>,
+    pub p50: Option<i64>,
+    pub p75: Option<i64>,
+    pub p90: Option<i64>,
+    pub p99: Option<i64>,
+}
+
+/// Value of the `granularity` column for rows summarizing `interval`s.
+pub fn granularity(interval: GasPriceInterval) -> i16 {
+    match interval {
+        GasPriceInterval::Epoch => 0,
+        GasPriceInterval::Hour => 1,
+    }
+}
+
+impl From<StoredGasPriceMetrics> for GasPriceHistory {
+    fn from(metrics: StoredGasPriceMetrics) -> Self {
+        let percentiles = match (
+            metrics.p25,
+            metrics.p50,
+            metrics.p75,
+            metrics.p90,
+            metrics.p99,
+        ) {
+            (Some(p25), Some(p50), Some(p75), Some(p90), Some(p99)) => Some(GasPricePercentiles {
+                p25: p25 as u64,
+                p50: p50 as u64,
+                p75: p75 as u64,
+                p90: p90 as u64,
+                p99: p99 as u64,
+            }),
+            _ => None,
+        };
+        Self {
+            start_timestamp_ms: metrics.start_timestamp_ms as u64,
+            epoch: metrics.epoch as u64,
+            reference_gas_price: metrics.reference_gas_price as u64,
+            total_transactions: metrics.total_transactions as u64,
+            percentiles,
+        }
+    }
+}
@@ -2,14 +2,22 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod address_metrics;
+pub mod bcs_codec;
+pub mod checkpoint_timestamps;
 pub mod checkpoints;
 pub mod display;
 pub mod epoch;
 pub mod events;
+pub mod fee_metrics;
+pub mod gas_price_metrics;
 pub mod move_call_metrics;
 pub mod network_metrics;
 pub mod objects;
+pub mod package_reindex;
 pub mod packages;
+pub mod purge_reports;
 pub mod transactions;
 pub mod tx_count_metrics;
 pub mod tx_indices;
+pub mod warehouse_exports;
+pub mod watchlist;
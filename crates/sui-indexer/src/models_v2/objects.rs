@@ -4,6 +4,8 @@
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 
+use diesel::deserialize;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use move_bytecode_utils::module_cache::GetModule;
 use sui_json_rpc_types::{Balance, Coin as SuiCoin};
@@ -14,8 +16,9 @@ use sui_types::object::Object;
 use sui_types::object::ObjectRead;
 
 use crate::errors::IndexerError;
+use crate::models_v2::bcs_codec::BcsCodec;
 use crate::schema_v2::objects;
-use crate::types_v2::IndexedObject;
+use crate::types_v2::{IndexedObject, IndexerResult};
 
 #[derive(Queryable)]
 pub struct DynamicFieldColumn {
@@ -38,7 +41,9 @@ pub struct ObjectRefColumn {
 // NOTE: please add updating statement like below in pg_indexer_store_v2.rs,
 // if new columns are added here:
 // objects::epoch.eq(excluded(objects::epoch))
-#[derive(Queryable, Insertable, Debug, Identifiable, Clone, QueryableByName)]
+/// A row of `objects`. `serialized_object` is decompressed as the row is read, and only
+/// compressed, per `bcs_codec`, by [`StoredObject::compress_bcs`] before it is inserted.
+#[derive(Insertable, Debug, Identifiable, Clone)]
 #[diesel(table_name = objects, primary_key(object_id))]
 pub struct StoredObject {
     pub object_id: Vec<u8>,
@@ -57,6 +62,70 @@ pub struct StoredObject {
     pub df_name: Option<Vec<u8>>,
     pub df_object_type: Option<String>,
     pub df_object_id: Option<Vec<u8>>,
+    pub bcs_codec: i16,
+    pub df_key_id: Option<Vec<u8>>,
+}
+
+type StoredObjectRow = (
+    Vec<u8>,
+    i64,
+    Vec<u8>,
+    i64,
+    i16,
+    Option<Vec<u8>>,
+    Option<String>,
+    Vec<u8>,
+    Option<String>,
+    Option<i64>,
+    Option<i16>,
+    Option<Vec<u8>>,
+    Option<String>,
+    Option<Vec<u8>>,
+    i16,
+    Option<Vec<u8>>,
+);
+
+impl Queryable<objects::SqlType, Pg> for StoredObject {
+    type Row = StoredObjectRow;
+
+    fn build(row: Self::Row) -> deserialize::Result<Self> {
+        let (
+            object_id,
+            object_version,
+            object_digest,
+            checkpoint_sequence_number,
+            owner_type,
+            owner_id,
+            object_type,
+            serialized_object,
+            coin_type,
+            coin_balance,
+            df_kind,
+            df_name,
+            df_object_type,
+            df_object_id,
+            bcs_codec,
+            df_key_id,
+        ) = row;
+        Ok(StoredObject {
+            object_id,
+            object_version,
+            object_digest,
+            checkpoint_sequence_number,
+            owner_type,
+            owner_id,
+            object_type,
+            serialized_object: BcsCodec::try_from(bcs_codec)?.decompress(serialized_object)?,
+            coin_type,
+            coin_balance,
+            df_kind,
+            df_name,
+            df_object_type,
+            df_object_id,
+            bcs_codec,
+            df_key_id,
+        })
+    }
 }
 
 #[derive(Queryable, Insertable, Debug, Identifiable, Clone, QueryableByName)]
@@ -88,6 +157,8 @@ impl From<IndexedObject> for StoredObject {
             df_name: o.df_info.as_ref().map(|n| bcs::to_bytes(&n.name).unwrap()),
             df_object_type: o.df_info.as_ref().map(|v| v.object_type.clone()),
             df_object_id: o.df_info.as_ref().map(|v| v.object_id.to_vec()),
+            bcs_codec: BcsCodec::Uncompressed as i16,
+            df_key_id: o.df_key_id.map(|id| id.to_vec()),
         }
     }
 }
@@ -106,6 +177,14 @@ impl TryFrom<StoredObject> for Object {
 }
 
 impl StoredObject {
+    /// Compress `serialized_object`, if it is large enough, for inserting the row.
+    pub fn compress_bcs(mut self) -> IndexerResult<Self> {
+        let codec = BcsCodec::for_len(self.serialized_object.len());
+        self.serialized_object = codec.compress(self.serialized_object)?;
+        self.bcs_codec = codec as i16;
+        Ok(self)
+    }
+
     pub fn try_into_object_read(
         self,
         module_cache: &impl GetModule,
@@ -0,0 +1,52 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use diesel::prelude::*;
+
+use sui_json_rpc_types::PackageReindexJob;
+use sui_types::base_types::ObjectID;
+
+use crate::errors::IndexerError;
+use crate::schema_v2::package_reindex_jobs;
+
+#[derive(Clone, Debug, Queryable)]
+#[diesel(table_name = package_reindex_jobs)]
+pub struct StoredPackageReindexJob {
+    pub id: i64,
+    pub package: Vec<u8>,
+    pub start_checkpoint: i64,
+    pub end_checkpoint: i64,
+    pub next_checkpoint: i64,
+    pub transactions_reindexed: i64,
+    pub objects_reindexed: i64,
+    pub requested_at_ms: i64,
+}
+
+impl StoredPackageReindexJob {
+    pub fn is_completed(&self) -> bool {
+        self.next_checkpoint >= self.end_checkpoint
+    }
+}
+
+impl TryFrom<StoredPackageReindexJob> for PackageReindexJob {
+    type Error = IndexerError;
+
+    fn try_from(stored: StoredPackageReindexJob) -> Result<Self, Self::Error> {
+        let package = ObjectID::from_bytes(&stored.package).map_err(|e| {
+            IndexerError::PersistentStorageDataCorruptionError(format!(
+                "Failed to parse reindexed package: {:?}, error: {}",
+                stored.package, e
+            ))
+        })?;
+        Ok(Self {
+            id: stored.id as u64,
+            package,
+            start_checkpoint: stored.start_checkpoint as u64,
+            end_checkpoint: stored.end_checkpoint as u64,
+            next_checkpoint: stored.next_checkpoint as u64,
+            transactions_reindexed: stored.transactions_reindexed as u64,
+            objects_reindexed: stored.objects_reindexed as u64,
+            completed: stored.is_completed(),
+        })
+    }
+}
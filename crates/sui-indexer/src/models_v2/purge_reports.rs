@@ -0,0 +1,47 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use diesel::prelude::*;
+
+use sui_json_rpc_types::PurgeReport;
+use sui_types::base_types::SuiAddress;
+
+use crate::errors::IndexerError;
+use crate::schema_v2::purge_reports;
+
+#[derive(Clone, Debug, Queryable)]
+#[diesel(table_name = purge_reports)]
+pub struct StoredPurgeReport {
+    pub id: i64,
+    pub address: Vec<u8>,
+    pub dry_run: bool,
+    pub requested_at_ms: i64,
+    /// JSON array of the purged columns
+    pub columns: String,
+}
+
+impl TryFrom<StoredPurgeReport> for PurgeReport {
+    type Error = IndexerError;
+
+    fn try_from(stored: StoredPurgeReport) -> Result<Self, Self::Error> {
+        let address = SuiAddress::from_bytes(&stored.address).map_err(|e| {
+            IndexerError::PersistentStorageDataCorruptionError(format!(
+                "Failed to parse purged address: {:?}, error: {}",
+                stored.address, e
+            ))
+        })?;
+        let columns = serde_json::from_str(&stored.columns).map_err(|e| {
+            IndexerError::PersistentStorageDataCorruptionError(format!(
+                "Failed to parse columns of purge report {}, error: {}",
+                stored.id, e
+            ))
+        })?;
+        Ok(Self {
+            id: stored.id as u64,
+            address,
+            dry_run: stored.dry_run,
+            requested_at_ms: stored.requested_at_ms as u64,
+            columns,
+        })
+    }
+}
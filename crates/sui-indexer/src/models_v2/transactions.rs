@@ -1,5 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
+use diesel::deserialize;
+use diesel::pg::Pg;
 use diesel::prelude::*;
 
 use move_bytecode_utils::module_cache::GetModule;
@@ -16,14 +18,19 @@ use sui_types::effects::TransactionEffectsAPI;
 use sui_types::effects::TransactionEvents;
 use sui_types::event::Event;
 use sui_types::transaction::SenderSignedData;
+use sui_types::transaction::TransactionDataAPI;
 
 use crate::errors::IndexerError;
+use crate::models_v2::bcs_codec::BcsCodec;
 use crate::schema_v2::transactions;
-use crate::types_v2::IndexedObjectChange;
 use crate::types_v2::IndexedTransaction;
 use crate::types_v2::IndexerResult;
+use crate::types_v2::{sort_balance_changes, sort_object_changes, IndexedObjectChange};
 
-#[derive(Clone, Debug, Queryable, Insertable, QueryableByName)]
+/// A row of `transactions`. `raw_transaction` and `raw_effects` are decompressed as the row is
+/// read, and only compressed, per `bcs_codec`, by [`StoredTransaction::compress_bcs`] before it
+/// is inserted.
+#[derive(Clone, Debug, Insertable)]
 #[diesel(table_name = transactions)]
 pub struct StoredTransaction {
     pub tx_sequence_number: i64,
@@ -37,6 +44,78 @@ pub struct StoredTransaction {
     pub events: Vec<Option<Vec<u8>>>,
     pub transaction_kind: i16,
     pub success_command_count: i16,
+    pub gas_price: Option<i64>,
+    pub computation_cost: Option<i64>,
+    pub storage_cost: Option<i64>,
+    pub storage_rebate: Option<i64>,
+    pub non_refundable_storage_fee: Option<i64>,
+    pub bcs_codec: i16,
+}
+
+type StoredTransactionRow = (
+    i64,
+    Vec<u8>,
+    Vec<u8>,
+    Vec<u8>,
+    i64,
+    i64,
+    Vec<Option<Vec<u8>>>,
+    Vec<Option<Vec<u8>>>,
+    Vec<Option<Vec<u8>>>,
+    i16,
+    i16,
+    Option<i64>,
+    Option<i64>,
+    Option<i64>,
+    Option<i64>,
+    Option<i64>,
+    i16,
+);
+
+impl Queryable<transactions::SqlType, Pg> for StoredTransaction {
+    type Row = StoredTransactionRow;
+
+    fn build(row: Self::Row) -> deserialize::Result<Self> {
+        let (
+            tx_sequence_number,
+            transaction_digest,
+            raw_transaction,
+            raw_effects,
+            checkpoint_sequence_number,
+            timestamp_ms,
+            object_changes,
+            balance_changes,
+            events,
+            transaction_kind,
+            success_command_count,
+            gas_price,
+            computation_cost,
+            storage_cost,
+            storage_rebate,
+            non_refundable_storage_fee,
+            bcs_codec,
+        ) = row;
+        let codec = BcsCodec::try_from(bcs_codec)?;
+        Ok(StoredTransaction {
+            tx_sequence_number,
+            transaction_digest,
+            raw_transaction: codec.decompress(raw_transaction)?,
+            raw_effects: codec.decompress(raw_effects)?,
+            checkpoint_sequence_number,
+            timestamp_ms,
+            object_changes,
+            balance_changes,
+            events,
+            transaction_kind,
+            success_command_count,
+            gas_price,
+            computation_cost,
+            storage_cost,
+            storage_rebate,
+            non_refundable_storage_fee,
+            bcs_codec,
+        })
+    }
 }
 
 #[derive(Clone, Debug, Queryable)]
@@ -68,6 +147,7 @@ impl From<&IndexedTransaction> for StoredTransaction {
             .execution_parts()
             .0
             .num_commands();
+        let gas_cost_summary = tx.effects.gas_cost_summary();
 
         StoredTransaction {
             tx_sequence_number: tx.tx_sequence_number as i64,
@@ -93,11 +173,26 @@ impl From<&IndexedTransaction> for StoredTransaction {
             timestamp_ms: tx.timestamp_ms as i64,
             transaction_kind: tx.transaction_kind.clone() as i16,
             success_command_count: tx.effects.status().is_ok() as i16 * cmd_count as i16,
+            gas_price: Some(tx.sender_signed_data.intent_message().value.gas_price() as i64),
+            computation_cost: Some(gas_cost_summary.computation_cost as i64),
+            storage_cost: Some(gas_cost_summary.storage_cost as i64),
+            storage_rebate: Some(gas_cost_summary.storage_rebate as i64),
+            non_refundable_storage_fee: Some(gas_cost_summary.non_refundable_storage_fee as i64),
+            bcs_codec: BcsCodec::Uncompressed as i16,
         }
     }
 }
 
 impl StoredTransaction {
+    /// Compress the BCS columns, if they are large enough, for inserting the row.
+    pub fn compress_bcs(mut self) -> IndexerResult<Self> {
+        let codec = BcsCodec::for_len(self.raw_transaction.len() + self.raw_effects.len());
+        self.raw_transaction = codec.compress(self.raw_transaction)?;
+        self.raw_effects = codec.compress(self.raw_effects)?;
+        self.bcs_codec = codec as i16;
+        Ok(self)
+    }
+
     pub fn try_into_sui_transaction_block_response(
         self,
         options: &SuiTransactionBlockResponseOptions,
@@ -162,26 +257,28 @@ impl StoredTransaction {
         };
 
         let object_changes = if options.show_object_changes {
-            let object_changes = self.object_changes.into_iter().map(|object_change| {
+            let mut object_changes = self.object_changes.into_iter().map(|object_change| {
                 match object_change {
                     Some(object_change) => {
                         let object_change: IndexedObjectChange = bcs::from_bytes(&object_change)
                             .map_err(|e| IndexerError::PersistentStorageDataCorruptionError(
                                 format!("Can't convert object_change bytes into IndexedObjectChange. tx_digest={:?} Error: {e}", tx_digest)
                             ))?;
-                        Ok(ObjectChange::from(object_change))
+                        Ok(object_change)
                     }
                     None => Err(IndexerError::PersistentStorageDataCorruptionError(format!("object_change should not be null, tx_digest={:?}", tx_digest))),
                 }
-            }).collect::<Result<Vec<ObjectChange>, IndexerError>>()?;
+            }).collect::<Result<Vec<IndexedObjectChange>, IndexerError>>()?;
+            // Transactions indexed by earlier versions may have been stored in another order.
+            sort_object_changes(&mut object_changes);
 
-            Some(object_changes)
+            Some(object_changes.into_iter().map(ObjectChange::from).collect())
         } else {
             None
         };
 
         let balance_changes = if options.show_balance_changes {
-            let balance_changes = self.balance_changes.into_iter().map(|balance_change| {
+            let mut balance_changes = self.balance_changes.into_iter().map(|balance_change| {
                 match balance_change {
                     Some(balance_change) => {
                         let balance_change: BalanceChange = bcs::from_bytes(&balance_change)
@@ -193,6 +290,7 @@ impl StoredTransaction {
                     None => Err(IndexerError::PersistentStorageDataCorruptionError(format!("object_change should not be null, tx_digest={:?}", tx_digest))),
                 }
             }).collect::<Result<Vec<BalanceChange>, IndexerError>>()?;
+            sort_balance_changes(&mut balance_changes);
 
             Some(balance_changes)
         } else {
@@ -2,7 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    schema_v2::{tx_calls, tx_changed_objects, tx_input_objects, tx_recipients, tx_senders},
+    schema_v2::{
+        tx_calls, tx_changed_objects, tx_dependencies, tx_indices, tx_input_objects, tx_recipients,
+        tx_senders,
+    },
     types_v2::TxIndex,
 };
 use diesel::prelude::*;
@@ -56,6 +59,70 @@ pub struct StoredTxCalls {
     pub func: String,
 }
 
+#[derive(Queryable, Insertable, Debug, Clone, Default)]
+#[diesel(table_name = tx_dependencies)]
+pub struct StoredTxDependency {
+    pub tx_sequence_number: i64,
+    pub transaction_digest: Vec<u8>,
+    pub dependency: Vec<u8>,
+}
+
+/// All the indices of a transaction in one row, the form read before the tx_* tables. Only
+/// written in [`crate::store::SchemaWriteMode::Dual`].
+#[derive(Queryable, Insertable, Debug, Clone, Default)]
+#[diesel(table_name = tx_indices)]
+pub struct StoredTxIndex {
+    pub tx_sequence_number: i64,
+    pub checkpoint_sequence_number: i64,
+    pub transaction_digest: Vec<u8>,
+    pub input_objects: Vec<Option<Vec<u8>>>,
+    pub changed_objects: Vec<Option<Vec<u8>>>,
+    pub senders: Vec<Option<Vec<u8>>>,
+    pub payers: Vec<Option<Vec<u8>>>,
+    pub recipients: Vec<Option<Vec<u8>>>,
+    pub packages: Vec<Option<Vec<u8>>>,
+    pub package_modules: Vec<Option<String>>,
+    pub package_module_functions: Vec<Option<String>>,
+}
+
+impl From<&TxIndex> for StoredTxIndex {
+    fn from(index: &TxIndex) -> Self {
+        StoredTxIndex {
+            tx_sequence_number: index.tx_sequence_number as i64,
+            checkpoint_sequence_number: index.checkpoint_sequence_number as i64,
+            transaction_digest: index.transaction_digest.into_inner().to_vec(),
+            input_objects: index
+                .input_objects
+                .iter()
+                .map(|o| Some(o.to_vec()))
+                .collect(),
+            changed_objects: index
+                .changed_objects
+                .iter()
+                .map(|o| Some(o.to_vec()))
+                .collect(),
+            senders: index.senders.iter().map(|s| Some(s.to_vec())).collect(),
+            payers: index.payers.iter().map(|s| Some(s.to_vec())).collect(),
+            recipients: index.recipients.iter().map(|s| Some(s.to_vec())).collect(),
+            packages: index
+                .move_calls
+                .iter()
+                .map(|(p, _, _)| Some(p.to_vec()))
+                .collect(),
+            package_modules: index
+                .move_calls
+                .iter()
+                .map(|(p, m, _)| Some(format!("{}::{}", p, m)))
+                .collect(),
+            package_module_functions: index
+                .move_calls
+                .iter()
+                .map(|(p, m, f)| Some(format!("{}::{}::{}", p, m, f)))
+                .collect(),
+        }
+    }
+}
+
 #[allow(clippy::type_complexity)]
 impl TxIndex {
     pub fn split(
@@ -66,6 +133,7 @@ impl TxIndex {
         Vec<StoredTxInputObject>,
         Vec<StoredTxChangedObject>,
         Vec<StoredTxCalls>,
+        Vec<StoredTxDependency>,
     ) {
         let tx_sequence_number = self.tx_sequence_number as i64;
         let tx_senders = self
@@ -110,12 +178,23 @@ impl TxIndex {
                 func: f.to_string(),
             })
             .collect();
+        let transaction_digest = self.transaction_digest.into_inner().to_vec();
+        let tx_dependencies = self
+            .dependencies
+            .iter()
+            .map(|d| StoredTxDependency {
+                tx_sequence_number,
+                transaction_digest: transaction_digest.clone(),
+                dependency: d.into_inner().to_vec(),
+            })
+            .collect();
         (
             tx_senders,
             tx_recipients,
             tx_input_objects,
             tx_changed_objects,
             tx_calls,
+            tx_dependencies,
         )
     }
 }
@@ -0,0 +1,17 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use diesel::prelude::*;
+
+use crate::schema_v2::warehouse_exports;
+
+#[derive(Clone, Debug, Queryable, Insertable)]
+#[diesel(table_name = warehouse_exports)]
+pub struct StoredWarehouseExport {
+    pub table_name: String,
+    pub first_checkpoint: i64,
+    pub last_checkpoint: i64,
+    pub file_path: String,
+    pub row_count: i64,
+    pub exported_at_ms: i64,
+}
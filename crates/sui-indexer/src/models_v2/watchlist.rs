@@ -0,0 +1,56 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use diesel::prelude::*;
+
+use sui_json_rpc_types::WatchlistBackfillProgress;
+use sui_types::base_types::SuiAddress;
+
+use crate::errors::IndexerError;
+use crate::schema_v2::{watchlist_addresses, watchlist_transactions};
+
+#[derive(Clone, Debug, Queryable, Insertable)]
+#[diesel(table_name = watchlist_addresses)]
+pub struct StoredWatchlistAddress {
+    pub address: Vec<u8>,
+    pub end_checkpoint: i64,
+    pub next_checkpoint: i64,
+    pub transactions_found: i64,
+    pub registered_at_ms: i64,
+}
+
+impl StoredWatchlistAddress {
+    pub fn is_completed(&self) -> bool {
+        self.next_checkpoint >= self.end_checkpoint
+    }
+}
+
+impl TryFrom<StoredWatchlistAddress> for WatchlistBackfillProgress {
+    type Error = IndexerError;
+
+    fn try_from(stored: StoredWatchlistAddress) -> Result<Self, Self::Error> {
+        let address = SuiAddress::from_bytes(&stored.address).map_err(|e| {
+            IndexerError::PersistentStorageDataCorruptionError(format!(
+                "Failed to parse watchlist address: {:?}, error: {}",
+                stored.address, e
+            ))
+        })?;
+        Ok(Self {
+            address,
+            next_checkpoint: stored.next_checkpoint as u64,
+            end_checkpoint: stored.end_checkpoint as u64,
+            transactions_found: stored.transactions_found as u64,
+            completed: stored.is_completed(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Queryable, Insertable)]
+#[diesel(table_name = watchlist_transactions)]
+pub struct StoredWatchlistTransaction {
+    pub address: Vec<u8>,
+    pub checkpoint_sequence_number: i64,
+    pub transaction_digest: Vec<u8>,
+    pub raw_transaction: Vec<u8>,
+    pub raw_effects: Vec<u8>,
+}
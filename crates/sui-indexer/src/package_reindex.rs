@@ -0,0 +1,409 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reindex of the rows of a single package over a range of checkpoints, from checkpoint files.
+//!
+//! Jobs are requested through the `suix_requestPackageReindex` method, typically after an
+//! indexing bug corrupted the rows of a package, and are processed one at a time, oldest first,
+//! in batches of checkpoints. Each batch is indexed again with the same code as the writer's
+//! pipeline, and the rows attributable to the package replace the stored ones in a single DB
+//! transaction, so that readers never see a partially reindexed batch.
+//!
+//! A transaction is attributable to the package if it calls the package, changes the package
+//! object itself, or emits an event of the package or of one of its types. Its transaction,
+//! event and index rows are all rewritten. Objects of the package's types are only rewritten if
+//! their indexed row is still at the version reindexed: later versions are left to the writer,
+//! as are deletions of objects and display updates.
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl};
+use futures::TryStreamExt;
+use tracing::{info, warn};
+
+use sui_checkpoint_ingestion::{CheckpointData, CheckpointIngestionBuilder};
+use sui_storage::object_store::ObjectStoreConfig;
+use sui_types::base_types::ObjectID;
+
+use crate::errors::IndexerError;
+use crate::handlers::checkpoint_handler_v2::CheckpointHandler;
+use crate::handlers::tx_processor::IndexingPackageCache;
+use crate::metrics::IndexerMetrics;
+use crate::models_v2::events::StoredEvent;
+use crate::models_v2::objects::StoredObject;
+use crate::models_v2::package_reindex::StoredPackageReindexJob;
+use crate::models_v2::transactions::StoredTransaction;
+use crate::models_v2::tx_indices::StoredTxIndex;
+use crate::schema_v2::{
+    events, objects, package_reindex_jobs, transactions, tx_calls, tx_changed_objects,
+    tx_dependencies, tx_indices, tx_input_objects, tx_recipients, tx_senders,
+};
+use crate::store::diesel_macro::{read_only_blocking, transactional_blocking_with_retry};
+use crate::store::module_resolver_v2::InterimModuleResolver;
+use crate::store::{IndexerStoreV2, SchemaWriteMode};
+use crate::types_v2::{IndexedObject, IndexerResult, TxIndex};
+use crate::PgConnectionPool;
+
+const BATCH_CHECKPOINTS: i64 = 100;
+const DOWNLOAD_CONCURRENCY: usize = 20;
+/// Rows written per statement, within the limit of bind parameters.
+const INSERT_CHUNK_SIZE: usize = 1000;
+/// Time to wait before looking for jobs again, when there are none or the last batch failed.
+const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct PackageReindexer<S> {
+    pool: PgConnectionPool,
+    store: S,
+    checkpoint_store_config: ObjectStoreConfig,
+    metrics: IndexerMetrics,
+    schema_write_mode: SchemaWriteMode,
+    compress_bcs: bool,
+}
+
+/// Rows of a batch of checkpoints attributable to the package being reindexed.
+#[derive(Default)]
+struct ReindexedRows {
+    tx_sequence_numbers: Vec<i64>,
+    transactions: Vec<StoredTransaction>,
+    events: Vec<StoredEvent>,
+    tx_indices: Vec<TxIndex>,
+    objects: Vec<StoredObject>,
+}
+
+impl<S> PackageReindexer<S>
+where
+    S: IndexerStoreV2 + Clone + Sync + Send + 'static,
+{
+    pub fn new(
+        pool: PgConnectionPool,
+        store: S,
+        checkpoint_store_config: ObjectStoreConfig,
+        metrics: IndexerMetrics,
+    ) -> Self {
+        Self {
+            pool,
+            store,
+            checkpoint_store_config,
+            metrics,
+            schema_write_mode: SchemaWriteMode::default(),
+            compress_bcs: false,
+        }
+    }
+
+    pub fn with_schema_write_mode(mut self, schema_write_mode: SchemaWriteMode) -> Self {
+        self.schema_write_mode = schema_write_mode;
+        self
+    }
+
+    pub fn with_bcs_compression(mut self, compress_bcs: bool) -> Self {
+        self.compress_bcs = compress_bcs;
+        self
+    }
+
+    pub async fn run_forever(&self) {
+        info!("Package reindexer started");
+        loop {
+            match self.reindex_batch().await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => warn!("Package reindex failed, retrying: {e}"),
+            }
+            tokio::time::sleep(IDLE_INTERVAL).await;
+        }
+    }
+
+    /// Reindex the next batch of checkpoints of the oldest pending job, and return whether there
+    /// was one.
+    async fn reindex_batch(&self) -> anyhow::Result<bool> {
+        let pool = self.pool.clone();
+        let Some(job) = tokio::task::spawn_blocking(move || get_next_job(&pool)).await?? else {
+            return Ok(false);
+        };
+        let package = ObjectID::from_bytes(&job.package)?;
+        let start = job.next_checkpoint;
+        let end = job.end_checkpoint.min(start + BATCH_CHECKPOINTS);
+
+        let checkpoints: Vec<CheckpointData> = CheckpointIngestionBuilder::new()
+            .object_store(self.checkpoint_store_config.clone())
+            .start_checkpoint(start as u64)
+            .end_checkpoint(end as u64 - 1)
+            .concurrency(DOWNLOAD_CONCURRENCY)
+            .build()?
+            .try_collect()
+            .await?;
+        let rows = self.index_checkpoints(package, checkpoints).await?;
+
+        let reindexed_transactions = rows.transactions.len();
+        let pool = self.pool.clone();
+        let schema_write_mode = self.schema_write_mode;
+        let reindexed_objects = tokio::task::spawn_blocking(move || {
+            persist_batch(&pool, job.id, rows, end, schema_write_mode)
+        })
+        .await??;
+        self.metrics
+            .package_reindex_checkpoints_scanned
+            .inc_by((end - start) as u64);
+        self.metrics
+            .package_reindex_transactions_reindexed
+            .inc_by(reindexed_transactions as u64);
+        info!(
+            "Reindexed checkpoints {start} to {} of package {package}: {reindexed_transactions} transactions, {reindexed_objects} objects",
+            end - 1
+        );
+        Ok(true)
+    }
+
+    async fn index_checkpoints(
+        &self,
+        package: ObjectID,
+        checkpoints: Vec<CheckpointData>,
+    ) -> IndexerResult<ReindexedRows> {
+        // Types are printed with their full address, e.g. in `0x00..02::coin::Coin<0x..::a::B>`
+        let type_prefix = format!("{}::", package.to_hex_uncompressed());
+        let packages = CheckpointHandler::<S>::index_packages(&checkpoints, &self.metrics);
+        let module_resolver = InterimModuleResolver::new(
+            self.store.module_cache(),
+            IndexingPackageCache::new(),
+            &packages,
+            self.metrics.clone(),
+        );
+
+        let mut rows = ReindexedRows::default();
+        // Latest version of each object of the package's types in the batch
+        let mut objects: BTreeMap<ObjectID, IndexedObject> = BTreeMap::new();
+        for data in checkpoints {
+            let object_changes = CheckpointHandler::<S>::index_objects(
+                data.clone(),
+                &self.metrics,
+                &module_resolver,
+            );
+            for object in object_changes.changed_objects {
+                let is_of_package = object
+                    .object
+                    .struct_tag()
+                    .is_some_and(|tag| tag.to_canonical_string(true).contains(&type_prefix));
+                if is_of_package {
+                    objects.insert(object.object_id, object);
+                }
+            }
+
+            let CheckpointData {
+                transactions,
+                checkpoint_summary,
+                checkpoint_contents,
+            } = data;
+            let (transactions, events, tx_indices, _displays) =
+                CheckpointHandler::<S>::index_transactions(
+                    transactions,
+                    &checkpoint_summary,
+                    &checkpoint_contents,
+                    &self.metrics,
+                )
+                .await?;
+            let reindexed: HashSet<u64> = tx_indices
+                .iter()
+                .filter(|index| {
+                    index.move_calls.iter().any(|(p, _, _)| *p == package)
+                        || index.changed_objects.contains(&package)
+                })
+                .map(|index| index.tx_sequence_number)
+                .chain(
+                    events
+                        .iter()
+                        .filter(|event| {
+                            event.package == package || event.event_type.contains(&type_prefix)
+                        })
+                        .map(|event| event.tx_sequence_number),
+                )
+                .collect();
+            if reindexed.is_empty() {
+                continue;
+            }
+
+            for tx in transactions
+                .iter()
+                .filter(|tx| reindexed.contains(&tx.tx_sequence_number))
+            {
+                rows.tx_sequence_numbers.push(tx.tx_sequence_number as i64);
+                let tx = StoredTransaction::from(tx);
+                rows.transactions.push(if self.compress_bcs {
+                    tx.compress_bcs()?
+                } else {
+                    tx
+                });
+            }
+            rows.events.extend(
+                events
+                    .into_iter()
+                    .filter(|event| reindexed.contains(&event.tx_sequence_number))
+                    .map(StoredEvent::from),
+            );
+            rows.tx_indices.extend(
+                tx_indices
+                    .into_iter()
+                    .filter(|index| reindexed.contains(&index.tx_sequence_number)),
+            );
+        }
+        for object in objects.into_values() {
+            let object = StoredObject::from(object);
+            rows.objects.push(if self.compress_bcs {
+                object.compress_bcs()?
+            } else {
+                object
+            });
+        }
+        Ok(rows)
+    }
+}
+
+fn get_next_job(pool: &PgConnectionPool) -> IndexerResult<Option<StoredPackageReindexJob>> {
+    read_only_blocking!(pool, |conn| {
+        package_reindex_jobs::table
+            .filter(package_reindex_jobs::next_checkpoint.lt(package_reindex_jobs::end_checkpoint))
+            .order(package_reindex_jobs::id.asc())
+            .first::<StoredPackageReindexJob>(conn)
+            .optional()
+    })
+}
+
+/// Replace the rows of the reindexed transactions and objects, and advance the job past the
+/// checkpoints before `end`. Returns the number of objects rewritten.
+fn persist_batch(
+    pool: &PgConnectionPool,
+    job_id: i64,
+    rows: ReindexedRows,
+    end: i64,
+    schema_write_mode: SchemaWriteMode,
+) -> IndexerResult<usize> {
+    let legacy_indices = (schema_write_mode == SchemaWriteMode::Dual).then(|| {
+        rows.tx_indices
+            .iter()
+            .map(StoredTxIndex::from)
+            .collect::<Vec<_>>()
+    });
+    let (mut senders, mut recipients, mut input_objects, mut changed_objects) =
+        (vec![], vec![], vec![], vec![]);
+    let (mut calls, mut dependencies) = (vec![], vec![]);
+    for index in rows.tx_indices {
+        let split = index.split();
+        senders.extend(split.0);
+        recipients.extend(split.1);
+        input_objects.extend(split.2);
+        changed_objects.extend(split.3);
+        calls.extend(split.4);
+        dependencies.extend(split.5);
+    }
+    let tx_sequence_numbers = &rows.tx_sequence_numbers;
+
+    transactional_blocking_with_retry!(
+        pool,
+        |conn| {
+            for chunk in tx_sequence_numbers.chunks(INSERT_CHUNK_SIZE) {
+                diesel::delete(transactions::table)
+                    .filter(transactions::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                diesel::delete(events::table)
+                    .filter(events::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                diesel::delete(tx_senders::table)
+                    .filter(tx_senders::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                diesel::delete(tx_recipients::table)
+                    .filter(tx_recipients::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                diesel::delete(tx_input_objects::table)
+                    .filter(tx_input_objects::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                diesel::delete(tx_changed_objects::table)
+                    .filter(tx_changed_objects::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                diesel::delete(tx_calls::table)
+                    .filter(tx_calls::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                diesel::delete(tx_dependencies::table)
+                    .filter(tx_dependencies::tx_sequence_number.eq_any(chunk))
+                    .execute(conn)?;
+                if legacy_indices.is_some() {
+                    diesel::delete(tx_indices::table)
+                        .filter(tx_indices::tx_sequence_number.eq_any(chunk))
+                        .execute(conn)?;
+                }
+            }
+            for chunk in rows.transactions.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(transactions::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            for chunk in rows.events.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(events::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            for chunk in senders.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(tx_senders::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            for chunk in recipients.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(tx_recipients::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            for chunk in input_objects.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(tx_input_objects::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            for chunk in changed_objects.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(tx_changed_objects::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            for chunk in calls.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(tx_calls::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            for chunk in dependencies.chunks(INSERT_CHUNK_SIZE) {
+                diesel::insert_into(tx_dependencies::table)
+                    .values(chunk)
+                    .execute(conn)?;
+            }
+            if let Some(legacy_indices) = &legacy_indices {
+                for chunk in legacy_indices.chunks(INSERT_CHUNK_SIZE) {
+                    diesel::insert_into(tx_indices::table)
+                        .values(chunk)
+                        .execute(conn)?;
+                }
+            }
+
+            let mut reindexed_objects = 0;
+            for object in &rows.objects {
+                let deleted = diesel::delete(objects::table)
+                    .filter(objects::object_id.eq(&object.object_id))
+                    .filter(objects::object_version.eq(object.object_version))
+                    .execute(conn)?;
+                if deleted > 0 {
+                    diesel::insert_into(objects::table)
+                        .values(object)
+                        .execute(conn)?;
+                    reindexed_objects += 1;
+                }
+            }
+
+            diesel::update(package_reindex_jobs::table.find(job_id))
+                .set((
+                    package_reindex_jobs::next_checkpoint.eq(end),
+                    package_reindex_jobs::transactions_reindexed
+                        .eq(package_reindex_jobs::transactions_reindexed
+                            + rows.transactions.len() as i64),
+                    package_reindex_jobs::objects_reindexed
+                        .eq(package_reindex_jobs::objects_reindexed + reindexed_objects as i64),
+                ))
+                .execute(conn)?;
+            Ok::<usize, IndexerError>(reindexed_objects)
+        },
+        Duration::from_secs(60)
+    )
+}
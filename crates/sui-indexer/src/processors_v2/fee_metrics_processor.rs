@@ -0,0 +1,42 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use tracing::info;
+
+use crate::store::IndexerAnalyticalStore;
+use crate::types_v2::IndexerResult;
+
+const FEE_METRICS_PROCESSOR_INTERVAL_SECS: u64 = 60;
+
+pub struct FeeMetricsProcessor<S> {
+    pub store: S,
+}
+
+impl<S> FeeMetricsProcessor<S>
+where
+    S: IndexerAnalyticalStore + Sync + Send + 'static,
+{
+    pub fn new(store: S) -> FeeMetricsProcessor<S> {
+        Self { store }
+    }
+
+    /// Sum up the fees and rebates of every epoch once it is over, i.e. once a checkpoint of a
+    /// later epoch has been indexed.
+    pub async fn start(&self) -> IndexerResult<()> {
+        info!("Indexer fee metrics async processor started...");
+        let latest_epoch_metrics = self.store.get_latest_epoch_fee_metrics().await?;
+        let mut next_epoch = latest_epoch_metrics.map_or(0, |metrics| metrics.epoch + 1);
+
+        loop {
+            let latest_stored_checkpoint = self.store.get_latest_stored_checkpoint().await?;
+            while next_epoch < latest_stored_checkpoint.epoch {
+                self.store.persist_epoch_fee_metrics(next_epoch).await?;
+                info!("Persisted epoch fee metrics for epoch {}", next_epoch);
+                next_epoch += 1;
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(
+                FEE_METRICS_PROCESSOR_INTERVAL_SECS,
+            ))
+            .await;
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use tracing::info;
+
+use sui_json_rpc_types::GasPriceInterval;
+
+use crate::models_v2::gas_price_metrics::HOUR_MS;
+use crate::store::IndexerAnalyticalStore;
+use crate::types_v2::IndexerResult;
+
+const GAS_PRICE_METRICS_PROCESSOR_INTERVAL_SECS: u64 = 60;
+
+pub struct GasPriceMetricsProcessor<S> {
+    pub store: S,
+}
+
+impl<S> GasPriceMetricsProcessor<S>
+where
+    S: IndexerAnalyticalStore + Sync + Send + 'static,
+{
+    pub fn new(store: S) -> GasPriceMetricsProcessor<S> {
+        Self { store }
+    }
+
+    /// Summarize gas prices of every hour and epoch once it is over, i.e. once a later checkpoint
+    /// has been indexed.
+    pub async fn start(&self) -> IndexerResult<()> {
+        info!("Indexer gas price metrics async processor started...");
+        let latest_hourly_metrics = self
+            .store
+            .get_latest_gas_price_metrics(GasPriceInterval::Hour)
+            .await?;
+        let latest_epoch_metrics = self
+            .store
+            .get_latest_gas_price_metrics(GasPriceInterval::Epoch)
+            .await?;
+
+        let mut next_hour_start_ms = match latest_hourly_metrics {
+            Some(metrics) => hour_start(metrics.start_timestamp_ms) + HOUR_MS,
+            None => {
+                // Start from the earliest indexed checkpoint.
+                let first_checkpoint = match self.store.get_checkpoints_in_range(0, 1).await?.pop()
+                {
+                    Some(checkpoint) => checkpoint,
+                    None => self.store.get_latest_stored_checkpoint().await?,
+                };
+                hour_start(first_checkpoint.timestamp_ms)
+            }
+        };
+        let mut next_epoch = latest_epoch_metrics.map_or(0, |metrics| metrics.epoch + 1);
+
+        loop {
+            let latest_stored_checkpoint = self.store.get_latest_stored_checkpoint().await?;
+            while next_hour_start_ms + HOUR_MS <= latest_stored_checkpoint.timestamp_ms {
+                self.store
+                    .persist_hourly_gas_price_metrics(next_hour_start_ms)
+                    .await?;
+                info!(
+                    "Persisted hourly gas price metrics for hour starting at {}",
+                    next_hour_start_ms
+                );
+                next_hour_start_ms += HOUR_MS;
+            }
+            while next_epoch < latest_stored_checkpoint.epoch {
+                self.store
+                    .persist_epoch_gas_price_metrics(next_epoch)
+                    .await?;
+                info!("Persisted epoch gas price metrics for epoch {}", next_epoch);
+                next_epoch += 1;
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(
+                GAS_PRICE_METRICS_PROCESSOR_INTERVAL_SECS,
+            ))
+            .await;
+        }
+    }
+}
+
+fn hour_start(timestamp_ms: i64) -> i64 {
+    timestamp_ms - timestamp_ms.rem_euclid(HOUR_MS)
+}
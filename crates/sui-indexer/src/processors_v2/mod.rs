@@ -2,6 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod address_metrics_processor;
+pub mod fee_metrics_processor;
+pub mod gas_price_metrics_processor;
 pub mod move_call_metrics_processor;
 pub mod network_metrics_processor;
 pub mod processor_orchestrator_v2;
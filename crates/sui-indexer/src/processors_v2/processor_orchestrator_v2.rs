@@ -7,6 +7,8 @@ use tracing::{error, info};
 use crate::store::IndexerAnalyticalStore;
 
 use super::address_metrics_processor::AddressMetricsProcessor;
+use super::fee_metrics_processor::FeeMetricsProcessor;
+use super::gas_price_metrics_processor::GasPriceMetricsProcessor;
 use super::move_call_metrics_processor::MoveCallMetricsProcessor;
 use super::network_metrics_processor::NetworkMetricsProcessor;
 
@@ -67,10 +69,40 @@ where
             }
         });
 
+        let gas_price_metrics_processor = GasPriceMetricsProcessor::new(self.store.clone());
+        let gas_price_metrics_handle = tokio::task::spawn(async move {
+            loop {
+                let gas_price_metrics_res = gas_price_metrics_processor.start().await;
+                if let Err(e) = gas_price_metrics_res {
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    error!(
+                        "Indexer gas price metrics processor failed with error {:?}, retrying in 5s...",
+                        e
+                    );
+                }
+            }
+        });
+
+        let fee_metrics_processor = FeeMetricsProcessor::new(self.store.clone());
+        let fee_metrics_handle = tokio::task::spawn(async move {
+            loop {
+                let fee_metrics_res = fee_metrics_processor.start().await;
+                if let Err(e) = fee_metrics_res {
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    error!(
+                        "Indexer fee metrics processor failed with error {:?}, retrying in 5s...",
+                        e
+                    );
+                }
+            }
+        });
+
         try_join_all(vec![
             network_metrics_handle,
             addr_metrics_handle,
             move_call_metrics_handle,
+            gas_price_metrics_handle,
+            fee_metrics_handle,
         ])
         .await
         .expect("Processor orchestrator should not run into errors.");
@@ -0,0 +1,66 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Budget of the filtered queries of the reader, estimated from the query plan before they run.
+//!
+//! The cost is the total cost Postgres estimates for the query, from the selectivity statistics
+//! of the filtered columns and the requested limit. Queries over the budget of the reader's tier
+//! fail with [`IndexerError::QueryCostExceeded`] right away, rather than after the statement
+//! timeout, along with the largest limit estimated to fit, which the API uses to serve a smaller
+//! page instead.
+
+use clap::ValueEnum;
+
+use crate::errors::IndexerError;
+
+/// Tier of the clients of a reader, setting the budget of its queries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum QueryTier {
+    /// Public endpoints, only serving queries with selective filters.
+    Public,
+    /// Known clients, allowed broader queries.
+    Partner,
+    /// No budget, queries are only bounded by the statement timeout.
+    #[default]
+    Unlimited,
+}
+
+impl QueryTier {
+    /// Maximum estimated cost of a query, in Postgres planner cost units.
+    pub fn max_cost(self) -> Option<f64> {
+        match self {
+            QueryTier::Public => Some(20_000.0),
+            QueryTier::Partner => Some(200_000.0),
+            QueryTier::Unlimited => None,
+        }
+    }
+
+    /// Check the estimated `cost` of a query of `limit` rows against the budget.
+    pub fn check(self, cost: f64, limit: usize) -> Result<(), IndexerError> {
+        match self.max_cost() {
+            Some(budget) if cost > budget => Err(IndexerError::QueryCostExceeded {
+                cost,
+                budget,
+                // The cost of a query with a limit grows about linearly with the limit.
+                max_limit: (limit as f64 * budget / cost).floor() as usize,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_tier_budgets() {
+        assert!(QueryTier::Unlimited.check(f64::MAX, 1000).is_ok());
+        assert!(QueryTier::Public.check(20_000.0, 50).is_ok());
+        match QueryTier::Public.check(80_000.0, 50) {
+            Err(IndexerError::QueryCostExceeded { max_limit, .. }) => assert_eq!(max_limit, 12),
+            r => panic!("Unexpected result {r:?}"),
+        }
+        assert!(QueryTier::Partner.check(80_000.0, 50).is_ok());
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Retention classes of the columns of the v2 tables holding addresses, and the purge of the rows
+//! of an address from them.
+//!
+//! Columns derived from transactions, e.g. the address indices, and the data registered by users,
+//! e.g. watchlists, are purged. Chain data, e.g. the owners of live objects, can be rebuilt from
+//! any full node and is only reported. Tables holding addresses must be tagged here when they are
+//! added, so that a purge covers them.
+
+use std::fmt::{Display, Formatter};
+
+use diesel::sql_types::{BigInt, Bytea};
+use diesel::{PgConnection, QueryableByName, RunQueryDsl};
+use sui_json_rpc_types::{PurgeAction, PurgedColumn};
+use sui_types::base_types::SuiAddress;
+
+use ColumnKind::{Address, AddressArray};
+use RetentionClass::{ChainData, Derived, UserSupplied};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionClass {
+    /// Indices and aggregates derived from transactions
+    Derived,
+    /// Data registered through the admin API, e.g. watchlists
+    UserSupplied,
+    /// Chain state or history as served by full nodes
+    ChainData,
+}
+
+impl Display for RetentionClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RetentionClass::Derived => write!(f, "derived"),
+            RetentionClass::UserSupplied => write!(f, "user_supplied"),
+            RetentionClass::ChainData => write!(f, "chain_data"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    /// The column holds a single address, and identifies the rows of the address
+    Address,
+    /// The column is an array of addresses, of rows shared with other addresses
+    AddressArray,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaggedColumn {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub kind: ColumnKind,
+    pub class: RetentionClass,
+}
+
+pub const TAGGED_COLUMNS: &[TaggedColumn] = &[
+    tag("active_addresses", "address", Address, Derived),
+    tag("addresses", "address", Address, Derived),
+    tag("tx_senders", "sender", Address, Derived),
+    tag("tx_recipients", "recipient", Address, Derived),
+    tag("tx_indices", "senders", AddressArray, Derived),
+    tag("tx_indices", "payers", AddressArray, Derived),
+    tag("tx_indices", "recipients", AddressArray, Derived),
+    tag("watchlist_addresses", "address", Address, UserSupplied),
+    tag("watchlist_transactions", "address", Address, UserSupplied),
+    tag("events", "senders", AddressArray, ChainData),
+    tag("objects", "owner_id", Address, ChainData),
+];
+
+const fn tag(
+    table: &'static str,
+    column: &'static str,
+    kind: ColumnKind,
+    class: RetentionClass,
+) -> TaggedColumn {
+    TaggedColumn {
+        table,
+        column,
+        kind,
+        class,
+    }
+}
+
+impl TaggedColumn {
+    pub fn action(&self) -> PurgeAction {
+        match (self.class, self.kind) {
+            (RetentionClass::ChainData, _) => PurgeAction::Retain,
+            (_, ColumnKind::Address) => PurgeAction::DeleteRows,
+            (_, ColumnKind::AddressArray) => PurgeAction::RemoveAddress,
+        }
+    }
+
+    fn condition(&self) -> String {
+        match self.kind {
+            ColumnKind::Address => format!("{} = $1", self.column),
+            ColumnKind::AddressArray => format!("$1 = ANY({})", self.column),
+        }
+    }
+
+    fn count_statement(&self) -> String {
+        format!(
+            "SELECT COUNT(*) AS count FROM {} WHERE {}",
+            self.table,
+            self.condition()
+        )
+    }
+
+    /// Statement purging the address from the column, `None` if its rows are retained.
+    fn purge_statement(&self) -> Option<String> {
+        match self.action() {
+            PurgeAction::DeleteRows => Some(format!(
+                "DELETE FROM {} WHERE {}",
+                self.table,
+                self.condition()
+            )),
+            PurgeAction::RemoveAddress => Some(format!(
+                "UPDATE {} SET {column} = array_remove({column}, $1) WHERE {}",
+                self.table,
+                self.condition(),
+                column = self.column,
+            )),
+            PurgeAction::Retain => None,
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct RowCount {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Purge `address` from every tagged column, or only count its rows if `dry_run`. Meant to run in
+/// a transaction, so that either every column or none of them is purged.
+pub fn purge_address(
+    conn: &mut PgConnection,
+    address: SuiAddress,
+    dry_run: bool,
+) -> Result<Vec<PurgedColumn>, diesel::result::Error> {
+    let address = address.to_vec();
+    let mut columns = Vec::with_capacity(TAGGED_COLUMNS.len());
+    for tagged in TAGGED_COLUMNS {
+        let rows = match tagged.purge_statement() {
+            Some(statement) if !dry_run => diesel::sql_query(statement)
+                .bind::<Bytea, _>(address.clone())
+                .execute(conn)? as u64,
+            _ => {
+                diesel::sql_query(tagged.count_statement())
+                    .bind::<Bytea, _>(address.clone())
+                    .get_result::<RowCount>(conn)?
+                    .count as u64
+            }
+        };
+        columns.push(PurgedColumn {
+            table: tagged.table.to_string(),
+            column: tagged.column.to_string(),
+            retention_class: tagged.class.to_string(),
+            action: tagged.action(),
+            rows,
+        });
+    }
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retention::{ColumnKind, RetentionClass, TaggedColumn, TAGGED_COLUMNS};
+    use std::collections::HashSet;
+    use sui_json_rpc_types::PurgeAction;
+
+    #[test]
+    fn test_tagged_columns() {
+        let mut tagged = HashSet::new();
+        for column in TAGGED_COLUMNS {
+            assert!(tagged.insert((column.table, column.column)));
+        }
+
+        let recipients = TaggedColumn {
+            table: "tx_indices",
+            column: "recipients",
+            kind: ColumnKind::AddressArray,
+            class: RetentionClass::Derived,
+        };
+        assert_eq!(recipients.action(), PurgeAction::RemoveAddress);
+        assert_eq!(
+            recipients.purge_statement().unwrap(),
+            "UPDATE tx_indices SET recipients = array_remove(recipients, $1) WHERE $1 = ANY(recipients)"
+        );
+
+        let owners = TaggedColumn {
+            table: "objects",
+            column: "owner_id",
+            kind: ColumnKind::Address,
+            class: RetentionClass::ChainData,
+        };
+        assert_eq!(owners.action(), PurgeAction::Retain);
+        assert_eq!(owners.purge_statement(), None);
+        assert_eq!(
+            owners.count_statement(),
+            "SELECT COUNT(*) AS count FROM objects WHERE owner_id = $1"
+        );
+    }
+}
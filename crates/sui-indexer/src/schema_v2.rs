@@ -33,6 +33,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    checkpoint_timestamps (sequence_number) {
+        sequence_number -> Int8,
+        epoch -> Int8,
+        timestamp_ms -> Int8,
+        network_total_transactions -> Int8,
+    }
+}
+
 diesel::table! {
     checkpoints (sequence_number) {
         sequence_number -> Int8,
@@ -63,6 +72,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    epoch_fee_metrics (epoch) {
+        epoch -> Int8,
+        total_transactions -> Int8,
+        computation_cost -> Int8,
+        storage_cost -> Int8,
+        storage_rebate -> Int8,
+        non_refundable_storage_fee -> Int8,
+    }
+}
+
 diesel::table! {
     epoch_peak_tps (epoch) {
         epoch -> Int8,
@@ -112,6 +132,21 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    gas_price_metrics (granularity, start_timestamp_ms) {
+        granularity -> Int2,
+        start_timestamp_ms -> Int8,
+        epoch -> Int8,
+        reference_gas_price -> Int8,
+        total_transactions -> Int8,
+        p25 -> Nullable<Int8>,
+        p50 -> Nullable<Int8>,
+        p75 -> Nullable<Int8>,
+        p90 -> Nullable<Int8>,
+        p99 -> Nullable<Int8>,
+    }
+}
+
 diesel::table! {
     move_call_metrics (id) {
         id -> Int8,
@@ -153,6 +188,31 @@ diesel::table! {
         df_name -> Nullable<Bytea>,
         df_object_type -> Nullable<Text>,
         df_object_id -> Nullable<Bytea>,
+        bcs_codec -> Int2,
+        df_key_id -> Nullable<Bytea>,
+    }
+}
+
+diesel::table! {
+    package_reindex_jobs (id) {
+        id -> Int8,
+        package -> Bytea,
+        start_checkpoint -> Int8,
+        end_checkpoint -> Int8,
+        next_checkpoint -> Int8,
+        transactions_reindexed -> Int8,
+        objects_reindexed -> Int8,
+        requested_at_ms -> Int8,
+    }
+}
+
+diesel::table! {
+    purge_reports (id) {
+        id -> Int8,
+        address -> Bytea,
+        dry_run -> Bool,
+        requested_at_ms -> Int8,
+        columns -> Text,
     }
 }
 
@@ -176,6 +236,12 @@ diesel::table! {
         events -> Array<Nullable<Bytea>>,
         transaction_kind -> Int2,
         success_command_count -> Int2,
+        gas_price -> Nullable<Int8>,
+        computation_cost -> Nullable<Int8>,
+        storage_cost -> Nullable<Int8>,
+        storage_rebate -> Nullable<Int8>,
+        non_refundable_storage_fee -> Nullable<Int8>,
+        bcs_codec -> Int2,
     }
 }
 
@@ -206,6 +272,14 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    tx_dependencies (transaction_digest, dependency) {
+        tx_sequence_number -> Int8,
+        transaction_digest -> Bytea,
+        dependency -> Bytea,
+    }
+}
+
 diesel::table! {
     tx_input_objects (object_id, tx_sequence_number) {
         tx_sequence_number -> Int8,
@@ -243,27 +317,67 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    warehouse_exports (table_name, first_checkpoint) {
+        table_name -> Text,
+        first_checkpoint -> Int8,
+        last_checkpoint -> Int8,
+        file_path -> Text,
+        row_count -> Int8,
+        exported_at_ms -> Int8,
+    }
+}
+
+diesel::table! {
+    watchlist_addresses (address) {
+        address -> Bytea,
+        end_checkpoint -> Int8,
+        next_checkpoint -> Int8,
+        transactions_found -> Int8,
+        registered_at_ms -> Int8,
+    }
+}
+
+diesel::table! {
+    watchlist_transactions (address, checkpoint_sequence_number, transaction_digest) {
+        address -> Bytea,
+        checkpoint_sequence_number -> Int8,
+        transaction_digest -> Bytea,
+        raw_transaction -> Bytea,
+        raw_effects -> Bytea,
+    }
+}
+
 diesel::allow_tables_to_appear_in_same_query!(
     active_addresses,
     address_metrics,
     addresses,
+    checkpoint_timestamps,
     checkpoints,
     display,
+    epoch_fee_metrics,
     epoch_peak_tps,
     epochs,
     events,
+    gas_price_metrics,
     move_call_metrics,
     move_calls,
     objects,
+    package_reindex_jobs,
     packages,
+    purge_reports,
     transactions,
     tx_calls,
     tx_changed_objects,
     tx_count_metrics,
+    tx_dependencies,
     tx_input_objects,
     tx_recipients,
     tx_senders,
     tx_indices,
+    warehouse_exports,
+    watchlist_addresses,
+    watchlist_transactions,
 );
 
 use diesel::sql_types::Text;
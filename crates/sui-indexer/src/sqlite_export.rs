@@ -0,0 +1,333 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Export of a slice of the indexed data, the transactions and events of a time range optionally
+//! narrowed to one package, into a self-contained SQLite file that can be analysed offline by
+//! people without access to the production database.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl, RunQueryDsl};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::checkpoint_range::CheckpointRange;
+use sui_types::digests::TransactionDigest;
+use sui_types::transaction::{SenderSignedData, TransactionDataAPI};
+use tracing::{info, warn};
+
+use crate::indexer_reader::IndexerReader;
+use crate::models_v2::events::StoredEvent;
+use crate::models_v2::transactions::StoredTransaction;
+use crate::schema_v2::{events, transactions, tx_calls};
+
+/// Number of rows read from Postgres, and written to SQLite in one transaction, at a time.
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+const SQLITE_SCHEMA: &str = "
+CREATE TABLE metadata (
+    key                         TEXT        PRIMARY KEY,
+    value                       TEXT        NOT NULL
+);
+
+CREATE TABLE transactions (
+    tx_sequence_number          INTEGER     PRIMARY KEY,
+    transaction_digest          TEXT        NOT NULL,
+    checkpoint_sequence_number  INTEGER     NOT NULL,
+    timestamp_ms                INTEGER     NOT NULL,
+    sender                      TEXT        NOT NULL,
+    transaction_kind            INTEGER     NOT NULL,
+    success_command_count       INTEGER     NOT NULL,
+    gas_price                   INTEGER,
+    -- bcs serialized SenderSignedData bytes
+    raw_transaction             BLOB        NOT NULL,
+    -- bcs serialized TransactionEffects bytes
+    raw_effects                 BLOB        NOT NULL
+);
+CREATE INDEX transactions_transaction_digest ON transactions (transaction_digest);
+CREATE INDEX transactions_sender ON transactions (sender);
+CREATE INDEX transactions_timestamp_ms ON transactions (timestamp_ms);
+
+CREATE TABLE events (
+    tx_sequence_number          INTEGER     NOT NULL,
+    event_sequence_number       INTEGER     NOT NULL,
+    transaction_digest          TEXT        NOT NULL,
+    checkpoint_sequence_number  INTEGER     NOT NULL,
+    sender                      TEXT,
+    package                     TEXT        NOT NULL,
+    module                      TEXT        NOT NULL,
+    event_type                  TEXT        NOT NULL,
+    timestamp_ms                INTEGER     NOT NULL,
+    -- the event as JSON, null if its type could not be resolved
+    parsed_json                 TEXT,
+    bcs                         BLOB        NOT NULL,
+    PRIMARY KEY (tx_sequence_number, event_sequence_number)
+);
+CREATE INDEX events_event_type ON events (event_type);
+CREATE INDEX events_timestamp_ms ON events (timestamp_ms);
+";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportFilter {
+    /// Only export transactions calling this package, and events emitted by it.
+    pub package: Option<ObjectID>,
+    /// Inclusive.
+    pub start_timestamp_ms: u64,
+    /// Exclusive.
+    pub end_timestamp_ms: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExportSummary {
+    pub transactions: usize,
+    pub events: usize,
+}
+
+/// Write the transactions and events matching `filter` into a new SQLite file at `path`.
+pub fn export_to_sqlite(
+    reader: &IndexerReader,
+    filter: &ExportFilter,
+    path: &Path,
+) -> anyhow::Result<ExportSummary> {
+    if path.exists() {
+        bail!("Export file {} already exists", path.display());
+    }
+    let mut sqlite = Connection::open(path)?;
+    create_schema(&sqlite, filter)?;
+
+    let mut summary = ExportSummary::default();
+    let Some(checkpoints) = reader
+        .get_checkpoint_range_for_timestamps(filter.start_timestamp_ms, filter.end_timestamp_ms)?
+    else {
+        warn!("No checkpoint indexed in the time range of {filter:?}");
+        return Ok(summary);
+    };
+    info!("Exporting checkpoints {checkpoints} to {}", path.display());
+
+    let mut cursor = -1;
+    loop {
+        let batch = read_transactions(reader, filter.package, checkpoints, cursor)?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        cursor = last.tx_sequence_number;
+        summary.transactions += write_transactions(&mut sqlite, &batch)?;
+    }
+
+    let mut cursor = (-1, -1);
+    loop {
+        let batch = read_events(reader, filter.package, checkpoints, cursor)?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        cursor = (last.tx_sequence_number, last.event_sequence_number);
+        summary.events += write_events(&mut sqlite, reader, batch)?;
+    }
+
+    info!(
+        "Exported {} transactions and {} events to {}",
+        summary.transactions,
+        summary.events,
+        path.display()
+    );
+    Ok(summary)
+}
+
+fn create_schema(sqlite: &Connection, filter: &ExportFilter) -> anyhow::Result<()> {
+    sqlite.execute_batch(SQLITE_SCHEMA)?;
+    let metadata = [
+        ("filter", serde_json::to_string(filter)?),
+        ("indexer_version", env!("CARGO_PKG_VERSION").to_string()),
+        (
+            "exported_at_ms",
+            chrono::Utc::now().timestamp_millis().to_string(),
+        ),
+    ];
+    for (key, value) in metadata {
+        sqlite.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+    }
+    Ok(())
+}
+
+fn read_transactions(
+    reader: &IndexerReader,
+    package: Option<ObjectID>,
+    checkpoints: CheckpointRange,
+    cursor: i64,
+) -> anyhow::Result<Vec<StoredTransaction>> {
+    Ok(reader.run_query(|conn| {
+        let mut query = transactions::table
+            .filter(transactions::checkpoint_sequence_number.ge(checkpoints.start() as i64))
+            .filter(transactions::checkpoint_sequence_number.lt(checkpoints.end() as i64))
+            .filter(transactions::tx_sequence_number.gt(cursor))
+            .order(transactions::tx_sequence_number.asc())
+            .limit(EXPORT_BATCH_SIZE)
+            .into_boxed();
+        if let Some(package) = package {
+            query = query.filter(
+                transactions::tx_sequence_number.eq_any(
+                    tx_calls::table
+                        .select(tx_calls::tx_sequence_number)
+                        .filter(tx_calls::package.eq(package.to_vec())),
+                ),
+            );
+        }
+        query.load::<StoredTransaction>(conn)
+    })?)
+}
+
+fn read_events(
+    reader: &IndexerReader,
+    package: Option<ObjectID>,
+    checkpoints: CheckpointRange,
+    (tx_cursor, event_cursor): (i64, i64),
+) -> anyhow::Result<Vec<StoredEvent>> {
+    Ok(reader.run_query(|conn| {
+        let mut query = events::table
+            .filter(events::checkpoint_sequence_number.ge(checkpoints.start() as i64))
+            .filter(events::checkpoint_sequence_number.lt(checkpoints.end() as i64))
+            .filter(
+                events::tx_sequence_number
+                    .gt(tx_cursor)
+                    .or(events::tx_sequence_number
+                        .eq(tx_cursor)
+                        .and(events::event_sequence_number.gt(event_cursor))),
+            )
+            .order((
+                events::tx_sequence_number.asc(),
+                events::event_sequence_number.asc(),
+            ))
+            .limit(EXPORT_BATCH_SIZE)
+            .into_boxed();
+        if let Some(package) = package {
+            query = query.filter(events::package.eq(package.to_vec()));
+        }
+        query.load::<StoredEvent>(conn)
+    })?)
+}
+
+fn write_transactions(
+    sqlite: &mut Connection,
+    batch: &[StoredTransaction],
+) -> anyhow::Result<usize> {
+    let sqlite_tx = sqlite.transaction()?;
+    {
+        let mut insert = sqlite_tx.prepare(
+            "INSERT INTO transactions (tx_sequence_number, transaction_digest, \
+            checkpoint_sequence_number, timestamp_ms, sender, transaction_kind, \
+            success_command_count, gas_price, raw_transaction, raw_effects) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for tx in batch {
+            let digest = TransactionDigest::try_from(tx.transaction_digest.as_slice())?;
+            let sender_signed_data: SenderSignedData = bcs::from_bytes(&tx.raw_transaction)
+                .map_err(|e| anyhow!("Failed to deserialize transaction {digest}: {e}"))?;
+            insert.execute(params![
+                tx.tx_sequence_number,
+                digest.to_string(),
+                tx.checkpoint_sequence_number,
+                tx.timestamp_ms,
+                sender_signed_data
+                    .intent_message()
+                    .value
+                    .sender()
+                    .to_string(),
+                tx.transaction_kind,
+                tx.success_command_count,
+                tx.gas_price,
+                tx.raw_transaction,
+                tx.raw_effects,
+            ])?;
+        }
+    }
+    sqlite_tx.commit()?;
+    Ok(batch.len())
+}
+
+fn write_events(
+    sqlite: &mut Connection,
+    reader: &IndexerReader,
+    batch: Vec<StoredEvent>,
+) -> anyhow::Result<usize> {
+    let count = batch.len();
+    let sqlite_tx = sqlite.transaction()?;
+    {
+        let mut insert = sqlite_tx.prepare(
+            "INSERT INTO events (tx_sequence_number, event_sequence_number, transaction_digest, \
+            checkpoint_sequence_number, sender, package, module, event_type, timestamp_ms, \
+            parsed_json, bcs) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )?;
+        for event in batch {
+            let digest = TransactionDigest::try_from(event.transaction_digest.as_slice())?;
+            let package = ObjectID::from_bytes(&event.package)?;
+            let sender = match event.senders.first() {
+                Some(Some(sender)) => Some(SuiAddress::from_bytes(sender)?.to_string()),
+                _ => None,
+            };
+            // Decoding needs the package defining the event type, which may not be indexed.
+            let parsed_json = match event.clone().try_into_sui_event(reader) {
+                Ok(sui_event) => Some(sui_event.parsed_json.to_string()),
+                Err(e) => {
+                    warn!(
+                        "Failed to decode event {} of {digest}: {e}",
+                        event.event_sequence_number
+                    );
+                    None
+                }
+            };
+            insert.execute(params![
+                event.tx_sequence_number,
+                event.event_sequence_number,
+                digest.to_string(),
+                event.checkpoint_sequence_number,
+                sender,
+                package.to_hex_uncompressed(),
+                event.module,
+                event.event_type,
+                event.timestamp_ms,
+                parsed_json,
+                event.bcs,
+            ])?;
+        }
+    }
+    sqlite_tx.commit()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_schema() {
+        let sqlite = Connection::open_in_memory().unwrap();
+        let filter = ExportFilter {
+            package: Some(ObjectID::from_single_byte(2)),
+            start_timestamp_ms: 1_696_118_400_000,
+            end_timestamp_ms: 1_698_796_800_000,
+        };
+        create_schema(&sqlite, &filter).unwrap();
+
+        let exported_filter: String = sqlite
+            .query_row(
+                "SELECT value FROM metadata WHERE key = 'filter'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(exported_filter, serde_json::to_string(&filter).unwrap());
+        for table in ["transactions", "events"] {
+            let count: i64 = sqlite
+                .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!(count, 0);
+        }
+    }
+}
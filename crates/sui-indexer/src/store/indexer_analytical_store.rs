@@ -2,9 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use async_trait::async_trait;
+use sui_json_rpc_types::GasPriceInterval;
 
 use crate::models_v2::address_metrics::{StoredActiveAddress, StoredAddress, StoredAddressMetrics};
 use crate::models_v2::checkpoints::StoredCheckpoint;
+use crate::models_v2::fee_metrics::StoredEpochFeeMetrics;
+use crate::models_v2::gas_price_metrics::StoredGasPriceMetrics;
 use crate::models_v2::move_call_metrics::{StoredMoveCall, StoredMoveCallMetrics};
 use crate::models_v2::network_metrics::StoredEpochPeakTps;
 use crate::models_v2::transactions::{
@@ -90,4 +93,16 @@ pub trait IndexerAnalyticalStore {
         &self,
         move_call_metrics: Vec<StoredMoveCallMetrics>,
     ) -> IndexerResult<()>;
+
+    // for gas price metrics
+    async fn get_latest_gas_price_metrics(
+        &self,
+        interval: GasPriceInterval,
+    ) -> IndexerResult<Option<StoredGasPriceMetrics>>;
+    async fn persist_hourly_gas_price_metrics(&self, hour_start_ms: i64) -> IndexerResult<()>;
+    async fn persist_epoch_gas_price_metrics(&self, epoch: i64) -> IndexerResult<()>;
+
+    // for fee metrics
+    async fn get_latest_epoch_fee_metrics(&self) -> IndexerResult<Option<StoredEpochFeeMetrics>>;
+    async fn persist_epoch_fee_metrics(&self, epoch: i64) -> IndexerResult<()>;
 }
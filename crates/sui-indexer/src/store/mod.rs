@@ -6,7 +6,7 @@ pub use indexer_store::*;
 pub(crate) use indexer_store_v2::*;
 pub use pg_indexer_analytical_store::PgIndexerAnalyticalStore;
 pub use pg_indexer_store::PgIndexerStore;
-pub use pg_indexer_store_v2::PgIndexerStoreV2;
+pub use pg_indexer_store_v2::{PgIndexerStoreV2, SchemaWriteMode};
 
 mod indexer_analytical_store;
 mod indexer_store;
@@ -18,6 +18,11 @@ mod pg_indexer_store;
 mod pg_indexer_store_v2;
 mod query;
 
+/// How long writes keep retrying failures of the connection to the DB, e.g. while the primary
+/// fails over, before giving up.
+pub(crate) const RECONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+pub(crate) const MAX_RECONNECT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
 pub(crate) mod diesel_macro {
     macro_rules! read_only_blocking {
         ($pool:expr, $query:expr) => {{
@@ -42,28 +47,64 @@ pub(crate) mod diesel_macro {
         }};
     }
 
+    /// Runs `$query` in a transaction, retrying failures with exponential backoff for up to
+    /// `$max_elapsed`. Failures of the connection itself, e.g. on a primary failover, are retried
+    /// for up to `RECONNECT_TIMEOUT` instead, so `$query` must be safe to run again.
+    ///
+    /// With a `$budget`, every retry is also drawn from it, and failures are returned as soon as
+    /// it is exhausted.
     macro_rules! transactional_blocking_with_retry {
         ($pool:expr, $query:expr, $max_elapsed:expr) => {{
+            crate::store::diesel_macro::transactional_blocking_with_retry!(
+                $pool,
+                $query,
+                $max_elapsed,
+                None
+            )
+        }};
+        ($pool:expr, $query:expr, $max_elapsed:expr, $budget:expr) => {{
+            use crate::errors::ConnectionFailure;
+
+            let max_elapsed: std::time::Duration = $max_elapsed;
+            let budget: Option<&sui_storage::object_store::retry::RetryBudget> = $budget;
+            let start = std::time::Instant::now();
             let mut backoff = backoff::ExponentialBackoff::default();
-            backoff.max_elapsed_time = Some($max_elapsed);
+            backoff.max_interval = crate::store::MAX_RECONNECT_INTERVAL;
+            backoff.max_elapsed_time = Some(max_elapsed.max(crate::store::RECONNECT_TIMEOUT));
+            let to_backoff_error = |err: IndexerError, connection_failure: bool| {
+                if !connection_failure && start.elapsed() >= max_elapsed {
+                    return backoff::Error::Permanent(err);
+                }
+                if budget.map_or(false, |budget| !budget.try_spend()) {
+                    tracing::warn!("Not retrying DB transaction, retry budget exhausted: {err}");
+                    return backoff::Error::Permanent(err);
+                }
+                if connection_failure {
+                    tracing::warn!(
+                        "Lost connection to DB, reconnecting after {:?}: {err}",
+                        start.elapsed()
+                    );
+                }
+                backoff::Error::Transient {
+                    err,
+                    retry_after: None,
+                }
+            };
 
             let result = match backoff::retry(backoff, || {
-                let mut pg_pool_conn = crate::get_pg_pool_connection($pool).map_err(|e| {
-                    backoff::Error::Transient {
-                        err: IndexerError::PostgresWriteError(e.to_string()),
-                        retry_after: None,
-                    }
-                })?;
+                let mut pg_pool_conn =
+                    crate::get_pg_pool_connection($pool).map_err(|e| to_backoff_error(e, true))?;
                 pg_pool_conn
                     .build_transaction()
                     .read_write()
                     .run($query)
                     .map_err(|e| {
                         tracing::error!("Error with persisting data into DB: {:?}", e);
-                        backoff::Error::Transient {
-                            err: IndexerError::PostgresWriteError(e.to_string()),
-                            retry_after: None,
-                        }
+                        let connection_failure = e.is_connection_failure();
+                        to_backoff_error(
+                            IndexerError::PostgresWriteError(e.to_string()),
+                            connection_failure,
+                        )
                     })
             }) {
                 Ok(v) => Ok(v),
@@ -10,12 +10,15 @@ use core::result::Result::Ok;
 use diesel::dsl::count;
 use diesel::upsert::excluded;
 use diesel::ExpressionMethods;
-use diesel::{QueryDsl, RunQueryDsl};
+use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+use sui_json_rpc_types::GasPriceInterval;
 use sui_types::base_types::ObjectID;
 
 use crate::errors::{Context, IndexerError};
 use crate::models_v2::address_metrics::{StoredActiveAddress, StoredAddress, StoredAddressMetrics};
 use crate::models_v2::checkpoints::StoredCheckpoint;
+use crate::models_v2::fee_metrics::StoredEpochFeeMetrics;
+use crate::models_v2::gas_price_metrics::{granularity, StoredGasPriceMetrics, HOUR_MS};
 use crate::models_v2::move_call_metrics::{
     build_move_call_metric_query, QueriedMoveCallMetrics, QueriedMoveMetrics, StoredMoveCall,
     StoredMoveCallMetrics,
@@ -27,11 +30,12 @@ use crate::models_v2::transactions::{
 use crate::models_v2::tx_count_metrics::StoredTxCountMetrics;
 use crate::models_v2::tx_indices::{StoredTxCalls, StoredTxRecipients, StoredTxSenders};
 use crate::schema_v2::{
-    active_addresses, address_metrics, addresses, checkpoints, epoch_peak_tps, move_call_metrics,
-    move_calls, transactions, tx_calls, tx_count_metrics, tx_recipients, tx_senders,
+    active_addresses, address_metrics, addresses, checkpoints, epoch_fee_metrics, epoch_peak_tps,
+    gas_price_metrics, move_call_metrics, move_calls, transactions, tx_calls, tx_count_metrics,
+    tx_recipients, tx_senders,
 };
 use crate::store::diesel_macro::{read_only_blocking, transactional_blocking_with_retry};
-use crate::types_v2::IndexerResult;
+use crate::types_v2::{IndexerResult, TransactionKind};
 use crate::PgConnectionPool;
 
 use super::IndexerAnalyticalStore;
@@ -487,6 +491,74 @@ impl IndexerAnalyticalStore for PgIndexerAnalyticalStore {
         .context("Failed persisting move call metrics to PostgresDB")?;
         Ok(())
     }
+
+    async fn get_latest_gas_price_metrics(
+        &self,
+        interval: GasPriceInterval,
+    ) -> IndexerResult<Option<StoredGasPriceMetrics>> {
+        let latest_gas_price_metrics = read_only_blocking!(&self.blocking_cp, |conn| {
+            gas_price_metrics::dsl::gas_price_metrics
+                .filter(gas_price_metrics::dsl::granularity.eq(granularity(interval)))
+                .order(gas_price_metrics::dsl::start_timestamp_ms.desc())
+                .first::<StoredGasPriceMetrics>(conn)
+                .optional()
+        })
+        .context("Failed reading latest gas price metrics from PostgresDB")?;
+        Ok(latest_gas_price_metrics)
+    }
+
+    async fn persist_hourly_gas_price_metrics(&self, hour_start_ms: i64) -> IndexerResult<()> {
+        let query = construct_hourly_gas_price_query(hour_start_ms);
+        transactional_blocking_with_retry!(
+            &self.blocking_cp,
+            |conn| {
+                diesel::sql_query(query.clone()).execute(conn)?;
+                Ok::<(), IndexerError>(())
+            },
+            Duration::from_secs(60)
+        )
+        .context("Failed persisting hourly gas price metrics to PostgresDB")?;
+        Ok(())
+    }
+
+    async fn persist_epoch_gas_price_metrics(&self, epoch: i64) -> IndexerResult<()> {
+        let query = construct_epoch_gas_price_query(epoch);
+        transactional_blocking_with_retry!(
+            &self.blocking_cp,
+            |conn| {
+                diesel::sql_query(query.clone()).execute(conn)?;
+                Ok::<(), IndexerError>(())
+            },
+            Duration::from_secs(60)
+        )
+        .context("Failed persisting epoch gas price metrics to PostgresDB")?;
+        Ok(())
+    }
+
+    async fn get_latest_epoch_fee_metrics(&self) -> IndexerResult<Option<StoredEpochFeeMetrics>> {
+        let latest_epoch_fee_metrics = read_only_blocking!(&self.blocking_cp, |conn| {
+            epoch_fee_metrics::dsl::epoch_fee_metrics
+                .order(epoch_fee_metrics::dsl::epoch.desc())
+                .first::<StoredEpochFeeMetrics>(conn)
+                .optional()
+        })
+        .context("Failed reading latest epoch fee metrics from PostgresDB")?;
+        Ok(latest_epoch_fee_metrics)
+    }
+
+    async fn persist_epoch_fee_metrics(&self, epoch: i64) -> IndexerResult<()> {
+        let query = construct_epoch_fee_query(epoch);
+        transactional_blocking_with_retry!(
+            &self.blocking_cp,
+            |conn| {
+                diesel::sql_query(query.clone()).execute(conn)?;
+                Ok::<(), IndexerError>(())
+            },
+            Duration::from_secs(60)
+        )
+        .context("Failed persisting epoch fee metrics to PostgresDB")?;
+        Ok(())
+    }
 }
 
 fn construct_checkpoint_tx_count_query(start_checkpoint: i64, end_checkpoint: i64) -> String {
@@ -546,3 +618,96 @@ fn construct_peak_tps_query(epoch: i64, offset: i64) -> String {
         epoch, offset
     )
 }
+
+// Transactions are bucketed by the epoch of their checkpoint, so an hour spanning an epoch change
+// gets a row per epoch, each starting no earlier than its epoch.
+fn construct_hourly_gas_price_query(hour_start_ms: i64) -> String {
+    format!(
+        "INSERT INTO gas_price_metrics
+            (granularity, start_timestamp_ms, epoch, reference_gas_price, total_transactions,
+             p25, p50, p75, p90, p99)
+          SELECT
+            {},
+            GREATEST({}, e.epoch_start_timestamp),
+            e.epoch,
+            e.reference_gas_price,
+            COUNT(t.gas_price),
+            {}
+          FROM checkpoint_timestamps c
+          JOIN transactions t
+            ON t.checkpoint_sequence_number = c.sequence_number AND t.transaction_kind = {}
+          JOIN epochs e
+            ON c.epoch = e.epoch
+          WHERE c.timestamp_ms >= {} AND c.timestamp_ms < {}
+          GROUP BY e.epoch, e.epoch_start_timestamp, e.reference_gas_price
+          ON CONFLICT DO NOTHING;
+        ",
+        granularity(GasPriceInterval::Hour),
+        hour_start_ms,
+        gas_price_percentiles_columns(),
+        TransactionKind::ProgrammableTransaction as i16,
+        hour_start_ms,
+        hour_start_ms + HOUR_MS,
+    )
+}
+
+// Epochs without user transactions still get a row, with NULL percentiles.
+fn construct_epoch_gas_price_query(epoch: i64) -> String {
+    format!(
+        "INSERT INTO gas_price_metrics
+            (granularity, start_timestamp_ms, epoch, reference_gas_price, total_transactions,
+             p25, p50, p75, p90, p99)
+          SELECT
+            {},
+            e.epoch_start_timestamp,
+            e.epoch,
+            e.reference_gas_price,
+            COUNT(t.gas_price),
+            {}
+          FROM epochs e
+          LEFT JOIN transactions t
+            ON t.checkpoint_sequence_number BETWEEN e.first_checkpoint_id AND e.last_checkpoint_id
+            AND t.transaction_kind = {}
+          WHERE e.epoch = {}
+          GROUP BY e.epoch, e.epoch_start_timestamp, e.reference_gas_price
+          ON CONFLICT DO NOTHING;
+        ",
+        granularity(GasPriceInterval::Epoch),
+        gas_price_percentiles_columns(),
+        TransactionKind::ProgrammableTransaction as i16,
+        epoch,
+    )
+}
+
+fn gas_price_percentiles_columns() -> &'static str {
+    "percentile_disc(0.25) WITHIN GROUP (ORDER BY t.gas_price),
+            percentile_disc(0.50) WITHIN GROUP (ORDER BY t.gas_price),
+            percentile_disc(0.75) WITHIN GROUP (ORDER BY t.gas_price),
+            percentile_disc(0.90) WITHIN GROUP (ORDER BY t.gas_price),
+            percentile_disc(0.99) WITHIN GROUP (ORDER BY t.gas_price)"
+}
+
+// Transactions indexed before the fee columns were added have NULL fees, and are left out.
+fn construct_epoch_fee_query(epoch: i64) -> String {
+    format!(
+        "INSERT INTO epoch_fee_metrics
+            (epoch, total_transactions, computation_cost, storage_cost, storage_rebate,
+             non_refundable_storage_fee)
+          SELECT
+            e.epoch,
+            COUNT(t.computation_cost),
+            COALESCE(SUM(t.computation_cost), 0)::BIGINT,
+            COALESCE(SUM(t.storage_cost), 0)::BIGINT,
+            COALESCE(SUM(t.storage_rebate), 0)::BIGINT,
+            COALESCE(SUM(t.non_refundable_storage_fee), 0)::BIGINT
+          FROM epochs e
+          LEFT JOIN transactions t
+            ON t.checkpoint_sequence_number BETWEEN e.first_checkpoint_id AND e.last_checkpoint_id
+            AND t.computation_cost IS NOT NULL
+          WHERE e.epoch = {}
+          GROUP BY e.epoch
+          ON CONFLICT DO NOTHING;
+        ",
+        epoch,
+    )
+}
@@ -29,6 +29,7 @@ use crate::handlers::EpochToCommit;
 use crate::handlers::TransactionObjectChangesToCommit;
 use crate::metrics::IndexerMetrics;
 
+use crate::models_v2::checkpoint_timestamps::StoredCheckpointTimestamp;
 use crate::models_v2::checkpoints::StoredCheckpoint;
 use crate::models_v2::display::StoredDisplay;
 use crate::models_v2::epoch::StoredEpochInfo;
@@ -36,9 +37,11 @@ use crate::models_v2::events::StoredEvent;
 use crate::models_v2::objects::StoredObject;
 use crate::models_v2::packages::StoredPackage;
 use crate::models_v2::transactions::StoredTransaction;
+use crate::models_v2::tx_indices::StoredTxIndex;
 use crate::schema_v2::{
-    checkpoints, display, epochs, events, objects, packages, transactions, tx_calls,
-    tx_changed_objects, tx_input_objects, tx_recipients, tx_senders,
+    checkpoint_timestamps, checkpoints, display, epochs, events, objects, packages, transactions,
+    tx_calls, tx_changed_objects, tx_dependencies, tx_indices, tx_input_objects, tx_recipients,
+    tx_senders,
 };
 use crate::store::diesel_macro::{read_only_blocking, transactional_blocking_with_retry};
 use crate::store::module_resolver_v2::IndexerStoreModuleResolver;
@@ -73,6 +76,19 @@ const PG_COMMIT_PARALLEL_CHUNK_SIZE_PER_DB_TX: usize = 500;
 // optimistic locking.
 const PG_COMMIT_OBJECTS_PARALLEL_CHUNK_SIZE_PER_DB_TX: usize = 500;
 
+/// Which forms of the schema the writer persists. Tables and columns changed in a release keep
+/// being written in their old form as well in `Dual` mode, so that readers can be upgraded one
+/// at a time before the writer switches to `Current` and the old forms are dropped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaWriteMode {
+    /// Only write the current schema.
+    #[default]
+    Current,
+    /// Also write the old form of changed tables and columns, currently the `tx_indices` table
+    /// replaced by the tx_* tables.
+    Dual,
+}
+
 #[derive(Clone)]
 pub struct PgIndexerStoreV2 {
     blocking_cp: PgConnectionPool,
@@ -80,6 +96,8 @@ pub struct PgIndexerStoreV2 {
     metrics: IndexerMetrics,
     parallel_chunk_size: usize,
     parallel_objects_chunk_size: usize,
+    schema_write_mode: SchemaWriteMode,
+    compress_bcs: bool,
 }
 
 impl PgIndexerStoreV2 {
@@ -101,9 +119,22 @@ impl PgIndexerStoreV2 {
             metrics,
             parallel_chunk_size,
             parallel_objects_chunk_size,
+            schema_write_mode: SchemaWriteMode::default(),
+            compress_bcs: false,
         }
     }
 
+    pub fn with_schema_write_mode(mut self, schema_write_mode: SchemaWriteMode) -> Self {
+        self.schema_write_mode = schema_write_mode;
+        self
+    }
+
+    /// Compress the large BCS columns of transactions and objects as they are written.
+    pub fn with_bcs_compression(mut self, compress_bcs: bool) -> Self {
+        self.compress_bcs = compress_bcs;
+        self
+    }
+
     fn get_latest_tx_checkpoint_sequence_number(&self) -> Result<Option<u64>, IndexerError> {
         read_only_blocking!(&self.blocking_cp, |conn| {
             checkpoints::dsl::checkpoints
@@ -180,7 +211,11 @@ impl PgIndexerStoreV2 {
         for object in objects {
             match object {
                 ObjectChangeToCommit::MutatedObject(o) => {
-                    mutated_objects.push(o);
+                    mutated_objects.push(if self.compress_bcs {
+                        o.compress_bcs()?
+                    } else {
+                        o
+                    });
                 }
                 ObjectChangeToCommit::DeletedObject(id) => {
                     deleted_object_ids.push(id);
@@ -215,6 +250,8 @@ impl PgIndexerStoreV2 {
                             objects::df_name.eq(excluded(objects::df_name)),
                             objects::df_object_type.eq(excluded(objects::df_object_type)),
                             objects::df_object_id.eq(excluded(objects::df_object_id)),
+                            objects::bcs_codec.eq(excluded(objects::bcs_codec)),
+                            objects::df_key_id.eq(excluded(objects::df_key_id)),
                         ))
                         .execute(conn)
                         .map_err(IndexerError::from)
@@ -263,6 +300,10 @@ impl PgIndexerStoreV2 {
             .checkpoint_db_commit_latency_checkpoints
             .start_timer();
 
+        let checkpoint_timestamps = checkpoints
+            .iter()
+            .map(StoredCheckpointTimestamp::from)
+            .collect::<Vec<_>>();
         let checkpoints = checkpoints
             .iter()
             .map(StoredCheckpoint::from)
@@ -270,6 +311,16 @@ impl PgIndexerStoreV2 {
         transactional_blocking_with_retry!(
             &self.blocking_cp,
             |conn| {
+                for timestamp_chunk in
+                    checkpoint_timestamps.chunks(PG_COMMIT_CHUNK_SIZE_INTRA_DB_TX)
+                {
+                    diesel::insert_into(checkpoint_timestamps::table)
+                        .values(timestamp_chunk)
+                        .on_conflict_do_nothing()
+                        .execute(conn)
+                        .map_err(IndexerError::from)
+                        .context("Failed to write checkpoint timestamps to PostgresDB")?;
+                }
                 for checkpoint_chunk in checkpoints.chunks(PG_COMMIT_CHUNK_SIZE_INTRA_DB_TX) {
                     diesel::insert_into(checkpoints::table)
                         .values(checkpoint_chunk)
@@ -302,8 +353,15 @@ impl PgIndexerStoreV2 {
             .start_timer();
         let transactions = transactions
             .iter()
-            .map(StoredTransaction::from)
-            .collect::<Vec<_>>();
+            .map(|tx| {
+                let tx = StoredTransaction::from(tx);
+                if self.compress_bcs {
+                    tx.compress_bcs()
+                } else {
+                    Ok(tx)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
         drop(transformation_guard);
 
         transactional_blocking_with_retry!(
@@ -407,15 +465,25 @@ impl PgIndexerStoreV2 {
             .checkpoint_db_commit_latency_tx_indices_chunks
             .start_timer();
         let len = indices.len();
-        let (senders, recipients, input_objects, changed_objects, calls) =
+        let legacy_indices = (self.schema_write_mode == SchemaWriteMode::Dual)
+            .then(|| indices.iter().map(StoredTxIndex::from).collect::<Vec<_>>());
+        let (senders, recipients, input_objects, changed_objects, calls, dependencies) =
             indices.into_iter().map(|i| i.split()).fold(
-                (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()),
+                (
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                ),
                 |(
                     mut tx_senders,
                     mut tx_recipients,
                     mut tx_input_objects,
                     mut tx_changed_objects,
                     mut tx_calls,
+                    mut tx_dependencies,
                 ),
                  index| {
                     tx_senders.extend(index.0);
@@ -423,6 +491,7 @@ impl PgIndexerStoreV2 {
                     tx_input_objects.extend(index.2);
                     tx_changed_objects.extend(index.3);
                     tx_calls.extend(index.4);
+                    tx_dependencies.extend(index.5);
 
                     (
                         tx_senders,
@@ -430,6 +499,7 @@ impl PgIndexerStoreV2 {
                         tx_input_objects,
                         tx_changed_objects,
                         tx_calls,
+                        tx_dependencies,
                     )
                 },
             );
@@ -548,6 +618,60 @@ impl PgIndexerStoreV2 {
                 info!(elapsed, "Persisted {} rows to tx_calls tables", calls_len);
             })
         }));
+        futures.push(self.spawn_blocking_task(move |this| {
+            let now = Instant::now();
+            let dependencies_len = dependencies.len();
+            transactional_blocking_with_retry!(
+                &this.blocking_cp,
+                |conn| {
+                    for chunk in dependencies.chunks(PG_COMMIT_CHUNK_SIZE_INTRA_DB_TX) {
+                        diesel::insert_into(tx_dependencies::table)
+                            .values(chunk)
+                            .on_conflict_do_nothing()
+                            .execute(conn)
+                            .map_err(IndexerError::from)
+                            .context("Failed to write tx_dependencies chunk to PostgresDB")?;
+                    }
+                    Ok::<(), IndexerError>(())
+                },
+                Duration::from_secs(60)
+            )
+            .tap(|_| {
+                let elapsed = now.elapsed().as_secs_f64();
+                info!(
+                    elapsed,
+                    "Persisted {} rows to tx_dependencies table", dependencies_len
+                );
+            })
+        }));
+        if let Some(legacy_indices) = legacy_indices {
+            futures.push(self.spawn_blocking_task(move |this| {
+                let now = Instant::now();
+                let legacy_indices_len = legacy_indices.len();
+                transactional_blocking_with_retry!(
+                    &this.blocking_cp,
+                    |conn| {
+                        for chunk in legacy_indices.chunks(PG_COMMIT_CHUNK_SIZE_INTRA_DB_TX) {
+                            diesel::insert_into(tx_indices::table)
+                                .values(chunk)
+                                .on_conflict_do_nothing()
+                                .execute(conn)
+                                .map_err(IndexerError::from)
+                                .context("Failed to write tx_indices chunk to PostgresDB")?;
+                        }
+                        Ok::<(), IndexerError>(())
+                    },
+                    Duration::from_secs(60)
+                )
+                .tap(|_| {
+                    let elapsed = now.elapsed().as_secs_f64();
+                    info!(
+                        elapsed,
+                        "Persisted {} rows to tx_indices table", legacy_indices_len
+                    );
+                })
+            }));
+        }
         futures::future::join_all(futures)
             .await
             .into_iter()
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::errors::IndexerError;
-use move_core_types::language_storage::StructTag;
+use move_core_types::language_storage::{StructTag, TypeTag};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use sui_json_rpc_types::ObjectChange;
@@ -10,7 +10,7 @@ use sui_types::base_types::{ObjectDigest, SequenceNumber};
 use sui_types::base_types::{ObjectID, SuiAddress};
 use sui_types::crypto::AggregateAuthoritySignature;
 use sui_types::digests::TransactionDigest;
-use sui_types::dynamic_field::DynamicFieldInfo;
+use sui_types::dynamic_field::{derive_dynamic_field_id, DynamicFieldInfo};
 use sui_types::effects::TransactionEffects;
 use sui_types::event::SystemEpochInfoEvent;
 use sui_types::messages_checkpoint::{
@@ -255,6 +255,8 @@ pub struct IndexedObject {
     pub coin_type: Option<String>,
     pub coin_balance: Option<u64>,
     pub df_info: Option<DynamicFieldInfo>,
+    /// See [`dynamic_field_key_id`], set for dynamic fields.
+    pub df_key_id: Option<ObjectID>,
 }
 
 impl IndexedObject {
@@ -272,6 +274,15 @@ impl IndexedObject {
         } else {
             None
         };
+        // The parent of a dynamic field is the object owning it.
+        let df_key_id = match (&df_info, owner_id) {
+            (Some(info), Some(parent)) => Some(dynamic_field_key_id(
+                parent,
+                &info.name.type_,
+                &info.bcs_name,
+            )),
+            _ => None,
+        };
 
         Self {
             checkpoint_sequence_number,
@@ -284,10 +295,24 @@ impl IndexedObject {
             coin_type,
             coin_balance,
             df_info,
+            df_key_id,
         }
     }
 }
 
+/// ID a dynamic field of `parent` named by the BCS bytes `bcs_name` of type `name_type` is indexed
+/// under, whether it is a dynamic field or a dynamic object field, so that it is found with a
+/// single lookup. This is the ID of the `Field` object of a dynamic field, while the one of a
+/// dynamic object field is derived from its name wrapped in `dynamic_object_field::Wrapper`.
+pub fn dynamic_field_key_id(
+    parent: impl Into<SuiAddress>,
+    name_type: &TypeTag,
+    bcs_name: &[u8],
+) -> ObjectID {
+    derive_dynamic_field_id(parent, name_type, bcs_name)
+        .expect("deriving dynamic field id can't fail")
+}
+
 #[derive(Debug)]
 pub struct IndexedPackage {
     pub package_id: ObjectID,
@@ -309,7 +334,9 @@ pub struct IndexedTransaction {
     pub effects: TransactionEffects,
     pub checkpoint_sequence_number: u64,
     pub timestamp_ms: u64,
+    /// In the canonical order of [`sort_object_changes`].
     pub object_changes: Vec<IndexedObjectChange>,
+    /// In the canonical order of [`sort_balance_changes`].
     pub balance_change: Vec<sui_json_rpc_types::BalanceChange>,
     pub events: Vec<sui_types::event::Event>,
     pub transaction_kind: TransactionKind,
@@ -327,6 +354,8 @@ pub struct TxIndex {
     pub senders: Vec<SuiAddress>,
     pub recipients: Vec<SuiAddress>,
     pub move_calls: Vec<(ObjectID, String, String)>,
+    /// Transactions this transaction depends on, from its effects.
+    pub dependencies: Vec<TransactionDigest>,
 }
 
 // ObjectChange is not bcs deserializable, IndexedObjectChange is.
@@ -387,6 +416,65 @@ pub enum IndexedObjectChange {
     },
 }
 
+impl IndexedObjectChange {
+    /// ID of the object changed, or of the package published.
+    pub fn object_id(&self) -> ObjectID {
+        match self {
+            Self::Published { package_id, .. } => *package_id,
+            Self::Transferred { object_id, .. }
+            | Self::Mutated { object_id, .. }
+            | Self::Deleted { object_id, .. }
+            | Self::Wrapped { object_id, .. }
+            | Self::Created { object_id, .. } => *object_id,
+        }
+    }
+
+    pub fn version(&self) -> SequenceNumber {
+        match self {
+            Self::Published { version, .. }
+            | Self::Transferred { version, .. }
+            | Self::Mutated { version, .. }
+            | Self::Deleted { version, .. }
+            | Self::Wrapped { version, .. }
+            | Self::Created { version, .. } => *version,
+        }
+    }
+
+    /// Rank of the kind of change in the canonical order.
+    fn kind_rank(&self) -> u8 {
+        match self {
+            Self::Published { .. } => 0,
+            Self::Created { .. } => 1,
+            Self::Mutated { .. } => 2,
+            Self::Transferred { .. } => 3,
+            Self::Wrapped { .. } => 4,
+            Self::Deleted { .. } => 5,
+        }
+    }
+}
+
+/// Sort the object changes of a transaction in their canonical order: by object ID, then by kind
+/// of change (published, created, mutated, transferred, wrapped, deleted), then by version.
+///
+/// Object changes are derived from the effects of a transaction, whose order of changed objects
+/// is not part of the protocol, so they are stored and served in this order instead, for clients
+/// to see the same order whichever indexer version indexed the transaction.
+pub fn sort_object_changes(object_changes: &mut [IndexedObjectChange]) {
+    object_changes.sort_by(|a, b| {
+        (a.object_id(), a.kind_rank(), a.version()).cmp(&(
+            b.object_id(),
+            b.kind_rank(),
+            b.version(),
+        ))
+    });
+}
+
+/// Sort the balance changes of a transaction in their canonical order: by owner, then by coin
+/// type, for the same reason as [`sort_object_changes`].
+pub fn sort_balance_changes(balance_changes: &mut [sui_json_rpc_types::BalanceChange]) {
+    balance_changes.sort_by(|a, b| (a.owner, &a.coin_type).cmp(&(b.owner, &b.coin_type)));
+}
+
 impl From<ObjectChange> for IndexedObjectChange {
     fn from(oc: ObjectChange) -> Self {
         match oc {
@@ -560,3 +648,163 @@ impl From<IndexedObjectChange> for ObjectChange {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use sui_json_rpc_types::BalanceChange;
+    use sui_types::gas_coin::GasCoin;
+    use sui_types::TypeTag;
+
+    fn object_change(kind: u8, id: [u8; 32], version: u64) -> IndexedObjectChange {
+        let sender = SuiAddress::ZERO;
+        let owner = Owner::AddressOwner(sender);
+        let object_type = GasCoin::type_();
+        let object_id = ObjectID::new(id);
+        let version = SequenceNumber::from_u64(version);
+        let digest = ObjectDigest::MIN;
+        match kind {
+            0 => IndexedObjectChange::Published {
+                package_id: object_id,
+                version,
+                digest,
+                modules: vec![],
+            },
+            1 => IndexedObjectChange::Created {
+                sender,
+                owner,
+                object_type,
+                object_id,
+                version,
+                digest,
+            },
+            2 => IndexedObjectChange::Mutated {
+                sender,
+                owner,
+                object_type,
+                object_id,
+                version,
+                previous_version: SequenceNumber::new(),
+                digest,
+            },
+            3 => IndexedObjectChange::Transferred {
+                sender,
+                recipient: owner,
+                object_type,
+                object_id,
+                version,
+                digest,
+            },
+            4 => IndexedObjectChange::Wrapped {
+                sender,
+                object_type,
+                object_id,
+                version,
+            },
+            _ => IndexedObjectChange::Deleted {
+                sender,
+                object_type,
+                object_id,
+                version,
+            },
+        }
+    }
+
+    fn object_changes() -> impl Strategy<Value = Vec<IndexedObjectChange>> {
+        // Few distinct IDs, so that the same object shows up in several changes.
+        prop::collection::vec((0u8..6, 0u8..4, 0u64..4), 0..20).prop_map(|changes| {
+            changes
+                .into_iter()
+                .map(|(kind, id, version)| object_change(kind, [id; 32], version))
+                .collect()
+        })
+    }
+
+    fn balance_changes() -> impl Strategy<Value = Vec<BalanceChange>> {
+        prop::collection::vec((0u8..4, any::<bool>(), any::<i64>()), 0..20).prop_map(|changes| {
+            changes
+                .into_iter()
+                .map(|(owner, gas, amount)| BalanceChange {
+                    owner: Owner::AddressOwner(SuiAddress::from(ObjectID::new([owner; 32]))),
+                    coin_type: if gas {
+                        TypeTag::Struct(Box::new(GasCoin::type_()))
+                    } else {
+                        TypeTag::U64
+                    },
+                    amount: amount.into(),
+                })
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_object_change_order_is_canonical(
+            (changes, shuffled) in object_changes()
+                .prop_flat_map(|changes| (Just(changes.clone()), Just(changes).prop_shuffle()))
+        ) {
+            let (mut changes, mut shuffled) = (changes, shuffled);
+            sort_object_changes(&mut changes);
+            sort_object_changes(&mut shuffled);
+            prop_assert_eq!(&changes, &shuffled);
+            for pair in changes.windows(2) {
+                prop_assert!(pair[0].object_id() <= pair[1].object_id());
+            }
+        }
+
+        #[test]
+        fn test_balance_change_order_is_canonical(
+            (changes, shuffled) in balance_changes()
+                .prop_flat_map(|changes| (Just(changes.clone()), Just(changes).prop_shuffle()))
+        ) {
+            let (mut changes, mut shuffled) = (changes, shuffled);
+            sort_balance_changes(&mut changes);
+            sort_balance_changes(&mut shuffled);
+            // A transaction has a single balance change per owner and coin type, so only those
+            // are compared.
+            let keys = |changes: &[BalanceChange]| -> Vec<_> {
+                changes.iter().map(|c| (c.owner, c.coin_type.clone())).collect()
+            };
+            prop_assert_eq!(keys(&changes), keys(&shuffled));
+            for pair in changes.windows(2) {
+                prop_assert!(pair[0].owner <= pair[1].owner);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dynamic_field_key_id() {
+        use sui_types::dynamic_field::{
+            derive_dynamic_object_field_id, DynamicFieldName, DynamicFieldType,
+        };
+
+        let parent = ObjectID::random();
+        let name_type = TypeTag::U64;
+        let bcs_name = bcs::to_bytes(&7u64).unwrap();
+        let field_id = derive_dynamic_field_id(parent, &name_type, &bcs_name).unwrap();
+        let object_field_id =
+            derive_dynamic_object_field_id(parent, &name_type, &bcs_name).unwrap();
+        let key_id = dynamic_field_key_id(parent, &name_type, &bcs_name);
+        // Dynamic fields are indexed under their own ID, dynamic object fields aren't.
+        assert_eq!(key_id, field_id);
+        assert_ne!(key_id, object_field_id);
+
+        let object = Object::with_object_owner_for_testing(object_field_id, parent);
+        let df_info = DynamicFieldInfo {
+            name: DynamicFieldName {
+                type_: name_type,
+                value: serde_json::json!("7"),
+            },
+            bcs_name,
+            type_: DynamicFieldType::DynamicObject,
+            object_type: GasCoin::type_().to_canonical_string(/* with_prefix */ true),
+            object_id: ObjectID::random(),
+            version: object.version(),
+            digest: object.digest(),
+        };
+        let indexed = IndexedObject::from_object(1, object.clone(), Some(df_info));
+        assert_eq!(indexed.df_key_id, Some(key_id));
+        assert_eq!(IndexedObject::from_object(1, object, None).df_key_id, None);
+    }
+}
@@ -4,8 +4,10 @@
 use crate::PgPoolConnection;
 use anyhow::anyhow;
 use diesel::migration::MigrationSource;
+use diesel::pg::Pg;
 use diesel::{PgConnection, RunQueryDsl};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::collections::HashSet;
 use tracing::info;
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
@@ -37,6 +39,45 @@ pub fn reset_database(
     Ok(())
 }
 
+/// Migrations this binary knows about that have not been applied to the database, and versions
+/// applied to the database that this binary does not know about.
+pub fn migration_drift(
+    conn: &mut PgConnection,
+    use_v2: bool,
+) -> Result<(Vec<String>, Vec<String>), anyhow::Error> {
+    let migration = if use_v2 { MIGRATIONS_V2 } else { MIGRATIONS };
+    let pending = conn
+        .pending_migrations(migration)
+        .map_err(|e| anyhow!("Failed to read pending migrations {e}"))?
+        .iter()
+        .map(|m| m.name().to_string())
+        .collect();
+    let known: HashSet<String> = MigrationSource::<Pg>::migrations(&migration)
+        .map_err(|e| anyhow!("Failed to read embedded migrations {e}"))?
+        .iter()
+        .map(|m| m.name().version().to_string())
+        .collect();
+    let unknown = conn
+        .applied_migrations()
+        .map_err(|e| anyhow!("Failed to read applied migrations {e}"))?
+        .into_iter()
+        .map(|version| version.to_string())
+        .filter(|version| !known.contains(version))
+        .collect();
+    Ok((pending, unknown))
+}
+
+/// Version of the latest migration this binary knows about, i.e. the schema version it expects.
+pub fn schema_version(use_v2: bool) -> Result<String, anyhow::Error> {
+    let migration = if use_v2 { MIGRATIONS_V2 } else { MIGRATIONS };
+    MigrationSource::<Pg>::migrations(&migration)
+        .map_err(|e| anyhow!("Failed to read embedded migrations {e}"))?
+        .iter()
+        .map(|m| m.name().version().to_string())
+        .max()
+        .ok_or_else(|| anyhow!("No embedded migrations"))
+}
+
 pub fn drop_all_tables(conn: &mut PgConnection) -> Result<(), diesel::result::Error> {
     info!("Dropping all tables in the database");
     let table_names: Vec<String> = diesel::dsl::sql::<diesel::sql_types::Text>(
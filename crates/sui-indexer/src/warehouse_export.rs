@@ -0,0 +1,704 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Export of the indexed transactions and events to files staged in an object store, for cloud
+//! warehouses like BigQuery or Snowflake to load.
+//!
+//! Committed checkpoints are exported in ranges of `checkpoints-per-file`, into one file per
+//! table and range, partitioned Hive style by the UTC date of the first checkpoint of the range:
+//!
+//! ```text
+//! <prefix>/<table>/_schema.json
+//! <prefix>/<table>/date=<yyyy-mm-dd>/<first>_<last>.<json|parquet>
+//! <prefix>/<table>/_manifests/<first>_<last>.json
+//! ```
+//!
+//! The schema lists the columns of the table in the BigQuery schema format. The manifest of a
+//! range is written after its file, so loaders only pick ranges whose file is complete.
+//!
+//! Exported ranges are recorded in the `warehouse_exports` table, and the next range starts
+//! after the last one recorded. Paths only depend on the range, so a range exported again, after
+//! failing between writing its files and recording it, overwrites the same files, which the
+//! loaders of both warehouses skip if they were already loaded.
+//!
+//! The storage and DB requests of a range can share a retry budget, set by
+//! `max-retries-per-range` and `max-retry-secs-per-range`, so that a range fails quickly when
+//! most requests fail instead of being retried for hours.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+use bytes::Bytes;
+use chrono::{TimeZone, Utc};
+use diesel::dsl::{max, min};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use fastcrypto::encoding::{Base64, Encoding};
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReader;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{error, info, warn};
+
+use sui_storage::object_store::retry::RetryBudget;
+use sui_storage::object_store::util::put;
+use sui_storage::object_store::ObjectStoreConfig;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::checkpoint_range::CheckpointRange;
+use sui_types::digests::TransactionDigest;
+
+use crate::errors::IndexerError;
+use crate::metrics::IndexerMetrics;
+use crate::models_v2::events::StoredEvent;
+use crate::models_v2::transactions::StoredTransaction;
+use crate::models_v2::warehouse_exports::StoredWarehouseExport;
+use crate::schema_v2::{checkpoints, events, transactions, warehouse_exports};
+use crate::store::diesel_macro::{read_only_blocking, transactional_blocking_with_retry};
+use crate::types_v2::IndexerResult;
+use crate::PgConnectionPool;
+
+/// Time to wait before looking for a range to export again, when none is complete yet or the
+/// last export failed.
+const IDLE_INTERVAL: Duration = Duration::from_secs(30);
+
+const PARQUET_BATCH_SIZE: usize = 1024;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarehouseFormat {
+    /// Newline delimited JSON, with BCS columns encoded in base64
+    #[default]
+    Ndjson,
+    /// Parquet, compressed with Snappy
+    Parquet,
+}
+
+impl WarehouseFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            WarehouseFormat::Ndjson => "json",
+            WarehouseFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Config of the export, read from the YAML file given by `--warehouse-export-config`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WarehouseExportConfig {
+    /// Store the files are staged in.
+    pub object_store: ObjectStoreConfig,
+    /// Prefix of all the paths in the store.
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub format: WarehouseFormat,
+    #[serde(default = "default_checkpoints_per_file")]
+    pub checkpoints_per_file: u64,
+    /// First checkpoint to export, if later than the earliest one indexed.
+    #[serde(default)]
+    pub start_checkpoint: u64,
+    /// Retries of the storage and DB requests of a range, after which its export fails.
+    #[serde(default)]
+    pub max_retries_per_range: Option<usize>,
+    /// Time after the first retry of a range past which its requests are no longer retried.
+    #[serde(default)]
+    pub max_retry_secs_per_range: Option<u64>,
+}
+
+impl WarehouseExportConfig {
+    fn retry_budget(&self) -> Option<RetryBudget> {
+        if self.max_retries_per_range.is_none() && self.max_retry_secs_per_range.is_none() {
+            return None;
+        }
+        Some(RetryBudget::new(
+            self.max_retries_per_range.unwrap_or(usize::MAX),
+            self.max_retry_secs_per_range
+                .map_or(Duration::MAX, Duration::from_secs),
+        ))
+    }
+}
+
+fn default_checkpoints_per_file() -> u64 {
+    10_000
+}
+
+/// Manifest of an exported range of a table, written once its file is complete.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct WarehouseManifest {
+    pub table: String,
+    pub first_checkpoint: i64,
+    pub last_checkpoint: i64,
+    pub format: WarehouseFormat,
+    pub files: Vec<String>,
+    pub row_count: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum ColumnType {
+    Int64,
+    String,
+}
+
+pub(crate) struct Column {
+    pub(crate) name: &'static str,
+    pub(crate) column_type: ColumnType,
+    pub(crate) nullable: bool,
+}
+
+const fn column(name: &'static str, column_type: ColumnType, nullable: bool) -> Column {
+    Column {
+        name,
+        column_type,
+        nullable,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Int64(Option<i64>),
+    String(Option<String>),
+}
+
+/// Table exported to the warehouse, with its columns in order.
+pub(crate) struct WarehouseTable {
+    pub(crate) name: &'static str,
+    pub(crate) columns: &'static [Column],
+    /// Rows of the checkpoints in the given range.
+    read_rows: fn(&PgConnectionPool, CheckpointRange) -> anyhow::Result<Vec<Vec<Value>>>,
+}
+
+pub(crate) const TRANSACTIONS: WarehouseTable = WarehouseTable {
+    name: "transactions",
+    columns: &[
+        column("tx_sequence_number", ColumnType::Int64, false),
+        column("transaction_digest", ColumnType::String, false),
+        column("checkpoint_sequence_number", ColumnType::Int64, false),
+        column("timestamp_ms", ColumnType::Int64, false),
+        column("transaction_kind", ColumnType::Int64, false),
+        column("success_command_count", ColumnType::Int64, false),
+        column("gas_price", ColumnType::Int64, true),
+        column("computation_cost", ColumnType::Int64, true),
+        column("storage_cost", ColumnType::Int64, true),
+        column("storage_rebate", ColumnType::Int64, true),
+        column("non_refundable_storage_fee", ColumnType::Int64, true),
+        // Base64 encoded BCS of the SenderSignedData and TransactionEffects.
+        column("raw_transaction", ColumnType::String, false),
+        column("raw_effects", ColumnType::String, false),
+    ],
+    read_rows: read_transaction_rows,
+};
+
+pub(crate) const EVENTS: WarehouseTable = WarehouseTable {
+    name: "events",
+    columns: &[
+        column("tx_sequence_number", ColumnType::Int64, false),
+        column("event_sequence_number", ColumnType::Int64, false),
+        column("transaction_digest", ColumnType::String, false),
+        column("checkpoint_sequence_number", ColumnType::Int64, false),
+        column("sender", ColumnType::String, true),
+        column("package", ColumnType::String, false),
+        column("module", ColumnType::String, false),
+        column("event_type", ColumnType::String, false),
+        column("timestamp_ms", ColumnType::Int64, false),
+        // Base64 encoded BCS of the event contents.
+        column("bcs", ColumnType::String, false),
+    ],
+    read_rows: read_event_rows,
+};
+
+pub(crate) const TABLES: [&WarehouseTable; 2] = [&TRANSACTIONS, &EVENTS];
+
+impl WarehouseTable {
+    /// Columns in the BigQuery schema format, also used to create Snowflake tables.
+    fn schema(&self) -> serde_json::Value {
+        self.columns
+            .iter()
+            .map(|column| {
+                json!({
+                    "name": column.name,
+                    "type": match column.column_type {
+                        ColumnType::Int64 => "INT64",
+                        ColumnType::String => "STRING",
+                    },
+                    "mode": if column.nullable { "NULLABLE" } else { "REQUIRED" },
+                })
+            })
+            .collect()
+    }
+
+    fn encode(&self, format: WarehouseFormat, rows: Vec<Vec<Value>>) -> anyhow::Result<Bytes> {
+        match format {
+            WarehouseFormat::Ndjson => self.encode_ndjson(rows),
+            WarehouseFormat::Parquet => self.encode_parquet(rows),
+        }
+    }
+
+    fn encode_ndjson(&self, rows: Vec<Vec<Value>>) -> anyhow::Result<Bytes> {
+        let mut bytes = vec![];
+        for row in rows {
+            let object: serde_json::Map<_, _> = self
+                .columns
+                .iter()
+                .zip(row)
+                .map(|(column, value)| {
+                    let value = match value {
+                        Value::Int64(value) => json!(value),
+                        Value::String(value) => json!(value),
+                    };
+                    (column.name.to_string(), value)
+                })
+                .collect();
+            serde_json::to_writer(&mut bytes, &object)?;
+            bytes.push(b'\n');
+        }
+        Ok(bytes.into())
+    }
+
+    fn encode_parquet(&self, rows: Vec<Vec<Value>>) -> anyhow::Result<Bytes> {
+        let mut arrays = vec![];
+        for (i, column) in self.columns.iter().enumerate() {
+            let array: ArrayRef = match column.column_type {
+                ColumnType::Int64 => Arc::new(
+                    rows.iter()
+                        .map(|row| match &row[i] {
+                            Value::Int64(value) => Ok(*value),
+                            value => Err(anyhow!("Unexpected {value:?} in {}", column.name)),
+                        })
+                        .collect::<anyhow::Result<Int64Array>>()?,
+                ),
+                ColumnType::String => Arc::new(
+                    rows.iter()
+                        .map(|row| match &row[i] {
+                            Value::String(value) => Ok(value.clone()),
+                            value => Err(anyhow!("Unexpected {value:?} in {}", column.name)),
+                        })
+                        .collect::<anyhow::Result<StringArray>>()?,
+                ),
+            };
+            arrays.push((column.name, array, column.nullable));
+        }
+        let batch = RecordBatch::try_from_iter_with_nullable(arrays)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut bytes = vec![];
+        let mut writer = ArrowWriter::try_new(&mut bytes, batch.schema(), Some(properties))?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(bytes.into())
+    }
+
+    /// Rows of a Parquet file of the table, the inverse of [`Self::encode`].
+    pub(crate) fn decode_parquet(&self, bytes: Bytes) -> anyhow::Result<Vec<Vec<Value>>> {
+        let mut rows = vec![];
+        for batch in ParquetRecordBatchReader::try_new(bytes, PARQUET_BATCH_SIZE)? {
+            let batch = batch?;
+            let mut batch_rows = vec![Vec::with_capacity(self.columns.len()); batch.num_rows()];
+            for column in self.columns {
+                let array = batch
+                    .column_by_name(column.name)
+                    .ok_or_else(|| anyhow!("Missing column {} of {}", column.name, self.name))?;
+                match column.column_type {
+                    ColumnType::Int64 => {
+                        let array = array
+                            .as_any()
+                            .downcast_ref::<Int64Array>()
+                            .ok_or_else(|| anyhow!("Column {} is not INT64", column.name))?;
+                        for (row, value) in batch_rows.iter_mut().zip(array.iter()) {
+                            row.push(Value::Int64(value));
+                        }
+                    }
+                    ColumnType::String => {
+                        let array = array
+                            .as_any()
+                            .downcast_ref::<StringArray>()
+                            .ok_or_else(|| anyhow!("Column {} is not STRING", column.name))?;
+                        for (row, value) in batch_rows.iter_mut().zip(array.iter()) {
+                            row.push(Value::String(value.map(str::to_string)));
+                        }
+                    }
+                }
+            }
+            rows.extend(batch_rows);
+        }
+        Ok(rows)
+    }
+}
+
+pub struct WarehouseExporter {
+    pool: PgConnectionPool,
+    store: Arc<DynObjectStore>,
+    config: WarehouseExportConfig,
+    /// Shared by the requests of the range being exported, reset for every range.
+    retry_budget: Option<Arc<RetryBudget>>,
+    metrics: IndexerMetrics,
+}
+
+impl WarehouseExporter {
+    pub fn new(
+        pool: PgConnectionPool,
+        config: WarehouseExportConfig,
+        metrics: IndexerMetrics,
+    ) -> anyhow::Result<Self> {
+        if config.checkpoints_per_file == 0 {
+            bail!("checkpoints-per-file must be positive");
+        }
+        let retry_budget = config.retry_budget().map(Arc::new);
+        let store = match &retry_budget {
+            Some(budget) => config.object_store.make_with_retry_budget(budget.clone())?,
+            None => config.object_store.make()?,
+        };
+        Ok(Self {
+            pool,
+            store,
+            config,
+            retry_budget,
+            metrics,
+        })
+    }
+
+    pub async fn run_forever(&self) {
+        info!("Warehouse export started");
+        loop {
+            match self.export_next_ranges().await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => warn!("Warehouse export failed, retrying: {e}"),
+            }
+            tokio::time::sleep(IDLE_INTERVAL).await;
+        }
+    }
+
+    /// Export the next range of checkpoints of every table, if all of its checkpoints are
+    /// committed, and return whether any was.
+    async fn export_next_ranges(&self) -> anyhow::Result<bool> {
+        let pool = self.pool.clone();
+        let Some(committed) =
+            tokio::task::spawn_blocking(move || get_checkpoint_range(&pool)).await??
+        else {
+            return Ok(false);
+        };
+        let mut exported = false;
+        for table in TABLES {
+            let pool = self.pool.clone();
+            let name = table.name;
+            let first = match tokio::task::spawn_blocking(move || {
+                get_last_exported_checkpoint(&pool, name)
+            })
+            .await??
+            {
+                Some(last_exported) => last_exported as u64 + 1,
+                None => committed.start().max(self.config.start_checkpoint),
+            };
+            let range = CheckpointRange::new(first, first + self.config.checkpoints_per_file);
+            if range.end() > committed.end() {
+                continue;
+            }
+            if let Some(budget) = &self.retry_budget {
+                budget.reset();
+            }
+            let result = self.export_range(table, range).await;
+            if let Some(budget) = &self.retry_budget {
+                self.metrics
+                    .warehouse_export_retries
+                    .inc_by(budget.retries() as u64);
+                if result.is_err() && budget.is_exhausted() {
+                    self.metrics.warehouse_export_retry_budget_exhausted.inc();
+                    error!(
+                        "Export of checkpoints {range} of {} exhausted its retry budget after {} retries",
+                        table.name,
+                        budget.retries()
+                    );
+                }
+            }
+            result?;
+            exported = true;
+        }
+        Ok(exported)
+    }
+
+    async fn export_range(
+        &self,
+        table: &'static WarehouseTable,
+        range: CheckpointRange,
+    ) -> anyhow::Result<()> {
+        // Files, manifests and rows of the exports are named by their first and last checkpoints
+        let first = range.start() as i64;
+        let last = range.last().context("Exported ranges can't be empty")? as i64;
+        let pool = self.pool.clone();
+        let (timestamp_ms, rows) = tokio::task::spawn_blocking(move || {
+            let timestamp_ms = get_checkpoint_timestamp(&pool, first)?;
+            let rows = (table.read_rows)(&pool, range)?;
+            Ok::<_, anyhow::Error>((timestamp_ms, rows))
+        })
+        .await??;
+
+        let row_count = rows.len();
+        let format = self.config.format;
+        let bytes = tokio::task::spawn_blocking(move || table.encode(format, rows)).await??;
+        let date = Utc
+            .timestamp_millis_opt(timestamp_ms)
+            .single()
+            .ok_or_else(|| anyhow!("Invalid timestamp {timestamp_ms} of checkpoint {first}"))?
+            .format("%Y-%m-%d");
+        let table_dir = self.table_dir(table);
+        let file_path = table_dir
+            .child(format!("date={date}"))
+            .child(format!("{first}_{last}.{}", format.extension()));
+        put(&self.store, &file_path, bytes).await?;
+
+        put(
+            &self.store,
+            &table_dir.child("_schema.json"),
+            serde_json::to_vec_pretty(&table.schema())?.into(),
+        )
+        .await?;
+        let manifest = WarehouseManifest {
+            table: table.name.to_string(),
+            first_checkpoint: first,
+            last_checkpoint: last,
+            format,
+            files: vec![file_path.to_string()],
+            row_count,
+        };
+        put(
+            &self.store,
+            &table_dir
+                .child("_manifests")
+                .child(format!("{first}_{last}.json")),
+            serde_json::to_vec_pretty(&manifest)?.into(),
+        )
+        .await?;
+
+        let export = StoredWarehouseExport {
+            table_name: table.name.to_string(),
+            first_checkpoint: first,
+            last_checkpoint: last,
+            file_path: file_path.to_string(),
+            row_count: row_count as i64,
+            exported_at_ms: Utc::now().timestamp_millis(),
+        };
+        let pool = self.pool.clone();
+        let retry_budget = self.retry_budget.clone();
+        tokio::task::spawn_blocking(move || persist_export(&pool, export, retry_budget.as_deref()))
+            .await??;
+        self.metrics
+            .warehouse_export_rows
+            .with_label_values(&[table.name])
+            .inc_by(row_count as u64);
+        info!(
+            "Exported checkpoints {first} to {last} of {} to {file_path}, {row_count} rows",
+            table.name
+        );
+        Ok(())
+    }
+
+    fn table_dir(&self, table: &WarehouseTable) -> Path {
+        table_dir(&self.config.prefix, table)
+    }
+}
+
+/// Directory of the files of `table` under `prefix`.
+pub(crate) fn table_dir(prefix: &str, table: &WarehouseTable) -> Path {
+    let prefix = Path::from(prefix);
+    if prefix.as_ref().is_empty() {
+        Path::from(table.name)
+    } else {
+        prefix.child(table.name)
+    }
+}
+
+fn transaction_row(tx: StoredTransaction) -> anyhow::Result<Vec<Value>> {
+    let digest = TransactionDigest::try_from(tx.transaction_digest.as_slice())?;
+    Ok(vec![
+        Value::Int64(Some(tx.tx_sequence_number)),
+        Value::String(Some(digest.to_string())),
+        Value::Int64(Some(tx.checkpoint_sequence_number)),
+        Value::Int64(Some(tx.timestamp_ms)),
+        Value::Int64(Some(tx.transaction_kind as i64)),
+        Value::Int64(Some(tx.success_command_count as i64)),
+        Value::Int64(tx.gas_price),
+        Value::Int64(tx.computation_cost),
+        Value::Int64(tx.storage_cost),
+        Value::Int64(tx.storage_rebate),
+        Value::Int64(tx.non_refundable_storage_fee),
+        Value::String(Some(Base64::encode(&tx.raw_transaction))),
+        Value::String(Some(Base64::encode(&tx.raw_effects))),
+    ])
+}
+
+fn event_row(event: StoredEvent) -> anyhow::Result<Vec<Value>> {
+    let digest = TransactionDigest::try_from(event.transaction_digest.as_slice())?;
+    let sender = match event.senders.first() {
+        Some(Some(sender)) => Some(SuiAddress::from_bytes(sender)?.to_string()),
+        _ => None,
+    };
+    Ok(vec![
+        Value::Int64(Some(event.tx_sequence_number)),
+        Value::Int64(Some(event.event_sequence_number)),
+        Value::String(Some(digest.to_string())),
+        Value::Int64(Some(event.checkpoint_sequence_number)),
+        Value::String(sender),
+        Value::String(Some(
+            ObjectID::from_bytes(&event.package)?.to_hex_uncompressed(),
+        )),
+        Value::String(Some(event.module)),
+        Value::String(Some(event.event_type)),
+        Value::Int64(Some(event.timestamp_ms)),
+        Value::String(Some(Base64::encode(&event.bcs))),
+    ])
+}
+
+/// Range of the committed checkpoints, if any.
+fn get_checkpoint_range(pool: &PgConnectionPool) -> IndexerResult<Option<CheckpointRange>> {
+    let (earliest, latest) = read_only_blocking!(pool, |conn| {
+        checkpoints::table
+            .select((
+                min(checkpoints::sequence_number),
+                max(checkpoints::sequence_number),
+            ))
+            .first::<(Option<i64>, Option<i64>)>(conn)
+    })?;
+    Ok(earliest
+        .zip(latest)
+        .map(|(earliest, latest)| CheckpointRange::inclusive(earliest as u64, latest as u64)))
+}
+
+fn get_checkpoint_timestamp(pool: &PgConnectionPool, checkpoint: i64) -> IndexerResult<i64> {
+    read_only_blocking!(pool, |conn| {
+        checkpoints::table
+            .find(checkpoint)
+            .select(checkpoints::timestamp_ms)
+            .first::<i64>(conn)
+    })
+}
+
+fn get_last_exported_checkpoint(
+    pool: &PgConnectionPool,
+    table_name: &str,
+) -> IndexerResult<Option<i64>> {
+    read_only_blocking!(pool, |conn| {
+        warehouse_exports::table
+            .filter(warehouse_exports::table_name.eq(table_name))
+            .select(max(warehouse_exports::last_checkpoint))
+            .first::<Option<i64>>(conn)
+    })
+}
+
+fn read_transaction_rows(
+    pool: &PgConnectionPool,
+    range: CheckpointRange,
+) -> anyhow::Result<Vec<Vec<Value>>> {
+    let transactions: Vec<StoredTransaction> = read_only_blocking!(pool, |conn| {
+        transactions::table
+            .filter(transactions::checkpoint_sequence_number.ge(range.start() as i64))
+            .filter(transactions::checkpoint_sequence_number.lt(range.end() as i64))
+            .order(transactions::tx_sequence_number.asc())
+            .load::<StoredTransaction>(conn)
+    })?;
+    transactions.into_iter().map(transaction_row).collect()
+}
+
+fn read_event_rows(
+    pool: &PgConnectionPool,
+    range: CheckpointRange,
+) -> anyhow::Result<Vec<Vec<Value>>> {
+    let events: Vec<StoredEvent> = read_only_blocking!(pool, |conn| {
+        events::table
+            .filter(events::checkpoint_sequence_number.ge(range.start() as i64))
+            .filter(events::checkpoint_sequence_number.lt(range.end() as i64))
+            .order((
+                events::tx_sequence_number.asc(),
+                events::event_sequence_number.asc(),
+            ))
+            .load::<StoredEvent>(conn)
+    })?;
+    events.into_iter().map(event_row).collect()
+}
+
+fn persist_export(
+    pool: &PgConnectionPool,
+    export: StoredWarehouseExport,
+    retry_budget: Option<&RetryBudget>,
+) -> IndexerResult<()> {
+    transactional_blocking_with_retry!(
+        pool,
+        |conn| {
+            diesel::insert_into(warehouse_exports::table)
+                .values(&export)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+            Ok::<(), IndexerError>(())
+        },
+        Duration::from_secs(60),
+        retry_budget
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<Vec<Value>> {
+        vec![
+            vec![
+                Value::Int64(Some(7)),
+                Value::Int64(Some(0)),
+                Value::String(Some("digest".to_string())),
+                Value::Int64(Some(3)),
+                Value::String(None),
+                Value::String(Some("0x2".to_string())),
+                Value::String(Some("coin".to_string())),
+                Value::String(Some("0x2::coin::Mint".to_string())),
+                Value::Int64(Some(1_698_796_800_000)),
+                Value::String(Some(Base64::encode(b"bcs"))),
+            ];
+            2
+        ]
+    }
+
+    #[test]
+    fn test_encode_ndjson() -> anyhow::Result<()> {
+        let bytes = EVENTS.encode(WarehouseFormat::Ndjson, rows())?;
+        let lines = std::str::from_utf8(&bytes)?.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let row: serde_json::Value = serde_json::from_str(lines[0])?;
+        assert_eq!(row["tx_sequence_number"], 7);
+        assert_eq!(row["sender"], serde_json::Value::Null);
+        assert_eq!(row["event_type"], "0x2::coin::Mint");
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_parquet() -> anyhow::Result<()> {
+        let bytes = EVENTS.encode(WarehouseFormat::Parquet, rows())?;
+        let reader = parquet::arrow::arrow_reader::ParquetRecordBatchReader::try_new(bytes, 1024)?;
+        let batches = reader.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+        assert_eq!(batches[0].num_columns(), EVENTS.columns.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_parquet() -> anyhow::Result<()> {
+        let bytes = EVENTS.encode(WarehouseFormat::Parquet, rows())?;
+        assert_eq!(EVENTS.decode_parquet(bytes)?, rows());
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = TRANSACTIONS.schema();
+        assert_eq!(schema.as_array().unwrap().len(), TRANSACTIONS.columns.len());
+        assert_eq!(
+            schema[6],
+            json!({"name": "gas_price", "type": "INT64", "mode": "NULLABLE"})
+        );
+    }
+}
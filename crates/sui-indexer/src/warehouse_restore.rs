@@ -0,0 +1,527 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Restore of the `transactions` and `events` tables from the Parquet files of the warehouse
+//! export, see `warehouse_export.rs`, which is much faster than reindexing them from the chain
+//! after losing the database.
+//!
+//! Ranges are loaded with `COPY`, `parallelism` of them at a time, each in a transaction that
+//! first deletes the rows already restored in the range, and records the range in
+//! `warehouse_exports` so that the export resumes after it. An interrupted restore can be run
+//! again from the start.
+//!
+//! Every transaction range is restored before the event ranges, which also fill the events of
+//! their transactions. The export doesn't have the object and balance changes of transactions,
+//! restored empty, nor more than one sender per event. The other tables, e.g. checkpoints and
+//! the transaction indices, aren't exported and are left to the indexer to rebuild.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use bytes::Bytes;
+use chrono::Utc;
+use fastcrypto::encoding::{Base64, Encoding, Hex};
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use move_core_types::identifier::Identifier;
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use tokio_postgres::{Client, NoTls, Transaction};
+use tracing::{info, warn};
+
+use sui_storage::object_store::util::get;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::digests::TransactionDigest;
+use sui_types::event::Event;
+use sui_types::parse_sui_struct_tag;
+
+use crate::models_v2::bcs_codec::BcsCodec;
+use crate::warehouse_export::{
+    table_dir, Value, WarehouseExportConfig, WarehouseFormat, WarehouseManifest, WarehouseTable,
+    EVENTS, TRANSACTIONS,
+};
+
+const TRANSACTION_COLUMNS: &[&str] = &[
+    "tx_sequence_number",
+    "transaction_digest",
+    "raw_transaction",
+    "raw_effects",
+    "checkpoint_sequence_number",
+    "timestamp_ms",
+    "object_changes",
+    "balance_changes",
+    "events",
+    "transaction_kind",
+    "success_command_count",
+    "gas_price",
+    "computation_cost",
+    "storage_cost",
+    "storage_rebate",
+    "non_refundable_storage_fee",
+    "bcs_codec",
+];
+
+const EVENT_COLUMNS: &[&str] = &[
+    "tx_sequence_number",
+    "event_sequence_number",
+    "transaction_digest",
+    "checkpoint_sequence_number",
+    "senders",
+    "package",
+    "module",
+    "event_type",
+    "timestamp_ms",
+    "bcs",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreSummary {
+    pub ranges: usize,
+    pub transactions: u64,
+    pub events: u64,
+}
+
+pub struct WarehouseRestorer {
+    db_url: String,
+    store: Arc<DynObjectStore>,
+    prefix: String,
+    parallelism: usize,
+}
+
+impl WarehouseRestorer {
+    /// Restorer of the files exported with `config` into the database at `db_url`.
+    pub fn new(
+        db_url: String,
+        config: &WarehouseExportConfig,
+        parallelism: usize,
+    ) -> anyhow::Result<Self> {
+        if parallelism == 0 {
+            bail!("Restore parallelism must be positive");
+        }
+        Ok(Self {
+            db_url,
+            store: config.object_store.make()?,
+            prefix: config.prefix.clone(),
+            parallelism,
+        })
+    }
+
+    pub async fn restore(&self) -> anyhow::Result<RestoreSummary> {
+        let mut summary = RestoreSummary::default();
+        for table in [&TRANSACTIONS, &EVENTS] {
+            let manifests = self.list_manifests(table).await?;
+            info!("Restoring {} ranges of {}", manifests.len(), table.name);
+            summary.ranges += manifests.len();
+            let rows: u64 = futures::stream::iter(manifests)
+                .map(|manifest| self.restore_range(table, manifest))
+                .buffer_unordered(self.parallelism)
+                .try_fold(0, |total, rows| async move { Ok(total + rows) })
+                .await?;
+            if table.name == TRANSACTIONS.name {
+                summary.transactions = rows;
+            } else {
+                summary.events = rows;
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Manifests of the exported ranges of `table`, by first checkpoint.
+    async fn list_manifests(
+        &self,
+        table: &WarehouseTable,
+    ) -> anyhow::Result<Vec<WarehouseManifest>> {
+        let manifests_dir = table_dir(&self.prefix, table).child("_manifests");
+        let paths: Vec<Path> = self
+            .store
+            .list(Some(&manifests_dir))
+            .await?
+            .map_ok(|object| object.location)
+            .try_collect()
+            .await?;
+        let mut manifests = vec![];
+        for path in paths {
+            let bytes = get(&self.store, &path).await?;
+            let manifest: WarehouseManifest = serde_json::from_slice(&bytes)
+                .map_err(|e| anyhow!("Failed to parse manifest {path}: {e}"))?;
+            if manifest.format != WarehouseFormat::Parquet {
+                bail!(
+                    "Only Parquet exports can be restored, checkpoints {} to {} of {} are {:?}",
+                    manifest.first_checkpoint,
+                    manifest.last_checkpoint,
+                    table.name,
+                    manifest.format
+                );
+            }
+            manifests.push(manifest);
+        }
+        manifests.sort_by_key(|manifest| manifest.first_checkpoint);
+        Ok(manifests)
+    }
+
+    async fn restore_range(
+        &self,
+        table: &'static WarehouseTable,
+        manifest: WarehouseManifest,
+    ) -> anyhow::Result<u64> {
+        let mut rows = vec![];
+        for file in &manifest.files {
+            let bytes = get(&self.store, &Path::from(file.as_str())).await?;
+            rows.extend(tokio::task::spawn_blocking(move || table.decode_parquet(bytes)).await??);
+        }
+        if rows.len() != manifest.row_count {
+            bail!(
+                "Files of checkpoints {} to {} of {} have {} rows, expected {}",
+                manifest.first_checkpoint,
+                manifest.last_checkpoint,
+                table.name,
+                rows.len(),
+                manifest.row_count
+            );
+        }
+
+        let mut client = self.connect().await?;
+        let transaction = client.transaction().await?;
+        transaction
+            .execute(
+                format!(
+                    "DELETE FROM {} WHERE checkpoint_sequence_number BETWEEN $1 AND $2",
+                    table.name
+                )
+                .as_str(),
+                &[&manifest.first_checkpoint, &manifest.last_checkpoint],
+            )
+            .await?;
+        let copied = if table.name == TRANSACTIONS.name {
+            let records = tokio::task::spawn_blocking(move || transaction_records(rows)).await??;
+            copy_in(&transaction, table.name, TRANSACTION_COLUMNS, records).await?
+        } else {
+            let (records, tx_events) =
+                tokio::task::spawn_blocking(move || event_records(rows)).await??;
+            let copied = copy_in(&transaction, table.name, EVENT_COLUMNS, records).await?;
+            restore_transaction_events(&transaction, tx_events).await?;
+            copied
+        };
+        transaction
+            .execute(
+                "INSERT INTO warehouse_exports \
+                (table_name, first_checkpoint, last_checkpoint, file_path, row_count, exported_at_ms) \
+                VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+                &[
+                    &table.name,
+                    &manifest.first_checkpoint,
+                    &manifest.last_checkpoint,
+                    &manifest.files.join(","),
+                    &(manifest.row_count as i64),
+                    &Utc::now().timestamp_millis(),
+                ],
+            )
+            .await?;
+        transaction.commit().await?;
+        info!(
+            "Restored checkpoints {} to {} of {}, {copied} rows",
+            manifest.first_checkpoint, manifest.last_checkpoint, table.name
+        );
+        Ok(copied)
+    }
+
+    async fn connect(&self) -> anyhow::Result<Client> {
+        let (client, connection) = tokio_postgres::connect(&self.db_url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                warn!("Restore connection to the database failed: {e}");
+            }
+        });
+        Ok(client)
+    }
+}
+
+/// Field of a row loaded by `COPY` in CSV format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Null,
+    Int(i64),
+    Text(String),
+    Bytea(Vec<u8>),
+    ByteaArray(Vec<Vec<u8>>),
+}
+
+impl Field {
+    fn write_csv(&self, out: &mut String) {
+        match self {
+            Field::Null => {}
+            Field::Int(value) => out.push_str(&value.to_string()),
+            Field::Text(value) => {
+                out.push('"');
+                out.push_str(&value.replace('"', "\"\""));
+                out.push('"');
+            }
+            Field::Bytea(bytes) => {
+                out.push_str("\\x");
+                out.push_str(&Hex::encode(bytes));
+            }
+            // Backslashes are escaped in array literals, which are quoted for their commas
+            Field::ByteaArray(elements) => {
+                out.push_str("\"{");
+                for (i, bytes) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str("\\\\x");
+                    out.push_str(&Hex::encode(bytes));
+                }
+                out.push_str("}\"");
+            }
+        }
+    }
+}
+
+fn encode_csv(records: &[Vec<Field>]) -> Bytes {
+    let mut out = String::new();
+    for record in records {
+        for (i, field) in record.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            field.write_csv(&mut out);
+        }
+        out.push('\n');
+    }
+    out.into()
+}
+
+async fn copy_in(
+    transaction: &Transaction<'_>,
+    table: &str,
+    columns: &[&str],
+    records: Vec<Vec<Field>>,
+) -> anyhow::Result<u64> {
+    let statement = format!(
+        "COPY {table} ({}) FROM STDIN WITH (FORMAT csv)",
+        columns.join(", ")
+    );
+    let sink = transaction.copy_in::<_, Bytes>(statement.as_str()).await?;
+    futures::pin_mut!(sink);
+    sink.send(encode_csv(&records)).await?;
+    Ok(sink.as_mut().finish().await?)
+}
+
+/// Set the events of the transactions of a restored event range, from the BCS of their events.
+async fn restore_transaction_events(
+    transaction: &Transaction<'_>,
+    tx_events: Vec<Vec<Field>>,
+) -> anyhow::Result<()> {
+    transaction
+        .batch_execute(
+            "CREATE TEMPORARY TABLE restored_tx_events \
+            (tx_sequence_number BIGINT PRIMARY KEY, events BYTEA[] NOT NULL) ON COMMIT DROP",
+        )
+        .await?;
+    copy_in(
+        transaction,
+        "restored_tx_events",
+        &["tx_sequence_number", "events"],
+        tx_events,
+    )
+    .await?;
+    transaction
+        .batch_execute(
+            "UPDATE transactions SET events = restored_tx_events.events FROM restored_tx_events \
+            WHERE transactions.tx_sequence_number = restored_tx_events.tx_sequence_number",
+        )
+        .await?;
+    Ok(())
+}
+
+/// Values of an exported row, by column name.
+struct ExportedRow<'a> {
+    table: &'a WarehouseTable,
+    values: Vec<Value>,
+}
+
+impl ExportedRow<'_> {
+    fn value(&self, name: &str) -> anyhow::Result<&Value> {
+        self.table
+            .columns
+            .iter()
+            .position(|column| column.name == name)
+            .and_then(|i| self.values.get(i))
+            .ok_or_else(|| anyhow!("Missing column {name} of {}", self.table.name))
+    }
+
+    fn int(&self, name: &str) -> anyhow::Result<Option<i64>> {
+        match self.value(name)? {
+            Value::Int64(value) => Ok(*value),
+            value => Err(anyhow!("Unexpected {value:?} in {name}")),
+        }
+    }
+
+    fn string(&self, name: &str) -> anyhow::Result<Option<&str>> {
+        match self.value(name)? {
+            Value::String(value) => Ok(value.as_deref()),
+            value => Err(anyhow!("Unexpected {value:?} in {name}")),
+        }
+    }
+
+    fn required_int(&self, name: &str) -> anyhow::Result<i64> {
+        self.int(name)?
+            .ok_or_else(|| anyhow!("Missing {name} of {}", self.table.name))
+    }
+
+    fn required_string(&self, name: &str) -> anyhow::Result<&str> {
+        self.string(name)?
+            .ok_or_else(|| anyhow!("Missing {name} of {}", self.table.name))
+    }
+
+    fn digest(&self) -> anyhow::Result<Vec<u8>> {
+        let digest = TransactionDigest::from_str(self.required_string("transaction_digest")?)?;
+        Ok(digest.into_inner().to_vec())
+    }
+}
+
+fn nullable_int(value: Option<i64>) -> Field {
+    value.map_or(Field::Null, Field::Int)
+}
+
+/// Records of `TRANSACTION_COLUMNS`, with the BCS columns compressed as the writer would.
+fn transaction_records(rows: Vec<Vec<Value>>) -> anyhow::Result<Vec<Vec<Field>>> {
+    rows.into_iter()
+        .map(|values| {
+            let row = ExportedRow {
+                table: &TRANSACTIONS,
+                values,
+            };
+            let raw_transaction = Base64::decode(row.required_string("raw_transaction")?)?;
+            let raw_effects = Base64::decode(row.required_string("raw_effects")?)?;
+            let codec = BcsCodec::for_len(raw_transaction.len() + raw_effects.len());
+            Ok(vec![
+                Field::Int(row.required_int("tx_sequence_number")?),
+                Field::Bytea(row.digest()?),
+                Field::Bytea(codec.compress(raw_transaction)?),
+                Field::Bytea(codec.compress(raw_effects)?),
+                Field::Int(row.required_int("checkpoint_sequence_number")?),
+                Field::Int(row.required_int("timestamp_ms")?),
+                Field::ByteaArray(vec![]),
+                Field::ByteaArray(vec![]),
+                // Filled once the events are restored
+                Field::ByteaArray(vec![]),
+                Field::Int(row.required_int("transaction_kind")?),
+                Field::Int(row.required_int("success_command_count")?),
+                nullable_int(row.int("gas_price")?),
+                nullable_int(row.int("computation_cost")?),
+                nullable_int(row.int("storage_cost")?),
+                nullable_int(row.int("storage_rebate")?),
+                nullable_int(row.int("non_refundable_storage_fee")?),
+                Field::Int(codec as i64),
+            ])
+        })
+        .collect()
+}
+
+/// Records of `EVENT_COLUMNS`, and of the events of their transactions, in order.
+fn event_records(rows: Vec<Vec<Value>>) -> anyhow::Result<(Vec<Vec<Field>>, Vec<Vec<Field>>)> {
+    let mut records = Vec::with_capacity(rows.len());
+    let mut tx_events: Vec<(i64, Vec<Vec<u8>>)> = vec![];
+    for values in rows {
+        let row = ExportedRow {
+            table: &EVENTS,
+            values,
+        };
+        let tx_sequence_number = row.required_int("tx_sequence_number")?;
+        let sender = row
+            .string("sender")?
+            .map(SuiAddress::from_str)
+            .transpose()?;
+        let package = ObjectID::from_str(row.required_string("package")?)?;
+        let module = row.required_string("module")?;
+        let event_type = row.required_string("event_type")?;
+        let contents = Base64::decode(row.required_string("bcs")?)?;
+
+        let sender = sender.ok_or_else(|| {
+            anyhow!("Missing sender of an event of transaction {tx_sequence_number}")
+        })?;
+        let event = Event {
+            package_id: package,
+            transaction_module: Identifier::new(module)?,
+            sender,
+            type_: parse_sui_struct_tag(event_type)?,
+            contents: contents.clone(),
+        };
+        // Rows are ordered by transaction, then event
+        match tx_events.last_mut() {
+            Some((tx, events)) if *tx == tx_sequence_number => events.push(bcs::to_bytes(&event)?),
+            _ => tx_events.push((tx_sequence_number, vec![bcs::to_bytes(&event)?])),
+        }
+
+        records.push(vec![
+            Field::Int(tx_sequence_number),
+            Field::Int(row.required_int("event_sequence_number")?),
+            Field::Bytea(row.digest()?),
+            Field::Int(row.required_int("checkpoint_sequence_number")?),
+            Field::ByteaArray(vec![sender.to_vec()]),
+            Field::Bytea(package.to_vec()),
+            Field::Text(module.to_string()),
+            Field::Text(event_type.to_string()),
+            Field::Int(row.required_int("timestamp_ms")?),
+            Field::Bytea(contents),
+        ]);
+    }
+    let tx_events = tx_events
+        .into_iter()
+        .map(|(tx, events)| vec![Field::Int(tx), Field::ByteaArray(events)])
+        .collect();
+    Ok((records, tx_events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_csv() {
+        let records = vec![vec![
+            Field::Int(7),
+            Field::Null,
+            Field::Text("0x2::coin::Mint<\"a\", b>".to_string()),
+            Field::Bytea(vec![0xab, 0x01]),
+            Field::ByteaArray(vec![vec![0xab], vec![0xcd]]),
+            Field::ByteaArray(vec![]),
+        ]];
+        assert_eq!(
+            encode_csv(&records),
+            Bytes::from(
+                "7,,\"0x2::coin::Mint<\"\"a\"\", b>\",\\xab01,\"{\\\\xab,\\\\xcd}\",\"{}\"\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_event_records() -> anyhow::Result<()> {
+        let sender = SuiAddress::random_for_testing_only();
+        let row = |tx: i64, event: i64| {
+            vec![
+                Value::Int64(Some(tx)),
+                Value::Int64(Some(event)),
+                Value::String(Some(TransactionDigest::random().to_string())),
+                Value::Int64(Some(3)),
+                Value::String(Some(sender.to_string())),
+                Value::String(Some("0x2".to_string())),
+                Value::String(Some("coin".to_string())),
+                Value::String(Some("0x2::coin::Mint".to_string())),
+                Value::Int64(Some(1_698_796_800_000)),
+                Value::String(Some(Base64::encode(b"bcs"))),
+            ]
+        };
+        let (records, tx_events) = event_records(vec![row(7, 0), row(7, 1), row(8, 0)])?;
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0][4], Field::ByteaArray(vec![sender.to_vec()]));
+        assert_eq!(tx_events.len(), 2);
+        let Field::ByteaArray(events) = &tx_events[0][1] else {
+            panic!("events of a transaction should be an array");
+        };
+        let event: Event = bcs::from_bytes(&events[1])?;
+        assert_eq!(event.sender, sender);
+        assert_eq!(event.contents, b"bcs");
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Backfill of the history of watchlist addresses from the checkpoint archive.
+//!
+//! Addresses registered through the `suix_registerWatchlistAddresses` method are backfilled from
+//! genesis until the earliest checkpoint indexed when they were registered, which may predate
+//! what the DB keeps. The archive is scanned in batches of checkpoints shared by all the
+//! addresses being backfilled, starting from the lowest checkpoint any of them needs, so that
+//! addresses registered together are backfilled in a single scan.
+//!
+//! A transaction is part of the history of an address if the address sent it, or if it left an
+//! object owned by the address, as for `tx_senders` and `tx_recipients`. Archives hold no
+//! objects, so only the transactions and their effects are stored.
+//!
+//! When waiting for checkpoints to be archived, the backfill watches the MANIFEST of the archive
+//! to resume as soon as it's updated, rather than after the idle interval, and picks updates up
+//! through the event notifications of the bucket if it's configured with an event queue.
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use prometheus::Registry;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use sui_archival::reader::{ArchiveReader, ArchiveReaderMetrics};
+use sui_config::node::ArchiveReaderConfig;
+use sui_storage::object_store::watch::{StoreChange, StoreWatcher};
+use sui_storage::object_store::ObjectStoreConfig;
+use sui_types::base_types::SuiAddress;
+use sui_types::checkpoint_range::CheckpointRange;
+use sui_types::effects::TransactionEffectsAPI;
+use sui_types::message_envelope::Message;
+use sui_types::object::Owner;
+use sui_types::storage::{ReadStore, SharedInMemoryStore};
+use sui_types::transaction::TransactionDataAPI;
+
+use crate::errors::IndexerError;
+use crate::metrics::IndexerMetrics;
+use crate::models_v2::watchlist::{StoredWatchlistAddress, StoredWatchlistTransaction};
+use crate::schema_v2::{watchlist_addresses, watchlist_transactions};
+use crate::store::diesel_macro::{read_only_blocking, transactional_blocking_with_retry};
+use crate::types_v2::IndexerResult;
+use crate::PgConnectionPool;
+
+const BATCH_CHECKPOINTS: i64 = 1000;
+const DOWNLOAD_CONCURRENCY: usize = 5;
+/// Time to wait before looking for addresses to backfill again, when there are none or the
+/// last batch failed, unless the archive is updated sooner.
+const IDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+pub struct WatchlistBackfill {
+    pool: PgConnectionPool,
+    archive_reader: ArchiveReader,
+    /// Changes of the objects at the root of the archive, e.g. MANIFEST updates
+    archive_changes: Mutex<BoxStream<'static, anyhow::Result<StoreChange>>>,
+    metrics: IndexerMetrics,
+}
+
+impl WatchlistBackfill {
+    pub fn new(
+        pool: PgConnectionPool,
+        archive_store_config: ObjectStoreConfig,
+        metrics: IndexerMetrics,
+    ) -> anyhow::Result<Self> {
+        let archive_changes = StoreWatcher::new(archive_store_config.make()?, None, IDLE_INTERVAL)
+            .shallow()
+            .with_s3_event_notifications(&archive_store_config)?
+            .into_stream();
+        let archive_reader = ArchiveReader::new(
+            ArchiveReaderConfig {
+                remote_store_config: archive_store_config,
+                mirror_store_configs: vec![],
+                download_concurrency: NonZeroUsize::new(DOWNLOAD_CONCURRENCY).unwrap(),
+                use_for_pruning_watermark: false,
+            },
+            &ArchiveReaderMetrics::new(&Registry::default()),
+        )?;
+        Ok(Self {
+            pool,
+            archive_reader,
+            archive_changes: Mutex::new(archive_changes),
+            metrics,
+        })
+    }
+
+    pub async fn run_forever(&self) {
+        info!("Watchlist backfill started");
+        loop {
+            match self.backfill_batch().await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => warn!("Watchlist backfill failed, retrying: {e}"),
+            }
+            self.wait_for_archive().await;
+        }
+    }
+
+    /// Wait for the idle interval, or until the archive is updated.
+    async fn wait_for_archive(&self) {
+        let mut archive_changes = self.archive_changes.lock().await;
+        tokio::select! {
+            _ = tokio::time::sleep(IDLE_INTERVAL) => {}
+            change = archive_changes.next() => {
+                if let Some(Err(e)) = change {
+                    warn!("Failed to watch the archive for updates: {e}");
+                }
+            }
+        }
+    }
+
+    /// Scan the next batch of checkpoints for the addresses being backfilled, and return whether
+    /// there were any.
+    async fn backfill_batch(&self) -> anyhow::Result<bool> {
+        let pool = self.pool.clone();
+        let pending = tokio::task::spawn_blocking(move || get_pending_addresses(&pool)).await??;
+        let Some(start) = pending.iter().map(|a| a.next_checkpoint).min() else {
+            return Ok(false);
+        };
+
+        self.archive_reader.sync_manifest_once().await?;
+        let latest_archived = self.archive_reader.latest_available_checkpoint().await? as i64;
+        let end = pending
+            .iter()
+            .map(|a| a.end_checkpoint)
+            .max()
+            .unwrap_or(start)
+            .min(start + BATCH_CHECKPOINTS)
+            .min(latest_archived + 1);
+        let range = CheckpointRange::new(start as u64, end as u64);
+        if range.is_empty() {
+            return Err(anyhow!(
+                "Checkpoint {start} is not archived yet, latest archived is {latest_archived}"
+            ));
+        }
+
+        let store = SharedInMemoryStore::default();
+        self.archive_reader
+            .read(
+                store.clone(),
+                range.into(),
+                Arc::new(AtomicU64::new(0)),
+                Arc::new(AtomicU64::new(0)),
+                false,
+            )
+            .await?;
+
+        let watched_addresses = pending
+            .iter()
+            .map(|watched| {
+                let watched_range = CheckpointRange::new(
+                    watched.next_checkpoint as u64,
+                    watched.end_checkpoint as u64,
+                );
+                Ok((
+                    SuiAddress::from_bytes(&watched.address)?,
+                    watched_range,
+                    watched,
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut transactions = vec![];
+        for checkpoint in range {
+            let contents = store
+                .get_full_checkpoint_contents_by_sequence_number(checkpoint)?
+                .ok_or_else(|| anyhow!("Checkpoint {checkpoint} is missing from the archive"))?;
+            for data in contents.iter() {
+                let mut addresses = vec![data.transaction.data().transaction_data().sender()];
+                addresses.extend(data.effects.all_changed_objects().into_iter().filter_map(
+                    |(_object_ref, owner, _write_kind)| match owner {
+                        Owner::AddressOwner(address) => Some(address),
+                        _ => None,
+                    },
+                ));
+                for (address, watched_range, watched) in &watched_addresses {
+                    if !watched_range.contains(checkpoint) {
+                        continue;
+                    }
+                    if addresses.contains(address) {
+                        transactions.push(StoredWatchlistTransaction {
+                            address: watched.address.clone(),
+                            checkpoint_sequence_number: checkpoint as i64,
+                            transaction_digest: data.transaction.digest().into_inner().to_vec(),
+                            raw_transaction: bcs::to_bytes(data.transaction.data())?,
+                            raw_effects: bcs::to_bytes(&data.effects)?,
+                        });
+                    }
+                }
+            }
+        }
+
+        let found = transactions.len();
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || persist_batch(&pool, pending, transactions, end))
+            .await??;
+        self.metrics
+            .watchlist_backfill_checkpoints_scanned
+            .inc_by(range.len());
+        self.metrics
+            .watchlist_backfill_transactions_found
+            .inc_by(found as u64);
+        info!(
+            "Scanned archived checkpoints {range} for watchlist addresses, found {found} transactions"
+        );
+        Ok(true)
+    }
+}
+
+fn get_pending_addresses(pool: &PgConnectionPool) -> IndexerResult<Vec<StoredWatchlistAddress>> {
+    read_only_blocking!(pool, |conn| {
+        watchlist_addresses::table
+            .filter(watchlist_addresses::next_checkpoint.lt(watchlist_addresses::end_checkpoint))
+            .load::<StoredWatchlistAddress>(conn)
+    })
+}
+
+/// Store the `transactions` found in the checkpoints before `end`, and advance the addresses
+/// being backfilled past them.
+fn persist_batch(
+    pool: &PgConnectionPool,
+    pending: Vec<StoredWatchlistAddress>,
+    transactions: Vec<StoredWatchlistTransaction>,
+    end: i64,
+) -> IndexerResult<()> {
+    let mut found = HashMap::new();
+    for transaction in &transactions {
+        *found.entry(transaction.address.clone()).or_insert(0i64) += 1;
+    }
+    transactional_blocking_with_retry!(
+        pool,
+        |conn| {
+            if !transactions.is_empty() {
+                diesel::insert_into(watchlist_transactions::table)
+                    .values(&transactions)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+            }
+            for watched in &pending {
+                if watched.next_checkpoint >= end {
+                    continue;
+                }
+                diesel::update(watchlist_addresses::table.find(&watched.address))
+                    .set((
+                        watchlist_addresses::next_checkpoint.eq(end.min(watched.end_checkpoint)),
+                        watchlist_addresses::transactions_found
+                            .eq(watchlist_addresses::transactions_found
+                                + found.get(&watched.address).copied().unwrap_or(0)),
+                    ))
+                    .execute(conn)?;
+            }
+            Ok::<(), IndexerError>(())
+        },
+        Duration::from_secs(60)
+    )
+}
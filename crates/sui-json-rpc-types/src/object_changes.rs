@@ -1,7 +1,10 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use anyhow::{anyhow, bail};
+use move_core_types::account_address::AccountAddress;
 use move_core_types::language_storage::StructTag;
+use mysten_metrics::monitored_scope;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -11,6 +14,15 @@ use sui_types::object::Owner;
 use sui_types::sui_serde::SequenceNumber as AsSequenceNumber;
 use sui_types::sui_serde::SuiStructTag;
 
+use crate::Filter;
+
+/// Limits on the size of an [`ObjectChangeFilter`], checked by [`ObjectChangeFilter::validate`]
+/// so that matching a filter against every object change of every transaction stays cheap.
+pub const OBJECT_CHANGE_FILTER_MAX_DEPTH: usize = 4;
+pub const OBJECT_CHANGE_FILTER_MAX_NODES: usize = 32;
+pub const OBJECT_CHANGE_FILTER_MAX_TYPE_PATTERN_LENGTH: usize = 256;
+pub const OBJECT_CHANGE_FILTER_MAX_TYPE_PATTERN_WILDCARDS: usize = 4;
+
 /// ObjectChange are derived from the object mutations in the TransactionEffect to provide richer object information.
 #[serde_as]
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
@@ -98,6 +110,42 @@ pub enum ObjectChange {
 }
 
 impl ObjectChange {
+    pub fn kind(&self) -> ObjectChangeKind {
+        match self {
+            ObjectChange::Published { .. } => ObjectChangeKind::Published,
+            ObjectChange::Transferred { .. } => ObjectChangeKind::Transferred,
+            ObjectChange::Mutated { .. } => ObjectChangeKind::Mutated,
+            ObjectChange::Deleted { .. } => ObjectChangeKind::Deleted,
+            ObjectChange::Wrapped { .. } => ObjectChangeKind::Wrapped,
+            ObjectChange::Created { .. } => ObjectChangeKind::Created,
+        }
+    }
+
+    /// The type of the changed object, `None` for published packages.
+    pub fn object_type(&self) -> Option<&StructTag> {
+        match self {
+            ObjectChange::Published { .. } => None,
+            ObjectChange::Transferred { object_type, .. }
+            | ObjectChange::Mutated { object_type, .. }
+            | ObjectChange::Deleted { object_type, .. }
+            | ObjectChange::Wrapped { object_type, .. }
+            | ObjectChange::Created { object_type, .. } => Some(object_type),
+        }
+    }
+
+    /// The owner of the object after the change, `None` if it no longer has one or is a package.
+    pub fn owner(&self) -> Option<&Owner> {
+        match self {
+            ObjectChange::Transferred { recipient, .. } => Some(recipient),
+            ObjectChange::Mutated { owner, .. } | ObjectChange::Created { owner, .. } => {
+                Some(owner)
+            }
+            ObjectChange::Published { .. }
+            | ObjectChange::Deleted { .. }
+            | ObjectChange::Wrapped { .. } => None,
+        }
+    }
+
     pub fn object_id(&self) -> ObjectID {
         match self {
             ObjectChange::Published { package_id, .. } => *package_id,
@@ -256,3 +304,187 @@ impl Display for ObjectChange {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ObjectChangeKind {
+    Published,
+    Transferred,
+    Mutated,
+    Deleted,
+    Wrapped,
+    Created,
+}
+
+/// Filter on object changes, shared by every API that streams or delivers object changes so that
+/// clients can use the same filter everywhere. Filters must pass [`ObjectChangeFilter::validate`]
+/// before being accepted.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+pub enum ObjectChangeFilter {
+    /// Match the object type against a pattern in which `*` matches any sequence of characters,
+    /// e.g. `0x2::coin::Coin<*>` or `0x2::kiosk::*`. Addresses may be written in short form.
+    ObjectType(String),
+    /// Match objects owned by the given address or object after the change.
+    Owner(SuiAddress),
+    /// Match objects whose type is defined in the given package, and the publishing of the
+    /// package itself.
+    Package(ObjectID),
+    /// Match changes of the given kind.
+    ChangeKind(ObjectChangeKind),
+
+    All(Vec<ObjectChangeFilter>),
+    Any(Vec<ObjectChangeFilter>),
+    Not(Box<ObjectChangeFilter>),
+}
+
+impl ObjectChangeFilter {
+    /// Check that the filter is well formed and within the complexity limits.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut nodes = 0;
+        self.validate_at_depth(1, &mut nodes)
+    }
+
+    fn validate_at_depth(&self, depth: usize, nodes: &mut usize) -> anyhow::Result<()> {
+        if depth > OBJECT_CHANGE_FILTER_MAX_DEPTH {
+            bail!(
+                "Object change filter is nested deeper than {OBJECT_CHANGE_FILTER_MAX_DEPTH} levels"
+            );
+        }
+        *nodes += 1;
+        if *nodes > OBJECT_CHANGE_FILTER_MAX_NODES {
+            bail!("Object change filter has more than {OBJECT_CHANGE_FILTER_MAX_NODES} terms");
+        }
+        match self {
+            ObjectChangeFilter::ObjectType(pattern) => validate_type_pattern(pattern),
+            ObjectChangeFilter::Owner(_)
+            | ObjectChangeFilter::Package(_)
+            | ObjectChangeFilter::ChangeKind(_) => Ok(()),
+            ObjectChangeFilter::All(filters) | ObjectChangeFilter::Any(filters) => {
+                if filters.is_empty() {
+                    bail!("All and Any object change filters must have at least one term");
+                }
+                filters
+                    .iter()
+                    .try_for_each(|f| f.validate_at_depth(depth + 1, nodes))
+            }
+            ObjectChangeFilter::Not(filter) => filter.validate_at_depth(depth + 1, nodes),
+        }
+    }
+
+    pub fn and(self, other_filter: ObjectChangeFilter) -> Self {
+        Self::All(vec![self, other_filter])
+    }
+    pub fn or(self, other_filter: ObjectChangeFilter) -> Self {
+        Self::Any(vec![self, other_filter])
+    }
+}
+
+impl Filter<ObjectChange> for ObjectChangeFilter {
+    fn matches(&self, item: &ObjectChange) -> bool {
+        let _scope = monitored_scope("ObjectChangeFilter::matches");
+        match self {
+            ObjectChangeFilter::ObjectType(pattern) => item.object_type().is_some_and(|t| {
+                glob_matches(
+                    &normalize_type_pattern(pattern),
+                    &t.to_string().replace(' ', ""),
+                )
+            }),
+            ObjectChangeFilter::Owner(address) => matches!(
+                item.owner(),
+                Some(Owner::AddressOwner(owner) | Owner::ObjectOwner(owner)) if owner == address
+            ),
+            ObjectChangeFilter::Package(package) => match item {
+                ObjectChange::Published { package_id, .. } => package_id == package,
+                _ => item
+                    .object_type()
+                    .is_some_and(|t| &ObjectID::from(t.address) == package),
+            },
+            ObjectChangeFilter::ChangeKind(kind) => &item.kind() == kind,
+            ObjectChangeFilter::All(filters) => filters.iter().all(|f| f.matches(item)),
+            ObjectChangeFilter::Any(filters) => filters.iter().any(|f| f.matches(item)),
+            ObjectChangeFilter::Not(filter) => !filter.matches(item),
+        }
+    }
+}
+
+fn validate_type_pattern(pattern: &str) -> anyhow::Result<()> {
+    if pattern.is_empty() {
+        bail!("Object type pattern must not be empty");
+    }
+    if pattern.len() > OBJECT_CHANGE_FILTER_MAX_TYPE_PATTERN_LENGTH {
+        bail!(
+            "Object type pattern is longer than {OBJECT_CHANGE_FILTER_MAX_TYPE_PATTERN_LENGTH} characters"
+        );
+    }
+    if let Some(c) = pattern
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || "_:<>,* ".contains(*c)))
+    {
+        bail!("Object type pattern {pattern:?} contains invalid character {c:?}");
+    }
+    if pattern.matches('*').count() > OBJECT_CHANGE_FILTER_MAX_TYPE_PATTERN_WILDCARDS {
+        bail!(
+            "Object type pattern {pattern:?} has more than {OBJECT_CHANGE_FILTER_MAX_TYPE_PATTERN_WILDCARDS} wildcards"
+        );
+    }
+    for address in type_pattern_addresses(pattern) {
+        AccountAddress::from_hex_literal(address)
+            .map_err(|e| anyhow!("Object type pattern {pattern:?} has invalid address: {e}"))?;
+    }
+    Ok(())
+}
+
+/// The `0x` prefixed addresses in a type pattern, excluding partial ones like `0x*`.
+fn type_pattern_addresses(pattern: &str) -> impl Iterator<Item = &str> {
+    pattern
+        .split(|c: char| "<>,: ".contains(c))
+        .filter(|s| s.starts_with("0x") && !s.contains('*'))
+}
+
+/// Rewrite the addresses of a type pattern in the short form used by the `Display` of
+/// `StructTag`, so that `0x2` and `0x00..02` match the same types, and drop whitespace.
+fn normalize_type_pattern(pattern: &str) -> String {
+    let pattern = pattern.replace(' ', "");
+    let mut normalized = String::with_capacity(pattern.len());
+    let mut rest = pattern.as_str();
+    while let Some(start) = rest.find("0x") {
+        let (head, tail) = rest.split_at(start + 2);
+        normalized.push_str(head);
+        let hex_len = tail
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(tail.len());
+        let hex = tail[..hex_len].trim_start_matches('0').to_lowercase();
+        if hex.is_empty() && hex_len > 0 {
+            normalized.push('0');
+        }
+        normalized.push_str(&hex);
+        rest = &tail[hex_len..];
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+/// Match `text` against a pattern in which `*` matches any, possibly empty, sequence of
+/// characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen, and of the text it currently extends to.
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, star_t)) = backtrack {
+            p = star + 1;
+            t = star_t + 1;
+            backtrack = Some((star, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
@@ -12,8 +12,9 @@ use serde_with::serde_as;
 use serde_with::DisplayFromStr;
 
 use sui_types::base_types::AuthorityName;
-use sui_types::base_types::{EpochId, ObjectID};
+use sui_types::base_types::{EpochId, ObjectID, SuiAddress};
 use sui_types::committee::Committee;
+use sui_types::digests::TransactionDigest;
 use sui_types::messages_checkpoint::CheckpointSequenceNumber;
 use sui_types::sui_serde::BigInt;
 use sui_types::sui_system_state::sui_system_state_summary::SuiValidatorSummary;
@@ -22,6 +23,7 @@ use crate::Page;
 
 pub type EpochPage = Page<EpochInfo, BigInt<u64>>;
 pub type EpochMetricsPage = Page<EpochMetrics, BigInt<u64>>;
+pub type GasPriceHistoryPage = Page<GasPriceHistory, BigInt<u64>>;
 
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
@@ -194,3 +196,190 @@ pub struct AddressMetrics {
     pub cumulative_active_addresses: u64,
     pub daily_active_addresses: u64,
 }
+
+/// Length of the periods gas price history is summarized over
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum GasPriceInterval {
+    #[default]
+    Epoch,
+    Hour,
+}
+
+/// Reference gas price and distribution of the gas prices paid by user transactions over an epoch,
+/// or an hour. Hours that span an epoch change are split in two at the change.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPriceHistory {
+    /// start of the period, also used as the paging cursor
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub start_timestamp_ms: u64,
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub epoch: EpochId,
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub reference_gas_price: u64,
+    /// number of user transactions in the period, system transactions are not included
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub total_transactions: u64,
+    /// percentiles of gas prices paid, absent if there were no transactions
+    pub percentiles: Option<GasPricePercentiles>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPricePercentiles {
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub p25: u64,
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub p50: u64,
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub p75: u64,
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub p90: u64,
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub p99: u64,
+}
+
+/// Progress of the backfill of the history of a watchlist address from the checkpoint archive.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchlistBackfillProgress {
+    pub address: SuiAddress,
+    /// next checkpoint to scan
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub next_checkpoint: CheckpointSequenceNumber,
+    /// checkpoint the backfill stops at, exclusive: the earliest checkpoint indexed when the
+    /// address was registered
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub end_checkpoint: CheckpointSequenceNumber,
+    /// number of transactions of the address found so far
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub transactions_found: u64,
+    pub completed: bool,
+}
+
+/// Direction of the dependency edges followed from a transaction.
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionDependencyDirection {
+    /// the transactions it depends on, transitively
+    #[default]
+    Ancestors,
+    /// the transactions depending on it, transitively
+    Descendants,
+    Both,
+}
+
+/// Edge of a transaction dependency graph: `transaction` depends on `dependency`, through the
+/// objects it reads or writes that `dependency` last wrote.
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionDependencyEdge {
+    pub transaction: TransactionDigest,
+    pub dependency: TransactionDigest,
+    /// distance of the edge from the root transaction, starting at 1
+    pub depth: u32,
+}
+
+/// Ancestors and/or descendants of a transaction, up to a depth limit.
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionDependencyGraph {
+    pub root: TransactionDigest,
+    pub edges: Vec<TransactionDependencyEdge>,
+    /// whether edges past the depth or size limit of the request were left out
+    pub truncated: bool,
+}
+
+/// Progress of the reindex of the rows of a package over a range of checkpoints, from the
+/// checkpoint files.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageReindexJob {
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub id: u64,
+    pub package: ObjectID,
+    /// first checkpoint to reindex
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub start_checkpoint: CheckpointSequenceNumber,
+    /// checkpoint the reindex stops at, exclusive
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub end_checkpoint: CheckpointSequenceNumber,
+    /// next checkpoint to reindex
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub next_checkpoint: CheckpointSequenceNumber,
+    /// number of transactions of the package reindexed so far
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub transactions_reindexed: u64,
+    /// number of objects of the package's types reindexed so far
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub objects_reindexed: u64,
+    pub completed: bool,
+}
+
+/// How a purge treats the rows of a column holding the purged address.
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PurgeAction {
+    /// the rows are deleted
+    DeleteRows,
+    /// the address is removed from the column, and the rows kept
+    RemoveAddress,
+    /// the rows are counted and kept, e.g. as they are public chain data
+    Retain,
+}
+
+/// Rows of a column tagged with a retention class matched by a purge.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgedColumn {
+    pub table: String,
+    pub column: String,
+    pub retention_class: String,
+    pub action: PurgeAction,
+    /// number of rows holding the address
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub rows: u64,
+}
+
+/// Audit record of a purge of the rows of an address from the tables tagged with a retention
+/// class.
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub id: u64,
+    pub address: SuiAddress,
+    /// whether the rows were only counted, and nothing was purged
+    pub dry_run: bool,
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub requested_at_ms: u64,
+    pub columns: Vec<PurgedColumn>,
+}
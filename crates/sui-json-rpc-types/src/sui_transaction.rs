@@ -904,6 +904,49 @@ pub struct DryRunTransactionBlockResponse {
     pub input: SuiTransactionBlockData,
 }
 
+/// Estimate of the contention on the shared objects of a transaction, from the transactions
+/// waiting on them on the node answering the request.
+#[serde_as]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedObjectContentionEstimate {
+    pub objects: Vec<SharedObjectContention>,
+    /// The longest estimated wait on the shared objects, in milliseconds. This is the time the
+    /// transaction is expected to wait for execution once sequenced, on top of the time it takes
+    /// consensus to sequence it.
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub estimated_inclusion_latency_ms: u64,
+}
+
+#[serde_as]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedObjectContention {
+    pub object_id: ObjectID,
+    /// Whether the transaction takes the object by mutable reference.
+    pub mutable: bool,
+    /// Number of sequenced transactions waiting on the object.
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub queue_length: u64,
+    /// How long the oldest transaction waiting on the object has been waiting, in milliseconds.
+    #[schemars(with = "Option<BigInt<u64>>")]
+    #[serde_as(as = "Option<BigInt<u64>>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_pending_ms: Option<u64>,
+    /// Average time between two transactions waiting on the object being executed, in
+    /// milliseconds, if the node has seen enough of them.
+    #[schemars(with = "Option<BigInt<u64>>")]
+    #[serde_as(as = "Option<BigInt<u64>>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_interval_ms: Option<u64>,
+    /// Estimated time until the transactions waiting on the object are executed, in milliseconds.
+    #[schemars(with = "BigInt<u64>")]
+    #[serde_as(as = "BigInt<u64>")]
+    pub estimated_wait_ms: u64,
+}
+
 #[derive(Eq, PartialEq, Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename = "TransactionBlockEvents", transparent)]
 pub struct SuiTransactionBlockEvents {
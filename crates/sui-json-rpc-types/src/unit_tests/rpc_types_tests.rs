@@ -16,7 +16,10 @@ use sui_types::gas_coin::GasCoin;
 use sui_types::object::{MoveObject, Owner};
 use sui_types::{parse_sui_struct_tag, MOVE_STDLIB_ADDRESS, SUI_FRAMEWORK_ADDRESS};
 
-use crate::{ObjectChange, SuiMoveStruct, SuiMoveValue};
+use crate::{
+    Filter, ObjectChange, ObjectChangeFilter, ObjectChangeKind, SuiMoveStruct, SuiMoveValue,
+    OBJECT_CHANGE_FILTER_MAX_DEPTH,
+};
 
 #[test]
 fn test_move_value_to_sui_coin() {
@@ -196,3 +199,76 @@ fn test_type_tag_struct_tag_devnet_inc_222() {
         assert_eq!(oc, deser);
     }
 }
+
+#[test]
+fn test_object_change_filter_matches() {
+    let owner = SuiAddress::random_for_testing_only();
+    let created = ObjectChange::Created {
+        sender: Default::default(),
+        owner: Owner::AddressOwner(owner),
+        object_type: parse_sui_struct_tag("0x2::coin::Coin<0x2::sui::SUI>").unwrap(),
+        object_id: ObjectID::random(),
+        version: Default::default(),
+        digest: ObjectDigest::random(),
+    };
+    let published = ObjectChange::Published {
+        package_id: ObjectID::from_single_byte(2),
+        version: Default::default(),
+        digest: ObjectDigest::random(),
+        modules: vec!["coin".to_string()],
+    };
+
+    let coins = ObjectChangeFilter::ObjectType("0x2::coin::Coin<*>".to_string());
+    assert!(coins.matches(&created));
+    assert!(!coins.matches(&published));
+    let long_form = ObjectChangeFilter::ObjectType(
+        "0x0000000000000000000000000000000000000000000000000000000000000002::coin::*".to_string(),
+    );
+    assert!(long_form.matches(&created));
+    assert!(!ObjectChangeFilter::ObjectType("0x2::kiosk::*".to_string()).matches(&created));
+
+    let package = ObjectChangeFilter::Package(ObjectID::from_single_byte(2));
+    assert!(package.matches(&created));
+    assert!(package.matches(&published));
+
+    let filter = ObjectChangeFilter::Owner(owner).and(ObjectChangeFilter::Not(Box::new(
+        ObjectChangeFilter::ChangeKind(ObjectChangeKind::Mutated),
+    )));
+    assert!(filter.matches(&created));
+    assert!(!filter.matches(&published));
+}
+
+#[test]
+fn test_object_change_filter_validation() {
+    let filter = ObjectChangeFilter::ObjectType("0x2::coin::Coin<*>".to_string())
+        .or(ObjectChangeFilter::ChangeKind(ObjectChangeKind::Published));
+    assert!(filter.validate().is_ok());
+
+    for pattern in [
+        "",
+        "0x2::coin::Coin<$>",
+        "0xzz::coin::Coin",
+        "0x2::*::*<*,*,*>",
+    ] {
+        assert!(
+            ObjectChangeFilter::ObjectType(pattern.to_string())
+                .validate()
+                .is_err(),
+            "{pattern:?} should be rejected"
+        );
+    }
+    assert!(ObjectChangeFilter::Any(vec![]).validate().is_err());
+
+    let mut nested = ObjectChangeFilter::ChangeKind(ObjectChangeKind::Created);
+    for _ in 0..OBJECT_CHANGE_FILTER_MAX_DEPTH {
+        nested = ObjectChangeFilter::Not(Box::new(nested));
+    }
+    assert!(nested.validate().is_err());
+    let wide = ObjectChangeFilter::All(vec![
+        ObjectChangeFilter::ChangeKind(
+            ObjectChangeKind::Created
+        );
+        100
+    ]);
+    assert!(wide.validate().is_err());
+}
@@ -5,10 +5,14 @@ use jsonrpsee::core::RpcResult;
 use jsonrpsee::proc_macros::rpc;
 
 use sui_json_rpc_types::{
-    AddressMetrics, CheckpointedObjectID, EpochInfo, EpochMetricsPage, EpochPage, MoveCallMetrics,
-    NetworkMetrics, QueryObjectsPage, SuiObjectResponseQuery,
+    AddressMetrics, CheckpointedObjectID, EpochInfo, EpochMetricsPage, EpochPage,
+    GasPriceHistoryPage, GasPriceInterval, MoveCallMetrics, NetworkMetrics, PackageReindexJob,
+    PurgeReport, QueryObjectsPage, SuiObjectResponseQuery, TransactionDependencyDirection,
+    TransactionDependencyGraph, WatchlistBackfillProgress,
 };
 use sui_open_rpc_macros::open_rpc;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::digests::TransactionDigest;
 use sui_types::sui_serde::BigInt;
 
 #[open_rpc(namespace = "suix", tag = "Extended API")]
@@ -38,6 +42,21 @@ pub trait ExtendedApi {
         descending_order: Option<bool>,
     ) -> RpcResult<EpochMetricsPage>;
 
+    /// Return the reference gas price and percentiles of the gas prices paid by user transactions,
+    /// per epoch or hour. Periods are only summarized once they are over.
+    #[method(name = "getGasPriceHistory")]
+    async fn get_gas_price_history(
+        &self,
+        /// length of the periods to summarize, defaults to epoch
+        interval: Option<GasPriceInterval>,
+        /// optional paging cursor, the start timestamp of a period
+        cursor: Option<BigInt<u64>>,
+        /// maximum number of items per page
+        limit: Option<usize>,
+        /// flag to return results in descending order
+        descending_order: Option<bool>,
+    ) -> RpcResult<GasPriceHistoryPage>;
+
     /// Return current epoch info
     #[method(name = "getCurrentEpoch")]
     async fn get_current_epoch(&self) -> RpcResult<EpochInfo>;
@@ -75,4 +94,74 @@ pub trait ExtendedApi {
 
     #[method(name = "getTotalTransactions")]
     async fn get_total_transactions(&self) -> RpcResult<BigInt<u64>>;
+
+    /// Register addresses for a backfill of their history from before the earliest indexed
+    /// checkpoint, read from the checkpoint archive. Addresses already registered are left as is.
+    /// Only available to clients with a full access API token.
+    #[method(name = "registerWatchlistAddresses")]
+    async fn register_watchlist_addresses(
+        &self,
+        addresses: Vec<SuiAddress>,
+    ) -> RpcResult<Vec<WatchlistBackfillProgress>>;
+
+    /// Return the backfill progress of the registered addresses among `addresses`
+    #[method(name = "getWatchlistBackfillProgress")]
+    async fn get_watchlist_backfill_progress(
+        &self,
+        addresses: Vec<SuiAddress>,
+    ) -> RpcResult<Vec<WatchlistBackfillProgress>>;
+
+    /// Return the dependency graph of a transaction, from the dependencies listed in the effects
+    /// of the transactions: the transactions it depends on, the transactions depending on it,
+    /// or both.
+    #[method(name = "getTransactionDependencyGraph")]
+    async fn get_transaction_dependency_graph(
+        &self,
+        /// the digest of the queried transaction
+        digest: TransactionDigest,
+        /// the edges to follow, ancestors by default
+        direction: Option<TransactionDependencyDirection>,
+        /// maximum distance of the returned transactions from the queried one
+        depth: Option<u32>,
+    ) -> RpcResult<TransactionDependencyGraph>;
+
+    /// Request a reindex of the transactions, events and objects of a package over a range of
+    /// indexed checkpoints, re-derived from the checkpoint files, to repair the rows of an
+    /// indexing bug. Only available to clients with a full access API token.
+    #[method(name = "requestPackageReindex")]
+    async fn request_package_reindex(
+        &self,
+        /// the package whose rows are reindexed
+        package: ObjectID,
+        /// the first checkpoint to reindex
+        start_checkpoint: BigInt<u64>,
+        /// the checkpoint the reindex stops at, exclusive
+        end_checkpoint: BigInt<u64>,
+    ) -> RpcResult<PackageReindexJob>;
+
+    /// Return the reindex jobs requested for a package, most recent first. Only available to
+    /// clients with a full access API token.
+    #[method(name = "getPackageReindexJobs")]
+    async fn get_package_reindex_jobs(
+        &self,
+        package: ObjectID,
+    ) -> RpcResult<Vec<PackageReindexJob>>;
+
+    /// Purge the rows holding an address from the tables and columns tagged with a retention
+    /// class, e.g. its transaction indexes and watchlist registrations, and return the report of
+    /// the rows purged, which is kept for audit. Public chain data holding the address is
+    /// reported but retained. Only available to clients with a full access API token.
+    #[method(name = "purgeAddressData")]
+    async fn purge_address_data(
+        &self,
+        /// the address whose rows are purged
+        address: SuiAddress,
+        /// only count the rows that would be purged, false by default
+        dry_run: Option<bool>,
+    ) -> RpcResult<PurgeReport>;
+
+    /// Return the reports of the purges of an address, most recent first. Only available to
+    /// clients with a full access API token.
+    #[method(name = "getPurgeReports")]
+    async fn get_purge_reports(&self, address: SuiAddress) -> RpcResult<Vec<PurgeReport>>;
 }
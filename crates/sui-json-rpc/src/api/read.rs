@@ -132,6 +132,16 @@ pub trait ReadApi {
         transaction_digest: TransactionDigest,
     ) -> RpcResult<Vec<SuiEvent>>;
 
+    /// Return the events of a list of transactions, in the order of the digests.
+    /// The method will throw an error if the input contains any duplicate or
+    /// the input size exceeds QUERY_MAX_RESULT_LIMIT
+    #[method(name = "multiGetEvents")]
+    async fn multi_get_events(
+        &self,
+        /// A list of transaction digests.
+        transaction_digests: Vec<TransactionDigest>,
+    ) -> RpcResult<Vec<Vec<SuiEvent>>>;
+
     /// Return the total number of transaction blocks known to the server.
     #[method(name = "getTotalTransactionBlocks")]
     async fn get_total_transaction_blocks(&self) -> RpcResult<BigInt<u64>>;
@@ -6,8 +6,8 @@ use jsonrpsee::core::RpcResult;
 use jsonrpsee::proc_macros::rpc;
 
 use sui_json_rpc_types::{
-    DevInspectResults, DryRunTransactionBlockResponse, SuiTransactionBlockResponse,
-    SuiTransactionBlockResponseOptions,
+    DevInspectResults, DryRunTransactionBlockResponse, SharedObjectContentionEstimate,
+    SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
 };
 use sui_open_rpc_macros::open_rpc;
 use sui_types::base_types::SuiAddress;
@@ -62,4 +62,14 @@ pub trait WriteApi {
         &self,
         tx_bytes: Base64,
     ) -> RpcResult<DryRunTransactionBlockResponse>;
+
+    /// Return an estimate of the contention on the shared objects of the transaction: how many
+    /// transactions are waiting on each of them on this node, how fast they are executed, and
+    /// how long the transaction would wait for them once sequenced.
+    #[method(name = "estimateSharedObjectContention")]
+    async fn estimate_shared_object_contention(
+        &self,
+        /// BCS serialized transaction data bytes without its type tag, as base-64 encoded string.
+        tx_bytes: Base64,
+    ) -> RpcResult<SharedObjectContentionEstimate>;
 }
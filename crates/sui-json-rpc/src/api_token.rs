@@ -0,0 +1,58 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bearer token of the HTTP request being served, for method implementations that scope what
+//! callers can see by their token.
+//!
+//! The token is taken from the `Authorization: Bearer <token>` header of the request and is only
+//! available to code running on the task serving it: work moved to another task, e.g. with
+//! `spawn_blocking`, must read it beforehand.
+
+use std::future::Future;
+
+use hyper::header::AUTHORIZATION;
+use hyper::HeaderMap;
+
+tokio::task_local! {
+    static API_TOKEN: Option<String>;
+}
+
+/// Token of the request served by the current task, if it has one.
+pub fn current_api_token() -> Option<String> {
+    API_TOKEN.try_with(|token| token.clone()).ok().flatten()
+}
+
+/// Run `f` with `token` as the token of the current request.
+pub async fn with_api_token<F: Future>(token: Option<String>, f: F) -> F::Output {
+    API_TOKEN.scope(token, f).await
+}
+
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api_token::{bearer_token, current_api_token, with_api_token};
+    use hyper::header::AUTHORIZATION;
+    use hyper::HeaderMap;
+
+    #[tokio::test]
+    async fn test_api_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert(AUTHORIZATION, "Bearer abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("abc".to_string()));
+        headers.insert(AUTHORIZATION, "Basic abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+
+        assert_eq!(current_api_token(), None);
+        let token = with_api_token(Some("abc".to_string()), async { current_api_token() }).await;
+        assert_eq!(token, Some("abc".to_string()));
+    }
+}
@@ -9,7 +9,7 @@ use mysten_metrics::spawn_monitored_task;
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use sui_core::authority::authority_per_epoch_store::AuthorityPerEpochStore;
-use sui_core::authority::{AuthorityState, AuthorityStore};
+use sui_core::authority::{AuthorityState, AuthorityStore, ObjectQueueEstimate};
 use sui_core::subscription_handler::SubscriptionHandler;
 use sui_json_rpc_types::{
     Coin as SuiCoin, DevInspectResults, DryRunTransactionBlockResponse, EventFilter, SuiEvent,
@@ -126,6 +126,8 @@ pub trait StateRead: Send + Sync {
         gas_price: Option<u64>,
     ) -> StateReadResult<DevInspectResults>;
 
+    fn get_objects_queue_estimates(&self, object_ids: Vec<ObjectID>) -> Vec<ObjectQueueEstimate>;
+
     // indexer_api
     fn get_subscription_handler(&self) -> Arc<SubscriptionHandler>;
 
@@ -353,6 +355,11 @@ impl StateRead for AuthorityState {
             .await?)
     }
 
+    fn get_objects_queue_estimates(&self, object_ids: Vec<ObjectID>) -> Vec<ObjectQueueEstimate> {
+        self.transaction_manager()
+            .objects_queue_estimates(object_ids)
+    }
+
     fn get_subscription_handler(&self) -> Arc<SubscriptionHandler> {
         self.subscription_handler.clone()
     }
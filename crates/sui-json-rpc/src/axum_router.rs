@@ -18,6 +18,8 @@ use jsonrpsee::types::{ErrorObject, Id, InvalidRequest, Params, Request};
 use jsonrpsee::{core::server::rpc_module::Methods, server::logger::Logger};
 use serde_json::value::RawValue;
 
+use crate::api_token::{bearer_token, with_api_token};
+use crate::request_log::RequestLogSampler;
 use crate::routing_layer::RpcRouter;
 use crate::CLIENT_TARGET_API_VERSION_HEADER;
 
@@ -32,15 +34,22 @@ pub struct JsonRpcService<L> {
     /// Registered server methods.
     methods: Methods,
     rpc_router: RpcRouter,
+    request_log: Option<Arc<RequestLogSampler>>,
 }
 
 impl<L> JsonRpcService<L> {
-    pub fn new(methods: Methods, rpc_router: RpcRouter, logger: L) -> Self {
+    pub fn new(
+        methods: Methods,
+        rpc_router: RpcRouter,
+        logger: L,
+        request_log: Option<Arc<RequestLogSampler>>,
+    ) -> Self {
         Self {
             methods,
             rpc_router,
             logger,
             id_provider: Arc::new(RandomIntegerIdProvider),
+            request_log,
         }
     }
 }
@@ -106,7 +115,15 @@ pub async fn json_rpc_handler<L: Logger>(
     let api_version = headers
         .get(CLIENT_TARGET_API_VERSION_HEADER)
         .and_then(|h| h.to_str().ok());
-    let response = process_raw_request(&service, api_version, raw_request.get()).await;
+    let start = std::time::Instant::now();
+    let response = with_api_token(
+        bearer_token(&headers),
+        process_raw_request(&service, api_version, raw_request.get()),
+    )
+    .await;
+    if let Some(request_log) = &service.request_log {
+        request_log.log_rpc_request("http", raw_request.get(), &response, start.elapsed());
+    }
 
     ok_response(response.result)
 }
@@ -273,9 +290,13 @@ pub mod ws {
                 maybe_message = socket.recv() => {
                     if let Some(Ok(message)) = maybe_message {
                         if let Message::Text(msg) = message {
+                            let start = std::time::Instant::now();
                             let response =
                                 process_raw_request(&service, &msg, bounded_subscriptions.clone(), &sink).await;
                             if let Some(response) = response {
+                                if let Some(request_log) = &service.request_log {
+                                    request_log.log_rpc_request("websocket", &msg, &response, start.elapsed());
+                                }
                                 let _ = sink.send_raw(response.result);
                             }
                         }
@@ -4,6 +4,7 @@
 use std::env;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use hyper::header::HeaderName;
 use hyper::header::HeaderValue;
@@ -23,9 +24,11 @@ use sui_open_rpc::{Module, Project};
 
 use crate::error::Error;
 use crate::metrics::MetricsLogger;
+use crate::request_log::RequestLogSampler;
 use crate::routing_layer::RpcRouter;
 
 pub mod api;
+pub mod api_token;
 pub mod authority_state;
 pub mod axum_router;
 mod balance_changes;
@@ -39,6 +42,7 @@ pub mod move_utils;
 pub mod name_service;
 mod object_changes;
 pub mod read_api;
+pub mod request_log;
 mod routing_layer;
 pub mod transaction_builder_api;
 pub mod transaction_execution_api;
@@ -111,6 +115,7 @@ impl JsonRpcServerBuilder {
             .allow_origin(acl)
             .allow_headers([
                 hyper::header::CONTENT_TYPE,
+                hyper::header::AUTHORIZATION,
                 HeaderName::from_static(CLIENT_SDK_TYPE_HEADER),
                 HeaderName::from_static(CLIENT_SDK_VERSION_HEADER),
                 HeaderName::from_static(CLIENT_TARGET_API_VERSION_HEADER),
@@ -173,8 +178,13 @@ impl JsonRpcServerBuilder {
             .layer(Self::trace_layer())
             .layer(Self::cors()?);
 
-        let service =
-            crate::axum_router::JsonRpcService::new(module.into(), rpc_router, metrics_logger);
+        let request_log = RequestLogSampler::from_env().map(Arc::new);
+        let service = crate::axum_router::JsonRpcService::new(
+            module.into(),
+            rpc_router,
+            metrics_logger,
+            request_log,
+        );
 
         let mut router = axum::Router::new();
 
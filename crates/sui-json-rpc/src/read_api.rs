@@ -459,6 +459,73 @@ impl ReadApi {
             .inc_by(converted_tx_block_resps.len() as u64);
         Ok(converted_tx_block_resps)
     }
+
+    async fn multi_get_events_internal(
+        &self,
+        digests: Vec<TransactionDigest>,
+    ) -> Result<Vec<Vec<SuiEvent>>, Error> {
+        if digests.len() > *QUERY_MAX_RESULT_LIMIT {
+            Err(SuiRpcInputError::SizeLimitExceeded(
+                QUERY_MAX_RESULT_LIMIT.to_string(),
+            ))?
+        }
+        if digests.iter().unique().count() < digests.len() {
+            Err(SuiRpcInputError::ContainsDuplicates)?
+        }
+
+        let effects = self
+            .transaction_kv_store
+            .multi_get_fx_by_tx_digest(&digests)
+            .await
+            .tap_err(|err| debug!(digests=?digests, "Failed to multi get effects: {:?}", err))?
+            .into_iter()
+            .zip(digests.iter())
+            .map(|(fx, digest)| fx.ok_or(SuiError::TransactionNotFound { digest: *digest }))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let event_digests_list = effects
+            .iter()
+            .filter_map(|fx| fx.events_digest().cloned())
+            .collect::<Vec<TransactionEventsDigest>>();
+        let events = self
+            .transaction_kv_store
+            .multi_get_events(&event_digests_list)
+            .await
+            .map_err(|e| {
+                Error::UnexpectedError(format!("Failed to call multi_get_events for transactions {digests:?} with event digests {event_digests_list:?}: {e:?}"))
+            })?;
+        let event_digest_to_events = event_digests_list
+            .into_iter()
+            .zip(events)
+            .collect::<HashMap<_, _>>();
+        let events = match_events_to_effects(
+            effects.iter().map(|fx| fx.events_digest().copied()),
+            &event_digest_to_events,
+        )?;
+
+        let epoch_store = self.state.load_epoch_store_one_call_per_task();
+        effects
+            .iter()
+            .zip(events)
+            .map(|(fx, events)| {
+                events
+                    .data
+                    .into_iter()
+                    .enumerate()
+                    .map(|(seq, e)| {
+                        SuiEvent::try_from(
+                            e,
+                            *fx.transaction_digest(),
+                            seq as u64,
+                            None,
+                            epoch_store.module_cache(),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(Error::SuiError)
+            })
+            .collect()
+    }
 }
 
 #[async_trait]
@@ -889,6 +956,23 @@ impl ReadApiServer for ReadApi {
         })
     }
 
+    #[instrument(skip(self))]
+    async fn multi_get_events(
+        &self,
+        transaction_digests: Vec<TransactionDigest>,
+    ) -> RpcResult<Vec<Vec<SuiEvent>>> {
+        with_tracing!(async move {
+            let cloned_self = self.clone();
+            spawn_monitored_task!(async move {
+                cloned_self
+                    .multi_get_events_internal(transaction_digests)
+                    .await
+            })
+            .await
+            .map_err(Error::from)?
+        })
+    }
+
     #[instrument(skip(self))]
     async fn get_latest_checkpoint_sequence_number(&self) -> RpcResult<BigInt<u64>> {
         with_tracing!(async move {
@@ -1384,9 +1468,57 @@ fn calculate_checkpoint_numbers(
     }
 }
 
+/// The events of each transaction, by the events digest of its effects, if it emitted any.
+/// Transactions emitting the same events share their digest, so the events are cloned out of
+/// `event_digest_to_events` rather than taken.
+fn match_events_to_effects(
+    events_digests: impl IntoIterator<Item = Option<TransactionEventsDigest>>,
+    event_digest_to_events: &HashMap<TransactionEventsDigest, Option<TransactionEvents>>,
+) -> Result<Vec<TransactionEvents>, SuiError> {
+    events_digests
+        .into_iter()
+        .map(|event_digest| {
+            let Some(event_digest) = event_digest else {
+                return Ok(TransactionEvents::default());
+            };
+            event_digest_to_events
+                .get(&event_digest)
+                .cloned()
+                .flatten()
+                .ok_or(SuiError::TransactionEventsNotFound {
+                    digest: event_digest,
+                })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sui_types::event::Event;
+
+    #[test]
+    fn test_match_events_to_effects_shared_digest() {
+        let shared = TransactionEventsDigest::new([1; 32]);
+        let missing = TransactionEventsDigest::new([2; 32]);
+        let events = TransactionEvents {
+            data: vec![Event::random_for_testing()],
+        };
+        let event_digest_to_events = HashMap::from([(shared, Some(events.clone()))]);
+
+        let matched =
+            match_events_to_effects([Some(shared), None, Some(shared)], &event_digest_to_events)
+                .unwrap();
+        assert_eq!(
+            matched,
+            vec![events.clone(), TransactionEvents::default(), events]
+        );
+
+        assert!(matches!(
+            match_events_to_effects([Some(missing)], &event_digest_to_events),
+            Err(SuiError::TransactionEventsNotFound { digest }) if digest == missing
+        ));
+    }
 
     #[test]
     fn test_calculate_checkpoint_numbers() {
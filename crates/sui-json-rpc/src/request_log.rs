@@ -0,0 +1,194 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sampled logging of the requests served by public endpoints, free of user-identifying data.
+//!
+//! A sample of requests is logged with the method, latency, result code and payload sizes.
+//! Parameters are never logged: only the addresses and object IDs they contain are, hashed with a
+//! key drawn at startup, so that requests touching the same address can be told apart within a
+//! run of the server without revealing the address.
+
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use axum::middleware::Next;
+use axum::response::Response;
+use hyper::{Body, Request};
+use jsonrpsee::core::server::helpers::MethodResponse;
+use jsonrpsee::types::Request as RpcRequest;
+use tracing::{info, warn};
+
+/// Fraction of requests logged, between 0 and 1. Logging is disabled when unset or 0.
+pub const REQUEST_LOG_SAMPLE_RATE_ENV_VAR: &str = "RPC_REQUEST_LOG_SAMPLE_RATE";
+
+const ADDRESS_HEX_LENGTH: usize = 64;
+
+pub struct RequestLogSampler {
+    sample_rate: f64,
+    hash_key: RandomState,
+}
+
+impl RequestLogSampler {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            hash_key: RandomState::new(),
+        }
+    }
+
+    /// Sampler configured by [`REQUEST_LOG_SAMPLE_RATE_ENV_VAR`], if request logging is enabled.
+    pub fn from_env() -> Option<Self> {
+        let value = env::var(REQUEST_LOG_SAMPLE_RATE_ENV_VAR).ok()?;
+        match value.parse::<f64>() {
+            Ok(sample_rate) if sample_rate > 0.0 => Some(Self::new(sample_rate)),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Invalid {REQUEST_LOG_SAMPLE_RATE_ENV_VAR} {value:?}: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn sample(&self) -> bool {
+        rand::random::<f64>() < self.sample_rate
+    }
+
+    fn hash_address(&self, address: &str) -> String {
+        let mut hasher = self.hash_key.build_hasher();
+        address.to_ascii_lowercase().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Hashes of the full length addresses and object IDs found in `text`.
+    fn hashed_addresses(&self, text: &str) -> Vec<String> {
+        find_addresses(text)
+            .map(|address| self.hash_address(address))
+            .collect()
+    }
+
+    /// `text` with the full length addresses and object IDs it contains replaced by their hash.
+    fn redact_addresses(&self, text: &str) -> String {
+        let mut redacted = String::with_capacity(text.len());
+        let mut rest = text;
+        for address in find_addresses(text) {
+            let offset = address.as_ptr() as usize - rest.as_ptr() as usize;
+            redacted.push_str(&rest[..offset]);
+            redacted.push_str(&self.hash_address(address));
+            rest = &rest[offset + address.len()..];
+        }
+        redacted.push_str(rest);
+        redacted
+    }
+
+    /// Log a JSON-RPC request, if it was sampled.
+    pub fn log_rpc_request(
+        &self,
+        protocol: &str,
+        raw_request: &str,
+        response: &MethodResponse,
+        latency: Duration,
+    ) {
+        if !self.sample() {
+            return;
+        }
+        let method = serde_json::from_str::<RpcRequest>(raw_request)
+            .map(|request| request.method.into_owned())
+            .unwrap_or_default();
+        info!(
+            protocol,
+            method = %method,
+            latency_ms = latency.as_millis() as u64,
+            code = response.error_code.unwrap_or(0),
+            request_size = raw_request.len(),
+            response_size = response.result.len(),
+            addresses = ?self.hashed_addresses(raw_request),
+            "Sampled RPC request"
+        );
+    }
+}
+
+/// Substrings of `text` that are `0x` followed by exactly 64 hex digits.
+fn find_addresses(text: &str) -> impl Iterator<Item = &str> {
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        while let Some(offset) = text[start..].find("0x") {
+            let address_start = start + offset;
+            let digits = bytes[address_start + 2..]
+                .iter()
+                .take_while(|b| b.is_ascii_hexdigit())
+                .count();
+            start = address_start + 2 + digits;
+            if digits == ADDRESS_HEX_LENGTH {
+                return Some(&text[address_start..start]);
+            }
+        }
+        None
+    })
+}
+
+/// Middleware logging a sample of the REST requests served by a router, with the addresses in
+/// their paths hashed. To be added with `axum::middleware::from_fn_with_state`.
+pub async fn log_rest_request(
+    State(sampler): State<Arc<RequestLogSampler>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    if !sampler.sample() {
+        return next.run(request).await;
+    }
+    let method = request.method().clone();
+    let path = sampler.redact_addresses(request.uri().path());
+    let request_size = content_length(request.headers());
+    let start = Instant::now();
+    let response = next.run(request).await;
+    info!(
+        protocol = "rest",
+        method = %method,
+        path = %path,
+        latency_ms = start.elapsed().as_millis() as u64,
+        code = response.status().as_u16(),
+        request_size,
+        response_size = content_length(response.headers()),
+        "Sampled REST request"
+    );
+    response
+}
+
+fn content_length(headers: &hyper::HeaderMap) -> u64 {
+    headers
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::request_log::RequestLogSampler;
+
+    #[test]
+    fn test_addresses_are_hashed() {
+        let sampler = RequestLogSampler::new(1.0);
+        let address = format!("0x{}", "ab".repeat(32));
+        let path = format!("/objects/{address}/version/0x2");
+
+        let redacted = sampler.redact_addresses(&path);
+        assert!(!redacted.contains(&address));
+        assert!(redacted.starts_with("/objects/"));
+        assert!(redacted.ends_with("/version/0x2"));
+
+        let request = format!(
+            r#"{{"method":"suix_getBalance","params":["{}","0x2::sui::SUI"]}}"#,
+            address.to_uppercase().replacen("0X", "0x", 1)
+        );
+        assert_eq!(
+            sampler.hashed_addresses(&request),
+            vec![sampler.hash_address(&address)]
+        );
+    }
+}
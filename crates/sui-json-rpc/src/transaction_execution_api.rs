@@ -16,8 +16,9 @@ use sui_core::authority::AuthorityState;
 use sui_core::authority_client::NetworkAuthorityClient;
 use sui_core::transaction_orchestrator::TransactiondOrchestrator;
 use sui_json_rpc_types::{
-    DevInspectResults, DryRunTransactionBlockResponse, SuiTransactionBlock,
-    SuiTransactionBlockEvents, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+    DevInspectResults, DryRunTransactionBlockResponse, SharedObjectContention,
+    SharedObjectContentionEstimate, SuiTransactionBlock, SuiTransactionBlockEvents,
+    SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
 };
 use sui_open_rpc::Module;
 use sui_types::base_types::SuiAddress;
@@ -264,6 +265,42 @@ impl TransactionExecutionApi {
             input: resp.input,
         })
     }
+
+    fn estimate_shared_object_contention(
+        &self,
+        tx_bytes: Base64,
+    ) -> Result<SharedObjectContentionEstimate, Error> {
+        let tx_data: TransactionData = self.convert_bytes(tx_bytes)?;
+        let shared_objects = tx_data.shared_input_objects();
+        let estimates = self
+            .state
+            .get_objects_queue_estimates(shared_objects.iter().map(|object| object.id).collect());
+        let objects: Vec<_> = shared_objects
+            .into_iter()
+            .zip(estimates)
+            .map(|(object, estimate)| SharedObjectContention {
+                object_id: object.id,
+                mutable: object.mutable,
+                queue_length: estimate.queue_len as u64,
+                oldest_pending_ms: estimate.oldest_age.map(|age| age.as_millis() as u64),
+                release_interval_ms: estimate
+                    .release_interval
+                    .map(|interval| interval.as_millis() as u64),
+                estimated_wait_ms: estimate.estimated_wait().as_millis() as u64,
+            })
+            .collect();
+        // The queues of the objects are drained concurrently, so the transaction waits for the
+        // longest of them.
+        let estimated_inclusion_latency_ms = objects
+            .iter()
+            .map(|object| object.estimated_wait_ms)
+            .max()
+            .unwrap_or_default();
+        Ok(SharedObjectContentionEstimate {
+            objects,
+            estimated_inclusion_latency_ms,
+        })
+    }
 }
 
 #[async_trait]
@@ -306,6 +343,14 @@ impl WriteApiServer for TransactionExecutionApi {
     ) -> RpcResult<DryRunTransactionBlockResponse> {
         with_tracing!(async move { self.dry_run_transaction_block(tx_bytes).await })
     }
+
+    #[instrument(skip(self))]
+    async fn estimate_shared_object_contention(
+        &self,
+        tx_bytes: Base64,
+    ) -> RpcResult<SharedObjectContentionEstimate> {
+        with_tracing!(async move { self.estimate_shared_object_contention(tx_bytes) })
+    }
 }
 
 impl SuiRpcModule for TransactionExecutionApi {
@@ -12,7 +12,9 @@ use backoff::ExponentialBackoff;
 use serde::Serialize;
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 use sui_config::node::TransactionKeyValueStoreWriteConfig;
+use sui_storage::object_store::compression::ZstdDictionary;
 
 #[derive(Hash, Eq, PartialEq, Debug, Copy, Clone)]
 pub enum KVTable {
@@ -27,6 +29,8 @@ pub enum KVTable {
 }
 
 const UPLOAD_PROGRESS_KEY: [u8; 1] = [0];
+/// zstd level values are compressed with when a dictionary is configured.
+const COMPRESSION_LEVEL: i32 = 3;
 
 #[async_trait]
 pub trait KVWriteClient {
@@ -57,10 +61,12 @@ pub struct DynamoDbClient {
     s3_client: s3::Client,
     table_name: String,
     bucket_name: String,
+    /// Values are compressed with this dictionary if set, and written as plain BCS otherwise.
+    dictionary: Option<Arc<ZstdDictionary>>,
 }
 
 impl DynamoDbClient {
-    pub async fn new(config: &TransactionKeyValueStoreWriteConfig) -> Self {
+    pub async fn new(config: &TransactionKeyValueStoreWriteConfig) -> anyhow::Result<Self> {
         let credentials = Credentials::new(
             &config.aws_access_key_id,
             &config.aws_secret_access_key,
@@ -75,11 +81,26 @@ impl DynamoDbClient {
             .await;
         let dynamo_client = dynamodb::Client::new(&aws_config);
         let s3_client = s3::Client::new(&aws_config);
-        Self {
+        let dictionary = config
+            .compression_dictionary_path
+            .as_deref()
+            .map(ZstdDictionary::from_file)
+            .transpose()?
+            .map(Arc::new);
+        Ok(Self {
             dynamo_client,
             s3_client,
             table_name: config.table_name.clone(),
             bucket_name: config.bucket_name.clone(),
+            dictionary,
+        })
+    }
+
+    fn encode<V: Serialize>(&self, value: &V) -> anyhow::Result<Vec<u8>> {
+        let bytes = bcs::to_bytes(value)?;
+        match &self.dictionary {
+            Some(dictionary) => dictionary.compress(&bytes, COMPRESSION_LEVEL),
+            None => Ok(bytes),
         }
     }
 
@@ -119,7 +140,7 @@ impl KVWriteClient for DynamoDbClient {
                         .item("type", AttributeValue::S(Self::type_name(table)))
                         .item(
                             "bcs",
-                            AttributeValue::B(Blob::new(bcs::to_bytes(value.borrow())?)),
+                            AttributeValue::B(Blob::new(self.encode(value.borrow())?)),
                         )
                         .build(),
                 ))
@@ -195,7 +216,7 @@ impl KVWriteClient for DynamoDbClient {
         key: Vec<u8>,
         value: V,
     ) -> anyhow::Result<()> {
-        let body = bcs::to_bytes(value.borrow())?.into();
+        let body = self.encode(value.borrow())?.into();
         self.s3_client
             .put_object()
             .bucket(self.bucket_name.clone())
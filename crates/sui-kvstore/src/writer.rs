@@ -64,7 +64,7 @@ async fn upload_to_kv_store(
     metrics: KVStoreMetrics,
 ) -> Result<()> {
     let mut updates: HashSet<u64> = HashSet::new();
-    let mut client = DynamoDbClient::new(&config).await;
+    let mut client = DynamoDbClient::new(&config).await?;
     let mut checkpoint_number = client
         .get_state()
         .await
@@ -127,7 +127,7 @@ pub async fn uploader(
     progress_sender: mpsc::Sender<u64>,
     mut receiver: oneshot::Receiver<()>,
 ) -> Result<()> {
-    let client = DynamoDbClient::new(&config).await;
+    let client = DynamoDbClient::new(&config).await?;
     while receiver.try_recv().is_err() {
         let last_executed_checkpoint = store
             .get_last_executed_checkpoint()?
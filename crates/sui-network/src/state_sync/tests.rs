@@ -287,7 +287,7 @@ async fn test_state_sync_using_archive() -> anyhow::Result<()> {
     };
     let archive_writer = ArchiveWriter::new(
         local_store_config.clone(),
-        remote_store_config.clone(),
+        remote_store_config.clone().into(),
         FileCompression::Zstd,
         StorageFormat::Blob,
         Duration::from_secs(10),
@@ -310,6 +310,7 @@ async fn test_state_sync_using_archive() -> anyhow::Result<()> {
     let kill = archive_writer.start(test_store).await?;
     let archive_reader_config = ArchiveReaderConfig {
         remote_store_config,
+        mirror_store_configs: vec![],
         download_concurrency: NonZeroUsize::new(1).unwrap(),
         use_for_pruning_watermark: false,
     };
@@ -6,12 +6,13 @@ use axum::{
     extract::{Query, State},
     http::StatusCode,
     routing::{get, post},
-    Router,
+    Json, Router,
 };
 use humantime::parse_duration;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use sui_protocol_config::SupportedProtocolVersions;
 use sui_types::error::SuiError;
 use telemetry_subscribers::TracingHandle;
 use tracing::info;
@@ -39,6 +40,10 @@ use tracing::info;
 //
 //   $ curl 'http://127.0.0.1:1337/node-config'
 //
+// View the build of this node, and the protocol versions and feature flags it runs with:
+//
+//   $ curl 'http://127.0.0.1:1337/build-info'
+//
 // Set a time-limited tracing config. After the duration expires, tracing will be disabled
 // automatically.
 //
@@ -56,24 +61,33 @@ const CLEAR_BUFFER_STAKE_ROUTE: &str = "/clear-override-buffer-stake";
 const FORCE_CLOSE_EPOCH: &str = "/force-close-epoch";
 const CAPABILITIES: &str = "/capabilities";
 const NODE_CONFIG: &str = "/node-config";
+const BUILD_INFO: &str = "/build-info";
 
 struct AppState {
     node: Arc<SuiNode>,
     tracing_handle: TracingHandle,
+    git_revision: &'static str,
 }
 
-pub async fn run_admin_server(node: Arc<SuiNode>, port: u16, tracing_handle: TracingHandle) {
+pub async fn run_admin_server(
+    node: Arc<SuiNode>,
+    port: u16,
+    tracing_handle: TracingHandle,
+    git_revision: &'static str,
+) {
     let filter = tracing_handle.get_log().unwrap();
 
     let app_state = AppState {
         node,
         tracing_handle,
+        git_revision,
     };
 
     let app = Router::new()
         .route(LOGGING_ROUTE, get(get_filter))
         .route(CAPABILITIES, get(capabilities))
         .route(NODE_CONFIG, get(node_config))
+        .route(BUILD_INFO, get(build_info))
         .route(LOGGING_ROUTE, post(set_filter))
         .route(
             SET_BUFFER_STAKE_ROUTE,
@@ -216,6 +230,49 @@ async fn node_config(State(state): State<Arc<AppState>>) -> (StatusCode, String)
     (StatusCode::OK, format!("{:#?}\n", node_config))
 }
 
+#[derive(Serialize)]
+struct BuildInfo {
+    version: &'static str,
+    git_revision: &'static str,
+    /// `debug` or `release`
+    profile: &'static str,
+    min_supported_protocol_version: u64,
+    max_supported_protocol_version: u64,
+    /// Protocol version of the current epoch
+    protocol_version: u64,
+    /// Protocol feature flags enabled in the current epoch
+    feature_flags: Vec<String>,
+}
+
+async fn build_info(State(state): State<Arc<AppState>>) -> Json<BuildInfo> {
+    let epoch_store = state.node.state().load_epoch_store_one_call_per_task();
+    let supported_protocol_versions = state
+        .node
+        .config
+        .supported_protocol_versions
+        .unwrap_or(SupportedProtocolVersions::SYSTEM_DEFAULT);
+    let feature_flags = epoch_store
+        .protocol_config()
+        .feature_map()
+        .into_iter()
+        .filter_map(|(flag, enabled)| enabled.then_some(flag))
+        .collect();
+
+    Json(BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_revision: state.git_revision,
+        profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        min_supported_protocol_version: supported_protocol_versions.min.as_u64(),
+        max_supported_protocol_version: supported_protocol_versions.max.as_u64(),
+        protocol_version: epoch_store.protocol_version().as_u64(),
+        feature_flags,
+    })
+}
+
 #[derive(Deserialize)]
 struct Epoch {
     epoch: u64,
@@ -90,6 +90,7 @@ use sui_json_rpc::governance_api::GovernanceReadApi;
 use sui_json_rpc::indexer_api::IndexerApi;
 use sui_json_rpc::move_utils::MoveUtils;
 use sui_json_rpc::read_api::ReadApi;
+use sui_json_rpc::request_log::{log_rest_request, RequestLogSampler};
 use sui_json_rpc::transaction_builder_api::TransactionBuilderApi;
 use sui_json_rpc::transaction_execution_api::TransactionExecutionApi;
 use sui_json_rpc::JsonRpcServerBuilder;
@@ -101,11 +102,14 @@ use sui_network::discovery::TrustedPeerChangeEvent;
 use sui_network::state_sync;
 use sui_protocol_config::{Chain, ProtocolConfig, SupportedProtocolVersions};
 use sui_snapshot::uploader::StateSnapshotUploader;
+use sui_storage::object_store::compression::ZstdDictionary;
 use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreType};
 use sui_storage::{
     http_key_value_store::HttpKVStore,
     key_value_store::{FallbackTransactionKVStore, TransactionKeyValueStore},
     key_value_store_metrics::KeyValueStoreMetrics,
+    memory_budget::init_memory_budget,
+    packed_key_value_store::PackReader,
 };
 use sui_storage::{FileCompression, IndexStore, StorageFormat};
 use sui_types::base_types::{AuthorityName, EpochId};
@@ -413,6 +417,10 @@ impl SuiNode {
         // Initialize metrics to track db usage before creating any stores
         DBMetrics::init(&prometheus_registry);
         mysten_metrics::init_metrics(&prometheus_registry);
+        // Caches register with the budget when created, so it must be set before any store.
+        if let Some(memory_budget_bytes) = config.memory_budget_bytes {
+            init_memory_budget(memory_budget_bytes);
+        }
 
         let genesis = config.genesis()?;
 
@@ -781,7 +789,7 @@ impl SuiNode {
         prometheus_registry: &Registry,
         state_sync_store: RocksDbStore,
     ) -> Result<Option<tokio::sync::broadcast::Sender<()>>> {
-        if let Some(remote_store_config) = &config.state_archive_write_config.object_store_config {
+        if let Some(remote_store_config) = config.state_archive_write_config.write_store_config() {
             let local_store_config = ObjectStoreConfig {
                 object_store: Some(ObjectStoreType::File),
                 directory: Some(config.archive_path()),
@@ -789,7 +797,7 @@ impl SuiNode {
             };
             let archive_writer = ArchiveWriter::new(
                 local_store_config,
-                remote_store_config.clone(),
+                remote_store_config,
                 FileCompression::Zstd,
                 StorageFormat::Blob,
                 Duration::from_secs(600),
@@ -807,11 +815,11 @@ impl SuiNode {
         config: &NodeConfig,
         prometheus_registry: &Registry,
     ) -> Result<Option<oneshot::Sender<()>>> {
-        if let Some(remote_store_config) = &config.state_snapshot_write_config.object_store_config {
+        if let Some(remote_store_config) = config.state_snapshot_write_config.write_store_config() {
             let snapshot_uploader = StateSnapshotUploader::new(
                 &config.db_checkpoint_path(),
                 &config.snapshot_path(),
-                remote_store_config.clone(),
+                remote_store_config,
                 60,
                 prometheus_registry,
             )?;
@@ -869,6 +877,9 @@ impl SuiNode {
                     db_checkpoint_config
                         .prune_and_compact_before_upload
                         .unwrap_or(true),
+                    db_checkpoint_config
+                        .content_addressed_upload
+                        .unwrap_or(false),
                     config.indirect_objects_threshold,
                     config.authority_store_pruning_config,
                     prometheus_registry,
@@ -1688,7 +1699,21 @@ fn build_kv_store(
     };
 
     let base_url = base_url.join(network_str)?.to_string();
-    let http_store = HttpKVStore::new_kv(&base_url, metrics.clone())?;
+    let dictionary = config
+        .transaction_kv_store_read_config
+        .compression_dictionary_path
+        .as_deref()
+        .map(ZstdDictionary::from_file)
+        .transpose()?
+        .map(Arc::new);
+    let packs = config
+        .transaction_kv_store_read_config
+        .pack_store_config
+        .as_ref()
+        .map(ObjectStoreConfig::make)
+        .transpose()?
+        .map(|store| PackReader::load_in_background(store, network_str.trim_start_matches('/')));
+    let http_store = HttpKVStore::new_kv(&base_url, dictionary, packs, metrics.clone())?;
     info!("using local key-value store with fallback to http key-value store");
     Ok(Arc::new(FallbackTransactionKVStore::new_kv(
         db_store,
@@ -1770,7 +1795,13 @@ pub fn build_http_server(
     router = router.merge(json_rpc_router);
 
     if config.enable_experimental_rest_api {
-        let rest_router = sui_rest_api::rest_router(state);
+        let mut rest_router = sui_rest_api::rest_router(state);
+        if let Some(request_log) = RequestLogSampler::from_env() {
+            rest_router = rest_router.layer(axum::middleware::from_fn_with_state(
+                Arc::new(request_log),
+                log_rest_request,
+            ));
+        }
         router = router.nest("/rest", rest_router);
     }
 
@@ -134,7 +134,8 @@ fn main() {
             ))
             .unwrap();
 
-        sui_node::admin::run_admin_server(node, admin_interface_port, filter_handle).await
+        sui_node::admin::run_admin_server(node, admin_interface_port, filter_handle, GIT_REVISION)
+            .await
     });
 
     runtimes.metrics.spawn(async move {
@@ -23,15 +23,15 @@ use sui_json_rpc_types::{
     DevInspectResults, DynamicFieldPage, EventFilter, EventPage, MoveCallParams,
     MoveFunctionArgType, ObjectChange, ObjectValueKind::ByImmutableReference,
     ObjectValueKind::ByMutableReference, ObjectValueKind::ByValue, ObjectsPage, OwnedObjectRef,
-    Page, ProtocolConfigResponse, RPCTransactionRequestParams, Stake, StakeStatus, SuiCoinMetadata,
-    SuiCommittee, SuiData, SuiEvent, SuiExecutionStatus, SuiGetPastObjectRequest,
-    SuiLoadedChildObject, SuiLoadedChildObjectsResponse, SuiMoveAbility, SuiMoveAbilitySet,
-    SuiMoveNormalizedFunction, SuiMoveNormalizedModule, SuiMoveNormalizedStruct,
-    SuiMoveNormalizedType, SuiMoveVisibility, SuiObjectData, SuiObjectDataFilter,
-    SuiObjectDataOptions, SuiObjectRef, SuiObjectResponse, SuiObjectResponseQuery, SuiParsedData,
-    SuiPastObjectResponse, SuiTransactionBlock, SuiTransactionBlockData,
-    SuiTransactionBlockEffects, SuiTransactionBlockEffectsV1, SuiTransactionBlockEvents,
-    SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+    Page, ProtocolConfigResponse, RPCTransactionRequestParams, SharedObjectContention,
+    SharedObjectContentionEstimate, Stake, StakeStatus, SuiCoinMetadata, SuiCommittee, SuiData,
+    SuiEvent, SuiExecutionStatus, SuiGetPastObjectRequest, SuiLoadedChildObject,
+    SuiLoadedChildObjectsResponse, SuiMoveAbility, SuiMoveAbilitySet, SuiMoveNormalizedFunction,
+    SuiMoveNormalizedModule, SuiMoveNormalizedStruct, SuiMoveNormalizedType, SuiMoveVisibility,
+    SuiObjectData, SuiObjectDataFilter, SuiObjectDataOptions, SuiObjectRef, SuiObjectResponse,
+    SuiObjectResponseQuery, SuiParsedData, SuiPastObjectResponse, SuiTransactionBlock,
+    SuiTransactionBlockData, SuiTransactionBlockEffects, SuiTransactionBlockEffectsV1,
+    SuiTransactionBlockEvents, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
     SuiTransactionBlockResponseQuery, TransactionBlockBytes, TransactionBlocksPage,
     TransactionFilter, TransferObjectParams,
 };
@@ -142,6 +142,8 @@ impl RpcExampleProvider {
             self.suix_resolve_name_service_address(),
             self.suix_resolve_name_service_names(),
             self.sui_try_multi_get_past_objects(),
+            self.sui_multi_get_events(),
+            self.sui_estimate_shared_object_contention(),
         ]
         .into_iter()
         .map(|example| (example.function_name, example.examples))
@@ -801,6 +803,80 @@ impl RpcExampleProvider {
         )
     }
 
+    fn sui_multi_get_events(&mut self) -> Examples {
+        let tx_digs = [
+            TransactionDigest::new([1; 32]),
+            TransactionDigest::new([2; 32]),
+        ];
+        let event = SuiEvent {
+            id: EventID {
+                tx_digest: tx_digs[0],
+                event_seq: 0,
+            },
+            package_id: ObjectID::from_single_byte(9),
+            transaction_module: Identifier::from_str("test_module").unwrap(),
+            sender: SuiAddress::from(ObjectID::from_single_byte(10)),
+            type_: parse_sui_struct_tag("0x9::test::TestEvent").unwrap(),
+            parsed_json: json!({"test": "example value"}),
+            bcs: vec![],
+            timestamp_ms: None,
+        };
+
+        Examples::new(
+            "sui_multiGetEvents",
+            vec![ExamplePairing::new(
+                "Returns the events each transaction in the request emits, in the order of the digests.",
+                vec![("transaction_digests", json!(tx_digs))],
+                json!(vec![vec![event], vec![]]),
+            )],
+        )
+    }
+
+    fn sui_estimate_shared_object_contention(&mut self) -> Examples {
+        let counter = ObjectID::from_single_byte(12);
+        let data = TransactionData::new_move_call(
+            SuiAddress::from(ObjectID::from_single_byte(10)),
+            ObjectID::from_single_byte(9),
+            Identifier::from_str("counter").unwrap(),
+            Identifier::from_str("increment").unwrap(),
+            vec![],
+            (
+                ObjectID::from_single_byte(11),
+                SequenceNumber::from_u64(2),
+                ObjectDigest::new([1; 32]),
+            ),
+            vec![CallArg::Object(ObjectArg::SharedObject {
+                id: counter,
+                initial_shared_version: SequenceNumber::from_u64(1),
+                mutable: true,
+            })],
+            10_000_000,
+            1_000,
+        )
+        .unwrap();
+        let tx_bytes = TransactionBlockBytes::from_data(data).unwrap();
+        let result = SharedObjectContentionEstimate {
+            objects: vec![SharedObjectContention {
+                object_id: counter,
+                mutable: true,
+                queue_length: 3,
+                oldest_pending_ms: Some(1_200),
+                release_interval_ms: Some(400),
+                estimated_wait_ms: 1_200,
+            }],
+            estimated_inclusion_latency_ms: 1_200,
+        };
+
+        Examples::new(
+            "sui_estimateSharedObjectContention",
+            vec![ExamplePairing::new(
+                "Estimates how long a transaction incrementing a shared counter would wait for it once sequenced.",
+                vec![("tx_bytes", json!(tx_bytes.tx_bytes))],
+                json!(result),
+            )],
+        )
+    }
+
     fn sui_get_committee_info(&mut self) -> Examples {
         let epoch = 5000;
         let committee = json!(Committee::new_simple_test_committee_of_size(4));
@@ -0,0 +1,138 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Dry run of the execution layer of this binary against archived checkpoints, to catch forks
+//! introduced by execution or protocol changes before they ship.
+//!
+//! Archives hold the transactions and effects of checkpoints but no objects, so input objects
+//! are still fetched from the RPC endpoint. Effects are compared against the archived ones,
+//! which covers the resulting state too, as effects commit to the digests of all written objects.
+
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use prometheus::Registry;
+use sui_archival::reader::{ArchiveReader, ArchiveReaderMetrics};
+use sui_config::node::{ArchiveReaderConfig, ExpensiveSafetyCheckConfig};
+use sui_json_rpc_types::SuiTransactionBlockEffects;
+use sui_storage::object_store::ObjectStoreConfig;
+use sui_types::digests::TransactionDigest;
+use sui_types::message_envelope::Message;
+use sui_types::storage::{ReadStore, SharedInMemoryStore};
+use tracing::{error, info};
+
+use crate::replay::LocalExec;
+
+pub struct ArchiveReplayConfig {
+    pub archive_store_config: ObjectStoreConfig,
+    pub download_concurrency: usize,
+    /// Inclusive.
+    pub start_checkpoint: u64,
+    /// Inclusive.
+    pub end_checkpoint: u64,
+    pub terminate_early: bool,
+    pub executor_version_override: Option<i64>,
+    pub protocol_version_override: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct ArchiveReplayReport {
+    pub total: u64,
+    pub matched: u64,
+    /// Transactions whose local effects differ from the archived ones.
+    pub forked: Vec<TransactionDigest>,
+    /// Transactions that could not be replayed, e.g. because an input object was pruned.
+    pub failed: Vec<(TransactionDigest, String)>,
+}
+
+/// Replay every transaction of the checkpoint range in `config`, reading them from the archive,
+/// and diff the effects of the local execution against the archived effects.
+pub async fn replay_archived_checkpoints(
+    rpc_url: String,
+    safety: ExpensiveSafetyCheckConfig,
+    use_authority: bool,
+    config: ArchiveReplayConfig,
+) -> anyhow::Result<ArchiveReplayReport> {
+    if config.start_checkpoint > config.end_checkpoint {
+        bail!("Start checkpoint must be <= end checkpoint");
+    }
+    let store = SharedInMemoryStore::default();
+    let archive_reader = ArchiveReader::new(
+        ArchiveReaderConfig {
+            remote_store_config: config.archive_store_config,
+            mirror_store_configs: vec![],
+            download_concurrency: NonZeroUsize::new(config.download_concurrency)
+                .ok_or_else(|| anyhow!("Download concurrency must be > 0"))?,
+            use_for_pruning_watermark: false,
+        },
+        &ArchiveReaderMetrics::new(&Registry::default()),
+    )?;
+    archive_reader.sync_manifest_once().await?;
+    archive_reader
+        .read(
+            store.clone(),
+            Range {
+                start: config.start_checkpoint,
+                end: config.end_checkpoint + 1,
+            },
+            Arc::new(AtomicU64::new(0)),
+            Arc::new(AtomicU64::new(0)),
+            false,
+        )
+        .await?;
+
+    let mut executor = LocalExec::new_from_fn_url(&rpc_url)
+        .await?
+        .init_for_execution()
+        .await?;
+    let mut report = ArchiveReplayReport::default();
+    for sequence_number in config.start_checkpoint..=config.end_checkpoint {
+        let contents = store
+            .get_full_checkpoint_contents_by_sequence_number(sequence_number)?
+            .ok_or_else(|| anyhow!("Checkpoint {sequence_number} is missing from the archive"))?;
+        info!(
+            "Replaying {} transactions of checkpoint {sequence_number}",
+            contents.size()
+        );
+        for data in contents.iter() {
+            let tx_digest = *data.transaction.digest();
+            report.total += 1;
+            let result = executor
+                .execute_transaction(
+                    &tx_digest,
+                    safety.clone(),
+                    use_authority,
+                    config.executor_version_override,
+                    config.protocol_version_override,
+                )
+                .await;
+            let mut sandbox_state = match result {
+                Ok(sandbox_state) => sandbox_state,
+                Err(e) => {
+                    error!("Failed to replay tx {tx_digest}: {e}");
+                    if config.terminate_early {
+                        return Err(e.into());
+                    }
+                    report.failed.push((tx_digest, e.to_string()));
+                    continue;
+                }
+            };
+            // Diff against the archived effects rather than the ones served by the RPC endpoint.
+            sandbox_state.transaction_info.effects =
+                SuiTransactionBlockEffects::try_from(data.effects.clone())?;
+            match sandbox_state.check_effects() {
+                Ok(()) => report.matched += 1,
+                Err(e) => {
+                    if config.terminate_early {
+                        return Err(e.into());
+                    }
+                    report.forked.push(tx_digest);
+                }
+            }
+        }
+    }
+    Ok(report)
+}
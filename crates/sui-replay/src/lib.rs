@@ -1,6 +1,7 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use archive::{replay_archived_checkpoints, ArchiveReplayConfig};
 use async_recursion::async_recursion;
 use clap::Parser;
 use config::ReplayableNetworkConfigSet;
@@ -22,13 +23,16 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use sui_config::node::ExpensiveSafetyCheckConfig;
 use sui_protocol_config::Chain;
+use sui_storage::object_store::ObjectStoreConfig;
 use sui_types::digests::TransactionDigest;
 use tracing::{error, info};
+pub mod archive;
 pub mod config;
 mod data_fetcher;
 pub mod fuzz;
 pub mod fuzz_mutations;
 mod replay;
+mod trace;
 pub mod transaction_provider;
 pub mod types;
 
@@ -77,6 +81,17 @@ pub enum ReplayToolCommand {
         protocol_version_override: Option<i64>,
     },
 
+    /// Replay a transaction with the Move VM gas profiler on, and print the gas used by each
+    /// command and the Move calls it made. The transaction and its input objects are fetched from
+    /// the `--rpc` endpoint, which can be a fullnode or an indexer serving JSON-RPC. Archives only
+    /// hold checkpoint contents and not objects, so cannot be replayed from. Only available in
+    /// debug builds, where the profiler is compiled in.
+    #[command(name = "trace")]
+    TraceTransaction {
+        #[arg(long, short)]
+        tx_digest: String,
+    },
+
     /// Replay transactions listed in a file
     #[command(name = "rb")]
     ReplayBatch {
@@ -121,6 +136,29 @@ pub enum ReplayToolCommand {
         max_tasks: u64,
     },
 
+    /// Replay all transactions in a range of checkpoints read from an archive, and diff their
+    /// effects against the archived effects. Meant as a dry run of execution or protocol changes
+    /// in this binary before they ship, with the version overrides selecting the protocol version
+    /// to dry run. Input objects are still fetched from the `--rpc` endpoint.
+    #[command(name = "ar")]
+    ReplayArchive {
+        #[arg(long, short)]
+        start: u64,
+        #[arg(long, short)]
+        end: u64,
+        #[arg(long, short)]
+        terminate_early: bool,
+        #[arg(long, allow_hyphen_values = true)]
+        executor_version_override: Option<i64>,
+        #[arg(long, allow_hyphen_values = true)]
+        protocol_version_override: Option<i64>,
+        #[arg(long, default_value = "5")]
+        download_concurrency: usize,
+        /// Archive store to read checkpoints from
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+    },
+
     /// Run the replay based fuzzer
     #[command(name = "fz")]
     Fuzz {
@@ -376,6 +414,86 @@ pub async fn execute_replay_command(
             Some((1u64, 1u64))
         }
 
+        ReplayToolCommand::TraceTransaction { tx_digest } => {
+            if !cfg!(debug_assertions) {
+                anyhow::bail!(
+                    "The Move VM gas profiler is only available in debug builds, rebuild without --release"
+                );
+            }
+            // The VM reads this once, so it must be set before anything is executed.
+            env::set_var(trace::MOVE_VM_PROFILE_ENV_VAR, "1");
+
+            let tx_digest = TransactionDigest::from_str(&tx_digest)?;
+            info!("Tracing tx: {}", tx_digest);
+            let started_at = std::time::SystemTime::now();
+            let sandbox_state = LocalExec::replay_with_network_config(
+                rpc_url,
+                cfg_path.map(|p| p.to_str().unwrap().to_string()),
+                tx_digest,
+                safety,
+                use_authority,
+                None,
+                None,
+            )
+            .await?;
+
+            // The profiler writes to the working directory.
+            let profile_path =
+                trace::find_gas_profile(&env::current_dir()?, &tx_digest, started_at)?;
+            let trace = trace::TransactionTrace::new(&sandbox_state, profile_path)?;
+            println!("{trace}");
+
+            // A trace of a forked execution is still useful for debugging, so only warn.
+            if let Err(e) = sandbox_state.check_effects() {
+                warn!("Local effects do not match on-chain effects: {e}");
+            }
+            Some((1u64, 1u64))
+        }
+
+        ReplayToolCommand::ReplayArchive {
+            start,
+            end,
+            terminate_early,
+            executor_version_override,
+            protocol_version_override,
+            download_concurrency,
+            object_store_config,
+        } => {
+            let config = ArchiveReplayConfig {
+                archive_store_config: object_store_config,
+                download_concurrency,
+                start_checkpoint: start,
+                end_checkpoint: end,
+                terminate_early,
+                executor_version_override,
+                protocol_version_override,
+            };
+            let report = replay_archived_checkpoints(
+                rpc_url.expect("Url must be provided"),
+                safety,
+                use_authority,
+                config,
+            )
+            .await?;
+            for tx_digest in &report.forked {
+                println!("Forked: {tx_digest}");
+            }
+            for (tx_digest, e) in &report.failed {
+                println!("Failed: {tx_digest}: {e}");
+            }
+            println!(
+                "Replayed checkpoints {start} to {end}: {} transactions, {} matched, {} forked, {} failed",
+                report.total,
+                report.matched,
+                report.forked.len(),
+                report.failed.len()
+            );
+            if !report.forked.is_empty() {
+                anyhow::bail!("Effects forked for {} transactions", report.forked.len());
+            }
+            Some((report.matched, report.total))
+        }
+
         ReplayToolCommand::Report => {
             let mut lx =
                 LocalExec::new_from_fn_url(&rpc_url.expect("Url must be provided")).await?;
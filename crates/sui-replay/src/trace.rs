@@ -0,0 +1,376 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Step-by-step traces of replayed transactions, built from the gas profile the Move VM writes
+//! when `MOVE_VM_PROFILE` is set (debug builds only).
+
+use crate::replay::ExecutionSandboxState;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use sui_json_rpc_types::SuiTransactionBlockEffectsAPI;
+use sui_types::digests::TransactionDigest;
+use sui_types::transaction::{Command, TransactionKind};
+
+/// Environment variable the Move VM checks (once, on first use) to decide whether to profile.
+pub const MOVE_VM_PROFILE_ENV_VAR: &str = "MOVE_VM_PROFILE";
+
+const OPEN_FRAME: &str = "O";
+const CLOSE_FRAME: &str = "C";
+
+/// The subset of the speedscope format written by the Move VM gas profiler that traces need.
+#[derive(Deserialize)]
+struct GasProfile {
+    shared: SharedFrames,
+    profiles: Vec<ProfileEvents>,
+}
+
+#[derive(Deserialize)]
+struct SharedFrames {
+    frames: Vec<Frame>,
+}
+
+#[derive(Deserialize)]
+struct Frame {
+    /// Fully qualified function name, `0x<address>::<module>::<function>`.
+    file: String,
+}
+
+#[derive(Deserialize)]
+struct ProfileEvents {
+    events: Vec<FrameEvent>,
+}
+
+#[derive(Deserialize)]
+struct FrameEvent {
+    #[serde(rename = "type")]
+    ty: String,
+    frame: usize,
+    /// Gas consumed by the transaction so far.
+    at: u64,
+}
+
+/// A call to a Move function, with the gas spent in it (including its callees).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallTrace {
+    pub function: String,
+    pub gas_used: u64,
+    pub calls: Vec<CallTrace>,
+}
+
+/// A command of a programmable transaction, with the Move calls it made.
+#[derive(Clone, Debug)]
+pub struct CommandTrace {
+    pub index: usize,
+    pub command: String,
+    /// Gas spent in the Move VM by this command. Commands that do not call into Move (e.g.
+    /// `SplitCoins`) are not metered separately.
+    pub gas_used: Option<u64>,
+    pub calls: Vec<CallTrace>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TransactionTrace {
+    pub tx_digest: TransactionDigest,
+    pub status: String,
+    pub gas_summary: String,
+    pub commands: Vec<CommandTrace>,
+    /// Calls that could not be attributed to a command.
+    pub unattributed_calls: Vec<CallTrace>,
+    pub profile_path: Option<PathBuf>,
+}
+
+impl TransactionTrace {
+    /// Build the trace of a replayed transaction from the gas profile at `profile_path`, if the
+    /// VM wrote one. System transactions are not metered, so have none.
+    pub fn new(
+        sandbox_state: &ExecutionSandboxState,
+        profile_path: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let calls = match &profile_path {
+            Some(path) => {
+                let profile: GasProfile = serde_json::from_slice(&std::fs::read(path)?)?;
+                call_traces(&profile)?
+            }
+            None => vec![],
+        };
+        let commands = match &sandbox_state.transaction_info.kind {
+            TransactionKind::ProgrammableTransaction(pt) => pt.commands.as_slice(),
+            _ => &[],
+        };
+        let (commands, unattributed_calls) = attribute_calls(commands, calls);
+
+        let status = match &sandbox_state.local_exec_status {
+            Some(Ok(())) | None => "success".to_string(),
+            Some(Err(e)) => match e.command() {
+                Some(command) => format!("failed in command {command}: {:?}", e.kind()),
+                None => format!("failed: {:?}", e.kind()),
+            },
+        };
+        let gas = sandbox_state.local_exec_effects.gas_cost_summary();
+        let gas_summary = format!(
+            "computation {}, storage {}, storage rebate {}, non-refundable storage fee {} \
+             (budget {}, price {})",
+            gas.computation_cost,
+            gas.storage_cost,
+            gas.storage_rebate,
+            gas.non_refundable_storage_fee,
+            sandbox_state.transaction_info.gas_budget,
+            sandbox_state.transaction_info.gas_price,
+        );
+
+        Ok(Self {
+            tx_digest: sandbox_state.transaction_info.tx_digest,
+            status,
+            gas_summary,
+            commands,
+            unattributed_calls,
+            profile_path,
+        })
+    }
+}
+
+impl Display for TransactionTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Transaction {}", self.tx_digest)?;
+        writeln!(f, "Status: {}", self.status)?;
+        writeln!(f, "Gas: {}", self.gas_summary)?;
+        for command in &self.commands {
+            let gas = command
+                .gas_used
+                .map_or_else(|| "-".to_string(), |gas| gas.to_string());
+            writeln!(f, "\n[{}] {}", command.index, command.command)?;
+            writeln!(f, "    VM gas: {gas}")?;
+            write_calls(f, &command.calls, 2)?;
+        }
+        if !self.unattributed_calls.is_empty() {
+            writeln!(f, "\nCalls not attributed to a command:")?;
+            write_calls(f, &self.unattributed_calls, 2)?;
+        }
+        match &self.profile_path {
+            Some(path) => writeln!(
+                f,
+                "\nFull gas profile (open with https://www.speedscope.app): {}",
+                path.display()
+            ),
+            None => writeln!(
+                f,
+                "\nNo gas profile was written, the transaction is unmetered."
+            ),
+        }
+    }
+}
+
+fn write_calls(f: &mut Formatter<'_>, calls: &[CallTrace], depth: usize) -> std::fmt::Result {
+    for call in calls {
+        writeln!(
+            f,
+            "{:indent$}{} [{}]",
+            "",
+            call.function,
+            call.gas_used,
+            indent = depth * 2
+        )?;
+        write_calls(f, &call.calls, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// Rebuild the call tree from the profile's open and close events, returning the calls made
+/// directly from the profiler's root frame.
+fn call_traces(profile: &GasProfile) -> anyhow::Result<Vec<CallTrace>> {
+    let Some(events) = profile.profiles.first().map(|p| &p.events) else {
+        return Ok(vec![]);
+    };
+
+    // Each open frame, with the gas consumed when it was opened.
+    let mut stack: Vec<(CallTrace, u64)> = vec![];
+    let mut roots = vec![];
+    for event in events {
+        let function = profile
+            .shared
+            .frames
+            .get(event.frame)
+            .ok_or_else(|| anyhow::anyhow!("Unknown frame {} in gas profile", event.frame))?
+            .file
+            .clone();
+        match event.ty.as_str() {
+            OPEN_FRAME => stack.push((
+                CallTrace {
+                    function,
+                    gas_used: 0,
+                    calls: vec![],
+                },
+                event.at,
+            )),
+            CLOSE_FRAME => {
+                let (mut call, opened_at) = stack
+                    .pop()
+                    .ok_or_else(|| anyhow::anyhow!("Unbalanced frame {function} in gas profile"))?;
+                call.gas_used = event.at.saturating_sub(opened_at);
+                match stack.last_mut() {
+                    Some((parent, _)) => parent.calls.push(call),
+                    None => roots.push(call),
+                }
+            }
+            ty => anyhow::bail!("Unknown event type {ty} in gas profile"),
+        }
+    }
+
+    // The profiler wraps the whole transaction in a single root frame.
+    Ok(match roots.pop() {
+        Some(root) if roots.is_empty() => root.calls,
+        Some(root) => roots.into_iter().chain(std::iter::once(root)).collect(),
+        None => vec![],
+    })
+}
+
+/// Assign top level calls to the commands that made them, in order: a `MoveCall` to the first
+/// remaining call of its function, and a `Publish` to the `init` calls that follow.
+fn attribute_calls(
+    commands: &[Command],
+    calls: Vec<CallTrace>,
+) -> (Vec<CommandTrace>, Vec<CallTrace>) {
+    let mut remaining = calls;
+    let mut unattributed = vec![];
+    let mut traces = vec![];
+    for (index, command) in commands.iter().enumerate() {
+        let calls = match command {
+            Command::MoveCall(call) => {
+                // The VM names functions by the package's original ID, which differs from the
+                // called package ID for upgraded packages, so only module and function match.
+                let suffix = format!("::{}::{}", call.module, call.function);
+                match remaining.iter().position(|c| c.function.ends_with(&suffix)) {
+                    Some(position) => {
+                        let mut calls: Vec<_> = remaining.drain(..=position).collect();
+                        let call = calls.pop().unwrap();
+                        unattributed.extend(calls);
+                        vec![call]
+                    }
+                    None => vec![],
+                }
+            }
+            Command::Publish(_, _) => {
+                let inits = remaining
+                    .iter()
+                    .take_while(|c| c.function.ends_with("::init"))
+                    .count();
+                remaining.drain(..inits).collect()
+            }
+            _ => vec![],
+        };
+        let gas_used = (!calls.is_empty()).then(|| calls.iter().map(|c| c.gas_used).sum());
+        traces.push(CommandTrace {
+            index,
+            command: command.to_string(),
+            gas_used,
+            calls,
+        });
+    }
+    unattributed.extend(remaining);
+    (traces, unattributed)
+}
+
+/// Find the gas profile the VM wrote to `dir` for `tx_digest` since `since`, picking the latest
+/// if there are several.
+pub fn find_gas_profile(
+    dir: &Path,
+    tx_digest: &TransactionDigest,
+    since: SystemTime,
+) -> anyhow::Result<Option<PathBuf>> {
+    let prefix = format!("gas_profile_{tx_digest}_");
+    let mut latest: Option<(SystemTime, PathBuf)> = None;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if modified >= since && latest.as_ref().map_or(true, |(t, _)| modified > *t) {
+            latest = Some((modified, entry.path()));
+        }
+    }
+    Ok(latest.map(|(_, path)| path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_core_types::identifier::Identifier;
+    use sui_types::base_types::ObjectID;
+    use sui_types::transaction::{Argument, ProgrammableMoveCall};
+
+    fn move_call(module: &str, function: &str) -> Command {
+        Command::MoveCall(Box::new(ProgrammableMoveCall {
+            package: ObjectID::ZERO,
+            module: Identifier::new(module).unwrap(),
+            function: Identifier::new(function).unwrap(),
+            type_arguments: vec![],
+            arguments: vec![],
+        }))
+    }
+
+    fn call(function: &str, gas_used: u64, calls: Vec<CallTrace>) -> CallTrace {
+        CallTrace {
+            function: function.to_string(),
+            gas_used,
+            calls,
+        }
+    }
+
+    #[test]
+    fn test_call_traces() {
+        let profile: GasProfile = serde_json::from_value(serde_json::json!({
+            "shared": { "frames": [
+                { "name": "root", "file": "root" },
+                { "name": "split", "file": "0x2::coin::split" },
+                { "name": "split", "file": "0x2::balance::split" },
+                { "name": "join", "file": "0x2::coin::join" },
+            ]},
+            "profiles": [{ "events": [
+                { "type": "O", "frame": 0, "at": 0 },
+                { "type": "O", "frame": 1, "at": 10 },
+                { "type": "O", "frame": 2, "at": 15 },
+                { "type": "C", "frame": 2, "at": 20 },
+                { "type": "C", "frame": 1, "at": 30 },
+                { "type": "O", "frame": 3, "at": 30 },
+                { "type": "C", "frame": 3, "at": 35 },
+                { "type": "C", "frame": 0, "at": 40 },
+            ]}],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            call_traces(&profile).unwrap(),
+            vec![
+                call(
+                    "0x2::coin::split",
+                    20,
+                    vec![call("0x2::balance::split", 5, vec![])]
+                ),
+                call("0x2::coin::join", 5, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attribute_calls() {
+        let commands = vec![
+            move_call("coin", "split"),
+            Command::TransferObjects(vec![Argument::Result(0)], Argument::Input(0)),
+            move_call("coin", "join"),
+        ];
+        let calls = vec![
+            call("0x2::coin::split", 20, vec![]),
+            call("0x2::coin::join", 5, vec![]),
+        ];
+
+        let (traces, unattributed) = attribute_calls(&commands, calls);
+        assert!(unattributed.is_empty());
+        assert_eq!(
+            traces.iter().map(|t| t.gas_used).collect::<Vec<_>>(),
+            vec![Some(20), None, Some(5)]
+        );
+    }
+}
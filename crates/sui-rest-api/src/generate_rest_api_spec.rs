@@ -0,0 +1,50 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use clap::Parser;
+use clap::ValueEnum;
+use pretty_assertions::assert_str_eq;
+use std::fs::File;
+use std::io::Write;
+use sui_rest_api::openapi::openapi_spec;
+
+#[derive(Debug, Parser, Clone, Copy, ValueEnum)]
+enum Action {
+    Print,
+    Test,
+    Record,
+}
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "Sui REST API spec generator",
+    about = "Generate the OpenAPI description of the Sui REST API"
+)]
+struct Options {
+    #[clap(value_enum, default_value = "Record", ignore_case = true)]
+    action: Action,
+}
+
+const FILE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/spec/openapi.json",);
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn main() {
+    let options = Options::parse();
+
+    let content = serde_json::to_string_pretty(&openapi_spec(VERSION)).unwrap();
+
+    match options.action {
+        Action::Print => {
+            println!("{content}");
+        }
+        Action::Record => {
+            let mut f = File::create(FILE_PATH).unwrap();
+            writeln!(f, "{content}").unwrap();
+        }
+        Action::Test => {
+            let reference = std::fs::read_to_string(FILE_PATH).unwrap();
+            assert_str_eq!(&reference, &(content + "\n"));
+        }
+    }
+}
@@ -8,6 +8,7 @@ mod client;
 pub mod headers;
 pub mod node_state_getter;
 mod objects;
+pub mod openapi;
 
 pub use checkpoints::{CheckpointData, CheckpointTransaction};
 pub use client::Client;
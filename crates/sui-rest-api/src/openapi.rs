@@ -0,0 +1,199 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! OpenAPI description of the routes served by [`rest_router`](crate::rest_router).
+//!
+//! Paths are derived from the same constants the router is built from, and JSON response
+//! schemas are generated from the Rust types the handlers return, so the spec cannot drift from
+//! the implementation without the `generate-rest-api-spec` check failing.
+
+use schemars::{
+    gen::{SchemaGenerator, SchemaSettings},
+    JsonSchema,
+};
+use serde_json::{json, Map, Value};
+use sui_types::messages_checkpoint::CertifiedCheckpointSummary;
+
+use crate::{checkpoints, objects, APPLICATION_BCS, APPLICATION_JSON, TEXT_PLAIN_UTF_8};
+
+const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Build the OpenAPI document for the REST API, reporting `version` as the API version.
+pub fn openapi_spec(version: &str) -> Value {
+    let mut gen = SchemaGenerator::new(SchemaSettings::openapi3());
+    let mut paths = Map::new();
+
+    paths.insert(
+        "/".to_owned(),
+        json!({
+            "get": {
+                "operationId": "healthCheck",
+                "summary": "Check that the service is up",
+                "responses": {
+                    "200": { "description": "The service is healthy" }
+                }
+            }
+        }),
+    );
+    paths.insert(
+        openapi_path(checkpoints::GET_LATEST_CHECKPOINT_PATH),
+        json!({
+            "get": {
+                "operationId": "getLatestCheckpoint",
+                "summary": "Fetch the summary of the latest executed checkpoint",
+                "responses": json_response::<CertifiedCheckpointSummary>(&mut gen)
+            }
+        }),
+    );
+    paths.insert(
+        openapi_path(checkpoints::GET_CHECKPOINT_PATH),
+        json!({
+            "get": {
+                "operationId": "getCheckpoint",
+                "summary": "Fetch the summary of a checkpoint",
+                "parameters": [u64_parameter("checkpoint", "Checkpoint sequence number")],
+                "responses": json_response::<CertifiedCheckpointSummary>(&mut gen)
+            }
+        }),
+    );
+    paths.insert(
+        openapi_path(checkpoints::GET_FULL_CHECKPOINT_PATH),
+        json!({
+            "get": {
+                "operationId": "getFullCheckpoint",
+                "summary": "Fetch a checkpoint with all of its transactions, effects, events and objects",
+                "description": format!("Requires an `Accept: {APPLICATION_BCS}` header."),
+                "parameters": [u64_parameter("checkpoint", "Checkpoint sequence number")],
+                "responses": bcs_response("BCS serialized `CheckpointData`")
+            }
+        }),
+    );
+    paths.insert(
+        openapi_path(objects::GET_OBJECT_PATH),
+        json!({
+            "get": {
+                "operationId": "getObject",
+                "summary": "Fetch the latest version of an object",
+                "parameters": [object_id_parameter()],
+                "responses": bcs_response("BCS serialized `Object`")
+            }
+        }),
+    );
+    paths.insert(
+        openapi_path(objects::GET_OBJECT_WITH_VERSION_PATH),
+        json!({
+            "get": {
+                "operationId": "getObjectWithVersion",
+                "summary": "Fetch a specific version of an object",
+                "parameters": [
+                    object_id_parameter(),
+                    u64_parameter("version", "Object version")
+                ],
+                "responses": bcs_response("BCS serialized `Object`")
+            }
+        }),
+    );
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Sui REST API",
+            "description": "REST API served by Sui full nodes",
+            "license": {
+                "name": "Apache-2.0",
+                "url": "https://raw.githubusercontent.com/MystenLabs/sui/main/LICENSE"
+            },
+            "version": version,
+        },
+        "paths": paths,
+        "components": {
+            "schemas": gen.take_definitions(),
+        },
+    })
+}
+
+/// Convert an axum route (`/objects/:object_id`) into an OpenAPI path template
+/// (`/objects/{object_id}`).
+fn openapi_path(route: &str) -> String {
+    route
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(param) => format!("{{{param}}}"),
+            None => segment.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn json_response<T: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    json!({
+        "200": {
+            "description": "Success",
+            "content": {
+                APPLICATION_JSON: { "schema": gen.subschema_for::<T>() }
+            }
+        },
+        "500": error_response(),
+    })
+}
+
+fn bcs_response(description: &str) -> Value {
+    json!({
+        "200": {
+            "description": description,
+            "content": {
+                APPLICATION_BCS: {
+                    "schema": { "type": "string", "format": "binary" }
+                }
+            }
+        },
+        "500": error_response(),
+    })
+}
+
+fn error_response() -> Value {
+    json!({
+        "description": "The request could not be served",
+        "content": {
+            TEXT_PLAIN_UTF_8: { "schema": { "type": "string" } }
+        }
+    })
+}
+
+fn u64_parameter(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "integer", "format": "uint64", "minimum": 0 }
+    })
+}
+
+fn object_id_parameter() -> Value {
+    json!({
+        "name": "object_id",
+        "in": "path",
+        "required": true,
+        "description": "Hex encoded object ID",
+        "schema": { "type": "string" }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_path() {
+        assert_eq!(openapi_path("/checkpoints"), "/checkpoints");
+        assert_eq!(
+            openapi_path(checkpoints::GET_FULL_CHECKPOINT_PATH),
+            "/checkpoints/{checkpoint}/full"
+        );
+        assert_eq!(
+            openapi_path(objects::GET_OBJECT_WITH_VERSION_PATH),
+            "/objects/{object_id}/version/{version}"
+        );
+    }
+}
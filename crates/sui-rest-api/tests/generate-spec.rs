@@ -0,0 +1,22 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+#[test]
+#[cfg_attr(msim, ignore)]
+fn test_rest_api_spec() {
+    // If this test breaks and you intended a REST API change, you need to run to get the fresh spec:
+    // # cargo -q run --example generate-rest-api-spec -- record
+    let status = std::process::Command::new("cargo")
+        .current_dir("..")
+        .args(["run", "--example", "generate-rest-api-spec", "--"])
+        .arg("test")
+        .status()
+        .expect("failed to execute process");
+    assert!(
+        status.success(),
+        "\n\
+If this test breaks and you intended a REST API change, you need to run to get the fresh spec:\n\
+cargo -q run --example generate-rest-api-spec -- record\n\
+        "
+    );
+}
@@ -0,0 +1,181 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::reader::StateSnapshotReaderV1;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use sui_core::authority::authority_store_tables::LiveObject;
+use sui_types::base_types::{ObjectID, ObjectRef};
+
+/// Groups objects that are not Move objects or packages, i.e. wrapped object tombstones.
+const WRAPPED: &str = "<wrapped>";
+const PACKAGE: &str = "<package>";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ObjectDiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Display for ObjectDiffKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjectDiffKind::Added => write!(f, "added"),
+            ObjectDiffKind::Removed => write!(f, "removed"),
+            ObjectDiffKind::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffCounts {
+    pub added: u64,
+    pub removed: u64,
+    pub changed: u64,
+}
+
+impl DiffCounts {
+    fn record(&mut self, kind: ObjectDiffKind) {
+        match kind {
+            ObjectDiffKind::Added => self.added += 1,
+            ObjectDiffKind::Removed => self.removed += 1,
+            ObjectDiffKind::Changed => self.changed += 1,
+        }
+    }
+}
+
+/// The type and defining package of an object, as grouped in a diff.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectClass {
+    pub type_: String,
+    pub package: String,
+}
+
+impl From<&LiveObject> for ObjectClass {
+    fn from(object: &LiveObject) -> Self {
+        match object {
+            LiveObject::Normal(object) => match object.struct_tag() {
+                Some(tag) => ObjectClass {
+                    type_: tag.to_canonical_string(/* with_prefix */ true),
+                    package: tag.address.to_canonical_string(/* with_prefix */ true),
+                },
+                None => ObjectClass {
+                    type_: PACKAGE.to_string(),
+                    package: object.id().to_string(),
+                },
+            },
+            LiveObject::Wrapped(_) => ObjectClass {
+                type_: WRAPPED.to_string(),
+                package: WRAPPED.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectDiff {
+    pub object_id: ObjectID,
+    pub kind: ObjectDiffKind,
+    /// The object in the older snapshot, if it was live there.
+    pub from: Option<ObjectRef>,
+    /// The object in the newer snapshot, if it is live there.
+    pub to: Option<ObjectRef>,
+    /// Unknown only if the object files of a snapshot do not match its reference files.
+    pub class: Option<ObjectClass>,
+}
+
+/// Differences between the live object sets of two snapshots.
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotDiff {
+    pub total: DiffCounts,
+    pub by_type: BTreeMap<String, DiffCounts>,
+    pub by_package: BTreeMap<String, DiffCounts>,
+    /// Per object differences, ordered by object ID.
+    pub objects: Vec<ObjectDiff>,
+}
+
+impl SnapshotDiff {
+    /// Compute the differences between the `from` and `to` snapshots. Object references are
+    /// compared first, and object files are only read to classify the objects that differ.
+    pub async fn compute(
+        from: &StateSnapshotReaderV1,
+        to: &StateSnapshotReaderV1,
+    ) -> Result<SnapshotDiff> {
+        let mut from_refs = HashMap::new();
+        from.for_each_object_ref(|object_ref| {
+            from_refs.insert(object_ref.0, object_ref);
+        })?;
+
+        let mut objects = BTreeMap::new();
+        to.for_each_object_ref(|to_ref| match from_refs.remove(&to_ref.0) {
+            None => {
+                objects.insert(
+                    to_ref.0,
+                    new_diff(ObjectDiffKind::Added, None, Some(to_ref)),
+                );
+            }
+            Some(from_ref) if from_ref != to_ref => {
+                objects.insert(
+                    to_ref.0,
+                    new_diff(ObjectDiffKind::Changed, Some(from_ref), Some(to_ref)),
+                );
+            }
+            Some(_) => {}
+        })?;
+        for (id, from_ref) in from_refs {
+            objects.insert(id, new_diff(ObjectDiffKind::Removed, Some(from_ref), None));
+        }
+
+        // Removed objects are classified by their old contents, the rest by their new contents.
+        from.for_each_live_object(|object| {
+            if let Some(diff) = objects.get_mut(&object.object_id()) {
+                if diff.kind == ObjectDiffKind::Removed {
+                    diff.class = Some(ObjectClass::from(&object));
+                }
+            }
+            Ok(())
+        })
+        .await?;
+        to.for_each_live_object(|object| {
+            if let Some(diff) = objects.get_mut(&object.object_id()) {
+                if diff.kind != ObjectDiffKind::Removed {
+                    diff.class = Some(ObjectClass::from(&object));
+                }
+            }
+            Ok(())
+        })
+        .await?;
+
+        let mut diff = SnapshotDiff::default();
+        for object in objects.into_values() {
+            diff.record(object);
+        }
+        Ok(diff)
+    }
+
+    fn record(&mut self, object: ObjectDiff) {
+        self.total.record(object.kind);
+        let (type_, package) = match &object.class {
+            Some(class) => (class.type_.clone(), class.package.clone()),
+            None => ("<unknown>".to_string(), "<unknown>".to_string()),
+        };
+        self.by_type.entry(type_).or_default().record(object.kind);
+        self.by_package
+            .entry(package)
+            .or_default()
+            .record(object.kind);
+        self.objects.push(object);
+    }
+}
+
+fn new_diff(kind: ObjectDiffKind, from: Option<ObjectRef>, to: Option<ObjectRef>) -> ObjectDiff {
+    ObjectDiff {
+        object_id: from.or(to).expect("Either side of a diff is present").0,
+        kind,
+        from,
+        to,
+        class: None,
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Garbage collection of the files of expired state snapshots.
+//!
+//! A snapshot file may only be deleted once no retained snapshot references it. References are
+//! counted from the MANIFESTs of the retained snapshots: every run rebuilds the counts from the
+//! MANIFESTs rather than trusting a stored index, so a crashed or concurrent run can't leave
+//! counts that let a referenced file be deleted. Snapshots without a success marker are still
+//! being uploaded and are never collected.
+//!
+//! Files of an expired snapshot are deleted before its MANIFEST, and its success marker last, so
+//! that an interrupted collection is finished by the next run. The uploader doesn't upload
+//! collected epochs again unless the node still has their db checkpoint.
+
+use crate::Manifest;
+use anyhow::{anyhow, Result};
+use futures::TryStreamExt;
+use indicatif::ProgressBar;
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use sui_core::db_checkpoint_handler::SUCCESS_MARKER;
+use sui_storage::object_store::batch_delete::DeleteObjectsResult;
+use sui_storage::object_store::util::{find_all_dirs_with_epoch_prefix, get};
+use sui_storage::object_store::{ObjectStoreDeleteExt, ObjectStoreHeadExt, ObjectStoreListExt};
+use tracing::info;
+
+const MANIFEST_FILENAME: &str = "MANIFEST";
+
+/// Number of MANIFESTs referencing each snapshot file.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct SnapshotReferences {
+    refcounts: BTreeMap<Path, usize>,
+}
+
+impl SnapshotReferences {
+    /// Count the references of the MANIFESTs of the snapshots in `epoch_dirs`.
+    pub async fn rebuild(
+        store: &Arc<DynObjectStore>,
+        epoch_dirs: &BTreeMap<u64, Path>,
+    ) -> Result<Self> {
+        let mut references = Self::default();
+        for (epoch, dir) in epoch_dirs {
+            let manifest = Manifest::from_bytes(&get(store, &dir.child(MANIFEST_FILENAME)).await?)?;
+            if manifest.epoch() != *epoch {
+                return Err(anyhow!(
+                    "MANIFEST of {dir} is for epoch {}",
+                    manifest.epoch()
+                ));
+            }
+            references.add(&manifest, dir);
+        }
+        Ok(references)
+    }
+
+    pub fn add(&mut self, manifest: &Manifest, dir: &Path) {
+        for file in manifest.file_metadata() {
+            *self.refcounts.entry(file.file_path(dir)).or_default() += 1;
+        }
+    }
+
+    pub fn refcount(&self, path: &Path) -> usize {
+        self.refcounts.get(path).copied().unwrap_or_default()
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (&Path, usize)> {
+        self.refcounts.iter().map(|(path, count)| (path, *count))
+    }
+}
+
+/// Outcome of a check of the references of the complete snapshots of a store.
+#[derive(Debug, Default)]
+pub struct ReferenceReport {
+    pub references: SnapshotReferences,
+    /// Files referenced by a MANIFEST but missing from the store
+    pub missing: Vec<Path>,
+    /// Snapshot files no MANIFEST references, e.g. left by interrupted uploads
+    pub unreferenced: Vec<Path>,
+}
+
+/// Rebuild the references of every complete snapshot of `store` from their MANIFESTs, and compare
+/// them with the files in the store.
+pub async fn check_references(store: &Arc<DynObjectStore>) -> Result<ReferenceReport> {
+    let complete = complete_snapshots(store).await?;
+    let references = SnapshotReferences::rebuild(store, &complete).await?;
+    let files = list_snapshot_files(store, complete.values()).await?;
+    let missing = references
+        .files()
+        .filter(|(path, _)| !files.contains(*path))
+        .map(|(path, _)| path.clone())
+        .collect();
+    let unreferenced = files
+        .into_iter()
+        .filter(|path| is_snapshot_file(path) && references.refcount(path) == 0)
+        .collect();
+    Ok(ReferenceReport {
+        references,
+        missing,
+        unreferenced,
+    })
+}
+
+#[derive(Debug, Default)]
+pub struct GcSummary {
+    pub retained_epochs: Vec<u64>,
+    pub expired_epochs: Vec<u64>,
+    /// Files deleted, or to be deleted on a dry run
+    pub garbage: Vec<Path>,
+    pub failed: Vec<(Path, anyhow::Error)>,
+}
+
+/// Delete the expired snapshots of `store`, keeping the `retain_latest` latest complete ones, and
+/// the snapshot files referenced by none of the retained snapshots. Nothing is deleted on a
+/// `dry_run`. See the [module documentation](self).
+pub async fn collect_garbage(
+    store: &Arc<DynObjectStore>,
+    deleter: &Arc<dyn ObjectStoreDeleteExt>,
+    retain_latest: NonZeroUsize,
+    dry_run: bool,
+    concurrency: NonZeroUsize,
+) -> Result<GcSummary> {
+    let mut expired = complete_snapshots(store).await?;
+    let expired_count = expired.len().saturating_sub(retain_latest.get());
+    let retained = match expired.keys().nth(expired_count).copied() {
+        Some(first_retained) => expired.split_off(&first_retained),
+        None => BTreeMap::new(),
+    };
+    let references = SnapshotReferences::rebuild(store, &retained).await?;
+
+    let mut files = vec![];
+    let mut manifests = vec![];
+    let mut markers = vec![];
+    for path in list_snapshot_files(store, retained.values()).await? {
+        if is_snapshot_file(&path) && references.refcount(&path) == 0 {
+            files.push(path);
+        }
+    }
+    for path in list_snapshot_files(store, expired.values()).await? {
+        if is_snapshot_file(&path) {
+            if references.refcount(&path) == 0 {
+                files.push(path);
+            }
+        } else if path.filename() == Some(SUCCESS_MARKER) {
+            markers.push(path);
+        } else {
+            manifests.push(path);
+        }
+    }
+
+    let mut summary = GcSummary {
+        retained_epochs: retained.keys().copied().collect(),
+        expired_epochs: expired.keys().copied().collect(),
+        ..Default::default()
+    };
+    if dry_run {
+        summary.garbage = [files, manifests, markers].concat();
+        return Ok(summary);
+    }
+    let progress_bar = ProgressBar::new((files.len() + manifests.len() + markers.len()) as u64);
+    for batch in [files, manifests, markers] {
+        let DeleteObjectsResult { deleted, failed } = deleter
+            .delete_objects(&batch, concurrency, Some(progress_bar.clone()))
+            .await;
+        summary.garbage.extend(deleted);
+        if !failed.is_empty() {
+            // Later batches must outlive the files of this one
+            summary.failed.extend(failed);
+            break;
+        }
+    }
+    progress_bar.finish_and_clear();
+    info!(
+        "Deleted {} snapshot files of expired epochs {:?}, {} failed",
+        summary.garbage.len(),
+        summary.expired_epochs,
+        summary.failed.len()
+    );
+    Ok(summary)
+}
+
+/// Epoch directories of the snapshots with a success marker.
+async fn complete_snapshots(store: &Arc<DynObjectStore>) -> Result<BTreeMap<u64, Path>> {
+    let mut complete = BTreeMap::new();
+    for (epoch, dir) in find_all_dirs_with_epoch_prefix(store, None).await? {
+        if store.exists(&dir.child(SUCCESS_MARKER)).await? {
+            complete.insert(epoch, dir);
+        }
+    }
+    Ok(complete)
+}
+
+async fn list_snapshot_files(
+    store: &Arc<DynObjectStore>,
+    dirs: impl Iterator<Item = &Path>,
+) -> Result<BTreeSet<Path>> {
+    let mut files = BTreeSet::new();
+    for dir in dirs {
+        let listed: Vec<_> = store
+            .list_objects(Some(dir))
+            .await?
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?;
+        files.extend(listed);
+    }
+    Ok(files)
+}
+
+/// Object and reference files, as opposed to MANIFESTs and markers.
+fn is_snapshot_file(path: &Path) -> bool {
+    matches!(path.extension(), Some("obj") | Some("ref"))
+}
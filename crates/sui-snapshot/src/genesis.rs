@@ -0,0 +1,138 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Genesis objects for private forks, taken from the live object set of a formal snapshot.
+//!
+//! Objects that genesis creates itself, i.e. the system packages and system objects, are left
+//! out of the snapshot objects. The objects can only be changed with the modifications listed in
+//! [`GenesisModification`], so that a fork is the snapshot state plus a reviewable set of changes
+//! rather than a hand-edited genesis blob.
+
+use crate::reader::StateSnapshotReaderV1;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use sui_core::authority::authority_store_tables::LiveObject;
+use sui_types::base_types::{ObjectID, SequenceNumber, SuiAddress};
+use sui_types::digests::TransactionDigest;
+use sui_types::object::{MoveObject, Object, Owner};
+use sui_types::{
+    is_system_package, SUI_AUTHENTICATOR_STATE_OBJECT_ID, SUI_CLOCK_OBJECT_ID,
+    SUI_RANDOMNESS_STATE_OBJECT_ID, SUI_SYSTEM_STATE_OBJECT_ID,
+};
+use tracing::info;
+
+/// Objects that genesis creates, and that are left out of the snapshot objects.
+const SYSTEM_OBJECT_IDS: [ObjectID; 4] = [
+    SUI_SYSTEM_STATE_OBJECT_ID,
+    SUI_CLOCK_OBJECT_ID,
+    SUI_AUTHENTICATOR_STATE_OBJECT_ID,
+    SUI_RANDOMNESS_STATE_OBJECT_ID,
+];
+
+/// The changes that can be made to the snapshot objects.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "kind")]
+pub enum GenesisModification {
+    /// Create a gas coin of `amount_mist` owned by `recipient`.
+    GrantBalance {
+        recipient: SuiAddress,
+        amount_mist: u64,
+    },
+    /// Give the objects owned by `from` to `to`, e.g. to control a mainnet account on the fork.
+    TransferOwnedObjects { from: SuiAddress, to: SuiAddress },
+    /// Leave the objects out of genesis.
+    ExcludeObjects { object_ids: Vec<ObjectID> },
+}
+
+/// The modifications to make to the snapshot objects, as read from a YAML file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GenesisModifications {
+    #[serde(default)]
+    pub modifications: Vec<GenesisModification>,
+}
+
+impl GenesisModifications {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+        Ok(serde_yaml::from_str(&contents)?)
+    }
+}
+
+/// Whether genesis creates `object` itself, or one of its dynamic fields.
+pub fn is_created_at_genesis(object: &Object) -> bool {
+    let id = object.id();
+    (object.is_package() && is_system_package(id))
+        || SYSTEM_OBJECT_IDS.contains(&id)
+        || matches!(object.owner, Owner::ObjectOwner(parent)
+            if SYSTEM_OBJECT_IDS.contains(&ObjectID::from(parent)))
+}
+
+/// Read the live objects of the snapshot, leaving out the ones created at genesis, and apply
+/// the `modifications` to them. The whole object set is held in memory.
+pub async fn genesis_objects_from_snapshot(
+    reader: &StateSnapshotReaderV1,
+    modifications: &GenesisModifications,
+) -> Result<Vec<Object>> {
+    let mut objects = BTreeMap::new();
+    reader
+        .for_each_live_object(|object| {
+            // Wrapped objects only exist within other objects.
+            if let LiveObject::Normal(object) = object {
+                if !is_created_at_genesis(&object) {
+                    objects.insert(object.id(), object);
+                }
+            }
+            Ok(())
+        })
+        .await?;
+    info!("Read {} objects from the snapshot", objects.len());
+    apply_genesis_modifications(objects, modifications)
+}
+
+/// Apply `modifications` to `objects`, in order.
+pub fn apply_genesis_modifications(
+    mut objects: BTreeMap<ObjectID, Object>,
+    modifications: &GenesisModifications,
+) -> Result<Vec<Object>> {
+    for (index, modification) in modifications.modifications.iter().enumerate() {
+        match modification {
+            GenesisModification::GrantBalance {
+                recipient,
+                amount_mist,
+            } => {
+                // Coin IDs are derived from the position of the grant, so that the same
+                // modifications give the same genesis.
+                let id = ObjectID::derive_id(TransactionDigest::genesis(), index as u64);
+                if objects.contains_key(&id) {
+                    return Err(anyhow!("Granted coin ID {id} is already in use"));
+                }
+                let coin = MoveObject::new_gas_coin(SequenceNumber::MIN, id, *amount_mist);
+                objects.insert(
+                    id,
+                    Object::new_move(
+                        coin,
+                        Owner::AddressOwner(*recipient),
+                        TransactionDigest::genesis(),
+                    ),
+                );
+            }
+            GenesisModification::TransferOwnedObjects { from, to } => {
+                for object in objects.values_mut() {
+                    if object.owner == Owner::AddressOwner(*from) {
+                        object.owner = Owner::AddressOwner(*to);
+                    }
+                }
+            }
+            GenesisModification::ExcludeObjects { object_ids } => {
+                let object_ids: BTreeSet<_> = object_ids.iter().collect();
+                objects.retain(|id, _| !object_ids.contains(id));
+            }
+        }
+    }
+    Ok(objects.into_values().collect())
+}
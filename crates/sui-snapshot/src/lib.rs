@@ -5,11 +5,16 @@
 #[cfg(test)]
 mod tests;
 
+pub mod diff;
+pub mod gc;
+pub mod genesis;
 pub mod reader;
 pub mod uploader;
 mod writer;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use byteorder::{BigEndian, ByteOrder};
+use fastcrypto::hash::{HashFunction, Sha3_256};
 use num_enum::IntoPrimitive;
 use num_enum::TryFromPrimitive;
 use object_store::path::Path;
@@ -192,6 +197,28 @@ impl Manifest {
             Self::V1(manifest) => manifest.epoch,
         }
     }
+    /// Parse a MANIFEST file, verifying its magic and checksum.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < MAGIC_BYTES + SHA3_BYTES {
+            return Err(anyhow!("Manifest of {} bytes is truncated", bytes.len()));
+        }
+        let magic = BigEndian::read_u32(&bytes[..MAGIC_BYTES]);
+        if magic != MANIFEST_FILE_MAGIC {
+            return Err(anyhow!("Unexpected magic byte: {}", magic));
+        }
+        let (content, sha3_digest) = bytes.split_at(bytes.len() - SHA3_BYTES);
+        let mut hasher = Sha3_256::default();
+        hasher.update(content);
+        let computed_digest = hasher.finalize().digest;
+        if computed_digest != sha3_digest {
+            return Err(anyhow!(
+                "Checksum: {:?} don't match: {:?}",
+                computed_digest,
+                sha3_digest
+            ));
+        }
+        Ok(bcs::from_bytes(&content[MAGIC_BYTES..])?)
+    }
 }
 
 pub fn create_file_metadata(
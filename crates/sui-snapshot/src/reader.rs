@@ -2,8 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    FileMetadata, FileType, Manifest, MAGIC_BYTES, MANIFEST_FILE_MAGIC, OBJECT_FILE_MAGIC,
-    OBJECT_ID_BYTES, OBJECT_REF_BYTES, REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES, SHA3_BYTES,
+    FileMetadata, FileType, Manifest, OBJECT_FILE_MAGIC, OBJECT_ID_BYTES, OBJECT_REF_BYTES,
+    REFERENCE_FILE_MAGIC, SEQUENCE_NUM_BYTES,
 };
 use anyhow::{anyhow, Context, Result};
 use byteorder::{BigEndian, ReadBytesExt};
@@ -17,8 +17,7 @@ use integer_encoding::VarIntReader;
 use object_store::path::Path;
 use std::collections::BTreeMap;
 use std::fs;
-use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::io::Read;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -27,8 +26,12 @@ use sui_core::authority::authority_store_tables::{AuthorityPerpetualTables, Live
 use sui_core::authority::AuthorityStore;
 use sui_storage::blob::{Blob, BlobEncoding};
 use sui_storage::object_store::http::HttpDownloaderBuilder;
-use sui_storage::object_store::util::{copy_file, copy_files, path_to_filesystem};
-use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreGetExt, ObjectStorePutExt};
+use sui_storage::object_store::util::{
+    copy_file, copy_files, find_missing_files, get, path_to_filesystem,
+};
+use sui_storage::object_store::{
+    ObjectStoreConfig, ObjectStoreGetExt, ObjectStoreHeadExt, ObjectStorePutExt,
+};
 use sui_types::accumulator::Accumulator;
 use sui_types::base_types::{ObjectDigest, ObjectID, ObjectRef, SequenceNumber};
 use tokio::sync::Mutex;
@@ -61,10 +64,17 @@ impl StateSnapshotReaderV1 {
         m: MultiProgress,
     ) -> Result<Self> {
         let epoch_dir = format!("epoch_{}", epoch);
-        let remote_object_store = if remote_store_config.no_sign_request {
-            remote_store_config.make_http()?
+        let (remote_object_store, remote_head_store): (
+            Arc<dyn ObjectStoreGetExt>,
+            Arc<dyn ObjectStoreHeadExt>,
+        ) = if remote_store_config.no_sign_request {
+            (
+                remote_store_config.make_http()?,
+                remote_store_config.make_http_head()?,
+            )
         } else {
-            remote_store_config.make().map(Arc::new)?
+            let store = remote_store_config.make()?;
+            (Arc::new(store.clone()), Arc::new(store))
         };
         let local_object_store: Arc<dyn ObjectStorePutExt> =
             local_store_config.make().map(Arc::new)?;
@@ -133,6 +143,27 @@ impl StateSnapshotReaderV1 {
                 files
             })
             .collect();
+        // Check that all files listed by the manifest were uploaded before downloading any of them,
+        // rather than failing once the ref files are downloaded
+        let object_file_paths: Vec<Path> = object_files
+            .values()
+            .flat_map(|entry| entry.values())
+            .map(|file_metadata| file_metadata.file_path(&epoch_dir_path))
+            .collect();
+        let missing_files = find_missing_files(
+            &[files.clone(), object_file_paths].concat(),
+            &remote_head_store,
+            download_concurrency,
+        )
+        .await?;
+        if !missing_files.is_empty() {
+            return Err(anyhow!(
+                "Snapshot of epoch {} is missing {} files listed in its manifest, e.g. {}",
+                epoch,
+                missing_files.len(),
+                missing_files[0]
+            ));
+        }
 
         let progress_bar = m.add(
             ProgressBar::new(files.len() as u64).with_style(
@@ -463,6 +494,60 @@ impl StateSnapshotReaderV1 {
         )
     }
 
+    /// Call `f` on every object reference in the snapshot, bucket by bucket.
+    pub fn for_each_object_ref<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(ObjectRef),
+    {
+        for (bucket, part_files) in self.ref_files.iter() {
+            for part in part_files.keys() {
+                self.ref_iter(*bucket, *part)?.for_each(&mut f);
+            }
+        }
+        Ok(())
+    }
+
+    /// Download every object file in the snapshot and call `f` on each live object in it. Objects
+    /// are visited in no particular order, and are not checked against the reference files.
+    pub async fn for_each_live_object<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(LiveObject) -> Result<()>,
+    {
+        let epoch_dir = self.epoch_dir();
+        let files: Vec<FileMetadata> = self
+            .object_files
+            .values()
+            .flat_map(|parts| parts.values().cloned())
+            .collect();
+        let progress_bar = self.m.add(
+            ProgressBar::new(files.len() as u64).with_style(
+                ProgressStyle::with_template(
+                    "[{elapsed_precise}] {wide_bar} {pos} out of {len} .obj files read",
+                )
+                .unwrap(),
+            ),
+        );
+
+        let mut downloads = futures::stream::iter(files)
+            .map(|file_metadata| {
+                let file_path = file_metadata.file_path(&epoch_dir);
+                let remote_object_store = self.remote_object_store.clone();
+                async move {
+                    let bytes = get(&remote_object_store, &file_path).await?;
+                    Ok::<(Bytes, FileMetadata), anyhow::Error>((bytes, file_metadata))
+                }
+            })
+            .buffer_unordered(self.concurrency);
+        while let Some((bytes, file_metadata)) = downloads.try_next().await? {
+            for object in LiveObjectIter::new(&file_metadata, bytes)? {
+                f(object)?;
+            }
+            progress_bar.inc(1);
+        }
+        progress_bar.finish_with_message("Objects read");
+        Ok(())
+    }
+
     fn buckets(&self) -> Result<Vec<u32>> {
         Ok(self.ref_files.keys().copied().collect())
     }
@@ -472,34 +557,7 @@ impl StateSnapshotReaderV1 {
     }
 
     fn read_manifest(path: PathBuf) -> anyhow::Result<Manifest> {
-        let manifest_file = File::open(path)?;
-        let manifest_file_size = manifest_file.metadata()?.len() as usize;
-        let mut manifest_reader = BufReader::new(manifest_file);
-        manifest_reader.rewind()?;
-        let magic = manifest_reader.read_u32::<BigEndian>()?;
-        if magic != MANIFEST_FILE_MAGIC {
-            return Err(anyhow!("Unexpected magic byte: {}", magic));
-        }
-        manifest_reader.seek(SeekFrom::End(-(SHA3_BYTES as i64)))?;
-        let mut sha3_digest = [0u8; SHA3_BYTES];
-        manifest_reader.read_exact(&mut sha3_digest)?;
-        manifest_reader.rewind()?;
-        let mut content_buf = vec![0u8; manifest_file_size - SHA3_BYTES];
-        manifest_reader.read_exact(&mut content_buf)?;
-        let mut hasher = Sha3_256::default();
-        hasher.update(&content_buf);
-        let computed_digest = hasher.finalize().digest;
-        if computed_digest != sha3_digest {
-            return Err(anyhow!(
-                "Checksum: {:?} don't match: {:?}",
-                computed_digest,
-                sha3_digest
-            ));
-        }
-        manifest_reader.rewind()?;
-        manifest_reader.seek(SeekFrom::Start(MAGIC_BYTES as u64))?;
-        let manifest = bcs::from_bytes(&content_buf[MAGIC_BYTES..])?;
-        Ok(manifest)
+        Manifest::from_bytes(&fs::read(path)?)
     }
 }
 
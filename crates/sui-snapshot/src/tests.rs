@@ -1,19 +1,30 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::diff::{DiffCounts, SnapshotDiff};
+use crate::gc::{check_references, collect_garbage};
+use crate::genesis::{
+    apply_genesis_modifications, is_created_at_genesis, GenesisModification, GenesisModifications,
+};
 use crate::reader::StateSnapshotReaderV1;
 use crate::writer::StateSnapshotWriterV1;
 use crate::FileCompression;
+use bytes::Bytes;
 use futures::future::AbortHandle;
 use indicatif::MultiProgress;
-use std::collections::HashSet;
+use object_store::path::Path;
+use std::collections::{BTreeMap, HashSet};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 use sui_core::authority::authority_store_tables::AuthorityPerpetualTables;
+use sui_core::db_checkpoint_handler::SUCCESS_MARKER;
 use sui_protocol_config::ProtocolConfig;
+use sui_storage::object_store::util::put;
 use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreType};
-use sui_types::base_types::ObjectID;
-use sui_types::object::Object;
+use sui_types::base_types::{ObjectID, SuiAddress};
+use sui_types::gas_coin::GasCoin;
+use sui_types::object::{Object, Owner};
+use sui_types::SUI_CLOCK_OBJECT_ID;
 use tempfile::tempdir;
 
 fn temp_dir() -> std::path::PathBuf {
@@ -160,3 +171,222 @@ async fn test_snapshot_empty_db() -> Result<(), anyhow::Error> {
     )?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_snapshot_gc() -> Result<(), anyhow::Error> {
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(temp_dir().join("local_dir")),
+        ..Default::default()
+    };
+    let remote = temp_dir().join("remote_dir");
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote.clone()),
+        ..Default::default()
+    };
+    let remote_store = remote_store_config.make()?;
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&temp_dir(), None));
+    insert_keys(&perpetual_db, 1000)?;
+    for epoch in 0..4 {
+        let snapshot_writer = StateSnapshotWriterV1::new(
+            &local_store_config,
+            &remote_store_config,
+            FileCompression::Zstd,
+            NonZeroUsize::new(1).unwrap(),
+        )
+        .await?;
+        snapshot_writer
+            .write_internal(epoch, true, perpetual_db.clone())
+            .await?;
+        // Epoch 3 is still being uploaded
+        if epoch < 3 {
+            put(
+                &remote_store,
+                &Path::from(format!("epoch_{epoch}/{SUCCESS_MARKER}")),
+                Bytes::from_static(b"success"),
+            )
+            .await?;
+        }
+    }
+    // Left by an interrupted upload of epoch 2
+    put(
+        &remote_store,
+        &Path::from("epoch_2/99999_1.obj"),
+        Bytes::from_static(b"orphan"),
+    )
+    .await?;
+
+    let report = check_references(&remote_store).await?;
+    assert!(report.missing.is_empty());
+    assert_eq!(report.unreferenced, vec![Path::from("epoch_2/99999_1.obj")]);
+    assert!(report.references.files().all(|(_, count)| count == 1));
+
+    let deleter = remote_store_config.make_batch_delete()?;
+    let gc = |dry_run| {
+        collect_garbage(
+            &remote_store,
+            &deleter,
+            NonZeroUsize::new(2).unwrap(),
+            dry_run,
+            NonZeroUsize::new(4).unwrap(),
+        )
+    };
+    let summary = gc(true).await?;
+    assert_eq!(summary.retained_epochs, vec![1, 2]);
+    assert_eq!(summary.expired_epochs, vec![0]);
+    assert!(remote.join("epoch_0").join("MANIFEST").exists());
+    assert!(remote.join("epoch_2").join("99999_1.obj").exists());
+
+    let summary = gc(false).await?;
+    assert!(summary.failed.is_empty());
+    assert!(summary.garbage.iter().all(
+        |path| path.as_ref().starts_with("epoch_0/") || path.as_ref() == "epoch_2/99999_1.obj"
+    ));
+    assert!(!remote.join("epoch_0").join("MANIFEST").exists());
+    assert!(!remote.join("epoch_0").join(SUCCESS_MARKER).exists());
+    assert!(!remote.join("epoch_2").join("99999_1.obj").exists());
+    assert!(remote.join("epoch_1").join("MANIFEST").exists());
+    assert!(remote.join("epoch_3").join("MANIFEST").exists());
+
+    let report = check_references(&remote_store).await?;
+    assert!(report.missing.is_empty());
+    assert!(report.unreferenced.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_diff() -> Result<(), anyhow::Error> {
+    let local = temp_dir().join("local_dir");
+    let remote = temp_dir().join("remote_dir");
+    let restored_local = temp_dir().join("local_dir_restore");
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(local),
+        ..Default::default()
+    };
+    let remote_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(remote),
+        ..Default::default()
+    };
+    let snapshot_writer = StateSnapshotWriterV1::new(
+        &local_store_config,
+        &remote_store_config,
+        FileCompression::Zstd,
+        NonZeroUsize::new(1).unwrap(),
+    )
+    .await?;
+
+    // Epoch 0 has objects [0, 1000), epoch 1 has objects [100, 1100) with [100, 110) modified.
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&temp_dir(), None));
+    insert_keys(&perpetual_db, 1000)?;
+    snapshot_writer
+        .write_internal(0, true, perpetual_db.clone())
+        .await?;
+    let perpetual_db = Arc::new(AuthorityPerpetualTables::open(&temp_dir(), None));
+    let ids = ObjectID::in_range(ObjectID::ZERO, 1100)?;
+    for (i, id) in ids.into_iter().enumerate().skip(100) {
+        let object = if i < 110 {
+            Object::with_id_owner_for_testing(id, SuiAddress::ZERO)
+        } else {
+            Object::immutable_with_id_for_testing(id)
+        };
+        perpetual_db.insert_object_test_only(object)?;
+    }
+    let snapshot_writer = StateSnapshotWriterV1::new(
+        &local_store_config,
+        &remote_store_config,
+        FileCompression::Zstd,
+        NonZeroUsize::new(1).unwrap(),
+    )
+    .await?;
+    snapshot_writer
+        .write_internal(1, true, perpetual_db.clone())
+        .await?;
+
+    let local_store_restore_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(restored_local),
+        ..Default::default()
+    };
+    let mut readers = vec![];
+    for epoch in [0, 1] {
+        readers.push(
+            StateSnapshotReaderV1::new(
+                epoch,
+                &remote_store_config,
+                &local_store_restore_config,
+                usize::MAX,
+                NonZeroUsize::new(1).unwrap(),
+                MultiProgress::new(),
+            )
+            .await?,
+        );
+    }
+
+    let diff = SnapshotDiff::compute(&readers[0], &readers[1]).await?;
+    let expected = DiffCounts {
+        added: 100,
+        removed: 100,
+        changed: 10,
+    };
+    assert_eq!(diff.total, expected);
+    assert_eq!(diff.by_type.len(), 1);
+    assert_eq!(diff.by_type.values().next(), Some(&expected));
+    assert_eq!(diff.objects.len(), 210);
+    assert!(diff.objects.iter().all(|object| object.class.is_some()));
+    assert!(diff
+        .objects
+        .windows(2)
+        .all(|pair| pair[0].object_id < pair[1].object_id));
+    Ok(())
+}
+
+#[test]
+fn test_genesis_modifications() -> Result<(), anyhow::Error> {
+    let mainnet_account = SuiAddress::random_for_testing_only();
+    let fork_account = SuiAddress::random_for_testing_only();
+    let owned = Object::with_owner_for_testing(mainnet_account);
+    let excluded = Object::immutable_with_id_for_testing(ObjectID::random());
+    let objects = BTreeMap::from([
+        (owned.id(), owned.clone()),
+        (excluded.id(), excluded.clone()),
+    ]);
+    assert!(!is_created_at_genesis(&owned));
+    assert!(is_created_at_genesis(
+        &Object::immutable_with_id_for_testing(SUI_CLOCK_OBJECT_ID)
+    ));
+
+    let modifications: GenesisModifications = serde_yaml::from_str(&format!(
+        r#"
+modifications:
+  - kind: transfer-owned-objects
+    from: "{mainnet_account}"
+    to: "{fork_account}"
+  - kind: exclude-objects
+    object-ids: ["{}"]
+  - kind: grant-balance
+    recipient: "{fork_account}"
+    amount-mist: 1000
+"#,
+        excluded.id()
+    ))?;
+    assert_eq!(
+        modifications.modifications[0],
+        GenesisModification::TransferOwnedObjects {
+            from: mainnet_account,
+            to: fork_account
+        }
+    );
+
+    let objects = apply_genesis_modifications(objects, &modifications)?;
+    assert_eq!(objects.len(), 2);
+    assert!(objects.iter().all(|o| o.id() != excluded.id()));
+    assert!(objects
+        .iter()
+        .all(|o| o.owner == Owner::AddressOwner(fork_account)));
+    let grant = objects.iter().find(|o| o.id() != owned.id()).unwrap();
+    assert_eq!(GasCoin::try_from(grant)?.value(), 1000);
+    Ok(())
+}
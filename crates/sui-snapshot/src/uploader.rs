@@ -15,6 +15,8 @@ use sui_core::authority::authority_store_tables::AuthorityPerpetualTables;
 use sui_core::db_checkpoint_handler::{
     STATE_SNAPSHOT_COMPLETED_MARKER, SUCCESS_MARKER, UPLOAD_COMPLETED_MARKER,
 };
+use sui_storage::object_store::mirror::MirroredObjectStoreConfig;
+use sui_storage::object_store::multipart::MultipartUploader;
 use sui_storage::object_store::util::{
     find_all_dirs_with_epoch_prefix, find_missing_epochs_dirs, path_to_filesystem, put,
 };
@@ -51,8 +53,9 @@ pub struct StateSnapshotUploader {
     staging_path: PathBuf,
     /// Store on local disk where state snapshots are staged for upload
     staging_store: Arc<DynObjectStore>,
-    /// Remote store i.e. S3, GCS, etc where state snapshots are uploaded to
+    /// Remote store i.e. S3, GCS, etc where state snapshots are uploaded to, and its mirrors
     snapshot_store: Arc<DynObjectStore>,
+    snapshot_multipart_uploader: Option<Arc<MultipartUploader>>,
     /// Time interval to check for presence of new db checkpoint
     interval: Duration,
     metrics: Arc<StateSnapshotUploaderMetrics>,
@@ -62,7 +65,7 @@ impl StateSnapshotUploader {
     pub fn new(
         db_checkpoint_path: &std::path::Path,
         staging_path: &std::path::Path,
-        snapshot_store_config: ObjectStoreConfig,
+        snapshot_store_config: MirroredObjectStoreConfig,
         interval_s: u64,
         registry: &Registry,
     ) -> Result<Self> {
@@ -76,12 +79,18 @@ impl StateSnapshotUploader {
             directory: Some(staging_path.to_path_buf()),
             ..Default::default()
         };
+        // Parts are uploaded to a single store, files mirrored to several ones are put whole.
+        let snapshot_multipart_uploader = match snapshot_store_config.stores.as_slice() {
+            [store_config] => store_config.make_multipart()?,
+            _ => None,
+        };
         Ok(StateSnapshotUploader {
             db_checkpoint_path: db_checkpoint_path.to_path_buf(),
             db_checkpoint_store: db_checkpoint_store_config.make()?,
             staging_path: staging_path.to_path_buf(),
             staging_store: staging_store_config.make()?,
-            snapshot_store: snapshot_store_config.make()?,
+            snapshot_store: Arc::new(snapshot_store_config.make()?),
+            snapshot_multipart_uploader,
             interval: Duration::from_secs(interval_s),
             metrics: StateSnapshotUploaderMetrics::new(registry),
         })
@@ -134,6 +143,7 @@ impl StateSnapshotUploader {
                     &self.staging_path,
                     &self.staging_store,
                     &self.snapshot_store,
+                    self.snapshot_multipart_uploader.clone(),
                     FileCompression::Zstd,
                     NonZeroUsize::new(20).unwrap(),
                 )
@@ -25,7 +25,11 @@ use sui_core::authority::authority_store_tables::{AuthorityPerpetualTables, Live
 use sui_core::authority::CHAIN_IDENTIFIER;
 use sui_protocol_config::{ProtocolConfig, ProtocolVersion};
 use sui_storage::blob::{Blob, BlobEncoding, BLOB_ENCODING_BYTES};
-use sui_storage::object_store::util::{copy_file, delete_recursively, path_to_filesystem};
+use sui_storage::object_store::multipart::MultipartUploader;
+use sui_storage::object_store::util::{
+    copy_file_with_threshold, delete_recursively, get_stream, path_to_filesystem,
+    DEFAULT_STREAMING_THRESHOLD,
+};
 use sui_storage::object_store::ObjectStoreConfig;
 use sui_types::base_types::{ObjectID, ObjectRef};
 use sui_types::sui_system_state::get_sui_system_state;
@@ -209,6 +213,10 @@ pub struct StateSnapshotWriterV1 {
     file_compression: FileCompression,
     remote_object_store: Arc<DynObjectStore>,
     local_staging_store: Arc<DynObjectStore>,
+    /// Uploads files larger than a part in parts, if the remote store supports it.
+    multipart_uploader: Option<Arc<MultipartUploader>>,
+    /// Size above which files are streamed to the remote store, when not uploaded in parts
+    streaming_threshold: usize,
     concurrency: usize,
 }
 
@@ -217,6 +225,7 @@ impl StateSnapshotWriterV1 {
         local_staging_path: &std::path::Path,
         local_staging_store: &Arc<DynObjectStore>,
         remote_object_store: &Arc<DynObjectStore>,
+        multipart_uploader: Option<Arc<MultipartUploader>>,
         file_compression: FileCompression,
         concurrency: NonZeroUsize,
     ) -> Result<Self> {
@@ -225,6 +234,8 @@ impl StateSnapshotWriterV1 {
             local_staging_dir: local_staging_path.to_path_buf(),
             remote_object_store: remote_object_store.clone(),
             local_staging_store: local_staging_store.clone(),
+            multipart_uploader,
+            streaming_threshold: DEFAULT_STREAMING_THRESHOLD,
             concurrency: concurrency.get(),
         })
     }
@@ -236,6 +247,7 @@ impl StateSnapshotWriterV1 {
         concurrency: NonZeroUsize,
     ) -> Result<Self> {
         let remote_object_store = remote_store_config.make()?;
+        let multipart_uploader = remote_store_config.make_multipart()?;
         let local_staging_store = local_store_config.make()?;
         let local_staging_dir = local_store_config
             .directory
@@ -247,6 +259,8 @@ impl StateSnapshotWriterV1 {
             file_compression,
             remote_object_store,
             local_staging_store,
+            multipart_uploader,
+            streaming_threshold: remote_store_config.streaming_threshold(),
             concurrency: concurrency.get(),
         })
     }
@@ -283,6 +297,8 @@ impl StateSnapshotWriterV1 {
         let local_staging_dir = self.local_staging_dir.clone();
         let local_object_store = self.local_staging_store.clone();
         let remote_object_store = self.remote_object_store.clone();
+        let multipart_uploader = self.multipart_uploader.clone();
+        let streaming_threshold = self.streaming_threshold;
 
         let (sender, receiver) = mpsc::channel::<FileMetadata>(1000);
         let upload_handle = self.start_upload(epoch, receiver)?;
@@ -310,6 +326,8 @@ impl StateSnapshotWriterV1 {
             manifest_file_path,
             local_object_store,
             remote_object_store,
+            multipart_uploader,
+            streaming_threshold,
         )
         .await?;
         Ok(())
@@ -322,6 +340,8 @@ impl StateSnapshotWriterV1 {
     ) -> Result<JoinHandle<Result<Vec<()>, anyhow::Error>>> {
         let remote_object_store = self.remote_object_store.clone();
         let local_staging_store = self.local_staging_store.clone();
+        let multipart_uploader = self.multipart_uploader.clone();
+        let streaming_threshold = self.streaming_threshold;
         let local_dir_path = self.local_staging_dir.clone();
         let epoch_dir = self.epoch_dir(epoch);
         let upload_concurrency = self.concurrency;
@@ -331,6 +351,7 @@ impl StateSnapshotWriterV1 {
                     let file_path = file_metadata.file_path(&epoch_dir);
                     let remote_object_store = remote_object_store.clone();
                     let local_object_store = local_staging_store.clone();
+                    let multipart_uploader = multipart_uploader.clone();
                     let local_dir_path = local_dir_path.clone();
                     async move {
                         Self::sync_file_to_remote(
@@ -338,6 +359,8 @@ impl StateSnapshotWriterV1 {
                             file_path.clone(),
                             local_object_store.clone(),
                             remote_object_store.clone(),
+                            multipart_uploader,
+                            streaming_threshold,
                         )
                         .await?;
                         Ok(())
@@ -468,10 +491,22 @@ impl StateSnapshotWriterV1 {
         path: Path,
         from: Arc<DynObjectStore>,
         to: Arc<DynObjectStore>,
+        multipart_uploader: Option<Arc<MultipartUploader>>,
+        streaming_threshold: usize,
     ) -> Result<()> {
         debug!("Syncing snapshot file to remote: {:?}", path);
-        copy_file(&path, &path, &from, &to).await?;
-        fs::remove_file(path_to_filesystem(local_path, &path)?)?;
+        let local_file_path = path_to_filesystem(local_path, &path)?;
+        match multipart_uploader {
+            Some(uploader)
+                if fs::metadata(&local_file_path)?.len() > uploader.part_size() as u64 =>
+            {
+                uploader
+                    .upload(&path, get_stream(&from, &path).await?)
+                    .await?;
+            }
+            _ => copy_file_with_threshold(&path, &path, &from, &to, streaming_threshold).await?,
+        }
+        fs::remove_file(local_file_path)?;
         Ok(())
     }
 }
@@ -0,0 +1,148 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use criterion::*;
+use futures::TryStreamExt;
+use object_store::path::Path;
+use rand::RngCore;
+use sui_storage::object_store::checksum::ChecksummedStore;
+use sui_storage::object_store::compression::CompressingObjectStore;
+use sui_storage::object_store::prefix::PrefixedStore;
+use sui_storage::object_store::{
+    ObjectStoreConfig, ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStoreListExt,
+    ObjectStorePutExt, ObjectStoreType,
+};
+use tempfile::TempDir;
+use tokio::runtime::Runtime;
+use tokio::time::Instant;
+
+/// Archive checkpoint files are large, indexer objects small.
+const WORKLOADS: [(&str, usize); 2] = [("archival", 4 * 1024 * 1024), ("indexer", 4 * 1024)];
+const LISTED_OBJECTS: usize = 1000;
+
+fn random_bytes(size: usize) -> Bytes {
+    let mut bytes = vec![0; size];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Bytes::from(bytes)
+}
+
+fn bench_get_put_delete<S>(c: &mut Criterion, runtime: &Runtime, name: &str, store: &S)
+where
+    S: ObjectStoreGetExt + ObjectStorePutExt + ObjectStoreDeleteExt,
+{
+    for (workload, size) in WORKLOADS {
+        let mut group = c.benchmark_group(format!("{name}/{workload}"));
+        group.throughput(Throughput::Bytes(size as u64));
+        let bytes = &random_bytes(size);
+        let path = &Path::from(format!("{workload}/object"));
+
+        group.bench_function("put", |b| {
+            b.to_async(runtime)
+                .iter(|| async move { store.put_bytes(path, bytes.clone()).await.unwrap() })
+        });
+        group.bench_function("get", |b| {
+            b.to_async(runtime)
+                .iter(|| async move { store.get_bytes(path).await.unwrap() })
+        });
+        // Only the deletes are timed, each of an object written beforehand
+        group.bench_function("delete", |b| {
+            b.to_async(runtime).iter_custom(|iters| async move {
+                let mut elapsed = Duration::ZERO;
+                for i in 0..iters {
+                    let path = Path::from(format!("{workload}/delete/{i}"));
+                    store.put_bytes(&path, bytes.clone()).await.unwrap();
+                    let start = Instant::now();
+                    store.delete_object(&path).await.unwrap();
+                    elapsed += start.elapsed();
+                }
+                elapsed
+            })
+        });
+        group.finish();
+    }
+}
+
+fn bench_list<S>(c: &mut Criterion, runtime: &Runtime, name: &str, store: &S)
+where
+    S: ObjectStoreListExt + ObjectStorePutExt,
+{
+    let prefix = &Path::from("listed");
+    runtime.block_on(async {
+        for i in 0..LISTED_OBJECTS {
+            store
+                .put_bytes(&prefix.child(format!("{i}.chk")), Bytes::from("checkpoint"))
+                .await
+                .unwrap();
+        }
+    });
+    let mut group = c.benchmark_group(name.to_string());
+    group.throughput(Throughput::Elements(LISTED_OBJECTS as u64));
+    group.bench_function("list", |b| {
+        b.to_async(runtime).iter(|| async move {
+            let listed: Vec<_> = store
+                .list_objects(Some(prefix))
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert_eq!(listed.len(), LISTED_OBJECTS);
+        })
+    });
+    group.finish();
+}
+
+fn object_store_benchmark(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let local = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(dir.path().to_path_buf()),
+        ..Default::default()
+    }
+    .make()
+    .unwrap();
+    let memory = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::Memory),
+        bucket: Some("object_store_bench".to_string()),
+        ..Default::default()
+    }
+    .make()
+    .unwrap();
+
+    bench_get_put_delete(c, &runtime, "memory", &memory);
+    bench_get_put_delete(c, &runtime, "local", &local);
+    bench_get_put_delete(
+        c,
+        &runtime,
+        "local_prefixed",
+        &PrefixedStore::new(local.clone(), "prefixed"),
+    );
+    bench_get_put_delete(
+        c,
+        &runtime,
+        "local_checksummed",
+        &ChecksummedStore::new(local.clone()),
+    );
+    bench_get_put_delete(
+        c,
+        &runtime,
+        "local_compressed",
+        &CompressingObjectStore::new(local.clone(), 0),
+    );
+
+    bench_list(c, &runtime, "memory", &memory);
+    bench_list(c, &runtime, "local", &local);
+    bench_list(
+        c,
+        &runtime,
+        "local_prefixed",
+        &PrefixedStore::new(local.clone(), "prefixed"),
+    );
+}
+
+criterion_group!(benches, object_store_benchmark);
+criterion_main!(benches);
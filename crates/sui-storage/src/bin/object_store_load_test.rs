@@ -0,0 +1,242 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use clap::*;
+use futures::{StreamExt, TryStreamExt};
+use hdrhistogram::Histogram;
+use object_store::path::Path;
+use rand::RngCore;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_storage::object_store::checksum::ChecksummedStore;
+use sui_storage::object_store::compression::CompressingObjectStore;
+use sui_storage::object_store::{
+    ObjectStoreConfig, ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStoreListExt,
+    ObjectStorePutExt,
+};
+use tokio::time::Instant;
+
+// Puts, gets, lists and deletes objects under a prefix of the configured store with the access
+// pattern of a workload, and reports the throughput and latency percentiles of each phase, to
+// evaluate a backend or a store wrapper before rolling it out.
+#[derive(Parser)]
+#[command(rename_all = "kebab-case")]
+struct Options {
+    #[arg(long, value_enum, default_value_t = Workload::Archival)]
+    workload: Workload,
+
+    /// Number of objects, defaults to the workload's
+    #[arg(long)]
+    objects: Option<usize>,
+
+    /// Size of the objects in bytes, defaults to the workload's
+    #[arg(long)]
+    object_size: Option<usize>,
+
+    #[arg(long, default_value = "16")]
+    concurrency: NonZeroUsize,
+
+    /// Prefix of the objects written, which is expected to be empty
+    #[arg(long, default_value = "load-test")]
+    prefix: String,
+
+    /// Number of listings of the prefix
+    #[arg(long, default_value_t = 10)]
+    list_iterations: usize,
+
+    /// Store wrapper to put, get and delete objects through
+    #[arg(long, value_enum, default_value_t = Wrapper::None)]
+    wrapper: Wrapper,
+
+    /// Leave the objects in the store
+    #[arg(long)]
+    keep: bool,
+
+    #[command(flatten)]
+    object_store_config: ObjectStoreConfig,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Workload {
+    /// Few large checkpoint files, written once and read back in full
+    Archival,
+    /// Many small objects, e.g. of checkpoint contents and transactions
+    Indexer,
+}
+
+impl Workload {
+    fn objects(&self) -> usize {
+        match self {
+            Workload::Archival => 100,
+            Workload::Indexer => 10_000,
+        }
+    }
+
+    fn object_size(&self) -> usize {
+        match self {
+            Workload::Archival => 8 * 1024 * 1024,
+            Workload::Indexer => 4 * 1024,
+        }
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Wrapper {
+    None,
+    Checksum,
+    Compression,
+}
+
+trait LoadTestStore: ObjectStoreGetExt + ObjectStorePutExt + ObjectStoreDeleteExt {}
+
+impl<S: ObjectStoreGetExt + ObjectStorePutExt + ObjectStoreDeleteExt> LoadTestStore for S {}
+
+/// Latencies of the operations of a phase, in microseconds.
+struct PhaseReport {
+    latencies: Histogram<u64>,
+    bytes: usize,
+    elapsed: Duration,
+}
+
+impl PhaseReport {
+    fn print(&self, name: &str) {
+        let ops = self.latencies.len();
+        let secs = self.elapsed.as_secs_f64();
+        let percentile = |q: f64| Duration::from_micros(self.latencies.value_at_quantile(q));
+        println!(
+            "{name:>6}: {ops} ops in {:?}, {:.1} ops/s, {:.2} MiB/s, latency p50 {:?} p90 {:?} \
+             p99 {:?} max {:?}",
+            self.elapsed,
+            ops as f64 / secs,
+            self.bytes as f64 / secs / (1024.0 * 1024.0),
+            percentile(0.5),
+            percentile(0.9),
+            percentile(0.99),
+            Duration::from_micros(self.latencies.max()),
+        );
+    }
+}
+
+/// Run `op` on every path, `concurrency` at a time, timing each of them. `op` returns the number
+/// of bytes it transferred.
+async fn run_phase<F, Fut>(paths: &[Path], concurrency: NonZeroUsize, op: F) -> Result<PhaseReport>
+where
+    F: Fn(Path) -> Fut,
+    Fut: Future<Output = Result<usize>>,
+{
+    let start = Instant::now();
+    let results: Vec<(Duration, usize)> = futures::stream::iter(paths.iter().cloned())
+        .map(|path| {
+            let op = op(path);
+            async move {
+                let op_start = Instant::now();
+                let bytes = op.await?;
+                Ok::<_, anyhow::Error>((op_start.elapsed(), bytes))
+            }
+        })
+        .buffer_unordered(concurrency.get())
+        .try_collect()
+        .await?;
+    let elapsed = start.elapsed();
+    let mut latencies = Histogram::new(3)?;
+    let mut bytes = 0;
+    for (latency, op_bytes) in results {
+        latencies.record(latency.as_micros() as u64)?;
+        bytes += op_bytes;
+    }
+    Ok(PhaseReport {
+        latencies,
+        bytes,
+        elapsed,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _guard = telemetry_subscribers::TelemetryConfig::new()
+        .with_env()
+        .init();
+
+    let options = Options::parse();
+    let objects = options.objects.unwrap_or(options.workload.objects());
+    let object_size = options
+        .object_size
+        .unwrap_or(options.workload.object_size());
+    let raw = options.object_store_config.make()?;
+    let store: Arc<dyn LoadTestStore> = match options.wrapper {
+        Wrapper::None => Arc::new(raw.clone()),
+        Wrapper::Checksum => Arc::new(ChecksummedStore::new(raw.clone())),
+        Wrapper::Compression => Arc::new(CompressingObjectStore::new(raw.clone(), 0)),
+    };
+    let prefix = Path::from(options.prefix.as_str());
+    if raw
+        .list_objects(Some(&prefix))
+        .await?
+        .next()
+        .await
+        .is_some()
+    {
+        return Err(anyhow!("Prefix {prefix} isn't empty"));
+    }
+
+    // Random contents, so that compressing wrappers don't get an unrealistic ratio
+    let mut contents = vec![0; object_size];
+    rand::thread_rng().fill_bytes(&mut contents);
+    let contents = Bytes::from(contents);
+    let paths: Vec<Path> = (0..objects)
+        .map(|i| prefix.child(format!("{i}.chk")))
+        .collect();
+    println!(
+        "{objects} objects of {object_size} bytes under {prefix}, {} at a time",
+        options.concurrency
+    );
+
+    run_phase(&paths, options.concurrency, |path| {
+        let store = store.clone();
+        let contents = contents.clone();
+        async move {
+            store.put_bytes(&path, contents.clone()).await?;
+            Ok(contents.len())
+        }
+    })
+    .await?
+    .print("put");
+
+    run_phase(&paths, options.concurrency, |path| {
+        let store = store.clone();
+        async move { Ok(store.get_bytes(&path).await?.len()) }
+    })
+    .await?
+    .print("get");
+
+    let listings = vec![prefix.clone(); options.list_iterations];
+    run_phase(&listings, options.concurrency, |path| {
+        let raw = raw.clone();
+        async move {
+            let listed: Vec<_> = raw.list_objects(Some(&path)).await?.try_collect().await?;
+            if listed.len() != objects {
+                return Err(anyhow!("Listed {} objects of {objects}", listed.len()));
+            }
+            Ok(0)
+        }
+    })
+    .await?
+    .print("list");
+
+    if !options.keep {
+        run_phase(&paths, options.concurrency, |path| {
+            let store = store.clone();
+            async move {
+                store.delete_object(&path).await?;
+                Ok(0)
+            }
+        })
+        .await?
+        .print("delete");
+    }
+    Ok(())
+}
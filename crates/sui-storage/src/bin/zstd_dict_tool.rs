@@ -0,0 +1,144 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, Result};
+use clap::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+use sui_storage::http_key_value_store::*;
+use sui_storage::key_value_store::TransactionKeyValueStore;
+use sui_storage::key_value_store_metrics::KeyValueStoreMetrics;
+use sui_storage::object_store::compression::ZstdDictionary;
+use sui_types::messages_checkpoint::CheckpointSequenceNumber;
+
+// Trains a zstd dictionary over the checkpoint summaries, contents, transactions and effects of
+// a range of checkpoints fetched from the HTTP key-value store, and reports the compression
+// ratio of held out samples with and without it.
+#[derive(Parser)]
+#[command(rename_all = "kebab-case")]
+struct Options {
+    #[arg(short, long, default_value = "https://transactions.sui.io/mainnet")]
+    base_url: String,
+
+    #[arg(short, long)]
+    start_checkpoint: CheckpointSequenceNumber,
+
+    #[arg(short, long, default_value_t = 1000)]
+    num_checkpoints: u64,
+
+    /// Maximum size of the dictionary, in bytes
+    #[arg(long, default_value_t = 112640)]
+    max_dict_size: usize,
+
+    #[arg(long, default_value_t = 3)]
+    level: i32,
+
+    /// One in this many samples is held out of training, to measure the dictionary on
+    #[arg(long, default_value_t = 10)]
+    hold_out_every: usize,
+
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+const BATCH_SIZE: u64 = 50;
+
+async fn fetch_samples(
+    kv: &TransactionKeyValueStore,
+    checkpoints: &[CheckpointSequenceNumber],
+) -> Result<Vec<Vec<u8>>> {
+    let (summaries, contents, _, _) = kv
+        .multi_get_checkpoints(checkpoints, checkpoints, &[], &[])
+        .await?;
+    let mut samples = vec![];
+    for summary in summaries.into_iter().flatten() {
+        samples.push(bcs::to_bytes(&summary)?);
+    }
+    let mut digests = vec![];
+    for contents in contents.into_iter().flatten() {
+        samples.push(bcs::to_bytes(&contents)?);
+        digests.extend(contents.iter().map(|digests| digests.transaction));
+    }
+    let (transactions, effects, _) = kv.multi_get(&digests, &digests, &[]).await?;
+    for transaction in transactions.into_iter().flatten() {
+        samples.push(bcs::to_bytes(&transaction)?);
+    }
+    for effects in effects.into_iter().flatten() {
+        samples.push(bcs::to_bytes(&effects)?);
+    }
+    Ok(samples)
+}
+
+fn compressed_size(
+    samples: &[Vec<u8>],
+    compress: impl Fn(&[u8]) -> Result<Vec<u8>>,
+) -> Result<usize> {
+    samples
+        .iter()
+        .map(|sample| compress(sample).map(|compressed| compressed.len()))
+        .sum()
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _guard = telemetry_subscribers::TelemetryConfig::new()
+        .with_env()
+        .init();
+
+    let options = Options::parse();
+    if options.hold_out_every < 2 {
+        return Err(anyhow!("--hold-out-every must be at least 2"));
+    }
+
+    let http_kv = Arc::new(HttpKVStore::new(&options.base_url)?);
+    let kv =
+        TransactionKeyValueStore::new("http_kv", KeyValueStoreMetrics::new_for_tests(), http_kv);
+
+    let end = options.start_checkpoint + options.num_checkpoints;
+    let mut training = vec![];
+    let mut held_out = vec![];
+    for batch_start in (options.start_checkpoint..end).step_by(BATCH_SIZE as usize) {
+        let checkpoints: Vec<_> = (batch_start..end.min(batch_start + BATCH_SIZE)).collect();
+        for sample in fetch_samples(&kv, &checkpoints).await? {
+            if (training.len() + held_out.len()) % options.hold_out_every == 0 {
+                held_out.push(sample);
+            } else {
+                training.push(sample);
+            }
+        }
+    }
+    let training_size: usize = training.iter().map(Vec::len).sum();
+    println!(
+        "Training over {} samples of {} bytes",
+        training.len(),
+        training_size
+    );
+
+    let dictionary = ZstdDictionary::train(&training, options.max_dict_size)?;
+    std::fs::write(&options.output, dictionary.as_bytes())?;
+    println!(
+        "Wrote dictionary {} of {} bytes to {}",
+        dictionary.id(),
+        dictionary.as_bytes().len(),
+        options.output.display()
+    );
+
+    let size: usize = held_out.iter().map(Vec::len).sum();
+    let plain = compressed_size(&held_out, |sample| {
+        Ok(zstd::bulk::compress(sample, options.level)?)
+    })?;
+    let with_dictionary = compressed_size(&held_out, |sample| {
+        dictionary.compress(sample, options.level)
+    })?;
+    println!(
+        "{} held out samples of {} bytes: compressed to {} bytes ({:.2}x) without the \
+         dictionary, {} bytes ({:.2}x) with it",
+        held_out.len(),
+        size,
+        plain,
+        size as f64 / plain as f64,
+        with_dictionary,
+        size as f64 / with_dictionary as f64,
+    );
+    Ok(())
+}
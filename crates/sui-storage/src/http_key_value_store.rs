@@ -32,10 +32,16 @@ use url::Url;
 
 use crate::key_value_store::{TransactionKeyValueStore, TransactionKeyValueStoreTrait};
 use crate::key_value_store_metrics::KeyValueStoreMetrics;
+use crate::object_store::compression::{decompress_frame, is_zstd_compressed, ZstdDictionary};
+use crate::packed_key_value_store::{PackReader, PackedKey};
 
 pub struct HttpKVStore {
     base_url: Url,
     client: Arc<Client<HttpsConnector<HttpConnector>>>,
+    /// Dictionary values compressed by the uploader may need, see [`ZstdDictionary`].
+    dictionary: Option<Arc<ZstdDictionary>>,
+    /// Packs of small values, read before falling back to the per-value URLs.
+    packs: Option<PackReader>,
 }
 
 pub fn encode_digest<T: AsRef<[u8]>>(digest: &T) -> String {
@@ -95,32 +101,57 @@ enum Value {
     TxToCheckpoint(CheckpointSequenceNumber),
 }
 
-fn key_to_path_elements(key: &Key) -> SuiResult<(String, &'static str)> {
+fn digest_bytes<T: AsRef<[u8]>>(digest: &T) -> Vec<u8> {
+    digest.as_ref().to_vec()
+}
+
+/// Bytes of the key and type of the value of `key`, as they are packed, see [`PackedKey`].
+fn key_bytes(key: &Key) -> (Vec<u8>, &'static str) {
+    let tagged_key = |seq: &CheckpointSequenceNumber| {
+        bcs::to_bytes(&TaggedKey::CheckpointSequenceNumber(*seq)).expect("failed to serialize key")
+    };
     match key {
-        Key::Tx(digest) => Ok((encode_digest(digest), "tx")),
-        Key::Fx(digest) => Ok((encode_digest(digest), "fx")),
-        Key::Events(digest) => Ok((encode_digest(digest), "ev")),
-        Key::CheckpointContents(seq) => Ok((
-            encoded_tagged_key(&TaggedKey::CheckpointSequenceNumber(*seq)),
-            "cc",
-        )),
-        Key::CheckpointSummary(seq) => Ok((
-            encoded_tagged_key(&TaggedKey::CheckpointSequenceNumber(*seq)),
-            "cs",
-        )),
-        Key::CheckpointContentsByDigest(digest) => Ok((encode_digest(digest), "cc")),
-        Key::CheckpointSummaryByDigest(digest) => Ok((encode_digest(digest), "cs")),
-        Key::TxToCheckpoint(digest) => Ok((encode_digest(digest), "tx2c")),
-        Key::ObjectKey(object_id, version) => Ok((encode_object_key(object_id, version), "ob")),
+        Key::Tx(digest) => (digest_bytes(digest), "tx"),
+        Key::Fx(digest) => (digest_bytes(digest), "fx"),
+        Key::Events(digest) => (digest_bytes(digest), "ev"),
+        Key::CheckpointContents(seq) => (tagged_key(seq), "cc"),
+        Key::CheckpointSummary(seq) => (tagged_key(seq), "cs"),
+        Key::CheckpointContentsByDigest(digest) => (digest_bytes(digest), "cc"),
+        Key::CheckpointSummaryByDigest(digest) => (digest_bytes(digest), "cs"),
+        Key::TxToCheckpoint(digest) => (digest_bytes(digest), "tx2c"),
+        Key::ObjectKey(object_id, version) => (
+            bcs::to_bytes(&ObjectKey(*object_id, *version))
+                .expect("failed to serialize object key"),
+            "ob",
+        ),
     }
 }
 
+fn key_to_path_elements(key: &Key) -> SuiResult<(String, &'static str)> {
+    let (bytes, item_type) = key_bytes(key);
+    Ok((base64_url::encode(&bytes), item_type))
+}
+
+pub fn packed_key(key: &Key) -> PackedKey {
+    let (bytes, item_type) = key_bytes(key);
+    PackedKey::new(item_type, bytes)
+}
+
 impl HttpKVStore {
     pub fn new_kv(
         base_url: &str,
+        dictionary: Option<Arc<ZstdDictionary>>,
+        packs: Option<PackReader>,
         metrics: Arc<KeyValueStoreMetrics>,
     ) -> SuiResult<TransactionKeyValueStore> {
-        let inner = Arc::new(Self::new(base_url)?);
+        let mut inner = Self::new(base_url)?;
+        if let Some(dictionary) = dictionary {
+            inner = inner.with_dictionary(dictionary);
+        }
+        if let Some(packs) = packs {
+            inner = inner.with_packs(packs);
+        }
+        let inner = Arc::new(inner);
         Ok(TransactionKeyValueStore::new("http", metrics, inner))
     }
 
@@ -147,9 +178,24 @@ impl HttpKVStore {
         Ok(Self {
             base_url,
             client: Arc::new(client),
+            dictionary: None,
+            packs: None,
         })
     }
 
+    /// Decompress values compressed with `dictionary`, in addition to uncompressed values and
+    /// values compressed without a dictionary.
+    pub fn with_dictionary(mut self, dictionary: Arc<ZstdDictionary>) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    /// Read the values found in `packs` from their pack, and only the others from their URL.
+    pub fn with_packs(mut self, packs: PackReader) -> Self {
+        self.packs = Some(packs);
+        self
+    }
+
     fn get_url(&self, key: &Key) -> SuiResult<Uri> {
         let (digest, item_type) = key_to_path_elements(key)?;
         let joined = self
@@ -160,17 +206,41 @@ impl HttpKVStore {
     }
 
     async fn multi_fetch(&self, uris: Vec<Key>) -> Vec<SuiResult<Option<Bytes>>> {
-        let uris_vec = uris.to_vec();
+        let packed = match &self.packs {
+            Some(packs) => {
+                let keys: Vec<_> = uris.iter().map(packed_key).collect();
+                packs.multi_get(&keys).await.unwrap_or_else(|e| {
+                    warn!(
+                        "Failed to read packed values, fetching them one by one: {:?}",
+                        e
+                    );
+                    vec![None; uris.len()]
+                })
+            }
+            None => vec![None; uris.len()],
+        };
+        let len = uris.len();
         let fetches = stream::iter(
-            uris_vec
-                .into_iter()
-                .enumerate()
-                .map(|(_i, uri)| self.fetch(uri)),
+            uris.into_iter()
+                .zip(packed)
+                .map(|(key, packed)| async move {
+                    match packed {
+                        Some(bytes) => Ok(Some(self.decompress(&key, bytes))),
+                        None => self.fetch_url(key).await,
+                    }
+                }),
         );
-        fetches.buffered(uris.len()).collect::<Vec<_>>().await
+        fetches.buffered(len).collect::<Vec<_>>().await
     }
 
     async fn fetch(&self, key: Key) -> SuiResult<Option<Bytes>> {
+        self.multi_fetch(vec![key])
+            .await
+            .pop()
+            .expect("one fetch per key")
+    }
+
+    async fn fetch_url(&self, key: Key) -> SuiResult<Option<Bytes>> {
         let uri = self.get_url(&key)?;
         trace!("fetching uri: {}", uri);
         let resp = self.client.get(uri.clone()).await.into_sui_result()?;
@@ -186,12 +256,27 @@ impl HttpKVStore {
         if resp.status().is_success() {
             hyper::body::to_bytes(resp.into_body())
                 .await
-                .map(Some)
+                .map(|bytes| Some(self.decompress(&key, bytes)))
                 .into_sui_result()
         } else {
             Ok(None)
         }
     }
+
+    /// Values are uploaded either as plain BCS or as zstd frames of it. BCS values starting with
+    /// the zstd magic number are returned as they are.
+    fn decompress(&self, key: &Key, bytes: Bytes) -> Bytes {
+        if !is_zstd_compressed(&bytes) {
+            return bytes;
+        }
+        match decompress_frame(&bytes, self.dictionary.as_deref()) {
+            Ok(decompressed) => decompressed.into(),
+            Err(e) => {
+                warn!("Failed to decompress value of key {:?}: {:?}", key, e);
+                bytes
+            }
+        }
+    }
 }
 
 fn deser<K, T>(key: &K, bytes: &[u8]) -> Option<T>
@@ -1178,18 +1178,13 @@ impl IndexStore {
             return Ok(Some(info.object_id));
         }
 
-        let dynamic_object_field_struct = DynamicFieldInfo::dynamic_object_field_wrapper(name_type);
-        let dynamic_object_field_type = TypeTag::Struct(Box::new(dynamic_object_field_struct));
-        let dynamic_object_field_id = dynamic_field::derive_dynamic_field_id(
-            object,
-            &dynamic_object_field_type,
-            name_bcs_bytes,
-        )
-        .map_err(|e| {
-            SuiError::Unknown(format!(
-                "Unable to generate dynamic field id. Got error: {e:?}"
-            ))
-        })?;
+        let dynamic_object_field_id =
+            dynamic_field::derive_dynamic_object_field_id(object, &name_type, name_bcs_bytes)
+                .map_err(|e| {
+                    SuiError::Unknown(format!(
+                        "Unable to generate dynamic field id. Got error: {e:?}"
+                    ))
+                })?;
         if let Some(info) = self
             .tables
             .dynamic_field_index
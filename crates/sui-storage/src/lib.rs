@@ -19,6 +19,7 @@ use std::fs::File;
 use std::io::{BufReader, Read, Write};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::{fs, io};
@@ -33,9 +34,11 @@ pub mod blob;
 pub mod http_key_value_store;
 pub mod key_value_store;
 pub mod key_value_store_metrics;
+pub mod memory_budget;
 pub mod mutex_table;
 pub mod object_store;
 pub mod package_object_cache;
+pub mod packed_key_value_store;
 pub mod sharded_lru;
 pub mod write_path_pending_tx_log;
 
@@ -96,6 +99,20 @@ impl FileCompression {
     }
 }
 
+impl FromStr for FileCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(FileCompression::None),
+            "zstd" => Ok(FileCompression::Zstd),
+            _ => Err(anyhow!(
+                "Unknown file compression: {s}, expected none or zstd"
+            )),
+        }
+    }
+}
+
 pub fn compute_sha3_checksum_for_bytes(bytes: Bytes) -> Result<[u8; 32]> {
     let mut hasher = Sha3_256::default();
     io::copy(&mut bytes.reader(), &mut hasher)?;
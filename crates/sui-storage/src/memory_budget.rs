@@ -0,0 +1,273 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Process wide memory budget shared by in-memory caches.
+//!
+//! Caches register with the budget, and charge it for the approximate size of every entry they
+//! insert. When the sum of all registered caches exceeds the budget, entries are evicted from
+//! the cache using the most memory relative to its weight, until the total fits again. Caches
+//! keep their own limits, the budget only bounds their sum.
+//!
+//! The budget is initialized once at startup with [`init_memory_budget`]. Caches created before
+//! that, or in processes that don't set one, are not accounted.
+
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use tracing::{debug, info, warn};
+
+static MEMORY_BUDGET: OnceCell<Arc<MemoryBudget>> = OnceCell::new();
+
+/// Set the process wide budget, in bytes, that caches registered from now on share.
+pub fn init_memory_budget(limit_bytes: usize) {
+    if MEMORY_BUDGET
+        .set(Arc::new(MemoryBudget::new(limit_bytes)))
+        .is_err()
+    {
+        warn!("Memory budget already initialized, ignoring new limit of {limit_bytes} bytes");
+    } else {
+        info!("Caches share a memory budget of {limit_bytes} bytes");
+    }
+}
+
+/// Register `cache` with the process wide budget, if one was initialized.
+pub fn register_cache(
+    name: &'static str,
+    weight: u32,
+    cache: Weak<dyn EvictableCache>,
+) -> Option<MemoryAccount> {
+    MEMORY_BUDGET
+        .get()
+        .map(|budget| budget.register(name, weight, cache))
+}
+
+/// A cache whose entries can be dropped to free memory.
+pub trait EvictableCache: Send + Sync {
+    /// Evict entries, least valuable first, until at least `bytes` are freed or the cache is
+    /// empty. Returns the number of bytes freed, which the budget releases from the cache's
+    /// [`MemoryAccount`] on its behalf.
+    ///
+    /// Called from within [`MemoryAccount::charge`], so caches must not hold their own locks
+    /// while charging.
+    fn evict(&self, bytes: usize) -> usize;
+}
+
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+    caches: Mutex<Vec<RegisteredCache>>,
+}
+
+struct RegisteredCache {
+    name: &'static str,
+    weight: u32,
+    used: Arc<AtomicUsize>,
+    cache: Weak<dyn EvictableCache>,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            caches: Mutex::new(vec![]),
+        }
+    }
+
+    /// Register a cache. Caches with a higher `weight` are entitled to a larger share of the
+    /// budget before being evicted from.
+    pub fn register(
+        self: &Arc<Self>,
+        name: &'static str,
+        weight: u32,
+        cache: Weak<dyn EvictableCache>,
+    ) -> MemoryAccount {
+        let used = Arc::new(AtomicUsize::new(0));
+        self.caches.lock().push(RegisteredCache {
+            name,
+            weight: weight.max(1),
+            used: used.clone(),
+            cache,
+        });
+        MemoryAccount {
+            budget: self.clone(),
+            used,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Bytes used by each registered cache.
+    pub fn usage(&self) -> Vec<(&'static str, usize)> {
+        self.caches
+            .lock()
+            .iter()
+            .filter(|c| c.cache.strong_count() > 0)
+            .map(|c| (c.name, c.used.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Evict from caches until the total usage fits in the budget, or nothing more can be
+    /// evicted.
+    fn enforce(&self) {
+        // A single caller evicts at a time, others carry on over budget until it is done.
+        let Some(mut caches) = self.caches.try_lock() else {
+            return;
+        };
+        caches.retain(|c| c.cache.strong_count() > 0);
+        while self.used() > self.limit {
+            let excess = self.used() - self.limit;
+            let Some(victim) = caches
+                .iter()
+                .filter(|c| c.used.load(Ordering::Relaxed) > 0)
+                .max_by(|a, b| a.usage_per_weight().total_cmp(&b.usage_per_weight()))
+            else {
+                return;
+            };
+            let freed = victim
+                .cache
+                .upgrade()
+                .map(|cache| cache.evict(excess))
+                .unwrap_or_default();
+            debug!(
+                "Evicted {freed} bytes from {} to fit the memory budget",
+                victim.name
+            );
+            if freed == 0 {
+                warn!(
+                    "Memory budget exceeded by {excess} bytes, but {} could not evict anything",
+                    victim.name
+                );
+                return;
+            }
+            saturating_sub(&victim.used, freed);
+            saturating_sub(&self.used, freed);
+        }
+    }
+}
+
+impl RegisteredCache {
+    fn usage_per_weight(&self) -> f64 {
+        self.used.load(Ordering::Relaxed) as f64 / self.weight as f64
+    }
+}
+
+/// Handle through which a registered cache reports the memory used by its entries.
+pub struct MemoryAccount {
+    budget: Arc<MemoryBudget>,
+    used: Arc<AtomicUsize>,
+}
+
+impl MemoryAccount {
+    /// Account for `bytes` newly used by the cache, evicting from caches if this exceeds the
+    /// budget.
+    pub fn charge(&self, bytes: usize) {
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+        if self.budget.used.fetch_add(bytes, Ordering::Relaxed) + bytes > self.budget.limit {
+            self.budget.enforce();
+        }
+    }
+
+    /// Account for `bytes` no longer used by the cache.
+    pub fn release(&self, bytes: usize) {
+        saturating_sub(&self.used, bytes);
+        saturating_sub(&self.budget.used, bytes);
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MemoryAccount {
+    fn drop(&mut self) {
+        saturating_sub(&self.budget.used, self.used.swap(0, Ordering::Relaxed));
+    }
+}
+
+/// Entries may be evicted between being inserted and charged, so counters can be asked to
+/// release more than they hold.
+fn saturating_sub(counter: &AtomicUsize, bytes: usize) {
+    let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+        Some(used.saturating_sub(bytes))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory_budget::{EvictableCache, MemoryAccount, MemoryBudget};
+    use parking_lot::Mutex;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Weak};
+
+    /// Cache of entries of the given sizes, evicted oldest first.
+    struct TestCache {
+        entries: Mutex<VecDeque<usize>>,
+        account: MemoryAccount,
+    }
+
+    impl TestCache {
+        fn new(budget: &Arc<MemoryBudget>, name: &'static str, weight: u32) -> Arc<Self> {
+            Arc::new_cyclic(|cache: &Weak<Self>| Self {
+                entries: Mutex::new(VecDeque::new()),
+                account: budget.register(name, weight, cache.clone()),
+            })
+        }
+
+        fn insert(&self, size: usize) {
+            self.entries.lock().push_back(size);
+            self.account.charge(size);
+        }
+
+        fn len(&self) -> usize {
+            self.entries.lock().len()
+        }
+    }
+
+    impl EvictableCache for TestCache {
+        fn evict(&self, bytes: usize) -> usize {
+            let mut freed = 0;
+            let mut entries = self.entries.lock();
+            while freed < bytes {
+                let Some(size) = entries.pop_front() else {
+                    break;
+                };
+                freed += size;
+            }
+            freed
+        }
+    }
+
+    #[test]
+    fn test_weighted_eviction() {
+        let budget = Arc::new(MemoryBudget::new(1000));
+        let heavy = TestCache::new(&budget, "heavy", 3);
+        let light = TestCache::new(&budget, "light", 1);
+
+        for _ in 0..6 {
+            heavy.insert(100);
+        }
+        for _ in 0..4 {
+            light.insert(100);
+        }
+        assert_eq!(budget.used(), 1000);
+
+        // 600 / 3 for heavy is less than 400 / 1 for light, so light is evicted from.
+        heavy.insert(100);
+        assert_eq!(heavy.len(), 7);
+        assert_eq!(light.len(), 3);
+        assert_eq!(budget.used(), 1000);
+
+        drop(light);
+        heavy.insert(100);
+        assert!(budget.used() <= 1000);
+        assert_eq!(budget.usage().len(), 1);
+    }
+}
@@ -0,0 +1,477 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Adaptive limit of the concurrent requests of a store, as an alternative to the fixed limit of
+//! `object_store::limit::LimitStore`, which is either too conservative on fat links or causes
+//! throttling storms on small buckets.
+//!
+//! The limit follows an AIMD scheme: it grows by one request after every window of requests
+//! whose p99 latency is within the target, and shrinks multiplicatively when the p99 latency
+//! exceeds the target or the store throttles a request, i.e. responds with a 429 or 503 status.
+//! Requests hold their slot until their response is consumed, but their latency is measured
+//! until the response starts, so that large downloads don't read as slow requests.
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use object_store::path::Path;
+use object_store::{
+    Error, GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta,
+    ObjectStore, Result,
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Number of requests whose latency is measured before the limit is adjusted.
+const WINDOW_SIZE: usize = 100;
+/// Ratio the limit is multiplied by when the store throttles requests.
+const THROTTLED_BACKOFF: f64 = 0.5;
+/// Ratio the limit is multiplied by when the p99 latency exceeds the target.
+const LATENCY_BACKOFF: f64 = 0.75;
+
+#[derive(Debug, Clone)]
+pub struct AdaptiveLimitConfig {
+    pub min_limit: usize,
+    pub max_limit: usize,
+    /// p99 latency of requests above which the limit shrinks
+    pub latency_target: Duration,
+}
+
+/// Concurrency limit adjusted to the latencies and throttling of the requests it lets through.
+#[derive(Debug)]
+pub struct AdaptiveLimiter {
+    config: AdaptiveLimitConfig,
+    state: Mutex<LimiterState>,
+    /// Notified when a slot frees up or the limit grows
+    released: Notify,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    limit: f64,
+    in_flight: usize,
+    /// Latencies of the requests completed in the current window
+    latencies: Vec<Duration>,
+    /// Requests in flight when the limit last shrank were sent under the previous limit, so
+    /// the limit doesn't shrink again for their throttling until the cooldown is over.
+    decrease_cooldown_until: Option<Instant>,
+}
+
+/// How a request completed, for the limiter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    /// The store responded, after the given time
+    Responded(Duration),
+    /// The store throttled the request
+    Throttled,
+    /// The request failed for another reason, which says nothing of the store's load
+    Ignored,
+}
+
+impl AdaptiveLimiter {
+    /// Limiter starting halfway between the minimum and maximum limits.
+    pub fn new(config: AdaptiveLimitConfig) -> Self {
+        let min_limit = config.min_limit.max(1);
+        let max_limit = config.max_limit.max(min_limit);
+        let config = AdaptiveLimitConfig {
+            min_limit,
+            max_limit,
+            ..config
+        };
+        AdaptiveLimiter {
+            state: Mutex::new(LimiterState {
+                limit: ((min_limit + max_limit) / 2) as f64,
+                in_flight: 0,
+                latencies: Vec::with_capacity(WINDOW_SIZE),
+                decrease_cooldown_until: None,
+            }),
+            config,
+            released: Notify::new(),
+        }
+    }
+
+    /// Current number of requests allowed in flight.
+    pub fn limit(&self) -> usize {
+        self.state.lock().limit as usize
+    }
+
+    /// Wait for a slot under the current limit.
+    pub async fn acquire(self: &Arc<Self>) -> AdaptivePermit {
+        loop {
+            // Registered before checking, so that no release is missed in between.
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            {
+                let mut state = self.state.lock();
+                if state.in_flight < state.limit as usize {
+                    state.in_flight += 1;
+                    return AdaptivePermit {
+                        limiter: self.clone(),
+                        started: Instant::now(),
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+
+    fn record(&self, outcome: Outcome) {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        let cooling_down = state
+            .decrease_cooldown_until
+            .map_or(false, |until| now < until);
+        match outcome {
+            Outcome::Responded(latency) => {
+                state.latencies.push(latency);
+                if state.latencies.len() < WINDOW_SIZE {
+                    return;
+                }
+                state.latencies.sort();
+                let p99 = state.latencies[WINDOW_SIZE * 99 / 100 - 1];
+                state.latencies.clear();
+                if p99 > self.config.latency_target {
+                    if !cooling_down {
+                        self.decrease(&mut state, LATENCY_BACKOFF, now);
+                    }
+                } else if state.limit < self.config.max_limit as f64 {
+                    state.limit += 1.0;
+                    debug!(
+                        limit = state.limit,
+                        ?p99,
+                        "Object store concurrency limit grew"
+                    );
+                    self.released.notify_waiters();
+                }
+            }
+            Outcome::Throttled if !cooling_down => {
+                self.decrease(&mut state, THROTTLED_BACKOFF, now);
+            }
+            Outcome::Throttled | Outcome::Ignored => {}
+        }
+    }
+
+    fn decrease(&self, state: &mut LimiterState, backoff: f64, now: Instant) {
+        state.limit = (state.limit * backoff)
+            .floor()
+            .max(self.config.min_limit as f64);
+        state.latencies.clear();
+        state.decrease_cooldown_until = Some(now + self.config.latency_target);
+        debug!(limit = state.limit, "Object store concurrency limit shrank");
+    }
+
+    fn release(&self) {
+        self.state.lock().in_flight -= 1;
+        self.released.notify_waiters();
+    }
+}
+
+/// Slot of a request under the limit, released when dropped.
+pub struct AdaptivePermit {
+    limiter: Arc<AdaptiveLimiter>,
+    started: Instant,
+}
+
+impl AdaptivePermit {
+    /// Record the outcome of the request, once its response starts or it fails.
+    fn record<T>(&self, result: &Result<T>) {
+        let outcome = match result {
+            Ok(_) | Err(Error::NotFound { .. }) => Outcome::Responded(self.started.elapsed()),
+            Err(Error::Generic { source, .. }) if is_throttled(&source.to_string()) => {
+                Outcome::Throttled
+            }
+            Err(_) => Outcome::Ignored,
+        };
+        self.limiter.record(outcome);
+    }
+}
+
+impl Drop for AdaptivePermit {
+    fn drop(&mut self) {
+        self.limiter.release();
+    }
+}
+
+/// Whether the error message carries a 429 or 503 status, as sent by stores throttling clients.
+fn is_throttled(message: &str) -> bool {
+    message.contains("client error (429") || message.contains("server error (503")
+}
+
+/// Store limiting its concurrent requests with an [`AdaptiveLimiter`].
+#[derive(Debug)]
+pub struct AdaptiveLimitStore<T> {
+    inner: T,
+    limiter: Arc<AdaptiveLimiter>,
+}
+
+impl<T: ObjectStore> AdaptiveLimitStore<T> {
+    pub fn new(inner: T, config: AdaptiveLimitConfig) -> Self {
+        Self {
+            inner,
+            limiter: Arc::new(AdaptiveLimiter::new(config)),
+        }
+    }
+
+    pub fn limiter(&self) -> &Arc<AdaptiveLimiter> {
+        &self.limiter
+    }
+
+    /// Run `request` under the limit, releasing its slot once it completes.
+    async fn limited<R>(&self, request: impl std::future::Future<Output = Result<R>>) -> Result<R> {
+        let permit = self.limiter.acquire().await;
+        let result = request.await;
+        permit.record(&result);
+        result
+    }
+
+    fn limit_get(permit: AdaptivePermit, result: GetResult) -> GetResult {
+        let payload = match result.payload {
+            GetResultPayload::Stream(stream) => {
+                GetResultPayload::Stream(hold_permit(stream, permit))
+            }
+            // Files are read locally, without holding a connection
+            payload => payload,
+        };
+        GetResult {
+            payload,
+            meta: result.meta,
+            range: result.range,
+        }
+    }
+}
+
+/// `stream`, holding `permit` until it is dropped.
+fn hold_permit<'a, T: Send + 'a>(
+    stream: BoxStream<'a, T>,
+    permit: AdaptivePermit,
+) -> BoxStream<'a, T> {
+    stream
+        .map(move |item| {
+            let _permit = &permit;
+            item
+        })
+        .boxed()
+}
+
+impl<T: ObjectStore> Display for AdaptiveLimitStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AdaptiveLimitStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for AdaptiveLimitStore<T> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        self.limited(self.inner.put(location, bytes)).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let permit = self.limiter.acquire().await;
+        let result = self.inner.put_multipart(location).await;
+        permit.record(&result);
+        let (multipart_id, writer) = result?;
+        Ok((
+            multipart_id,
+            Box::new(PermitWriter {
+                inner: writer,
+                _permit: permit,
+            }),
+        ))
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.limited(self.inner.abort_multipart(location, multipart_id))
+            .await
+    }
+
+    async fn append(&self, location: &Path) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        let permit = self.limiter.acquire().await;
+        let result = self.inner.append(location).await;
+        permit.record(&result);
+        Ok(Box::new(PermitWriter {
+            inner: result?,
+            _permit: permit,
+        }))
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        let permit = self.limiter.acquire().await;
+        let result = self.inner.get(location).await;
+        permit.record(&result);
+        Ok(Self::limit_get(permit, result?))
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        let permit = self.limiter.acquire().await;
+        let result = self.inner.get_opts(location, options).await;
+        permit.record(&result);
+        Ok(Self::limit_get(permit, result?))
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.limited(self.inner.get_range(location, range)).await
+    }
+
+    async fn get_ranges(&self, location: &Path, ranges: &[Range<usize>]) -> Result<Vec<Bytes>> {
+        self.limited(self.inner.get_ranges(location, ranges)).await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.limited(self.inner.head(location)).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.limited(self.inner.delete(location)).await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        let permit = self.limiter.acquire().await;
+        let result = self.inner.list(prefix).await;
+        permit.record(&result);
+        Ok(hold_permit(result?, permit))
+    }
+
+    async fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        let permit = self.limiter.acquire().await;
+        let result = self.inner.list_with_offset(prefix, offset).await;
+        permit.record(&result);
+        Ok(hold_permit(result?, permit))
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        self.limited(self.inner.list_with_delimiter(prefix)).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.limited(self.inner.copy(from, to)).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.limited(self.inner.rename(from, to)).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.limited(self.inner.copy_if_not_exists(from, to)).await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.limited(self.inner.rename_if_not_exists(from, to))
+            .await
+    }
+}
+
+/// Writer holding a slot until the upload is done with.
+struct PermitWriter {
+    inner: Box<dyn AsyncWrite + Unpin + Send>,
+    _permit: AdaptivePermit,
+}
+
+impl AsyncWrite for PermitWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::adaptive::{
+        is_throttled, AdaptiveLimitConfig, AdaptiveLimiter, Outcome, WINDOW_SIZE,
+    };
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn limiter() -> Arc<AdaptiveLimiter> {
+        Arc::new(AdaptiveLimiter::new(AdaptiveLimitConfig {
+            min_limit: 2,
+            max_limit: 16,
+            latency_target: Duration::from_millis(500),
+        }))
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn test_limit_grows_and_shrinks() {
+        let limiter = limiter();
+        assert_eq!(limiter.limit(), 9);
+
+        // Fast windows grow the limit by one request
+        for _ in 0..WINDOW_SIZE {
+            limiter.record(Outcome::Responded(Duration::from_millis(100)));
+        }
+        assert_eq!(limiter.limit(), 10);
+
+        // Throttling halves it, once per cooldown
+        limiter.record(Outcome::Throttled);
+        limiter.record(Outcome::Throttled);
+        assert_eq!(limiter.limit(), 5);
+        tokio::time::advance(Duration::from_secs(1)).await;
+
+        // A slow window shrinks it, down to the minimum
+        for _ in 0..WINDOW_SIZE {
+            limiter.record(Outcome::Responded(Duration::from_secs(1)));
+        }
+        assert_eq!(limiter.limit(), 3);
+        tokio::time::advance(Duration::from_secs(1)).await;
+        limiter.record(Outcome::Throttled);
+        assert_eq!(limiter.limit(), 2);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn test_requests_wait_for_a_slot() {
+        let limiter = limiter();
+        let permits = futures::future::join_all((0..9).map(|_| limiter.acquire())).await;
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), limiter.acquire())
+                .await
+                .is_err()
+        );
+        drop(permits);
+        tokio::time::timeout(Duration::from_secs(1), limiter.acquire())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_is_throttled() {
+        assert!(is_throttled(
+            "HTTP status client error (429 Too Many Requests) for url (https://bucket/a)"
+        ));
+        assert!(is_throttled(
+            "HTTP status server error (503 Service Unavailable) for url (https://bucket/a)"
+        ));
+        assert!(!is_throttled(
+            "HTTP status server error (500 Internal Server Error) for url (https://bucket/a)"
+        ));
+    }
+}
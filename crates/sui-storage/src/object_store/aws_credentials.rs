@@ -0,0 +1,430 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::object_store::ObjectStoreConfig;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use aws_config::environment::EnvironmentVariableCredentialsProvider;
+use aws_config::imds::credentials::ImdsCredentialsProvider;
+use aws_config::meta::credentials::CredentialsProviderChain;
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_config::provider_config::ProviderConfig;
+use aws_config::sts::AssumeRoleProvider;
+use aws_config::web_identity_token::{StaticConfiguration, WebIdentityTokenCredentialsProvider};
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_credential_types::Credentials;
+use aws_types::region::Region;
+use object_store::aws::AwsCredential;
+use object_store::CredentialProvider;
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Environment variables set up by IAM Roles for Service Accounts on EKS.
+const WEB_IDENTITY_TOKEN_FILE_ENV_VAR: &str = "AWS_WEB_IDENTITY_TOKEN_FILE";
+const ROLE_ARN_ENV_VAR: &str = "AWS_ROLE_ARN";
+const ROLE_SESSION_NAME_ENV_VAR: &str = "AWS_ROLE_SESSION_NAME";
+const REGION_ENV_VAR: &str = "AWS_REGION";
+const DEFAULT_ROLE_SESSION_NAME: &str = "sui-object-store";
+
+/// Credentials are reloaded on use if they expire within this long, so that requests signed with
+/// them do not race their expiry.
+const EXPIRY_BUFFER: Duration = Duration::from_secs(5 * 60);
+/// Credentials that expire are reloaded in the background this long before they do, so that
+/// requests never wait on a reload.
+const REFRESH_BEFORE_EXPIRY: Duration = Duration::from_secs(10 * 60);
+/// How long to wait before retrying a failed background reload.
+const REFRESH_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where S3 requests get their credentials from, in order of precedence:
+///
+/// 1. `--aws-access-key-id` and `--aws-secret-access-key`, if set.
+/// 2. The named profile in `--aws-profile`, resolved from the shared AWS config and credentials
+///    files (`~/.aws/config`, `~/.aws/credentials`, or the files pointed to by
+///    `AWS_CONFIG_FILE` and `AWS_SHARED_CREDENTIALS_FILE`). Static keys, `source_profile` role
+///    chains, SSO sessions and `credential_process` are all supported.
+/// 3. With `--aws-web-identity`, the web identity token in `AWS_WEB_IDENTITY_TOKEN_FILE`,
+///    exchanged through STS for credentials of the role in `AWS_ROLE_ARN`. The token file is
+///    re-read on every exchange, so tokens rotated by Kubernetes are picked up.
+/// 4. The instance metadata service, or web identity and container credentials if the
+///    environment is set up for them.
+///
+/// If `--aws-role-arn` is set, the credentials from this source are only used to assume that
+/// role, and requests are signed with the role's temporary credentials.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AwsCredentialSource {
+    Static,
+    Profile(String),
+    WebIdentity,
+    Instance,
+}
+
+/// Credential provider for `object_store` backed by a provider from the AWS SDK. Credentials are
+/// cached, and ones that expire (e.g. from SSO or STS) are reloaded in the background ahead of
+/// their expiry, so long running processes keep working past the lifetime of a session.
+pub struct AwsSdkCredentialProvider {
+    inner: Arc<Inner>,
+    refreshing: AtomicBool,
+}
+
+struct Inner {
+    /// Describes where credentials come from, for logs and errors.
+    source: String,
+    provider: SharedCredentialsProvider,
+    cached: Mutex<Option<CachedCredential>>,
+}
+
+struct CachedCredential {
+    credential: Arc<AwsCredential>,
+    expiry: Option<SystemTime>,
+}
+
+impl AwsSdkCredentialProvider {
+    /// Load credentials from a named profile in the shared AWS config files.
+    pub fn profile(profile: &str) -> Self {
+        Self::new(
+            format!("profile {profile}"),
+            ProfileFileCredentialsProvider::builder()
+                .profile_name(profile)
+                .build(),
+        )
+    }
+
+    /// Exchange the web identity token in the environment for credentials of the role it is
+    /// set up for, as with IAM Roles for Service Accounts.
+    pub fn web_identity(config: &ObjectStoreConfig) -> Result<Self> {
+        let (role_arn, provider) = web_identity_provider(config)?;
+        Ok(Self::new(
+            format!("web identity for role {role_arn}"),
+            provider,
+        ))
+    }
+
+    /// Assume `role_arn` through STS, calling STS with credentials from `config`'s
+    /// [`AwsCredentialSource`].
+    pub fn assume_role(config: &ObjectStoreConfig, role_arn: &str) -> Result<Self> {
+        Ok(Self::new(
+            format!("role {role_arn}"),
+            assume_role_provider(config, role_arn)?,
+        ))
+    }
+
+    fn new(source: String, provider: impl ProvideCredentials + 'static) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                source,
+                provider: SharedCredentialsProvider::new(provider),
+                cached: Mutex::new(None),
+            }),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Credential provider for clients of the AWS SDK, e.g. for S3 requests `object_store` does not
+/// support, resolving credentials the same way as [`ObjectStoreConfig::make`] does.
+pub fn sdk_credentials_provider(config: &ObjectStoreConfig) -> Result<SharedCredentialsProvider> {
+    match &config.aws_role_arn {
+        Some(role_arn) => Ok(SharedCredentialsProvider::new(assume_role_provider(
+            config, role_arn,
+        )?)),
+        None => base_credentials_provider(config),
+    }
+}
+
+fn base_credentials_provider(config: &ObjectStoreConfig) -> Result<SharedCredentialsProvider> {
+    Ok(match config.aws_credential_source() {
+        AwsCredentialSource::Static => {
+            let (Some(key_id), Some(secret)) =
+                (&config.aws_access_key_id, &config.aws_secret_access_key)
+            else {
+                bail!("Static credentials need both a key id and secret");
+            };
+            SharedCredentialsProvider::new(Credentials::from_keys(key_id, secret, None))
+        }
+        AwsCredentialSource::Profile(profile) => SharedCredentialsProvider::new(
+            ProfileFileCredentialsProvider::builder()
+                .profile_name(profile)
+                .build(),
+        ),
+        AwsCredentialSource::WebIdentity => {
+            SharedCredentialsProvider::new(web_identity_provider(config)?.1)
+        }
+        AwsCredentialSource::Instance => SharedCredentialsProvider::new(
+            CredentialsProviderChain::first_try(
+                "Environment",
+                EnvironmentVariableCredentialsProvider::new(),
+            )
+            .or_else(
+                "Ec2InstanceMetadata",
+                ImdsCredentialsProvider::builder().build(),
+            ),
+        ),
+    })
+}
+
+fn assume_role_provider(config: &ObjectStoreConfig, role_arn: &str) -> Result<AssumeRoleProvider> {
+    let base = base_credentials_provider(config)?;
+    let mut builder = AssumeRoleProvider::builder(role_arn);
+    if let Some(external_id) = &config.aws_external_id {
+        builder = builder.external_id(external_id);
+    }
+    if let Some(session_name) = &config.aws_role_session_name {
+        builder = builder.session_name(session_name);
+    }
+    if let Some(region) = &config.aws_region {
+        builder = builder.region(Region::new(region.clone()));
+    }
+    Ok(builder.build(base))
+}
+
+/// Build a provider for the web identity set up in the environment, returning the ARN of the role
+/// it assumes. Fails early if the environment is not set up, rather than on the first request.
+fn web_identity_provider(
+    config: &ObjectStoreConfig,
+) -> Result<(String, WebIdentityTokenCredentialsProvider)> {
+    let (Ok(token_file), Ok(role_arn)) = (
+        std::env::var(WEB_IDENTITY_TOKEN_FILE_ENV_VAR),
+        std::env::var(ROLE_ARN_ENV_VAR),
+    ) else {
+        bail!(
+            "Web identity credentials need {WEB_IDENTITY_TOKEN_FILE_ENV_VAR} and \
+             {ROLE_ARN_ENV_VAR} to be set"
+        );
+    };
+    // STS is called in the bucket's region, unless one is not configured.
+    let Some(region) = config
+        .aws_region
+        .clone()
+        .or_else(|| std::env::var(REGION_ENV_VAR).ok())
+    else {
+        bail!("Web identity credentials need --aws-region or {REGION_ENV_VAR} to be set");
+    };
+    let session_name = std::env::var(ROLE_SESSION_NAME_ENV_VAR)
+        .unwrap_or_else(|_| DEFAULT_ROLE_SESSION_NAME.to_string());
+
+    let provider = WebIdentityTokenCredentialsProvider::builder()
+        .configure(&ProviderConfig::without_region().with_region(Some(Region::new(region))))
+        .static_configuration(StaticConfiguration {
+            web_identity_token_file: PathBuf::from(token_file),
+            role_arn: role_arn.clone(),
+            session_name,
+        })
+        .build();
+    Ok((role_arn, provider))
+}
+
+impl Inner {
+    async fn load(&self) -> object_store::Result<CachedCredential> {
+        let credentials = self.provider.provide_credentials().await.map_err(|e| {
+            object_store::Error::Generic {
+                store: "S3",
+                source: format!("Failed to load AWS credentials from {}: {e}", self.source).into(),
+            }
+        })?;
+        info!(source = %self.source, expiry = ?credentials.expiry(), "Loaded AWS credentials");
+        Ok(CachedCredential {
+            credential: Arc::new(AwsCredential {
+                key_id: credentials.access_key_id().to_string(),
+                secret_key: credentials.secret_access_key().to_string(),
+                token: credentials.session_token().map(str::to_string),
+            }),
+            expiry: credentials.expiry(),
+        })
+    }
+}
+
+impl CachedCredential {
+    fn is_fresh(&self) -> bool {
+        self.expiry
+            .map_or(true, |expiry| SystemTime::now() + EXPIRY_BUFFER < expiry)
+    }
+}
+
+impl Debug for AwsSdkCredentialProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsSdkCredentialProvider")
+            .field("source", &self.inner.source)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for AwsSdkCredentialProvider {
+    type Credential = AwsCredential;
+
+    async fn get_credential(&self) -> object_store::Result<Arc<AwsCredential>> {
+        let mut cached = self.inner.cached.lock().await;
+        if let Some(cached) = cached.as_ref().filter(|cached| cached.is_fresh()) {
+            return Ok(cached.credential.clone());
+        }
+
+        let loaded = self.inner.load().await?;
+        let credential = loaded.credential.clone();
+        let expires = loaded.expiry.is_some();
+        *cached = Some(loaded);
+        drop(cached);
+
+        if expires && !self.refreshing.swap(true, Ordering::SeqCst) {
+            tokio::spawn(refresh_in_background(Arc::downgrade(&self.inner)));
+        }
+        Ok(credential)
+    }
+}
+
+/// Reload credentials ahead of their expiry until the provider is dropped, or the credentials
+/// loaded no longer expire.
+async fn refresh_in_background(inner: Weak<Inner>) {
+    let mut previous_expiry = None;
+    loop {
+        let (delay, expiry) = {
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            let expiry = inner
+                .cached
+                .lock()
+                .await
+                .as_ref()
+                .and_then(|cached| cached.expiry);
+            let Some(expiry) = expiry else {
+                return;
+            };
+            (
+                refresh_delay(expiry, previous_expiry, SystemTime::now()),
+                expiry,
+            )
+        };
+        tokio::time::sleep(delay).await;
+
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        match inner.load().await {
+            Ok(loaded) => {
+                *inner.cached.lock().await = Some(loaded);
+                previous_expiry = Some(expiry);
+            }
+            Err(e) => {
+                warn!(source = %inner.source, "Failed to refresh AWS credentials: {e}");
+                drop(inner);
+                tokio::time::sleep(REFRESH_RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// How long to wait before reloading credentials expiring at `expiry`. If the last reload didn't
+/// push the expiry past `previous_expiry`, e.g. because the source keeps handing out credentials
+/// expiring within [`REFRESH_BEFORE_EXPIRY`], wait at least [`REFRESH_RETRY_INTERVAL`] rather
+/// than reloading them in a loop.
+fn refresh_delay(
+    expiry: SystemTime,
+    previous_expiry: Option<SystemTime>,
+    now: SystemTime,
+) -> Duration {
+    let delay = expiry
+        .duration_since(now)
+        .unwrap_or_default()
+        .saturating_sub(REFRESH_BEFORE_EXPIRY);
+    match previous_expiry {
+        Some(previous_expiry) if expiry <= previous_expiry => delay.max(REFRESH_RETRY_INTERVAL),
+        _ => delay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::aws_credentials::{
+        refresh_delay, AwsCredentialSource, AwsSdkCredentialProvider, REFRESH_BEFORE_EXPIRY,
+        REFRESH_RETRY_INTERVAL,
+    };
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_credential_source_precedence() {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::S3),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.aws_credential_source(),
+            AwsCredentialSource::Instance
+        );
+
+        let config = ObjectStoreConfig {
+            aws_profile: Some("archive".to_string()),
+            ..config
+        };
+        assert_eq!(
+            config.aws_credential_source(),
+            AwsCredentialSource::Profile("archive".to_string())
+        );
+
+        let config = ObjectStoreConfig {
+            aws_access_key_id: Some("key".to_string()),
+            aws_secret_access_key: Some("secret".to_string()),
+            ..config
+        };
+        assert_eq!(config.aws_credential_source(), AwsCredentialSource::Static);
+    }
+
+    #[test]
+    fn test_web_identity_credential_source() {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::S3),
+            aws_web_identity: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.aws_credential_source(),
+            AwsCredentialSource::WebIdentity
+        );
+
+        // A profile is more specific than the environment.
+        let config = ObjectStoreConfig {
+            aws_profile: Some("archive".to_string()),
+            ..config
+        };
+        assert_eq!(
+            config.aws_credential_source(),
+            AwsCredentialSource::Profile("archive".to_string())
+        );
+    }
+
+    #[test]
+    fn test_assume_role_needs_both_keys() {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::S3),
+            aws_access_key_id: Some("key".to_string()),
+            aws_role_arn: Some("arn:aws:iam::123456789012:role/archive".to_string()),
+            ..Default::default()
+        };
+        assert!(AwsSdkCredentialProvider::assume_role(
+            &config,
+            "arn:aws:iam::123456789012:role/archive"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_refresh_delay() {
+        let now = SystemTime::now();
+        let hour = Duration::from_secs(60 * 60);
+        assert_eq!(
+            refresh_delay(now + hour, None, now),
+            hour - REFRESH_BEFORE_EXPIRY
+        );
+        // Credentials expiring within the refresh window are reloaded right away...
+        let soon = now + Duration::from_secs(60);
+        assert_eq!(refresh_delay(soon, None, now), Duration::ZERO);
+        assert_eq!(refresh_delay(soon, Some(now - hour), now), Duration::ZERO);
+        // ...but not again if reloading them didn't extend their expiry.
+        assert_eq!(refresh_delay(soon, Some(soon), now), REFRESH_RETRY_INTERVAL);
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::PathBuf;
+
+/// Where Azure Blob Storage requests get their credentials from, in order of precedence:
+///
+/// 1. `--azure-storage-access-key`, if set.
+/// 2. `--azure-storage-sas-token`, if set. SAS tokens are used as is, so must be reissued by the
+///    operator before they expire.
+/// 3. Workload identity federation, if `--azure-client-id`, `--azure-tenant-id` and
+///    `--azure-federated-token-file` are all set, as they are by workload identity on AKS. The
+///    token file is re-read whenever an access token is requested, so rotated tokens are picked
+///    up.
+/// 4. The managed identity of the host, through the instance metadata service. Set
+///    `--azure-client-id` to pick a user assigned identity.
+///
+/// Access tokens from workload and managed identities are cached, and refreshed by the store
+/// before they expire.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AzureCredentialSource {
+    AccessKey,
+    SasToken,
+    WorkloadIdentity {
+        client_id: String,
+        tenant_id: String,
+        federated_token_file: PathBuf,
+    },
+    ManagedIdentity {
+        client_id: Option<String>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::azure_credentials::AzureCredentialSource;
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_credential_source_precedence() {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Azure),
+            azure_client_id: Some("client".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.azure_credential_source(),
+            AzureCredentialSource::ManagedIdentity {
+                client_id: Some("client".to_string())
+            }
+        );
+
+        let config = ObjectStoreConfig {
+            azure_tenant_id: Some("tenant".to_string()),
+            azure_federated_token_file: Some(PathBuf::from("/var/run/secrets/azure/token")),
+            ..config
+        };
+        assert_eq!(
+            config.azure_credential_source(),
+            AzureCredentialSource::WorkloadIdentity {
+                client_id: "client".to_string(),
+                tenant_id: "tenant".to_string(),
+                federated_token_file: PathBuf::from("/var/run/secrets/azure/token"),
+            }
+        );
+
+        let config = ObjectStoreConfig {
+            azure_storage_sas_token: Some("sv=2022-11-02&sig=signature".to_string()),
+            ..config
+        };
+        assert_eq!(
+            config.azure_credential_source(),
+            AzureCredentialSource::SasToken
+        );
+
+        let config = ObjectStoreConfig {
+            azure_storage_access_key: Some("key".to_string()),
+            ..config
+        };
+        assert_eq!(
+            config.azure_credential_source(),
+            AzureCredentialSource::AccessKey
+        );
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Deletes of many objects at once, e.g. of the files of old epochs.
+//!
+//! Every store deletes objects in parallel through [`ObjectStoreDeleteExt::delete_objects`].
+//! [`S3BatchDelete`] sends them to S3 in `DeleteObjects` requests of up to 1000 keys instead,
+//! through the AWS SDK as the S3 client of object_store doesn't make these requests.
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::num::NonZeroUsize;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use futures::StreamExt;
+use indicatif::ProgressBar;
+use object_store::path::Path;
+
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::multipart::s3_sdk_client;
+use crate::object_store::{ObjectStoreConfig, ObjectStoreDeleteExt};
+
+/// Maximum number of keys of an S3 `DeleteObjects` request.
+const S3_MAX_KEYS_PER_DELETE: usize = 1000;
+
+/// Outcome of [`ObjectStoreDeleteExt::delete_objects`]: objects which failed to be deleted
+/// don't fail the whole batch.
+#[derive(Default)]
+pub struct DeleteObjectsResult {
+    pub deleted: Vec<Path>,
+    pub failed: Vec<(Path, anyhow::Error)>,
+}
+
+impl DeleteObjectsResult {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// The number of deleted objects, or an error naming the first failure if any object
+    /// failed to be deleted.
+    pub fn into_result(self) -> Result<usize> {
+        match self.failed.into_iter().next() {
+            None => Ok(self.deleted.len()),
+            Some((path, e)) => Err(e.context(format!(
+                "Failed to delete {path} among other objects, {} were deleted",
+                self.deleted.len()
+            ))),
+        }
+    }
+
+    pub(crate) fn record(&mut self, path: Path, result: ObjectStoreResult<()>) {
+        match result {
+            Ok(()) => self.deleted.push(path),
+            Err(e) => self.failed.push((path, e.into_error())),
+        }
+    }
+
+    pub(crate) fn extend(&mut self, other: DeleteObjectsResult) {
+        self.deleted.extend(other.deleted);
+        self.failed.extend(other.failed);
+    }
+}
+
+impl Debug for DeleteObjectsResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeleteObjectsResult")
+            .field("deleted", &self.deleted.len())
+            .field(
+                "failed",
+                &self
+                    .failed
+                    .iter()
+                    .map(|(path, e)| format!("{path}: {e}"))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Deletes of S3 objects through the AWS SDK, batched in `DeleteObjects` requests.
+pub struct S3BatchDelete {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3BatchDelete {
+    pub fn new(config: &ObjectStoreConfig) -> Result<Self> {
+        let bucket = config
+            .bucket
+            .clone()
+            .ok_or_else(|| anyhow!("No bucket configured for S3 batch deletes"))?;
+        Ok(Self {
+            client: s3_sdk_client(config)?,
+            bucket,
+        })
+    }
+
+    async fn delete_batch(&self, srcs: &[Path]) -> DeleteObjectsResult {
+        let objects = srcs
+            .iter()
+            .map(|src| ObjectIdentifier::builder().key(src.to_string()).build())
+            .collect();
+        let response = self
+            .client
+            .delete_objects()
+            .bucket(&self.bucket)
+            .delete(
+                Delete::builder()
+                    .set_objects(Some(objects))
+                    .quiet(true)
+                    .build(),
+            )
+            .send()
+            .await;
+        let mut result = DeleteObjectsResult::default();
+        match response {
+            Ok(output) => {
+                // Quiet requests only answer with the keys which failed to be deleted.
+                let mut errors: HashMap<&str, String> = output
+                    .errors()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|error| {
+                        let message = format!(
+                            "{}: {}",
+                            error.code().unwrap_or_default(),
+                            error.message().unwrap_or_default()
+                        );
+                        error.key().map(|key| (key, message))
+                    })
+                    .collect();
+                for src in srcs {
+                    match errors.remove(src.as_ref()) {
+                        Some(message) => result.failed.push((
+                            src.clone(),
+                            anyhow!("Failed to delete {src} with error: {message}"),
+                        )),
+                        None => result.deleted.push(src.clone()),
+                    }
+                }
+            }
+            Err(e) => {
+                let e = e.to_string();
+                for src in srcs {
+                    result.failed.push((
+                        src.clone(),
+                        anyhow!("Failed to delete {src} with error: {e}"),
+                    ));
+                }
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl ObjectStoreDeleteExt for S3BatchDelete {
+    async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(src.to_string())
+            .send()
+            .await
+            .map_err(|e| {
+                ObjectStoreError::from(anyhow::Error::new(e))
+                    .context(format!("Failed to delete file: {src}"))
+            })?;
+        Ok(())
+    }
+
+    async fn delete_objects(
+        &self,
+        srcs: &[Path],
+        concurrency: NonZeroUsize,
+        progress_bar: Option<ProgressBar>,
+    ) -> DeleteObjectsResult {
+        let mut batches = futures::stream::iter(srcs.chunks(S3_MAX_KEYS_PER_DELETE))
+            .map(|batch| self.delete_batch(batch))
+            .buffer_unordered(concurrency.get());
+        let mut result = DeleteObjectsResult::default();
+        while let Some(batch_result) = batches.next().await {
+            if let Some(progress_bar) = &progress_bar {
+                progress_bar.inc((batch_result.deleted.len() + batch_result.failed.len()) as u64);
+            }
+            result.extend(batch_result);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::prefix::PrefixedStore;
+    use crate::object_store::util::put;
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreDeleteExt, ObjectStoreType};
+    use bytes::Bytes;
+    use indicatif::ProgressBar;
+    use object_store::path::Path;
+    use std::num::NonZeroUsize;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    pub async fn test_delete_objects() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let store = config.make()?;
+        for i in 0..10 {
+            put(
+                &store,
+                &Path::from(format!("epoch_1/{i}.obj")),
+                Bytes::from("Lorem ipsum"),
+            )
+            .await?;
+        }
+
+        // Deleting a missing object fails, without failing the deletes of the others
+        let prefixed = PrefixedStore::new(config.make_batch_delete()?, "epoch_1");
+        let mut srcs: Vec<Path> = (0..10).map(|i| Path::from(format!("{i}.obj"))).collect();
+        srcs.push(Path::from("missing.obj"));
+        let progress_bar = ProgressBar::hidden();
+        let result = prefixed
+            .delete_objects(
+                &srcs,
+                NonZeroUsize::new(4).unwrap(),
+                Some(progress_bar.clone()),
+            )
+            .await;
+
+        assert_eq!(progress_bar.position(), 11);
+        assert_eq!(result.deleted.len(), 10);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].0, Path::from("missing.obj"));
+        assert!(!result.is_complete());
+        assert!(result.into_result().is_err());
+        for i in 0..10 {
+            assert!(!dir.path().join("epoch_1").join(format!("{i}.obj")).exists());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Content addressed storage, deduplicating identical objects written under different paths.
+//!
+//! [`ContentAddressedStore`] writes every object under the `cas/` prefix of the inner store, named
+//! by the SHA-256 digest of its content, and records the logical path it was written at in a
+//! [`CasManifest`]. Objects whose content is already in the store aren't uploaded again, e.g. the
+//! SST files shared by the db checkpoints of consecutive epochs. Objects are read back through the
+//! manifest they were written with.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{HashFunction, Sha256};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use object_store::path::Path;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::object_store::checksum::ChecksumMismatch;
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::{ObjectStoreGetExt, ObjectStoreHeadExt, ObjectStorePutExt};
+
+/// Prefix of the inner store the content of objects is written under.
+pub const CAS_PREFIX: &str = "cas";
+
+/// Name of the manifest written next to the objects of a directory uploaded to a CAS.
+pub const CAS_MANIFEST_FILENAME: &str = "CAS_MANIFEST";
+
+/// Location in the inner store of the content with the given hex encoded SHA-256 digest.
+pub fn blob_path(digest: &str) -> Path {
+    Path::from(CAS_PREFIX).child(digest)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CasEntry {
+    /// Hex encoded SHA-256 digest of the content of the object
+    pub digest: String,
+    pub size: usize,
+}
+
+/// Logical paths of the objects written to a [`ContentAddressedStore`], and the content they
+/// point to. Written as JSON, so that scripts can download objects without this crate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CasManifest {
+    pub entries: BTreeMap<String, CasEntry>,
+}
+
+impl CasManifest {
+    pub fn get(&self, location: &Path) -> Option<&CasEntry> {
+        self.entries.get(location.as_ref())
+    }
+
+    pub fn to_bytes(&self) -> anyhow::Result<Bytes> {
+        Ok(Bytes::from(serde_json::to_vec(self)?))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+pub struct ContentAddressedStore<S> {
+    inner: S,
+    manifest: RwLock<CasManifest>,
+    uploaded_bytes: AtomicU64,
+    deduplicated_bytes: AtomicU64,
+}
+
+impl<S> ContentAddressedStore<S> {
+    /// Store writing objects to `inner`, with an empty manifest.
+    pub fn new(inner: S) -> Self {
+        Self::with_manifest(inner, CasManifest::default())
+    }
+
+    /// Store reading the objects of `manifest` from `inner`, and adding the ones written to it.
+    pub fn with_manifest(inner: S, manifest: CasManifest) -> Self {
+        Self {
+            inner,
+            manifest: RwLock::new(manifest),
+            uploaded_bytes: AtomicU64::new(0),
+            deduplicated_bytes: AtomicU64::new(0),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn manifest(&self) -> CasManifest {
+        self.manifest.read().clone()
+    }
+
+    /// Bytes of content written to the inner store, and bytes that weren't as the content was
+    /// already there.
+    pub fn upload_stats(&self) -> (u64, u64) {
+        (
+            self.uploaded_bytes.load(Ordering::Relaxed),
+            self.deduplicated_bytes.load(Ordering::Relaxed),
+        )
+    }
+
+    fn entry(&self, location: &Path) -> ObjectStoreResult<CasEntry> {
+        self.manifest.read().get(location).cloned().ok_or_else(|| {
+            ObjectStoreError::NotFound(anyhow!("No object at {location} in the CAS manifest"))
+        })
+    }
+}
+
+impl<S: ObjectStoreGetExt> ContentAddressedStore<S> {
+    /// Store reading the objects of the manifest at `manifest_path` of `inner`.
+    pub async fn open(inner: S, manifest_path: &Path) -> ObjectStoreResult<Self> {
+        let bytes = inner.get_bytes(manifest_path).await?;
+        let manifest = CasManifest::from_bytes(&bytes).map_err(ObjectStoreError::corrupt)?;
+        Ok(Self::with_manifest(inner, manifest))
+    }
+}
+
+impl<S: ObjectStorePutExt> ContentAddressedStore<S> {
+    /// Write the manifest of the objects written so far at `manifest_path` of the inner store.
+    /// Should be written once all of them are, as readers take it as the list of objects to read.
+    pub async fn write_manifest(&self, manifest_path: &Path) -> ObjectStoreResult<()> {
+        let bytes = self.manifest.read().to_bytes()?;
+        self.inner.put_bytes(manifest_path, bytes).await
+    }
+}
+
+impl<S: Display> Display for ContentAddressedStore<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContentAddressedStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetExt> ObjectStoreGetExt for ContentAddressedStore<S> {
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        let entry = self.entry(src)?;
+        let bytes = self.inner.get_bytes(&blob_path(&entry.digest)).await?;
+        let actual = Hex::encode(Sha256::digest(&bytes).digest);
+        if actual != entry.digest {
+            return Err(ObjectStoreError::corrupt(ChecksumMismatch {
+                location: src.clone(),
+                expected: entry.digest,
+                actual,
+            }));
+        }
+        Ok(bytes)
+    }
+
+    /// Streamed objects aren't checked against their digest.
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        let entry = self.entry(src)?;
+        self.inner.get_stream(&blob_path(&entry.digest)).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStorePutExt + ObjectStoreHeadExt> ObjectStorePutExt for ContentAddressedStore<S> {
+    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
+        let entry = CasEntry {
+            digest: Hex::encode(Sha256::digest(&bytes).digest),
+            size: bytes.len(),
+        };
+        let blob = blob_path(&entry.digest);
+        // Concurrent writes of the same content may both upload it, which is harmless.
+        if self.inner.exists(&blob).await? {
+            debug!("Content of {src} already stored at {blob}, skipping upload");
+            self.deduplicated_bytes
+                .fetch_add(entry.size as u64, Ordering::Relaxed);
+        } else {
+            self.inner.put_bytes(&blob, bytes).await?;
+            self.uploaded_bytes
+                .fetch_add(entry.size as u64, Ordering::Relaxed);
+        }
+        self.manifest.write().entries.insert(src.to_string(), entry);
+        Ok(())
+    }
+
+    /// Objects are named by their digest, so the whole stream is read in memory to compute it
+    /// before anything is uploaded.
+    async fn put_stream(
+        &self,
+        src: &Path,
+        mut stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> ObjectStoreResult<()> {
+        let mut bytes = BytesMut::new();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        self.put_bytes(src, bytes.freeze()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::cas::{blob_path, ContentAddressedStore};
+    use crate::object_store::error::ObjectStoreError;
+    use crate::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_deduplication() -> anyhow::Result<()> {
+        let inner: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let store = ContentAddressedStore::new(inner.clone());
+        let sst = Bytes::from_static(b"sst");
+        store
+            .put_bytes(&Path::from("epoch_1/store/000001.sst"), sst.clone())
+            .await?;
+        store
+            .put_bytes(&Path::from("epoch_2/store/000001.sst"), sst.clone())
+            .await?;
+        store
+            .put_bytes(
+                &Path::from("epoch_2/store/000002.sst"),
+                Bytes::from_static(b"new sst"),
+            )
+            .await?;
+        assert_eq!(store.upload_stats(), (10, 3));
+        assert_eq!(
+            inner.list_with_delimiter(None).await?.common_prefixes.len(),
+            1
+        );
+
+        // Objects are read back through the manifest, with their content checked.
+        let manifest_path = Path::from("epoch_2/CAS_MANIFEST");
+        store.write_manifest(&manifest_path).await?;
+        let reader = ContentAddressedStore::open(inner.clone(), &manifest_path).await?;
+        assert_eq!(reader.manifest().entries.len(), 3);
+        assert_eq!(
+            reader
+                .get_bytes(&Path::from("epoch_2/store/000001.sst"))
+                .await?,
+            sst
+        );
+        assert!(reader
+            .get_bytes(&Path::from("epoch_3/store/000001.sst"))
+            .await
+            .unwrap_err()
+            .is_not_found());
+
+        let digest = &reader.manifest().entries["epoch_1/store/000001.sst"].digest;
+        inner
+            .put(&blob_path(digest), Bytes::from_static(b"ss7"))
+            .await?;
+        assert!(matches!(
+            reader
+                .get_bytes(&Path::from("epoch_1/store/000001.sst"))
+                .await,
+            Err(ObjectStoreError::Corrupt(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! End to end integrity checks of objects, on top of any object store.
+//!
+//! [`ChecksummedStore`] writes the SHA-256 digest of every object it puts to a sidecar object
+//! next to it, at the object's path with a `.sha256` suffix, and checks objects it gets against
+//! their sidecar. Objects written without one, e.g. before checksums were introduced, are read
+//! without being checked.
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{Digest, HashFunction, Sha256};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path;
+use parking_lot::Mutex;
+use tracing::debug;
+
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::{ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStorePutExt};
+
+pub const CHECKSUM_SUFFIX: &str = "sha256";
+
+/// Path of the sidecar holding the checksum of the object at `location`.
+pub fn checksum_path(location: &Path) -> Path {
+    Path::from(format!("{location}.{CHECKSUM_SUFFIX}"))
+}
+
+pub fn is_checksum_path(location: &Path) -> bool {
+    location.extension() == Some(CHECKSUM_SUFFIX)
+}
+
+/// Returned when the content of an object doesn't match the checksum written along with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    pub location: Path,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Checksum mismatch for {}: expected sha256 {}, got {}",
+            self.location, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+pub struct ChecksummedStore<S> {
+    inner: S,
+}
+
+impl<S> ChecksummedStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: ObjectStoreGetExt> ChecksummedStore<S> {
+    /// The checksum written along with the object at `location`, if any.
+    async fn expected_checksum(&self, location: &Path) -> ObjectStoreResult<Option<String>> {
+        match self.inner.get_bytes(&checksum_path(location)).await {
+            Ok(bytes) => Ok(Some(
+                String::from_utf8(bytes.to_vec())
+                    .map_err(ObjectStoreError::corrupt)?
+                    .trim()
+                    .to_string(),
+            )),
+            Err(e) if e.is_not_found() => {
+                debug!("No checksum for {location}, skipping verification");
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn hex_digest(digest: Digest<32>) -> String {
+    Hex::encode(digest.digest)
+}
+
+fn verify(location: &Path, expected: String, actual: String) -> ObjectStoreResult<()> {
+    if expected != actual {
+        return Err(ObjectStoreError::corrupt(ChecksumMismatch {
+            location: location.clone(),
+            expected,
+            actual,
+        }));
+    }
+    Ok(())
+}
+
+impl<S: Display> Display for ChecksummedStore<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ChecksummedStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetExt> ObjectStoreGetExt for ChecksummedStore<S> {
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        let expected = self.expected_checksum(src).await?;
+        let bytes = self.inner.get_bytes(src).await?;
+        if let Some(expected) = expected {
+            verify(src, expected, hex_digest(Sha256::digest(&bytes)))?;
+        }
+        Ok(bytes)
+    }
+
+    /// The checksum is verified once the stream is exhausted, which then ends with an error on
+    /// mismatch, so consumers must not trust any data read before the end of the stream.
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        let expected = self.expected_checksum(src).await?;
+        let stream = self.inner.get_stream(src).await?;
+        let Some(expected) = expected else {
+            return Ok(stream);
+        };
+        let hasher = Arc::new(Mutex::new(Sha256::default()));
+        let final_hasher = hasher.clone();
+        let src = src.clone();
+        let verification = futures::stream::once(async move {
+            let actual = hex_digest(std::mem::take(&mut *final_hasher.lock()).finalize());
+            verify(&src, expected, actual)
+        })
+        .filter_map(|result| async move { result.err().map(Err) });
+        Ok(stream
+            .inspect_ok(move |chunk| hasher.lock().update(chunk))
+            .chain(verification)
+            .boxed())
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStorePutExt> ObjectStorePutExt for ChecksummedStore<S> {
+    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
+        let checksum = hex_digest(Sha256::digest(&bytes));
+        // The object is written first, so that a checksum is never found without its object.
+        self.inner.put_bytes(src, bytes).await?;
+        self.inner
+            .put_bytes(&checksum_path(src), Bytes::from(checksum))
+            .await
+    }
+
+    async fn put_stream(
+        &self,
+        src: &Path,
+        stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> ObjectStoreResult<()> {
+        let hasher = Arc::new(Mutex::new(Sha256::default()));
+        let chunk_hasher = hasher.clone();
+        let stream = stream
+            .inspect_ok(move |chunk| chunk_hasher.lock().update(chunk))
+            .boxed();
+        self.inner.put_stream(src, stream).await?;
+        let checksum = hex_digest(std::mem::take(&mut *hasher.lock()).finalize());
+        self.inner
+            .put_bytes(&checksum_path(src), Bytes::from(checksum))
+            .await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreDeleteExt> ObjectStoreDeleteExt for ChecksummedStore<S> {
+    async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()> {
+        match self.inner.delete_object(&checksum_path(src)).await {
+            Err(e) if !e.is_not_found() => return Err(e),
+            _ => {}
+        }
+        self.inner.delete_object(src).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::checksum::{checksum_path, ChecksumMismatch, ChecksummedStore};
+    use crate::object_store::error::ObjectStoreError;
+    use crate::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_checksum_mismatch() -> anyhow::Result<()> {
+        let inner: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let store = ChecksummedStore::new(inner.clone());
+        let path = Path::from("epoch_0/store/000001.sst");
+
+        store.put_bytes(&path, Bytes::from_static(b"sst")).await?;
+        assert_eq!(store.get_bytes(&path).await?, Bytes::from_static(b"sst"));
+
+        // Corrupt the object behind the store's back.
+        inner.put(&path, Bytes::from_static(b"ss7")).await?;
+        let error = store.get_bytes(&path).await.unwrap_err();
+        assert!(matches!(error, ObjectStoreError::Corrupt(_)));
+        let mismatch = error.error().downcast_ref::<ChecksumMismatch>().unwrap();
+        assert_eq!(mismatch.location, path);
+        let error = store
+            .get_stream(&path)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_err();
+        assert!(error.error().downcast_ref::<ChecksumMismatch>().is_some());
+
+        // Objects without a checksum are read unchecked.
+        inner.delete(&checksum_path(&path)).await?;
+        assert_eq!(store.get_bytes(&path).await?, Bytes::from_static(b"ss7"));
+        Ok(())
+    }
+}
@@ -0,0 +1,590 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Circuit breaker failing requests to an unavailable store fast, rather than retrying each of
+//! them against the store, e.g. during a regional outage of the backend.
+//!
+//! The circuit opens after a number of consecutive requests fail with backend errors, i.e. errors
+//! other than rejections of the request like missing objects or bad credentials. While it is
+//! open, requests fail with [`CircuitOpen`] without reaching the store, and are not retried by
+//! [`RetryingObjectStore`](crate::object_store::retry::RetryingObjectStore). Once per probe
+//! interval, one request is let through as a probe: the circuit closes if it succeeds, and stays
+//! open for another interval otherwise.
+//!
+//! Only whole requests count: failures reading the body of a `get` or writing a multipart upload
+//! don't open the circuit.
+
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use object_store::path::Path;
+use object_store::{
+    Error, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, Result,
+};
+use parking_lot::Mutex;
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_vec_with_registry, IntCounterVec,
+    IntGaugeVec, Registry,
+};
+use tokio::io::AsyncWrite;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::object_store::error::ObjectStoreError;
+use crate::object_store::retry::is_client_error;
+
+pub struct CircuitBreakerMetrics {
+    pub object_store_circuit_state: IntGaugeVec,
+    pub object_store_circuit_rejections: IntCounterVec,
+}
+
+impl CircuitBreakerMetrics {
+    pub fn new(registry: &Registry) -> Arc<Self> {
+        Arc::new(Self {
+            object_store_circuit_state: register_int_gauge_vec_with_registry!(
+                "object_store_circuit_state",
+                "State of the circuit breaker of a store: 0 if closed, 1 if open, 2 if probing",
+                &["store"],
+                registry,
+            )
+            .unwrap(),
+            object_store_circuit_rejections: register_int_counter_vec_with_registry!(
+                "object_store_circuit_rejections",
+                "Number of requests failed fast while the circuit breaker of a store was open",
+                &["store"],
+                registry,
+            )
+            .unwrap(),
+        })
+    }
+
+    pub fn new_for_tests() -> Arc<Self> {
+        Self::new(&Registry::new())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    /// Open, with a probe request in flight
+    HalfOpen,
+}
+
+impl CircuitState {
+    fn metric_value(&self) -> i64 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::Open => 1,
+            CircuitState::HalfOpen => 2,
+        }
+    }
+}
+
+/// Returned, as the source of a generic [`object_store::Error`], for requests failed fast while
+/// the circuit is open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitOpen {
+    pub store: String,
+    /// Time until the next probe of the store
+    pub retry_in: Duration,
+}
+
+impl Display for CircuitOpen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Circuit breaker of {} is open after repeated failures, next probe in {:?}",
+            self.store, self.retry_in
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// Whether `error` is a request failed fast by an open circuit.
+pub fn is_circuit_open(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<ObjectStoreError>() {
+        return is_circuit_open(error.error());
+    }
+    match error.downcast_ref::<Error>() {
+        Some(error) => is_circuit_open_error(error),
+        None => error.downcast_ref::<CircuitOpen>().is_some(),
+    }
+}
+
+pub(crate) fn is_circuit_open_error(error: &Error) -> bool {
+    matches!(error, Error::Generic { source, .. } if source.is::<CircuitOpen>())
+}
+
+/// Whether a request failing with `error` counts as a failure of the store.
+fn is_backend_failure(error: &Error) -> bool {
+    match error {
+        Error::Generic { source, .. } => {
+            !source.is::<CircuitOpen>() && !is_client_error(&source.to_string())
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failed requests opening the circuit
+    pub failure_threshold: usize,
+    /// Time between probes of the store while the circuit is open
+    pub probe_interval: Duration,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: usize,
+    /// When the circuit opened, or its last probe failed
+    opened_at: Option<Instant>,
+    probing: bool,
+}
+
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    name: String,
+    state: Mutex<BreakerState>,
+    metrics: Option<Arc<CircuitBreakerMetrics>>,
+}
+
+impl CircuitBreaker {
+    /// Breaker of the store `name`, reporting its state to `metrics` under that name, if any.
+    pub fn new(
+        name: impl Into<String>,
+        config: CircuitBreakerConfig,
+        metrics: Option<Arc<CircuitBreakerMetrics>>,
+    ) -> Self {
+        let breaker = Self {
+            config,
+            name: name.into(),
+            state: Mutex::new(BreakerState::default()),
+            metrics,
+        };
+        breaker.report(CircuitState::Closed);
+        breaker
+    }
+
+    pub fn state(&self) -> CircuitState {
+        Self::state_of(&self.state.lock())
+    }
+
+    fn state_of(state: &BreakerState) -> CircuitState {
+        match (state.opened_at, state.probing) {
+            (None, _) => CircuitState::Closed,
+            (Some(_), false) => CircuitState::Open,
+            (Some(_), true) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Let a request through, as a probe if the circuit is open and due for one, or fail it with
+    /// [`CircuitOpen`].
+    fn admit(&self) -> std::result::Result<CircuitPermit<'_>, CircuitOpen> {
+        let mut state = self.state.lock();
+        let Some(opened_at) = state.opened_at else {
+            return Ok(CircuitPermit {
+                breaker: self,
+                probe: false,
+            });
+        };
+        let next_probe = opened_at + self.config.probe_interval;
+        if !state.probing && Instant::now() >= next_probe {
+            state.probing = true;
+            drop(state);
+            self.report(CircuitState::HalfOpen);
+            return Ok(CircuitPermit {
+                breaker: self,
+                probe: true,
+            });
+        }
+        drop(state);
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .object_store_circuit_rejections
+                .with_label_values(&[&self.name])
+                .inc();
+        }
+        Err(CircuitOpen {
+            store: self.name.clone(),
+            retry_in: next_probe.saturating_duration_since(Instant::now()),
+        })
+    }
+
+    fn record(&self, failed: bool, probe: bool) {
+        let mut state = self.state.lock();
+        let previous = Self::state_of(&state);
+        if !failed {
+            *state = BreakerState::default();
+        } else {
+            state.consecutive_failures += 1;
+            if probe {
+                state.opened_at = Some(Instant::now());
+                state.probing = false;
+            } else if state.opened_at.is_none()
+                && state.consecutive_failures >= self.config.failure_threshold
+            {
+                state.opened_at = Some(Instant::now());
+            }
+        }
+        let current = Self::state_of(&state);
+        drop(state);
+        if current == previous {
+            return;
+        }
+        match current {
+            CircuitState::Closed => info!("Circuit breaker of {} closed", self.name),
+            CircuitState::Open if previous == CircuitState::Closed => warn!(
+                "Circuit breaker of {} opened after {} consecutive failures, probing every {:?}",
+                self.name, self.config.failure_threshold, self.config.probe_interval
+            ),
+            _ => {}
+        }
+        self.report(current);
+    }
+
+    /// Let the next request through as a probe, as the probe in flight was dropped before it
+    /// completed.
+    fn abandon_probe(&self) {
+        let mut state = self.state.lock();
+        if state.probing {
+            state.probing = false;
+            drop(state);
+            self.report(CircuitState::Open);
+        }
+    }
+
+    fn report(&self, state: CircuitState) {
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .object_store_circuit_state
+                .with_label_values(&[&self.name])
+                .set(state.metric_value());
+        }
+    }
+}
+
+/// A request let through by a [`CircuitBreaker`], whose outcome is recorded once it completes.
+struct CircuitPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl CircuitPermit<'_> {
+    fn record<T>(mut self, result: &Result<T>) {
+        let failed = matches!(result, Err(e) if is_backend_failure(e));
+        self.breaker.record(failed, self.probe);
+        self.probe = false;
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.abandon_probe();
+        }
+    }
+}
+
+/// Store failing requests fast while its [`CircuitBreaker`] is open.
+pub struct CircuitBreakerObjectStore<T> {
+    inner: T,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl<T: ObjectStore> CircuitBreakerObjectStore<T> {
+    pub fn new(inner: T, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { inner, breaker }
+    }
+
+    async fn guard<R, Fut>(&self, request: Fut) -> Result<R>
+    where
+        Fut: Future<Output = Result<R>>,
+    {
+        let permit = self.breaker.admit().map_err(|e| Error::Generic {
+            store: "CircuitBreaker",
+            source: Box::new(e),
+        })?;
+        let result = request.await;
+        permit.record(&result);
+        result
+    }
+}
+
+impl<T: ObjectStore> std::fmt::Debug for CircuitBreakerObjectStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreakerObjectStore")
+            .field("inner", &self.inner)
+            .field("state", &self.breaker.state())
+            .finish()
+    }
+}
+
+impl<T: ObjectStore> Display for CircuitBreakerObjectStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CircuitBreakerObjectStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for CircuitBreakerObjectStore<T> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        self.guard(self.inner.put(location, bytes)).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.guard(self.inner.put_multipart(location)).await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        // Always let aborts through, so that uploads aren't left behind once the store recovers
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn append(&self, location: &Path) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.guard(self.inner.append(location)).await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        self.guard(self.inner.get(location)).await
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        self.guard(self.inner.get_opts(location, options)).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.guard(self.inner.get_range(location, range)).await
+    }
+
+    async fn get_ranges(&self, location: &Path, ranges: &[Range<usize>]) -> Result<Vec<Bytes>> {
+        self.guard(self.inner.get_ranges(location, ranges)).await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.guard(self.inner.head(location)).await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.guard(self.inner.delete(location)).await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        self.guard(self.inner.list(prefix)).await
+    }
+
+    async fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        self.guard(self.inner.list_with_offset(prefix, offset))
+            .await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        self.guard(self.inner.list_with_delimiter(prefix)).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.guard(self.inner.copy(from, to)).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.guard(self.inner.rename(from, to)).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.guard(self.inner.copy_if_not_exists(from, to)).await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.guard(self.inner.rename_if_not_exists(from, to)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::circuit::{
+        is_circuit_open_error, CircuitBreaker, CircuitBreakerConfig, CircuitBreakerMetrics,
+        CircuitBreakerObjectStore, CircuitState,
+    };
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::{Error, ObjectStore};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Fails every request with a server error while `failing` is set.
+    #[derive(Debug)]
+    struct FlakyStore {
+        inner: InMemory,
+        failing: Arc<AtomicBool>,
+    }
+
+    impl std::fmt::Display for FlakyStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "FlakyStore")
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ObjectStore for FlakyStore {
+        async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+            if self.failing.load(Ordering::Relaxed) {
+                return Err(Error::Generic {
+                    store: "Flaky",
+                    source: "server error (503 Service Unavailable)".into(),
+                });
+            }
+            self.inner.put(location, bytes).await
+        }
+
+        async fn put_multipart(
+            &self,
+            location: &Path,
+        ) -> object_store::Result<(
+            object_store::MultipartId,
+            Box<dyn tokio::io::AsyncWrite + Unpin + Send>,
+        )> {
+            self.inner.put_multipart(location).await
+        }
+
+        async fn abort_multipart(
+            &self,
+            location: &Path,
+            multipart_id: &object_store::MultipartId,
+        ) -> object_store::Result<()> {
+            self.inner.abort_multipart(location, multipart_id).await
+        }
+
+        async fn get_opts(
+            &self,
+            location: &Path,
+            options: object_store::GetOptions,
+        ) -> object_store::Result<object_store::GetResult> {
+            self.inner.get_opts(location, options).await
+        }
+
+        async fn head(&self, location: &Path) -> object_store::Result<object_store::ObjectMeta> {
+            self.inner.head(location).await
+        }
+
+        async fn delete(&self, location: &Path) -> object_store::Result<()> {
+            self.inner.delete(location).await
+        }
+
+        async fn list(
+            &self,
+            prefix: Option<&Path>,
+        ) -> object_store::Result<
+            futures::stream::BoxStream<'_, object_store::Result<object_store::ObjectMeta>>,
+        > {
+            self.inner.list(prefix).await
+        }
+
+        async fn list_with_delimiter(
+            &self,
+            prefix: Option<&Path>,
+        ) -> object_store::Result<object_store::ListResult> {
+            self.inner.list_with_delimiter(prefix).await
+        }
+
+        async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+            self.inner.copy(from, to).await
+        }
+
+        async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+            self.inner.copy_if_not_exists(from, to).await
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn test_circuit_opens_and_recovers() -> anyhow::Result<()> {
+        let failing = Arc::new(AtomicBool::new(true));
+        let metrics = CircuitBreakerMetrics::new_for_tests();
+        let breaker = Arc::new(CircuitBreaker::new(
+            "flaky",
+            CircuitBreakerConfig {
+                failure_threshold: 3,
+                probe_interval: Duration::from_secs(30),
+            },
+            Some(metrics.clone()),
+        ));
+        let store = CircuitBreakerObjectStore::new(
+            FlakyStore {
+                inner: InMemory::new(),
+                failing: failing.clone(),
+            },
+            breaker.clone(),
+        );
+        let path = Path::from("archive/1.chk");
+
+        // Missing objects don't count as failures
+        assert!(matches!(
+            store.head(&path).await,
+            Err(Error::NotFound { .. })
+        ));
+        for _ in 0..3 {
+            let e = store
+                .put(&path, Bytes::from("checkpoint"))
+                .await
+                .unwrap_err();
+            assert!(!is_circuit_open_error(&e));
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // Requests fail fast until the next probe, even once the store recovers
+        failing.store(false, Ordering::Relaxed);
+        let e = store.head(&path).await.unwrap_err();
+        assert!(is_circuit_open_error(&e));
+        let gauge = metrics
+            .object_store_circuit_state
+            .with_label_values(&["flaky"]);
+        assert_eq!(gauge.get(), 1);
+
+        // A failed probe keeps the circuit open for another interval
+        failing.store(true, Ordering::Relaxed);
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let e = store
+            .put(&path, Bytes::from("checkpoint"))
+            .await
+            .unwrap_err();
+        assert!(!is_circuit_open_error(&e));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(is_circuit_open_error(
+            &store
+                .put(&path, Bytes::from("checkpoint"))
+                .await
+                .unwrap_err()
+        ));
+
+        // A successful probe closes it
+        failing.store(false, Ordering::Relaxed);
+        tokio::time::advance(Duration::from_secs(30)).await;
+        store.put(&path, Bytes::from("checkpoint")).await?;
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(gauge.get(), 0);
+        store.head(&path).await?;
+        assert!(
+            metrics
+                .object_store_circuit_rejections
+                .with_label_values(&["flaky"])
+                .get()
+                >= 2
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transparent zstd compression of objects, on top of any object store.
+//!
+//! [`CompressingObjectStore`] compresses every object it puts, and decompresses objects it gets
+//! if they start with the zstd frame magic number. Objects written uncompressed, e.g. before
+//! compression was enabled on a prefix, are returned as they are, so that compressed and
+//! uncompressed objects can be mixed under the same prefix.
+//!
+//! Small objects, like the checkpoint blobs served by the key-value store, compress poorly on
+//! their own as they have little repetition within them. A [`ZstdDictionary`] trained over
+//! representative objects holds the structure they share, so that objects compressed with it
+//! only encode what is specific to them. Frames carry the ID of the dictionary they were
+//! compressed with, which readers need to hold to decompress them.
+
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path;
+
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::{ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStorePutExt};
+
+/// Magic number every zstd frame starts with.
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+pub fn is_zstd_compressed(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ZSTD_MAGIC)
+}
+
+/// A zstd dictionary, as written by `zstd --train` or [`ZstdDictionary::train`].
+#[derive(Clone, Debug)]
+pub struct ZstdDictionary {
+    id: u32,
+    bytes: Bytes,
+}
+
+impl ZstdDictionary {
+    pub fn new(bytes: Bytes) -> Result<Self> {
+        let id = zstd::zstd_safe::get_dict_id_from_dict(&bytes);
+        if id == 0 {
+            return Err(anyhow!("Not a zstd dictionary"));
+        }
+        Ok(Self { id, bytes })
+    }
+
+    pub fn from_file(path: &std::path::Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read zstd dictionary {}", path.display()))?;
+        Self::new(bytes.into())
+    }
+
+    /// Train a dictionary of at most `max_size` bytes over `samples`. Samples should add up to
+    /// about a hundred times the size of the dictionary.
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Self> {
+        let bytes = zstd::dict::from_samples(samples, max_size)
+            .context("Failed to train zstd dictionary")?;
+        Self::new(bytes.into())
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn compress(&self, bytes: &[u8], level: i32) -> Result<Vec<u8>> {
+        zstd::bulk::Compressor::with_dictionary(level, &self.bytes)
+            .and_then(|mut compressor| compressor.compress(bytes))
+            .context("Failed to compress with zstd dictionary")
+    }
+}
+
+/// Decompress a zstd frame, with `dictionary` if the frame was compressed with one.
+pub fn decompress_frame(bytes: &[u8], dictionary: Option<&ZstdDictionary>) -> Result<Vec<u8>> {
+    let mut decompressed = vec![];
+    match zstd::zstd_safe::get_dict_id_from_frame(bytes) {
+        0 => zstd::stream::Decoder::new(bytes)?.read_to_end(&mut decompressed)?,
+        id if dictionary.map(ZstdDictionary::id) == Some(id) => {
+            zstd::stream::Decoder::with_dictionary(bytes, dictionary.unwrap().as_bytes())?
+                .read_to_end(&mut decompressed)?
+        }
+        id => return Err(anyhow!("Compressed with unknown zstd dictionary {id}")),
+    };
+    Ok(decompressed)
+}
+
+pub struct CompressingObjectStore<S> {
+    inner: S,
+    level: i32,
+    dictionary: Option<Arc<ZstdDictionary>>,
+}
+
+impl<S> CompressingObjectStore<S> {
+    /// Objects are compressed with the given zstd `level`, from 1 to 22, 0 meaning the zstd
+    /// default.
+    pub fn new(inner: S, level: i32) -> Self {
+        Self {
+            inner,
+            level,
+            dictionary: None,
+        }
+    }
+
+    /// Compress objects with `dictionary`. Objects compressed without it, or uncompressed, can
+    /// still be read.
+    pub fn with_dictionary(mut self, dictionary: Arc<ZstdDictionary>) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Display> Display for CompressingObjectStore<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CompressingObjectStore({})", self.inner)
+    }
+}
+
+async fn compress(
+    bytes: Bytes,
+    level: i32,
+    dictionary: Option<Arc<ZstdDictionary>>,
+) -> Result<Bytes> {
+    tokio::task::spawn_blocking(move || match dictionary {
+        Some(dictionary) => dictionary.compress(&bytes, level),
+        None => zstd::bulk::compress(&bytes, level).context("Failed to compress object"),
+    })
+    .await?
+    .map(Bytes::from)
+}
+
+async fn decompress(
+    location: &Path,
+    bytes: Bytes,
+    dictionary: Option<Arc<ZstdDictionary>>,
+) -> ObjectStoreResult<Bytes> {
+    if !is_zstd_compressed(&bytes) {
+        return Ok(bytes);
+    }
+    tokio::task::spawn_blocking(move || decompress_frame(&bytes, dictionary.as_deref()))
+        .await
+        .map_err(anyhow::Error::from)?
+        .map(Bytes::from)
+        .map_err(|e| {
+            ObjectStoreError::corrupt(e.context(format!("Failed to decompress {location}")))
+        })
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetExt> ObjectStoreGetExt for CompressingObjectStore<S> {
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        let bytes = self.inner.get_bytes(src).await?;
+        decompress(src, bytes, self.dictionary.clone()).await
+    }
+
+    /// Objects are read in full to be decompressed, before being streamed from memory.
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        let bytes = self.get_bytes(src).await?;
+        Ok(futures::stream::once(async move { Ok(bytes) }).boxed())
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStorePutExt> ObjectStorePutExt for CompressingObjectStore<S> {
+    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
+        let compressed = compress(bytes, self.level, self.dictionary.clone()).await?;
+        self.inner.put_bytes(src, compressed).await
+    }
+
+    /// The stream is read in full to be compressed, before being written.
+    async fn put_stream(
+        &self,
+        src: &Path,
+        stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> ObjectStoreResult<()> {
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        self.put_bytes(src, Bytes::from(chunks.concat())).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreDeleteExt> ObjectStoreDeleteExt for CompressingObjectStore<S> {
+    async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()> {
+        self.inner.delete_object(src).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::compression::{
+        decompress_frame, is_zstd_compressed, CompressingObjectStore, ZstdDictionary,
+    };
+    use crate::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_mixed_compressed_objects() -> anyhow::Result<()> {
+        let inner: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let store = CompressingObjectStore::new(inner.clone(), 3);
+        let data = Bytes::from(vec![7u8; 64 * 1024]);
+
+        let compressed = Path::from("epoch_1/1.chk");
+        store.put_bytes(&compressed, data.clone()).await?;
+        let stored = inner.get_bytes(&compressed).await?;
+        assert!(is_zstd_compressed(&stored));
+        assert!(stored.len() < data.len());
+        assert_eq!(store.get_bytes(&compressed).await?, data);
+
+        // Objects written before compression was enabled are read as they are.
+        let uncompressed = Path::from("epoch_0/1.chk");
+        inner.put_bytes(&uncompressed, data.clone()).await?;
+        assert_eq!(store.get_bytes(&uncompressed).await?, data);
+        Ok(())
+    }
+
+    /// Checkpoint-like samples: a shared layout with a few fields specific to each of them.
+    fn checkpoint_samples(count: u64) -> Vec<Vec<u8>> {
+        (0..count)
+            .map(|i| {
+                let mut sample = b"{\"epoch\":17,\"sequence_number\":".to_vec();
+                sample.extend_from_slice(i.to_string().as_bytes());
+                sample.extend_from_slice(b",\"network_total_transactions\":");
+                sample.extend_from_slice((i * 7919 % 100_003).to_string().as_bytes());
+                sample.extend_from_slice(b",\"previous_digest\":\"");
+                sample.extend_from_slice(&i.wrapping_mul(0x9e3779b97f4a7c15).to_be_bytes());
+                sample.extend_from_slice(
+                    b"\",\"end_of_epoch_data\":null,\"version_specific_data\":[]}",
+                );
+                sample
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_dictionary_compression() -> anyhow::Result<()> {
+        let dictionary = Arc::new(ZstdDictionary::train(&checkpoint_samples(5000), 4096)?);
+
+        let held_out = checkpoint_samples(5100).split_off(5000);
+        let plain: usize = held_out
+            .iter()
+            .map(|s| zstd::bulk::compress(s, 3).unwrap().len())
+            .sum();
+        let with_dictionary: usize = held_out
+            .iter()
+            .map(|s| dictionary.compress(s, 3).unwrap().len())
+            .sum();
+        assert!(with_dictionary < plain);
+
+        let inner: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let plain_store = CompressingObjectStore::new(inner.clone(), 3);
+        let store = CompressingObjectStore::new(inner.clone(), 3).with_dictionary(dictionary);
+        let data = Bytes::from(held_out[0].clone());
+        let path = Path::from("cs/1");
+        store.put_bytes(&path, data.clone()).await?;
+        assert_eq!(store.get_bytes(&path).await?, data);
+        // Frames name the dictionary they need.
+        assert!(plain_store.get_bytes(&path).await.is_err());
+        let stored = inner.get_bytes(&path).await?;
+        assert!(decompress_frame(&stored, None).is_err());
+
+        // Objects compressed without the dictionary are still read.
+        plain_store.put_bytes(&path, data.clone()).await?;
+        assert_eq!(store.get_bytes(&path).await?, data);
+        Ok(())
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Separate limits of the concurrent reads and writes of a store, so that a burst of reads, e.g.
+//! of a backfill, can't take every connection of the store and starve its writes.
+//!
+//! Requests hold a permit of their operation's limit until their response is consumed, i.e. while
+//! streaming the body of a get, the results of a list or the writes of a multipart upload. Lists
+//! share the read limit and deletes the write limit, unless they have their own.
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use object_store::path::Path;
+use object_store::{
+    GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta, ObjectStore,
+};
+use tokio::io::AsyncWrite;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Gets and heads
+    Read,
+    List,
+    /// Puts, multipart uploads, appends, copies and renames
+    Write,
+    Delete,
+}
+
+/// Concurrency limits of the operations of a store, `None` if unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyLimits {
+    read: Option<Arc<Semaphore>>,
+    list: Option<Arc<Semaphore>>,
+    write: Option<Arc<Semaphore>>,
+    delete: Option<Arc<Semaphore>>,
+}
+
+impl ConcurrencyLimits {
+    /// Limits of `read` and `write` requests. Lists count against the read limit and deletes
+    /// against the write limit, unless they are limited separately.
+    pub fn new(
+        read: Option<usize>,
+        write: Option<usize>,
+        list: Option<usize>,
+        delete: Option<usize>,
+    ) -> Self {
+        let semaphore = |limit: usize| Arc::new(Semaphore::new(limit));
+        let read = read.map(semaphore);
+        let write = write.map(semaphore);
+        ConcurrencyLimits {
+            list: list.map(semaphore).or_else(|| read.clone()),
+            delete: delete.map(semaphore).or_else(|| write.clone()),
+            read,
+            write,
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.read.is_none() && self.list.is_none() && self.write.is_none() && self.delete.is_none()
+    }
+
+    /// Wait for a permit of the limit of `operation`, if any.
+    pub async fn acquire(&self, operation: Operation) -> Option<OwnedSemaphorePermit> {
+        let semaphore = match operation {
+            Operation::Read => &self.read,
+            Operation::List => &self.list,
+            Operation::Write => &self.write,
+            Operation::Delete => &self.delete,
+        };
+        match semaphore {
+            // Semaphores of the limits are never closed
+            Some(semaphore) => Some(semaphore.clone().acquire_owned().await.unwrap()),
+            None => None,
+        }
+    }
+}
+
+/// Store limiting its concurrent requests of each operation to its [`ConcurrencyLimits`].
+#[derive(Debug)]
+pub struct ConcurrencyLimitedObjectStore<T> {
+    inner: T,
+    limits: ConcurrencyLimits,
+}
+
+impl<T: ObjectStore> ConcurrencyLimitedObjectStore<T> {
+    pub fn new(inner: T, limits: ConcurrencyLimits) -> Self {
+        Self { inner, limits }
+    }
+
+    fn limit_writer(
+        permit: Option<OwnedSemaphorePermit>,
+        writer: Box<dyn AsyncWrite + Unpin + Send>,
+    ) -> Box<dyn AsyncWrite + Unpin + Send> {
+        match permit {
+            Some(permit) => Box::new(PermitWriter {
+                inner: writer,
+                _permit: permit,
+            }),
+            None => writer,
+        }
+    }
+
+    fn limit_get(permit: Option<OwnedSemaphorePermit>, result: GetResult) -> GetResult {
+        let Some(permit) = permit else {
+            return result;
+        };
+        let payload = match result.payload {
+            GetResultPayload::Stream(stream) => {
+                GetResultPayload::Stream(hold_permit(stream, permit))
+            }
+            // Files are read locally, without holding a connection
+            payload => payload,
+        };
+        GetResult {
+            payload,
+            meta: result.meta,
+            range: result.range,
+        }
+    }
+}
+
+/// `stream`, holding `permit` until it is dropped.
+fn hold_permit<'a, T: Send + 'a>(
+    stream: BoxStream<'a, T>,
+    permit: OwnedSemaphorePermit,
+) -> BoxStream<'a, T> {
+    stream
+        .map(move |item| {
+            let _permit = &permit;
+            item
+        })
+        .boxed()
+}
+
+impl<T: ObjectStore> Display for ConcurrencyLimitedObjectStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConcurrencyLimitedObjectStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for ConcurrencyLimitedObjectStore<T> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        let _permit = self.limits.acquire(Operation::Write).await;
+        self.inner.put(location, bytes).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let permit = self.limits.acquire(Operation::Write).await;
+        let (multipart_id, writer) = self.inner.put_multipart(location).await?;
+        Ok((multipart_id, Self::limit_writer(permit, writer)))
+    }
+
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        let _permit = self.limits.acquire(Operation::Write).await;
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn append(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        let permit = self.limits.acquire(Operation::Write).await;
+        let writer = self.inner.append(location).await?;
+        Ok(Self::limit_writer(permit, writer))
+    }
+
+    async fn get(&self, location: &Path) -> object_store::Result<GetResult> {
+        let permit = self.limits.acquire(Operation::Read).await;
+        let result = self.inner.get(location).await?;
+        Ok(Self::limit_get(permit, result))
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let permit = self.limits.acquire(Operation::Read).await;
+        let result = self.inner.get_opts(location, options).await?;
+        Ok(Self::limit_get(permit, result))
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        let _permit = self.limits.acquire(Operation::Read).await;
+        self.inner.get_range(location, range).await
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        let _permit = self.limits.acquire(Operation::Read).await;
+        self.inner.get_ranges(location, ranges).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        let _permit = self.limits.acquire(Operation::Read).await;
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        let _permit = self.limits.acquire(Operation::Delete).await;
+        self.inner.delete(location).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        let permit = self.limits.acquire(Operation::List).await;
+        let stream = self.inner.list(prefix).await?;
+        Ok(match permit {
+            Some(permit) => hold_permit(stream, permit),
+            None => stream,
+        })
+    }
+
+    async fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        let permit = self.limits.acquire(Operation::List).await;
+        let stream = self.inner.list_with_offset(prefix, offset).await?;
+        Ok(match permit {
+            Some(permit) => hold_permit(stream, permit),
+            None => stream,
+        })
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let _permit = self.limits.acquire(Operation::List).await;
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        let _permit = self.limits.acquire(Operation::Write).await;
+        self.inner.copy(from, to).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        let _permit = self.limits.acquire(Operation::Write).await;
+        self.inner.rename(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        let _permit = self.limits.acquire(Operation::Write).await;
+        self.inner.copy_if_not_exists(from, to).await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        let _permit = self.limits.acquire(Operation::Write).await;
+        self.inner.rename_if_not_exists(from, to).await
+    }
+}
+
+/// Writer holding a permit until the upload is done with.
+struct PermitWriter {
+    inner: Box<dyn AsyncWrite + Unpin + Send>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl AsyncWrite for PermitWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::concurrency::{ConcurrencyLimitedObjectStore, ConcurrencyLimits};
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::ObjectStore;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn test_reads_and_writes_limited_separately() -> anyhow::Result<()> {
+        let store = ConcurrencyLimitedObjectStore::new(
+            InMemory::new(),
+            ConcurrencyLimits::new(Some(1), Some(1), None, None),
+        );
+        let path = Path::from("archive/1.chk");
+        store.put(&path, Bytes::from("checkpoint")).await?;
+
+        // An ongoing read holds the only read permit, also used by lists, but not writes
+        let reading = store.get(&path).await?;
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), store.head(&path))
+                .await
+                .is_err()
+        );
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), store.list_with_delimiter(None))
+                .await
+                .is_err()
+        );
+        store.put(&path, Bytes::from("checkpoint 2")).await?;
+
+        // An ongoing upload holds the only write permit, also used by deletes
+        let (_id, mut writer) = store.put_multipart(&Path::from("archive/2.chk")).await?;
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), store.delete(&path))
+                .await
+                .is_err()
+        );
+
+        // Permits are released once the responses are consumed
+        reading.bytes().await?;
+        store.head(&path).await?;
+        writer.write_all(b"checkpoint").await?;
+        writer.shutdown().await?;
+        drop(writer);
+        store.delete(&path).await?;
+        Ok(())
+    }
+}
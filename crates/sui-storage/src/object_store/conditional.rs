@@ -0,0 +1,235 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conditional writes, so that writers racing to update the same object, like the manifest of an
+//! archive, don't overwrite each other's changes.
+//!
+//! Writes to S3, GCS and Azure are sent to presigned PUT URLs with the store's preconditions:
+//! `If-None-Match: *` on S3 and Azure or `x-goog-if-generation-match: 0` on GCS for objects that
+//! must not exist yet, and `If-Match` with the ETag the writer last read otherwise. Writes to the
+//! local file system and in-memory stores are only serialized within the process.
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use reqwest::header::{IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use tokio::sync::Mutex;
+
+use crate::object_store::sign::{ObjectStoreSignExt, SignMethod};
+use crate::object_store::ObjectStoreType;
+
+/// Validity of the URLs conditional writes are sent to, only used right away.
+const CONDITIONAL_PUT_URL_EXPIRY: Duration = Duration::from_secs(60);
+const GCS_IF_GENERATION_MATCH_HEADER: &str = "x-goog-if-generation-match";
+const AZURE_BLOB_TYPE_HEADER: &str = "x-ms-blob-type";
+
+/// Returned when a conditional write is rejected, as the object at its location was written
+/// since the writer last read it. Callers usually read it again and retry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreconditionFailed {
+    pub location: Path,
+}
+
+impl Display for PreconditionFailed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Precondition failed writing {}: it was changed by another writer",
+            self.location
+        )
+    }
+}
+
+impl std::error::Error for PreconditionFailed {}
+
+/// Whether `error` is a rejected conditional write.
+pub fn is_precondition_failed(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<PreconditionFailed>().is_some()
+}
+
+#[async_trait]
+pub trait ObjectStoreConditionalPutExt: Send + Sync + 'static {
+    /// Write the bytes at the given location in object store, failing with
+    /// [`PreconditionFailed`] if there is already an object at that location
+    async fn put_bytes_if_not_exists(&self, src: &Path, bytes: Bytes) -> Result<()>;
+
+    /// Write the bytes at the given location in object store, failing with
+    /// [`PreconditionFailed`] unless the object at that location has the ETag `etag`
+    async fn put_bytes_if_match(&self, src: &Path, bytes: Bytes, etag: &str) -> Result<()>;
+}
+
+/// Conditional writes to presigned URLs of S3, GCS and Azure objects.
+pub(crate) struct SignedConditionalPut {
+    store_type: ObjectStoreType,
+    signer: Arc<dyn ObjectStoreSignExt>,
+    client: Client,
+}
+
+impl SignedConditionalPut {
+    pub(crate) fn new(
+        store_type: ObjectStoreType,
+        signer: Arc<dyn ObjectStoreSignExt>,
+        client: Client,
+    ) -> Self {
+        Self {
+            store_type,
+            signer,
+            client,
+        }
+    }
+
+    async fn put(
+        &self,
+        src: &Path,
+        bytes: Bytes,
+        precondition: impl FnOnce(RequestBuilder) -> RequestBuilder + Send,
+    ) -> Result<()> {
+        let url = self
+            .signer
+            .signed_url(SignMethod::Put, src, CONDITIONAL_PUT_URL_EXPIRY)
+            .await?;
+        let mut request = precondition(self.client.put(url).body(bytes));
+        if self.store_type == ObjectStoreType::Azure {
+            request = request.header(AZURE_BLOB_TYPE_HEADER, "BlockBlob");
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to write {src}"))?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            // S3 answers concurrent conditional writes of the same object with a conflict, and
+            // Azure writes to existing blobs with If-None-Match.
+            StatusCode::PRECONDITION_FAILED | StatusCode::CONFLICT => Err(PreconditionFailed {
+                location: src.clone(),
+            }
+            .into()),
+            status => Err(anyhow!("Failed to write {src} with status: {status}")),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStoreConditionalPutExt for SignedConditionalPut {
+    async fn put_bytes_if_not_exists(&self, src: &Path, bytes: Bytes) -> Result<()> {
+        let gcs = self.store_type == ObjectStoreType::GCS;
+        self.put(src, bytes, |request| {
+            if gcs {
+                request.header(GCS_IF_GENERATION_MATCH_HEADER, "0")
+            } else {
+                request.header(IF_NONE_MATCH, "*")
+            }
+        })
+        .await
+    }
+
+    async fn put_bytes_if_match(&self, src: &Path, bytes: Bytes, etag: &str) -> Result<()> {
+        let etag = etag.to_string();
+        self.put(src, bytes, |request| request.header(IF_MATCH, etag))
+            .await
+    }
+}
+
+/// Conditional writes to local file system and in-memory stores, checked and written under a
+/// lock. Only writers going through the same instance are serialized.
+pub(crate) struct LockedConditionalPut {
+    store: Arc<DynObjectStore>,
+    lock: Mutex<()>,
+}
+
+impl LockedConditionalPut {
+    pub(crate) fn new(store: Arc<DynObjectStore>) -> Self {
+        Self {
+            store,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// ETag of the object at `src`, `None` if there is no object there.
+    async fn current_etag(&self, src: &Path) -> Result<Option<Option<String>>> {
+        match self.store.head(src).await {
+            Ok(meta) => Ok(Some(meta.e_tag)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStoreConditionalPutExt for LockedConditionalPut {
+    async fn put_bytes_if_not_exists(&self, src: &Path, bytes: Bytes) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        if self.current_etag(src).await?.is_some() {
+            return Err(PreconditionFailed {
+                location: src.clone(),
+            }
+            .into());
+        }
+        self.store.put(src, bytes).await?;
+        Ok(())
+    }
+
+    async fn put_bytes_if_match(&self, src: &Path, bytes: Bytes, etag: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        if self.current_etag(src).await?.flatten().as_deref() != Some(etag) {
+            return Err(PreconditionFailed {
+                location: src.clone(),
+            }
+            .into());
+        }
+        self.store.put(src, bytes).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::conditional::is_precondition_failed;
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use bytes::Bytes;
+    use object_store::path::Path;
+
+    #[tokio::test]
+    async fn test_conditional_puts() -> anyhow::Result<()> {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Memory),
+            bucket: Some("test_conditional_puts".to_string()),
+            ..Default::default()
+        };
+        let store = config.make()?;
+        let conditional = config.make_conditional_put()?;
+        let manifest = Path::from("MANIFEST");
+
+        conditional
+            .put_bytes_if_not_exists(&manifest, Bytes::from_static(b"v1"))
+            .await?;
+        let error = conditional
+            .put_bytes_if_not_exists(&manifest, Bytes::from_static(b"v1'"))
+            .await
+            .unwrap_err();
+        assert!(is_precondition_failed(&error));
+
+        let etag = store.head(&manifest).await?.e_tag.unwrap();
+        conditional
+            .put_bytes_if_match(&manifest, Bytes::from_static(b"v2"), &etag)
+            .await?;
+        // The ETag read before the last write is stale.
+        let error = conditional
+            .put_bytes_if_match(&manifest, Bytes::from_static(b"v2'"), &etag)
+            .await
+            .unwrap_err();
+        assert!(is_precondition_failed(&error));
+        assert_eq!(
+            store.get(&manifest).await?.bytes().await?,
+            Bytes::from_static(b"v2")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Waits for written objects to become visible, for stores that aren't read-after-write
+//! consistent, e.g. some S3-compatible stores serving the previous version of an overwritten
+//! object for a while. Writers whose next write assumes the previous one is readable, like the
+//! archive writer updating the MANIFEST after uploading files, poll the metadata of what they
+//! wrote until it matches.
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use bytes::Bytes;
+use object_store::path::Path;
+use object_store::ObjectMeta;
+use prometheus::{
+    exponential_buckets, register_histogram_with_registry, register_int_counter_with_registry,
+    Histogram, IntCounter, Registry,
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::object_store::{ObjectStoreHeadExt, ObjectStorePutExt};
+
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ReadAfterWriteMetrics {
+    pub object_store_visibility_wait_seconds: Histogram,
+    pub object_store_visibility_timeouts: IntCounter,
+}
+
+impl ReadAfterWriteMetrics {
+    pub fn new(registry: &Registry) -> Arc<Self> {
+        Arc::new(Self {
+            object_store_visibility_wait_seconds: register_histogram_with_registry!(
+                "object_store_visibility_wait_seconds",
+                "Time written objects took to become visible to reads",
+                exponential_buckets(0.01, 2.0, 12).unwrap(),
+                registry,
+            )
+            .unwrap(),
+            object_store_visibility_timeouts: register_int_counter_with_registry!(
+                "object_store_visibility_timeouts",
+                "Number of written objects that didn't become visible in time",
+                registry,
+            )
+            .unwrap(),
+        })
+    }
+
+    pub fn new_for_tests() -> Arc<Self> {
+        Self::new(&Registry::new())
+    }
+}
+
+/// The object a writer expects to read back.
+#[derive(Debug, Clone)]
+pub struct ExpectedObject {
+    pub size: usize,
+    /// Metadata of the object the write replaced, if any. Reads returning the same ETag and
+    /// modification time are stale.
+    pub replaced: Option<ObjectMeta>,
+}
+
+impl ExpectedObject {
+    fn matches(&self, meta: &ObjectMeta) -> bool {
+        meta.size == self.size
+            && self.replaced.as_ref().map_or(true, |replaced| {
+                meta.e_tag != replaced.e_tag || meta.last_modified > replaced.last_modified
+            })
+    }
+}
+
+/// Returned when a written object doesn't become visible before the timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotVisible {
+    pub location: Path,
+    pub timeout: Duration,
+}
+
+impl Display for NotVisible {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Object written at {} not visible after {:?}",
+            self.location, self.timeout
+        )
+    }
+}
+
+impl std::error::Error for NotVisible {}
+
+/// Poll the metadata of `location` until it matches `expected`, with backoff, and return it.
+/// Fails with [`NotVisible`] after `timeout`.
+pub async fn wait_until_visible<S: ObjectStoreHeadExt>(
+    store: &S,
+    location: &Path,
+    expected: &ExpectedObject,
+    timeout: Duration,
+    metrics: Option<&ReadAfterWriteMetrics>,
+) -> Result<ObjectMeta> {
+    let start = Instant::now();
+    let mut interval = INITIAL_POLL_INTERVAL;
+    loop {
+        if store.exists(location).await? {
+            let meta = store.head_object(location).await?;
+            if expected.matches(&meta) {
+                if let Some(metrics) = metrics {
+                    metrics
+                        .object_store_visibility_wait_seconds
+                        .observe(start.elapsed().as_secs_f64());
+                }
+                return Ok(meta);
+            }
+        }
+        if start.elapsed() >= timeout {
+            if let Some(metrics) = metrics {
+                metrics.object_store_visibility_timeouts.inc();
+            }
+            return Err(NotVisible {
+                location: location.clone(),
+                timeout,
+            }
+            .into());
+        }
+        debug!("Waiting for {location} to become visible");
+        tokio::time::sleep(interval.min(timeout.saturating_sub(start.elapsed()))).await;
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+/// Write `bytes` at `location` and wait until reads return them, see [`wait_until_visible`].
+pub async fn put_visible<S: ObjectStorePutExt + ObjectStoreHeadExt>(
+    store: &S,
+    location: &Path,
+    bytes: Bytes,
+    timeout: Duration,
+    metrics: Option<&ReadAfterWriteMetrics>,
+) -> Result<ObjectMeta> {
+    let replaced = if store.exists(location).await? {
+        Some(store.head_object(location).await?)
+    } else {
+        None
+    };
+    let expected = ExpectedObject {
+        size: bytes.len(),
+        replaced,
+    };
+    store.put_bytes(location, bytes).await?;
+    wait_until_visible(store, location, &expected, timeout, metrics).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::consistency::{
+        put_visible, wait_until_visible, ExpectedObject, NotVisible, ReadAfterWriteMetrics,
+    };
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use bytes::Bytes;
+    use object_store::path::Path;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_wait_until_visible() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make()?;
+        let metrics = ReadAfterWriteMetrics::new_for_tests();
+        let location = Path::from("epoch_0/1.chk");
+        let timeout = Duration::from_millis(200);
+
+        let meta = put_visible(
+            &store,
+            &location,
+            Bytes::from("checkpoints"),
+            timeout,
+            Some(metrics.as_ref()),
+        )
+        .await?;
+        assert_eq!(meta.size, 11);
+        assert_eq!(
+            metrics
+                .object_store_visibility_wait_seconds
+                .get_sample_count(),
+            1
+        );
+
+        // An object of another size never matches
+        let error = wait_until_visible(
+            &store,
+            &location,
+            &ExpectedObject {
+                size: 3,
+                replaced: None,
+            },
+            timeout,
+            Some(metrics.as_ref()),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            error.downcast::<NotVisible>()?,
+            NotVisible { location, timeout }
+        );
+        assert_eq!(metrics.object_store_visibility_timeouts.get(), 1);
+        Ok(())
+    }
+}
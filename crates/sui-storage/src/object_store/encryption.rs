@@ -0,0 +1,474 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Client-side encryption of objects, with envelope keys.
+//!
+//! [`EncryptedObjectStore`] encrypts every object with AES-256-GCM under a fresh data key, and
+//! stores the data key wrapped by an [`EnvelopeKey`] in a header before the ciphertext:
+//!
+//! ```text
+//! magic (7 bytes) | wrapped key length (u16, big endian) | wrapped key | nonce (12 bytes) | ciphertext
+//! ```
+//!
+//! The envelope key is either a local 256-bit key or a KMS key, so that the store holding the
+//! objects sees neither the data nor the keys.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::ops::Range;
+use std::path::Path as FsPath;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::DataKeySpec;
+use bytes::Bytes;
+use clap::ValueEnum;
+use fastcrypto::encoding::{Encoding, Hex};
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, StreamExt};
+use object_store::path::Path;
+use object_store::{
+    DynObjectStore, GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta,
+    ObjectStore,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
+
+const STORE_NAME: &str = "EncryptedObjectStore";
+const MAGIC: &[u8; 7] = b"SUIENC1";
+const NONCE_LENGTH: usize = 12;
+const KEY_LENGTH: usize = 32;
+
+/// Where the envelope key objects are encrypted with is taken from.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+pub enum EncryptionKeySource {
+    /// Local file holding a hex encoded 256-bit key
+    #[default]
+    File,
+    /// AWS KMS key, given by its ID or ARN
+    Kms,
+}
+
+/// Key wrapping the data keys objects are encrypted with.
+#[async_trait]
+pub trait EnvelopeKey: Send + Sync {
+    /// A new data key, along with its wrapped form stored with the object.
+    async fn generate_data_key(&self) -> Result<(Vec<u8>, Vec<u8>)>;
+
+    async fn unwrap_data_key(&self, wrapped: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Envelope key held locally, wrapping data keys with AES-256-GCM.
+pub struct LocalEnvelopeKey {
+    cipher: Aes256Gcm,
+}
+
+impl LocalEnvelopeKey {
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.len() != KEY_LENGTH {
+            bail!(
+                "Encryption key must be {KEY_LENGTH} bytes, got {}",
+                key.len()
+            );
+        }
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        })
+    }
+
+    /// Read the key from a file holding it hex encoded.
+    pub fn from_file(path: &FsPath) -> Result<Self> {
+        let hex = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read encryption key from {}", path.display()))?;
+        let key = Hex::decode(hex.trim()).map_err(|e| anyhow!("Invalid encryption key: {e}"))?;
+        Self::new(&key)
+    }
+}
+
+#[async_trait]
+impl EnvelopeKey for LocalEnvelopeKey {
+    async fn generate_data_key(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let data_key = Aes256Gcm::generate_key(&mut OsRng).to_vec();
+        Ok((data_key.clone(), seal(&self.cipher, &data_key)?))
+    }
+
+    async fn unwrap_data_key(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        open(&self.cipher, wrapped)
+    }
+}
+
+/// Envelope key held by AWS KMS, which generates and unwraps data keys.
+pub struct KmsEnvelopeKey {
+    client: aws_sdk_kms::Client,
+    key_id: String,
+}
+
+impl KmsEnvelopeKey {
+    pub fn new(client: aws_sdk_kms::Client, key_id: String) -> Self {
+        Self { client, key_id }
+    }
+}
+
+#[async_trait]
+impl EnvelopeKey for KmsEnvelopeKey {
+    async fn generate_data_key(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let output = self
+            .client
+            .generate_data_key()
+            .key_id(&self.key_id)
+            .key_spec(DataKeySpec::Aes256)
+            .send()
+            .await
+            .context("Failed to generate data key")?;
+        let data_key = output
+            .plaintext()
+            .ok_or_else(|| anyhow!("KMS returned no data key"))?;
+        let wrapped = output
+            .ciphertext_blob()
+            .ok_or_else(|| anyhow!("KMS returned no wrapped data key"))?;
+        Ok((data_key.as_ref().to_vec(), wrapped.as_ref().to_vec()))
+    }
+
+    async fn unwrap_data_key(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        let output = self
+            .client
+            .decrypt()
+            .key_id(&self.key_id)
+            .ciphertext_blob(Blob::new(wrapped))
+            .send()
+            .await
+            .context("Failed to unwrap data key")?;
+        let data_key = output
+            .plaintext()
+            .ok_or_else(|| anyhow!("KMS returned no data key"))?;
+        Ok(data_key.as_ref().to_vec())
+    }
+}
+
+/// `nonce | ciphertext` of `plaintext`, under a random nonce.
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LENGTH {
+        bail!("Encrypted payload is truncated");
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Decryption failed, the object was tampered with or the key is wrong"))
+}
+
+async fn encrypt(key: &dyn EnvelopeKey, plaintext: Bytes) -> Result<Bytes> {
+    let (data_key, wrapped) = key.generate_data_key().await?;
+    let wrapped_length = u16::try_from(wrapped.len()).context("Wrapped data key is too long")?;
+    let cipher = Aes256Gcm::new_from_slice(&data_key).map_err(|_| anyhow!("Invalid data key"))?;
+    let sealed = tokio::task::spawn_blocking(move || seal(&cipher, &plaintext)).await??;
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + wrapped.len() + sealed.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&wrapped_length.to_be_bytes());
+    bytes.extend_from_slice(&wrapped);
+    bytes.extend_from_slice(&sealed);
+    Ok(Bytes::from(bytes))
+}
+
+async fn decrypt(key: &dyn EnvelopeKey, bytes: Bytes) -> Result<Bytes> {
+    let Some(rest) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        bail!("Object is not encrypted");
+    };
+    if rest.len() < 2 {
+        bail!("Encrypted object is truncated");
+    }
+    let wrapped_length = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+    let header_length = MAGIC.len() + 2 + wrapped_length;
+    if bytes.len() < header_length {
+        bail!("Encrypted object is truncated");
+    }
+    let data_key = key
+        .unwrap_data_key(&bytes[MAGIC.len() + 2..header_length])
+        .await?;
+    let cipher = Aes256Gcm::new_from_slice(&data_key).map_err(|_| anyhow!("Invalid data key"))?;
+    let plaintext =
+        tokio::task::spawn_blocking(move || open(&cipher, &bytes[header_length..])).await??;
+    Ok(Bytes::from(plaintext))
+}
+
+fn to_store_error(location: &Path, error: anyhow::Error) -> object_store::Error {
+    object_store::Error::Generic {
+        store: STORE_NAME,
+        source: format!("{location}: {error:#}").into(),
+    }
+}
+
+/// Object store encrypting objects client-side, see the [module documentation](self).
+///
+/// Objects are read and written whole, as they are encrypted and authenticated at once. Object
+/// sizes reported by `head` and `list` are the sizes of the encrypted objects.
+pub struct EncryptedObjectStore {
+    inner: Arc<DynObjectStore>,
+    key: Arc<dyn EnvelopeKey>,
+}
+
+impl EncryptedObjectStore {
+    pub fn new(inner: Arc<DynObjectStore>, key: Arc<dyn EnvelopeKey>) -> Self {
+        Self { inner, key }
+    }
+
+    async fn get_decrypted(&self, location: &Path) -> object_store::Result<(Bytes, ObjectMeta)> {
+        let result = self.inner.get(location).await?;
+        let meta = result.meta.clone();
+        let bytes = result.bytes().await?;
+        let plaintext = decrypt(self.key.as_ref(), bytes)
+            .await
+            .map_err(|e| to_store_error(location, e))?;
+        Ok((plaintext, meta))
+    }
+}
+
+impl Debug for EncryptedObjectStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EncryptedObjectStore({})", self.inner)
+    }
+}
+
+impl Display for EncryptedObjectStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EncryptedObjectStore({})", self.inner)
+    }
+}
+
+fn slice(location: &Path, bytes: &Bytes, range: Range<usize>) -> object_store::Result<Bytes> {
+    if range.start > range.end || range.end > bytes.len() {
+        return Err(to_store_error(
+            location,
+            anyhow!(
+                "Range {range:?} is out of bounds of object of {} bytes",
+                bytes.len()
+            ),
+        ));
+    }
+    Ok(bytes.slice(range))
+}
+
+#[async_trait]
+impl ObjectStore for EncryptedObjectStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        let ciphertext = encrypt(self.key.as_ref(), bytes)
+            .await
+            .map_err(|e| to_store_error(location, e))?;
+        self.inner.put(location, ciphertext).await
+    }
+
+    /// Multipart writes are buffered in memory, and the object is encrypted and written at once
+    /// on shutdown.
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let writer = EncryptingWriter {
+            inner: self.inner.clone(),
+            key: self.key.clone(),
+            location: location.clone(),
+            buffer: vec![],
+            upload: None,
+        };
+        Ok((String::new(), Box::new(writer)))
+    }
+
+    async fn abort_multipart(
+        &self,
+        _location: &Path,
+        _multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        // Nothing is written before the upload completes.
+        Ok(())
+    }
+
+    async fn get(&self, location: &Path) -> object_store::Result<GetResult> {
+        self.get_opts(location, GetOptions::default()).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let range = options.range.clone();
+        let options = GetOptions {
+            range: None,
+            ..options
+        };
+        let result = self.inner.get_opts(location, options).await?;
+        let mut meta = result.meta.clone();
+        let bytes = result.bytes().await?;
+        let plaintext = decrypt(self.key.as_ref(), bytes)
+            .await
+            .map_err(|e| to_store_error(location, e))?;
+        meta.size = plaintext.len();
+        let range = range.unwrap_or(0..plaintext.len());
+        let payload = slice(location, &plaintext, range.clone())?;
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(
+                futures::stream::once(async move { Ok(payload) }).boxed(),
+            ),
+            meta,
+            range,
+        })
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        let (plaintext, _) = self.get_decrypted(location).await?;
+        slice(location, &plaintext, range)
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        let (plaintext, _) = self.get_decrypted(location).await?;
+        ranges
+            .iter()
+            .map(|range| slice(location, &plaintext, range.clone()))
+            .collect()
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.inner.list(prefix).await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename_if_not_exists(from, to).await
+    }
+}
+
+/// Buffers a multipart write, to encrypt and write the object once the writer is shut down.
+struct EncryptingWriter {
+    inner: Arc<DynObjectStore>,
+    key: Arc<dyn EnvelopeKey>,
+    location: Path,
+    buffer: Vec<u8>,
+    upload: Option<BoxFuture<'static, io::Result<()>>>,
+}
+
+impl AsyncWrite for EncryptingWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.upload.is_some() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Write after shutdown",
+            )));
+        }
+        self.buffer.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let upload = this.upload.get_or_insert_with(|| {
+            let (inner, key, location) =
+                (this.inner.clone(), this.key.clone(), this.location.clone());
+            let plaintext = Bytes::from(std::mem::take(&mut this.buffer));
+            async move {
+                let ciphertext = encrypt(key.as_ref(), plaintext)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                inner
+                    .put(&location, ciphertext)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            }
+            .boxed()
+        });
+        upload.poll_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::encryption::{EncryptedObjectStore, LocalEnvelopeKey};
+    use crate::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_encrypted_round_trip() -> anyhow::Result<()> {
+        let inner: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let key = Arc::new(LocalEnvelopeKey::new(&[7; 32])?);
+        let store: Arc<DynObjectStore> = Arc::new(EncryptedObjectStore::new(inner.clone(), key));
+        let path = Path::from("epoch_0/backup/000001.sst");
+        let data = Bytes::from_static(b"node backup");
+
+        store.put_bytes(&path, data.clone()).await?;
+        assert!(!inner
+            .get_bytes(&path)
+            .await?
+            .windows(data.len())
+            .any(|window| window == data.as_ref()));
+        assert_eq!(store.get_bytes(&path).await?, data);
+
+        let stream = futures::stream::iter([Ok(data.slice(..4)), Ok(data.slice(4..))]).boxed();
+        store.put_stream(&path, stream).await?;
+        assert_eq!(store.get_bytes(&path).await?, data);
+
+        // Objects encrypted with another key can't be read.
+        let other_key = Arc::new(LocalEnvelopeKey::new(&[8; 32])?);
+        let other: Arc<DynObjectStore> = Arc::new(EncryptedObjectStore::new(inner, other_key));
+        assert!(other.get_bytes(&path).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Errors of the `ObjectStore*Ext` traits, classified by what callers can do about them, e.g.
+//! treat a missing object as absent, or retry a throttled request later.
+//!
+//! Errors of the `object_store` backends are classified from their variant, and for the generic
+//! ones from the HTTP status in their message, errors of the HTTP stores from their response
+//! status, and I/O errors from their kind. Every classification keeps the original error, so
+//! messages and downcasts are unchanged.
+
+use std::fmt::{Display, Formatter};
+use std::io;
+
+use object_store::Error;
+use reqwest::StatusCode;
+
+use crate::object_store::retry::is_transient_error;
+
+pub type ObjectStoreResult<T> = std::result::Result<T, ObjectStoreError>;
+
+#[derive(Debug)]
+pub enum ObjectStoreError {
+    /// There is no object at the path
+    NotFound(anyhow::Error),
+    /// The credentials are missing, invalid or not allowed to access the path
+    PermissionDenied(anyhow::Error),
+    /// The store asked to slow down, or is unavailable for now
+    Throttled(anyhow::Error),
+    /// The request, or the connection to the store, timed out
+    Timeout(anyhow::Error),
+    /// The object doesn't have the expected contents, e.g. it is truncated or fails its checksum
+    Corrupt(anyhow::Error),
+    /// Any other error, e.g. a server error or an invalid request
+    Other(anyhow::Error),
+}
+
+/// Constructor of one of the variants of [`ObjectStoreError`].
+type ErrorKind = fn(anyhow::Error) -> ObjectStoreError;
+
+impl ObjectStoreError {
+    pub fn corrupt(error: impl Into<anyhow::Error>) -> Self {
+        ObjectStoreError::Corrupt(error.into())
+    }
+
+    /// Error of a response of an HTTP store with the unsuccessful `status`.
+    pub fn from_status(status: StatusCode, error: anyhow::Error) -> Self {
+        status_kind(status)(StatusError { status, error }.into())
+    }
+
+    pub fn error(&self) -> &anyhow::Error {
+        match self {
+            ObjectStoreError::NotFound(error)
+            | ObjectStoreError::PermissionDenied(error)
+            | ObjectStoreError::Throttled(error)
+            | ObjectStoreError::Timeout(error)
+            | ObjectStoreError::Corrupt(error)
+            | ObjectStoreError::Other(error) => error,
+        }
+    }
+
+    pub fn into_error(self) -> anyhow::Error {
+        match self {
+            ObjectStoreError::NotFound(error)
+            | ObjectStoreError::PermissionDenied(error)
+            | ObjectStoreError::Throttled(error)
+            | ObjectStoreError::Timeout(error)
+            | ObjectStoreError::Corrupt(error)
+            | ObjectStoreError::Other(error) => error,
+        }
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ObjectStoreError::NotFound(_) => ObjectStoreError::NotFound,
+            ObjectStoreError::PermissionDenied(_) => ObjectStoreError::PermissionDenied,
+            ObjectStoreError::Throttled(_) => ObjectStoreError::Throttled,
+            ObjectStoreError::Timeout(_) => ObjectStoreError::Timeout,
+            ObjectStoreError::Corrupt(_) => ObjectStoreError::Corrupt,
+            ObjectStoreError::Other(_) => ObjectStoreError::Other,
+        }
+    }
+
+    /// The same error, with `context` added to its message.
+    pub fn context<C: Display + Send + Sync + 'static>(self, context: C) -> Self {
+        let kind = self.kind();
+        kind(self.into_error().context(context))
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ObjectStoreError::NotFound(_))
+    }
+
+    /// Whether the request may succeed if it is sent again, after a backoff.
+    ///
+    /// Throttled and timed out requests are, as are the generic errors of the backends that are
+    /// transient, i.e. server errors and connection failures, unless a circuit breaker failed them.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ObjectStoreError::Throttled(_) | ObjectStoreError::Timeout(_) => true,
+            ObjectStoreError::Other(error) => {
+                if let Some(error) = error.downcast_ref::<ObjectStoreError>() {
+                    error.is_retryable()
+                } else if let Some(error) = error.downcast_ref::<Error>() {
+                    matches!(error, Error::Generic { source, .. } if is_transient_error(source.as_ref()))
+                } else if let Some(error) = error.downcast_ref::<io::Error>() {
+                    is_transient_io_error(error)
+                } else if let Some(error) = error.downcast_ref::<StatusError>() {
+                    error.status.is_server_error()
+                } else {
+                    error
+                        .downcast_ref::<reqwest::Error>()
+                        .map_or(false, |error| {
+                            error.is_connect() || error.is_request() || error.is_body()
+                        })
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Error of a response of an HTTP store, with its status, so that server errors are retried like
+/// those of the `object_store` backends.
+#[derive(Debug)]
+struct StatusError {
+    status: StatusCode,
+    error: anyhow::Error,
+}
+
+impl Display for StatusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+fn status_kind(status: StatusCode) -> ErrorKind {
+    match status {
+        StatusCode::NOT_FOUND => ObjectStoreError::NotFound,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ObjectStoreError::PermissionDenied,
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+            ObjectStoreError::Throttled
+        }
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => ObjectStoreError::Timeout,
+        _ => ObjectStoreError::Other,
+    }
+}
+
+fn store_error_kind(error: &Error) -> ErrorKind {
+    let message = match error {
+        Error::NotFound { .. } => return ObjectStoreError::NotFound,
+        Error::Generic { source, .. } => source.to_string(),
+        _ => return ObjectStoreError::Other,
+    };
+    // Generic errors of the HTTP backends end with "<client|server> error (<status>)"
+    let status = ["client error (", "server error ("]
+        .iter()
+        .find_map(|kind| {
+            message
+                .find(kind)
+                .map(|start| &message[start + kind.len()..])
+        })
+        .and_then(|status| status.get(..3))
+        .and_then(|status| status.parse::<u16>().ok())
+        .and_then(|status| StatusCode::from_u16(status).ok());
+    match status {
+        Some(status) => status_kind(status),
+        None if message.contains("timed out") => ObjectStoreError::Timeout,
+        None => ObjectStoreError::Other,
+    }
+}
+
+fn io_error_kind(error: &io::Error) -> ErrorKind {
+    match error.kind() {
+        io::ErrorKind::NotFound => ObjectStoreError::NotFound,
+        io::ErrorKind::PermissionDenied => ObjectStoreError::PermissionDenied,
+        io::ErrorKind::TimedOut => ObjectStoreError::Timeout,
+        _ => ObjectStoreError::Other,
+    }
+}
+
+fn reqwest_error_kind(error: &reqwest::Error) -> ErrorKind {
+    if error.is_timeout() {
+        return ObjectStoreError::Timeout;
+    }
+    error
+        .status()
+        .map_or(ObjectStoreError::Other as ErrorKind, status_kind)
+}
+
+fn is_transient_io_error(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+impl Display for ObjectStoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.error())
+    }
+}
+
+impl std::error::Error for ObjectStoreError {}
+
+impl From<Error> for ObjectStoreError {
+    fn from(error: Error) -> Self {
+        store_error_kind(&error)(error.into())
+    }
+}
+
+impl From<io::Error> for ObjectStoreError {
+    fn from(error: io::Error) -> Self {
+        io_error_kind(&error)(error.into())
+    }
+}
+
+impl From<reqwest::Error> for ObjectStoreError {
+    fn from(error: reqwest::Error) -> Self {
+        reqwest_error_kind(&error)(error.into())
+    }
+}
+
+/// Errors of the stores and their wrappers, classified if they are, or have the context of, one
+/// of the typed errors above.
+impl From<anyhow::Error> for ObjectStoreError {
+    fn from(error: anyhow::Error) -> Self {
+        let kind = if let Some(classified) = error.downcast_ref::<ObjectStoreError>() {
+            classified.kind()
+        } else if let Some(store_error) = error.downcast_ref::<Error>() {
+            store_error_kind(store_error)
+        } else if let Some(io_error) = error.downcast_ref::<io::Error>() {
+            io_error_kind(io_error)
+        } else if let Some(reqwest_error) = error.downcast_ref::<reqwest::Error>() {
+            reqwest_error_kind(reqwest_error)
+        } else {
+            ObjectStoreError::Other
+        };
+        kind(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::error::ObjectStoreError;
+    use object_store::Error;
+    use reqwest::StatusCode;
+    use std::io;
+
+    #[test]
+    fn test_classify_errors() {
+        let generic = |message: &str| {
+            ObjectStoreError::from(Error::Generic {
+                store: "S3",
+                source: message.to_string().into(),
+            })
+        };
+        let not_found = ObjectStoreError::from(Error::NotFound {
+            path: "epoch_0/MANIFEST".to_string(),
+            source: "missing".into(),
+        });
+        assert!(not_found.is_not_found() && !not_found.is_retryable());
+        assert!(matches!(
+            generic("Client error with status 403: client error (403 Forbidden)"),
+            ObjectStoreError::PermissionDenied(_)
+        ));
+        let throttled = generic("Server error with status 503: server error (503 Slow Down)");
+        assert!(matches!(throttled, ObjectStoreError::Throttled(_)) && throttled.is_retryable());
+        let server_error =
+            generic("Server error with status 500: server error (500 Internal Server Error)");
+        assert!(matches!(server_error, ObjectStoreError::Other(_)) && server_error.is_retryable());
+        let bad_request = generic("Client error with status 400: client error (400 Bad Request)");
+        assert!(!bad_request.is_retryable());
+        assert!(matches!(
+            generic("error sending request: operation timed out"),
+            ObjectStoreError::Timeout(_)
+        ));
+        assert!(!ObjectStoreError::from(Error::NotImplemented).is_retryable());
+
+        // Errors of the HTTP stores are classified from their status
+        let bad_gateway = ObjectStoreError::from_status(
+            StatusCode::BAD_GATEWAY,
+            anyhow::anyhow!("Failed to get MANIFEST with status: 502 Bad Gateway"),
+        );
+        assert!(matches!(bad_gateway, ObjectStoreError::Other(_)) && bad_gateway.is_retryable());
+        assert_eq!(
+            bad_gateway.to_string(),
+            "Failed to get MANIFEST with status: 502 Bad Gateway"
+        );
+        let forbidden = ObjectStoreError::from_status(StatusCode::FORBIDDEN, anyhow::anyhow!(""));
+        assert!(matches!(forbidden, ObjectStoreError::PermissionDenied(_)));
+        assert!(!forbidden.is_retryable());
+
+        // Errors converted to anyhow keep their classification, and their message
+        let error = anyhow::Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(ObjectStoreError::from(error.context("Failed to read file")).is_not_found());
+        let error = generic("server error (503 Slow Down)").context("Failed to get file: 1.chk");
+        let error = ObjectStoreError::from(anyhow::Error::from(error).context("Failed to sync"));
+        assert!(matches!(error, ObjectStoreError::Throttled(_)) && error.is_retryable());
+        assert!(error
+            .to_string()
+            .starts_with("Failed to sync: Failed to get file: 1.chk: "));
+    }
+}
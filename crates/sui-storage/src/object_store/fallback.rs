@@ -0,0 +1,485 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reads failing over from a primary object store to secondary ones, e.g. an archive replicated
+//! to several regions.
+//!
+//! [`FallbackObjectStore`] reads from its primary store and, if the read fails or the object is
+//! not found, from each of its secondary stores in turn, returning the first successful read.
+//!
+//! Stores serving the same data from different places, e.g. a public archive and its community
+//! mirrors, can be ranked by latency instead: [`spawn_latency_probe`] periodically times a read
+//! of the metadata of an object from every store, and reads then start from the fastest store.
+
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::ops::Range;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future::join_all;
+use futures::stream::BoxStream;
+use object_store::path::Path;
+use object_store::{DynObjectStore, ListResult, ObjectMeta};
+use parking_lot::RwLock;
+use prometheus::{
+    register_int_counter_vec_with_registry, register_int_gauge_vec_with_registry, IntCounterVec,
+    IntGaugeVec, Registry,
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::object_store::error::ObjectStoreResult;
+use crate::object_store::{
+    ObjectStoreConfig, ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreHeadExt,
+    ObjectStoreListExt,
+};
+
+/// Time after which a latency probe of a store counts as failed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct FallbackObjectStoreMetrics {
+    pub object_store_failovers: IntCounterVec,
+    pub object_store_probe_latency_ms: IntGaugeVec,
+}
+
+impl FallbackObjectStoreMetrics {
+    pub fn new(registry: &Registry) -> Arc<Self> {
+        Arc::new(Self {
+            object_store_failovers: register_int_counter_vec_with_registry!(
+                "object_store_failovers",
+                "Number of reads retried on the next store after failing on a store",
+                &["operation", "reason"],
+                registry,
+            )
+            .unwrap(),
+            object_store_probe_latency_ms: register_int_gauge_vec_with_registry!(
+                "object_store_probe_latency_ms",
+                "Latency of the last probe of a store, -1 if it failed",
+                &["store"],
+                registry,
+            )
+            .unwrap(),
+        })
+    }
+
+    pub fn new_for_tests() -> Arc<Self> {
+        Self::new(&Registry::new())
+    }
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FallbackObjectStoreConfig {
+    pub primary: ObjectStoreConfig,
+    /// Stores read from, in order, when the read from the previous store fails.
+    #[serde(default)]
+    pub secondaries: Vec<ObjectStoreConfig>,
+}
+
+impl FallbackObjectStoreConfig {
+    pub fn make(
+        &self,
+        metrics: Arc<FallbackObjectStoreMetrics>,
+    ) -> Result<FallbackObjectStore<Arc<DynObjectStore>>> {
+        let secondaries = self
+            .secondaries
+            .iter()
+            .map(ObjectStoreConfig::make)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(FallbackObjectStore::new(
+            self.primary.make()?,
+            secondaries,
+            metrics,
+        ))
+    }
+}
+
+/// Order in which a [`FallbackObjectStore`] reads from its stores, by their index. Stores reading
+/// from the same endpoints, e.g. for objects and for their metadata, can share a ranking.
+pub struct StoreRanking {
+    order: RwLock<Vec<usize>>,
+}
+
+impl StoreRanking {
+    /// The stores in their configured order, primary first.
+    pub fn new(num_stores: usize) -> Arc<Self> {
+        Arc::new(Self {
+            order: RwLock::new((0..num_stores).collect()),
+        })
+    }
+
+    pub fn order(&self) -> Vec<usize> {
+        self.order.read().clone()
+    }
+}
+
+pub struct FallbackObjectStore<S> {
+    /// The primary store, followed by the secondary ones.
+    stores: Vec<S>,
+    ranking: Arc<StoreRanking>,
+    metrics: Arc<FallbackObjectStoreMetrics>,
+}
+
+impl<S> FallbackObjectStore<S> {
+    pub fn new(primary: S, secondaries: Vec<S>, metrics: Arc<FallbackObjectStoreMetrics>) -> Self {
+        let mut stores = vec![primary];
+        stores.extend(secondaries);
+        Self {
+            ranking: StoreRanking::new(stores.len()),
+            stores,
+            metrics,
+        }
+    }
+
+    /// Read from the stores in the order of `ranking`, which must rank as many stores.
+    pub fn with_ranking(mut self, ranking: Arc<StoreRanking>) -> Result<Self> {
+        ensure!(
+            ranking.order().len() == self.stores.len(),
+            "Ranking of {} stores can't order {} stores",
+            ranking.order().len(),
+            self.stores.len()
+        );
+        self.ranking = ranking;
+        Ok(self)
+    }
+
+    pub fn ranking(&self) -> &Arc<StoreRanking> {
+        &self.ranking
+    }
+
+    pub fn primary(&self) -> &S {
+        &self.stores[0]
+    }
+
+    pub fn secondaries(&self) -> &[S] {
+        &self.stores[1..]
+    }
+
+    /// The primary store, followed by the secondary ones.
+    pub fn stores(&self) -> &[S] {
+        &self.stores
+    }
+}
+
+impl<S: Display> FallbackObjectStore<S> {
+    /// Run `read` on every store in the order of the ranking, until it succeeds or fails on the
+    /// last store.
+    async fn read<'a, T, F, Fut>(
+        &'a self,
+        operation: &str,
+        location: &Path,
+        read: F,
+    ) -> ObjectStoreResult<T>
+    where
+        F: Fn(&'a S) -> Fut,
+        Fut: Future<Output = ObjectStoreResult<T>>,
+    {
+        let order = self.ranking.order();
+        let (last, stores) = order.split_last().expect("at least the primary store");
+        for store in stores.iter().map(|index| &self.stores[*index]) {
+            match read(store).await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    let reason = if e.is_not_found() {
+                        "not_found"
+                    } else {
+                        "error"
+                    };
+                    warn!("Failed to {operation} {location} from {store}, failing over: {e:?}");
+                    self.metrics
+                        .object_store_failovers
+                        .with_label_values(&[operation, reason])
+                        .inc();
+                }
+            }
+        }
+        read(&self.stores[*last]).await
+    }
+}
+
+impl<S: ObjectStoreHeadExt + Clone> FallbackObjectStore<S> {
+    /// Rank the stores by the latency of reading the metadata of `path` every `interval`, see
+    /// [`spawn_latency_probe`].
+    pub fn spawn_latency_probe(&self, path: Path, interval: Duration) -> JoinHandle<()> {
+        spawn_latency_probe(
+            self.stores.clone(),
+            path,
+            interval,
+            &self.ranking,
+            self.metrics.clone(),
+        )
+    }
+}
+
+/// Time a read of the metadata of `path` from every store, and rank the stores fastest first.
+/// Stores failing the probe are ranked last, in their configured order.
+pub async fn probe_latencies<H: ObjectStoreHeadExt>(
+    stores: &[H],
+    path: &Path,
+    ranking: &StoreRanking,
+    metrics: &FallbackObjectStoreMetrics,
+) {
+    let latencies = join_all(stores.iter().map(|store| async move {
+        let start = Instant::now();
+        match tokio::time::timeout(PROBE_TIMEOUT, store.head_object(path)).await {
+            Ok(Ok(_)) => Some(start.elapsed()),
+            Ok(Err(e)) => {
+                warn!("Latency probe of {store} failed: {e:?}");
+                None
+            }
+            Err(_) => {
+                warn!("Latency probe of {store} timed out");
+                None
+            }
+        }
+    }))
+    .await;
+    for (store, latency) in stores.iter().zip(&latencies) {
+        metrics
+            .object_store_probe_latency_ms
+            .with_label_values(&[&store.to_string()])
+            .set(latency.map_or(-1, |latency| latency.as_millis() as i64));
+    }
+    let mut order: Vec<usize> = (0..stores.len()).collect();
+    order.sort_by_key(|index| (latencies[*index].is_none(), latencies[*index]));
+    *ranking.order.write() = order;
+}
+
+/// Probe the latencies of `stores` every `interval` and rank them in `ranking`, until the
+/// ranking is dropped.
+pub fn spawn_latency_probe<H: ObjectStoreHeadExt>(
+    stores: Vec<H>,
+    path: Path,
+    interval: Duration,
+    ranking: &Arc<StoreRanking>,
+    metrics: Arc<FallbackObjectStoreMetrics>,
+) -> JoinHandle<()> {
+    let ranking: Weak<StoreRanking> = Arc::downgrade(ranking);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            let Some(ranking) = ranking.upgrade() else {
+                break;
+            };
+            probe_latencies(&stores, &path, &ranking, &metrics).await;
+        }
+    })
+}
+
+impl<S: Display> Display for FallbackObjectStore<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FallbackObjectStore(")?;
+        for (index, store) in self.stores.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{store}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetExt> ObjectStoreGetExt for FallbackObjectStore<S> {
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        self.read("get", src, |store| store.get_bytes(src)).await
+    }
+
+    /// Fails over if the object can't be opened, not if the stream fails once opened.
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        self.read("get", src, |store| store.get_stream(src)).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetRangeExt> ObjectStoreGetRangeExt for FallbackObjectStore<S> {
+    async fn get_byte_range(&self, src: &Path, range: Range<usize>) -> ObjectStoreResult<Bytes> {
+        self.read("get_range", src, |store| {
+            store.get_byte_range(src, range.clone())
+        })
+        .await
+    }
+
+    async fn get_byte_ranges(
+        &self,
+        src: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        self.read("get_range", src, |store| store.get_byte_ranges(src, ranges))
+            .await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreListExt + Display> ObjectStoreListExt for FallbackObjectStore<S> {
+    /// Fails over if the listing can't be started, not if the stream fails once started.
+    async fn list_objects(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        let location = src.cloned().unwrap_or_default();
+        self.read("list", &location, |store| store.list_objects(src))
+            .await
+    }
+
+    async fn list_objects_with_delimiter(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<ListResult> {
+        let location = src.cloned().unwrap_or_default();
+        self.read("list", &location, |store| {
+            store.list_objects_with_delimiter(src)
+        })
+        .await
+    }
+
+    async fn list_objects_after(
+        &self,
+        src: Option<&Path>,
+        offset: &Path,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        let location = src.cloned().unwrap_or_default();
+        self.read("list", &location, |store| {
+            store.list_objects_after(src, offset)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreHeadExt> ObjectStoreHeadExt for FallbackObjectStore<S> {
+    async fn head_object(&self, src: &Path) -> ObjectStoreResult<ObjectMeta> {
+        self.read("head", src, |store| store.head_object(src)).await
+    }
+
+    /// Whether any store has the object. Fails only if no store could tell.
+    async fn exists(&self, src: &Path) -> ObjectStoreResult<bool> {
+        let mut answered = false;
+        let mut first_error = None;
+        for index in self.ranking.order() {
+            let store = &self.stores[index];
+            match store.exists(src).await {
+                Ok(true) => return Ok(true),
+                Ok(false) => answered = true,
+                Err(e) => {
+                    warn!("Failed to head {src} from {store}: {e:?}");
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) if !answered => Err(e),
+            _ => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::fallback::{
+        probe_latencies, FallbackObjectStore, FallbackObjectStoreMetrics, StoreRanking,
+    };
+    use crate::object_store::{ObjectStoreGetExt, ObjectStoreHeadExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_failover_on_not_found() -> anyhow::Result<()> {
+        let primary: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let secondary: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let metrics = FallbackObjectStoreMetrics::new_for_tests();
+        let store =
+            FallbackObjectStore::new(primary.clone(), vec![secondary.clone()], metrics.clone());
+        let replicated = Path::from("epoch_0/1.chk");
+        let missing = Path::from("epoch_0/2.chk");
+
+        primary
+            .put_bytes(&replicated, Bytes::from_static(b"primary"))
+            .await?;
+        secondary
+            .put_bytes(&replicated, Bytes::from_static(b"secondary"))
+            .await?;
+        secondary
+            .put_bytes(&missing, Bytes::from_static(b"secondary"))
+            .await?;
+
+        assert_eq!(
+            store.get_bytes(&replicated).await?,
+            Bytes::from_static(b"primary")
+        );
+        assert_eq!(
+            store.get_bytes(&missing).await?,
+            Bytes::from_static(b"secondary")
+        );
+        assert!(store.get_bytes(&Path::from("epoch_0/3.chk")).await.is_err());
+        assert_eq!(
+            metrics
+                .object_store_failovers
+                .with_label_values(&["get", "not_found"])
+                .get(),
+            2
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_latency_ranking() -> anyhow::Result<()> {
+        let primary: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let mirror: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let metrics = FallbackObjectStoreMetrics::new_for_tests();
+        let store =
+            FallbackObjectStore::new(primary.clone(), vec![mirror.clone()], metrics.clone());
+        let manifest = Path::from("MANIFEST");
+        let replicated = Path::from("epoch_0/1.chk");
+        primary
+            .put_bytes(&replicated, Bytes::from_static(b"primary"))
+            .await?;
+        mirror
+            .put_bytes(&replicated, Bytes::from_static(b"mirror"))
+            .await?;
+        mirror
+            .put_bytes(&manifest, Bytes::from_static(b"manifest"))
+            .await?;
+
+        // The primary fails the probe, so reads start from the mirror
+        probe_latencies(store.stores(), &manifest, store.ranking(), &metrics).await;
+        assert_eq!(store.ranking().order(), vec![1, 0]);
+        assert_eq!(
+            store.get_bytes(&replicated).await?,
+            Bytes::from_static(b"mirror")
+        );
+        assert_eq!(
+            metrics
+                .object_store_probe_latency_ms
+                .with_label_values(&[&primary.to_string()])
+                .get(),
+            -1
+        );
+
+        primary
+            .put_bytes(&manifest, Bytes::from_static(b"manifest"))
+            .await?;
+        probe_latencies(store.stores(), &manifest, store.ranking(), &metrics).await;
+        assert_eq!(store.ranking().order().len(), 2);
+        assert!(store.exists(&manifest).await?);
+        assert!(!store.exists(&Path::from("epoch_0/2.chk")).await?);
+
+        // Rankings are shared by stores with as many stores only
+        let store =
+            FallbackObjectStore::new(primary.clone(), vec![mirror.clone()], metrics.clone());
+        assert!(store.with_ranking(StoreRanking::new(3)).is_err());
+        Ok(())
+    }
+}
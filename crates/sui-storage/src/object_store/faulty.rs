@@ -0,0 +1,353 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Store injecting faults into the requests to another store, to test the archive, snapshot and
+//! indexer pipelines against a failing store.
+//!
+//! Faults are configured per [`Operation`] and drawn from a seeded RNG, so that a test, or a
+//! simtest seed, sees the same faults on every run as long as it sends its requests in the same
+//! order.
+
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path;
+use object_store::{
+    Error, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore,
+};
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use tokio::io::AsyncWrite;
+
+use crate::object_store::concurrency::Operation;
+
+/// Faults injected into the requests of an operation. The default injects none.
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    /// Probability between 0 and 1 that a request fails with an [`InjectedFault`], before
+    /// reaching the inner store.
+    pub error_rate: f64,
+    /// Range of the latency added to every request, uniformly distributed.
+    pub latency: Option<Range<Duration>>,
+    /// Probability between 0 and 1 that a put only writes a random prefix of its bytes to the
+    /// inner store and then fails, as an upload interrupted by a store without atomic writes
+    /// would. Only applies to writes.
+    pub partial_write_rate: f64,
+    /// Whether to shuffle the objects and prefixes of listings, which stores only return in
+    /// lexicographic order by convention. Only applies to lists.
+    pub shuffle_listings: bool,
+}
+
+/// Error of a request failed on purpose by a [`FaultyObjectStore`]. Reported as a generic error,
+/// i.e. a transient one that is retried.
+#[derive(Debug)]
+pub struct InjectedFault {
+    pub operation: Operation,
+    pub location: String,
+}
+
+impl Display for InjectedFault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Injected fault of {:?} request to {}",
+            self.operation, self.location
+        )
+    }
+}
+
+impl std::error::Error for InjectedFault {}
+
+#[derive(Debug)]
+pub struct FaultyObjectStore<T> {
+    inner: T,
+    read: FaultConfig,
+    list: FaultConfig,
+    write: FaultConfig,
+    delete: FaultConfig,
+    rng: Mutex<StdRng>,
+}
+
+impl<T: ObjectStore> FaultyObjectStore<T> {
+    /// Store forwarding every request to `inner` untouched, until faults are configured with
+    /// [`with_faults`](Self::with_faults).
+    pub fn new(inner: T, seed: u64) -> Self {
+        Self {
+            inner,
+            read: FaultConfig::default(),
+            list: FaultConfig::default(),
+            write: FaultConfig::default(),
+            delete: FaultConfig::default(),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    pub fn with_faults(mut self, operation: Operation, faults: FaultConfig) -> Self {
+        *self.faults_mut(operation) = faults;
+        self
+    }
+
+    fn faults(&self, operation: Operation) -> &FaultConfig {
+        match operation {
+            Operation::Read => &self.read,
+            Operation::List => &self.list,
+            Operation::Write => &self.write,
+            Operation::Delete => &self.delete,
+        }
+    }
+
+    fn faults_mut(&mut self, operation: Operation) -> &mut FaultConfig {
+        match operation {
+            Operation::Read => &mut self.read,
+            Operation::List => &mut self.list,
+            Operation::Write => &mut self.write,
+            Operation::Delete => &mut self.delete,
+        }
+    }
+
+    fn sample(&self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.lock().gen_bool(probability.min(1.0))
+    }
+
+    /// Wait for the latency of a request of `operation`, and fail it if an error is drawn.
+    async fn inject(&self, operation: Operation, location: &Path) -> object_store::Result<()> {
+        let faults = self.faults(operation);
+        if let Some(latency) = &faults.latency {
+            let latency = if latency.is_empty() {
+                latency.start
+            } else {
+                self.rng.lock().gen_range(latency.clone())
+            };
+            tokio::time::sleep(latency).await;
+        }
+        if self.sample(faults.error_rate) {
+            return Err(injected_fault(operation, location));
+        }
+        Ok(())
+    }
+
+    fn shuffle<V>(&self, values: &mut [V]) {
+        values.shuffle(&mut *self.rng.lock());
+    }
+}
+
+fn injected_fault(operation: Operation, location: &Path) -> Error {
+    Error::Generic {
+        store: "FaultyObjectStore",
+        source: Box::new(InjectedFault {
+            operation,
+            location: location.to_string(),
+        }),
+    }
+}
+
+impl<T: ObjectStore> Display for FaultyObjectStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FaultyObjectStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for FaultyObjectStore<T> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        self.inject(Operation::Write, location).await?;
+        if !bytes.is_empty() && self.sample(self.write.partial_write_rate) {
+            let written = self.rng.lock().gen_range(0..bytes.len());
+            self.inner.put(location, bytes.slice(..written)).await?;
+            return Err(injected_fault(Operation::Write, location));
+        }
+        self.inner.put(location, bytes).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.inject(Operation::Write, location).await?;
+        self.inner.put_multipart(location).await
+    }
+
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inject(Operation::Write, location).await?;
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn append(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.inject(Operation::Write, location).await?;
+        self.inner.append(location).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.inject(Operation::Read, location).await?;
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inject(Operation::Read, location).await?;
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inject(Operation::Delete, location).await?;
+        self.inner.delete(location).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.inject(Operation::List, prefix.unwrap_or(&Path::default()))
+            .await?;
+        let stream = self.inner.list(prefix).await?;
+        if !self.list.shuffle_listings {
+            return Ok(stream);
+        }
+        let mut objects: Vec<_> = stream.try_collect().await?;
+        self.shuffle(&mut objects);
+        Ok(futures::stream::iter(objects.into_iter().map(Ok)).boxed())
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.inject(Operation::List, prefix.unwrap_or(&Path::default()))
+            .await?;
+        let mut result = self.inner.list_with_delimiter(prefix).await?;
+        if self.list.shuffle_listings {
+            self.shuffle(&mut result.objects);
+            self.shuffle(&mut result.common_prefixes);
+        }
+        Ok(result)
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inject(Operation::Write, to).await?;
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inject(Operation::Write, to).await?;
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::concurrency::Operation;
+    use crate::object_store::faulty::{FaultConfig, FaultyObjectStore, InjectedFault};
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::{Error, ObjectStore};
+    use std::time::Duration;
+
+    async fn outcomes(seed: u64) -> Vec<bool> {
+        let store = FaultyObjectStore::new(InMemory::new(), seed).with_faults(
+            Operation::Write,
+            FaultConfig {
+                error_rate: 0.5,
+                ..Default::default()
+            },
+        );
+        let mut outcomes = vec![];
+        for i in 0..20 {
+            let path = Path::from(format!("archive/{i}.chk"));
+            outcomes.push(store.put(&path, Bytes::from("checkpoint")).await.is_ok());
+        }
+        outcomes
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn test_faults_are_deterministic() -> anyhow::Result<()> {
+        let first = outcomes(7).await;
+        assert_eq!(first, outcomes(7).await);
+        assert!(first.contains(&true) && first.contains(&false));
+
+        // Reads are only delayed, and writes fail with an injected fault
+        let store = FaultyObjectStore::new(InMemory::new(), 7)
+            .with_faults(
+                Operation::Read,
+                FaultConfig {
+                    latency: Some(Duration::from_secs(1)..Duration::from_secs(2)),
+                    ..Default::default()
+                },
+            )
+            .with_faults(
+                Operation::Write,
+                FaultConfig {
+                    error_rate: 1.0,
+                    ..Default::default()
+                },
+            );
+        let path = Path::from("archive/1.chk");
+        let Err(Error::Generic { source, .. }) = store.put(&path, Bytes::from("checkpoint")).await
+        else {
+            panic!("put should fail");
+        };
+        assert!(source.is::<InjectedFault>());
+        let start = tokio::time::Instant::now();
+        assert!(matches!(
+            store.head(&path).await,
+            Err(Error::NotFound { .. })
+        ));
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn test_partial_writes_and_shuffled_listings() -> anyhow::Result<()> {
+        let store = FaultyObjectStore::new(InMemory::new(), 7).with_faults(
+            Operation::Write,
+            FaultConfig {
+                partial_write_rate: 1.0,
+                ..Default::default()
+            },
+        );
+        let path = Path::from("archive/1.chk");
+        assert!(store.put(&path, Bytes::from("checkpoint")).await.is_err());
+        let written = store.get(&path).await?.bytes().await?;
+        assert!(b"checkpoint".starts_with(&written) && written.len() < b"checkpoint".len());
+
+        let store = FaultyObjectStore::new(InMemory::new(), 7).with_faults(
+            Operation::List,
+            FaultConfig {
+                shuffle_listings: true,
+                ..Default::default()
+            },
+        );
+        let mut paths = vec![];
+        for i in 0..20 {
+            let path = Path::from(format!("archive/{i:02}.chk"));
+            store.put(&path, Bytes::from("checkpoint")).await?;
+            paths.push(path);
+        }
+        let listed: Vec<_> = store
+            .list_with_delimiter(Some(&Path::from("archive")))
+            .await?
+            .objects
+            .into_iter()
+            .map(|object| object.location)
+            .collect();
+        assert_ne!(listed, paths);
+        let mut sorted = listed;
+        sorted.sort();
+        assert_eq!(sorted, paths);
+        Ok(())
+    }
+}
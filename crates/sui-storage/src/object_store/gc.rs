@@ -0,0 +1,212 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Age-based garbage collection of the objects under a prefix, e.g. of debug dumps and old DB
+//! checkpoints uploaded by nodes, which nothing else deletes once they are no longer useful.
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::ObjectMeta;
+use prometheus::{register_int_counter_with_registry, IntCounter, Registry};
+use tracing::info;
+
+use crate::object_store::batch_delete::DeleteObjectsResult;
+use crate::object_store::{ObjectStoreDeleteExt, ObjectStoreListExt};
+
+/// Number of delete requests in flight.
+const GC_DELETE_CONCURRENCY: usize = 16;
+
+pub struct GcMetrics {
+    pub object_store_gc_deleted_objects: IntCounter,
+    pub object_store_gc_deleted_bytes: IntCounter,
+    pub object_store_gc_failed_deletes: IntCounter,
+}
+
+impl GcMetrics {
+    pub fn new(registry: &Registry) -> Arc<Self> {
+        Arc::new(Self {
+            object_store_gc_deleted_objects: register_int_counter_with_registry!(
+                "object_store_gc_deleted_objects",
+                "Number of objects deleted for being older than their retention",
+                registry,
+            )
+            .unwrap(),
+            object_store_gc_deleted_bytes: register_int_counter_with_registry!(
+                "object_store_gc_deleted_bytes",
+                "Total size of the objects deleted for being older than their retention",
+                registry,
+            )
+            .unwrap(),
+            object_store_gc_failed_deletes: register_int_counter_with_registry!(
+                "object_store_gc_failed_deletes",
+                "Number of expired objects which failed to be deleted",
+                registry,
+            )
+            .unwrap(),
+        })
+    }
+
+    pub fn new_for_tests() -> Arc<Self> {
+        Self::new(&Registry::new())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PrefixGcSummary {
+    /// Objects older than the retention, deleted unless on a dry run
+    pub expired: Vec<ObjectMeta>,
+    /// Expired objects which failed to be deleted, with their error
+    pub failed: Vec<(Path, anyhow::Error)>,
+    /// Number of objects within the retention
+    pub retained: usize,
+}
+
+impl PrefixGcSummary {
+    pub fn expired_bytes(&self) -> u64 {
+        self.expired.iter().map(|object| object.size as u64).sum()
+    }
+}
+
+/// Delete the objects under `prefix` of `store` last modified more than `retention` ago. Nothing
+/// is deleted on a `dry_run`, which only lists the expired objects. Objects which fail to be
+/// deleted are returned in the summary rather than failing the others.
+pub async fn gc_prefix<S: ObjectStoreListExt + ObjectStoreDeleteExt>(
+    store: &S,
+    prefix: &Path,
+    retention: Duration,
+    dry_run: bool,
+    metrics: Option<&GcMetrics>,
+) -> Result<PrefixGcSummary> {
+    let cutoff = Utc::now() - chrono::Duration::from_std(retention)?;
+    let mut summary = PrefixGcSummary::default();
+    let mut objects = store.list_objects(Some(prefix)).await?;
+    while let Some(object) = objects.try_next().await? {
+        if object.last_modified <= cutoff {
+            summary.expired.push(object);
+        } else {
+            summary.retained += 1;
+        }
+    }
+    if dry_run || summary.expired.is_empty() {
+        return Ok(summary);
+    }
+
+    let locations: Vec<Path> = summary
+        .expired
+        .iter()
+        .map(|object| object.location.clone())
+        .collect();
+    let DeleteObjectsResult { failed, .. } = store
+        .delete_objects(
+            &locations,
+            NonZeroUsize::new(GC_DELETE_CONCURRENCY).unwrap(),
+            None,
+        )
+        .await;
+    summary.expired.retain(|object| {
+        !failed
+            .iter()
+            .any(|(location, _)| *location == object.location)
+    });
+    summary.failed = failed;
+    if let Some(metrics) = metrics {
+        metrics
+            .object_store_gc_deleted_objects
+            .inc_by(summary.expired.len() as u64);
+        metrics
+            .object_store_gc_deleted_bytes
+            .inc_by(summary.expired_bytes());
+        metrics
+            .object_store_gc_failed_deletes
+            .inc_by(summary.failed.len() as u64);
+    }
+    info!(
+        "Deleted {} objects ({} bytes) under {prefix} older than {:?}, {} failed",
+        summary.expired.len(),
+        summary.expired_bytes(),
+        retention,
+        summary.failed.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::gc::{gc_prefix, GcMetrics};
+    use crate::object_store::util::put;
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreHeadExt, ObjectStoreType};
+    use bytes::Bytes;
+    use object_store::path::Path;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_gc_prefix() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make()?;
+        let metrics = GcMetrics::new_for_tests();
+        let prefix = Path::from("dumps");
+        for (path, contents) in [
+            ("dumps/1.dump", "0123"),
+            ("dumps/2/2.dump", "012345"),
+            ("checkpoints/1", "01"),
+        ] {
+            put(&store, &Path::from(path), Bytes::from(contents)).await?;
+        }
+
+        // Nothing is older than an hour
+        let summary = gc_prefix(
+            &store,
+            &prefix,
+            Duration::from_secs(3600),
+            false,
+            Some(metrics.as_ref()),
+        )
+        .await?;
+        assert!(summary.expired.is_empty());
+        assert_eq!(summary.retained, 2);
+
+        // A dry run only lists the expired objects
+        let summary = gc_prefix(
+            &store,
+            &prefix,
+            Duration::ZERO,
+            true,
+            Some(metrics.as_ref()),
+        )
+        .await?;
+        assert_eq!(summary.expired.len(), 2);
+        assert_eq!(summary.expired_bytes(), 10);
+        assert!(store.exists(&Path::from("dumps/1.dump")).await?);
+        assert_eq!(metrics.object_store_gc_deleted_objects.get(), 0);
+
+        let summary = gc_prefix(
+            &store,
+            &prefix,
+            Duration::ZERO,
+            false,
+            Some(metrics.as_ref()),
+        )
+        .await?;
+        assert_eq!(summary.expired.len(), 2);
+        assert!(summary.failed.is_empty());
+        assert!(!store.exists(&Path::from("dumps/1.dump")).await?);
+        assert!(!store.exists(&Path::from("dumps/2/2.dump")).await?);
+        // Objects outside of the prefix are left alone
+        assert!(store.exists(&Path::from("checkpoints/1")).await?);
+        assert_eq!(metrics.object_store_gc_deleted_objects.get(), 2);
+        assert_eq!(metrics.object_store_gc_deleted_bytes.get(), 10);
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+/// Environment variable pointing to an application default credentials file, as set up by
+/// `gcloud auth application-default login` or by operators.
+pub const GOOGLE_APPLICATION_CREDENTIALS_ENV_VAR: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+
+/// Where Google Cloud Storage requests get their credentials from, in order of precedence:
+///
+/// 1. With `--no-sign-request`, none. Public buckets can then only be read, through
+///    [`HttpDownloaderBuilder::make_http`](crate::object_store::http::HttpDownloaderBuilder).
+/// 2. The service account key file in `--google-service-account`, if set.
+/// 3. Application default credentials: the file in `--google-application-credentials` or
+///    `GOOGLE_APPLICATION_CREDENTIALS` if either is set, then the file written by
+///    `gcloud auth application-default login` if there is one, and finally the metadata server
+///    on GCE and GKE. On GKE with workload identity, the metadata server hands out tokens for
+///    the Kubernetes service account's bound Google service account.
+///
+/// Tokens are cached, and refreshed by the store before they expire.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GcsCredentialSource {
+    Anonymous,
+    ServiceAccount(String),
+    ApplicationDefault(Option<String>),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::gcs_credentials::GcsCredentialSource;
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+
+    #[test]
+    fn test_credential_source_precedence() {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::GCS),
+            google_application_credentials: Some("adc.json".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.gcs_credential_source(),
+            GcsCredentialSource::ApplicationDefault(Some("adc.json".to_string()))
+        );
+
+        let config = ObjectStoreConfig {
+            google_service_account: Some("service_account.json".to_string()),
+            ..config
+        };
+        assert_eq!(
+            config.gcs_credential_source(),
+            GcsCredentialSource::ServiceAccount("service_account.json".to_string())
+        );
+
+        let config = ObjectStoreConfig {
+            no_sign_request: true,
+            ..config
+        };
+        assert_eq!(
+            config.gcs_credential_source(),
+            GcsCredentialSource::Anonymous
+        );
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! On-disk cache of the objects downloaded by the HTTP stores, for objects polled over and over,
+//! like the MANIFEST of an archive. Gets of a cached object carry its `ETag` and `Last-Modified`,
+//! so that the store answers `304 Not Modified` and the object is served from the cache until it
+//! changes.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{HashFunction, Sha256};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+#[derive(Debug)]
+pub(crate) struct EtagCache {
+    directory: PathBuf,
+}
+
+/// Object cached along with the validators of its version, written as a single file so that a
+/// body is never paired with the `ETag` of another version.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CachedObject {
+    pub e_tag: String,
+    pub last_modified: DateTime<Utc>,
+    pub bytes: Vec<u8>,
+}
+
+impl CachedObject {
+    /// Value of the `If-Modified-Since` header, as an HTTP date.
+    pub fn if_modified_since(&self) -> String {
+        self.last_modified
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    }
+}
+
+impl EtagCache {
+    pub fn new(directory: &Path) -> Result<Self> {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create ETag cache dir {}", directory.display()))?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+        })
+    }
+
+    /// The cached copy of the object at `url`, if any. Unreadable entries are treated as missing,
+    /// so the object is downloaded again and the entry overwritten.
+    pub async fn get(&self, url: &str) -> Option<CachedObject> {
+        let path = self.entry_path(url);
+        let entry = match tokio::fs::read(&path).await {
+            Ok(entry) => entry,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                debug!("Failed to read ETag cache entry {}: {e}", path.display());
+                return None;
+            }
+        };
+        match bcs::from_bytes(&entry) {
+            Ok(object) => Some(object),
+            Err(e) => {
+                debug!("Invalid ETag cache entry {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Cache `bytes` as the version of the object at `url` with the given validators.
+    pub async fn put(
+        &self,
+        url: &str,
+        e_tag: &str,
+        last_modified: DateTime<Utc>,
+        bytes: &Bytes,
+    ) -> Result<()> {
+        let entry = bcs::to_bytes(&CachedObject {
+            e_tag: e_tag.to_string(),
+            last_modified,
+            bytes: bytes.to_vec(),
+        })?;
+        // Written to a temporary file first, so that concurrent gets never read a partial entry
+        let path = self.entry_path(url);
+        let tmp_path = path.with_extension(format!("{:016x}.tmp", rand::random::<u64>()));
+        tokio::fs::write(&tmp_path, entry)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        if let Err(e) = tokio::fs::rename(&tmp_path, &path).await {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+        }
+        Ok(())
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        let key = Hex::encode(Sha256::digest(url.as_bytes()).digest);
+        self.directory.join(key).with_extension("entry")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::http::etag_cache::EtagCache;
+    use bytes::Bytes;
+    use chrono::{TimeZone, Utc};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_etag_cache() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let cache = EtagCache::new(dir.path())?;
+        let url = "https://storage.googleapis.com/archive/MANIFEST";
+        assert!(cache.get(url).await.is_none());
+
+        let last_modified = Utc.with_ymd_and_hms(2023, 10, 5, 7, 3, 9).unwrap();
+        cache
+            .put(url, "\"v1\"", last_modified, &Bytes::from("manifest"))
+            .await?;
+        cache
+            .put(url, "\"v2\"", last_modified, &Bytes::from("manifest v2"))
+            .await?;
+        let cached = cache.get(url).await.unwrap();
+        assert_eq!(cached.e_tag, "\"v2\"");
+        assert_eq!(cached.bytes, b"manifest v2");
+        assert_eq!(cached.if_modified_since(), "Thu, 05 Oct 2023 07:03:09 GMT");
+        assert!(cache
+            .get("https://storage.googleapis.com/archive/1.chk")
+            .await
+            .is_none());
+
+        // Corrupt entries are misses
+        for entry in std::fs::read_dir(dir.path())? {
+            std::fs::write(entry?.path(), b"corrupt")?;
+        }
+        assert!(cache.get(url).await.is_none());
+        Ok(())
+    }
+}
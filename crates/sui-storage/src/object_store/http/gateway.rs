@@ -0,0 +1,165 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Uploads through a gateway handing out signed upload URLs, so that contributors to an archive
+//! mirror can write to it without holding credentials of its bucket.
+//!
+//! For every object, the uploader asks the gateway for a URL with a `POST` of the object's path,
+//! size and SHA-256 digest to `<gateway>/sign-upload`, authenticated by the contributor's bearer
+//! token if one is configured. The gateway decides whether to accept the object and answers with
+//! the URL and the headers to `PUT` it with, e.g. a presigned S3 URL.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{HashFunction, Sha256};
+use object_store::path::Path;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, ClientBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::http::DEFAULT_USER_AGENT;
+use crate::object_store::ObjectStorePutExt;
+
+#[derive(Serialize)]
+struct SignUploadRequest<'a> {
+    path: &'a str,
+    size: usize,
+    /// Hex encoded SHA-256 digest of the object
+    sha256: String,
+}
+
+#[derive(Deserialize)]
+struct SignUploadResponse {
+    url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+/// Writer of objects through a signed-upload gateway, see the [module documentation](self).
+#[derive(Debug)]
+pub struct UploadGateway {
+    url: String,
+    token: Option<String>,
+    client: Client,
+}
+
+impl UploadGateway {
+    /// Requests are sent with a client from `builder`, e.g. with proxy or TLS settings.
+    pub fn new(url: &str, token: Option<String>, builder: ClientBuilder) -> Result<Self> {
+        let client = builder.user_agent(DEFAULT_USER_AGENT).build()?;
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            client,
+        })
+    }
+
+    async fn sign_upload(
+        &self,
+        location: &Path,
+        bytes: &Bytes,
+    ) -> ObjectStoreResult<SignUploadResponse> {
+        let request = SignUploadRequest {
+            path: location.as_ref(),
+            size: bytes.len(),
+            sha256: Hex::encode(Sha256::digest(bytes).digest),
+        };
+        let mut builder = self
+            .client
+            .post(format!("{}/sign-upload", self.url))
+            .json(&request);
+        if let Some(token) = &self.token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder
+            .send()
+            .await
+            .with_context(|| format!("Failed to request an upload url for {location}"))?;
+        match response.status() {
+            status if status.is_success() => Ok(response.json().await?),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(ObjectStoreError::PermissionDenied(anyhow!(
+                    "Upload gateway {} refused to sign the upload of {location}",
+                    self.url
+                )))
+            }
+            status => Err(ObjectStoreError::from_status(
+                status,
+                anyhow!("Failed to request an upload url for {location} with status: {status}"),
+            )),
+        }
+    }
+}
+
+fn upload_headers(headers: HashMap<String, String>) -> Result<HeaderMap> {
+    headers
+        .into_iter()
+        .map(|(name, value)| {
+            Ok((
+                HeaderName::try_from(name.as_str())
+                    .with_context(|| format!("Invalid upload header {name}"))?,
+                HeaderValue::try_from(value.as_str())
+                    .with_context(|| format!("Invalid value of upload header {name}"))?,
+            ))
+        })
+        .collect()
+}
+
+impl fmt::Display for UploadGateway {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "upload-gateway:{}", self.url)
+    }
+}
+
+#[async_trait]
+impl ObjectStorePutExt for UploadGateway {
+    async fn put_bytes(&self, location: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
+        let signed = self.sign_upload(location, &bytes).await?;
+        let status = self
+            .client
+            .put(&signed.url)
+            .headers(upload_headers(signed.headers)?)
+            .body(bytes)
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload {location}"))?
+            .status();
+        if !status.is_success() {
+            return Err(ObjectStoreError::from_status(
+                status,
+                anyhow!("Failed to upload {location} with status: {status}"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::http::gateway::{upload_headers, SignUploadResponse};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_sign_upload_response() -> anyhow::Result<()> {
+        let response: SignUploadResponse = serde_json::from_str(
+            r#"{"url": "https://mirror.example/archive/epoch_0/1.chk?sig=abc",
+                "headers": {"x-amz-server-side-encryption": "AES256"}}"#,
+        )?;
+        let headers = upload_headers(response.headers)?;
+        assert_eq!(headers["x-amz-server-side-encryption"], "AES256");
+
+        let response: SignUploadResponse =
+            serde_json::from_str(r#"{"url": "https://mirror.example/1.chk"}"#)?;
+        assert!(response.headers.is_empty());
+
+        let invalid = HashMap::from([("bad header".to_string(), "value".to_string())]);
+        assert!(upload_headers(invalid).is_err());
+        Ok(())
+    }
+}
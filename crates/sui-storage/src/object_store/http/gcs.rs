@@ -1,17 +1,22 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::object_store::http::{get, DEFAULT_USER_AGENT};
-use crate::object_store::ObjectStoreGetExt;
-use anyhow::Result;
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::http::etag_cache::EtagCache;
+use crate::object_store::http::{get, get_range, get_ranges, head, DEFAULT_USER_AGENT};
+use crate::object_store::{ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreHeadExt};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use object_store::path::Path;
-use object_store::GetResult;
+use object_store::{GetResult, ObjectMeta};
 use percent_encoding::{percent_encode, utf8_percent_encode, NON_ALPHANUMERIC};
 use reqwest::Client;
 use reqwest::ClientBuilder;
 use std::fmt;
+use std::ops::Range;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -21,10 +26,11 @@ struct GoogleCloudStorageClient {
 }
 
 impl GoogleCloudStorageClient {
-    pub fn new(bucket: &str) -> Result<Self> {
-        let mut builder = ClientBuilder::new();
-        builder = builder.user_agent(DEFAULT_USER_AGENT);
-        let client = builder.https_only(false).build()?;
+    pub fn new(bucket: &str, builder: ClientBuilder) -> Result<Self> {
+        let client = builder
+            .user_agent(DEFAULT_USER_AGENT)
+            .https_only(false)
+            .build()?;
         let bucket_name_encoded = percent_encode(bucket.as_bytes(), NON_ALPHANUMERIC).to_string();
 
         Ok(Self {
@@ -33,9 +39,32 @@ impl GoogleCloudStorageClient {
         })
     }
 
-    async fn get(&self, path: &Path) -> Result<GetResult> {
+    async fn get(
+        &self,
+        path: &Path,
+        etag_cache: Option<&EtagCache>,
+    ) -> ObjectStoreResult<GetResult> {
         let url = self.object_url(path);
-        get(&url, "gcs", path, &self.client).await
+        get(&url, "gcs", path, &self.client, etag_cache).await
+    }
+
+    async fn get_range(&self, path: &Path, range: Range<usize>) -> ObjectStoreResult<Bytes> {
+        let url = self.object_url(path);
+        get_range(&url, range, &self.client).await
+    }
+
+    async fn get_ranges(
+        &self,
+        path: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        let url = self.object_url(path);
+        get_ranges(&url, ranges, &self.client).await
+    }
+
+    async fn head(&self, path: &Path) -> ObjectStoreResult<Option<ObjectMeta>> {
+        let url = self.object_url(path);
+        head(&url, path, &self.client).await
     }
 
     fn object_url(&self, path: &Path) -> String {
@@ -51,15 +80,24 @@ impl GoogleCloudStorageClient {
 #[derive(Debug)]
 pub struct GoogleCloudStorage {
     client: Arc<GoogleCloudStorageClient>,
+    etag_cache: Option<EtagCache>,
 }
 
 impl GoogleCloudStorage {
-    pub fn new(bucket: &str) -> Result<Self> {
-        let gcs_client = GoogleCloudStorageClient::new(bucket)?;
+    /// Requests are sent with a client from `builder`, e.g. with proxy or TLS settings.
+    pub fn new(bucket: &str, builder: ClientBuilder) -> Result<Self> {
+        let gcs_client = GoogleCloudStorageClient::new(bucket, builder)?;
         Ok(GoogleCloudStorage {
             client: Arc::new(gcs_client),
+            etag_cache: None,
         })
     }
+
+    /// Serve objects unchanged since they were last downloaded from `etag_cache`, if any.
+    pub(crate) fn with_etag_cache(mut self, etag_cache: Option<EtagCache>) -> Self {
+        self.etag_cache = etag_cache;
+        self
+    }
 }
 
 impl fmt::Display for GoogleCloudStorage {
@@ -70,9 +108,50 @@ impl fmt::Display for GoogleCloudStorage {
 
 #[async_trait]
 impl ObjectStoreGetExt for GoogleCloudStorage {
-    async fn get_bytes(&self, location: &Path) -> Result<Bytes> {
-        let result = self.client.get(location).await?;
+    async fn get_bytes(&self, location: &Path) -> ObjectStoreResult<Bytes> {
+        let result = self.client.get(location, self.etag_cache.as_ref()).await?;
         let bytes = result.bytes().await?;
         Ok(bytes)
     }
+
+    async fn get_stream(
+        &self,
+        location: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        let result = self.client.get(location, self.etag_cache.as_ref()).await?;
+        Ok(result.into_stream().map_err(ObjectStoreError::from).boxed())
+    }
+}
+
+#[async_trait]
+impl ObjectStoreGetRangeExt for GoogleCloudStorage {
+    async fn get_byte_range(
+        &self,
+        location: &Path,
+        range: Range<usize>,
+    ) -> ObjectStoreResult<Bytes> {
+        self.client.get_range(location, range).await
+    }
+
+    async fn get_byte_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        self.client.get_ranges(location, ranges).await
+    }
+}
+
+#[async_trait]
+impl ObjectStoreHeadExt for GoogleCloudStorage {
+    async fn head_object(&self, location: &Path) -> ObjectStoreResult<ObjectMeta> {
+        self.client
+            .head(location)
+            .await?
+            .ok_or_else(|| ObjectStoreError::NotFound(anyhow!("File not found: {location}")))
+    }
+
+    async fn exists(&self, location: &Path) -> ObjectStoreResult<bool> {
+        Ok(self.client.head(location).await?.is_some())
+    }
 }
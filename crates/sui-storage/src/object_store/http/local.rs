@@ -1,14 +1,18 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
 use crate::object_store::util::path_to_filesystem;
-use crate::object_store::ObjectStoreGetExt;
+use crate::object_store::{ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreHeadExt};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use object_store::path::Path;
+use object_store::ObjectMeta;
 use std::fs::File;
-use std::io::Read;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::{fmt, fs};
 
@@ -35,16 +39,69 @@ impl fmt::Display for LocalStorage {
 
 #[async_trait]
 impl ObjectStoreGetExt for LocalStorage {
-    async fn get_bytes(&self, location: &Path) -> Result<Bytes> {
+    async fn get_bytes(&self, location: &Path) -> ObjectStoreResult<Bytes> {
         let path_to_filesystem = path_to_filesystem(self.root.clone(), location)?;
         let handle = tokio::task::spawn_blocking(move || {
-            let mut f = File::open(path_to_filesystem)
-                .map_err(|e| anyhow!("Failed to open file with error: {}", e.to_string()))?;
+            let mut f = File::open(path_to_filesystem).context("Failed to open file")?;
             let mut buf = vec![];
-            f.read_to_end(&mut buf)
-                .context(anyhow!("Failed to read file"))?;
+            f.read_to_end(&mut buf).context("Failed to read file")?;
             Ok(buf.into())
         });
-        handle.await?
+        handle.await.map_err(anyhow::Error::from)?
+    }
+}
+
+#[async_trait]
+impl ObjectStoreGetRangeExt for LocalStorage {
+    async fn get_byte_range(
+        &self,
+        location: &Path,
+        range: Range<usize>,
+    ) -> ObjectStoreResult<Bytes> {
+        let path_to_filesystem = path_to_filesystem(self.root.clone(), location)?;
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut f = File::open(path_to_filesystem).context("Failed to open file")?;
+            f.seek(SeekFrom::Start(range.start as u64))
+                .context("Failed to seek file")?;
+            let mut buf = vec![0; range.len()];
+            // A range past the end of the file fails for good, unlike a read cut short over the
+            // network, so the I/O error isn't kept.
+            f.read_exact(&mut buf).map_err(|e| {
+                ObjectStoreError::Other(anyhow!("Failed to read range {range:?} of file: {e}"))
+            })?;
+            Ok(buf.into())
+        });
+        handle.await.map_err(anyhow::Error::from)?
+    }
+}
+
+#[async_trait]
+impl ObjectStoreHeadExt for LocalStorage {
+    async fn head_object(&self, location: &Path) -> ObjectStoreResult<ObjectMeta> {
+        let path_to_filesystem = path_to_filesystem(self.root.clone(), location)?;
+        let metadata = fs::metadata(path_to_filesystem).context("Failed to head file")?;
+        if !metadata.is_file() {
+            return Err(ObjectStoreError::NotFound(anyhow!(
+                "Not a file: {location}"
+            )));
+        }
+        Ok(ObjectMeta {
+            location: location.clone(),
+            last_modified: metadata
+                .modified()
+                .map(DateTime::<Utc>::from)
+                .context(anyhow!("Failed to read modification time"))?,
+            size: metadata.len() as usize,
+            e_tag: None,
+        })
+    }
+
+    async fn exists(&self, location: &Path) -> ObjectStoreResult<bool> {
+        let path_to_filesystem = path_to_filesystem(self.root.clone(), location)?;
+        match fs::metadata(path_to_filesystem) {
+            Ok(metadata) => Ok(metadata.is_file()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(ObjectStoreError::from(e).context("Failed to head file")),
+        }
     }
 }
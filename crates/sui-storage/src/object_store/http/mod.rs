@@ -1,23 +1,45 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+mod auth;
+mod etag_cache;
+mod gateway;
 mod gcs;
 mod local;
+mod retry;
 mod s3;
 
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::http::auth::auth_headers;
+use crate::object_store::http::etag_cache::EtagCache;
+use crate::object_store::http::gateway::UploadGateway;
 use crate::object_store::http::gcs::GoogleCloudStorage;
 use crate::object_store::http::local::LocalStorage;
+use crate::object_store::http::retry::RetryingDownloader;
 use crate::object_store::http::s3::AmazonS3;
-use crate::object_store::{ObjectStoreConfig, ObjectStoreGetExt, ObjectStoreType};
+use crate::object_store::proxy::{proxy_url, url_host};
+use crate::object_store::tls;
+use crate::object_store::{
+    ObjectStoreConfig, ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreHeadExt,
+    ObjectStorePutExt, ObjectStoreType, GCS_HOST,
+};
 use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::{StreamExt, TryStreamExt};
 use object_store::path::Path;
 use object_store::{Error, GetResult, GetResultPayload, ObjectMeta};
-use reqwest::header::{HeaderMap, CONTENT_LENGTH, ETAG, LAST_MODIFIED};
-use reqwest::{Client, Method};
+use reqwest::header::{
+    HeaderMap, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, RANGE,
+};
+use reqwest::redirect::Policy;
+use reqwest::{Client, ClientBuilder, Method, Proxy, StatusCode};
+use tracing::warn;
 
 // http://docs.aws.amazon.com/general/latest/gr/sigv4-create-canonical-request.html
 //
@@ -33,6 +55,8 @@ static DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CAR
 
 pub trait HttpDownloaderBuilder {
     fn make_http(&self) -> Result<Arc<dyn ObjectStoreGetExt>>;
+    fn make_http_range(&self) -> Result<Arc<dyn ObjectStoreGetRangeExt>>;
+    fn make_http_head(&self) -> Result<Arc<dyn ObjectStoreHeadExt>>;
 }
 
 impl HttpDownloaderBuilder for ObjectStoreConfig {
@@ -41,42 +65,172 @@ impl HttpDownloaderBuilder for ObjectStoreConfig {
             Some(ObjectStoreType::File) => {
                 Ok(LocalStorage::new(self.directory.as_ref().unwrap()).map(Arc::new)?)
             }
-            Some(ObjectStoreType::S3) => {
-                let bucket_endpoint = if let Some(endpoint) = &self.aws_endpoint {
-                    if self.aws_virtual_hosted_style_request {
-                        endpoint.clone()
-                    } else {
-                        let bucket = self.bucket.as_ref().unwrap();
-                        format!("{endpoint}/{bucket}")
-                    }
-                } else {
-                    let bucket = self.bucket.as_ref().unwrap();
-                    let region = self.aws_region.as_ref().unwrap();
-                    if self.aws_virtual_hosted_style_request {
-                        format!("https://{bucket}.s3.{region}.amazonaws.com")
-                    } else {
-                        format!("https://s3.{region}.amazonaws.com/{bucket}")
-                    }
-                };
-                Ok(AmazonS3::new(&bucket_endpoint).map(Arc::new)?)
+            Some(ObjectStoreType::S3) => Ok(Arc::new(self.http_retrying(self.http_s3()?))),
+            Some(ObjectStoreType::GCS) => Ok(Arc::new(self.http_retrying(self.http_gcs()?))),
+            _ => Err(anyhow!("At least one storage backend should be provided")),
+        }
+    }
+
+    fn make_http_range(&self) -> Result<Arc<dyn ObjectStoreGetRangeExt>> {
+        match self.object_store {
+            Some(ObjectStoreType::File) => {
+                Ok(LocalStorage::new(self.directory.as_ref().unwrap()).map(Arc::new)?)
             }
-            Some(ObjectStoreType::GCS) => {
-                Ok(GoogleCloudStorage::new(self.bucket.as_ref().unwrap()).map(Arc::new)?)
+            Some(ObjectStoreType::S3) => Ok(Arc::new(self.http_retrying(self.http_s3()?))),
+            Some(ObjectStoreType::GCS) => Ok(Arc::new(self.http_retrying(self.http_gcs()?))),
+            _ => Err(anyhow!("At least one storage backend should be provided")),
+        }
+    }
+
+    fn make_http_head(&self) -> Result<Arc<dyn ObjectStoreHeadExt>> {
+        match self.object_store {
+            Some(ObjectStoreType::File) => {
+                Ok(LocalStorage::new(self.directory.as_ref().unwrap()).map(Arc::new)?)
             }
+            Some(ObjectStoreType::S3) => Ok(Arc::new(self.http_retrying(self.http_s3()?))),
+            Some(ObjectStoreType::GCS) => Ok(Arc::new(self.http_retrying(self.http_gcs()?))),
             _ => Err(anyhow!("At least one storage backend should be provided")),
         }
     }
 }
 
+pub trait HttpUploaderBuilder {
+    /// Writer of objects through the signed-upload gateway at `--http-upload-gateway-url`.
+    fn make_http_upload(&self) -> Result<Arc<dyn ObjectStorePutExt>>;
+}
+
+impl HttpUploaderBuilder for ObjectStoreConfig {
+    fn make_http_upload(&self) -> Result<Arc<dyn ObjectStorePutExt>> {
+        let url = self
+            .http_upload_gateway_url
+            .as_ref()
+            .ok_or_else(|| anyhow!("No upload gateway configured"))?;
+        let builder = self.http_client_builder(&url_host(url)?)?;
+        Ok(Arc::new(UploadGateway::new(
+            url,
+            self.http_upload_gateway_token.clone(),
+            builder,
+        )?))
+    }
+}
+
+impl ObjectStoreConfig {
+    fn http_s3(&self) -> Result<AmazonS3> {
+        let endpoint = self.s3_bucket_endpoint();
+        let builder = self.http_download_client_builder(&url_host(&endpoint)?)?;
+        Ok(AmazonS3::new(&endpoint, builder)?.with_etag_cache(self.http_etag_cache()?))
+    }
+    fn http_gcs(&self) -> Result<GoogleCloudStorage> {
+        let builder = self.http_download_client_builder(GCS_HOST)?;
+        Ok(
+            GoogleCloudStorage::new(self.bucket.as_ref().unwrap(), builder)?
+                .with_etag_cache(self.http_etag_cache()?),
+        )
+    }
+    fn http_retrying<S: std::fmt::Display>(&self, store: S) -> RetryingDownloader<S> {
+        RetryingDownloader::new(store, self.retry_config())
+    }
+    fn http_etag_cache(&self) -> Result<Option<EtagCache>> {
+        self.http_etag_cache_dir
+            .as_deref()
+            .map(EtagCache::new)
+            .transpose()
+    }
+    /// Builder of the client of a downloader from `host`, which also sends the credentials of
+    /// `--http-bearer-token`, `--http-basic-auth-username` or `--http-header` with every request,
+    /// and follows at most `--http-max-redirects` redirects.
+    fn http_download_client_builder(&self, host: &str) -> Result<ClientBuilder> {
+        let mut builder = self
+            .http_client_builder(host)?
+            .default_headers(auth_headers(&self.http_auth)?);
+        if let Some(max_redirects) = self.http_max_redirects {
+            builder = builder.redirect(match max_redirects {
+                0 => Policy::none(),
+                max_redirects => Policy::limited(max_redirects),
+            });
+        }
+        Ok(builder)
+    }
+    /// Builder of the client of a downloader from `host`, with the proxy, timeout and TLS
+    /// settings.
+    pub(crate) fn http_client_builder(&self, host: &str) -> Result<ClientBuilder> {
+        let mut builder = ClientBuilder::new();
+        if let Some(proxy_url) = proxy_url(&self.object_store_client, host)? {
+            builder = builder.proxy(Proxy::all(proxy_url)?);
+        }
+        if let Some(secs) = self.object_store_client.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.object_store_client.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        tls::apply_to_http_client(&self.object_store_client, builder)
+    }
+    fn s3_bucket_endpoint(&self) -> String {
+        if let Some(endpoint) = &self.aws_endpoint {
+            if self.aws_virtual_hosted_style_request {
+                endpoint.clone()
+            } else {
+                let bucket = self.bucket.as_ref().unwrap();
+                format!("{endpoint}/{bucket}")
+            }
+        } else {
+            let bucket = self.bucket.as_ref().unwrap();
+            let region = self.aws_region.as_ref().unwrap();
+            if self.aws_virtual_hosted_style_request {
+                format!("https://{bucket}.s3.{region}.amazonaws.com")
+            } else {
+                format!("https://s3.{region}.amazonaws.com/{bucket}")
+            }
+        }
+    }
+}
+
+/// Get the object at `url`. With an `etag_cache`, the object is served from the cache if the
+/// store answers that it is unchanged, and otherwise read in full to be cached.
 async fn get(
     url: &str,
     store: &'static str,
     location: &Path,
     client: &Client,
-) -> Result<GetResult> {
-    let request = client.request(Method::GET, url);
+    etag_cache: Option<&EtagCache>,
+) -> ObjectStoreResult<GetResult> {
+    let cached = match etag_cache {
+        Some(etag_cache) => etag_cache.get(url).await,
+        None => None,
+    };
+    let mut request = client.request(Method::GET, url);
+    if let Some(cached) = &cached {
+        request = request
+            .header(IF_NONE_MATCH, &cached.e_tag)
+            .header(IF_MODIFIED_SINCE, cached.if_modified_since());
+    }
     let response = request.send().await.context("failed to get")?;
+    let status = response.status();
+    if let (StatusCode::NOT_MODIFIED, Some(cached)) = (status, cached) {
+        let meta = ObjectMeta {
+            location: location.clone(),
+            last_modified: cached.last_modified,
+            size: cached.bytes.len(),
+            e_tag: Some(cached.e_tag),
+        };
+        return Ok(bytes_result(meta, cached.bytes.into()));
+    }
+    if !status.is_success() {
+        return Err(ObjectStoreError::from_status(
+            status,
+            anyhow!("Failed to get {location} with status: {status}"),
+        ));
+    }
     let meta = header_meta(location, response.headers()).context("Failed to get header")?;
+    if let Some(etag_cache) = etag_cache {
+        let bytes = response.bytes().await?;
+        let e_tag = meta.e_tag.as_deref().unwrap_or_default();
+        if let Err(e) = etag_cache.put(url, e_tag, meta.last_modified, &bytes).await {
+            warn!("Failed to cache {location}: {e:#}");
+        }
+        return Ok(bytes_result(meta, bytes));
+    }
     let stream = response
         .bytes_stream()
         .map_err(|source| Error::Generic {
@@ -91,6 +245,161 @@ async fn get(
     })
 }
 
+fn bytes_result(meta: ObjectMeta, bytes: Bytes) -> GetResult {
+    GetResult {
+        range: 0..bytes.len(),
+        payload: GetResultPayload::Stream(futures::stream::once(async move { Ok(bytes) }).boxed()),
+        meta,
+    }
+}
+
+/// Fetch only the bytes in `range` of the object at `url`, with an HTTP range request.
+async fn get_range(url: &str, range: Range<usize>, client: &Client) -> ObjectStoreResult<Bytes> {
+    let mut bytes = get_ranges(url, &[range], client).await?;
+    Ok(bytes.pop().expect("one range was requested"))
+}
+
+/// Fetch the bytes in each of `ranges` of the object at `url`. Ranges close to each other are
+/// fetched with a single range request, and none are fetched after a server ignoring the range
+/// header returned the whole object.
+async fn get_ranges(
+    url: &str,
+    ranges: &[Range<usize>],
+    client: &Client,
+) -> ObjectStoreResult<Vec<Bytes>> {
+    let mut fetched: Vec<(Range<usize>, Bytes)> = vec![];
+    for range in coalesce_ranges(ranges) {
+        match fetch_range(url, range.clone(), client).await? {
+            RangeBytes::Partial(bytes) => fetched.push((range, bytes)),
+            RangeBytes::Whole(bytes) => {
+                fetched = vec![(0..bytes.len(), bytes)];
+                break;
+            }
+        }
+    }
+    ranges
+        .iter()
+        .map(|range| {
+            if range.is_empty() {
+                return Ok(Bytes::new());
+            }
+            fetched
+                .iter()
+                .find(|(fetched, _)| fetched.start <= range.start && range.end <= fetched.end)
+                .map(|(fetched, bytes)| {
+                    bytes.slice(range.start - fetched.start..range.end - fetched.start)
+                })
+                .ok_or_else(|| {
+                    ObjectStoreError::Other(anyhow!(
+                        "Range {range:?} is out of bounds of object of {} bytes",
+                        fetched.last().map_or(0, |(_, bytes)| bytes.len())
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Ranges at most this far apart are fetched with a single request, as it is cheaper to download
+/// the bytes in between than to send another request.
+const RANGE_COALESCE_GAP: usize = 1024 * 1024;
+
+/// The non-empty `ranges`, sorted and merged with the ranges close enough to them.
+fn coalesce_ranges(ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut sorted: Vec<_> = ranges.iter().filter(|r| !r.is_empty()).cloned().collect();
+    sorted.sort_by_key(|range| range.start);
+    let mut coalesced: Vec<Range<usize>> = vec![];
+    for range in sorted {
+        match coalesced.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(RANGE_COALESCE_GAP) => {
+                last.end = last.end.max(range.end);
+            }
+            _ => coalesced.push(range),
+        }
+    }
+    coalesced
+}
+
+enum RangeBytes {
+    /// The bytes of the requested range
+    Partial(Bytes),
+    /// The whole object, from a server ignoring the range header
+    Whole(Bytes),
+}
+
+async fn fetch_range(
+    url: &str,
+    range: Range<usize>,
+    client: &Client,
+) -> ObjectStoreResult<RangeBytes> {
+    let response = client
+        .request(Method::GET, url)
+        .header(RANGE, format!("bytes={}-{}", range.start, range.end - 1))
+        .send()
+        .await
+        .context("failed to get range")?;
+    match response.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            // A server may send a different range than requested, e.g. one cut at the end of the
+            // object
+            let start = content_range_start(response.headers());
+            let bytes = response.bytes().await?;
+            if start != Some(range.start) || bytes.len() != range.len() {
+                return Err(ObjectStoreError::corrupt(anyhow!(
+                    "Expected {} bytes for range {range:?}, got {} from {start:?}",
+                    range.len(),
+                    bytes.len()
+                )));
+            }
+            Ok(RangeBytes::Partial(bytes))
+        }
+        StatusCode::OK => Ok(RangeBytes::Whole(response.bytes().await?)),
+        StatusCode::RANGE_NOT_SATISFIABLE => Err(ObjectStoreError::Other(anyhow!(
+            "Range {range:?} is out of bounds of object at {url}"
+        ))),
+        status => Err(ObjectStoreError::from_status(
+            status,
+            anyhow!("Failed to get range {range:?} with status: {status}"),
+        )),
+    }
+}
+
+/// First byte of the range of a partial response, from its `Content-Range: bytes <first>-<last>/
+/// <length>` header.
+fn content_range_start(headers: &HeaderMap) -> Option<usize> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split_once('-')?
+        .0
+        .parse()
+        .ok()
+}
+
+/// Metadata of the object at `url` from a `HEAD` request, `None` if there is no object there.
+async fn head(
+    url: &str,
+    location: &Path,
+    client: &Client,
+) -> ObjectStoreResult<Option<ObjectMeta>> {
+    let response = client
+        .request(Method::HEAD, url)
+        .send()
+        .await
+        .context("failed to head")?;
+    match response.status() {
+        status if status.is_success() => Ok(Some(
+            header_meta(location, response.headers()).context("Failed to get header")?,
+        )),
+        StatusCode::NOT_FOUND => Ok(None),
+        status => Err(ObjectStoreError::from_status(
+            status,
+            anyhow!("Failed to head {location} with status: {status}"),
+        )),
+    }
+}
+
 fn header_meta(location: &Path, headers: &HeaderMap) -> Result<ObjectMeta> {
     let last_modified = headers
         .get(LAST_MODIFIED)
@@ -121,12 +430,96 @@ fn header_meta(location: &Path, headers: &HeaderMap) -> Result<ObjectMeta> {
 
 #[cfg(test)]
 mod tests {
-    use crate::object_store::http::HttpDownloaderBuilder;
+    use crate::object_store::http::{coalesce_ranges, HttpDownloaderBuilder, RANGE_COALESCE_GAP};
     use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use hyper::header::{CONTENT_RANGE, LOCATION, RANGE};
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server, StatusCode};
     use object_store::path::Path;
+    use std::convert::Infallible;
     use std::fs;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
     use tempfile::TempDir;
 
+    const CONTENTS: &[u8] = b"Lorem ipsum";
+
+    /// Response to a get of [`CONTENTS`], with only the requested range if `honor_ranges`.
+    fn respond(request: &Request<Body>, honor_ranges: bool) -> Response<Body> {
+        if request.uri().path().ends_with("/moved") {
+            return Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(LOCATION, "file1")
+                .body(Body::empty())
+                .unwrap();
+        }
+        let range = request
+            .headers()
+            .get(RANGE)
+            .filter(|_| honor_ranges)
+            .and_then(|range| {
+                let (first, last) = range
+                    .to_str()
+                    .ok()?
+                    .strip_prefix("bytes=")?
+                    .split_once('-')?;
+                Some((first.parse::<usize>().ok()?, last.parse::<usize>().ok()?))
+            });
+        match range {
+            None => Response::new(Body::from(CONTENTS)),
+            Some((first, last)) if last < CONTENTS.len() => Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    CONTENT_RANGE,
+                    format!("bytes {first}-{last}/{}", CONTENTS.len()),
+                )
+                .body(Body::from(&CONTENTS[first..=last]))
+                .unwrap(),
+            Some(_) => Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .body(Body::empty())
+                .unwrap(),
+        }
+    }
+
+    /// Local server of [`CONTENTS`] at every path, failing the first `failures` requests with a
+    /// 502, and the number of requests it has served.
+    fn serve(honor_ranges: bool, failures: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let counter = counter.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let response = if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                        Response::builder()
+                            .status(StatusCode::BAD_GATEWAY)
+                            .body(Body::empty())
+                            .unwrap()
+                    } else {
+                        respond(&request, honor_ranges)
+                    };
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, requests)
+    }
+
+    fn http_config(addr: SocketAddr) -> ObjectStoreConfig {
+        ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::S3),
+            aws_endpoint: Some(format!("http://{addr}")),
+            bucket: Some("archive".to_string()),
+            object_store_retry_base_delay_ms: 1,
+            ..Default::default()
+        }
+    }
+
     #[tokio::test]
     pub async fn test_local_download() -> anyhow::Result<()> {
         let input = TempDir::new()?;
@@ -151,4 +544,114 @@ mod tests {
         assert_eq!(downloaded.to_vec(), b"Lorem ipsum");
         Ok(())
     }
+
+    #[tokio::test]
+    pub async fn test_local_range_download() -> anyhow::Result<()> {
+        let input = TempDir::new()?;
+        fs::write(input.path().join("file1"), b"Lorem ipsum")?;
+
+        let input_store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(input.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make_http_range()?;
+
+        let path = Path::from("file1");
+        let downloaded = input_store.get_byte_range(&path, 6..11).await?;
+        assert_eq!(downloaded.to_vec(), b"ipsum");
+        let downloaded = input_store.get_byte_ranges(&path, &[0..5, 3..3]).await?;
+        assert_eq!(downloaded, vec![&b"Lorem"[..], &b""[..]]);
+        assert!(input_store.get_byte_range(&path, 6..12).await.is_err());
+        Ok(())
+    }
+    #[tokio::test]
+    pub async fn test_http_range_download() -> anyhow::Result<()> {
+        for honor_ranges in [true, false] {
+            let (addr, requests) = serve(honor_ranges, 0);
+            let input_store = http_config(addr).make_http_range()?;
+
+            let path = Path::from("file1");
+            let downloaded = input_store.get_byte_range(&path, 6..11).await?;
+            assert_eq!(downloaded.to_vec(), b"ipsum");
+            assert_eq!(requests.swap(0, Ordering::SeqCst), 1);
+            // Nearby ranges are fetched at once
+            let downloaded = input_store
+                .get_byte_ranges(&path, &[6..11, 0..5, 3..3, 2..7])
+                .await?;
+            assert_eq!(
+                downloaded,
+                vec![&b"ipsum"[..], &b"Lorem"[..], &b""[..], &b"rem i"[..]]
+            );
+            assert_eq!(requests.swap(0, Ordering::SeqCst), 1);
+            assert!(input_store.get_byte_range(&path, 6..12).await.is_err());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_http_retries_and_redirects() -> anyhow::Result<()> {
+        let path = Path::from("file1");
+        let (addr, requests) = serve(true, 2);
+        let input_store = ObjectStoreConfig {
+            object_store_max_retries: 2,
+            ..http_config(addr)
+        }
+        .make_http_range()?;
+        assert_eq!(input_store.get_byte_range(&path, 0..5).await?, "Lorem");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let (addr, requests) = serve(true, 2);
+        let input_store = ObjectStoreConfig {
+            object_store_max_retries: 1,
+            ..http_config(addr)
+        }
+        .make_http_range()?;
+        assert!(input_store.get_byte_range(&path, 0..5).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let (addr, _) = serve(true, 0);
+        let moved = Path::from("moved");
+        let input_store = http_config(addr).make_http_range()?;
+        assert_eq!(input_store.get_byte_range(&moved, 0..5).await?, "Lorem");
+        let input_store = ObjectStoreConfig {
+            http_max_redirects: Some(0),
+            ..http_config(addr)
+        }
+        .make_http_range()?;
+        assert!(input_store.get_byte_range(&moved, 0..5).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_coalesce_ranges() {
+        let far = 100 + RANGE_COALESCE_GAP + 1;
+        assert_eq!(
+            coalesce_ranges(&[far..far + 10, 50..60, 0..10, 5..5, 20..100]),
+            vec![0..100, far..far + 10]
+        );
+        assert!(coalesce_ranges(&[3..3]).is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_local_head() -> anyhow::Result<()> {
+        let input = TempDir::new()?;
+        fs::write(input.path().join("file1"), b"Lorem ipsum")?;
+        fs::create_dir(input.path().join("child"))?;
+
+        let input_store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(input.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make_http_head()?;
+
+        let meta = input_store.head_object(&Path::from("file1")).await?;
+        assert_eq!(meta.size, 11);
+        assert!(input_store.exists(&Path::from("file1")).await?);
+        assert!(!input_store.exists(&Path::from("file2")).await?);
+        assert!(!input_store.exists(&Path::from("child")).await?);
+        assert!(input_store.head_object(&Path::from("file2")).await.is_err());
+        Ok(())
+    }
 }
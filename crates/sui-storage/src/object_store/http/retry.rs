@@ -0,0 +1,131 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt;
+use std::future::Future;
+use std::ops::Range;
+
+use async_trait::async_trait;
+use backoff::backoff::Backoff;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use object_store::path::Path;
+use object_store::ObjectMeta;
+use tracing::warn;
+
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::retry::RetryConfig;
+use crate::object_store::{ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreHeadExt};
+
+/// HTTP downloader retrying requests that fail with transient errors, e.g. the 502 and 503 of a
+/// CDN failing over to another origin, with exponential backoff.
+///
+/// As for the `object_store` backends, only whole requests are retried: a stream failing while
+/// its body is read fails for good.
+pub(crate) struct RetryingDownloader<S> {
+    inner: S,
+    config: RetryConfig,
+}
+
+impl<S: fmt::Display> RetryingDownloader<S> {
+    pub fn new(inner: S, config: RetryConfig) -> Self {
+        Self { inner, config }
+    }
+
+    async fn retry<R, F, Fut>(
+        &self,
+        operation: &str,
+        location: &Path,
+        mut request: F,
+    ) -> ObjectStoreResult<R>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ObjectStoreResult<R>>,
+    {
+        let mut backoff = self.config.backoff();
+        let mut retries = 0;
+        loop {
+            match request().await {
+                Err(e) if retries < self.config.max_retries && self.is_retryable(&e) => {
+                    let Some(delay) = backoff.next_backoff() else {
+                        return Err(e);
+                    };
+                    if !self.config.spend_budget() {
+                        warn!(
+                            "Not retrying {operation} of {location}, retry budget exhausted: {e}"
+                        );
+                        return Err(e);
+                    }
+                    retries += 1;
+                    warn!(
+                        "Retrying {operation} of {location} from {} in {delay:?} ({retries}/{}) after error: {e}",
+                        self.inner, self.config.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn is_retryable(&self, error: &ObjectStoreError) -> bool {
+        error.is_retryable() || (self.config.retry_not_found && error.is_not_found())
+    }
+}
+
+impl<S: fmt::Display> fmt::Display for RetryingDownloader<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RetryingDownloader({}, {})",
+            self.config.max_retries, self.inner
+        )
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetExt> ObjectStoreGetExt for RetryingDownloader<S> {
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        self.retry("get", src, || self.inner.get_bytes(src)).await
+    }
+
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        self.retry("get", src, || self.inner.get_stream(src)).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetRangeExt> ObjectStoreGetRangeExt for RetryingDownloader<S> {
+    async fn get_byte_range(&self, src: &Path, range: Range<usize>) -> ObjectStoreResult<Bytes> {
+        self.retry("get_range", src, || {
+            self.inner.get_byte_range(src, range.clone())
+        })
+        .await
+    }
+
+    async fn get_byte_ranges(
+        &self,
+        src: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        self.retry("get_ranges", src, || {
+            self.inner.get_byte_ranges(src, ranges)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreHeadExt> ObjectStoreHeadExt for RetryingDownloader<S> {
+    async fn head_object(&self, src: &Path) -> ObjectStoreResult<ObjectMeta> {
+        self.retry("head", src, || self.inner.head_object(src))
+            .await
+    }
+
+    async fn exists(&self, src: &Path) -> ObjectStoreResult<bool> {
+        self.retry("head", src, || self.inner.exists(src)).await
+    }
+}
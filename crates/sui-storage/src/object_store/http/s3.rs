@@ -1,17 +1,24 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::object_store::http::{get, DEFAULT_USER_AGENT, STRICT_PATH_ENCODE_SET};
-use crate::object_store::ObjectStoreGetExt;
-use anyhow::Result;
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::http::etag_cache::EtagCache;
+use crate::object_store::http::{
+    get, get_range, get_ranges, head, DEFAULT_USER_AGENT, STRICT_PATH_ENCODE_SET,
+};
+use crate::object_store::{ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreHeadExt};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use object_store::path::Path;
-use object_store::GetResult;
+use object_store::{GetResult, ObjectMeta};
 use percent_encoding::{utf8_percent_encode, PercentEncode};
 use reqwest::Client;
 use reqwest::ClientBuilder;
 use std::fmt;
+use std::ops::Range;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -21,19 +28,40 @@ pub(crate) struct S3Client {
 }
 
 impl S3Client {
-    pub fn new(endpoint: &str) -> Result<Self> {
-        let mut builder = ClientBuilder::new();
-        builder = builder.user_agent(DEFAULT_USER_AGENT);
-        let client = builder.https_only(false).build()?;
+    pub fn new(endpoint: &str, builder: ClientBuilder) -> Result<Self> {
+        let client = builder
+            .user_agent(DEFAULT_USER_AGENT)
+            .https_only(false)
+            .build()?;
 
         Ok(Self {
             endpoint: endpoint.to_string(),
             client,
         })
     }
-    async fn get(&self, location: &Path) -> Result<GetResult> {
+    async fn get(
+        &self,
+        location: &Path,
+        etag_cache: Option<&EtagCache>,
+    ) -> ObjectStoreResult<GetResult> {
         let url = self.path_url(location);
-        get(&url, "s3", location, &self.client).await
+        get(&url, "s3", location, &self.client, etag_cache).await
+    }
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> ObjectStoreResult<Bytes> {
+        let url = self.path_url(location);
+        get_range(&url, range, &self.client).await
+    }
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        let url = self.path_url(location);
+        get_ranges(&url, ranges, &self.client).await
+    }
+    async fn head(&self, location: &Path) -> ObjectStoreResult<Option<ObjectMeta>> {
+        let url = self.path_url(location);
+        head(&url, location, &self.client).await
     }
     fn path_url(&self, path: &Path) -> String {
         format!("{}/{}", self.endpoint, Self::encode_path(path))
@@ -47,15 +75,24 @@ impl S3Client {
 #[derive(Debug)]
 pub struct AmazonS3 {
     client: Arc<S3Client>,
+    etag_cache: Option<EtagCache>,
 }
 
 impl AmazonS3 {
-    pub fn new(endpoint: &str) -> Result<Self> {
-        let s3_client = S3Client::new(endpoint)?;
+    /// Requests are sent with a client from `builder`, e.g. with proxy or TLS settings.
+    pub fn new(endpoint: &str, builder: ClientBuilder) -> Result<Self> {
+        let s3_client = S3Client::new(endpoint, builder)?;
         Ok(AmazonS3 {
             client: Arc::new(s3_client),
+            etag_cache: None,
         })
     }
+
+    /// Serve objects unchanged since they were last downloaded from `etag_cache`, if any.
+    pub(crate) fn with_etag_cache(mut self, etag_cache: Option<EtagCache>) -> Self {
+        self.etag_cache = etag_cache;
+        self
+    }
 }
 
 impl fmt::Display for AmazonS3 {
@@ -66,9 +103,50 @@ impl fmt::Display for AmazonS3 {
 
 #[async_trait]
 impl ObjectStoreGetExt for AmazonS3 {
-    async fn get_bytes(&self, location: &Path) -> Result<Bytes> {
-        let result = self.client.get(location).await?;
+    async fn get_bytes(&self, location: &Path) -> ObjectStoreResult<Bytes> {
+        let result = self.client.get(location, self.etag_cache.as_ref()).await?;
         let bytes = result.bytes().await?;
         Ok(bytes)
     }
+
+    async fn get_stream(
+        &self,
+        location: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        let result = self.client.get(location, self.etag_cache.as_ref()).await?;
+        Ok(result.into_stream().map_err(ObjectStoreError::from).boxed())
+    }
+}
+
+#[async_trait]
+impl ObjectStoreGetRangeExt for AmazonS3 {
+    async fn get_byte_range(
+        &self,
+        location: &Path,
+        range: Range<usize>,
+    ) -> ObjectStoreResult<Bytes> {
+        self.client.get_range(location, range).await
+    }
+
+    async fn get_byte_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        self.client.get_ranges(location, ranges).await
+    }
+}
+
+#[async_trait]
+impl ObjectStoreHeadExt for AmazonS3 {
+    async fn head_object(&self, location: &Path) -> ObjectStoreResult<ObjectMeta> {
+        self.client
+            .head(location)
+            .await?
+            .ok_or_else(|| ObjectStoreError::NotFound(anyhow!("File not found: {location}")))
+    }
+
+    async fn exists(&self, location: &Path) -> ObjectStoreResult<bool> {
+        Ok(self.client.head(location).await?.is_some())
+    }
 }
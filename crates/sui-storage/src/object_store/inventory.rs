@@ -0,0 +1,147 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Inventory of the objects under a prefix, e.g. of an archive or snapshot bucket, broken down
+//! by the `epoch_N` directories right under the prefix, to monitor its growth and spot missing
+//! epochs.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::ObjectMeta;
+use serde::{Deserialize, Serialize};
+
+use crate::object_store::ObjectStoreListExt;
+
+const EPOCH_DIR_PREFIX: &str = "epoch_";
+
+/// Object count, size and age of a set of objects.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub object_count: usize,
+    pub total_bytes: u64,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+impl UsageSummary {
+    pub fn add(&mut self, object: &ObjectMeta) {
+        self.object_count += 1;
+        self.total_bytes += object.size as u64;
+        self.oldest = Some(
+            self.oldest
+                .map_or(object.last_modified, |t| t.min(object.last_modified)),
+        );
+        self.newest = Some(
+            self.newest
+                .map_or(object.last_modified, |t| t.max(object.last_modified)),
+        );
+    }
+}
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    pub prefix: String,
+    /// All objects under the prefix
+    pub total: UsageSummary,
+    /// Objects of each `epoch_N` directory under the prefix
+    pub epochs: BTreeMap<u64, UsageSummary>,
+    /// Objects outside of epoch directories, e.g. MANIFEST files
+    pub other: UsageSummary,
+    /// Epochs between the first and last epoch directories with no directory
+    pub missing_epochs: Vec<u64>,
+}
+
+/// List every object under `prefix` of `store`, or of the whole store, and summarize them.
+pub async fn inventory<S: ObjectStoreListExt>(
+    store: &S,
+    prefix: Option<&Path>,
+) -> Result<Inventory> {
+    let mut inventory = Inventory {
+        prefix: prefix.map(|prefix| prefix.to_string()).unwrap_or_default(),
+        ..Default::default()
+    };
+    let mut objects = store.list_objects(prefix).await?;
+    while let Some(object) = objects.try_next().await? {
+        inventory.total.add(&object);
+        match epoch_of(&object.location, prefix) {
+            Some(epoch) => inventory.epochs.entry(epoch).or_default().add(&object),
+            None => inventory.other.add(&object),
+        }
+    }
+    if let (Some(first), Some(last)) = (
+        inventory.epochs.keys().next(),
+        inventory.epochs.keys().next_back(),
+    ) {
+        inventory.missing_epochs = (*first..*last)
+            .filter(|epoch| !inventory.epochs.contains_key(epoch))
+            .collect();
+    }
+    Ok(inventory)
+}
+
+/// Epoch of the `epoch_N` directory right under `prefix` holding `location`, if any.
+fn epoch_of(location: &Path, prefix: Option<&Path>) -> Option<u64> {
+    let relative: Vec<_> = match prefix {
+        Some(prefix) => location.prefix_match(prefix)?.collect(),
+        None => location.parts().collect(),
+    };
+    match relative.as_slice() {
+        [dir, _, ..] => dir.as_ref().strip_prefix(EPOCH_DIR_PREFIX)?.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::inventory::inventory;
+    use crate::object_store::util::put;
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use bytes::Bytes;
+    use object_store::path::Path;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_inventory() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make()?;
+        for (path, contents) in [
+            ("archive/MANIFEST", "manifest"),
+            ("archive/epoch_0/0.chk", "0123"),
+            ("archive/epoch_0/0.sum", "01"),
+            ("archive/epoch_1/4.chk", "0123456"),
+            ("archive/epoch_3/9.chk", "012"),
+            ("archive/epoch_4", "not a directory"),
+            ("other/epoch_2/5.chk", "01234"),
+        ] {
+            put(&store, &Path::from(path), Bytes::from(contents)).await?;
+        }
+
+        let inventory = inventory(&store, Some(&Path::from("archive"))).await?;
+        assert_eq!(inventory.prefix, "archive");
+        assert_eq!(inventory.total.object_count, 6);
+        assert_eq!(inventory.total.total_bytes, 39);
+        assert!(inventory.total.oldest <= inventory.total.newest);
+        assert_eq!(
+            inventory.epochs.keys().copied().collect::<Vec<_>>(),
+            vec![0, 1, 3]
+        );
+        assert_eq!(inventory.epochs[&0].object_count, 2);
+        assert_eq!(inventory.epochs[&0].total_bytes, 6);
+        assert_eq!(inventory.other.object_count, 2);
+        assert_eq!(inventory.missing_epochs, vec![2]);
+
+        // The inventory is reported as JSON
+        let json = serde_json::to_value(&inventory)?;
+        assert_eq!(json["epochs"]["1"]["total_bytes"], 7);
+        Ok(())
+    }
+}
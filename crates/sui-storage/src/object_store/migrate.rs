@@ -0,0 +1,278 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Migration of every object under a prefix from one store to another, e.g. of an archive from S3
+//! to GCS.
+//!
+//! Objects are streamed from the source to the destination while their SHA3 checksum is computed,
+//! and read back from the destination to verify it. Each copied object is then appended to a
+//! local checkpoint file, so that an interrupted migration started again with the same file
+//! only copies the objects it hadn't copied yet.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use fastcrypto::hash::{HashFunction, Sha3_256};
+use futures::{StreamExt, TryStreamExt};
+use indicatif::ProgressBar;
+use object_store::path::Path;
+use object_store::ObjectMeta;
+use tokio::time::Instant;
+use tracing::info;
+
+use crate::object_store::error::ObjectStoreResult;
+use crate::object_store::{ObjectStoreGetExt, ObjectStoreListExt, ObjectStorePutExt};
+use crate::SHA3_BYTES;
+
+/// Outcome of [`copy_prefix`].
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct CopyPrefixSummary {
+    pub copied: usize,
+    /// Objects already copied according to the checkpoint file
+    pub skipped: usize,
+    /// Bytes of the copied objects
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl CopyPrefixSummary {
+    pub fn bytes_per_second(&self) -> f64 {
+        throughput(self.bytes, self.elapsed)
+    }
+}
+
+/// Locations of the objects copied so far, one per line.
+struct CopyCheckpoint {
+    file: File,
+    copied: HashSet<String>,
+}
+
+impl CopyCheckpoint {
+    fn open(path: &std::path::Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open checkpoint file {}", path.display()))?;
+        // A line cut short by a crash doesn't match any location, its object is copied again
+        let copied = BufReader::new(&file)
+            .lines()
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self { file, copied })
+    }
+
+    fn contains(&self, location: &Path) -> bool {
+        self.copied.contains(location.as_ref())
+    }
+
+    fn record(&mut self, location: &Path) -> Result<()> {
+        writeln!(self.file, "{location}")?;
+        self.file.flush()?;
+        self.copied.insert(location.to_string());
+        Ok(())
+    }
+}
+
+/// Copy every object under `prefix` of `src_store` to the same location in `dest_store`,
+/// `concurrency` objects at a time, verifying the checksum of each copy. Objects recorded in
+/// `checkpoint_file` are skipped, and copied ones are recorded in it. See the
+/// [module documentation](self).
+pub async fn copy_prefix<S, D>(
+    src_store: &S,
+    dest_store: &D,
+    prefix: &Path,
+    checkpoint_file: Option<&std::path::Path>,
+    concurrency: NonZeroUsize,
+    progress_bar: Option<ProgressBar>,
+) -> Result<CopyPrefixSummary>
+where
+    S: ObjectStoreGetExt + ObjectStoreListExt,
+    D: ObjectStoreGetExt + ObjectStorePutExt,
+{
+    let start = Instant::now();
+    let mut checkpoint = checkpoint_file.map(CopyCheckpoint::open).transpose()?;
+    let list_prefix = (!prefix.as_ref().is_empty()).then_some(prefix);
+    let objects: Vec<ObjectMeta> = src_store
+        .list_objects(list_prefix)
+        .await?
+        .try_collect()
+        .await?;
+    let mut summary = CopyPrefixSummary::default();
+    let mut pending = vec![];
+    for object in objects {
+        match &checkpoint {
+            Some(checkpoint) if checkpoint.contains(&object.location) => summary.skipped += 1,
+            _ => pending.push(object),
+        }
+    }
+    if let Some(progress_bar) = &progress_bar {
+        progress_bar.set_length(pending.len() as u64);
+    }
+    let mut copies = futures::stream::iter(pending)
+        .map(|object| async move {
+            copy_verified(src_store, dest_store, &object).await?;
+            Ok::<_, anyhow::Error>(object)
+        })
+        .buffer_unordered(concurrency.get());
+    while let Some(object) = copies.try_next().await? {
+        if let Some(checkpoint) = &mut checkpoint {
+            checkpoint.record(&object.location)?;
+        }
+        summary.copied += 1;
+        summary.bytes += object.size as u64;
+        if let Some(progress_bar) = &progress_bar {
+            progress_bar.inc(1);
+            progress_bar.set_message(format!(
+                "{:.2} MiB/s",
+                throughput(summary.bytes, start.elapsed()) / (1024.0 * 1024.0)
+            ));
+        }
+    }
+    summary.elapsed = start.elapsed();
+    info!(
+        "Copied {} objects ({} bytes) under {prefix} in {:?} at {:.0} bytes/s, skipped {}",
+        summary.copied,
+        summary.bytes,
+        summary.elapsed,
+        summary.bytes_per_second(),
+        summary.skipped
+    );
+    Ok(summary)
+}
+
+/// Stream `object` from `src_store` to `dest_store`, then read it back and compare checksums.
+async fn copy_verified<S, D>(src_store: &S, dest_store: &D, object: &ObjectMeta) -> Result<()>
+where
+    S: ObjectStoreGetExt,
+    D: ObjectStoreGetExt + ObjectStorePutExt,
+{
+    let location = &object.location;
+    let hasher = Arc::new(Mutex::new(Sha3_256::default()));
+    if object.size == 0 {
+        dest_store.put_bytes(location, Bytes::new()).await?;
+    } else {
+        let stream_hasher = hasher.clone();
+        let stream = src_store
+            .get_stream(location)
+            .await?
+            .inspect_ok(move |chunk| stream_hasher.lock().unwrap().update(chunk));
+        dest_store.put_stream(location, stream.boxed()).await?;
+    }
+    let src_digest = finalize(hasher);
+    let dest_digest = checksum(dest_store.get_stream(location).await?).await?;
+    if src_digest != dest_digest {
+        return Err(anyhow!(
+            "Checksum of the copy of {location} doesn't match the source"
+        ));
+    }
+    Ok(())
+}
+
+async fn checksum(
+    mut stream: futures::stream::BoxStream<'static, ObjectStoreResult<Bytes>>,
+) -> Result<[u8; SHA3_BYTES]> {
+    let mut hasher = Sha3_256::default();
+    while let Some(chunk) = stream.try_next().await? {
+        hasher.update(&chunk);
+    }
+    Ok(hasher.finalize().digest)
+}
+
+fn finalize(hasher: Arc<Mutex<Sha3_256>>) -> [u8; SHA3_BYTES] {
+    let hasher = std::mem::take(&mut *hasher.lock().unwrap());
+    hasher.finalize().digest
+}
+
+fn throughput(bytes: u64, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    bytes as f64 / elapsed.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::migrate::copy_prefix;
+    use crate::object_store::util::{get, put};
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use bytes::Bytes;
+    use object_store::path::Path;
+    use std::num::NonZeroUsize;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    pub async fn test_copy_prefix_resume() -> anyhow::Result<()> {
+        let src_dir = TempDir::new()?;
+        let dest_dir = TempDir::new()?;
+        let checkpoint_dir = TempDir::new()?;
+        let make = |dir: &TempDir| {
+            ObjectStoreConfig {
+                object_store: Some(ObjectStoreType::File),
+                directory: Some(dir.path().to_path_buf()),
+                ..Default::default()
+            }
+            .make()
+        };
+        let src = make(&src_dir)?;
+        let dest = make(&dest_dir)?;
+        for i in 0..5 {
+            put(
+                &src,
+                &Path::from(format!("archive/epoch_0/{i}.chk")),
+                Bytes::from(format!("checkpoint {i}")),
+            )
+            .await?;
+        }
+        put(&src, &Path::from("archive/empty"), Bytes::new()).await?;
+        put(&src, &Path::from("other/0.chk"), Bytes::from("other")).await?;
+
+        let prefix = Path::from("archive");
+        let checkpoint_file = checkpoint_dir.path().join("copy.checkpoint");
+        let concurrency = NonZeroUsize::new(2).unwrap();
+        let summary = copy_prefix(
+            &src,
+            &dest,
+            &prefix,
+            Some(&checkpoint_file),
+            concurrency,
+            None,
+        )
+        .await?;
+        assert_eq!(summary.copied, 6);
+        assert_eq!(summary.skipped, 0);
+        assert_eq!(
+            get(&dest, &Path::from("archive/epoch_0/3.chk")).await?,
+            Bytes::from("checkpoint 3")
+        );
+        assert!(get(&dest, &Path::from("archive/empty")).await?.is_empty());
+        assert!(!dest_dir.path().join("other").exists());
+
+        // Objects recorded in the checkpoint file are skipped when resuming
+        put(
+            &src,
+            &Path::from("archive/epoch_1/0.chk"),
+            Bytes::from("new"),
+        )
+        .await?;
+        let summary = copy_prefix(
+            &src,
+            &dest,
+            &prefix,
+            Some(&checkpoint_file),
+            concurrency,
+            None,
+        )
+        .await?;
+        assert_eq!(summary.copied, 1);
+        assert_eq!(summary.skipped, 6);
+        assert_eq!(summary.bytes, 3);
+        Ok(())
+    }
+}
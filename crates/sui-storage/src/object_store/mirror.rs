@@ -0,0 +1,532 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Writes mirrored to several object stores, e.g. an S3 and a GCS bucket for disaster recovery.
+//!
+//! [`MirroredObjectStore`] puts and deletes objects in every store it mirrors, and reads and
+//! lists them from the first one, the primary.
+//!
+//! Mirrors of `Arc<DynObjectStore>`s are object stores themselves, so that writers taking a
+//! `DynObjectStore`, like the archive and snapshot writers, can write to mirrors. Writes that
+//! can't be mirrored, like conditional writes, go to the primary, see
+//! [`MirroredObjectStore::put_to_mirrors`].
+
+use std::fmt::{Debug, Display, Formatter};
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::future::{join_all, try_join_all, BoxFuture};
+use futures::stream::BoxStream;
+use futures::{FutureExt, TryFutureExt, TryStreamExt};
+use object_store::path::Path;
+use object_store::{
+    DynObjectStore, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
+use tracing::error;
+
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::{
+    ObjectStoreConfig, ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStoreListExt,
+    ObjectStorePutExt,
+};
+
+/// What a write to a [`MirroredObjectStore`] does when it fails on some of the stores.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MirrorFailurePolicy {
+    /// The write fails as soon as it fails on any store, without waiting for the other stores.
+    #[default]
+    FailFast,
+    /// The write succeeds if it succeeds on at least one store. Failures on the other stores are
+    /// logged and reported to the failure handler, if any.
+    BestEffort,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MirroredObjectStoreConfig {
+    /// Stores to mirror writes to. The first one is the primary, that reads are served from.
+    pub stores: Vec<ObjectStoreConfig>,
+    #[serde(default)]
+    pub failure_policy: MirrorFailurePolicy,
+}
+
+impl MirroredObjectStoreConfig {
+    pub fn make(&self) -> Result<MirroredObjectStore<Arc<DynObjectStore>>> {
+        self.make_with(ObjectStoreConfig::make)
+    }
+
+    /// Mirror of the stores made with `make`, e.g. to wrap each of them in a circuit breaker.
+    pub fn make_with(
+        &self,
+        make: impl Fn(&ObjectStoreConfig) -> Result<Arc<DynObjectStore>>,
+    ) -> Result<MirroredObjectStore<Arc<DynObjectStore>>> {
+        let stores = self.stores.iter().map(make).collect::<Result<Vec<_>>>()?;
+        MirroredObjectStore::new(stores, self.failure_policy)
+    }
+
+    /// Config of the primary store, that reads and writes that can't be mirrored go to.
+    pub fn primary(&self) -> Result<&ObjectStoreConfig> {
+        self.stores
+            .first()
+            .ok_or_else(|| anyhow!("A mirrored object store needs at least one store"))
+    }
+}
+
+/// Config of a single store, without mirrors.
+impl From<ObjectStoreConfig> for MirroredObjectStoreConfig {
+    fn from(config: ObjectStoreConfig) -> Self {
+        Self {
+            stores: vec![config],
+            failure_policy: MirrorFailurePolicy::default(),
+        }
+    }
+}
+
+/// Called with the index of the store, the location and the error of every write that failed on
+/// a store but succeeded overall under [`MirrorFailurePolicy::BestEffort`].
+pub type MirrorFailureHandler = Arc<dyn Fn(usize, &Path, &ObjectStoreError) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct MirroredObjectStore<S> {
+    stores: Vec<S>,
+    failure_policy: MirrorFailurePolicy,
+    failure_handler: Option<MirrorFailureHandler>,
+}
+
+impl<S> MirroredObjectStore<S> {
+    pub fn new(stores: Vec<S>, failure_policy: MirrorFailurePolicy) -> Result<Self> {
+        if stores.is_empty() {
+            return Err(anyhow!("A mirrored object store needs at least one store"));
+        }
+        Ok(Self {
+            stores,
+            failure_policy,
+            failure_handler: None,
+        })
+    }
+
+    /// Report the failures of best effort writes to `handler`, e.g. to count them in a metric
+    /// or to queue the writes for repair.
+    pub fn with_failure_handler(mut self, handler: MirrorFailureHandler) -> Self {
+        self.failure_handler = Some(handler);
+        self
+    }
+
+    pub fn stores(&self) -> &[S] {
+        &self.stores
+    }
+
+    pub fn primary(&self) -> &S {
+        &self.stores[0]
+    }
+}
+
+impl<S: Display> MirroredObjectStore<S> {
+    /// Write `location` to the stores from the `first` one on with `write`, under the failure
+    /// policy.
+    async fn write_from<'a, F, Fut>(
+        &'a self,
+        first: usize,
+        location: &Path,
+        write: F,
+    ) -> ObjectStoreResult<()>
+    where
+        F: Fn(&'a S) -> Fut,
+        Fut: Future<Output = ObjectStoreResult<()>>,
+    {
+        let stores = self.stores.iter().enumerate().skip(first);
+        match self.failure_policy {
+            MirrorFailurePolicy::FailFast => {
+                // Returns on the first failure, dropping the writes still in flight.
+                try_join_all(stores.map(|(index, store)| {
+                    write(store).map_err(move |e| {
+                        error!(
+                            "Mirrored write of {location} failed on {}: {e:?}",
+                            self.stores[index]
+                        );
+                        e
+                    })
+                }))
+                .await?;
+                Ok(())
+            }
+            MirrorFailurePolicy::BestEffort => {
+                let results = join_all(
+                    stores.map(|(index, store)| write(store).map(move |result| (index, result))),
+                )
+                .await;
+                // The primary already holds the object when only the mirrors are written.
+                self.check_results(first > 0, location, results)
+            }
+        }
+    }
+
+    /// Succeed if any of the best effort writes of `location` in `results` succeeded, and
+    /// report the failed ones.
+    fn check_results(
+        &self,
+        mut succeeded: bool,
+        location: &Path,
+        results: Vec<(usize, ObjectStoreResult<()>)>,
+    ) -> ObjectStoreResult<()> {
+        let mut first_error = None;
+        for (index, result) in results {
+            match result {
+                Ok(()) => succeeded = true,
+                Err(e) => {
+                    error!(
+                        "Mirrored write of {location} failed on {}: {e:?}",
+                        self.stores[index]
+                    );
+                    if let Some(handler) = &self.failure_handler {
+                        handler(index, location, &e);
+                    }
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) if !succeeded => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<S: ObjectStorePutExt + Display> MirroredObjectStore<S> {
+    /// Write `bytes` to `src` in every store but the primary, e.g. after a conditional write to
+    /// the primary, which can't be mirrored.
+    pub async fn put_to_mirrors(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
+        self.write_from(1, src, |store| store.put_bytes(src, bytes.clone()))
+            .await
+    }
+}
+
+impl<S: Debug> Debug for MirroredObjectStore<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MirroredObjectStore")
+            .field("stores", &self.stores)
+            .field("failure_policy", &self.failure_policy)
+            .finish()
+    }
+}
+
+impl<S: Display> Display for MirroredObjectStore<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MirroredObjectStore(")?;
+        for (index, store) in self.stores.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{store}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetExt> ObjectStoreGetExt for MirroredObjectStore<S> {
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        self.primary().get_bytes(src).await
+    }
+
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        self.primary().get_stream(src).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreListExt> ObjectStoreListExt for MirroredObjectStore<S> {
+    async fn list_objects(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        self.primary().list_objects(src).await
+    }
+
+    async fn list_objects_with_delimiter(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<ListResult> {
+        self.primary().list_objects_with_delimiter(src).await
+    }
+
+    async fn list_objects_after(
+        &self,
+        src: Option<&Path>,
+        offset: &Path,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        self.primary().list_objects_after(src, offset).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStorePutExt + Display> ObjectStorePutExt for MirroredObjectStore<S> {
+    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
+        self.write_from(0, src, |store| store.put_bytes(src, bytes.clone()))
+            .await
+    }
+
+    /// The stream is read in full, before being written to every store.
+    async fn put_stream(
+        &self,
+        src: &Path,
+        stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> ObjectStoreResult<()> {
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        self.put_bytes(src, Bytes::from(chunks.concat())).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreDeleteExt + Display> ObjectStoreDeleteExt for MirroredObjectStore<S> {
+    async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()> {
+        self.write_from(0, src, |store| store.delete_object(src))
+            .await
+    }
+}
+
+impl MirroredObjectStore<Arc<DynObjectStore>> {
+    /// Write to every store with `write`, as an `object_store` request.
+    async fn write_request<'a, F, Fut>(
+        &'a self,
+        location: &Path,
+        write: F,
+    ) -> object_store::Result<()>
+    where
+        F: Fn(&'a Arc<DynObjectStore>) -> Fut,
+        Fut: Future<Output = object_store::Result<()>>,
+    {
+        self.write_from(0, location, |store| {
+            write(store).err_into::<ObjectStoreError>()
+        })
+        .await
+        .map_err(|e| object_store::Error::Generic {
+            store: "MirroredObjectStore",
+            source: Box::new(e),
+        })
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MirroredObjectStore<Arc<DynObjectStore>> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        self.write_request(location, |store| store.put(location, bytes.clone()))
+            .await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let writer = MirroringWriter {
+            mirrored: self.clone(),
+            location: location.clone(),
+            buffer: vec![],
+            upload: None,
+        };
+        Ok((String::new(), Box::new(writer)))
+    }
+
+    async fn abort_multipart(
+        &self,
+        _location: &Path,
+        _multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        // Nothing is written before the upload completes.
+        Ok(())
+    }
+
+    async fn get(&self, location: &Path) -> object_store::Result<GetResult> {
+        self.primary().get(location).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.primary().get_opts(location, options).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        self.primary().get_range(location, range).await
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        self.primary().get_ranges(location, ranges).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.primary().head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.write_request(location, |store| store.delete(location))
+            .await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.primary().list(prefix).await
+    }
+
+    async fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.primary().list_with_offset(prefix, offset).await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.primary().list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.write_request(to, |store| store.copy(from, to)).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.write_request(to, |store| store.rename(from, to)).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.write_request(to, |store| store.copy_if_not_exists(from, to))
+            .await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.write_request(to, |store| store.rename_if_not_exists(from, to))
+            .await
+    }
+}
+
+/// Buffers a multipart write, to put the object in every store once the writer is shut down.
+struct MirroringWriter {
+    mirrored: MirroredObjectStore<Arc<DynObjectStore>>,
+    location: Path,
+    buffer: Vec<u8>,
+    upload: Option<BoxFuture<'static, io::Result<()>>>,
+}
+
+impl AsyncWrite for MirroringWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.upload.is_some() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Write after shutdown",
+            )));
+        }
+        self.buffer.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let upload = this.upload.get_or_insert_with(|| {
+            let (mirrored, location) = (this.mirrored.clone(), this.location.clone());
+            let bytes = Bytes::from(std::mem::take(&mut this.buffer));
+            async move {
+                mirrored
+                    .put(&location, bytes)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            }
+            .boxed()
+        });
+        upload.poll_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::mirror::{MirrorFailurePolicy, MirroredObjectStore};
+    use crate::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use object_store::local::LocalFileSystem;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_mirrored_writes() -> anyhow::Result<()> {
+        let s3: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let gcs: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        // Puts fail on a local store rooted at a file rather than a directory.
+        let file = tempfile::NamedTempFile::new()?;
+        let broken: Arc<DynObjectStore> = Arc::new(LocalFileSystem::new_with_prefix(file.path())?);
+        let path = Path::from("epoch_0/MANIFEST");
+        let data = Bytes::from_static(b"manifest");
+
+        let mirrored =
+            MirroredObjectStore::new(vec![s3.clone(), gcs.clone()], MirrorFailurePolicy::FailFast)?;
+        mirrored.put_bytes(&path, data.clone()).await?;
+        assert_eq!(s3.get_bytes(&path).await?, data);
+        assert_eq!(gcs.get_bytes(&path).await?, data);
+
+        let fail_fast = MirroredObjectStore::new(
+            vec![s3.clone(), broken.clone()],
+            MirrorFailurePolicy::FailFast,
+        )?;
+        assert!(fail_fast.put_bytes(&path, data.clone()).await.is_err());
+
+        let failures = Arc::new(AtomicUsize::new(0));
+        let failures_clone = failures.clone();
+        let best_effort =
+            MirroredObjectStore::new(vec![s3, broken], MirrorFailurePolicy::BestEffort)?
+                .with_failure_handler(Arc::new(move |index, _, _| {
+                    assert_eq!(index, 1);
+                    failures_clone.fetch_add(1, Ordering::Relaxed);
+                }));
+        best_effort.put_bytes(&path, data.clone()).await?;
+        assert_eq!(failures.load(Ordering::Relaxed), 1);
+
+        // Mirrors are object stores themselves, streamed writes included.
+        let s3: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let gcs: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let mirrored =
+            MirroredObjectStore::new(vec![s3.clone(), gcs.clone()], MirrorFailurePolicy::FailFast)?;
+        let store: Arc<DynObjectStore> = Arc::new(mirrored.clone());
+        let stream = futures::stream::iter([Ok(data.slice(..4)), Ok(data.slice(4..))]).boxed();
+        store.put_stream(&path, stream).await?;
+        assert_eq!(s3.get_bytes(&path).await?, data);
+        assert_eq!(gcs.get_bytes(&path).await?, data);
+
+        // Writes to the mirrors only leave the primary as is.
+        let update = Bytes::from_static(b"manifest'");
+        mirrored.put_to_mirrors(&path, update.clone()).await?;
+        assert_eq!(s3.get_bytes(&path).await?, data);
+        assert_eq!(gcs.get_bytes(&path).await?, update);
+        Ok(())
+    }
+}
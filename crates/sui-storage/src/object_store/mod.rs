@@ -1,22 +1,103 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::object_store::adaptive::{AdaptiveLimitConfig, AdaptiveLimitStore};
+use crate::object_store::aws_credentials::{
+    sdk_credentials_provider, AwsCredentialSource, AwsSdkCredentialProvider,
+};
+use crate::object_store::azure_credentials::AzureCredentialSource;
+use crate::object_store::batch_delete::{DeleteObjectsResult, S3BatchDelete};
+use crate::object_store::circuit::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerMetrics, CircuitBreakerObjectStore,
+};
+use crate::object_store::concurrency::{ConcurrencyLimitedObjectStore, ConcurrencyLimits};
+use crate::object_store::conditional::{
+    LockedConditionalPut, ObjectStoreConditionalPutExt, SignedConditionalPut,
+};
+use crate::object_store::encryption::{
+    EncryptedObjectStore, EncryptionKeySource, EnvelopeKey, KmsEnvelopeKey, LocalEnvelopeKey,
+};
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::gcs_credentials::{
+    GcsCredentialSource, GOOGLE_APPLICATION_CREDENTIALS_ENV_VAR,
+};
+use crate::object_store::multipart::{
+    LocalMultipartStore, MultipartConfig, MultipartUploader, S3MultipartStore, DEFAULT_AWS_REGION,
+};
+use crate::object_store::prefix::PrefixedStore;
+use crate::object_store::requester_pays::RequesterPaysS3Store;
+use crate::object_store::retry::{RetryBudget, RetryConfig, RetryingObjectStore};
+use crate::object_store::s3_write::{
+    AwsSse, AwsStorageClass, S3WriteOptions, S3WriteStore, ServerSideEncryption,
+};
+use crate::object_store::sign::{AzureSigner, GcsSigner, ObjectStoreSignExt, S3Signer};
+use crate::object_store::throttle::{
+    BandwidthLimits, ThrottledMultipartStore, ThrottledObjectStore, TokenBucket,
+};
+use crate::object_store::tls::TlsVersion;
+use crate::object_store::validate::{validate_store, ValidationError, ValidationStep};
+use crate::object_store::versions::{GcsVersionedReader, ObjectStoreVersionExt, S3VersionedReader};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use clap::*;
 use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use indicatif::ProgressBar;
 use object_store::aws::AmazonS3Builder;
+use object_store::memory::InMemory;
 use object_store::path::Path;
-use object_store::{DynObjectStore, ObjectMeta};
+use object_store::{ClientOptions, DynObjectStore, ListResult, ObjectMeta};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
+use std::num::NonZeroUsize;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
 
+pub mod adaptive;
+pub mod aws_credentials;
+pub mod azure_credentials;
+pub mod batch_delete;
+pub mod cas;
+pub mod checksum;
+pub mod circuit;
+pub mod compression;
+pub mod concurrency;
+pub mod conditional;
+pub mod consistency;
+pub mod encryption;
+pub mod error;
+pub mod fallback;
+pub mod faulty;
+pub mod gc;
+pub mod gcs_credentials;
 pub mod http;
+pub mod inventory;
+pub mod migrate;
+pub mod mirror;
+pub mod multipart;
+pub mod prefix;
+pub mod proxy;
+pub mod requester_pays;
+pub mod retry;
+pub mod s3_write;
+pub mod sign;
+pub mod throttle;
+pub mod tls;
 pub mod util;
+pub mod validate;
+pub mod versions;
+pub mod watch;
+
+/// Host GCS requests are sent to, by the store and the HTTP downloader.
+pub(crate) const GCS_HOST: &str = "storage.googleapis.com";
 
 /// Object-store type.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
@@ -29,6 +110,9 @@ pub enum ObjectStoreType {
     GCS,
     /// Azure Blob Store
     Azure,
+    /// In-memory store, dropped with the process. Stores configured with the same bucket share
+    /// their objects, e.g. between the writer and the reader of an archive in a test.
+    Memory,
 }
 
 #[derive(Default, Debug, Clone, Deserialize, Serialize, Args)]
@@ -66,9 +150,34 @@ pub struct ObjectStoreConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub aws_region: Option<String>,
+    /// When using Amazon S3 as the object store, set this to a named profile
+    /// in the shared AWS config files to take credentials from. Ignored if
+    /// an access key is set. See [`AwsCredentialSource`] for precedence.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub aws_profile: Option<String>,
+    /// When using Amazon S3 as the object store, set this to the ARN of an
+    /// IAM role to assume through STS. The role's temporary credentials are
+    /// refreshed in the background before they expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub aws_role_arn: Option<String>,
+    /// External ID required by the trust policy of `--aws-role-arn`, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub aws_external_id: Option<String>,
+    /// Session name to assume `--aws-role-arn` with, which shows up in
+    /// CloudTrail logs for requests made with the role
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub aws_role_session_name: Option<String>,
+    /// When using Amazon S3 as the object store, exchange the web identity
+    /// token in `AWS_WEB_IDENTITY_TOKEN_FILE` for credentials of the role in
+    /// `AWS_ROLE_ARN`, as set up by IAM Roles for Service Accounts on EKS.
+    /// Ignored if an access key or profile is set.
+    #[serde(default)]
+    #[arg(long, default_value_t = false)]
+    pub aws_web_identity: bool,
     /// Enable virtual hosted style requests
     #[serde(default)]
     #[arg(long, default_value_t = true)]
@@ -77,11 +186,48 @@ pub struct ObjectStoreConfig {
     #[serde(default)]
     #[arg(long, default_value_t = true)]
     pub aws_allow_http: bool,
+    /// Encrypt the objects written to S3 server-side, with keys managed by
+    /// S3 or with a KMS key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub aws_sse: Option<AwsSse>,
+    /// ID or ARN of the KMS key objects are encrypted with, with
+    /// `--aws-sse kms`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub aws_kms_key_id: Option<String>,
+    /// Storage class of the objects written to S3, e.g. STANDARD_IA or
+    /// GLACIER_IR, instead of the default of the bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long, value_enum)]
+    pub aws_storage_class: Option<AwsStorageClass>,
+    /// URL of the SQS queue the event notifications of the bucket are sent
+    /// to, for watchers of the bucket to pick up changes before their next
+    /// poll. See [`StoreWatcher`](watch::StoreWatcher).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub aws_event_queue_url: Option<String>,
+    /// Acknowledge that reads of the bucket are billed to the requester, for
+    /// requester-pays S3 and GCS buckets. GCS reads are billed to
+    /// `--google-billing-project`.
+    #[serde(default)]
+    #[arg(long, default_value_t = false)]
+    pub requester_pays: bool,
     /// When using Google Cloud Storage as the object store, set this to the
     /// path to the JSON file that contains the Google credentials.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub google_service_account: Option<String>,
+    /// When using Google Cloud Storage as the object store, set this to the
+    /// path of an application default credentials file. Ignored if a service
+    /// account is set. See [`GcsCredentialSource`] for precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub google_application_credentials: Option<String>,
+    /// Project the requests to requester-pays GCS buckets are billed to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub google_billing_project: Option<String>,
     /// When using Microsoft Azure as the object store, set this to the
     /// azure account name
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -92,19 +238,429 @@ pub struct ObjectStoreConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[arg(long)]
     pub azure_storage_access_key: Option<String>,
+    /// When using Microsoft Azure as the object store, set this to a shared
+    /// access signature token for the container, as a query string. Ignored
+    /// if an access key is set. See [`AzureCredentialSource`] for precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub azure_storage_sas_token: Option<String>,
+    /// When using Microsoft Azure as the object store, set this to the client
+    /// ID of the managed identity or workload identity application to
+    /// authenticate as
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub azure_client_id: Option<String>,
+    /// Tenant of `--azure-client-id`, for workload identity federation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub azure_tenant_id: Option<String>,
+    /// Path of the federated token file to exchange for access tokens of
+    /// `--azure-client-id`, for workload identity federation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub azure_federated_token_file: Option<PathBuf>,
     #[serde(default = "default_object_store_connection_limit")]
     #[arg(long, default_value_t = 20)]
     pub object_store_connection_limit: usize,
+    /// Adapt the number of concurrent requests to the latency and throttling of the store,
+    /// between `--object-store-adaptive-min-connections` and `--object-store-connection-limit`,
+    /// rather than always allowing `--object-store-connection-limit` requests
+    #[serde(default)]
+    #[arg(long, default_value_t = false)]
+    pub object_store_adaptive_concurrency: bool,
+    /// Number of concurrent requests the adaptive limit never goes below
+    #[serde(default = "default_object_store_adaptive_min_connections")]
+    #[arg(long, default_value_t = 2)]
+    pub object_store_adaptive_min_connections: usize,
+    /// p99 latency of requests in milliseconds above which the adaptive limit shrinks
+    #[serde(default = "default_object_store_adaptive_latency_target_ms")]
+    #[arg(long, default_value_t = 2000)]
+    pub object_store_adaptive_latency_target_ms: u64,
+    /// Number of concurrent reads, i.e. gets and heads, so that reads can't starve writes.
+    /// Requests are also bound by `--object-store-connection-limit`, which should be at least
+    /// the sum of the read and write limits. Unlimited if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_read_connection_limit: Option<usize>,
+    /// Number of concurrent writes, i.e. puts, uploads, copies and renames. Unlimited if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_write_connection_limit: Option<usize>,
+    /// Number of concurrent lists. Lists count against the read limit if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_list_connection_limit: Option<usize>,
+    /// Number of concurrent deletes. Deletes count against the write limit if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_delete_connection_limit: Option<usize>,
+    #[serde(default)]
+    #[command(flatten)]
+    pub object_store_client: ObjectStoreClientConfig,
+    #[serde(default)]
+    #[command(flatten)]
+    pub http_auth: HttpAuthConfig,
+    /// Number of times requests failing with transient errors, like server
+    /// errors, throttling or timeouts, are retried, by the stores and the HTTP
+    /// downloaders. Set to 0 to disable retries.
+    #[serde(default = "default_object_store_max_retries")]
+    #[arg(long, default_value_t = 3)]
+    pub object_store_max_retries: usize,
+    /// Delay before the first retry in milliseconds, doubled on every retry
+    #[serde(default = "default_object_store_retry_base_delay_ms")]
+    #[arg(long, default_value_t = 100)]
+    pub object_store_retry_base_delay_ms: u64,
+    /// Randomization factor between 0 and 1 applied to retry delays
+    #[serde(default = "default_object_store_retry_jitter")]
+    #[arg(long, default_value_t = 0.5)]
+    pub object_store_retry_jitter: f64,
+    /// Also retry requests for objects that are not found, for buckets that
+    /// are only eventually consistent after writes
+    #[serde(default)]
+    #[arg(long, default_value_t = false)]
+    pub object_store_retry_not_found: bool,
+    /// Number of consecutive requests failing with server errors, throttling or timeouts after
+    /// which requests fail fast without reaching the store, rather than each being retried
+    /// against an unavailable store. Disabled if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_circuit_breaker_failures: Option<usize>,
+    /// Time in seconds between probe requests to the store while requests fail fast. The first
+    /// successful probe lets requests through again.
+    #[serde(default = "default_object_store_circuit_breaker_probe_interval_secs")]
+    #[arg(long, default_value_t = 30)]
+    pub object_store_circuit_breaker_probe_interval_secs: u64,
+    /// Time in seconds writers wait for the objects they wrote to become visible to reads before
+    /// writing objects that refer to them, e.g. the MANIFEST of an archive, for stores that
+    /// aren't read-after-write consistent. Writers don't wait if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_read_after_write_timeout_secs: Option<u64>,
+    /// Upload rate limit in megabits per second, shared by the stores made from this config, e.g.
+    /// so that snapshot uploads leave room for consensus traffic. Unlimited if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_max_upload_mbps: Option<u64>,
+    /// Download rate limit in megabits per second, shared by the stores made from this config.
+    /// Unlimited if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_max_download_mbps: Option<u64>,
+    /// Size in MiB of the parts large objects are split into for multipart
+    /// uploads, at least 5. Up to this many MiB times
+    /// `--object-store-multipart-concurrency` are held in memory per upload.
+    #[serde(default = "default_object_store_multipart_part_size_mb")]
+    #[arg(long, default_value_t = 64)]
+    pub object_store_multipart_part_size_mb: usize,
+    /// Number of parts of a multipart upload uploaded at once
+    #[serde(default = "default_object_store_multipart_concurrency")]
+    #[arg(long, default_value_t = 4)]
+    pub object_store_multipart_concurrency: usize,
+    /// Size in MiB above which objects are streamed to the store with multipart uploads, rather
+    /// than written in a single request, see [`util::put_with_threshold`]. 64 MiB if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_streaming_threshold_mb: Option<usize>,
     #[serde(default)]
     #[arg(long, default_value_t = false)]
     pub no_sign_request: bool,
+    /// Encrypt objects client-side with AES-256-GCM, under data keys wrapped by this key: the
+    /// path of a file holding a hex encoded 256-bit key, or the ID or ARN of a KMS key,
+    /// depending on `--object-store-encryption-key-source`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub object_store_encryption_key: Option<String>,
+    #[serde(default)]
+    #[arg(long, value_enum, default_value_t = EncryptionKeySource::File)]
+    pub object_store_encryption_key_source: EncryptionKeySource,
+    /// URL of a gateway handing out signed upload URLs, to write objects through with
+    /// [`HttpUploaderBuilder::make_http_upload`](crate::object_store::http::HttpUploaderBuilder)
+    /// without credentials of the bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub http_upload_gateway_url: Option<String>,
+    /// Bearer token authenticating uploads to `--http-upload-gateway-url`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub http_upload_gateway_token: Option<String>,
+    /// Directory caching the objects the HTTP downloaders get, so that an object is only
+    /// downloaded again once its ETag changes, e.g. for stores whose MANIFEST is polled. Objects
+    /// are read in full, rather than streamed, to be cached. Disabled if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub http_etag_cache_dir: Option<PathBuf>,
+    /// Maximum number of redirects the HTTP downloaders follow, e.g. from a CDN to the origin of
+    /// an archive, 0 to follow none. Defaults to 10.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long)]
+    pub http_max_redirects: Option<usize>,
+    /// Time in seconds the presigned URLs handed out for objects of the store are valid for,
+    /// at most 7 days
+    #[serde(default = "default_object_store_presigned_url_expiry_secs")]
+    #[arg(long, default_value_t = 3600)]
+    pub object_store_presigned_url_expiry_secs: u64,
+}
+
+/// Settings of the HTTP clients of the S3, GCS and Azure stores, and of the HTTP downloaders.
+/// Settings left unset keep the defaults of the clients.
+#[derive(Default, Debug, Clone, Deserialize, Serialize, Args)]
+#[serde(rename_all = "kebab-case")]
+pub struct ObjectStoreClientConfig {
+    /// Timeout of requests in seconds, from connecting until the response body is read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-timeout-secs")]
+    pub timeout_secs: Option<u64>,
+    /// Timeout of connecting in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-connect-timeout-secs")]
+    pub connect_timeout_secs: Option<u64>,
+    /// Time in seconds idle connections are kept open for reuse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-pool-idle-timeout-secs")]
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// Maximum number of idle connections kept open per host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-pool-max-idle-per-host")]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Interval in seconds of the HTTP/2 pings keeping connections alive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-http2-keep-alive-interval-secs")]
+    pub http2_keep_alive_interval_secs: Option<u64>,
+    /// Only use HTTP/2, rather than negotiating the HTTP version
+    #[serde(default)]
+    #[arg(long = "object-store-http2-only", default_value_t = false)]
+    pub http2_only: bool,
+    /// URL of the HTTP or HTTPS proxy to send requests through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-proxy-url")]
+    pub proxy_url: Option<String>,
+    /// Username to authenticate to the proxy with, with basic authentication
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-proxy-username")]
+    pub proxy_username: Option<String>,
+    /// Password to authenticate to the proxy with, with basic authentication
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-proxy-password")]
+    pub proxy_password: Option<String>,
+    /// Hosts and domains reached without the proxy, comma separated, or `*` for all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[arg(long = "object-store-no-proxy", value_delimiter = ',')]
+    pub no_proxy: Vec<String>,
+    /// Path of a PEM bundle of the CA certificates to trust, e.g. of an on-prem store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-tls-ca-cert-file")]
+    pub tls_ca_cert_file: Option<PathBuf>,
+    /// Accept invalid and self-signed certificates. Only for lab environments
+    #[serde(default)]
+    #[arg(long = "object-store-tls-skip-verify", default_value_t = false)]
+    pub tls_skip_verify: bool,
+    /// Minimum TLS version of connections to the store
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "object-store-tls-min-version", value_enum)]
+    pub tls_min_version: Option<TlsVersion>,
+}
+
+/// Credentials of the HTTP downloaders, for read-only stores behind an authenticating proxy or
+/// CDN, e.g. an archive only served to token holders. Sent with every request of
+/// [`HttpDownloaderBuilder`](crate::object_store::http::HttpDownloaderBuilder) stores, and
+/// ignored by the other stores.
+#[derive(Default, Debug, Clone, Deserialize, Serialize, Args)]
+#[serde(rename_all = "kebab-case")]
+pub struct HttpAuthConfig {
+    /// Token sent as `Authorization: Bearer <token>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "http-bearer-token")]
+    pub bearer_token: Option<String>,
+    /// Username to authenticate with, with basic authentication. Can't be combined with a
+    /// bearer token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "http-basic-auth-username")]
+    pub basic_auth_username: Option<String>,
+    /// Password of `--http-basic-auth-username`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[arg(long = "http-basic-auth-password")]
+    pub basic_auth_password: Option<String>,
+    /// Header sent with every request, as `<name>: <value>`, e.g. `x-api-key: <key>`. Can be
+    /// repeated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[arg(long = "http-header")]
+    pub headers: Vec<String>,
 }
 
 fn default_object_store_connection_limit() -> usize {
     20
 }
 
+fn default_object_store_adaptive_min_connections() -> usize {
+    2
+}
+
+fn default_object_store_adaptive_latency_target_ms() -> u64 {
+    2000
+}
+
+fn default_object_store_max_retries() -> usize {
+    3
+}
+
+fn default_object_store_retry_base_delay_ms() -> u64 {
+    100
+}
+
+fn default_object_store_retry_jitter() -> f64 {
+    0.5
+}
+
+fn default_object_store_circuit_breaker_probe_interval_secs() -> u64 {
+    30
+}
+
+fn default_object_store_multipart_part_size_mb() -> usize {
+    64
+}
+
+fn default_object_store_multipart_concurrency() -> usize {
+    4
+}
+
+fn default_object_store_presigned_url_expiry_secs() -> u64 {
+    3600
+}
+
+/// Retries of the S3, GCS and Azure clients, which are disabled: requests are only retried by the
+/// `RetryingObjectStore` of `make_with_retry`, with the configured retries, jitter and budget.
+fn backend_retry_config() -> object_store::RetryConfig {
+    object_store::RetryConfig {
+        max_retries: 0,
+        ..Default::default()
+    }
+}
+
 impl ObjectStoreConfig {
+    /// Where S3 credentials are taken from, given the configured keys, profile and web identity.
+    pub fn aws_credential_source(&self) -> AwsCredentialSource {
+        if self.aws_access_key_id.is_some() || self.aws_secret_access_key.is_some() {
+            AwsCredentialSource::Static
+        } else if let Some(profile) = &self.aws_profile {
+            AwsCredentialSource::Profile(profile.clone())
+        } else if self.aws_web_identity {
+            AwsCredentialSource::WebIdentity
+        } else {
+            AwsCredentialSource::Instance
+        }
+    }
+    /// Server-side encryption of the objects written to S3, if configured.
+    pub fn server_side_encryption(&self) -> Result<Option<ServerSideEncryption>> {
+        match self.aws_sse {
+            Some(mode) => Ok(Some(ServerSideEncryption::new(
+                mode,
+                self.aws_kms_key_id.clone(),
+            )?)),
+            None if self.aws_kms_key_id.is_some() => {
+                Err(anyhow!("--aws-kms-key-id requires --aws-sse kms"))
+            }
+            None => Ok(None),
+        }
+    }
+    /// Settings of the objects written to S3.
+    pub fn s3_write_options(&self) -> Result<S3WriteOptions> {
+        Ok(S3WriteOptions {
+            sse: self.server_side_encryption()?,
+            storage_class: self.aws_storage_class,
+        })
+    }
+    /// Where Azure credentials are taken from, given the configured key, SAS token and identity.
+    pub fn azure_credential_source(&self) -> AzureCredentialSource {
+        if self.azure_storage_access_key.is_some() {
+            AzureCredentialSource::AccessKey
+        } else if self.azure_storage_sas_token.is_some() {
+            AzureCredentialSource::SasToken
+        } else if let (Some(client_id), Some(tenant_id), Some(federated_token_file)) = (
+            &self.azure_client_id,
+            &self.azure_tenant_id,
+            &self.azure_federated_token_file,
+        ) {
+            AzureCredentialSource::WorkloadIdentity {
+                client_id: client_id.clone(),
+                tenant_id: tenant_id.clone(),
+                federated_token_file: federated_token_file.clone(),
+            }
+        } else {
+            AzureCredentialSource::ManagedIdentity {
+                client_id: self.azure_client_id.clone(),
+            }
+        }
+    }
+    /// Where GCS credentials are taken from, given the configured service account and application
+    /// default credentials.
+    pub fn gcs_credential_source(&self) -> GcsCredentialSource {
+        if self.no_sign_request {
+            GcsCredentialSource::Anonymous
+        } else if let Some(account) = &self.google_service_account {
+            GcsCredentialSource::ServiceAccount(account.clone())
+        } else {
+            GcsCredentialSource::ApplicationDefault(self.google_application_credentials.clone())
+        }
+    }
+    /// Options of the HTTP clients of the S3, GCS and Azure stores, sending requests to `host`.
+    pub fn client_options(&self, host: &str) -> Result<ClientOptions, anyhow::Error> {
+        let config = &self.object_store_client;
+        let mut options = ClientOptions::new();
+        if let Some(proxy_url) = proxy::proxy_url(config, host)? {
+            options = options.with_proxy_url(proxy_url);
+        }
+        if let Some(secs) = config.timeout_secs {
+            options = options.with_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = config.connect_timeout_secs {
+            options = options.with_connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = config.pool_idle_timeout_secs {
+            options = options.with_pool_idle_timeout(Duration::from_secs(secs));
+        }
+        if let Some(max_idle) = config.pool_max_idle_per_host {
+            options = options.with_pool_max_idle_per_host(max_idle);
+        }
+        if let Some(secs) = config.http2_keep_alive_interval_secs {
+            options = options.with_http2_keep_alive_interval(Duration::from_secs(secs));
+        }
+        if config.http2_only {
+            options = options.with_http2_only();
+        }
+        tls::apply_to_client_options(config, options)
+    }
+    /// Host S3 requests are sent to, for the no-proxy list.
+    fn s3_host(&self) -> Result<String, anyhow::Error> {
+        if let Some(endpoint) = &self.aws_endpoint {
+            return proxy::url_host(endpoint);
+        }
+        let region = self.aws_region.as_deref().unwrap_or(DEFAULT_AWS_REGION);
+        match (&self.bucket, self.aws_virtual_hosted_style_request) {
+            (Some(bucket), true) => Ok(format!("{bucket}.s3.{region}.amazonaws.com")),
+            _ => Ok(format!("s3.{region}.amazonaws.com")),
+        }
+    }
+    /// `store` with at most `--object-store-connection-limit` requests in flight, or an adaptive
+    /// number of them.
+    fn limit_connections<T: object_store::ObjectStore>(&self, store: T) -> Arc<DynObjectStore> {
+        if self.object_store_adaptive_concurrency {
+            Arc::new(AdaptiveLimitStore::new(
+                store,
+                AdaptiveLimitConfig {
+                    min_limit: self.object_store_adaptive_min_connections,
+                    max_limit: self.object_store_connection_limit,
+                    latency_target: Duration::from_millis(
+                        self.object_store_adaptive_latency_target_ms,
+                    ),
+                },
+            ))
+        } else {
+            Arc::new(object_store::limit::LimitStore::new(
+                store,
+                self.object_store_connection_limit,
+            ))
+        }
+    }
     fn new_local_fs(&self) -> Result<Arc<DynObjectStore>, anyhow::Error> {
         info!(directory=?self.directory, object_store_type="File", "Object Store");
         if let Some(path) = &self.directory {
@@ -119,12 +675,27 @@ impl ObjectStoreConfig {
             Err(anyhow!("No directory provided for local fs storage"))
         }
     }
+    fn new_memory(&self) -> Result<Arc<DynObjectStore>, anyhow::Error> {
+        static MEMORY_STORES: Lazy<Mutex<HashMap<String, Arc<InMemory>>>> =
+            Lazy::new(Default::default);
+        info!(bucket=?self.bucket, object_store_type="Memory", "Object Store");
+        let store = MEMORY_STORES
+            .lock()
+            .entry(self.bucket.clone().unwrap_or_default())
+            .or_insert_with(|| Arc::new(InMemory::new()))
+            .clone();
+        Ok(store)
+    }
     fn new_s3(&self) -> Result<Arc<DynObjectStore>, anyhow::Error> {
-        use object_store::limit::LimitStore;
-
-        info!(bucket=?self.bucket, object_store_type="S3", "Object Store");
+        let credential_source = self.aws_credential_source();
+        info!(bucket=?self.bucket, object_store_type="S3", credentials=?credential_source,
+          role_arn=?self.aws_role_arn, "Object Store");
 
-        let mut builder = AmazonS3Builder::new().with_imdsv1_fallback();
+        // Set first, as the other client settings of the builder are stored in the options.
+        let mut builder = AmazonS3Builder::new()
+            .with_client_options(self.client_options(&self.s3_host()?)?)
+            .with_retry(backend_retry_config())
+            .with_imdsv1_fallback();
 
         if self.aws_virtual_hosted_style_request {
             builder = builder.with_virtual_hosted_style_request(true);
@@ -138,51 +709,112 @@ impl ObjectStoreConfig {
         if let Some(bucket) = &self.bucket {
             builder = builder.with_bucket_name(bucket);
         }
-        if let Some(key_id) = &self.aws_access_key_id {
-            builder = builder.with_access_key_id(key_id);
-        }
-        if let Some(secret) = &self.aws_secret_access_key {
-            builder = builder.with_secret_access_key(secret);
-        }
         if let Some(endpoint) = &self.aws_endpoint {
             builder = builder.with_endpoint(endpoint);
         }
-        // if let Some(profile) = &self.aws_profile {
-        //     builder = builder.with_profile(profile);
-        // }
-        Ok(Arc::new(LimitStore::new(
-            builder.build().context("Invalid s3 config")?,
-            self.object_store_connection_limit,
-        )))
+        if let (AwsCredentialSource::Static, Some(profile)) =
+            (&credential_source, &self.aws_profile)
+        {
+            warn!(%profile, "Ignoring AWS profile as an access key is configured");
+        }
+        if self.aws_web_identity && credential_source != AwsCredentialSource::WebIdentity {
+            warn!("Ignoring AWS web identity as an access key or profile is configured");
+        }
+        if let Some(role_arn) = &self.aws_role_arn {
+            builder = builder.with_credentials(Arc::new(AwsSdkCredentialProvider::assume_role(
+                self, role_arn,
+            )?));
+        } else {
+            match credential_source {
+                AwsCredentialSource::Static => {
+                    if let Some(key_id) = &self.aws_access_key_id {
+                        builder = builder.with_access_key_id(key_id);
+                    }
+                    if let Some(secret) = &self.aws_secret_access_key {
+                        builder = builder.with_secret_access_key(secret);
+                    }
+                }
+                AwsCredentialSource::Profile(profile) => {
+                    builder = builder
+                        .with_credentials(Arc::new(AwsSdkCredentialProvider::profile(&profile)));
+                }
+                AwsCredentialSource::WebIdentity => {
+                    builder = builder
+                        .with_credentials(Arc::new(AwsSdkCredentialProvider::web_identity(self)?));
+                }
+                // Left to the builder, which falls back to web identity, container and instance
+                // metadata credentials in that order.
+                AwsCredentialSource::Instance => {}
+            }
+        }
+        let mut store = self.limit_connections(builder.build().context("Invalid s3 config")?);
+        let options = self.s3_write_options()?;
+        if !options.is_empty() {
+            store = Arc::new(S3WriteStore::new(store, self, options)?);
+        }
+        if self.requester_pays {
+            store = Arc::new(RequesterPaysS3Store::new(store, self)?);
+        }
+        Ok(store)
     }
     fn new_gcs(&self) -> Result<Arc<DynObjectStore>, anyhow::Error> {
         use object_store::gcp::GoogleCloudStorageBuilder;
-        use object_store::limit::LimitStore;
 
-        info!(bucket=?self.bucket, object_store_type="GCS", "Object Store");
+        let credential_source = self.gcs_credential_source();
+        info!(bucket=?self.bucket, object_store_type="GCS", credentials=?credential_source,
+          "Object Store");
 
-        let mut builder = GoogleCloudStorageBuilder::new();
+        let mut options = self.client_options(GCS_HOST)?;
+        if self.requester_pays {
+            let project = self.google_billing_project.as_deref().ok_or_else(|| {
+                anyhow!("--google-billing-project is required with --requester-pays")
+            })?;
+            options = options.with_default_headers(requester_pays::gcs_headers(project)?);
+        }
+        let mut builder = GoogleCloudStorageBuilder::new()
+            .with_client_options(options)
+            .with_retry(backend_retry_config());
 
         if let Some(bucket) = &self.bucket {
             builder = builder.with_bucket_name(bucket);
         }
-        if let Some(account) = &self.google_service_account {
-            builder = builder.with_service_account_path(account);
+        match credential_source {
+            // Every request made by the store is signed.
+            GcsCredentialSource::Anonymous => {
+                return Err(anyhow!(
+                    "Unsigned requests to GCS are only supported for downloads, through make_http"
+                ));
+            }
+            GcsCredentialSource::ServiceAccount(account) => {
+                builder = builder.with_service_account_path(account);
+            }
+            GcsCredentialSource::ApplicationDefault(path) => {
+                // Without a path, the builder falls back to the gcloud credentials file and then
+                // the metadata server.
+                if let Some(path) =
+                    path.or_else(|| std::env::var(GOOGLE_APPLICATION_CREDENTIALS_ENV_VAR).ok())
+                {
+                    builder = builder.with_application_credentials(path);
+                }
+            }
         }
 
-        Ok(Arc::new(LimitStore::new(
-            builder.build().context("Invalid gcs config")?,
-            self.object_store_connection_limit,
-        )))
+        Ok(self.limit_connections(builder.build().context("Invalid gcs config")?))
     }
     fn new_azure(&self) -> Result<Arc<DynObjectStore>, anyhow::Error> {
-        use object_store::azure::MicrosoftAzureBuilder;
-        use object_store::limit::LimitStore;
+        use object_store::azure::{AzureConfigKey, MicrosoftAzureBuilder};
 
+        let credential_source = self.azure_credential_source();
         info!(bucket=?self.bucket, account=?self.azure_storage_account,
-          object_store_type="Azure", "Object Store");
+          object_store_type="Azure", credentials=?credential_source, "Object Store");
 
-        let mut builder = MicrosoftAzureBuilder::new();
+        let host = match &self.azure_storage_account {
+            Some(account) => format!("{account}.blob.core.windows.net"),
+            None => "blob.core.windows.net".to_string(),
+        };
+        let mut builder = MicrosoftAzureBuilder::new()
+            .with_client_options(self.client_options(&host)?)
+            .with_retry(backend_retry_config());
 
         if let Some(bucket) = &self.bucket {
             builder = builder.with_container_name(bucket);
@@ -190,22 +822,333 @@ impl ObjectStoreConfig {
         if let Some(account) = &self.azure_storage_account {
             builder = builder.with_account(account)
         }
-        if let Some(key) = &self.azure_storage_access_key {
-            builder = builder.with_access_key(key)
+        // Only the chosen credentials are passed on, as the builder has its own precedence.
+        match credential_source {
+            AzureCredentialSource::AccessKey => {
+                if let Some(key) = &self.azure_storage_access_key {
+                    builder = builder.with_access_key(key)
+                }
+            }
+            AzureCredentialSource::SasToken => {
+                if let Some(token) = &self.azure_storage_sas_token {
+                    builder = builder.with_config(AzureConfigKey::SasKey, token);
+                }
+            }
+            AzureCredentialSource::WorkloadIdentity {
+                client_id,
+                tenant_id,
+                federated_token_file,
+            } => {
+                builder = builder
+                    .with_client_id(client_id)
+                    .with_tenant_id(tenant_id)
+                    .with_federated_token_file(federated_token_file.to_string_lossy());
+            }
+            AzureCredentialSource::ManagedIdentity { client_id } => {
+                if let Some(client_id) = client_id {
+                    builder = builder.with_client_id(client_id);
+                }
+            }
         }
 
-        Ok(Arc::new(LimitStore::new(
-            builder.build().context("Invalid azure config")?,
-            self.object_store_connection_limit,
+        Ok(self.limit_connections(builder.build().context("Invalid azure config")?))
+    }
+    pub fn retry_config(&self) -> RetryConfig {
+        RetryConfig {
+            max_retries: self.object_store_max_retries,
+            base_delay: Duration::from_millis(self.object_store_retry_base_delay_ms),
+            jitter: self.object_store_retry_jitter,
+            retry_not_found: self.object_store_retry_not_found,
+            budget: None,
+        }
+    }
+    pub fn multipart_config(&self) -> MultipartConfig {
+        MultipartConfig {
+            part_size: self.object_store_multipart_part_size_mb * 1024 * 1024,
+            concurrency: self.object_store_multipart_concurrency,
+            retry: self.retry_config(),
+        }
+    }
+    /// Size in bytes above which objects are streamed to the store, see
+    /// `--object-store-streaming-threshold-mb`.
+    pub fn streaming_threshold(&self) -> usize {
+        self.object_store_streaming_threshold_mb
+            .map_or(util::DEFAULT_STREAMING_THRESHOLD, |mb| mb * 1024 * 1024)
+    }
+    /// Key objects are encrypted with, if `--object-store-encryption-key` is set.
+    pub fn envelope_key(&self) -> Result<Option<Arc<dyn EnvelopeKey>>, anyhow::Error> {
+        let Some(key) = &self.object_store_encryption_key else {
+            return Ok(None);
+        };
+        let key: Arc<dyn EnvelopeKey> = match self.object_store_encryption_key_source {
+            EncryptionKeySource::File => Arc::new(LocalEnvelopeKey::from_file(key.as_ref())?),
+            EncryptionKeySource::Kms => {
+                let region = self
+                    .aws_region
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_AWS_REGION.to_string());
+                let config = aws_sdk_kms::Config::builder()
+                    .region(aws_sdk_kms::config::Region::new(region))
+                    .credentials_provider(sdk_credentials_provider(self)?)
+                    .build();
+                Arc::new(KmsEnvelopeKey::new(
+                    aws_sdk_kms::Client::from_conf(config),
+                    key.clone(),
+                ))
+            }
+        };
+        Ok(Some(key))
+    }
+    pub fn read_after_write_timeout(&self) -> Option<Duration> {
+        self.object_store_read_after_write_timeout_secs
+            .map(Duration::from_secs)
+    }
+    /// Bandwidth limits of the stores made from this config. Configs of the same store and limits
+    /// share their token buckets, so that the limits hold across every store made from them.
+    pub fn bandwidth_limits(&self) -> BandwidthLimits {
+        static BANDWIDTH_LIMITS: Lazy<Mutex<HashMap<String, BandwidthLimits>>> =
+            Lazy::new(Default::default);
+        if self.object_store_max_upload_mbps.is_none()
+            && self.object_store_max_download_mbps.is_none()
+        {
+            return BandwidthLimits::default();
+        }
+        let key = format!(
+            "{:?}/{:?}/{:?}/{:?}/{:?}",
+            self.object_store,
+            self.bucket,
+            self.directory,
+            self.object_store_max_upload_mbps,
+            self.object_store_max_download_mbps
+        );
+        BANDWIDTH_LIMITS
+            .lock()
+            .entry(key)
+            .or_insert_with(|| BandwidthLimits {
+                upload: self
+                    .object_store_max_upload_mbps
+                    .map(|mbps| Arc::new(TokenBucket::from_mbps(mbps))),
+                download: self
+                    .object_store_max_download_mbps
+                    .map(|mbps| Arc::new(TokenBucket::from_mbps(mbps))),
+            })
+            .clone()
+    }
+    /// Limits of the concurrent reads and writes of each store made from this config.
+    pub fn concurrency_limits(&self) -> ConcurrencyLimits {
+        ConcurrencyLimits::new(
+            self.object_store_read_connection_limit,
+            self.object_store_write_connection_limit,
+            self.object_store_list_connection_limit,
+            self.object_store_delete_connection_limit,
+        )
+    }
+    /// Circuit breaker of the stores made from this config, if
+    /// `--object-store-circuit-breaker-failures` is set.
+    pub fn circuit_breaker_config(&self) -> Option<CircuitBreakerConfig> {
+        self.object_store_circuit_breaker_failures
+            .map(|failure_threshold| CircuitBreakerConfig {
+                failure_threshold,
+                probe_interval: Duration::from_secs(
+                    self.object_store_circuit_breaker_probe_interval_secs,
+                ),
+            })
+    }
+    /// Validity of the presigned URLs handed out for objects of the store.
+    pub fn presigned_url_expiry(&self) -> Duration {
+        Duration::from_secs(self.object_store_presigned_url_expiry_secs)
+    }
+    /// Signer of URLs granting access to objects of the store without credentials. S3 URLs are
+    /// signed with any credentials, GCS URLs need a service account and Azure URLs an access key.
+    /// Encrypted stores have none, as objects would be read and written unencrypted.
+    pub fn make_signer(&self) -> Result<Arc<dyn ObjectStoreSignExt>, anyhow::Error> {
+        if self.object_store_encryption_key.is_some() {
+            return Err(anyhow!("Objects of encrypted stores can't be presigned"));
+        }
+        match &self.object_store {
+            Some(ObjectStoreType::S3) => Ok(Arc::new(S3Signer::new(self)?)),
+            Some(ObjectStoreType::GCS) => match self.gcs_credential_source() {
+                GcsCredentialSource::ServiceAccount(path) => {
+                    Ok(Arc::new(GcsSigner::new(self, &path)?))
+                }
+                _ => Err(anyhow!(
+                    "Presigning GCS urls requires --google-service-account"
+                )),
+            },
+            Some(ObjectStoreType::Azure) => match &self.azure_storage_access_key {
+                Some(key) => Ok(Arc::new(AzureSigner::new(self, key)?)),
+                None => Err(anyhow!(
+                    "Presigning Azure urls requires --azure-storage-access-key"
+                )),
+            },
+            store => Err(anyhow!("Objects of {store:?} stores can't be presigned")),
+        }
+    }
+    /// Writer of objects that must not exist yet or must not have changed since they were read.
+    /// S3, GCS and Azure writes are sent to presigned URLs, and need the same credentials as
+    /// [`Self::make_signer`].
+    pub fn make_conditional_put(
+        &self,
+    ) -> Result<Arc<dyn ObjectStoreConditionalPutExt>, anyhow::Error> {
+        let Some(store_type) = self.object_store else {
+            return Err(anyhow!("At least one storage backend should be provided"));
+        };
+        let host = match store_type {
+            ObjectStoreType::File | ObjectStoreType::Memory => {
+                return Ok(Arc::new(LockedConditionalPut::new(self.make()?)));
+            }
+            ObjectStoreType::S3 => self.s3_host()?,
+            ObjectStoreType::GCS => GCS_HOST.to_string(),
+            ObjectStoreType::Azure => format!(
+                "{}.blob.core.windows.net",
+                self.azure_storage_account.as_deref().unwrap_or_default()
+            ),
+        };
+        let client = self.http_client_builder(&host)?.build()?;
+        Ok(Arc::new(SignedConditionalPut::new(
+            store_type,
+            self.make_signer()?,
+            client,
         )))
     }
-    pub fn make(&self) -> Result<Arc<DynObjectStore>, anyhow::Error> {
+    /// Reader of the previous versions of objects, in S3 and GCS buckets with versioning enabled.
+    /// GCS versions are read with an access token of the service account, and need the same
+    /// credentials as [`Self::make_signer`].
+    pub fn make_versioned_reader(&self) -> Result<Arc<dyn ObjectStoreVersionExt>, anyhow::Error> {
+        if self.object_store_encryption_key.is_some() {
+            return Err(anyhow!(
+                "Versions of objects of encrypted stores can't be read"
+            ));
+        }
+        match &self.object_store {
+            Some(ObjectStoreType::S3) => Ok(Arc::new(S3VersionedReader::new(self)?)),
+            Some(ObjectStoreType::GCS) => match self.gcs_credential_source() {
+                GcsCredentialSource::ServiceAccount(path) => {
+                    Ok(Arc::new(GcsVersionedReader::new(self, &path)?))
+                }
+                _ => Err(anyhow!(
+                    "Reading versions of GCS objects requires --google-service-account"
+                )),
+            },
+            store => Err(anyhow!("Objects of {store:?} stores have no versions")),
+        }
+    }
+    /// Uploader for large objects, retrying parts individually. Only S3 and the local file system
+    /// are supported, other stores upload large objects with [`ObjectStorePutExt::put_stream`].
+    /// Encrypted stores have none, as parts would be written unencrypted.
+    pub fn make_multipart(&self) -> Result<Option<Arc<MultipartUploader>>, anyhow::Error> {
+        if self.object_store_encryption_key.is_some() {
+            return Ok(None);
+        }
+        let store: Arc<dyn multipart::MultipartStore> = match &self.object_store {
+            Some(ObjectStoreType::File) => Arc::new(LocalMultipartStore::new(
+                self.directory
+                    .clone()
+                    .ok_or_else(|| anyhow!("No directory provided for local fs storage"))?,
+            )),
+            Some(ObjectStoreType::S3) => Arc::new(S3MultipartStore::new(self)?),
+            _ => return Ok(None),
+        };
+        let store: Arc<dyn multipart::MultipartStore> = match self.bandwidth_limits().upload {
+            Some(upload) => Arc::new(ThrottledMultipartStore::new(store, upload)),
+            None => store,
+        };
+        Ok(Some(Arc::new(MultipartUploader::new(
+            store,
+            self.multipart_config(),
+        ))))
+    }
+    /// Deleter of many objects at once, see [`ObjectStoreDeleteExt::delete_objects`]. S3 objects
+    /// are deleted up to 1000 at a time, objects of other stores one by one.
+    pub fn make_batch_delete(&self) -> Result<Arc<dyn ObjectStoreDeleteExt>, anyhow::Error> {
         match &self.object_store {
+            Some(ObjectStoreType::S3) => Ok(Arc::new(S3BatchDelete::new(self)?)),
+            _ => Ok(Arc::new(self.make()?)),
+        }
+    }
+    /// Store scoped to `prefix` of the configured store, for subsystems sharing a bucket.
+    pub fn with_prefix(
+        &self,
+        prefix: impl Into<Path>,
+    ) -> Result<PrefixedStore<Arc<DynObjectStore>>, anyhow::Error> {
+        Ok(PrefixedStore::new(self.make()?, prefix))
+    }
+    pub fn make(&self) -> Result<Arc<DynObjectStore>, anyhow::Error> {
+        self.make_with_retry(self.retry_config(), None)
+    }
+    /// Store reporting the state of its circuit breaker, if any, to `metrics`.
+    pub fn make_with_circuit_breaker_metrics(
+        &self,
+        metrics: Arc<CircuitBreakerMetrics>,
+    ) -> Result<Arc<DynObjectStore>, anyhow::Error> {
+        self.make_with_retry(self.retry_config(), Some(metrics))
+    }
+    /// Check that the configured store can be written, read, listed and deleted from with a probe
+    /// object, see [`validate`].
+    pub async fn validate(&self) -> Result<(), ValidationError> {
+        let store = self
+            .make()
+            .map_err(|e| ValidationError::new(ValidationStep::Configure, e))?;
+        validate_store(&store).await
+    }
+    /// Store whose retries are drawn from `budget`, shared with the other requests of a
+    /// pipeline iteration.
+    pub fn make_with_retry_budget(
+        &self,
+        budget: Arc<RetryBudget>,
+    ) -> Result<Arc<DynObjectStore>, anyhow::Error> {
+        self.make_with_retry(
+            RetryConfig {
+                budget: Some(budget),
+                ..self.retry_config()
+            },
+            None,
+        )
+    }
+    fn make_with_retry(
+        &self,
+        retry: RetryConfig,
+        circuit_breaker_metrics: Option<Arc<CircuitBreakerMetrics>>,
+    ) -> Result<Arc<DynObjectStore>, anyhow::Error> {
+        let store = match &self.object_store {
             Some(ObjectStoreType::File) => self.new_local_fs(),
             Some(ObjectStoreType::S3) => self.new_s3(),
             Some(ObjectStoreType::GCS) => self.new_gcs(),
             Some(ObjectStoreType::Azure) => self.new_azure(),
+            Some(ObjectStoreType::Memory) => self.new_memory(),
             _ => Err(anyhow!("At least one storage backend should be provided")),
+        }?;
+        let concurrency_limits = self.concurrency_limits();
+        let store: Arc<DynObjectStore> = if concurrency_limits.is_unlimited() {
+            store
+        } else {
+            Arc::new(ConcurrencyLimitedObjectStore::new(
+                store,
+                concurrency_limits,
+            ))
+        };
+        let limits = self.bandwidth_limits();
+        let store: Arc<DynObjectStore> = if limits.is_unlimited() {
+            store
+        } else {
+            Arc::new(ThrottledObjectStore::new(store, limits))
+        };
+        let store: Arc<DynObjectStore> = match self.circuit_breaker_config() {
+            Some(config) => {
+                let breaker =
+                    CircuitBreaker::new(store.to_string(), config, circuit_breaker_metrics);
+                Arc::new(CircuitBreakerObjectStore::new(store, Arc::new(breaker)))
+            }
+            None => store,
+        };
+        let store: Arc<DynObjectStore> = if self.object_store_max_retries == 0 {
+            store
+        } else {
+            Arc::new(RetryingObjectStore::new(store, retry))
+        };
+        match self.envelope_key()? {
+            Some(key) => Ok(Arc::new(EncryptedObjectStore::new(store, key))),
+            None => Ok(store),
         }
     }
 }
@@ -213,16 +1156,33 @@ impl ObjectStoreConfig {
 #[async_trait]
 pub trait ObjectStoreGetExt: std::fmt::Display + Send + Sync + 'static {
     /// Return the bytes at given path in object store
-    async fn get_bytes(&self, src: &Path) -> Result<Bytes>;
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes>;
+
+    /// Return the bytes at given path in object store as a stream of chunks, so that large
+    /// objects don't need to fit in memory. Stores that can't stream return a single chunk.
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        let bytes = self.get_bytes(src).await?;
+        Ok(futures::stream::once(async { Ok(bytes) }).boxed())
+    }
 }
 
 macro_rules! as_ref_get_ext_impl {
     ($type:ty) => {
         #[async_trait]
         impl ObjectStoreGetExt for $type {
-            async fn get_bytes(&self, src: &Path) -> Result<Bytes> {
+            async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
                 self.as_ref().get_bytes(src).await
             }
+
+            async fn get_stream(
+                &self,
+                src: &Path,
+            ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+                self.as_ref().get_stream(src).await
+            }
         }
     };
 }
@@ -232,12 +1192,93 @@ as_ref_get_ext_impl!(Box<dyn ObjectStoreGetExt>);
 
 #[async_trait]
 impl ObjectStoreGetExt for Arc<DynObjectStore> {
-    async fn get_bytes(&self, src: &Path) -> Result<Bytes> {
-        self.get(src)
-            .await?
-            .bytes()
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        let result = async { self.get(src).await?.bytes().await }.await;
+        result.map_err(|e| ObjectStoreError::from(e).context(format!("Failed to get file: {src}")))
+    }
+
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        let src = src.clone();
+        let stream = self
+            .get(&src)
             .await
-            .map_err(|e| anyhow!("Failed to get file: {} with error: {}", src, e.to_string()))
+            .map_err(|e| ObjectStoreError::from(e).context(format!("Failed to get file: {src}")))?
+            .into_stream();
+        Ok(stream
+            .map_err(move |e| {
+                ObjectStoreError::from(e).context(format!("Failed to get file: {src}"))
+            })
+            .boxed())
+    }
+}
+
+#[async_trait]
+pub trait ObjectStoreGetRangeExt: std::fmt::Display + Send + Sync + 'static {
+    /// Return the bytes in the given range of the object at given path in object store, without
+    /// downloading the rest of the object
+    async fn get_byte_range(&self, src: &Path, range: Range<usize>) -> ObjectStoreResult<Bytes>;
+
+    /// Return the bytes in each of the given ranges of the object at given path in object store
+    async fn get_byte_ranges(
+        &self,
+        src: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        let mut result = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            result.push(self.get_byte_range(src, range.clone()).await?);
+        }
+        Ok(result)
+    }
+}
+
+macro_rules! as_ref_get_range_ext_impl {
+    ($type:ty) => {
+        #[async_trait]
+        impl ObjectStoreGetRangeExt for $type {
+            async fn get_byte_range(
+                &self,
+                src: &Path,
+                range: Range<usize>,
+            ) -> ObjectStoreResult<Bytes> {
+                self.as_ref().get_byte_range(src, range).await
+            }
+
+            async fn get_byte_ranges(
+                &self,
+                src: &Path,
+                ranges: &[Range<usize>],
+            ) -> ObjectStoreResult<Vec<Bytes>> {
+                self.as_ref().get_byte_ranges(src, ranges).await
+            }
+        }
+    };
+}
+
+as_ref_get_range_ext_impl!(Arc<dyn ObjectStoreGetRangeExt>);
+as_ref_get_range_ext_impl!(Box<dyn ObjectStoreGetRangeExt>);
+
+#[async_trait]
+impl ObjectStoreGetRangeExt for Arc<DynObjectStore> {
+    async fn get_byte_range(&self, src: &Path, range: Range<usize>) -> ObjectStoreResult<Bytes> {
+        self.get_range(src, range.clone()).await.map_err(|e| {
+            ObjectStoreError::from(e)
+                .context(format!("Failed to get range {range:?} of file: {src}"))
+        })
+    }
+
+    async fn get_byte_ranges(
+        &self,
+        src: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        // Nearby ranges are coalesced into fewer requests by the store.
+        self.get_ranges(src, ranges).await.map_err(|e| {
+            ObjectStoreError::from(e).context(format!("Failed to get ranges of file: {src}"))
+        })
     }
 }
 
@@ -247,7 +1288,96 @@ pub trait ObjectStoreListExt: Send + Sync + 'static {
     async fn list_objects(
         &self,
         src: Option<&Path>,
-    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>>;
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>>;
+
+    /// List the objects directly under the given path, and the prefixes of the objects nested
+    /// deeper ("directories"), e.g. `epoch_10` for `epoch_10/1_1.obj` at the root. Stores that
+    /// list by delimiter don't return the nested objects; others list every object under the
+    /// path and group them.
+    async fn list_objects_with_delimiter(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<ListResult> {
+        let depth = src.map_or(0, |src| src.parts().count());
+        let mut common_prefixes = BTreeSet::new();
+        let mut objects = vec![];
+        let mut stream = self.list_objects(src).await?;
+        while let Some(meta) = stream.try_next().await? {
+            let parts: Vec<_> = meta.location.parts().collect();
+            if parts.len() > depth + 1 {
+                common_prefixes.insert(parts.into_iter().take(depth + 1).collect::<Path>());
+            } else {
+                objects.push(meta);
+            }
+        }
+        Ok(ListResult {
+            common_prefixes: common_prefixes.into_iter().collect(),
+            objects,
+        })
+    }
+
+    /// The objects under `src` whose locations sort after `offset`, e.g. to list only the
+    /// objects added to a prefix since the last one seen. Stores that can't start listing at an
+    /// offset list every object and skip the others.
+    async fn list_objects_after(
+        &self,
+        src: Option<&Path>,
+        offset: &Path,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        let offset = offset.clone();
+        Ok(self
+            .list_objects(src)
+            .await?
+            .try_filter(move |meta| futures::future::ready(meta.location > offset))
+            .boxed())
+    }
+
+    /// A page of at most `max_results` entries of [`Self::list_objects_with_delimiter`],
+    /// prefixes and objects in lexicographic order. `token` is the `next_token` of the previous
+    /// page, or `None` for the first page.
+    async fn list_objects_page(
+        &self,
+        src: Option<&Path>,
+        token: Option<&str>,
+        max_results: NonZeroUsize,
+    ) -> ObjectStoreResult<ListPage> {
+        let result = self.list_objects_with_delimiter(src).await?;
+        let mut entries: Vec<(Path, Option<ObjectMeta>)> = result
+            .common_prefixes
+            .into_iter()
+            .map(|prefix| (prefix, None))
+            .chain(
+                result
+                    .objects
+                    .into_iter()
+                    .map(|meta| (meta.location.clone(), Some(meta))),
+            )
+            .filter(|(location, _)| token.map_or(true, |token| location.as_ref() > token))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+        let next_token = (entries.len() > max_results.get())
+            .then(|| entries[max_results.get() - 1].0.to_string());
+        let mut page = ListPage {
+            next_token,
+            ..Default::default()
+        };
+        for (location, meta) in entries.into_iter().take(max_results.get()) {
+            match meta {
+                Some(meta) => page.objects.push(meta),
+                None => page.common_prefixes.push(location),
+            }
+        }
+        Ok(page)
+    }
+}
+
+/// A page of [`ObjectStoreListExt::list_objects_page`].
+#[derive(Debug, Default)]
+pub struct ListPage {
+    pub common_prefixes: Vec<Path>,
+    pub objects: Vec<ObjectMeta>,
+    /// Token of the next page, `None` if this is the last one
+    pub next_token: Option<String>,
 }
 
 macro_rules! as_ref_list_ext_impl {
@@ -257,9 +1387,32 @@ macro_rules! as_ref_list_ext_impl {
             async fn list_objects(
                 &self,
                 src: Option<&Path>,
-            ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+            ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
                 self.as_ref().list_objects(src).await
             }
+            async fn list_objects_with_delimiter(
+                &self,
+                src: Option<&Path>,
+            ) -> ObjectStoreResult<ListResult> {
+                self.as_ref().list_objects_with_delimiter(src).await
+            }
+            async fn list_objects_after(
+                &self,
+                src: Option<&Path>,
+                offset: &Path,
+            ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+                self.as_ref().list_objects_after(src, offset).await
+            }
+            async fn list_objects_page(
+                &self,
+                src: Option<&Path>,
+                token: Option<&str>,
+                max_results: NonZeroUsize,
+            ) -> ObjectStoreResult<ListPage> {
+                self.as_ref()
+                    .list_objects_page(src, token, max_results)
+                    .await
+            }
         }
     };
 }
@@ -272,24 +1425,73 @@ impl ObjectStoreListExt for Arc<DynObjectStore> {
     async fn list_objects(
         &self,
         src: Option<&Path>,
-    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
-        self.list(src).await
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        let stream = self.list(src).await.map_err(|e| {
+            ObjectStoreError::from(e).context(format!("Failed to list objects under: {src:?}"))
+        })?;
+        Ok(stream.map_err(ObjectStoreError::from).boxed())
+    }
+
+    async fn list_objects_with_delimiter(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<ListResult> {
+        object_store::ObjectStore::list_with_delimiter(self.as_ref(), src)
+            .await
+            .map_err(|e| {
+                ObjectStoreError::from(e).context(format!("Failed to list objects under: {src:?}"))
+            })
+    }
+
+    /// S3 starts listing after the offset, other stores filter their listings.
+    async fn list_objects_after(
+        &self,
+        src: Option<&Path>,
+        offset: &Path,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        let stream = self.list_with_offset(src, offset).await.map_err(|e| {
+            ObjectStoreError::from(e).context(format!("Failed to list objects under: {src:?}"))
+        })?;
+        Ok(stream.map_err(ObjectStoreError::from).boxed())
     }
 }
 
 #[async_trait]
 pub trait ObjectStorePutExt: Send + Sync + 'static {
     /// Write the bytes at the given location in object store
-    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> Result<()>;
+    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()>;
+
+    /// Write the chunks of `stream` at the given location in object store, with a multipart
+    /// upload where the store supports it so that large objects don't need to fit in memory.
+    /// Stores that can't stream buffer the whole stream and write it at once.
+    async fn put_stream(
+        &self,
+        src: &Path,
+        mut stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> ObjectStoreResult<()> {
+        let mut bytes = BytesMut::new();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+        }
+        self.put_bytes(src, bytes.freeze()).await
+    }
 }
 
 macro_rules! as_ref_put_ext_impl {
     ($type:ty) => {
         #[async_trait]
         impl ObjectStorePutExt for $type {
-            async fn put_bytes(&self, src: &Path, bytes: Bytes) -> Result<()> {
+            async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
                 self.as_ref().put_bytes(src, bytes).await
             }
+
+            async fn put_stream(
+                &self,
+                src: &Path,
+                stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+            ) -> ObjectStoreResult<()> {
+                self.as_ref().put_stream(src, stream).await
+            }
         }
     };
 }
@@ -299,25 +1501,82 @@ as_ref_put_ext_impl!(Box<dyn ObjectStorePutExt>);
 
 #[async_trait]
 impl ObjectStorePutExt for Arc<DynObjectStore> {
-    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> Result<()> {
+    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
         self.put(src, bytes).await?;
         Ok(())
     }
+
+    async fn put_stream(
+        &self,
+        src: &Path,
+        mut stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> ObjectStoreResult<()> {
+        let (multipart_id, mut writer) = self.put_multipart(src).await?;
+        let result = async {
+            while let Some(chunk) = stream.next().await {
+                writer.write_all(&chunk?).await?;
+            }
+            // Completes the multipart upload.
+            writer.shutdown().await?;
+            Ok::<_, ObjectStoreError>(())
+        }
+        .await;
+        if let Err(e) = result {
+            // Otherwise the parts already uploaded are kept, and billed, by the store.
+            if let Err(abort_error) = self.abort_multipart(src, &multipart_id).await {
+                warn!("Failed to abort multipart upload of {src}: {abort_error}");
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
 pub trait ObjectStoreDeleteExt: Send + Sync + 'static {
     /// Delete the object at the given location in object store
-    async fn delete_object(&self, src: &Path) -> Result<()>;
+    async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()>;
+
+    /// Delete the objects at the given locations, `concurrency` requests at a time, advancing
+    /// `progress_bar` by one for every object. Objects which fail to be deleted are returned
+    /// with their error rather than failing the others.
+    async fn delete_objects(
+        &self,
+        srcs: &[Path],
+        concurrency: NonZeroUsize,
+        progress_bar: Option<ProgressBar>,
+    ) -> DeleteObjectsResult {
+        let mut results = futures::stream::iter(srcs)
+            .map(|src| async move { (src.clone(), self.delete_object(src).await) })
+            .buffer_unordered(concurrency.get());
+        let mut result = DeleteObjectsResult::default();
+        while let Some((src, deleted)) = results.next().await {
+            if let Some(progress_bar) = &progress_bar {
+                progress_bar.inc(1);
+            }
+            result.record(src, deleted);
+        }
+        result
+    }
 }
 
 macro_rules! as_ref_delete_ext_impl {
     ($type:ty) => {
         #[async_trait]
         impl ObjectStoreDeleteExt for $type {
-            async fn delete_object(&self, src: &Path) -> Result<()> {
+            async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()> {
                 self.as_ref().delete_object(src).await
             }
+            async fn delete_objects(
+                &self,
+                srcs: &[Path],
+                concurrency: NonZeroUsize,
+                progress_bar: Option<ProgressBar>,
+            ) -> DeleteObjectsResult {
+                self.as_ref()
+                    .delete_objects(srcs, concurrency, progress_bar)
+                    .await
+            }
         }
     };
 }
@@ -328,8 +1587,163 @@ as_ref_delete_ext_impl!(Box<dyn ObjectStoreDeleteExt>);
 #[async_trait]
 
 impl ObjectStoreDeleteExt for Arc<DynObjectStore> {
-    async fn delete_object(&self, src: &Path) -> Result<()> {
+    async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()> {
         self.delete(src).await?;
         Ok(())
     }
 }
+
+#[async_trait]
+pub trait ObjectStoreHeadExt: std::fmt::Display + Send + Sync + 'static {
+    /// Return the metadata of the object at given path in object store, e.g. its size, without
+    /// downloading it
+    async fn head_object(&self, src: &Path) -> ObjectStoreResult<ObjectMeta>;
+
+    /// Return whether there is an object at given path in object store
+    async fn exists(&self, src: &Path) -> ObjectStoreResult<bool>;
+}
+
+macro_rules! as_ref_head_ext_impl {
+    ($type:ty) => {
+        #[async_trait]
+        impl ObjectStoreHeadExt for $type {
+            async fn head_object(&self, src: &Path) -> ObjectStoreResult<ObjectMeta> {
+                self.as_ref().head_object(src).await
+            }
+
+            async fn exists(&self, src: &Path) -> ObjectStoreResult<bool> {
+                self.as_ref().exists(src).await
+            }
+        }
+    };
+}
+
+as_ref_head_ext_impl!(Arc<dyn ObjectStoreHeadExt>);
+as_ref_head_ext_impl!(Box<dyn ObjectStoreHeadExt>);
+
+#[async_trait]
+impl ObjectStoreHeadExt for Arc<DynObjectStore> {
+    async fn head_object(&self, src: &Path) -> ObjectStoreResult<ObjectMeta> {
+        self.head(src)
+            .await
+            .map_err(|e| ObjectStoreError::from(e).context(format!("Failed to head file: {src}")))
+    }
+
+    async fn exists(&self, src: &Path) -> ObjectStoreResult<bool> {
+        match self.head_object(src).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.is_not_found() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+pub trait ObjectStoreCopyExt: Send + Sync + 'static {
+    /// Copy the object at `src` to `dst` within the object store, without downloading it
+    async fn copy_object(&self, src: &Path, dst: &Path) -> ObjectStoreResult<()>;
+
+    /// Move the object at `src` to `dst` within the object store. Stores without a native
+    /// rename copy the object and then delete `src`
+    async fn rename_object(&self, src: &Path, dst: &Path) -> ObjectStoreResult<()>;
+}
+
+macro_rules! as_ref_copy_ext_impl {
+    ($type:ty) => {
+        #[async_trait]
+        impl ObjectStoreCopyExt for $type {
+            async fn copy_object(&self, src: &Path, dst: &Path) -> ObjectStoreResult<()> {
+                self.as_ref().copy_object(src, dst).await
+            }
+
+            async fn rename_object(&self, src: &Path, dst: &Path) -> ObjectStoreResult<()> {
+                self.as_ref().rename_object(src, dst).await
+            }
+        }
+    };
+}
+
+as_ref_copy_ext_impl!(Arc<dyn ObjectStoreCopyExt>);
+as_ref_copy_ext_impl!(Box<dyn ObjectStoreCopyExt>);
+
+#[async_trait]
+impl ObjectStoreCopyExt for Arc<DynObjectStore> {
+    async fn copy_object(&self, src: &Path, dst: &Path) -> ObjectStoreResult<()> {
+        self.copy(src, dst).await.map_err(|e| {
+            ObjectStoreError::from(e).context(format!("Failed to copy file: {src} to {dst}"))
+        })
+    }
+
+    async fn rename_object(&self, src: &Path, dst: &Path) -> ObjectStoreResult<()> {
+        // The local file system renames natively, S3, GCS and Azure copy and delete server side.
+        self.rename(src, dst).await.map_err(|e| {
+            ObjectStoreError::from(e).context(format!("Failed to rename file: {src} to {dst}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::{
+        ObjectStoreClientConfig, ObjectStoreConfig, ObjectStoreGetExt, ObjectStorePutExt,
+        ObjectStoreType,
+    };
+    use bytes::Bytes;
+    use object_store::path::Path;
+
+    #[tokio::test]
+    async fn test_memory_stores_share_bucket() -> anyhow::Result<()> {
+        let config = |bucket: &str| ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Memory),
+            bucket: Some(bucket.to_string()),
+            ..Default::default()
+        };
+        let path = Path::from("epoch_0/MANIFEST");
+        config("archive")
+            .make()?
+            .put_bytes(&path, Bytes::from_static(b"manifest"))
+            .await?;
+
+        assert_eq!(
+            config("archive").make()?.get_bytes(&path).await?,
+            Bytes::from_static(b"manifest")
+        );
+        assert!(config("snapshots").make()?.get_bytes(&path).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_settings_apply_to_cloud_stores() -> anyhow::Result<()> {
+        let client = ObjectStoreClientConfig {
+            timeout_secs: Some(10),
+            connect_timeout_secs: Some(2),
+            pool_idle_timeout_secs: Some(30),
+            pool_max_idle_per_host: Some(4),
+            http2_keep_alive_interval_secs: Some(15),
+            http2_only: true,
+            proxy_url: Some("http://proxy.internal:3128".to_string()),
+            no_proxy: vec!["blob.core.windows.net".to_string()],
+            ..Default::default()
+        };
+        ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::S3),
+            bucket: Some("archive".to_string()),
+            aws_region: Some("us-west-2".to_string()),
+            aws_access_key_id: Some("key".to_string()),
+            aws_secret_access_key: Some("secret".to_string()),
+            object_store_client: client.clone(),
+            ..Default::default()
+        }
+        .make()?;
+        ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Azure),
+            bucket: Some("archive".to_string()),
+            azure_storage_account: Some("account".to_string()),
+            azure_storage_access_key: Some("a2V5".to_string()),
+            object_store_client: client,
+            ..Default::default()
+        }
+        .make()?;
+        Ok(())
+    }
+}
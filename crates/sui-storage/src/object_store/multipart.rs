@@ -0,0 +1,551 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Multipart uploads of large objects, driven part by part.
+//!
+//! Uploads through `ObjectStore::put_multipart` fail as a whole on the first failed part, with a
+//! part size and concurrency fixed by the store. [`MultipartUploader`] instead splits objects
+//! into parts of a configured size, keeps a window of them in flight, retries every part on its
+//! own, and aborts the upload when a part keeps failing, so that no parts are left behind.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use backoff::backoff::Backoff;
+use bytes::{Bytes, BytesMut};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use object_store::path::Path;
+use tokio::io::AsyncWriteExt;
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::object_store::aws_credentials::sdk_credentials_provider;
+use crate::object_store::error::ObjectStoreResult;
+use crate::object_store::retry::RetryConfig;
+use crate::object_store::s3_write::{with_write_options, S3WriteOptions};
+use crate::object_store::util::path_to_filesystem;
+use crate::object_store::ObjectStoreConfig;
+
+/// Smallest part size S3 accepts, for all parts but the last one.
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+/// Largest number of parts S3 accepts in an upload.
+pub const MAX_PARTS: usize = 10_000;
+pub(crate) const DEFAULT_AWS_REGION: &str = "us-east-1";
+
+/// Part of a multipart upload, as returned by the store when it was uploaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartId {
+    pub part_idx: usize,
+    pub content_id: String,
+}
+
+/// Store on which multipart uploads can be driven part by part.
+#[async_trait]
+pub trait MultipartStore: Send + Sync + 'static {
+    /// Start a multipart upload of the object at `location`.
+    async fn create_multipart(&self, location: &Path) -> Result<Box<dyn MultipartUpload>>;
+}
+
+#[async_trait]
+pub trait MultipartUpload: Send + Sync + 'static {
+    /// Upload `data` as the part with the given index, starting from 0, and return the id the
+    /// store gave it. Uploading the same part again replaces it.
+    async fn put_part(&self, part_idx: usize, data: Bytes) -> Result<String>;
+
+    /// Assemble the object from all of its parts, in order.
+    async fn complete(&self, parts: Vec<PartId>) -> Result<()>;
+
+    /// Discard the upload and all of its parts uploaded so far.
+    async fn abort(&self) -> Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct MultipartConfig {
+    /// Size of every part but the last one. Raised to [`MIN_PART_SIZE`] if lower.
+    pub part_size: usize,
+    /// Number of parts uploaded at once. Up to this many parts are held in memory.
+    pub concurrency: usize,
+    /// How failed parts are retried.
+    pub retry: RetryConfig,
+}
+
+pub struct MultipartUploader {
+    store: Arc<dyn MultipartStore>,
+    config: MultipartConfig,
+}
+
+impl MultipartUploader {
+    pub fn new(store: Arc<dyn MultipartStore>, config: MultipartConfig) -> Self {
+        Self { store, config }
+    }
+
+    pub fn part_size(&self) -> usize {
+        self.config.part_size.max(MIN_PART_SIZE)
+    }
+
+    /// Upload the chunks of `stream` as the object at `location`, returning its size. On
+    /// failure, the upload is aborted and nothing is written at `location`.
+    pub async fn upload(
+        &self,
+        location: &Path,
+        stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> Result<usize> {
+        let upload: Arc<dyn MultipartUpload> =
+            Arc::from(self.store.create_multipart(location).await?);
+        let result = async {
+            let (parts, size) = self.upload_parts(&upload, stream).await?;
+            debug!("Completing upload of {} parts to {location}", parts.len());
+            upload.complete(parts).await?;
+            Ok(size)
+        }
+        .await;
+        if result.is_err() {
+            if let Err(e) = upload.abort().await {
+                warn!("Failed to abort multipart upload of {location}: {e}");
+            }
+        }
+        result.with_context(|| format!("Failed multipart upload of {location}"))
+    }
+
+    async fn upload_parts(
+        &self,
+        upload: &Arc<dyn MultipartUpload>,
+        mut stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> Result<(Vec<PartId>, usize)> {
+        let part_size = self.part_size();
+        let concurrency = self.config.concurrency.max(1);
+        // Parts are uploaded on their own tasks, so that they make progress while the stream is
+        // read. Parts still in flight are cancelled when the set is dropped on error.
+        let mut in_flight = JoinSet::new();
+        let mut parts = vec![];
+        let mut buffer = BytesMut::new();
+        let mut size = 0;
+        let mut next_part_idx = 0;
+        let mut done = false;
+        while !done {
+            match stream.next().await {
+                Some(chunk) => buffer.extend_from_slice(&chunk?),
+                None => done = true,
+            }
+            // The last part may be smaller than the others, and is uploaded even when empty so
+            // that empty objects can be written too.
+            while buffer.len() >= part_size || (done && (!buffer.is_empty() || next_part_idx == 0))
+            {
+                if next_part_idx == MAX_PARTS {
+                    bail!("Object exceeds {MAX_PARTS} parts of {part_size} bytes");
+                }
+                if in_flight.len() >= concurrency {
+                    parts.push(join_part(&mut in_flight).await?);
+                }
+                let data = buffer.split_to(part_size.min(buffer.len())).freeze();
+                size += data.len();
+                in_flight.spawn(put_part_with_retries(
+                    upload.clone(),
+                    self.config.retry.clone(),
+                    next_part_idx,
+                    data,
+                ));
+                next_part_idx += 1;
+                if buffer.is_empty() {
+                    break;
+                }
+            }
+        }
+        while !in_flight.is_empty() {
+            parts.push(join_part(&mut in_flight).await?);
+        }
+        parts.sort_by_key(|part| part.part_idx);
+        Ok((parts, size))
+    }
+}
+
+async fn join_part(in_flight: &mut JoinSet<Result<PartId>>) -> Result<PartId> {
+    in_flight
+        .join_next()
+        .await
+        .ok_or_else(|| anyhow!("No part in flight"))?
+        .context("Part upload task failed")?
+}
+
+async fn put_part_with_retries(
+    upload: Arc<dyn MultipartUpload>,
+    retry: RetryConfig,
+    part_idx: usize,
+    data: Bytes,
+) -> Result<PartId> {
+    let mut backoff = retry.backoff();
+    let mut retries = 0;
+    loop {
+        match upload.put_part(part_idx, data.clone()).await {
+            Ok(content_id) => {
+                return Ok(PartId {
+                    part_idx,
+                    content_id,
+                })
+            }
+            Err(e) if retries < retry.max_retries => {
+                let Some(delay) = backoff.next_backoff() else {
+                    return Err(e);
+                };
+                if !retry.spend_budget() {
+                    return Err(e.context(format!(
+                        "Failed to upload part {part_idx}, retry budget exhausted"
+                    )));
+                }
+                retries += 1;
+                warn!(
+                    "Retrying upload of part {part_idx} in {delay:?} ({retries}/{}) after error: {e}",
+                    retry.max_retries
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e.context(format!("Failed to upload part {part_idx}"))),
+        }
+    }
+}
+
+/// Multipart uploads to S3, through the AWS SDK.
+pub struct S3MultipartStore {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    options: S3WriteOptions,
+}
+
+impl S3MultipartStore {
+    pub fn new(config: &ObjectStoreConfig) -> Result<Self> {
+        let bucket = config
+            .bucket
+            .clone()
+            .ok_or_else(|| anyhow!("No bucket configured for S3 multipart uploads"))?;
+        Ok(Self {
+            client: s3_sdk_client(config)?,
+            bucket,
+            options: config.s3_write_options()?,
+        })
+    }
+}
+
+/// AWS SDK client of the S3 store of `config`, for the requests the S3 client of object_store
+/// can't make.
+pub(crate) fn s3_sdk_client(config: &ObjectStoreConfig) -> Result<aws_sdk_s3::Client> {
+    let region = config
+        .aws_region
+        .clone()
+        .unwrap_or_else(|| DEFAULT_AWS_REGION.to_string());
+    let mut builder = aws_sdk_s3::Config::builder()
+        .region(Region::new(region))
+        .credentials_provider(sdk_credentials_provider(config)?)
+        .force_path_style(!config.aws_virtual_hosted_style_request);
+    if let Some(endpoint) = &config.aws_endpoint {
+        builder = builder.endpoint_url(endpoint);
+    }
+    Ok(aws_sdk_s3::Client::from_conf(builder.build()))
+}
+
+#[async_trait]
+impl MultipartStore for S3MultipartStore {
+    async fn create_multipart(&self, location: &Path) -> Result<Box<dyn MultipartUpload>> {
+        let key = location.to_string();
+        let request = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&key);
+        let output = with_write_options!(request, &self.options).send().await?;
+        let upload_id = output
+            .upload_id()
+            .ok_or_else(|| anyhow!("S3 returned no upload id for {key}"))?
+            .to_string();
+        Ok(Box::new(S3MultipartUpload {
+            client: self.client.clone(),
+            bucket: self.bucket.clone(),
+            key,
+            upload_id,
+        }))
+    }
+}
+
+struct S3MultipartUpload {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    key: String,
+    upload_id: String,
+}
+
+impl S3MultipartUpload {
+    /// S3 numbers parts from 1.
+    fn part_number(part_idx: usize) -> i32 {
+        part_idx as i32 + 1
+    }
+}
+
+#[async_trait]
+impl MultipartUpload for S3MultipartUpload {
+    async fn put_part(&self, part_idx: usize, data: Bytes) -> Result<String> {
+        let output = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .part_number(Self::part_number(part_idx))
+            .body(ByteStream::from(data))
+            .send()
+            .await?;
+        output
+            .e_tag()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("S3 returned no ETag for part {part_idx} of {}", self.key))
+    }
+
+    async fn complete(&self, parts: Vec<PartId>) -> Result<()> {
+        let parts = parts
+            .into_iter()
+            .map(|part| {
+                CompletedPart::builder()
+                    .part_number(Self::part_number(part.part_idx))
+                    .e_tag(part.content_id)
+                    .build()
+            })
+            .collect();
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn abort(&self) -> Result<()> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&self.key)
+            .upload_id(&self.upload_id)
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+/// Multipart uploads to a local directory. Parts are staged as files next to the object, and
+/// concatenated into it on completion.
+pub struct LocalMultipartStore {
+    root: PathBuf,
+}
+
+impl LocalMultipartStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl MultipartStore for LocalMultipartStore {
+    async fn create_multipart(&self, location: &Path) -> Result<Box<dyn MultipartUpload>> {
+        let target = path_to_filesystem(self.root.clone(), location)?;
+        let parent = target
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid location {location}"))?;
+        tokio::fs::create_dir_all(parent).await?;
+        let staging_dir = tempfile::Builder::new()
+            .prefix(".multipart")
+            .tempdir_in(parent)?
+            .into_path();
+        Ok(Box::new(LocalMultipartUpload {
+            staging_dir,
+            target,
+        }))
+    }
+}
+
+struct LocalMultipartUpload {
+    staging_dir: PathBuf,
+    target: PathBuf,
+}
+
+#[async_trait]
+impl MultipartUpload for LocalMultipartUpload {
+    async fn put_part(&self, part_idx: usize, data: Bytes) -> Result<String> {
+        let path = self.staging_dir.join(part_idx.to_string());
+        tokio::fs::write(&path, data).await?;
+        Ok(path.display().to_string())
+    }
+
+    async fn complete(&self, parts: Vec<PartId>) -> Result<()> {
+        // Parts are assembled in the staging directory and then moved, so the object is never
+        // seen partially written.
+        let assembled = self.staging_dir.join("object");
+        let mut file = tokio::fs::File::create(&assembled).await?;
+        for part in parts {
+            let mut part = tokio::fs::File::open(&part.content_id).await?;
+            tokio::io::copy(&mut part, &mut file).await?;
+        }
+        file.flush().await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&assembled, &self.target).await?;
+        tokio::fs::remove_dir_all(&self.staging_dir).await?;
+        Ok(())
+    }
+
+    async fn abort(&self) -> Result<()> {
+        tokio::fs::remove_dir_all(&self.staging_dir).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::error::ObjectStoreResult;
+    use crate::object_store::multipart::{
+        LocalMultipartStore, MultipartConfig, MultipartStore, MultipartUpload, MultipartUploader,
+        PartId, MIN_PART_SIZE,
+    };
+    use crate::object_store::retry::RetryConfig;
+    use anyhow::{bail, Result};
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use object_store::path::Path;
+    use parking_lot::Mutex;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Fails the first attempt at uploading every part, and every attempt at `poisoned_part`.
+    struct FlakyStore {
+        inner: LocalMultipartStore,
+        poisoned_part: Option<usize>,
+        attempted: Arc<Mutex<HashSet<usize>>>,
+        aborted: Arc<Mutex<bool>>,
+    }
+
+    struct FlakyUpload {
+        inner: Box<dyn MultipartUpload>,
+        poisoned_part: Option<usize>,
+        attempted: Arc<Mutex<HashSet<usize>>>,
+        aborted: Arc<Mutex<bool>>,
+    }
+
+    #[async_trait]
+    impl MultipartStore for FlakyStore {
+        async fn create_multipart(&self, location: &Path) -> Result<Box<dyn MultipartUpload>> {
+            Ok(Box::new(FlakyUpload {
+                inner: self.inner.create_multipart(location).await?,
+                poisoned_part: self.poisoned_part,
+                attempted: self.attempted.clone(),
+                aborted: self.aborted.clone(),
+            }))
+        }
+    }
+
+    #[async_trait]
+    impl MultipartUpload for FlakyUpload {
+        async fn put_part(&self, part_idx: usize, data: Bytes) -> Result<String> {
+            if self.attempted.lock().insert(part_idx) || self.poisoned_part == Some(part_idx) {
+                bail!("Connection reset");
+            }
+            self.inner.put_part(part_idx, data).await
+        }
+
+        async fn complete(&self, parts: Vec<PartId>) -> Result<()> {
+            self.inner.complete(parts).await
+        }
+
+        async fn abort(&self) -> Result<()> {
+            *self.aborted.lock() = true;
+            self.inner.abort().await
+        }
+    }
+
+    fn uploader(store: FlakyStore) -> MultipartUploader {
+        MultipartUploader::new(
+            Arc::new(store),
+            MultipartConfig {
+                part_size: MIN_PART_SIZE,
+                concurrency: 2,
+                retry: RetryConfig {
+                    max_retries: 2,
+                    base_delay: Duration::from_millis(1),
+                    jitter: 0.0,
+                    retry_not_found: false,
+                    budget: None,
+                },
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_retries_parts() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let attempted = Arc::new(Mutex::new(HashSet::new()));
+        let aborted = Arc::new(Mutex::new(false));
+        let uploader = uploader(FlakyStore {
+            inner: LocalMultipartStore::new(dir.path().to_path_buf()),
+            poisoned_part: None,
+            attempted: attempted.clone(),
+            aborted: aborted.clone(),
+        });
+
+        // 3 full parts and a partial one, from chunks that don't line up with parts.
+        let data: Vec<u8> = (0..3 * MIN_PART_SIZE + 1000)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let chunks: Vec<ObjectStoreResult<Bytes>> = data
+            .chunks(1024 * 1024 + 7)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let size = uploader
+            .upload(
+                &Path::from("epoch_0/1_1.obj"),
+                futures::stream::iter(chunks).boxed(),
+            )
+            .await?;
+
+        assert_eq!(size, data.len());
+        assert_eq!(attempted.lock().len(), 4);
+        assert!(!*aborted.lock());
+        assert_eq!(std::fs::read(dir.path().join("epoch_0/1_1.obj"))?, data);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_aborts_on_failure() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let aborted = Arc::new(Mutex::new(false));
+        let uploader = uploader(FlakyStore {
+            inner: LocalMultipartStore::new(dir.path().to_path_buf()),
+            poisoned_part: Some(1),
+            attempted: Default::default(),
+            aborted: aborted.clone(),
+        });
+
+        let data = Bytes::from(vec![0u8; 2 * MIN_PART_SIZE]);
+        let result = uploader
+            .upload(
+                &Path::from("epoch_0/1_1.obj"),
+                futures::stream::once(async { Ok(data) }).boxed(),
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert!(*aborted.lock());
+        assert!(!dir.path().join("epoch_0/1_1.obj").exists());
+        // Staged parts are cleaned up.
+        assert_eq!(std::fs::read_dir(dir.path().join("epoch_0"))?.count(), 0);
+        Ok(())
+    }
+}
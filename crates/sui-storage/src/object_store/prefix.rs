@@ -0,0 +1,313 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use indicatif::ProgressBar;
+use object_store::path::Path;
+use object_store::{ListResult, ObjectMeta};
+
+use crate::object_store::batch_delete::DeleteObjectsResult;
+use crate::object_store::error::ObjectStoreResult;
+use crate::object_store::{
+    ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreListExt,
+    ObjectStorePutExt,
+};
+
+/// Scopes an object store to a prefix: every location is relative to the prefix, which is
+/// prepended to locations given to the store and stripped from the locations it lists. Lets
+/// subsystems sharing a bucket each see their own part of it.
+pub struct PrefixedStore<S> {
+    inner: S,
+    prefix: Path,
+}
+
+impl<S> PrefixedStore<S> {
+    pub fn new(inner: S, prefix: impl Into<Path>) -> Self {
+        Self {
+            inner,
+            prefix: prefix.into(),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    /// Location in the inner store of `location` in this store.
+    pub fn full_path(&self, location: &Path) -> Path {
+        self.prefix.parts().chain(location.parts()).collect()
+    }
+
+    /// Location in this store of `location` in the inner store, if it is under the prefix.
+    pub fn strip_prefix(&self, location: &Path) -> Option<Path> {
+        Some(location.prefix_match(&self.prefix)?.collect())
+    }
+}
+
+impl<S: Display> Display for PrefixedStore<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PrefixedStore({}, {})", self.prefix, self.inner)
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetExt> ObjectStoreGetExt for PrefixedStore<S> {
+    async fn get_bytes(&self, src: &Path) -> ObjectStoreResult<Bytes> {
+        self.inner.get_bytes(&self.full_path(src)).await
+    }
+
+    async fn get_stream(
+        &self,
+        src: &Path,
+    ) -> ObjectStoreResult<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+        self.inner.get_stream(&self.full_path(src)).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreGetRangeExt> ObjectStoreGetRangeExt for PrefixedStore<S> {
+    async fn get_byte_range(&self, src: &Path, range: Range<usize>) -> ObjectStoreResult<Bytes> {
+        self.inner.get_byte_range(&self.full_path(src), range).await
+    }
+
+    async fn get_byte_ranges(
+        &self,
+        src: &Path,
+        ranges: &[Range<usize>],
+    ) -> ObjectStoreResult<Vec<Bytes>> {
+        self.inner
+            .get_byte_ranges(&self.full_path(src), ranges)
+            .await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreListExt> ObjectStoreListExt for PrefixedStore<S> {
+    async fn list_objects(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        let location = match src {
+            Some(src) => self.full_path(src),
+            None => self.prefix.clone(),
+        };
+        let stream = self.inner.list_objects(Some(&location)).await?;
+        Ok(stream
+            .try_filter_map(move |meta| async move {
+                // Stores match prefixes on whole path segments, so this only skips objects
+                // listed by stores that don't.
+                Ok(self
+                    .strip_prefix(&meta.location)
+                    .map(|location| ObjectMeta { location, ..meta }))
+            })
+            .boxed())
+    }
+
+    async fn list_objects_after(
+        &self,
+        src: Option<&Path>,
+        offset: &Path,
+    ) -> ObjectStoreResult<BoxStream<'_, ObjectStoreResult<ObjectMeta>>> {
+        let location = match src {
+            Some(src) => self.full_path(src),
+            None => self.prefix.clone(),
+        };
+        let stream = self
+            .inner
+            .list_objects_after(Some(&location), &self.full_path(offset))
+            .await?;
+        Ok(stream
+            .try_filter_map(move |meta| async move {
+                Ok(self
+                    .strip_prefix(&meta.location)
+                    .map(|location| ObjectMeta { location, ..meta }))
+            })
+            .boxed())
+    }
+
+    async fn list_objects_with_delimiter(
+        &self,
+        src: Option<&Path>,
+    ) -> ObjectStoreResult<ListResult> {
+        let location = match src {
+            Some(src) => self.full_path(src),
+            None => self.prefix.clone(),
+        };
+        let result = self
+            .inner
+            .list_objects_with_delimiter(Some(&location))
+            .await?;
+        Ok(ListResult {
+            common_prefixes: result
+                .common_prefixes
+                .iter()
+                .filter_map(|prefix| self.strip_prefix(prefix))
+                .collect(),
+            objects: result
+                .objects
+                .into_iter()
+                .filter_map(|meta| {
+                    self.strip_prefix(&meta.location)
+                        .map(|location| ObjectMeta { location, ..meta })
+                })
+                .collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStorePutExt> ObjectStorePutExt for PrefixedStore<S> {
+    async fn put_bytes(&self, src: &Path, bytes: Bytes) -> ObjectStoreResult<()> {
+        self.inner.put_bytes(&self.full_path(src), bytes).await
+    }
+
+    async fn put_stream(
+        &self,
+        src: &Path,
+        stream: BoxStream<'static, ObjectStoreResult<Bytes>>,
+    ) -> ObjectStoreResult<()> {
+        self.inner.put_stream(&self.full_path(src), stream).await
+    }
+}
+
+#[async_trait]
+impl<S: ObjectStoreDeleteExt> ObjectStoreDeleteExt for PrefixedStore<S> {
+    async fn delete_object(&self, src: &Path) -> ObjectStoreResult<()> {
+        self.inner.delete_object(&self.full_path(src)).await
+    }
+
+    async fn delete_objects(
+        &self,
+        srcs: &[Path],
+        concurrency: NonZeroUsize,
+        progress_bar: Option<ProgressBar>,
+    ) -> DeleteObjectsResult {
+        let full_paths: Vec<Path> = srcs.iter().map(|src| self.full_path(src)).collect();
+        let result = self
+            .inner
+            .delete_objects(&full_paths, concurrency, progress_bar)
+            .await;
+        let strip = |path: Path| self.strip_prefix(&path).unwrap_or(path);
+        DeleteObjectsResult {
+            deleted: result.deleted.into_iter().map(strip).collect(),
+            failed: result
+                .failed
+                .into_iter()
+                .map(|(path, e)| (strip(path), e))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::prefix::PrefixedStore;
+    use crate::object_store::{ObjectStoreGetExt, ObjectStoreListExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::num::NonZeroUsize;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_prefixed_store() -> anyhow::Result<()> {
+        let inner: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let snapshots = PrefixedStore::new(inner.clone(), "snapshots");
+        let archive = PrefixedStore::new(inner.clone(), "archive");
+
+        snapshots
+            .put_bytes(
+                &Path::from("epoch_0/MANIFEST"),
+                Bytes::from_static(b"snapshot"),
+            )
+            .await?;
+        archive
+            .put_bytes(
+                &Path::from("epoch_0/MANIFEST"),
+                Bytes::from_static(b"archive"),
+            )
+            .await?;
+        assert_eq!(
+            inner
+                .get_bytes(&Path::from("snapshots/epoch_0/MANIFEST"))
+                .await?,
+            Bytes::from_static(b"snapshot")
+        );
+        assert_eq!(
+            archive.get_bytes(&Path::from("epoch_0/MANIFEST")).await?,
+            Bytes::from_static(b"archive")
+        );
+
+        let listed: Vec<_> = snapshots
+            .list_objects(None)
+            .await?
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?;
+        assert_eq!(listed, vec![Path::from("epoch_0/MANIFEST")]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_page() -> anyhow::Result<()> {
+        let inner: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let snapshots = PrefixedStore::new(inner.clone(), "snapshots");
+        for path in [
+            "MANIFEST",
+            "epoch_0/MANIFEST",
+            "epoch_1/1_1.obj",
+            "epoch_1/1_1.ref",
+            "epoch_2/MANIFEST",
+        ] {
+            snapshots
+                .put_bytes(&Path::from(path), Bytes::from_static(b"Lorem ipsum"))
+                .await?;
+        }
+        inner
+            .put_bytes(&Path::from("archive/MANIFEST"), Bytes::from_static(b""))
+            .await?;
+
+        let page_size = NonZeroUsize::new(2).unwrap();
+        let page = snapshots.list_objects_page(None, None, page_size).await?;
+        assert_eq!(page.common_prefixes, vec![Path::from("epoch_0")]);
+        assert_eq!(
+            page.objects
+                .iter()
+                .map(|meta| meta.location.clone())
+                .collect::<Vec<_>>(),
+            vec![Path::from("MANIFEST")]
+        );
+        let token = page.next_token.expect("a second page");
+
+        let page = snapshots
+            .list_objects_page(None, Some(&token), page_size)
+            .await?;
+        assert_eq!(
+            page.common_prefixes,
+            vec![Path::from("epoch_1"), Path::from("epoch_2")]
+        );
+        assert!(page.objects.is_empty());
+        assert_eq!(page.next_token, None);
+
+        let result = snapshots
+            .list_objects_with_delimiter(Some(&Path::from("epoch_1")))
+            .await?;
+        assert!(result.common_prefixes.is_empty());
+        assert_eq!(result.objects.len(), 2);
+        assert_eq!(result.objects[0].location, Path::from("epoch_1/1_1.obj"));
+        Ok(())
+    }
+}
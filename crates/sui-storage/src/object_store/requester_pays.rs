@@ -0,0 +1,370 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reads from requester-pays buckets, whose requests are billed to the requester rather than the
+//! bucket owner, as for some buckets of public archive data.
+//!
+//! GCS requests name the billed project in the `x-goog-user-project` header, set on all requests
+//! of the client. S3 requests must acknowledge the charges in a signed `x-amz-request-payer`
+//! header, which the S3 client of object_store can't sign, so [`RequesterPaysS3Store`] reads and
+//! lists objects through the AWS SDK and leaves writes to the wrapped store.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::DateTime as SdkDateTime;
+use aws_sdk_s3::types::RequestPayer;
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::{Path, DELIMITER};
+use object_store::{
+    DynObjectStore, GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta,
+    ObjectStore,
+};
+use reqwest::header::{HeaderMap, HeaderValue};
+use tokio::io::AsyncWrite;
+
+use crate::object_store::multipart::s3_sdk_client;
+use crate::object_store::ObjectStoreConfig;
+
+const STORE_NAME: &str = "RequesterPaysS3Store";
+const GOOG_USER_PROJECT_HEADER: &str = "x-goog-user-project";
+
+/// Headers of the requests to requester-pays GCS buckets, billed to `billing_project`.
+pub(crate) fn gcs_headers(billing_project: &str) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        GOOG_USER_PROJECT_HEADER,
+        HeaderValue::from_str(billing_project).context("Invalid GCS billing project")?,
+    );
+    Ok(headers)
+}
+
+fn to_store_error(
+    location: &Path,
+    error: impl std::error::Error + Send + Sync + 'static,
+) -> object_store::Error {
+    object_store::Error::Generic {
+        store: STORE_NAME,
+        source: format!("{location}: {:#}", anyhow::Error::new(error)).into(),
+    }
+}
+
+fn not_found(
+    location: &Path,
+    error: impl std::error::Error + Send + Sync + 'static,
+) -> object_store::Error {
+    object_store::Error::NotFound {
+        path: location.to_string(),
+        source: Box::new(error),
+    }
+}
+
+fn to_sdk_time(time: DateTime<Utc>) -> SdkDateTime {
+    SdkDateTime::from_millis(time.timestamp_millis())
+}
+
+fn from_sdk_time(time: Option<&SdkDateTime>) -> DateTime<Utc> {
+    time.and_then(|time| Utc.timestamp_opt(time.secs(), time.subsec_nanos()).single())
+        .unwrap_or_default()
+}
+
+/// Size of the whole object, from the `Content-Range` header of a range request.
+fn object_size(content_range: &str) -> Option<usize> {
+    content_range.rsplit_once('/')?.1.parse().ok()
+}
+
+/// Key prefix of the objects under `prefix`, as listed by object_store.
+fn key_prefix(prefix: Option<&Path>) -> Option<String> {
+    prefix
+        .filter(|prefix| !prefix.as_ref().is_empty())
+        .map(|prefix| format!("{prefix}{DELIMITER}"))
+}
+
+/// S3 store reading from a requester-pays bucket, see the [module documentation](self).
+///
+/// Objects are read whole, in a single response.
+pub struct RequesterPaysS3Store {
+    inner: Arc<DynObjectStore>,
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl RequesterPaysS3Store {
+    pub fn new(inner: Arc<DynObjectStore>, config: &ObjectStoreConfig) -> Result<Self> {
+        let Some(bucket) = config.bucket.clone() else {
+            bail!("No bucket configured for requester-pays reads");
+        };
+        Ok(Self {
+            inner,
+            client: s3_sdk_client(config)?,
+            bucket,
+        })
+    }
+
+    async fn list_page(
+        &self,
+        prefix: Option<&Path>,
+        delimiter: bool,
+        continuation_token: Option<String>,
+    ) -> object_store::Result<aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output> {
+        self.client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .request_payer(RequestPayer::Requester)
+            .set_prefix(key_prefix(prefix))
+            .set_delimiter(delimiter.then(|| DELIMITER.to_string()))
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|e| to_store_error(prefix.unwrap_or(&Path::default()), e))
+    }
+}
+
+fn object_meta(object: &aws_sdk_s3::types::Object) -> ObjectMeta {
+    ObjectMeta {
+        location: Path::from(object.key().unwrap_or_default()),
+        last_modified: from_sdk_time(object.last_modified()),
+        size: object.size() as usize,
+        e_tag: object.e_tag().map(str::to_string),
+    }
+}
+
+impl Debug for RequesterPaysS3Store {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RequesterPaysS3Store({})", self.inner)
+    }
+}
+
+impl Display for RequesterPaysS3Store {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RequesterPaysS3Store({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for RequesterPaysS3Store {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        self.inner.put(location, bytes).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.inner.put_multipart(location).await
+    }
+
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn get(&self, location: &Path) -> object_store::Result<GetResult> {
+        self.get_opts(location, GetOptions::default()).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let range_header = options
+            .range
+            .as_ref()
+            .map(|range| format!("bytes={}-{}", range.start, range.end.saturating_sub(1)));
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(location.to_string())
+            .request_payer(RequestPayer::Requester)
+            .set_if_match(options.if_match)
+            .set_if_none_match(options.if_none_match)
+            .set_if_modified_since(options.if_modified_since.map(to_sdk_time))
+            .set_if_unmodified_since(options.if_unmodified_since.map(to_sdk_time))
+            .set_range(range_header)
+            .send()
+            .await
+            .map_err(|e| match e.into_service_error() {
+                e if e.is_no_such_key() => not_found(location, e),
+                e => to_store_error(location, e),
+            })?;
+        let last_modified = from_sdk_time(output.last_modified());
+        let e_tag = output.e_tag().map(str::to_string);
+        let object_size = output.content_range().and_then(object_size);
+        let bytes = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| to_store_error(location, e))?
+            .into_bytes();
+        let range = options.range.unwrap_or(0..bytes.len());
+        let meta = ObjectMeta {
+            location: location.clone(),
+            last_modified,
+            size: object_size.unwrap_or(bytes.len()),
+            e_tag,
+        };
+        Ok(GetResult {
+            payload: GetResultPayload::Stream(
+                futures::stream::once(async move { Ok(bytes) }).boxed(),
+            ),
+            meta,
+            range,
+        })
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        if range.is_empty() {
+            return Ok(Bytes::new());
+        }
+        let options = GetOptions {
+            range: Some(range),
+            ..Default::default()
+        };
+        self.get_opts(location, options).await?.bytes().await
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        futures::future::try_join_all(
+            ranges
+                .iter()
+                .map(|range| self.get_range(location, range.clone())),
+        )
+        .await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        let output = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(location.to_string())
+            .request_payer(RequestPayer::Requester)
+            .send()
+            .await
+            .map_err(|e| match e.into_service_error() {
+                e if e.is_not_found() => not_found(location, e),
+                e => to_store_error(location, e),
+            })?;
+        Ok(ObjectMeta {
+            location: location.clone(),
+            last_modified: from_sdk_time(output.last_modified()),
+            size: output.content_length() as usize,
+            e_tag: output.e_tag().map(str::to_string),
+        })
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        let prefix = prefix.cloned();
+        // The continuation token of the next page, or `None` once the last page was listed.
+        let pages = futures::stream::try_unfold(Some(None), move |token| {
+            let prefix = prefix.clone();
+            async move {
+                let Some(token) = token else {
+                    return Ok(None);
+                };
+                let output = self.list_page(prefix.as_ref(), false, token).await?;
+                let next = output
+                    .next_continuation_token()
+                    .map(|token| Some(token.to_string()));
+                let objects = output
+                    .contents()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|object| Ok(object_meta(object)))
+                    .collect::<Vec<_>>();
+                Ok::<_, object_store::Error>(Some((futures::stream::iter(objects), next)))
+            }
+        });
+        Ok(pages.try_flatten().boxed())
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let mut result = ListResult {
+            common_prefixes: vec![],
+            objects: vec![],
+        };
+        let mut token = None;
+        loop {
+            let output = self.list_page(prefix, true, token).await?;
+            result.common_prefixes.extend(
+                output
+                    .common_prefixes()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|prefix| prefix.prefix())
+                    .map(Path::from),
+            );
+            result.objects.extend(
+                output
+                    .contents()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(object_meta),
+            );
+            match output.next_continuation_token() {
+                Some(next) => token = Some(next.to_string()),
+                None => return Ok(result),
+            }
+        }
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename_if_not_exists(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::requester_pays::{gcs_headers, key_prefix, object_size};
+    use object_store::path::Path;
+
+    #[test]
+    fn test_requests_metadata() {
+        assert_eq!(object_size("bytes 0-99/4096"), Some(4096));
+        assert_eq!(object_size("bytes 0-99/*"), None);
+        assert_eq!(key_prefix(None), None);
+        assert_eq!(key_prefix(Some(&Path::from(""))), None);
+        assert_eq!(
+            key_prefix(Some(&Path::from("epoch_10"))).as_deref(),
+            Some("epoch_10/")
+        );
+        assert_eq!(
+            gcs_headers("archive-readers").unwrap()["x-goog-user-project"],
+            "archive-readers"
+        );
+    }
+}
@@ -0,0 +1,430 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use object_store::path::Path;
+use object_store::{
+    Error, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore, Result,
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+use tracing::warn;
+
+use crate::object_store::circuit::CircuitOpen;
+use crate::object_store::faulty::InjectedFault;
+
+/// Upper bound on the delay between two attempts, however many retries are configured.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// Number of times a failed request is retried before its error is returned.
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled on every further retry.
+    pub base_delay: Duration,
+    /// Randomization factor between 0 and 1 applied to every delay, so that clients failing at
+    /// the same time don't retry in lockstep.
+    pub jitter: f64,
+    /// Whether to also retry requests for missing objects, for stores that only provide eventual
+    /// consistency after writes.
+    pub retry_not_found: bool,
+    /// Budget shared with the other requests of a pipeline iteration, which every retry draws
+    /// from. Requests are no longer retried once it is exhausted.
+    pub budget: Option<Arc<RetryBudget>>,
+}
+
+impl RetryConfig {
+    /// Whether one more retry is allowed by the budget, if any. Called right before retrying.
+    pub fn spend_budget(&self) -> bool {
+        self.budget
+            .as_ref()
+            .map_or(true, |budget| budget.try_spend())
+    }
+
+    /// Whether a request failing with `error` may succeed if it is sent again.
+    ///
+    /// Requests that reached the store and were rejected by it, e.g. for bad credentials or
+    /// missing objects, fail the same way when retried. Everything else the backends report as
+    /// generic errors: server errors, throttling, timeouts and connection failures, which are
+    /// usually transient. Requests failed fast by an open circuit breaker aren't retried either.
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        match error {
+            Error::Generic { source, .. } => is_transient_error(source.as_ref()),
+            Error::NotFound { .. } => self.retry_not_found,
+            _ => false,
+        }
+    }
+
+    pub fn backoff(&self) -> ExponentialBackoff {
+        ExponentialBackoff {
+            initial_interval: self.base_delay,
+            current_interval: self.base_delay,
+            randomization_factor: self.jitter.clamp(0.0, 1.0),
+            multiplier: 2.0,
+            max_interval: MAX_RETRY_DELAY,
+            max_elapsed_time: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// Retries allowed across all the requests of a pipeline iteration, e.g. the storage and DB
+/// requests of a batch of checkpoints, so that a store or DB failing most requests fails the
+/// batch quickly instead of stretching it over hours of retries.
+///
+/// The budget is exhausted after `max_retries` retries, or once `max_retry_time` has passed since
+/// its first retry. Pipelines [`reset`](Self::reset) it at the start of every iteration.
+#[derive(Debug)]
+pub struct RetryBudget {
+    max_retries: usize,
+    max_retry_time: Duration,
+    retries: AtomicUsize,
+    first_retry: Mutex<Option<Instant>>,
+}
+
+impl RetryBudget {
+    pub fn new(max_retries: usize, max_retry_time: Duration) -> Self {
+        Self {
+            max_retries,
+            max_retry_time,
+            retries: AtomicUsize::new(0),
+            first_retry: Mutex::new(None),
+        }
+    }
+
+    /// Take one retry from the budget, returning false if it is exhausted.
+    pub fn try_spend(&self) -> bool {
+        let first_retry = *self.first_retry.lock().get_or_insert_with(Instant::now);
+        if first_retry.elapsed() > self.max_retry_time {
+            return false;
+        }
+        self.retries
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |retries| {
+                (retries < self.max_retries).then_some(retries + 1)
+            })
+            .is_ok()
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.retries.load(Ordering::Relaxed) >= self.max_retries
+            || self.first_retry.lock().map_or(false, |first_retry| {
+                first_retry.elapsed() > self.max_retry_time
+            })
+    }
+
+    /// Number of retries taken since the last reset.
+    pub fn retries(&self) -> usize {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.retries.store(0, Ordering::Relaxed);
+        *self.first_retry.lock() = None;
+    }
+}
+
+/// Whether the error message carries a 4xx status, other than request timeouts and throttling.
+pub(crate) fn is_client_error(message: &str) -> bool {
+    let Some(start) = message.find("client error (") else {
+        return false;
+    };
+    let status = &message[start + "client error (".len()..];
+    !(status.starts_with("408") || status.starts_with("429"))
+}
+
+/// Whether the source of a generic error is of a request that may succeed once sent again: one
+/// failed with a 5xx status, a request timeout or throttling, that couldn't be sent or timed out,
+/// or a fault injected in its place. Other errors, e.g. of invalid responses, fail the same way
+/// every time, and requests rejected by an open circuit are failed fast rather than retried.
+pub(crate) fn is_transient_error(source: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    if source.is::<CircuitOpen>() {
+        return false;
+    }
+    if source.is::<InjectedFault>() {
+        return true;
+    }
+    let message = source.to_string();
+    if message.contains("client error (") {
+        return !is_client_error(&message);
+    }
+    [
+        "server error (",
+        "error sending request",
+        "error trying to connect",
+        "connection",
+        "timed out",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+/// Object store retrying requests that fail with transient errors, with exponential backoff.
+///
+/// Only whole requests are retried: reading the body of a `get` or writing a multipart upload
+/// fail on the first error.
+#[derive(Debug)]
+pub struct RetryingObjectStore<T: ObjectStore> {
+    inner: T,
+    config: RetryConfig,
+}
+
+impl<T: ObjectStore> RetryingObjectStore<T> {
+    pub fn new(inner: T, config: RetryConfig) -> Self {
+        Self { inner, config }
+    }
+
+    async fn retry<R, F, Fut>(&self, operation: &str, location: &Path, mut request: F) -> Result<R>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        let mut backoff = self.config.backoff();
+        let mut retries = 0;
+        loop {
+            match request().await {
+                Err(e) if retries < self.config.max_retries && self.config.is_retryable(&e) => {
+                    let Some(delay) = backoff.next_backoff() else {
+                        return Err(e);
+                    };
+                    if !self.config.spend_budget() {
+                        warn!(
+                            "Not retrying {operation} of {location}, retry budget exhausted: {e}"
+                        );
+                        return Err(e);
+                    }
+                    retries += 1;
+                    warn!(
+                        "Retrying {operation} of {location} in {delay:?} ({retries}/{}) after error: {e}",
+                        self.config.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<T: ObjectStore> Display for RetryingObjectStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RetryingObjectStore({}, {})",
+            self.config.max_retries, self.inner
+        )
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for RetryingObjectStore<T> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> Result<()> {
+        self.retry("put", location, || self.inner.put(location, bytes.clone()))
+            .await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        self.retry("put_multipart", location, || {
+            self.inner.put_multipart(location)
+        })
+        .await
+    }
+
+    async fn abort_multipart(&self, location: &Path, multipart_id: &MultipartId) -> Result<()> {
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn append(&self, location: &Path) -> Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        self.inner.append(location).await
+    }
+
+    async fn get(&self, location: &Path) -> Result<GetResult> {
+        self.retry("get", location, || self.inner.get(location))
+            .await
+    }
+
+    async fn get_opts(&self, location: &Path, options: GetOptions) -> Result<GetResult> {
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> Result<Bytes> {
+        self.retry("get_range", location, || {
+            self.inner.get_range(location, range.clone())
+        })
+        .await
+    }
+
+    async fn get_ranges(&self, location: &Path, ranges: &[Range<usize>]) -> Result<Vec<Bytes>> {
+        self.retry("get_ranges", location, || {
+            self.inner.get_ranges(location, ranges)
+        })
+        .await
+    }
+
+    async fn head(&self, location: &Path) -> Result<ObjectMeta> {
+        self.retry("head", location, || self.inner.head(location))
+            .await
+    }
+
+    async fn delete(&self, location: &Path) -> Result<()> {
+        self.retry("delete", location, || self.inner.delete(location))
+            .await
+    }
+
+    async fn list(&self, prefix: Option<&Path>) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        let location = prefix.cloned().unwrap_or_default();
+        self.retry("list", &location, || self.inner.list(prefix))
+            .await
+    }
+
+    async fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> Result<BoxStream<'_, Result<ObjectMeta>>> {
+        let location = prefix.cloned().unwrap_or_default();
+        self.retry("list", &location, || {
+            self.inner.list_with_offset(prefix, offset)
+        })
+        .await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> Result<ListResult> {
+        let location = prefix.cloned().unwrap_or_default();
+        self.retry("list", &location, || self.inner.list_with_delimiter(prefix))
+            .await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        self.retry("copy", from, || self.inner.copy(from, to)).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.retry("rename", from, || self.inner.rename(from, to))
+            .await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        self.retry("copy", from, || self.inner.copy_if_not_exists(from, to))
+            .await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> Result<()> {
+        // Not retried, as a rename that copied the object but failed to delete the source would
+        // fail on retry because the destination now exists.
+        self.inner.rename_if_not_exists(from, to).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::retry::{RetryBudget, RetryConfig, RetryingObjectStore};
+    use object_store::local::LocalFileSystem;
+    use object_store::path::Path;
+    use object_store::{Error, ObjectStore};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn config(retry_not_found: bool) -> RetryConfig {
+        RetryConfig {
+            max_retries: 3,
+            base_delay: Duration::from_millis(10),
+            jitter: 0.5,
+            retry_not_found,
+            budget: None,
+        }
+    }
+
+    fn generic(message: &str) -> Error {
+        Error::Generic {
+            store: "S3",
+            source: message.into(),
+        }
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let config = config(false);
+        assert!(config.is_retryable(&generic(
+            "HTTP status server error (503 Service Unavailable) for url (https://example.com)"
+        )));
+        assert!(config.is_retryable(&generic("error sending request: operation timed out")));
+        assert!(config.is_retryable(&generic(
+            "HTTP status client error (429 Too Many Requests) for url (https://example.com)"
+        )));
+        assert!(!config.is_retryable(&generic(
+            "HTTP status client error (403 Forbidden) for url (https://example.com)"
+        )));
+        // Errors of invalid responses aren't sent again.
+        assert!(!config.is_retryable(&generic("Error decoding response body: EOF")));
+
+        let not_found = Error::NotFound {
+            path: "missing".to_string(),
+            source: "not found".into(),
+        };
+        assert!(!config.is_retryable(&not_found));
+        assert!(RetryConfig {
+            retry_not_found: true,
+            ..config
+        }
+        .is_retryable(&not_found));
+    }
+
+    #[tokio::test]
+    async fn test_retry_not_found() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store =
+            RetryingObjectStore::new(LocalFileSystem::new_with_prefix(dir.path())?, config(true));
+        let start = tokio::time::Instant::now();
+        let result = store.get(&Path::from("missing")).await;
+        assert!(matches!(result, Err(Error::NotFound { .. })));
+        // Three retries, after at least 5, 10 and 20ms.
+        assert!(start.elapsed() >= Duration::from_millis(35));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let budget = Arc::new(RetryBudget::new(4, Duration::from_secs(60)));
+        let store = RetryingObjectStore::new(
+            LocalFileSystem::new_with_prefix(dir.path())?,
+            RetryConfig {
+                budget: Some(budget.clone()),
+                ..config(true)
+            },
+        );
+        // The first request takes three retries, the second the last one left.
+        assert!(store.get(&Path::from("missing")).await.is_err());
+        assert_eq!(budget.retries(), 3);
+        assert!(!budget.is_exhausted());
+        let start = tokio::time::Instant::now();
+        assert!(store.get(&Path::from("missing")).await.is_err());
+        assert!(start.elapsed() < Duration::from_millis(35));
+        assert!(budget.is_exhausted());
+
+        budget.reset();
+        assert_eq!(budget.retries(), 0);
+        assert!(budget.try_spend());
+
+        let expired = RetryBudget::new(100, Duration::from_millis(20));
+        assert!(expired.try_spend());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(!expired.try_spend());
+        assert!(expired.is_exhausted());
+        Ok(())
+    }
+}
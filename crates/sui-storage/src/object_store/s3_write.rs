@@ -0,0 +1,389 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Settings of the objects written to S3: server-side encryption, with keys managed by S3
+//! (SSE-S3) or a KMS key (SSE-KMS), and storage class.
+//!
+//! The S3 client of object_store can't set these headers on writes, so [`S3WriteStore`] writes
+//! and copies objects through the AWS SDK and leaves all other requests to the wrapped store. S3
+//! decrypts objects on reads, which need no headers.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+use bytes::Bytes;
+use clap::ValueEnum;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::FutureExt;
+use object_store::path::Path;
+use object_store::{
+    DynObjectStore, GetOptions, GetResult, ListResult, MultipartId, ObjectMeta, ObjectStore,
+};
+use percent_encoding::utf8_percent_encode;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
+
+use crate::object_store::http::STRICT_ENCODE_SET;
+use crate::object_store::multipart::s3_sdk_client;
+use crate::object_store::ObjectStoreConfig;
+
+const STORE_NAME: &str = "S3WriteStore";
+const COPY_SOURCE_ENCODE_SET: percent_encoding::AsciiSet = STRICT_ENCODE_SET.remove(b'/');
+
+/// Server-side encryption of the objects written to S3.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+pub enum AwsSse {
+    /// AES256 with keys managed by S3
+    S3,
+    /// KMS key given by `--aws-kms-key-id`, or the AWS managed key of S3 if unset
+    Kms,
+}
+
+/// Storage class of the objects written to S3. Classes whose objects must be restored before
+/// they can be read, like `GLACIER` and `DEEP_ARCHIVE`, are not supported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[value(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AwsStorageClass {
+    Standard,
+    StandardIa,
+    OnezoneIa,
+    IntelligentTiering,
+    GlacierIr,
+}
+
+impl AwsStorageClass {
+    pub(crate) fn sdk_storage_class(self) -> aws_sdk_s3::types::StorageClass {
+        use aws_sdk_s3::types::StorageClass;
+        match self {
+            AwsStorageClass::Standard => StorageClass::Standard,
+            AwsStorageClass::StandardIa => StorageClass::StandardIa,
+            AwsStorageClass::OnezoneIa => StorageClass::OnezoneIa,
+            AwsStorageClass::IntelligentTiering => StorageClass::IntelligentTiering,
+            AwsStorageClass::GlacierIr => StorageClass::GlacierIr,
+        }
+    }
+}
+
+/// Encryption settings set on every object written to S3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerSideEncryption {
+    pub mode: AwsSse,
+    pub kms_key_id: Option<String>,
+}
+
+impl ServerSideEncryption {
+    pub fn new(mode: AwsSse, kms_key_id: Option<String>) -> Result<Self> {
+        if mode != AwsSse::Kms && kms_key_id.is_some() {
+            bail!("A KMS key id can only be set with SSE-KMS");
+        }
+        Ok(Self { mode, kms_key_id })
+    }
+
+    pub(crate) fn algorithm(&self) -> aws_sdk_s3::types::ServerSideEncryption {
+        match self.mode {
+            AwsSse::S3 => aws_sdk_s3::types::ServerSideEncryption::Aes256,
+            AwsSse::Kms => aws_sdk_s3::types::ServerSideEncryption::AwsKms,
+        }
+    }
+}
+
+/// Settings set on every object written to S3.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct S3WriteOptions {
+    pub sse: Option<ServerSideEncryption>,
+    pub storage_class: Option<AwsStorageClass>,
+}
+
+impl S3WriteOptions {
+    pub fn is_empty(&self) -> bool {
+        self.sse.is_none() && self.storage_class.is_none()
+    }
+}
+
+/// Set the [`S3WriteOptions`] on a `PutObject`, `CreateMultipartUpload` or `CopyObject` request,
+/// whose builders have the same setters but no common trait.
+macro_rules! with_write_options {
+    ($request:expr, $options:expr) => {{
+        let options: &$crate::object_store::s3_write::S3WriteOptions = $options;
+        $request
+            .set_server_side_encryption(options.sse.as_ref().map(|sse| sse.algorithm()))
+            .set_ssekms_key_id(options.sse.as_ref().and_then(|sse| sse.kms_key_id.clone()))
+            .set_storage_class(options.storage_class.map(|class| class.sdk_storage_class()))
+    }};
+}
+pub(crate) use with_write_options;
+
+/// Write `bytes` to `location` with `options`.
+async fn put_with_options(
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    options: &S3WriteOptions,
+    location: &Path,
+    bytes: Bytes,
+) -> object_store::Result<()> {
+    let request = client
+        .put_object()
+        .bucket(bucket)
+        .key(location.to_string())
+        .body(ByteStream::from(bytes));
+    with_write_options!(request, options)
+        .send()
+        .await
+        .map_err(|e| to_store_error(location, e))?;
+    Ok(())
+}
+
+fn to_store_error(
+    location: &Path,
+    error: impl std::error::Error + Send + Sync + 'static,
+) -> object_store::Error {
+    object_store::Error::Generic {
+        store: STORE_NAME,
+        source: format!("{location}: {:#}", anyhow::Error::new(error)).into(),
+    }
+}
+
+/// S3 store setting [`S3WriteOptions`] on all objects it writes, see the
+/// [module documentation](self).
+pub struct S3WriteStore {
+    inner: Arc<DynObjectStore>,
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    options: S3WriteOptions,
+}
+
+impl S3WriteStore {
+    pub fn new(
+        inner: Arc<DynObjectStore>,
+        config: &ObjectStoreConfig,
+        options: S3WriteOptions,
+    ) -> Result<Self> {
+        let Some(bucket) = config.bucket.clone() else {
+            bail!("No bucket configured for S3 write options");
+        };
+        Ok(Self {
+            inner,
+            client: s3_sdk_client(config)?,
+            bucket,
+            options,
+        })
+    }
+}
+
+impl Debug for S3WriteStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "S3WriteStore({})", self.inner)
+    }
+}
+
+impl Display for S3WriteStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "S3WriteStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3WriteStore {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        put_with_options(&self.client, &self.bucket, &self.options, location, bytes).await
+    }
+
+    /// Multipart writes are buffered in memory and written at once on shutdown. Large objects
+    /// are uploaded part by part with [`S3MultipartStore`](crate::object_store::multipart::S3MultipartStore),
+    /// which sets the same options.
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let writer = S3Writer {
+            client: self.client.clone(),
+            bucket: self.bucket.clone(),
+            options: self.options.clone(),
+            location: location.clone(),
+            buffer: vec![],
+            upload: None,
+        };
+        Ok((String::new(), Box::new(writer)))
+    }
+
+    async fn abort_multipart(
+        &self,
+        _location: &Path,
+        _multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        // Nothing is written before the upload completes.
+        Ok(())
+    }
+
+    async fn get(&self, location: &Path) -> object_store::Result<GetResult> {
+        self.inner.get(location).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        self.inner.get_range(location, range).await
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        self.inner.get_ranges(location, ranges).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.inner.list(prefix).await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    /// Copies get the configured options, rather than keeping the ones of the source object.
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        let source = format!("{}/{}", self.bucket, from);
+        let request = self
+            .client
+            .copy_object()
+            .bucket(&self.bucket)
+            .key(to.to_string())
+            .copy_source(utf8_percent_encode(&source, &COPY_SOURCE_ENCODE_SET).to_string());
+        with_write_options!(request, &self.options)
+            .send()
+            .await
+            .map_err(|e| to_store_error(to, e))?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.copy(from, to).await?;
+        self.inner.delete(from).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename_if_not_exists(from, to).await
+    }
+}
+
+/// Buffers a multipart write, to write the object with its options once the writer is shut down.
+struct S3Writer {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    options: S3WriteOptions,
+    location: Path,
+    buffer: Vec<u8>,
+    upload: Option<BoxFuture<'static, io::Result<()>>>,
+}
+
+impl AsyncWrite for S3Writer {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.upload.is_some() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Write after shutdown",
+            )));
+        }
+        self.buffer.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let upload = this.upload.get_or_insert_with(|| {
+            let (client, bucket, options, location) = (
+                this.client.clone(),
+                this.bucket.clone(),
+                this.options.clone(),
+                this.location.clone(),
+            );
+            let bytes = Bytes::from(std::mem::take(&mut this.buffer));
+            async move {
+                put_with_options(&client, &bucket, &options, &location, bytes)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            }
+            .boxed()
+        });
+        upload.poll_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::s3_write::{AwsSse, AwsStorageClass, ServerSideEncryption};
+    use clap::ValueEnum;
+
+    #[test]
+    fn test_kms_key_requires_sse_kms() {
+        assert!(ServerSideEncryption::new(AwsSse::S3, Some("alias/node".to_string())).is_err());
+        let sse = ServerSideEncryption::new(
+            AwsSse::Kms,
+            Some("arn:aws:kms:us-west-2:111122223333:key/node".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            sse.algorithm(),
+            aws_sdk_s3::types::ServerSideEncryption::AwsKms
+        );
+        assert_eq!(
+            ServerSideEncryption::new(AwsSse::S3, None)
+                .unwrap()
+                .algorithm(),
+            aws_sdk_s3::types::ServerSideEncryption::Aes256
+        );
+    }
+
+    #[test]
+    fn test_storage_class_names() {
+        assert_eq!(
+            AwsStorageClass::from_str("GLACIER_IR", false),
+            Ok(AwsStorageClass::GlacierIr)
+        );
+        assert_eq!(
+            AwsStorageClass::from_str("STANDARD_IA", false),
+            Ok(AwsStorageClass::StandardIa)
+        );
+        assert!(AwsStorageClass::from_str("GLACIER", false).is_err());
+        assert!(AwsStorageClass::from_str("DEEP_ARCHIVE", false).is_err());
+    }
+}
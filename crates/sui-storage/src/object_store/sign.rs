@@ -0,0 +1,478 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Presigned URLs, granting access to a single object for a limited time without credentials,
+//! e.g. to hand out download links to snapshots and archive chunks.
+//!
+//! S3 URLs are presigned by the AWS SDK, with the configured credentials. GCS URLs are signed
+//! with the V4 scheme and the private key of the service account, and Azure URLs carry a service
+//! SAS signed with the account key: other GCS and Azure credentials can't sign URLs.
+//!
+//! URLs are signed for the object alone, so writes through a presigned PUT URL don't get the
+//! server-side encryption or storage class configured for the store, only the defaults of the
+//! bucket. PUT requests to Azure must also send the `x-ms-blob-type: BlockBlob` header.
+
+use std::fs;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::presigning::PresigningConfig;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use object_store::path::{Path, DELIMITER};
+use percent_encoding::utf8_percent_encode;
+use ring::{digest, hmac, rand, signature};
+use serde::Deserialize;
+use url::Url;
+
+use crate::object_store::http::STRICT_ENCODE_SET;
+use crate::object_store::multipart::s3_sdk_client;
+use crate::object_store::{ObjectStoreConfig, GCS_HOST};
+
+/// Longest validity of a presigned URL, the limit of S3 and GCS.
+pub const MAX_PRESIGNED_URL_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Endpoint exchanging service account JWTs for OAuth access tokens.
+pub(crate) const GCS_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+/// Validity of the access tokens requested with [`GcsSigner::token_assertion`], the longest
+/// Google grants.
+pub(crate) const GCS_TOKEN_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+const GCS_SIGNING_ALGORITHM: &str = "GOOG4-RSA-SHA256";
+const AZURE_SAS_VERSION: &str = "2020-12-06";
+
+/// Request a presigned URL is valid for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SignMethod {
+    Get,
+    Put,
+}
+
+impl SignMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            SignMethod::Get => "GET",
+            SignMethod::Put => "PUT",
+        }
+    }
+}
+
+#[async_trait]
+pub trait ObjectStoreSignExt: Send + Sync + 'static {
+    /// Return a URL allowing `method` requests on the object at `src`, without credentials,
+    /// until `expires_in` has elapsed
+    async fn signed_url(&self, method: SignMethod, src: &Path, expires_in: Duration)
+        -> Result<Url>;
+}
+
+fn check_expiry(expires_in: Duration) -> Result<()> {
+    if expires_in.is_zero() || expires_in > MAX_PRESIGNED_URL_EXPIRY {
+        bail!(
+            "Presigned URLs expire after 1s to {}s, not {}s",
+            MAX_PRESIGNED_URL_EXPIRY.as_secs(),
+            expires_in.as_secs()
+        );
+    }
+    Ok(())
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, &STRICT_ENCODE_SET).to_string()
+}
+
+/// URL path of the object at `location`, with each segment percent encoded.
+fn encode_path(location: &Path) -> String {
+    location
+        .as_ref()
+        .split(DELIMITER)
+        .map(encode)
+        .collect::<Vec<_>>()
+        .join(DELIMITER)
+}
+
+/// Query string of `params`, sorted by name as signatures expect.
+fn canonical_query(params: &[(&str, String)]) -> String {
+    let mut params = params
+        .iter()
+        .map(|(name, value)| (encode(name), encode(value)))
+        .collect::<Vec<_>>();
+    params.sort();
+    params
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub(crate) struct S3Signer {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3Signer {
+    pub(crate) fn new(config: &ObjectStoreConfig) -> Result<Self> {
+        let Some(bucket) = config.bucket.clone() else {
+            bail!("No bucket configured to presign URLs for");
+        };
+        Ok(Self {
+            client: s3_sdk_client(config)?,
+            bucket,
+        })
+    }
+}
+
+#[async_trait]
+impl ObjectStoreSignExt for S3Signer {
+    async fn signed_url(
+        &self,
+        method: SignMethod,
+        src: &Path,
+        expires_in: Duration,
+    ) -> Result<Url> {
+        check_expiry(expires_in)?;
+        let presigning = PresigningConfig::expires_in(expires_in)?;
+        let request = match method {
+            SignMethod::Get => {
+                self.client
+                    .get_object()
+                    .bucket(&self.bucket)
+                    .key(src.to_string())
+                    .presigned(presigning)
+                    .await?
+            }
+            SignMethod::Put => {
+                self.client
+                    .put_object()
+                    .bucket(&self.bucket)
+                    .key(src.to_string())
+                    .presigned(presigning)
+                    .await?
+            }
+        };
+        Url::parse(&request.uri().to_string()).context("S3 presigned an invalid url")
+    }
+}
+
+/// Fields of a service account key file used for signing.
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+}
+
+pub(crate) struct GcsSigner {
+    bucket: String,
+    client_email: String,
+    key_pair: signature::RsaKeyPair,
+}
+
+impl GcsSigner {
+    /// Signer with the key of the service account key file at `path`.
+    pub(crate) fn new(config: &ObjectStoreConfig, path: &str) -> Result<Self> {
+        let Some(bucket) = config.bucket.clone() else {
+            bail!("No bucket configured to presign URLs for");
+        };
+        let key: ServiceAccountKey = serde_json::from_slice(
+            &fs::read(path).with_context(|| format!("Failed to read service account {path}"))?,
+        )
+        .with_context(|| format!("Invalid service account {path}"))?;
+        let der = rustls_pemfile::pkcs8_private_keys(&mut key.private_key.as_bytes())
+            .with_context(|| format!("Invalid private key in service account {path}"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No private key in service account {path}"))?;
+        let key_pair = signature::RsaKeyPair::from_pkcs8(&der)
+            .map_err(|e| anyhow!("Invalid private key in service account {path}: {e}"))?;
+        Ok(Self {
+            bucket,
+            client_email: key.client_email,
+            key_pair,
+        })
+    }
+
+    /// Canonical request and query string of a V4 signed URL, signed at `now`.
+    fn canonical_request(
+        &self,
+        method: SignMethod,
+        src: &Path,
+        expires_in: Duration,
+        now: DateTime<Utc>,
+    ) -> (String, String) {
+        let scope = format!("{}/auto/storage/goog4_request", now.format("%Y%m%d"));
+        let query = canonical_query(&[
+            ("X-Goog-Algorithm", GCS_SIGNING_ALGORITHM.to_string()),
+            (
+                "X-Goog-Credential",
+                format!("{}/{scope}", self.client_email),
+            ),
+            ("X-Goog-Date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+            ("X-Goog-Expires", expires_in.as_secs().to_string()),
+            ("X-Goog-SignedHeaders", "host".to_string()),
+        ]);
+        let request = format!(
+            "{}\n/{}/{}\n{query}\nhost:{GCS_HOST}\n\nhost\nUNSIGNED-PAYLOAD",
+            method.as_str(),
+            self.bucket,
+            encode_path(src),
+        );
+        (request, query)
+    }
+
+    fn sign(
+        &self,
+        method: SignMethod,
+        src: &Path,
+        expires_in: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Url> {
+        let (request, query) = self.canonical_request(method, src, expires_in, now);
+        let string_to_sign = format!(
+            "{GCS_SIGNING_ALGORITHM}\n{}\n{}/auto/storage/goog4_request\n{}",
+            now.format("%Y%m%dT%H%M%SZ"),
+            now.format("%Y%m%d"),
+            hex::encode(digest::digest(&digest::SHA256, request.as_bytes())),
+        );
+        let signature = self
+            .rsa_sign(string_to_sign.as_bytes())
+            .with_context(|| format!("Failed to sign url of {src}"))?;
+        Url::parse(&format!(
+            "https://{GCS_HOST}/{}/{}?{query}&X-Goog-Signature={}",
+            self.bucket,
+            encode_path(src),
+            hex::encode(signature),
+        ))
+        .context("Failed to build presigned url")
+    }
+
+    /// JWT of the service account, to exchange at [`GCS_TOKEN_URL`] for an access token with the
+    /// given scope, for the requests which can't be presigned.
+    pub(crate) fn token_assertion(&self, scope: &str, now: DateTime<Utc>) -> Result<String> {
+        let encode = |value: serde_json::Value| {
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(value.to_string())
+        };
+        let header = encode(serde_json::json!({ "alg": "RS256", "typ": "JWT" }));
+        let claims = encode(serde_json::json!({
+            "iss": self.client_email,
+            "scope": scope,
+            "aud": GCS_TOKEN_URL,
+            "iat": now.timestamp(),
+            "exp": now.timestamp() + GCS_TOKEN_LIFETIME.as_secs() as i64,
+        }));
+        let message = format!("{header}.{claims}");
+        let signature = self
+            .rsa_sign(message.as_bytes())
+            .context("Failed to sign access token request")?;
+        Ok(format!(
+            "{message}.{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    fn rsa_sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let mut signature = vec![0; self.key_pair.public_modulus_len()];
+        self.key_pair
+            .sign(
+                &signature::RSA_PKCS1_SHA256,
+                &rand::SystemRandom::new(),
+                message,
+                &mut signature,
+            )
+            .map_err(|e| anyhow!("{e}"))?;
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl ObjectStoreSignExt for GcsSigner {
+    async fn signed_url(
+        &self,
+        method: SignMethod,
+        src: &Path,
+        expires_in: Duration,
+    ) -> Result<Url> {
+        check_expiry(expires_in)?;
+        self.sign(method, src, expires_in, Utc::now())
+    }
+}
+
+pub(crate) struct AzureSigner {
+    account: String,
+    container: String,
+    key: hmac::Key,
+}
+
+impl AzureSigner {
+    pub(crate) fn new(config: &ObjectStoreConfig, access_key: &str) -> Result<Self> {
+        let (Some(account), Some(container)) =
+            (config.azure_storage_account.clone(), config.bucket.clone())
+        else {
+            bail!("No account and container configured to presign URLs for");
+        };
+        let key = base64::engine::general_purpose::STANDARD
+            .decode(access_key)
+            .context("Invalid Azure access key")?;
+        Ok(Self {
+            account,
+            container,
+            key: hmac::Key::new(hmac::HMAC_SHA256, &key),
+        })
+    }
+
+    /// Fields of the service SAS of `src`, unsigned, and the string they are signed as.
+    fn string_to_sign(
+        &self,
+        method: SignMethod,
+        src: &Path,
+        expiry: DateTime<Utc>,
+    ) -> (Vec<(&'static str, String)>, String) {
+        let permissions = match method {
+            SignMethod::Get => "r",
+            SignMethod::Put => "cw",
+        };
+        let expiry = expiry.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let resource = format!("/blob/{}/{}/{}", self.account, self.container, src);
+        // Permissions, start, expiry, resource, identifier, IP, protocol, version, resource
+        // type, snapshot time, encryption scope and the five response header overrides.
+        let string_to_sign = format!(
+            "{permissions}\n\n{expiry}\n{resource}\n\n\nhttps\n{AZURE_SAS_VERSION}\nb\n\n\n\n\n\n\n"
+        );
+        let params = vec![
+            ("sv", AZURE_SAS_VERSION.to_string()),
+            ("sr", "b".to_string()),
+            ("sp", permissions.to_string()),
+            ("se", expiry),
+            ("spr", "https".to_string()),
+        ];
+        (params, string_to_sign)
+    }
+
+    fn sign(&self, method: SignMethod, src: &Path, expiry: DateTime<Utc>) -> Result<Url> {
+        let (mut params, string_to_sign) = self.string_to_sign(method, src, expiry);
+        let signature = hmac::sign(&self.key, string_to_sign.as_bytes());
+        params.push((
+            "sig",
+            base64::engine::general_purpose::STANDARD.encode(signature.as_ref()),
+        ));
+        Url::parse(&format!(
+            "https://{}.blob.core.windows.net/{}/{}?{}",
+            self.account,
+            self.container,
+            encode_path(src),
+            canonical_query(&params),
+        ))
+        .context("Failed to build presigned url")
+    }
+}
+
+#[async_trait]
+impl ObjectStoreSignExt for AzureSigner {
+    async fn signed_url(
+        &self,
+        method: SignMethod,
+        src: &Path,
+        expires_in: Duration,
+    ) -> Result<Url> {
+        check_expiry(expires_in)?;
+        self.sign(
+            method,
+            src,
+            Utc::now() + chrono::Duration::from_std(expires_in)?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::sign::{
+        canonical_query, check_expiry, encode_path, AzureSigner, ObjectStoreSignExt, SignMethod,
+        MAX_PRESIGNED_URL_EXPIRY,
+    };
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use bytes::Bytes;
+    use chrono::{TimeZone, Utc};
+    use object_store::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_expiry_and_encoding() {
+        assert!(check_expiry(Duration::from_secs(3600)).is_ok());
+        assert!(check_expiry(MAX_PRESIGNED_URL_EXPIRY).is_ok());
+        assert!(check_expiry(Duration::ZERO).is_err());
+        assert!(check_expiry(MAX_PRESIGNED_URL_EXPIRY + Duration::from_secs(1)).is_err());
+        assert_eq!(
+            encode_path(&Path::from("epoch_10/snapshot 1.ref")),
+            "epoch_10/snapshot%201.ref"
+        );
+        assert_eq!(
+            canonical_query(&[("b", "x/y".to_string()), ("a", "1".to_string())]),
+            "a=1&b=x%2Fy"
+        );
+    }
+
+    #[test]
+    fn test_azure_sas() -> anyhow::Result<()> {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Azure),
+            bucket: Some("archive".to_string()),
+            azure_storage_account: Some("suiarchive".to_string()),
+            ..Default::default()
+        };
+        let signer = AzureSigner::new(&config, "c2VjcmV0")?;
+        let expiry = Utc.with_ymd_and_hms(2023, 11, 8, 12, 0, 0).unwrap();
+        let src = Path::from("epoch_10/1.chk");
+        let (_, string_to_sign) = signer.string_to_sign(SignMethod::Get, &src, expiry);
+        assert_eq!(
+            string_to_sign,
+            "r\n\n2023-11-08T12:00:00Z\n/blob/suiarchive/archive/epoch_10/1.chk\n\n\nhttps\n\
+             2020-12-06\nb\n\n\n\n\n\n\n"
+        );
+        let url = signer.sign(SignMethod::Put, &src, expiry)?;
+        assert_eq!(url.host_str(), Some("suiarchive.blob.core.windows.net"));
+        assert_eq!(url.path(), "/archive/epoch_10/1.chk");
+        let params = url.query_pairs().collect::<Vec<_>>();
+        assert!(params.iter().any(|(k, v)| k == "sp" && v == "cw"));
+        assert!(params.iter().any(|(k, _)| k == "sig"));
+        Ok(())
+    }
+
+    /// Round trip through presigned URLs of a local MinIO server, started with e.g.
+    /// `docker run -p 9000:9000 minio/minio server /data` and with a `sui-storage-test` bucket.
+    /// The endpoint is taken from `MINIO_ENDPOINT` if set.
+    #[tokio::test]
+    #[ignore]
+    async fn test_minio_presigned_urls() -> anyhow::Result<()> {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::S3),
+            bucket: Some("sui-storage-test".to_string()),
+            aws_endpoint: Some(
+                std::env::var("MINIO_ENDPOINT")
+                    .unwrap_or_else(|_| "http://localhost:9000".to_string()),
+            ),
+            aws_access_key_id: Some("minioadmin".to_string()),
+            aws_secret_access_key: Some("minioadmin".to_string()),
+            aws_virtual_hosted_style_request: false,
+            ..Default::default()
+        };
+        let signer = config.make_signer()?;
+        let src = Path::from("presigned/1.chk");
+        let expires_in = Duration::from_secs(60);
+        let client = reqwest::Client::new();
+
+        let bytes = Bytes::from_static(b"checkpoint contents");
+        let url = signer.signed_url(SignMethod::Put, &src, expires_in).await?;
+        client
+            .put(url)
+            .body(bytes.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        let url = signer.signed_url(SignMethod::Get, &src, expires_in).await?;
+        let response = client.get(url.clone()).send().await?.error_for_status()?;
+        assert_eq!(response.bytes().await?, bytes);
+
+        // The signature covers the method.
+        assert!(!client.delete(url).send().await?.status().is_success());
+        Ok(())
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bandwidth limits of the uploads and downloads of a store, so that transfers like snapshot
+//! uploads don't saturate a validator's uplink and slow down consensus.
+//!
+//! Bytes are drawn from token buckets refilled at the configured rate, which hold up to a second
+//! of transfers. Writes and parts of multipart uploads wait for their bytes before they are sent,
+//! while downloads and streamed writes wait after every chunk, once its bytes are drawn.
+
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context as TaskContext, Poll};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use object_store::path::Path;
+use object_store::{
+    GetOptions, GetResult, GetResultPayload, ListResult, MultipartId, ObjectMeta, ObjectStore,
+};
+use parking_lot::Mutex;
+use tokio::io::AsyncWrite;
+use tokio::time::{Instant, Sleep};
+
+use crate::object_store::multipart::{MultipartStore, MultipartUpload, PartId};
+
+/// Token bucket of a byte rate, holding up to a second of bytes. Callers drawing more bytes than
+/// the bucket holds go into debt, and wait until it is paid back.
+#[derive(Debug)]
+pub struct TokenBucket {
+    bytes_per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second = bytes_per_second.max(1) as f64;
+        TokenBucket {
+            bytes_per_second,
+            state: Mutex::new(BucketState {
+                tokens: bytes_per_second,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Bucket of a rate in megabits per second.
+    pub fn from_mbps(mbps: u64) -> Self {
+        Self::new(mbps.saturating_mul(1_000_000) / 8)
+    }
+
+    /// Draw `bytes` from the bucket, and return how long to wait before they are available.
+    fn reserve(&self, bytes: usize) -> Duration {
+        let mut state = self.state.lock();
+        let now = Instant::now();
+        let refill = now.duration_since(state.refilled_at).as_secs_f64() * self.bytes_per_second;
+        state.tokens = (state.tokens + refill).min(self.bytes_per_second) - bytes as f64;
+        state.refilled_at = now;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.bytes_per_second)
+        }
+    }
+
+    /// Draw `bytes` from the bucket, waiting until they are available.
+    pub async fn consume(&self, bytes: usize) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Upload and download limits of a store, `None` if unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthLimits {
+    pub upload: Option<Arc<TokenBucket>>,
+    pub download: Option<Arc<TokenBucket>>,
+}
+
+impl BandwidthLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.upload.is_none() && self.download.is_none()
+    }
+}
+
+/// Store whose uploads and downloads are limited to the rates of its [`BandwidthLimits`].
+/// Requests without a body, like listings and deletes, aren't limited.
+#[derive(Debug)]
+pub struct ThrottledObjectStore<T> {
+    inner: T,
+    limits: BandwidthLimits,
+}
+
+impl<T: ObjectStore> ThrottledObjectStore<T> {
+    pub fn new(inner: T, limits: BandwidthLimits) -> Self {
+        Self { inner, limits }
+    }
+
+    async fn throttle_upload(&self, bytes: usize) {
+        if let Some(upload) = &self.limits.upload {
+            upload.consume(bytes).await;
+        }
+    }
+
+    async fn throttle_download(&self, bytes: usize) {
+        if let Some(download) = &self.limits.download {
+            download.consume(bytes).await;
+        }
+    }
+
+    fn throttle_writer(
+        &self,
+        writer: Box<dyn AsyncWrite + Unpin + Send>,
+    ) -> Box<dyn AsyncWrite + Unpin + Send> {
+        match &self.limits.upload {
+            Some(upload) => Box::new(ThrottledWriter {
+                inner: writer,
+                bucket: upload.clone(),
+                delay: None,
+            }),
+            None => writer,
+        }
+    }
+
+    fn throttle_get(&self, result: GetResult) -> GetResult {
+        let Some(download) = self.limits.download.clone() else {
+            return result;
+        };
+        let meta = result.meta.clone();
+        let range = result.range.clone();
+        let stream = result
+            .into_stream()
+            .then(move |chunk| {
+                let download = download.clone();
+                async move {
+                    if let Ok(bytes) = &chunk {
+                        download.consume(bytes.len()).await;
+                    }
+                    chunk
+                }
+            })
+            .boxed();
+        GetResult {
+            payload: GetResultPayload::Stream(stream),
+            meta,
+            range,
+        }
+    }
+}
+
+impl<T: ObjectStore> Display for ThrottledObjectStore<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ThrottledObjectStore({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl<T: ObjectStore> ObjectStore for ThrottledObjectStore<T> {
+    async fn put(&self, location: &Path, bytes: Bytes) -> object_store::Result<()> {
+        self.throttle_upload(bytes.len()).await;
+        self.inner.put(location, bytes).await
+    }
+
+    async fn put_multipart(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<(MultipartId, Box<dyn AsyncWrite + Unpin + Send>)> {
+        let (multipart_id, writer) = self.inner.put_multipart(location).await?;
+        Ok((multipart_id, self.throttle_writer(writer)))
+    }
+
+    async fn abort_multipart(
+        &self,
+        location: &Path,
+        multipart_id: &MultipartId,
+    ) -> object_store::Result<()> {
+        self.inner.abort_multipart(location, multipart_id).await
+    }
+
+    async fn append(
+        &self,
+        location: &Path,
+    ) -> object_store::Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        let writer = self.inner.append(location).await?;
+        Ok(self.throttle_writer(writer))
+    }
+
+    async fn get(&self, location: &Path) -> object_store::Result<GetResult> {
+        let result = self.inner.get(location).await?;
+        Ok(self.throttle_get(result))
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        let result = self.inner.get_opts(location, options).await?;
+        Ok(self.throttle_get(result))
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        let bytes = self.inner.get_range(location, range).await?;
+        self.throttle_download(bytes.len()).await;
+        Ok(bytes)
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        let result = self.inner.get_ranges(location, ranges).await?;
+        self.throttle_download(result.iter().map(Bytes::len).sum())
+            .await;
+        Ok(result)
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    async fn list(
+        &self,
+        prefix: Option<&Path>,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.inner.list(prefix).await
+    }
+
+    async fn list_with_offset(
+        &self,
+        prefix: Option<&Path>,
+        offset: &Path,
+    ) -> object_store::Result<BoxStream<'_, object_store::Result<ObjectMeta>>> {
+        self.inner.list_with_offset(prefix, offset).await
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        self.inner.list_with_delimiter(prefix).await
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn rename(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+
+    async fn rename_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.rename_if_not_exists(from, to).await
+    }
+}
+
+/// Writer drawing the bytes of every write from a bucket, and holding the next write back until
+/// they are available.
+struct ThrottledWriter {
+    inner: Box<dyn AsyncWrite + Unpin + Send>,
+    bucket: Arc<TokenBucket>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl AsyncWrite for ThrottledWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if let Some(delay) = self.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            self.delay = None;
+        }
+        let written = ready!(Pin::new(&mut self.inner).poll_write(cx, buf))?;
+        let wait = self.bucket.reserve(written);
+        if !wait.is_zero() {
+            self.delay = Some(Box::pin(tokio::time::sleep(wait)));
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Multipart uploads whose parts are limited to the rate of an upload bucket.
+pub struct ThrottledMultipartStore {
+    inner: Arc<dyn MultipartStore>,
+    upload: Arc<TokenBucket>,
+}
+
+impl ThrottledMultipartStore {
+    pub fn new(inner: Arc<dyn MultipartStore>, upload: Arc<TokenBucket>) -> Self {
+        Self { inner, upload }
+    }
+}
+
+#[async_trait]
+impl MultipartStore for ThrottledMultipartStore {
+    async fn create_multipart(&self, location: &Path) -> anyhow::Result<Box<dyn MultipartUpload>> {
+        Ok(Box::new(ThrottledMultipartUpload {
+            inner: self.inner.create_multipart(location).await?,
+            upload: self.upload.clone(),
+        }))
+    }
+}
+
+struct ThrottledMultipartUpload {
+    inner: Box<dyn MultipartUpload>,
+    upload: Arc<TokenBucket>,
+}
+
+#[async_trait]
+impl MultipartUpload for ThrottledMultipartUpload {
+    async fn put_part(&self, part_idx: usize, data: Bytes) -> anyhow::Result<String> {
+        self.upload.consume(data.len()).await;
+        self.inner.put_part(part_idx, data).await
+    }
+
+    async fn complete(&self, parts: Vec<PartId>) -> anyhow::Result<()> {
+        self.inner.complete(parts).await
+    }
+
+    async fn abort(&self) -> anyhow::Result<()> {
+        self.inner.abort().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::throttle::{BandwidthLimits, ThrottledObjectStore, TokenBucket};
+    use crate::object_store::{ObjectStoreGetExt, ObjectStorePutExt};
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn test_throttled_store() -> anyhow::Result<()> {
+        let limits = BandwidthLimits {
+            upload: Some(Arc::new(TokenBucket::new(1000))),
+            download: Some(Arc::new(TokenBucket::new(1000))),
+        };
+        let store: Arc<DynObjectStore> =
+            Arc::new(ThrottledObjectStore::new(InMemory::new(), limits));
+        let path = Path::from("snapshot/1.obj");
+
+        // The buckets start full, with a second of transfers
+        let start = Instant::now();
+        store.put_bytes(&path, Bytes::from(vec![0; 1000])).await?;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // 2000 more bytes take 2 seconds to upload
+        store.put_bytes(&path, Bytes::from(vec![0; 2000])).await?;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+
+        // and 1 more second to download, as the download bucket is full
+        let start = Instant::now();
+        assert_eq!(store.get_bytes(&path).await?.len(), 2000);
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_token_bucket_from_mbps() {
+        // 8 megabits per second are a million bytes
+        let bucket = TokenBucket::from_mbps(8);
+        assert_eq!(bucket.reserve(1_000_000), Duration::ZERO);
+        assert!(bucket.reserve(500_000) <= Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,125 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! TLS settings of the object store clients, for stores behind a private CA like on-prem MinIO
+//! or Ceph RGW.
+//!
+//! The HTTP downloaders trust the CA bundle on top of the built-in roots. The clients of the
+//! S3, GCS and Azure stores can only be given a CA bundle through `SSL_CERT_FILE`, which is set
+//! to it and replaces the system roots for the whole process, and always use at least TLS 1.2.
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use object_store::ClientOptions;
+use reqwest::{Certificate, ClientBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::object_store::ObjectStoreClientConfig;
+
+const SSL_CERT_FILE_ENV_VAR: &str = "SSL_CERT_FILE";
+
+/// Minimum TLS version of connections to the store.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    #[value(name = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    #[value(name = "1.3")]
+    Tls13,
+}
+
+/// Apply the TLS settings to the options of the S3, GCS and Azure clients.
+pub(crate) fn apply_to_client_options(
+    config: &ObjectStoreClientConfig,
+    mut options: ClientOptions,
+) -> Result<ClientOptions> {
+    if config.tls_min_version == Some(TlsVersion::Tls13) {
+        bail!("The S3, GCS and Azure stores can't require TLS 1.3");
+    }
+    if let Some(path) = &config.tls_ca_cert_file {
+        read_ca_certificates(path)?;
+        match std::env::var_os(SSL_CERT_FILE_ENV_VAR) {
+            Some(current) if Path::new(&current) != path.as_path() => bail!(
+                "{SSL_CERT_FILE_ENV_VAR} is set to {}, rather than the CA bundle {}",
+                Path::new(&current).display(),
+                path.display()
+            ),
+            Some(_) => {}
+            None => std::env::set_var(SSL_CERT_FILE_ENV_VAR, path),
+        }
+    }
+    if config.tls_skip_verify {
+        options = options.with_allow_invalid_certificates(true);
+    }
+    Ok(options)
+}
+
+/// Apply the TLS settings to the client of the HTTP downloaders.
+pub(crate) fn apply_to_http_client(
+    config: &ObjectStoreClientConfig,
+    mut builder: ClientBuilder,
+) -> Result<ClientBuilder> {
+    if let Some(path) = &config.tls_ca_cert_file {
+        for der in read_ca_certificates(path)? {
+            builder = builder.add_root_certificate(Certificate::from_der(&der)?);
+        }
+    }
+    if let Some(version) = config.tls_min_version {
+        builder = builder.min_tls_version(match version {
+            TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+        });
+    }
+    if config.tls_skip_verify {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+/// DER encoded certificates of the PEM bundle at `path`.
+fn read_ca_certificates(path: &Path) -> Result<Vec<Vec<u8>>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open CA bundle {}", path.display()))?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+        .with_context(|| format!("Failed to parse CA bundle {}", path.display()))?;
+    if certificates.is_empty() {
+        return Err(anyhow!("No certificates in CA bundle {}", path.display()));
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::tls::{apply_to_client_options, read_ca_certificates, TlsVersion};
+    use crate::object_store::ObjectStoreClientConfig;
+    use object_store::ClientOptions;
+    use std::io::Write;
+
+    #[test]
+    fn test_ca_bundle_must_hold_certificates() -> anyhow::Result<()> {
+        let mut bundle = tempfile::NamedTempFile::new()?;
+        bundle.write_all(b"not a certificate\n")?;
+        assert!(read_ca_certificates(bundle.path()).is_err());
+        assert!(read_ca_certificates(&bundle.path().with_extension("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_store_clients_cannot_require_tls_1_3() {
+        let config = ObjectStoreClientConfig {
+            tls_min_version: Some(TlsVersion::Tls13),
+            ..Default::default()
+        };
+        assert!(apply_to_client_options(&config, ClientOptions::new()).is_err());
+        let config = ObjectStoreClientConfig {
+            tls_min_version: Some(TlsVersion::Tls12),
+            tls_skip_verify: true,
+            ..Default::default()
+        };
+        assert!(apply_to_client_options(&config, ClientOptions::new()).is_ok());
+    }
+}
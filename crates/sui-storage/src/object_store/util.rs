@@ -1,67 +1,157 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::object_store::conditional::{
+    is_precondition_failed, ObjectStoreConditionalPutExt, PreconditionFailed,
+};
+use crate::object_store::error::ObjectStoreResult;
 use crate::object_store::{
-    ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStoreListExt, ObjectStorePutExt,
+    ObjectStoreCopyExt, ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStoreHeadExt,
+    ObjectStoreListExt, ObjectStorePutExt,
 };
 use anyhow::{anyhow, Context, Result};
-use backoff::future::retry;
 use bytes::Bytes;
+use futures::stream::BoxStream;
 use futures::StreamExt;
 use futures::TryStreamExt;
 use indicatif::ProgressBar;
 use object_store::path::Path;
-use object_store::{DynObjectStore, Error, ObjectStore};
+use object_store::{DynObjectStore, Error, ObjectMeta, ObjectStore};
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::num::NonZeroUsize;
-use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sui_types::checkpoint_range::CheckpointRange;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::Instant;
 use tracing::{error, warn};
 use url::Url;
 
 pub const MANIFEST_FILENAME: &str = "MANIFEST";
+/// Size above which files are streamed to object stores with multipart uploads, rather than
+/// written in a single request, unless `--object-store-streaming-threshold-mb` is set.
+pub const DEFAULT_STREAMING_THRESHOLD: usize = 64 * 1024 * 1024;
+const STREAM_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+// Requests aren't retried here: stores made from an `ObjectStoreConfig` retry transient errors
+// with the configured policy, and retrying them again would multiply the attempts of every
+// request.
 
 pub async fn get<S: ObjectStoreGetExt>(store: &S, src: &Path) -> Result<Bytes> {
-    let bytes = retry(backoff::ExponentialBackoff::default(), || async {
-        store.get_bytes(src).await.map_err(|e| {
-            error!("Failed to read file from object store with error: {:?}", &e);
-            backoff::Error::transient(e)
-        })
-    })
-    .await?;
+    let bytes = store.get_bytes(src).await.map_err(|e| {
+        error!("Failed to read file from object store with error: {:?}", &e);
+        e
+    })?;
     Ok(bytes)
 }
 
+/// Open a stream over the bytes at the given path. Errors while reading the stream are returned
+/// to the caller, as the stream can't be resumed.
+pub async fn get_stream<S: ObjectStoreGetExt>(
+    store: &S,
+    src: &Path,
+) -> Result<BoxStream<'static, ObjectStoreResult<Bytes>>> {
+    let stream = store.get_stream(src).await.map_err(|e| {
+        error!("Failed to read file from object store with error: {:?}", &e);
+        e
+    })?;
+    Ok(stream)
+}
+
 pub async fn put<S: ObjectStorePutExt>(store: &S, src: &Path, bytes: Bytes) -> Result<()> {
-    retry(backoff::ExponentialBackoff::default(), || async {
-        if !bytes.is_empty() {
-            store.put_bytes(src, bytes.clone()).await.map_err(|e| {
-                error!("Failed to write file to object store with error: {:?}", &e);
-                backoff::Error::transient(e)
-            })
-        } else {
-            warn!("Not copying empty file: {:?}", src);
-            Ok(())
-        }
-    })
-    .await?;
+    put_with_threshold(store, src, bytes, DEFAULT_STREAMING_THRESHOLD).await
+}
+
+/// Write `bytes` at the given path, with a multipart upload if there are more than
+/// `streaming_threshold` of them.
+pub async fn put_with_threshold<S: ObjectStorePutExt>(
+    store: &S,
+    src: &Path,
+    bytes: Bytes,
+    streaming_threshold: usize,
+) -> Result<()> {
+    let result = if bytes.is_empty() {
+        warn!("Not copying empty file: {:?}", src);
+        Ok(())
+    } else if bytes.len() > streaming_threshold {
+        store.put_stream(src, chunked(bytes)).await
+    } else {
+        store.put_bytes(src, bytes).await
+    };
+    result.map_err(|e| {
+        error!("Failed to write file to object store with error: {:?}", &e);
+        e
+    })?;
     Ok(())
 }
 
+fn chunked(bytes: Bytes) -> BoxStream<'static, ObjectStoreResult<Bytes>> {
+    let chunks: Vec<_> = (0..bytes.len())
+        .step_by(STREAM_CHUNK_SIZE)
+        .map(|start| Ok(bytes.slice(start..bytes.len().min(start + STREAM_CHUNK_SIZE))))
+        .collect();
+    futures::stream::iter(chunks).boxed()
+}
+
 pub async fn copy_file<S: ObjectStoreGetExt, D: ObjectStorePutExt>(
     src: &Path,
     dest: &Path,
     src_store: &S,
     dest_store: &D,
 ) -> Result<()> {
-    let bytes = get(src_store, src).await?;
-    if !bytes.is_empty() {
-        put(dest_store, dest, bytes).await
-    } else {
-        warn!("Not copying empty file: {:?}", src);
-        Ok(())
+    copy_file_with_threshold(
+        src,
+        dest,
+        src_store,
+        dest_store,
+        DEFAULT_STREAMING_THRESHOLD,
+    )
+    .await
+}
+
+/// Copy a file between object stores. Files of up to `streaming_threshold` bytes are read in
+/// memory and written in a single request, larger ones are streamed from one store to the other.
+pub async fn copy_file_with_threshold<S: ObjectStoreGetExt, D: ObjectStorePutExt>(
+    src: &Path,
+    dest: &Path,
+    src_store: &S,
+    dest_store: &D,
+    streaming_threshold: usize,
+) -> Result<()> {
+    // Up to the threshold is read in memory, past it the rest is streamed.
+    let mut stream = get_stream(src_store, src).await?;
+    let mut head = vec![];
+    let mut head_size = 0;
+    while head_size <= streaming_threshold {
+        let Some(chunk) = stream.next().await else {
+            break;
+        };
+        let chunk = chunk.map_err(|e| {
+            error!("Failed to read file from object store with error: {:?}", &e);
+            e
+        })?;
+        head_size += chunk.len();
+        head.push(chunk);
+    }
+    let rest = (head_size > streaming_threshold).then_some(stream);
+    match rest {
+        None => {
+            let bytes = Bytes::from(head.concat());
+            if bytes.is_empty() {
+                warn!("Not copying empty file: {:?}", src);
+                return Ok(());
+            }
+            put_with_threshold(dest_store, dest, bytes, streaming_threshold).await
+        }
+        Some(rest) => {
+            let head = futures::stream::iter(head.into_iter().map(Ok));
+            Ok(dest_store
+                .put_stream(dest, head.chain(rest).boxed())
+                .await?)
+        }
     }
 }
 
@@ -127,20 +217,18 @@ pub async fn delete_files<S: ObjectStoreDeleteExt>(
     store: &S,
     concurrency: NonZeroUsize,
 ) -> Result<Vec<()>> {
-    let results: Vec<Result<()>> = futures::stream::iter(files)
-        .map(|f| {
-            retry(backoff::ExponentialBackoff::default(), || async {
-                store.delete_object(f).await.map_err(|e| {
-                    error!("Failed to delete file on object store with error: {:?}", &e);
-                    backoff::Error::transient(e)
-                })
+    let results: Vec<ObjectStoreResult<()>> = futures::stream::iter(files)
+        .map(|f| async move {
+            store.delete_object(f).await.map_err(|e| {
+                error!("Failed to delete file on object store with error: {:?}", &e);
+                e
             })
         })
         .boxed()
         .buffer_unordered(concurrency.get())
         .collect()
         .await;
-    results.into_iter().collect()
+    Ok(results.into_iter().collect::<ObjectStoreResult<_>>()?)
 }
 
 pub async fn delete_recursively<S: ObjectStoreDeleteExt + ObjectStoreListExt>(
@@ -160,6 +248,211 @@ pub async fn delete_recursively<S: ObjectStoreDeleteExt + ObjectStoreListExt>(
     delete_files(&paths_to_delete, store, concurrency).await
 }
 
+/// Move every object under `src_prefix` to the same location under `dst_prefix` of the same
+/// store, e.g. to promote staged files, without downloading them.
+pub async fn rename_recursively<S: ObjectStoreCopyExt + ObjectStoreListExt>(
+    src_prefix: &Path,
+    dst_prefix: &Path,
+    store: &S,
+    concurrency: NonZeroUsize,
+) -> Result<Vec<()>> {
+    let mut renames = vec![];
+    let mut paths = store.list_objects(Some(src_prefix)).await?;
+    while let Some(res) = paths.next().await {
+        let src = res?.location;
+        let relative = src
+            .prefix_match(src_prefix)
+            .ok_or_else(|| anyhow!("Listed {src} outside of {src_prefix}"))?;
+        let dst: Path = dst_prefix.parts().chain(relative).collect();
+        renames.push((src, dst));
+    }
+    futures::stream::iter(renames)
+        .map(|(src, dst)| async move { store.rename_object(&src, &dst).await })
+        .buffer_unordered(concurrency.get())
+        .try_collect()
+        .await
+        .map_err(Into::into)
+}
+
+/// Return the paths of `files` with no object in the store, e.g. to check that all the files
+/// listed by a manifest were uploaded before downloading any of them.
+pub async fn find_missing_files<S: ObjectStoreHeadExt>(
+    files: &[Path],
+    store: &S,
+    concurrency: NonZeroUsize,
+) -> Result<Vec<Path>> {
+    let exists: Vec<bool> = futures::stream::iter(files)
+        .map(|f| store.exists(f))
+        .buffered(concurrency.get())
+        .try_collect()
+        .await?;
+    Ok(files
+        .iter()
+        .zip(exists)
+        .filter(|(_, exists)| !exists)
+        .map(|(f, _)| f.clone())
+        .collect())
+}
+
+/// Outcome of [`sync_dir_to_store`], [`sync_store_to_dir`] and [`sync_prefix`].
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// Files copied as they were missing or changed at the destination, relative to the prefix
+    pub copied: Vec<Path>,
+    pub unchanged: usize,
+    /// Destination files missing from the source that were deleted, relative to the prefix
+    pub deleted: Vec<Path>,
+}
+
+/// Make `dest_prefix` of `dest_store` a copy of the local directory `dir`, uploading only the
+/// files that are missing or changed, see [`sync_prefix`].
+pub async fn sync_dir_to_store<D>(
+    dir: &std::path::Path,
+    dest_store: &D,
+    dest_prefix: &Path,
+    concurrency: NonZeroUsize,
+    delete: bool,
+) -> Result<SyncSummary>
+where
+    D: ObjectStorePutExt + ObjectStoreListExt + ObjectStoreDeleteExt,
+{
+    let local = local_dir_store(dir)?;
+    sync_prefix(
+        &local,
+        &Path::default(),
+        dest_store,
+        dest_prefix,
+        concurrency,
+        delete,
+    )
+    .await
+}
+
+/// Make the local directory `dir` a copy of `src_prefix` of `src_store`, downloading only the
+/// objects that are missing or changed, see [`sync_prefix`].
+pub async fn sync_store_to_dir<S>(
+    src_store: &S,
+    src_prefix: &Path,
+    dir: &std::path::Path,
+    concurrency: NonZeroUsize,
+    delete: bool,
+) -> Result<SyncSummary>
+where
+    S: ObjectStoreGetExt + ObjectStoreListExt,
+{
+    std::fs::create_dir_all(dir)?;
+    let local = local_dir_store(dir)?;
+    sync_prefix(
+        src_store,
+        src_prefix,
+        &local,
+        &Path::default(),
+        concurrency,
+        delete,
+    )
+    .await
+}
+
+/// Copy every object under `src_prefix` of `src_store` that is missing or changed under
+/// `dest_prefix` of `dest_store`, `concurrency` objects at a time, see [`is_changed`]. With
+/// `delete`, objects under `dest_prefix` with no object under `src_prefix` are deleted.
+pub async fn sync_prefix<S, D>(
+    src_store: &S,
+    src_prefix: &Path,
+    dest_store: &D,
+    dest_prefix: &Path,
+    concurrency: NonZeroUsize,
+    delete: bool,
+) -> Result<SyncSummary>
+where
+    S: ObjectStoreGetExt + ObjectStoreListExt,
+    D: ObjectStorePutExt + ObjectStoreListExt + ObjectStoreDeleteExt,
+{
+    let src_objects = list_relative(src_store, src_prefix).await?;
+    let mut dest_objects = list_relative(dest_store, dest_prefix).await?;
+    let mut summary = SyncSummary::default();
+    let mut copies = vec![];
+    for (relative, src) in src_objects {
+        let dest = dest_objects.remove(&relative);
+        if is_changed(&src, dest.as_ref()) {
+            copies.push((src, join_relative(dest_prefix, &relative), relative));
+        } else {
+            summary.unchanged += 1;
+        }
+    }
+    summary.copied = futures::stream::iter(copies)
+        .map(|(src, dest, relative)| async move {
+            if src.size == 0 {
+                // copy_file skips empty files
+                dest_store.put_bytes(&dest, Bytes::new()).await?;
+            } else {
+                copy_file(&src.location, &dest, src_store, dest_store).await?;
+            }
+            Ok::<_, anyhow::Error>(relative)
+        })
+        .buffer_unordered(concurrency.get())
+        .try_collect()
+        .await?;
+    if delete && !dest_objects.is_empty() {
+        let (relatives, locations): (Vec<_>, Vec<_>) = dest_objects
+            .into_iter()
+            .map(|(relative, meta)| (relative, meta.location))
+            .unzip();
+        dest_store
+            .delete_objects(&locations, concurrency, None)
+            .await
+            .into_result()?;
+        summary.deleted = relatives;
+    }
+    Ok(summary)
+}
+
+/// Whether `src` must be copied over `dest`. Objects with the same ETag are unchanged, others are
+/// changed if their sizes differ or the source was modified after the destination was written.
+/// ETags of local files are derived from their metadata rather than their contents, so local
+/// files and remote objects are only compared by size and modification time.
+pub fn is_changed(src: &ObjectMeta, dest: Option<&ObjectMeta>) -> bool {
+    let Some(dest) = dest else {
+        return true;
+    };
+    if src.e_tag.is_some() && src.e_tag == dest.e_tag {
+        return false;
+    }
+    src.size != dest.size || src.last_modified > dest.last_modified
+}
+
+fn local_dir_store(dir: &std::path::Path) -> Result<Arc<DynObjectStore>> {
+    let store = object_store::local::LocalFileSystem::new_with_prefix(dir)
+        .with_context(|| format!("Failed to open local directory {}", dir.display()))?;
+    Ok(Arc::new(store))
+}
+
+/// Objects under `prefix`, by their location relative to it.
+async fn list_relative<S: ObjectStoreListExt>(
+    store: &S,
+    prefix: &Path,
+) -> Result<BTreeMap<Path, ObjectMeta>> {
+    let list_prefix = (!prefix.as_ref().is_empty()).then_some(prefix);
+    let mut objects = BTreeMap::new();
+    let mut stream = store.list_objects(list_prefix).await?;
+    while let Some(meta) = stream.try_next().await? {
+        let relative: Path = match list_prefix {
+            Some(prefix) => meta
+                .location
+                .prefix_match(prefix)
+                .ok_or_else(|| anyhow!("Listed {} outside of {prefix}", meta.location))?
+                .collect(),
+            None => meta.location.clone(),
+        };
+        objects.insert(relative, meta);
+    }
+    Ok(objects)
+}
+
+fn join_relative(prefix: &Path, relative: &Path) -> Path {
+    prefix.parts().chain(relative.parts()).collect()
+}
+
 pub fn path_to_filesystem(local_dir_path: PathBuf, location: &Path) -> anyhow::Result<PathBuf> {
     // Convert an `object_store::path::Path` to `std::path::PathBuf`
     let path = std::fs::canonicalize(local_dir_path)?;
@@ -198,12 +491,12 @@ pub async fn find_all_dirs_with_epoch_prefix(
     Ok(dirs)
 }
 
-/// This function will find all child directories in the input store which are of the form "epoch_num"
-/// and return a map of epoch number to the directory path
+/// This function will find all files in the input store which are of the form "<start>_<end>.<ext>"
+/// and return the range of checkpoints of each, `end` excluded
 pub async fn find_all_files_with_epoch_prefix(
     store: &Arc<DynObjectStore>,
     prefix: Option<&Path>,
-) -> anyhow::Result<Vec<Range<u64>>> {
+) -> anyhow::Result<Vec<CheckpointRange>> {
     let mut ranges = Vec::new();
     let entries = store.list_with_delimiter(prefix).await?;
     for entry in entries.objects {
@@ -216,9 +509,8 @@ pub async fn find_all_files_with_epoch_prefix(
             .0
             .split_once('_')
             .context("Failed to split dir name")
-            .map(|(start, end)| Range {
-                start: start.parse::<u64>().unwrap(),
-                end: end.parse::<u64>().unwrap(),
+            .map(|(start, end)| {
+                CheckpointRange::new(start.parse::<u64>().unwrap(), end.parse::<u64>().unwrap())
             })?;
 
         ranges.push(checkpoint_seq_range);
@@ -307,15 +599,480 @@ pub async fn write_snapshot_manifest<S: ObjectStoreListExt + ObjectStorePutExt>(
     Ok(())
 }
 
+/// Name of the lock object of a [`StoreLease`], under the leased prefix.
+pub const LEASE_FILENAME: &str = "_LEASE";
+
+/// Content of the lock object of a [`StoreLease`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct LeaseRecord {
+    holder: String,
+    /// Unix time in milliseconds the lease expires at, unless renewed.
+    expires_at_ms: u64,
+}
+
+/// Returned when a [`StoreLease`] can't be acquired, as another holder's lease hasn't expired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaseHeld {
+    pub location: Path,
+    pub holder: String,
+    pub expires_at_ms: u64,
+}
+
+impl std::fmt::Display for LeaseHeld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Lease {} is held by {} until {}ms",
+            self.location, self.holder, self.expires_at_ms
+        )
+    }
+}
+
+impl std::error::Error for LeaseHeld {}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after the epoch")
+        .as_millis() as u64
+}
+
+/// Exclusive lease over a prefix of a bucket, so that a single archive writer or snapshot
+/// uploader writes to it at a time.
+///
+/// The lease is an object at `<prefix>/_LEASE` naming its holder and expiry. It is acquired by
+/// creating that object, or by replacing it once expired, with conditional writes, and renewed
+/// in the background every third of its TTL. Holders must stop writing once the lease is
+/// [lost](Self::lost), when a renewal fails for longer than the TTL. Expiry is compared across
+/// the clocks of the holders, which must be within a fraction of the TTL of each other.
+pub struct StoreLease {
+    location: Path,
+    holder: String,
+    conditional: Arc<dyn ObjectStoreConditionalPutExt>,
+    /// ETag of the lock object as last written by this holder.
+    etag: Arc<tokio::sync::Mutex<String>>,
+    lost: watch::Receiver<bool>,
+    heartbeat: JoinHandle<()>,
+}
+
+impl StoreLease {
+    /// Acquire the lease of `prefix` as `holder`, failing with [`LeaseHeld`] if another holder
+    /// has it.
+    pub async fn acquire(
+        store: Arc<DynObjectStore>,
+        conditional: Arc<dyn ObjectStoreConditionalPutExt>,
+        prefix: &Path,
+        holder: String,
+        ttl: Duration,
+    ) -> Result<Self> {
+        let location = prefix.child(LEASE_FILENAME);
+        let record = LeaseRecord {
+            holder: holder.clone(),
+            expires_at_ms: now_ms() + ttl.as_millis() as u64,
+        };
+        let bytes = Bytes::from(serde_json::to_vec(&record)?);
+        match conditional
+            .put_bytes_if_not_exists(&location, bytes.clone())
+            .await
+        {
+            Ok(()) => {}
+            Err(e) if is_precondition_failed(&e) => {
+                let (current, etag) = read_lease(&store, &location).await?;
+                if current.holder != holder && current.expires_at_ms > now_ms() {
+                    return Err(LeaseHeld {
+                        location,
+                        holder: current.holder,
+                        expires_at_ms: current.expires_at_ms,
+                    }
+                    .into());
+                }
+                // Taken over from an expired holder, or from a previous run of this one. Fails
+                // if another holder took it over first.
+                conditional
+                    .put_bytes_if_match(&location, bytes, &etag)
+                    .await?;
+            }
+            Err(e) => return Err(e),
+        }
+        let etag = Arc::new(tokio::sync::Mutex::new(
+            written_etag(&store, &location, &holder).await?,
+        ));
+        let (lost_sender, lost) = watch::channel(false);
+        let heartbeat = tokio::spawn(renew_lease(
+            store,
+            conditional.clone(),
+            location.clone(),
+            holder.clone(),
+            ttl,
+            etag.clone(),
+            lost_sender,
+        ));
+        Ok(Self {
+            location,
+            holder,
+            conditional,
+            etag,
+            lost,
+            heartbeat,
+        })
+    }
+
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// Whether the lease is still held.
+    pub fn is_held(&self) -> bool {
+        !*self.lost.borrow()
+    }
+
+    /// Wait until the lease is lost.
+    pub async fn lost(&self) {
+        let mut lost = self.lost.clone();
+        // An error means the heartbeat task is gone, which only happens once the lease is lost.
+        let _ = lost.wait_for(|lost| *lost).await;
+    }
+
+    /// Stop renewing the lease and let other holders acquire it right away.
+    pub async fn release(self) -> Result<()> {
+        self.heartbeat.abort();
+        if !self.is_held() {
+            return Ok(());
+        }
+        let record = LeaseRecord {
+            holder: self.holder.clone(),
+            expires_at_ms: 0,
+        };
+        let etag = self.etag.lock().await.clone();
+        self.conditional
+            .put_bytes_if_match(
+                &self.location,
+                Bytes::from(serde_json::to_vec(&record)?),
+                &etag,
+            )
+            .await
+    }
+}
+
+impl Drop for StoreLease {
+    fn drop(&mut self) {
+        // The lease then expires after its TTL.
+        self.heartbeat.abort();
+    }
+}
+
+/// Lease in the lock object at `location`, and its ETag.
+async fn read_lease(store: &Arc<DynObjectStore>, location: &Path) -> Result<(LeaseRecord, String)> {
+    let result = store.get(location).await?;
+    let etag = result
+        .meta
+        .e_tag
+        .clone()
+        .ok_or_else(|| anyhow!("Store returned no ETag for lease {location}"))?;
+    let record = serde_json::from_slice(&result.bytes().await?)
+        .with_context(|| format!("Invalid lease {location}"))?;
+    Ok((record, etag))
+}
+
+/// ETag of the lock object just written by `holder`, checking it wasn't taken over since.
+async fn written_etag(
+    store: &Arc<DynObjectStore>,
+    location: &Path,
+    holder: &str,
+) -> Result<String> {
+    let (record, etag) = read_lease(store, location).await?;
+    if record.holder != holder {
+        return Err(LeaseHeld {
+            location: location.clone(),
+            holder: record.holder,
+            expires_at_ms: record.expires_at_ms,
+        }
+        .into());
+    }
+    Ok(etag)
+}
+
+async fn renew_lease(
+    store: Arc<DynObjectStore>,
+    conditional: Arc<dyn ObjectStoreConditionalPutExt>,
+    location: Path,
+    holder: String,
+    ttl: Duration,
+    etag: Arc<tokio::sync::Mutex<String>>,
+    lost: watch::Sender<bool>,
+) {
+    let mut expires_at_ms = now_ms() + ttl.as_millis() as u64;
+    loop {
+        tokio::time::sleep(ttl / 3).await;
+        let record = LeaseRecord {
+            holder: holder.clone(),
+            expires_at_ms: now_ms() + ttl.as_millis() as u64,
+        };
+        let mut current_etag = etag.lock().await;
+        let renewed = async {
+            conditional
+                .put_bytes_if_match(
+                    &location,
+                    Bytes::from(serde_json::to_vec(&record)?),
+                    &current_etag,
+                )
+                .await?;
+            written_etag(&store, &location, &holder).await
+        }
+        .await;
+        match renewed {
+            Ok(new_etag) => {
+                *current_etag = new_etag;
+                expires_at_ms = record.expires_at_ms;
+            }
+            Err(e) if is_precondition_failed(&e) || e.downcast_ref::<LeaseHeld>().is_some() => {
+                error!("Lost lease {location} of {holder}: {e}");
+                break;
+            }
+            Err(e) if now_ms() >= expires_at_ms => {
+                error!("Lost lease {location} of {holder}, failed to renew it before expiry: {e}");
+                break;
+            }
+            Err(e) => warn!("Failed to renew lease {location} of {holder}: {e}"),
+        }
+    }
+    let _ = lost.send(true);
+}
+
+/// Name of the manifest a [`TransactionalWriter`] commits, under the prefix it writes to.
+pub const COMMIT_MANIFEST_FILENAME: &str = "_COMMIT";
+/// Directory under the prefix of a [`TransactionalWriter`] its files are staged in.
+pub const STAGING_DIRNAME: &str = "_staging";
+
+/// Files committed by a [`TransactionalWriter`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitManifest {
+    /// Staging directory of the transaction, under `<prefix>/_staging/`.
+    pub transaction: String,
+    /// Paths of the files, relative to the prefix, and their sizes.
+    pub files: BTreeMap<String, usize>,
+}
+
+impl CommitManifest {
+    /// Location in the store of the committed file at `path`, relative to `prefix`.
+    pub fn location(&self, prefix: &Path, path: &Path) -> Path {
+        staging_dir(prefix, &self.transaction)
+            .parts()
+            .chain(path.parts())
+            .collect()
+    }
+}
+
+fn staging_dir(prefix: &Path, transaction: &str) -> Path {
+    prefix.child(STAGING_DIRNAME).child(transaction)
+}
+
+/// The manifest last committed to `prefix`, and its ETag, if any.
+pub async fn read_commit_manifest(
+    store: &Arc<DynObjectStore>,
+    prefix: &Path,
+) -> Result<Option<(CommitManifest, String)>> {
+    let location = prefix.child(COMMIT_MANIFEST_FILENAME);
+    let result = match store.get(&location).await {
+        Ok(result) => result,
+        Err(Error::NotFound { .. }) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let etag = result
+        .meta
+        .e_tag
+        .clone()
+        .ok_or_else(|| anyhow!("Store returned no ETag for {location}"))?;
+    let manifest = serde_json::from_slice(&result.bytes().await?)
+        .with_context(|| format!("Invalid commit manifest {location}"))?;
+    Ok(Some((manifest, etag)))
+}
+
+/// Writes a batch of files under a prefix all at once, so that readers never see some of them
+/// without the others, e.g. a partially uploaded snapshot or archive chunk.
+///
+/// Files are written to a staging directory of the transaction, under `<prefix>/_staging/`, and
+/// committed by writing the manifest listing them at `<prefix>/_COMMIT`. Readers find the files
+/// through the manifest, see [`read_commit_manifest`], so they read the ones of the last commit
+/// until the next one replaces the manifest. Manifests are written with conditional puts: a
+/// commit fails with [`PreconditionFailed`](crate::object_store::conditional::PreconditionFailed)
+/// if another writer committed to the prefix since this one began.
+///
+/// The staged files of writers that crash before committing or aborting are left behind, and
+/// deleted by [`delete_stale_transactions`].
+pub struct TransactionalWriter {
+    store: Arc<DynObjectStore>,
+    conditional: Arc<dyn ObjectStoreConditionalPutExt>,
+    prefix: Path,
+    transaction: String,
+    /// ETag of the manifest when the transaction began, `None` if there was none.
+    base_etag: Option<String>,
+    files: BTreeMap<String, usize>,
+}
+
+impl TransactionalWriter {
+    /// Begin a transaction writing to `prefix`.
+    pub async fn begin(
+        store: Arc<DynObjectStore>,
+        conditional: Arc<dyn ObjectStoreConditionalPutExt>,
+        prefix: Path,
+    ) -> Result<Self> {
+        let base_etag = read_commit_manifest(&store, &prefix)
+            .await?
+            .map(|(_, etag)| etag);
+        Ok(Self {
+            store,
+            conditional,
+            prefix,
+            transaction: format!("{}-{:016x}", now_ms(), rand::random::<u64>()),
+            base_etag,
+            files: BTreeMap::new(),
+        })
+    }
+
+    pub fn transaction(&self) -> &str {
+        &self.transaction
+    }
+
+    /// Stage `bytes` as the file at `path`, relative to the prefix. Empty files are written too,
+    /// as every file of the manifest must be readable.
+    pub async fn put(&mut self, path: &Path, bytes: Bytes) -> Result<()> {
+        let len = bytes.len();
+        let location = self.staged_location(path);
+        self.store.put_bytes(&location, bytes).await.map_err(|e| {
+            error!("Failed to write file to object store with error: {:?}", &e);
+            e
+        })?;
+        self.files.insert(path.to_string(), len);
+        Ok(())
+    }
+
+    /// Make the staged files visible to readers, replacing the ones of the previous commit,
+    /// whose manifest is returned so that its files can be deleted with
+    /// [`delete_committed_files`] once readers are done with them.
+    pub async fn commit(self) -> Result<Option<CommitManifest>> {
+        let previous = read_commit_manifest(&self.store, &self.prefix).await?;
+        let manifest = CommitManifest {
+            transaction: self.transaction.clone(),
+            files: self.files.clone(),
+        };
+        let location = self.prefix.child(COMMIT_MANIFEST_FILENAME);
+        let bytes = Bytes::from(serde_json::to_vec(&manifest)?);
+        let committed = match (&self.base_etag, &previous) {
+            (None, None) => {
+                self.conditional
+                    .put_bytes_if_not_exists(&location, bytes)
+                    .await
+            }
+            (Some(etag), Some((_, current))) if etag == current => {
+                self.conditional
+                    .put_bytes_if_match(&location, bytes, etag)
+                    .await
+            }
+            // Another writer committed since this transaction began.
+            _ => Err(PreconditionFailed { location }.into()),
+        };
+        if let Err(e) = committed {
+            if let Err(abort_error) = self.abort().await {
+                warn!("Failed to delete the staged files of a failed commit: {abort_error}");
+            }
+            return Err(e);
+        }
+        Ok(previous.map(|(manifest, _)| manifest))
+    }
+
+    /// Delete the staged files, without committing them.
+    pub async fn abort(self) -> Result<()> {
+        delete_recursively(
+            &staging_dir(&self.prefix, &self.transaction),
+            &self.store,
+            NonZeroUsize::new(20).unwrap(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn staged_location(&self, path: &Path) -> Path {
+        staging_dir(&self.prefix, &self.transaction)
+            .parts()
+            .chain(path.parts())
+            .collect()
+    }
+}
+
+/// Delete the files of a manifest replaced by a later commit to `prefix`.
+pub async fn delete_committed_files(
+    store: &Arc<DynObjectStore>,
+    prefix: &Path,
+    manifest: &CommitManifest,
+    concurrency: NonZeroUsize,
+) -> Result<()> {
+    delete_recursively(
+        &staging_dir(prefix, &manifest.transaction),
+        store,
+        concurrency,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Delete the staging directories under `prefix` of transactions that began at least `ttl` ago
+/// and were never committed, e.g. of writers that crashed, and return their names. `ttl` must be
+/// longer than any transaction takes to commit, or the files of an ongoing one are deleted.
+pub async fn delete_stale_transactions(
+    store: &Arc<DynObjectStore>,
+    prefix: &Path,
+    ttl: Duration,
+    concurrency: NonZeroUsize,
+) -> Result<Vec<String>> {
+    let committed = read_commit_manifest(store, prefix)
+        .await?
+        .map(|(manifest, _)| manifest.transaction);
+    let staging = store
+        .list_with_delimiter(Some(&prefix.child(STAGING_DIRNAME)))
+        .await?;
+    let now = now_ms();
+    let mut deleted = vec![];
+    for dir in staging.common_prefixes {
+        let Some(transaction) = dir.filename().map(str::to_string) else {
+            continue;
+        };
+        // Transactions are named after the time they began, see `TransactionalWriter::begin`.
+        let Some(began) = transaction
+            .split('-')
+            .next()
+            .and_then(|ms| ms.parse::<u64>().ok())
+        else {
+            warn!("Skipping unknown staging directory: {dir}");
+            continue;
+        };
+        if committed.as_ref() == Some(&transaction)
+            || u128::from(now.saturating_sub(began)) < ttl.as_millis()
+        {
+            continue;
+        }
+        delete_recursively(&dir, store, concurrency).await?;
+        deleted.push(transaction);
+    }
+    Ok(deleted)
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::object_store::conditional::is_precondition_failed;
     use crate::object_store::util::{
-        copy_recursively, delete_recursively, write_snapshot_manifest, MANIFEST_FILENAME,
+        copy_file_with_threshold, copy_recursively, delete_committed_files, delete_recursively,
+        delete_stale_transactions, read_commit_manifest, rename_recursively, sync_dir_to_store,
+        sync_store_to_dir, write_snapshot_manifest, LeaseHeld, StoreLease, TransactionalWriter,
+        MANIFEST_FILENAME,
     };
     use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+    use bytes::Bytes;
     use object_store::path::Path;
+    use object_store::ObjectStore;
     use std::fs;
     use std::num::NonZeroUsize;
+    use std::time::Duration;
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -372,6 +1129,36 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    pub async fn test_copy_file_streaming() -> anyhow::Result<()> {
+        let input = TempDir::new()?;
+        let content: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+        fs::write(input.path().join("small"), b"Lorem ipsum")?;
+        fs::write(input.path().join("large"), &content)?;
+
+        let output = TempDir::new()?;
+        let input_store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(input.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make()?;
+        let output_store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(output.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make()?;
+
+        for file in ["small", "large"] {
+            let path = Path::from(file);
+            copy_file_with_threshold(&path, &path, &input_store, &output_store, 1024).await?;
+        }
+        assert_eq!(fs::read(output.path().join("small"))?, b"Lorem ipsum");
+        assert_eq!(fs::read(output.path().join("large"))?, content);
+        Ok(())
+    }
+
     #[tokio::test]
     pub async fn test_write_snapshot_manifest() -> anyhow::Result<()> {
         let input = TempDir::new()?;
@@ -409,6 +1196,40 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    pub async fn test_rename_recursively() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let staging = dir.path().join("staging").join("epoch_5");
+        fs::create_dir_all(staging.join("grand_child"))?;
+        fs::write(staging.join("file1"), b"Lorem ipsum")?;
+        fs::write(staging.join("grand_child").join("file2"), b"dolor sit amet")?;
+
+        let store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make()?;
+
+        rename_recursively(
+            &Path::from("staging/epoch_5"),
+            &Path::from("epoch_5"),
+            &store,
+            NonZeroUsize::new(2).unwrap(),
+        )
+        .await?;
+
+        let promoted = dir.path().join("epoch_5");
+        assert_eq!(fs::read(promoted.join("file1"))?, b"Lorem ipsum");
+        assert_eq!(
+            fs::read(promoted.join("grand_child").join("file2"))?,
+            b"dolor sit amet"
+        );
+        assert!(!staging.join("file1").exists());
+        assert!(!staging.join("grand_child").join("file2").exists());
+        Ok(())
+    }
+
     #[tokio::test]
     pub async fn test_delete_recursively() -> anyhow::Result<()> {
         let input = TempDir::new()?;
@@ -444,4 +1265,200 @@ mod tests {
             .exists());
         Ok(())
     }
+
+    #[tokio::test]
+    pub async fn test_sync_dir() -> anyhow::Result<()> {
+        let input = TempDir::new()?;
+        let input_path = input.path();
+        fs::create_dir(input_path.join("child"))?;
+        fs::write(input_path.join("file1"), b"Lorem ipsum")?;
+        fs::write(input_path.join("child").join("file2"), b"dolor sit amet")?;
+        fs::write(input_path.join("empty"), b"")?;
+        let remote = TempDir::new()?;
+        let remote_store = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(remote.path().to_path_buf()),
+            ..Default::default()
+        }
+        .make()?;
+        let prefix = Path::from("backup");
+        let concurrency = NonZeroUsize::new(2).unwrap();
+
+        let summary =
+            sync_dir_to_store(input_path, &remote_store, &prefix, concurrency, false).await?;
+        assert_eq!(summary.copied.len(), 3);
+        assert_eq!(
+            fs::read(remote.path().join("backup").join("child").join("file2"))?,
+            b"dolor sit amet"
+        );
+        assert!(remote.path().join("backup").join("empty").exists());
+
+        let summary =
+            sync_dir_to_store(input_path, &remote_store, &prefix, concurrency, false).await?;
+        assert!(summary.copied.is_empty());
+        assert_eq!(summary.unchanged, 3);
+
+        fs::remove_file(input_path.join("file1"))?;
+        fs::write(input_path.join("file3"), b"consectetur")?;
+        let summary =
+            sync_dir_to_store(input_path, &remote_store, &prefix, concurrency, true).await?;
+        assert_eq!(summary.copied, vec![Path::from("file3")]);
+        assert_eq!(summary.deleted, vec![Path::from("file1")]);
+        assert!(!remote.path().join("backup").join("file1").exists());
+
+        let output = TempDir::new()?;
+        fs::write(output.path().join("extraneous"), b"")?;
+        let summary =
+            sync_store_to_dir(&remote_store, &prefix, output.path(), concurrency, true).await?;
+        assert_eq!(summary.copied.len(), 3);
+        assert_eq!(summary.deleted, vec![Path::from("extraneous")]);
+        assert_eq!(fs::read(output.path().join("file3"))?, b"consectetur");
+        assert_eq!(
+            fs::read(output.path().join("child").join("file2"))?,
+            b"dolor sit amet"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_store_lease() -> anyhow::Result<()> {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Memory),
+            bucket: Some("test_store_lease".to_string()),
+            ..Default::default()
+        };
+        let store = config.make()?;
+        let conditional = config.make_conditional_put()?;
+        let prefix = Path::from("archive");
+        let ttl = Duration::from_millis(300);
+
+        let lease = StoreLease::acquire(
+            store.clone(),
+            conditional.clone(),
+            &prefix,
+            "writer-1".to_string(),
+            ttl,
+        )
+        .await?;
+        // Renewed past its TTL.
+        tokio::time::sleep(2 * ttl).await;
+        assert!(lease.is_held());
+        let error = StoreLease::acquire(
+            store.clone(),
+            conditional.clone(),
+            &prefix,
+            "writer-2".to_string(),
+            ttl,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.downcast_ref::<LeaseHeld>().unwrap().holder,
+            "writer-1"
+        );
+
+        lease.release().await?;
+        let lease = StoreLease::acquire(
+            store.clone(),
+            conditional.clone(),
+            &prefix,
+            "writer-2".to_string(),
+            ttl,
+        )
+        .await?;
+
+        // Taken over once expired, without renewals.
+        drop(lease);
+        tokio::time::sleep(2 * ttl).await;
+        let lease =
+            StoreLease::acquire(store, conditional, &prefix, "writer-3".to_string(), ttl).await?;
+        assert_eq!(lease.holder(), "writer-3");
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_transactional_writer() -> anyhow::Result<()> {
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Memory),
+            bucket: Some("test_transactional_writer".to_string()),
+            ..Default::default()
+        };
+        let store = config.make()?;
+        let conditional = config.make_conditional_put()?;
+        let prefix = Path::from("epoch_1");
+        let file = Path::from("store/000001.sst");
+
+        let mut writer =
+            TransactionalWriter::begin(store.clone(), conditional.clone(), prefix.clone()).await?;
+        writer.put(&file, Bytes::from_static(b"sst")).await?;
+        // Staged files aren't visible until committed.
+        assert!(read_commit_manifest(&store, &prefix).await?.is_none());
+        assert!(writer.commit().await?.is_none());
+        let (first, _) = read_commit_manifest(&store, &prefix).await?.unwrap();
+        assert_eq!(
+            store
+                .get(&first.location(&prefix, &file))
+                .await?
+                .bytes()
+                .await?,
+            Bytes::from_static(b"sst")
+        );
+
+        // A writer that began before another one committed can't commit.
+        let mut stale =
+            TransactionalWriter::begin(store.clone(), conditional.clone(), prefix.clone()).await?;
+        let mut writer =
+            TransactionalWriter::begin(store.clone(), conditional.clone(), prefix.clone()).await?;
+        writer.put(&file, Bytes::from_static(b"new sst")).await?;
+        stale.put(&file, Bytes::from_static(b"stale sst")).await?;
+        let replaced = writer.commit().await?.unwrap();
+        assert_eq!(replaced, first);
+        let staged = stale.staged_location(&file);
+        assert!(is_precondition_failed(&stale.commit().await.unwrap_err()));
+        // Its staged files are deleted.
+        assert!(store.head(&staged).await.is_err());
+
+        let (second, _) = read_commit_manifest(&store, &prefix).await?.unwrap();
+        assert_eq!(
+            store
+                .get(&second.location(&prefix, &file))
+                .await?
+                .bytes()
+                .await?,
+            Bytes::from_static(b"new sst")
+        );
+        delete_committed_files(&store, &prefix, &replaced, NonZeroUsize::new(1).unwrap()).await?;
+        assert!(store.head(&first.location(&prefix, &file)).await.is_err());
+
+        // Empty files are committed like any other, and the staged files of a writer that never
+        // commits are deleted once stale, unlike the committed ones.
+        let empty = Path::from("store/LOCK");
+        let mut writer =
+            TransactionalWriter::begin(store.clone(), conditional.clone(), prefix.clone()).await?;
+        writer.put(&empty, Bytes::new()).await?;
+        writer.commit().await?;
+        let (third, _) = read_commit_manifest(&store, &prefix).await?.unwrap();
+        assert!(store
+            .get(&third.location(&prefix, &empty))
+            .await?
+            .bytes()
+            .await?
+            .is_empty());
+        let mut crashed =
+            TransactionalWriter::begin(store.clone(), conditional.clone(), prefix.clone()).await?;
+        crashed.put(&file, Bytes::from_static(b"sst")).await?;
+        let concurrency = NonZeroUsize::new(1).unwrap();
+        let ttl = Duration::from_secs(3600);
+        assert!(delete_stale_transactions(&store, &prefix, ttl, concurrency)
+            .await?
+            .is_empty());
+        let deleted =
+            delete_stale_transactions(&store, &prefix, Duration::ZERO, concurrency).await?;
+        assert!(deleted.contains(&crashed.transaction().to_string()));
+        assert!(!deleted.contains(&third.transaction));
+        assert!(store.head(&crashed.staged_location(&file)).await.is_err());
+        assert!(store.head(&third.location(&prefix, &empty)).await.is_ok());
+        Ok(())
+    }
 }
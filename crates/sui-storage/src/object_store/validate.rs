@@ -0,0 +1,229 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Validation of an object store config before a node starts with it: a probe object is written,
+//! read back, listed and deleted, and the first failing step is reported with the likely cause,
+//! e.g. bad credentials or a missing bucket, rather than failing at the first upload hours later.
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use futures::TryStreamExt;
+use object_store::path::Path;
+use object_store::DynObjectStore;
+
+use crate::object_store::error::ObjectStoreError;
+use crate::object_store::{
+    ObjectStoreDeleteExt, ObjectStoreGetExt, ObjectStoreListExt, ObjectStorePutExt,
+};
+
+/// Prefix of the probe objects written by validations.
+pub const VALIDATION_PROBE_PREFIX: &str = "_validation";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ValidationStep {
+    /// Building a store from the config
+    Configure,
+    Write,
+    Read,
+    List,
+    Delete,
+}
+
+/// Likely cause of a failed step, from the status and message of the store's response.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ValidationFailure {
+    InvalidConfig,
+    /// Credentials missing, malformed or rejected
+    Unauthenticated,
+    /// Credentials accepted, but not allowed this operation
+    PermissionDenied,
+    BucketNotFound,
+    /// The probe object read back or listed isn't the one written
+    Inconsistent,
+    Other,
+}
+
+impl ValidationFailure {
+    fn classify(message: &str) -> Self {
+        const BUCKET_NOT_FOUND: [&str; 4] = [
+            "NoSuchBucket",
+            "ContainerNotFound",
+            "The specified bucket does not exist",
+            "The specified container does not exist",
+        ];
+        if BUCKET_NOT_FOUND.iter().any(|m| message.contains(m)) {
+            ValidationFailure::BucketNotFound
+        } else if message.contains("(401 ") || message.contains("InvalidAccessKeyId") {
+            ValidationFailure::Unauthenticated
+        } else if message.contains("(403 ") || message.contains("AccessDenied") {
+            ValidationFailure::PermissionDenied
+        } else {
+            ValidationFailure::Other
+        }
+    }
+}
+
+impl Display for ValidationFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ValidationFailure::InvalidConfig => "invalid config",
+            ValidationFailure::Unauthenticated => "authentication failed",
+            ValidationFailure::PermissionDenied => "permission denied",
+            ValidationFailure::BucketNotFound => "bucket not found",
+            ValidationFailure::Inconsistent => "inconsistent probe object",
+            ValidationFailure::Other => "request failed",
+        })
+    }
+}
+
+/// Returned by [`ObjectStoreConfig::validate`](crate::object_store::ObjectStoreConfig::validate)
+/// for the first step that failed.
+#[derive(Debug)]
+pub struct ValidationError {
+    pub step: ValidationStep,
+    pub failure: ValidationFailure,
+    pub error: anyhow::Error,
+}
+
+impl ValidationError {
+    pub(crate) fn new(step: ValidationStep, error: anyhow::Error) -> Self {
+        let failure = match step {
+            ValidationStep::Configure => ValidationFailure::InvalidConfig,
+            _ => ValidationFailure::classify(&format!("{error:#}")),
+        };
+        Self {
+            step,
+            failure,
+            error,
+        }
+    }
+
+    fn inconsistent(step: ValidationStep, error: anyhow::Error) -> Self {
+        Self {
+            step,
+            failure: ValidationFailure::Inconsistent,
+            error,
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} step failed, {}: {:#}",
+            self.step, self.failure, self.error
+        )
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Write, read, list and delete a probe object under [`VALIDATION_PROBE_PREFIX`] of `store`.
+pub(crate) async fn validate_store(store: &Arc<DynObjectStore>) -> Result<(), ValidationError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let prefix = Path::from(VALIDATION_PROBE_PREFIX);
+    let probe = prefix.child(format!("probe-{}-{nanos}", std::process::id()));
+    let contents = Bytes::from(format!("validation probe {nanos}"));
+
+    store
+        .put_bytes(&probe, contents.clone())
+        .await
+        .map_err(|e| ValidationError::new(ValidationStep::Write, e.into_error()))?;
+    let read = store
+        .get_bytes(&probe)
+        .await
+        .map_err(|e| ValidationError::new(ValidationStep::Read, e.into_error()))?;
+    if read != contents {
+        return Err(ValidationError::inconsistent(
+            ValidationStep::Read,
+            anyhow::anyhow!(
+                "Read {} bytes back instead of {}",
+                read.len(),
+                contents.len()
+            ),
+        ));
+    }
+    let listed: Vec<_> = async { store.list_objects(Some(&prefix)).await?.try_collect().await }
+        .await
+        .map_err(|e: ObjectStoreError| {
+            ValidationError::new(ValidationStep::List, e.into_error())
+        })?;
+    if !listed.iter().any(|meta| meta.location == probe) {
+        return Err(ValidationError::inconsistent(
+            ValidationStep::List,
+            anyhow::anyhow!("{probe} missing from the listing of {prefix}"),
+        ));
+    }
+    store
+        .delete_object(&probe)
+        .await
+        .map_err(|e| ValidationError::new(ValidationStep::Delete, e.into_error()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::validate::{
+        ValidationFailure, ValidationStep, VALIDATION_PROBE_PREFIX,
+    };
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreListExt, ObjectStoreType};
+    use futures::StreamExt;
+    use object_store::path::Path;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_validate() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            directory: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        config.validate().await?;
+        // The probe is deleted
+        let prefix = Path::from(VALIDATION_PROBE_PREFIX);
+        let store = config.make()?;
+        let mut listed = store.list_objects(Some(&prefix)).await?;
+        assert!(listed.next().await.is_none());
+
+        let error = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::File),
+            ..Default::default()
+        }
+        .validate()
+        .await
+        .unwrap_err();
+        assert_eq!(error.step, ValidationStep::Configure);
+        assert_eq!(error.failure, ValidationFailure::InvalidConfig);
+        Ok(())
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            ValidationFailure::classify(
+                "Generic S3 error: response error \"<Code>NoSuchBucket</Code>\", after 0 \
+                 retries: HTTP status client error (404 Not Found) for url (https://s3)"
+            ),
+            ValidationFailure::BucketNotFound
+        );
+        assert_eq!(
+            ValidationFailure::classify("HTTP status client error (403 Forbidden) for url"),
+            ValidationFailure::PermissionDenied
+        );
+        assert_eq!(
+            ValidationFailure::classify("HTTP status client error (401 Unauthorized) for url"),
+            ValidationFailure::Unauthenticated
+        );
+        assert_eq!(
+            ValidationFailure::classify("error sending request for url"),
+            ValidationFailure::Other
+        );
+    }
+}
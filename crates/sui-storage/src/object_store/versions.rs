@@ -0,0 +1,378 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reads of the previous versions of objects, in buckets with object versioning enabled, e.g. to
+//! recover a manifest overwritten by a buggy writer.
+//!
+//! The clients of object_store only read the live version of objects. S3 versions are read
+//! through the AWS SDK, by version ID. GCS versions are read through the JSON API, by
+//! generation, with an access token of the service account the store is configured with.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use bytes::Bytes;
+use chrono::{DateTime, TimeZone, Utc};
+use object_store::path::Path;
+use percent_encoding::utf8_percent_encode;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::object_store::error::{ObjectStoreError, ObjectStoreResult};
+use crate::object_store::http::STRICT_ENCODE_SET;
+use crate::object_store::multipart::s3_sdk_client;
+use crate::object_store::sign::{GcsSigner, GCS_TOKEN_URL};
+use crate::object_store::{ObjectStoreConfig, GCS_HOST};
+
+const GCS_READ_ONLY_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+const JWT_BEARER_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+/// Access tokens are requested again this long before they expire.
+const GCS_TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectVersion {
+    /// S3 version ID, or GCS generation, to read the version with
+    pub version: String,
+    pub size: usize,
+    /// When the version was written, if the store reported it
+    pub last_modified: Option<DateTime<Utc>>,
+    /// Whether the version is the live object, rather than an overwritten or deleted one
+    pub is_latest: bool,
+}
+
+#[async_trait]
+pub trait ObjectStoreVersionExt: Send + Sync + 'static {
+    /// Read the given version of the object at `src`, see [`ObjectVersion::version`]
+    async fn get_bytes_version(&self, src: &Path, version: &str) -> ObjectStoreResult<Bytes>;
+
+    /// List the versions of the object at `src`, in the order the store lists them: newest first
+    /// for S3, by generation for GCS. Deletes of S3 objects, recorded as delete markers, have no
+    /// content and aren't listed. Objects without any version are not found.
+    async fn list_versions(&self, src: &Path) -> ObjectStoreResult<Vec<ObjectVersion>>;
+}
+
+/// Error of a request of the AWS SDK, classified by its error code, as the errors of the
+/// operations on versions don't model missing versions.
+fn s3_error<E>(error: SdkError<E>, context: String) -> ObjectStoreError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    let code = match &error {
+        SdkError::ServiceError(e) => e.err().code().map(str::to_string),
+        _ => None,
+    };
+    let timed_out = matches!(error, SdkError::TimeoutError(_));
+    let error = anyhow::Error::new(error).context(context);
+    match code.as_deref() {
+        Some("NoSuchKey" | "NoSuchVersion" | "NotFound") => ObjectStoreError::NotFound(error),
+        Some("AccessDenied") => ObjectStoreError::PermissionDenied(error),
+        Some("SlowDown" | "ServiceUnavailable") => ObjectStoreError::Throttled(error),
+        _ if timed_out => ObjectStoreError::Timeout(error),
+        _ => ObjectStoreError::Other(error),
+    }
+}
+
+pub(crate) struct S3VersionedReader {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3VersionedReader {
+    pub(crate) fn new(config: &ObjectStoreConfig) -> Result<Self> {
+        let Some(bucket) = config.bucket.clone() else {
+            bail!("No bucket configured to read object versions from");
+        };
+        Ok(Self {
+            client: s3_sdk_client(config)?,
+            bucket,
+        })
+    }
+}
+
+#[async_trait]
+impl ObjectStoreVersionExt for S3VersionedReader {
+    async fn get_bytes_version(&self, src: &Path, version: &str) -> ObjectStoreResult<Bytes> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(src.to_string())
+            .version_id(version)
+            .send()
+            .await
+            .map_err(|e| s3_error(e, format!("Failed to read version {version} of {src}")))?;
+        Ok(output
+            .body
+            .collect()
+            .await
+            .with_context(|| format!("Failed to read version {version} of {src}"))?
+            .into_bytes())
+    }
+
+    async fn list_versions(&self, src: &Path) -> ObjectStoreResult<Vec<ObjectVersion>> {
+        let key = src.to_string();
+        let mut versions = vec![];
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+        loop {
+            // Versions are listed by prefix, which the keys of other objects may also start with.
+            let output = self
+                .client
+                .list_object_versions()
+                .bucket(&self.bucket)
+                .prefix(&key)
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await
+                .map_err(|e| s3_error(e, format!("Failed to list the versions of {src}")))?;
+            versions.extend(
+                output
+                    .versions()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|version| version.key() == Some(key.as_str()))
+                    .map(|version| ObjectVersion {
+                        version: version.version_id().unwrap_or("null").to_string(),
+                        size: version.size() as usize,
+                        last_modified: version.last_modified().and_then(|time| {
+                            Utc.timestamp_opt(time.secs(), time.subsec_nanos()).single()
+                        }),
+                        is_latest: version.is_latest(),
+                    }),
+            );
+            if !output.is_truncated() {
+                break;
+            }
+            key_marker = output.next_key_marker().map(str::to_string);
+            version_id_marker = output.next_version_id_marker().map(str::to_string);
+        }
+        if versions.is_empty() {
+            return Err(ObjectStoreError::NotFound(anyhow!("No versions of {src}")));
+        }
+        Ok(versions)
+    }
+}
+
+/// Page of the listing of a GCS bucket.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GcsObjectList {
+    #[serde(default)]
+    items: Vec<GcsObject>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GcsObject {
+    name: String,
+    generation: String,
+    size: String,
+    time_created: DateTime<Utc>,
+    /// Set once the generation is overwritten or deleted
+    time_deleted: Option<DateTime<Utc>>,
+}
+
+impl GcsObject {
+    fn into_version(self) -> Result<ObjectVersion> {
+        Ok(ObjectVersion {
+            size: self
+                .size
+                .parse()
+                .with_context(|| format!("Invalid size of {}: {}", self.name, self.size))?,
+            version: self.generation,
+            last_modified: Some(self.time_created),
+            is_latest: self.time_deleted.is_none(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct GcsAccessToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+pub(crate) struct GcsVersionedReader {
+    bucket: String,
+    signer: GcsSigner,
+    client: Client,
+    /// Access token, and when it must be requested again
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl GcsVersionedReader {
+    pub(crate) fn new(config: &ObjectStoreConfig, service_account: &str) -> Result<Self> {
+        let Some(bucket) = config.bucket.clone() else {
+            bail!("No bucket configured to read object versions from");
+        };
+        Ok(Self {
+            bucket,
+            signer: GcsSigner::new(config, service_account)?,
+            client: config.http_client_builder(GCS_HOST)?.build()?,
+            token: Mutex::new(None),
+        })
+    }
+
+    async fn access_token(&self) -> Result<String> {
+        let mut token = self.token.lock().await;
+        if let Some((token, refresh_at)) = token.as_ref() {
+            if Instant::now() < *refresh_at {
+                return Ok(token.clone());
+            }
+        }
+        let assertion = self
+            .signer
+            .token_assertion(GCS_READ_ONLY_SCOPE, Utc::now())?;
+        let response: GcsAccessToken = self
+            .client
+            .post(GCS_TOKEN_URL)
+            .form(&[
+                ("grant_type", JWT_BEARER_GRANT_TYPE),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("Failed to request a GCS access token")?
+            .json()
+            .await
+            .context("Invalid GCS access token")?;
+        let refresh_at = Instant::now()
+            + Duration::from_secs(response.expires_in).saturating_sub(GCS_TOKEN_REFRESH_MARGIN);
+        *token = Some((response.access_token.clone(), refresh_at));
+        Ok(response.access_token)
+    }
+
+    fn objects_url(&self) -> String {
+        format!("https://{GCS_HOST}/storage/v1/b/{}/o", self.bucket)
+    }
+}
+
+#[async_trait]
+impl ObjectStoreVersionExt for GcsVersionedReader {
+    async fn get_bytes_version(&self, src: &Path, version: &str) -> ObjectStoreResult<Bytes> {
+        // The object name is a single segment of the URL, its slashes are encoded too.
+        let url = format!(
+            "{}/{}",
+            self.objects_url(),
+            utf8_percent_encode(src.as_ref(), &STRICT_ENCODE_SET)
+        );
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(self.access_token().await?)
+            .query(&[("alt", "media"), ("generation", version)])
+            .send()
+            .await
+            .with_context(|| format!("Failed to read version {version} of {src}"))?;
+        match response.status() {
+            status if status.is_success() => Ok(response.bytes().await?),
+            StatusCode::NOT_FOUND => Err(ObjectStoreError::from_status(
+                StatusCode::NOT_FOUND,
+                anyhow!("No version {version} of {src}"),
+            )),
+            status => Err(ObjectStoreError::from_status(
+                status,
+                anyhow!("Failed to read version {version} of {src} with status: {status}"),
+            )),
+        }
+    }
+
+    async fn list_versions(&self, src: &Path) -> ObjectStoreResult<Vec<ObjectVersion>> {
+        let mut versions = vec![];
+        let mut page_token = None;
+        loop {
+            let mut request = self
+                .client
+                .get(self.objects_url())
+                .bearer_auth(self.access_token().await?)
+                .query(&[("prefix", src.as_ref()), ("versions", "true")]);
+            if let Some(page_token) = &page_token {
+                request = request.query(&[("pageToken", page_token)]);
+            }
+            let response = request
+                .send()
+                .await
+                .with_context(|| format!("Failed to list the versions of {src}"))?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(ObjectStoreError::from_status(
+                    status,
+                    anyhow!("Failed to list the versions of {src} with status: {status}"),
+                ));
+            }
+            let page: GcsObjectList = response
+                .json()
+                .await
+                .with_context(|| format!("Invalid listing of the versions of {src}"))?;
+            for object in page.items {
+                if object.name == src.as_ref() {
+                    versions.push(object.into_version()?);
+                }
+            }
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+        if versions.is_empty() {
+            return Err(ObjectStoreError::NotFound(anyhow!("No versions of {src}")));
+        }
+        Ok(versions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::versions::GcsObjectList;
+    use crate::object_store::{ObjectStoreConfig, ObjectStoreType};
+
+    #[test]
+    fn test_gcs_versions() -> anyhow::Result<()> {
+        let page: GcsObjectList = serde_json::from_str(
+            r#"{
+                "kind": "storage#objects",
+                "items": [
+                    {
+                        "name": "MANIFEST",
+                        "generation": "1700000000000001",
+                        "size": "1024",
+                        "timeCreated": "2023-11-14T22:13:20.000Z",
+                        "timeDeleted": "2023-11-15T22:13:20.000Z"
+                    },
+                    {
+                        "name": "MANIFEST",
+                        "generation": "1700086400000001",
+                        "size": "12",
+                        "timeCreated": "2023-11-15T22:13:20.000Z"
+                    }
+                ]
+            }"#,
+        )?;
+        assert!(page.next_page_token.is_none());
+        let versions = page
+            .items
+            .into_iter()
+            .map(|object| object.into_version())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(versions[0].version, "1700000000000001");
+        assert_eq!(versions[0].size, 1024);
+        assert!(!versions[0].is_latest);
+        assert!(versions[1].is_latest);
+
+        // Only S3 and GCS objects have versions.
+        let config = ObjectStoreConfig {
+            object_store: Some(ObjectStoreType::Memory),
+            bucket: Some("test_gcs_versions".to_string()),
+            ..Default::default()
+        };
+        assert!(config.make_versioned_reader().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,516 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Change feed of the objects under a prefix, e.g. for consumers of an archive to pick up new
+//! checkpoint files as they are uploaded.
+//!
+//! Changes are found by listing the prefix periodically. Listings are also triggered by the
+//! event notifications of S3 buckets configured with `--aws-event-queue-url`, see
+//! [`StoreWatcher::with_s3_event_notifications`], to pick up changes before the next poll.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_sqs::config::Region;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path;
+use object_store::ObjectMeta;
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::object_store::aws_credentials::sdk_credentials_provider;
+use crate::object_store::multipart::DEFAULT_AWS_REGION;
+use crate::object_store::{ObjectStoreConfig, ObjectStoreListExt};
+
+/// Longest time a receive of event notifications waits for one, as allowed by SQS.
+const SQS_WAIT_TIME_SECS: i32 = 20;
+/// Time to wait before receiving event notifications again after a failure.
+const SQS_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreChange {
+    /// Object written under the prefix since the last listing, or overwritten, in which case its
+    /// new metadata is reported.
+    Added(ObjectMeta),
+    /// Object deleted since the last listing, with its last known metadata.
+    Removed(ObjectMeta),
+}
+
+/// How a [`StoreWatcher`] lists its prefix.
+enum Listing {
+    /// Every object under the prefix, compared with the objects of the last listing
+    Recursive(BTreeMap<Path, ObjectMeta>),
+    /// The objects directly under the prefix, compared with the ones of the last listing
+    Shallow(BTreeMap<Path, ObjectMeta>),
+    /// The objects after the last one seen, for prefixes only appended to
+    AppendOnly(Option<Path>),
+}
+
+/// Watches a prefix of a store for added and removed objects, by listing it periodically and
+/// comparing the listing with the previous one. Prefixes only ever appended to are listed from
+/// the last object seen instead, see [`Self::append_only`].
+///
+/// Listings can also be triggered by notifications, e.g. S3 event notifications consumed from a
+/// queue, to pick up changes sooner than the next poll. Notifications only trigger listings, so
+/// missed or duplicated ones delay changes by at most the poll interval, and are never reported
+/// twice.
+pub struct StoreWatcher<S> {
+    store: S,
+    prefix: Option<Path>,
+    poll_interval: Duration,
+    notifications: Option<BoxStream<'static, ()>>,
+    listing: Listing,
+}
+
+impl<S: ObjectStoreListExt> StoreWatcher<S> {
+    /// Watcher of `prefix`, or of the whole store, reporting every object already there as added
+    /// on the first listing.
+    pub fn new(store: S, prefix: Option<Path>, poll_interval: Duration) -> Self {
+        Self {
+            store,
+            prefix,
+            poll_interval,
+            notifications: None,
+            listing: Listing::Recursive(BTreeMap::new()),
+        }
+    }
+
+    /// Only watch the objects directly under the prefix, listed with a single request, e.g. the
+    /// MANIFEST of an archive rather than every file of every epoch.
+    pub fn shallow(mut self) -> Self {
+        if let Listing::Recursive(known) = self.listing {
+            self.listing = Listing::Shallow(known);
+        }
+        self
+    }
+
+    /// Only list the objects after the last one seen, for prefixes whose objects are written in
+    /// the lexicographic order of their locations and never removed or overwritten, e.g. named
+    /// by a timestamp or a zero-padded sequence number. Removals and overwrites aren't reported,
+    /// and neither are objects written before the last one seen.
+    pub fn append_only(mut self) -> Self {
+        if let Listing::Recursive(known) | Listing::Shallow(known) = self.listing {
+            self.listing = Listing::AppendOnly(known.into_keys().next_back());
+        }
+        self
+    }
+
+    /// List the prefix as soon as an item of `notifications` is received, rather than at the
+    /// next poll. The watcher keeps polling once they end.
+    pub fn with_notifications(mut self, notifications: BoxStream<'static, ()>) -> Self {
+        self.notifications = Some(notifications);
+        self
+    }
+
+    /// List the prefix as soon as the S3 bucket of `config` notifies of a change under it, if the
+    /// bucket sends its event notifications to the SQS queue at `--aws-event-queue-url`. Received
+    /// notifications are deleted from the queue, which must only be read by this watcher.
+    pub fn with_s3_event_notifications(self, config: &ObjectStoreConfig) -> Result<Self> {
+        let Some(queue_url) = config.aws_event_queue_url.clone() else {
+            return Ok(self);
+        };
+        let bucket = config
+            .bucket
+            .clone()
+            .ok_or_else(|| anyhow!("No bucket configured to receive event notifications of"))?;
+        let notifications =
+            s3_event_notifications(sqs_client(config)?, queue_url, bucket, self.prefix.clone());
+        Ok(self.with_notifications(notifications))
+    }
+
+    /// Objects already known to the consumer, e.g. the ones it processed before a restart, which
+    /// are only reported if they're removed or overwritten.
+    pub fn with_known_objects(mut self, objects: impl IntoIterator<Item = ObjectMeta>) -> Self {
+        let objects = objects
+            .into_iter()
+            .map(|object| (object.location.clone(), object));
+        match &mut self.listing {
+            Listing::Recursive(known) | Listing::Shallow(known) => known.extend(objects),
+            Listing::AppendOnly(last) => {
+                *last = last
+                    .take()
+                    .into_iter()
+                    .chain(objects.map(|(location, _)| location))
+                    .max()
+            }
+        }
+        self
+    }
+
+    /// List the prefix, and return the changes since the last listing: removed objects first,
+    /// then added ones, each ordered by location.
+    pub async fn poll(&mut self) -> Result<Vec<StoreChange>> {
+        let prefix = self.prefix.as_ref();
+        let (known, listed) = match &mut self.listing {
+            Listing::AppendOnly(last) => {
+                let stream = match last {
+                    Some(offset) => self.store.list_objects_after(prefix, offset).await?,
+                    None => self.store.list_objects(prefix).await?,
+                };
+                let mut added: Vec<ObjectMeta> = stream.try_collect().await?;
+                added.sort_by(|a, b| a.location.cmp(&b.location));
+                if let Some(object) = added.last() {
+                    *last = Some(object.location.clone());
+                }
+                return Ok(added.into_iter().map(StoreChange::Added).collect());
+            }
+            Listing::Recursive(known) => {
+                let listed: Vec<ObjectMeta> =
+                    self.store.list_objects(prefix).await?.try_collect().await?;
+                (known, listed)
+            }
+            Listing::Shallow(known) => {
+                let listed = self.store.list_objects_with_delimiter(prefix).await?;
+                (known, listed.objects)
+            }
+        };
+        let listed: BTreeMap<Path, ObjectMeta> = listed
+            .into_iter()
+            .map(|object| (object.location.clone(), object))
+            .collect();
+        let mut changes: Vec<_> = known
+            .iter()
+            .filter(|(location, _)| !listed.contains_key(*location))
+            .map(|(_, object)| StoreChange::Removed(object.clone()))
+            .collect();
+        changes.extend(
+            listed
+                .iter()
+                .filter(|(location, object)| known.get(*location) != Some(*object))
+                .map(|(_, object)| StoreChange::Added(object.clone())),
+        );
+        *known = listed;
+        Ok(changes)
+    }
+
+    /// Stream of the changes of every listing, starting with one right away. Failed listings are
+    /// reported as errors, and retried at the next poll.
+    pub fn into_stream(self) -> BoxStream<'static, Result<StoreChange>> {
+        futures::stream::unfold(
+            (self, VecDeque::new(), true),
+            |(mut watcher, mut pending, mut first)| async move {
+                loop {
+                    if let Some(change) = pending.pop_front() {
+                        return Some((Ok(change), (watcher, pending, first)));
+                    }
+                    if !first {
+                        watcher.wait().await;
+                    }
+                    first = false;
+                    match watcher.poll().await {
+                        Ok(changes) => pending.extend(changes),
+                        Err(e) => return Some((Err(e), (watcher, pending, first))),
+                    }
+                }
+            },
+        )
+        .boxed()
+    }
+
+    /// Wait for the next poll, or the next notification.
+    async fn wait(&mut self) {
+        let sleep = tokio::time::sleep(self.poll_interval);
+        let Some(notifications) = self.notifications.as_mut() else {
+            sleep.await;
+            return;
+        };
+        let ended = tokio::select! {
+            _ = sleep => false,
+            notification = notifications.next() => notification.is_none(),
+        };
+        if ended {
+            warn!(
+                "Notifications of changes under {:?} ended, only polling every {:?}",
+                self.prefix, self.poll_interval
+            );
+            self.notifications = None;
+        }
+    }
+}
+
+fn sqs_client(config: &ObjectStoreConfig) -> Result<aws_sdk_sqs::Client> {
+    let region = config
+        .aws_region
+        .clone()
+        .unwrap_or_else(|| DEFAULT_AWS_REGION.to_string());
+    let sqs_config = aws_sdk_sqs::Config::builder()
+        .region(Region::new(region))
+        .credentials_provider(sdk_credentials_provider(config)?)
+        .build();
+    Ok(aws_sdk_sqs::Client::from_conf(sqs_config))
+}
+
+/// Stream of an item for every batch of event notifications received from `queue_url` that
+/// changed an object of `bucket` under `prefix`.
+fn s3_event_notifications(
+    client: aws_sdk_sqs::Client,
+    queue_url: String,
+    bucket: String,
+    prefix: Option<Path>,
+) -> BoxStream<'static, ()> {
+    futures::stream::unfold((client, queue_url, bucket, prefix), |state| async move {
+        let (client, queue_url, bucket, prefix) = &state;
+        loop {
+            match receive_s3_events(client, queue_url).await {
+                Ok(objects) => {
+                    let changed = objects.iter().any(|(object_bucket, location)| {
+                        object_bucket == bucket
+                            && prefix
+                                .as_ref()
+                                .map_or(true, |prefix| location.prefix_match(prefix).is_some())
+                    });
+                    if changed {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to receive event notifications from {queue_url}: {e:?}");
+                    tokio::time::sleep(SQS_RETRY_INTERVAL).await;
+                }
+            }
+        }
+        Some(((), state))
+    })
+    .boxed()
+}
+
+/// Receive a batch of event notifications from `queue_url`, delete them from the queue, and
+/// return the buckets and locations of the objects they're about.
+async fn receive_s3_events(
+    client: &aws_sdk_sqs::Client,
+    queue_url: &str,
+) -> Result<Vec<(String, Path)>> {
+    let output = client
+        .receive_message()
+        .queue_url(queue_url)
+        .max_number_of_messages(10)
+        .wait_time_seconds(SQS_WAIT_TIME_SECS)
+        .send()
+        .await
+        .context("Failed to receive event notifications")?;
+    let mut objects = vec![];
+    for message in output.messages().unwrap_or_default() {
+        match message.body().map(parse_s3_event).transpose() {
+            Ok(events) => objects.extend(events.unwrap_or_default()),
+            Err(e) => warn!("Ignoring invalid event notification: {e:?}"),
+        }
+        if let Some(receipt_handle) = message.receipt_handle() {
+            client
+                .delete_message()
+                .queue_url(queue_url)
+                .receipt_handle(receipt_handle)
+                .send()
+                .await
+                .context("Failed to delete event notification")?;
+        }
+    }
+    debug!("Received event notifications of {} objects", objects.len());
+    Ok(objects)
+}
+
+/// S3 event notification, see
+/// <https://docs.aws.amazon.com/AmazonS3/latest/userguide/notification-content-structure.html>.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct S3EventNotification {
+    /// Missing from the test event sent when notifications are configured
+    #[serde(default)]
+    records: Vec<S3EventRecord>,
+}
+
+#[derive(Deserialize)]
+struct S3EventRecord {
+    s3: S3EventEntity,
+}
+
+#[derive(Deserialize)]
+struct S3EventEntity {
+    bucket: S3EventBucket,
+    object: S3EventObject,
+}
+
+#[derive(Deserialize)]
+struct S3EventBucket {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct S3EventObject {
+    key: String,
+}
+
+/// Buckets and locations of the objects of an S3 event notification.
+fn parse_s3_event(body: &str) -> Result<Vec<(String, Path)>> {
+    let notification: S3EventNotification = serde_json::from_str(body)?;
+    notification
+        .records
+        .into_iter()
+        .map(|record| {
+            // Keys are URL encoded, with spaces as `+`
+            let key = record.s3.object.key.replace('+', " ");
+            let key = percent_decode_str(&key).decode_utf8()?;
+            Ok((record.s3.bucket.name, Path::parse(key)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::object_store::watch::{parse_s3_event, StoreChange, StoreWatcher};
+    use bytes::Bytes;
+    use futures::StreamExt;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::{DynObjectStore, ObjectStore};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn location(change: &StoreChange) -> (&'static str, String) {
+        match change {
+            StoreChange::Added(object) => ("added", object.location.to_string()),
+            StoreChange::Removed(object) => ("removed", object.location.to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_watcher() -> anyhow::Result<()> {
+        let store: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        store
+            .put(&Path::from("epoch_0/1.chk"), Bytes::from_static(b"1"))
+            .await?;
+        store
+            .put(&Path::from("other/1.chk"), Bytes::from_static(b"1"))
+            .await?;
+        let (notifier, notifications) = futures::channel::mpsc::unbounded();
+        // Changes are only picked up through notifications, the watcher never polls in this test.
+        let mut changes = StoreWatcher::new(
+            store.clone(),
+            Some(Path::from("epoch_0")),
+            Duration::from_secs(3600),
+        )
+        .with_notifications(notifications.boxed())
+        .into_stream();
+
+        let change = changes.next().await.unwrap()?;
+        assert_eq!(location(&change), ("added", "epoch_0/1.chk".to_string()));
+
+        store
+            .put(&Path::from("epoch_0/2.chk"), Bytes::from_static(b"2"))
+            .await?;
+        store.delete(&Path::from("epoch_0/1.chk")).await?;
+        notifier.unbounded_send(())?;
+        let change = changes.next().await.unwrap()?;
+        assert_eq!(location(&change), ("removed", "epoch_0/1.chk".to_string()));
+        let change = changes.next().await.unwrap()?;
+        assert_eq!(location(&change), ("added", "epoch_0/2.chk".to_string()));
+
+        // Known objects are only reported once overwritten.
+        let mut watcher = StoreWatcher::new(store.clone(), None, Duration::from_secs(3600))
+            .with_known_objects(vec![store.head(&Path::from("epoch_0/2.chk")).await?]);
+        let changes = watcher.poll().await?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(location(&changes[0]), ("added", "other/1.chk".to_string()));
+        assert!(watcher.poll().await?.is_empty());
+        store
+            .put(&Path::from("epoch_0/2.chk"), Bytes::from_static(b"2'"))
+            .await?;
+        let changes = watcher.poll().await?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            location(&changes[0]),
+            ("added", "epoch_0/2.chk".to_string())
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_store_watcher_listings() -> anyhow::Result<()> {
+        let store: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        for location in ["MANIFEST", "epoch_0/1.chk", "epoch_0/2.chk"] {
+            store
+                .put(&Path::from(location), Bytes::from_static(b"1"))
+                .await?;
+        }
+
+        // Shallow watchers only list the objects directly under the prefix.
+        let mut watcher =
+            StoreWatcher::new(store.clone(), None, Duration::from_secs(3600)).shallow();
+        let changes = watcher.poll().await?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(location(&changes[0]), ("added", "MANIFEST".to_string()));
+        store
+            .put(&Path::from("MANIFEST"), Bytes::from_static(b"2"))
+            .await?;
+        let changes = watcher.poll().await?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(location(&changes[0]), ("added", "MANIFEST".to_string()));
+
+        // Append-only watchers only list the objects after the last one seen.
+        let mut watcher = StoreWatcher::new(
+            store.clone(),
+            Some(Path::from("epoch_0")),
+            Duration::from_secs(3600),
+        )
+        .append_only()
+        .with_known_objects(vec![store.head(&Path::from("epoch_0/1.chk")).await?]);
+        let changes = watcher.poll().await?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            location(&changes[0]),
+            ("added", "epoch_0/2.chk".to_string())
+        );
+        assert!(watcher.poll().await?.is_empty());
+        store
+            .put(&Path::from("epoch_0/3.chk"), Bytes::from_static(b"3"))
+            .await?;
+        store.delete(&Path::from("epoch_0/1.chk")).await?;
+        let changes = watcher.poll().await?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            location(&changes[0]),
+            ("added", "epoch_0/3.chk".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_s3_event() -> anyhow::Result<()> {
+        let objects = parse_s3_event(
+            r#"{
+                "Records": [
+                    {
+                        "eventVersion": "2.1",
+                        "eventSource": "aws:s3",
+                        "eventName": "ObjectCreated:Put",
+                        "s3": {
+                            "bucket": { "name": "archive" },
+                            "object": { "key": "epoch_0/1.chk", "size": 1024 }
+                        }
+                    },
+                    {
+                        "s3": {
+                            "bucket": { "name": "archive" },
+                            "object": { "key": "epoch+0/a%2Bb.chk" }
+                        }
+                    }
+                ]
+            }"#,
+        )?;
+        assert_eq!(
+            objects,
+            vec![
+                ("archive".to_string(), Path::from("epoch_0/1.chk")),
+                ("archive".to_string(), Path::from("epoch 0/a+b.chk")),
+            ]
+        );
+        // The test event sent when notifications are configured has no records.
+        let objects = parse_s3_event(
+            r#"{"Service": "Amazon S3", "Event": "s3:TestEvent", "Bucket": "archive"}"#,
+        )?;
+        assert!(objects.is_empty());
+        Ok(())
+    }
+}
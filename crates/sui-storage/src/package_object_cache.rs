@@ -1,27 +1,48 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::memory_budget::{register_cache, EvictableCache, MemoryAccount};
 use lru::LruCache;
 use parking_lot::RwLock;
 use std::num::NonZeroUsize;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 use sui_types::base_types::ObjectID;
 use sui_types::error::{SuiError, SuiResult, UserInputError};
 use sui_types::storage::{ObjectStore, PackageObjectArc};
 
 pub struct PackageObjectCache {
     cache: RwLock<LruCache<ObjectID, PackageObjectArc>>,
+    memory_account: Option<MemoryAccount>,
 }
 
 const CACHE_CAP: usize = 1024 * 1024;
+/// Packages are read by every transaction execution, so they are kept longer than other cache
+/// entries when the memory budget is exceeded.
+const MEMORY_BUDGET_WEIGHT: u32 = 4;
 
 impl PackageObjectCache {
     pub fn new() -> Arc<Self> {
-        Arc::new(Self {
+        Arc::new_cyclic(|cache: &Weak<Self>| Self {
             cache: RwLock::new(LruCache::new(NonZeroUsize::new(CACHE_CAP).unwrap())),
+            memory_account: register_cache(
+                "package_object_cache",
+                MEMORY_BUDGET_WEIGHT,
+                cache.clone(),
+            ),
         })
     }
 
+    fn insert(&self, package_id: ObjectID, package: PackageObjectArc) {
+        let size = package.object().object_size_for_gas_metering();
+        let replaced = self.cache.write().push(package_id, package);
+        if let Some(account) = &self.memory_account {
+            if let Some((_, replaced)) = replaced {
+                account.release(replaced.object().object_size_for_gas_metering());
+            }
+            account.charge(size);
+        }
+    }
+
     pub fn get_package_object(
         &self,
         package_id: &ObjectID,
@@ -47,7 +68,7 @@ impl PackageObjectCache {
         if let Some(p) = store.get_object(package_id)? {
             if p.is_package() {
                 let p = PackageObjectArc::new(p);
-                self.cache.write().push(*package_id, p.clone());
+                self.insert(*package_id, p.clone());
                 Ok(Some(p))
             } else {
                 Err(SuiError::UserInputError {
@@ -72,12 +93,24 @@ impl PackageObjectCache {
                 .expect("Failed to update system packages")
             {
                 assert!(p.is_package());
-                self.cache
-                    .write()
-                    .push(package_id, PackageObjectArc::new(p));
+                self.insert(package_id, PackageObjectArc::new(p));
             }
             // It's possible that a package is not found if it's newly added system package ID
             // that hasn't got created yet. This should be very very rare though.
         }
     }
 }
+
+impl EvictableCache for PackageObjectCache {
+    fn evict(&self, bytes: usize) -> usize {
+        let mut cache = self.cache.write();
+        let mut freed = 0;
+        while freed < bytes {
+            let Some((_, package)) = cache.pop_lru() else {
+                break;
+            };
+            freed += package.object().object_size_for_gas_metering();
+        }
+        freed
+    }
+}
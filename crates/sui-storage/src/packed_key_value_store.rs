@@ -0,0 +1,371 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Packing of the small values of the key value store, e.g. transactions, effects and events,
+//! into larger objects of an object store, so that a checkpoint's worth of values costs one
+//! write rather than one per value.
+//!
+//! A [`PackWriter`] buffers values and uploads them as a pack, `<prefix>/<name>.pack`, with their
+//! concatenated bytes, followed by its index, `<prefix>/<name>.idx`, with the offset and length of
+//! every value. Packs are named by the digest of their bytes, so writers never overwrite each
+//! other's packs and a pack uploaded again after a failure is the same object. A [`PackReader`]
+//! resolves keys to their location with a [`PackIndex`], and reads the values of a pack with
+//! ranged gets.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{HashFunction, Sha256};
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path;
+use object_store::DynObjectStore;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::object_store::util::get;
+use crate::object_store::{
+    ObjectStoreGetExt, ObjectStoreGetRangeExt, ObjectStoreListExt, ObjectStorePutExt,
+};
+
+const PACK_FILE_SUFFIX: &str = "pack";
+const INDEX_FILE_SUFFIX: &str = "idx";
+/// Number of packs read from, or index files loaded, at once.
+const CONCURRENCY: usize = 16;
+
+/// Key of a packed value: the type of the value, as in the paths of the HTTP key value store,
+/// e.g. `tx` or `fx`, and the bytes of its key, e.g. a transaction digest.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PackedKey {
+    pub table: String,
+    pub key: Vec<u8>,
+}
+
+impl PackedKey {
+    pub fn new(table: &str, key: Vec<u8>) -> Self {
+        Self {
+            table: table.to_string(),
+            key,
+        }
+    }
+}
+
+/// Where the bytes of a value are, in the pack of the given name.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackLocation {
+    pub pack: String,
+    pub offset: u64,
+    pub len: u64,
+}
+
+impl PackLocation {
+    fn range(&self) -> Range<usize> {
+        self.offset as usize..(self.offset + self.len) as usize
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PackIndexEntry {
+    key: PackedKey,
+    offset: u64,
+    len: u64,
+}
+
+fn pack_path(prefix: &Path, pack: &str) -> Path {
+    prefix.child(format!("{pack}.{PACK_FILE_SUFFIX}"))
+}
+
+fn index_path(prefix: &Path, pack: &str) -> Path {
+    prefix.child(format!("{pack}.{INDEX_FILE_SUFFIX}"))
+}
+
+pub struct PackWriter<S> {
+    store: S,
+    prefix: Path,
+    target_pack_size: usize,
+    max_value_size: usize,
+    buffer: Vec<u8>,
+    entries: Vec<PackIndexEntry>,
+}
+
+impl<S: ObjectStorePutExt> PackWriter<S> {
+    /// Writer of packs of about `target_pack_size` bytes, of values of at most `max_value_size`
+    /// bytes. Larger values aren't worth packing, and are left to the caller to write on their own.
+    pub fn new(store: S, prefix: Path, target_pack_size: usize, max_value_size: usize) -> Self {
+        Self {
+            store,
+            prefix,
+            target_pack_size,
+            max_value_size,
+            buffer: Vec::with_capacity(target_pack_size),
+            entries: vec![],
+        }
+    }
+
+    /// Buffer `value` into the next pack, and return whether it was, i.e. whether it isn't larger
+    /// than the maximum size of packed values. The pack is written on the next [`Self::flush`],
+    /// e.g. once [`Self::is_full`].
+    pub fn add(&mut self, key: PackedKey, value: &[u8]) -> bool {
+        if value.len() > self.max_value_size {
+            return false;
+        }
+        self.entries.push(PackIndexEntry {
+            key,
+            offset: self.buffer.len() as u64,
+            len: value.len() as u64,
+        });
+        self.buffer.extend_from_slice(value);
+        true
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.len() >= self.target_pack_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the buffered values as a pack, then its index, and return where each value is, e.g.
+    /// to record them in a [`PackIndex`]. Nothing is written if no value is buffered. The values
+    /// stay buffered if the pack fails to be written, so that flushing can be retried.
+    pub async fn flush(&mut self) -> Result<Vec<(PackedKey, PackLocation)>> {
+        if self.entries.is_empty() {
+            return Ok(vec![]);
+        }
+        let pack = Bytes::from(self.buffer.clone());
+        let name = Hex::encode(Sha256::digest(&pack).digest);
+        self.store
+            .put_bytes(&pack_path(&self.prefix, &name), pack)
+            .await?;
+        // The index is written last, so that readers loading indexes never miss the pack
+        self.store
+            .put_bytes(
+                &index_path(&self.prefix, &name),
+                bcs::to_bytes(&self.entries)?.into(),
+            )
+            .await?;
+        self.buffer.clear();
+        Ok(std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|entry| {
+                let location = PackLocation {
+                    pack: name.clone(),
+                    offset: entry.offset,
+                    len: entry.len,
+                };
+                (entry.key, location)
+            })
+            .collect())
+    }
+}
+
+/// Lookup of the location of packed values, e.g. loaded from the index files of the packs, or
+/// kept in a database by the writer of the packs.
+#[async_trait]
+pub trait PackIndex: Send + Sync + 'static {
+    /// Location of each of `keys`, `None` for keys that aren't packed.
+    async fn locate(&self, keys: &[PackedKey]) -> Result<Vec<Option<PackLocation>>>;
+}
+
+/// Index of packs held in memory, for stores whose keys fit in memory.
+#[derive(Default)]
+pub struct InMemoryPackIndex {
+    locations: RwLock<HashMap<PackedKey, PackLocation>>,
+}
+
+impl InMemoryPackIndex {
+    /// Index of every pack under `prefix`, from their index files.
+    pub async fn load<S: ObjectStoreListExt + ObjectStoreGetExt>(
+        store: &S,
+        prefix: &Path,
+    ) -> Result<Self> {
+        let index = Self::default();
+        index.load_packs(store, prefix).await?;
+        Ok(index)
+    }
+
+    /// Add every pack under `prefix` to the index, from their index files, e.g. in the background
+    /// of a reader already serving the values of the packs loaded so far.
+    pub async fn load_packs<S: ObjectStoreListExt + ObjectStoreGetExt>(
+        &self,
+        store: &S,
+        prefix: &Path,
+    ) -> Result<()> {
+        let index_files: Vec<Path> = store
+            .list_objects(Some(prefix))
+            .await?
+            .map_ok(|meta| meta.location)
+            .try_filter(|location| {
+                futures::future::ready(location.extension() == Some(INDEX_FILE_SUFFIX))
+            })
+            .try_collect()
+            .await?;
+        let indexes: Vec<_> = futures::stream::iter(index_files)
+            .map(|path| async move {
+                let pack = path
+                    .filename()
+                    .and_then(|filename| filename.strip_suffix(&format!(".{INDEX_FILE_SUFFIX}")))
+                    .ok_or_else(|| anyhow!("Invalid pack index file name {path}"))?
+                    .to_string();
+                let entries: Vec<PackIndexEntry> = bcs::from_bytes(&get(store, &path).await?)?;
+                Ok::<_, anyhow::Error>((pack, entries))
+            })
+            .buffer_unordered(CONCURRENCY)
+            .try_collect()
+            .await?;
+        let packs = indexes.len();
+        for (pack, entries) in indexes {
+            self.insert(entries.into_iter().map(|entry| {
+                let location = PackLocation {
+                    pack: pack.clone(),
+                    offset: entry.offset,
+                    len: entry.len,
+                };
+                (entry.key, location)
+            }));
+        }
+        info!(
+            "Loaded index of {packs} packs under {prefix}, {} values indexed",
+            self.locations.read().len()
+        );
+        Ok(())
+    }
+
+    pub fn insert(&self, locations: impl IntoIterator<Item = (PackedKey, PackLocation)>) {
+        self.locations.write().extend(locations);
+    }
+}
+
+#[async_trait]
+impl PackIndex for InMemoryPackIndex {
+    async fn locate(&self, keys: &[PackedKey]) -> Result<Vec<Option<PackLocation>>> {
+        let locations = self.locations.read();
+        Ok(keys.iter().map(|key| locations.get(key).cloned()).collect())
+    }
+}
+
+pub struct PackReader {
+    store: Arc<dyn ObjectStoreGetRangeExt>,
+    prefix: Path,
+    index: Arc<dyn PackIndex>,
+}
+
+impl PackReader {
+    pub fn new(
+        store: Arc<dyn ObjectStoreGetRangeExt>,
+        prefix: Path,
+        index: Arc<dyn PackIndex>,
+    ) -> Self {
+        Self {
+            store,
+            prefix,
+            index,
+        }
+    }
+
+    /// Reader of the packs under `prefix`, whose index files are loaded in the background. Keys of
+    /// packs not loaded yet, or if loading fails, aren't found, and are left to the caller to read
+    /// from elsewhere.
+    pub fn load_in_background(store: Arc<DynObjectStore>, prefix: &str) -> Self {
+        let prefix = Path::from(prefix);
+        let index = Arc::new(InMemoryPackIndex::default());
+        let (cloned_store, cloned_prefix, cloned_index) =
+            (store.clone(), prefix.clone(), index.clone());
+        tokio::spawn(async move {
+            if let Err(e) = cloned_index.load_packs(&cloned_store, &cloned_prefix).await {
+                error!("Failed to load pack index under {cloned_prefix}: {e:?}");
+            }
+        });
+        Self::new(Arc::new(store), prefix, index)
+    }
+
+    /// Values of `keys`, `None` for keys that aren't packed. The values of a pack are read with
+    /// one request for all of their ranges.
+    pub async fn multi_get(&self, keys: &[PackedKey]) -> Result<Vec<Option<Bytes>>> {
+        let locations = self.index.locate(keys).await?;
+        let mut by_pack: HashMap<String, Vec<(usize, Range<usize>)>> = HashMap::new();
+        for (i, location) in locations.into_iter().enumerate() {
+            if let Some(location) = location {
+                let range = location.range();
+                by_pack.entry(location.pack).or_default().push((i, range));
+            }
+        }
+        let reads: Vec<_> = futures::stream::iter(by_pack)
+            .map(|(pack, values)| async move {
+                let ranges: Vec<_> = values.iter().map(|(_, range)| range.clone()).collect();
+                let bytes = self
+                    .store
+                    .get_byte_ranges(&pack_path(&self.prefix, &pack), &ranges)
+                    .await?;
+                Ok::<_, anyhow::Error>(values.into_iter().map(|(i, _)| i).zip(bytes))
+            })
+            .buffer_unordered(CONCURRENCY)
+            .try_collect()
+            .await?;
+        let mut values = vec![None; keys.len()];
+        for (i, bytes) in reads.into_iter().flatten() {
+            values[i] = Some(bytes);
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packed_key_value_store::{InMemoryPackIndex, PackReader, PackWriter, PackedKey};
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::DynObjectStore;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_pack_round_trip() -> anyhow::Result<()> {
+        let store: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let prefix = Path::from("packs");
+        let mut writer = PackWriter::new(store.clone(), prefix.clone(), 16, 8);
+        let key = |i: u8| PackedKey::new("tx", vec![i]);
+        assert!(writer.add(key(0), b"zero"));
+        assert!(writer.add(key(1), b"one"));
+        assert!(!writer.add(key(9), b"too large to pack"));
+        assert!(!writer.is_full());
+        let first = writer.flush().await?;
+        assert!(writer.add(key(2), b"two"));
+        assert!(writer.add(key(3), b""));
+        let second = writer.flush().await?;
+        assert!(writer.flush().await?.is_empty());
+        assert_eq!((first.len(), second.len()), (2, 2));
+        assert_ne!(first[0].1.pack, second[0].1.pack);
+
+        // Values are read back with the index written along the packs, or recorded by the writer
+        let loaded = InMemoryPackIndex::load(&store, &prefix).await?;
+        let recorded = InMemoryPackIndex::default();
+        recorded.insert(first.into_iter().chain(second));
+        for index in [loaded, recorded] {
+            let reader = PackReader::new(Arc::new(store.clone()), prefix.clone(), Arc::new(index));
+            let values = reader
+                .multi_get(&[key(2), key(9), key(0), key(3), key(1)])
+                .await?;
+            let values: Vec<_> = values
+                .iter()
+                .map(|value| value.as_ref().map(|bytes| bytes.to_vec()))
+                .collect();
+            assert_eq!(
+                values,
+                vec![
+                    Some(b"two".to_vec()),
+                    None,
+                    Some(b"zero".to_vec()),
+                    Some(vec![]),
+                    Some(b"one".to_vec())
+                ]
+            );
+        }
+        Ok(())
+    }
+}
@@ -2,19 +2,25 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    check_formal_snapshot_references, convert_archive_compression, copy_store_prefix,
     db_tool::{execute_db_tool_command, print_db_all_tables, DbToolCommand},
-    download_db_snapshot, download_formal_snapshot, dump_checkpoints_from_archive, get_object,
-    get_transaction_block, make_clients, restore_from_db_checkpoint, state_sync_from_archive,
-    verify_archive, verify_archive_by_checksum, ConciseObjectOutput, GroupedObjectOutput,
-    VerboseObjectOutput,
+    diff_formal_snapshots, download_db_snapshot, download_formal_snapshot,
+    dump_checkpoints_from_archive, gc_formal_snapshots, genesis_objects_from_formal_snapshot,
+    get_object, get_transaction_block, make_clients, restore_from_db_checkpoint,
+    state_sync_from_archive,
+    store_tool::{execute_store_tool_command, StoreToolCommand},
+    validate_object_store, verify_archive, verify_archive_by_checksum, ConciseObjectOutput,
+    GroupedObjectOutput, VerboseObjectOutput,
 };
 use anyhow::Result;
 use std::env;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use sui_config::genesis::Genesis;
 use sui_core::authority_client::AuthorityAPI;
 use sui_protocol_config::Chain;
 use sui_replay::{execute_replay_command, ReplayToolCommand};
+use sui_snapshot::genesis::GenesisModifications;
 use telemetry_subscribers::TracingHandle;
 
 use sui_types::{base_types::*, object::Owner};
@@ -24,6 +30,7 @@ use fastcrypto::encoding::Encoding;
 use sui_config::Config;
 use sui_core::authority_aggregator::AuthorityAggregatorBuilder;
 use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreType};
+use sui_storage::FileCompression;
 use sui_types::messages_checkpoint::{
     CheckpointRequest, CheckpointResponse, CheckpointSequenceNumber,
 };
@@ -122,6 +129,111 @@ pub enum ToolCommand {
         cmd: Option<DbToolCommand>,
     },
 
+    /// Inspect and modify the contents of an object store (archives, snapshots, db checkpoints)
+    #[command(name = "store")]
+    Store {
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+        #[command(subcommand)]
+        cmd: StoreToolCommand,
+    },
+
+    /// Write, read, list and delete a probe object to check an object store config, e.g. the
+    /// archive or snapshot store of a node, before starting the node with it
+    #[command(name = "validate-object-store")]
+    ValidateObjectStore {
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+    },
+
+    /// Compare the live object sets of two formal snapshots
+    #[command(name = "diff-snapshots")]
+    DiffSnapshots {
+        #[arg(long = "from-epoch")]
+        from_epoch: u64,
+        #[arg(long = "to-epoch")]
+        to_epoch: u64,
+        /// Local directory to stage snapshot files in while diffing
+        #[arg(long = "path", default_value = "/tmp")]
+        path: PathBuf,
+        /// Number of parallel downloads to perform. Defaults to a reasonable
+        /// value based on number of available logical cores.
+        #[arg(long = "num-parallel-downloads")]
+        num_parallel_downloads: Option<usize>,
+        /// If specified, write every differing object as CSV to this file
+        #[arg(long = "detail-output")]
+        detail_output: Option<PathBuf>,
+        /// Snapshot store holding both epochs
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+    },
+
+    /// Delete the formal snapshots older than the latest `--retain-latest` ones, and the snapshot
+    /// files referenced by none of the retained snapshots' MANIFESTs
+    #[command(name = "gc-snapshots")]
+    GcSnapshots {
+        #[arg(long = "retain-latest")]
+        retain_latest: NonZeroUsize,
+        /// Print the files that would be deleted without deleting them
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+        #[arg(long = "concurrency", default_value = "16")]
+        concurrency: NonZeroUsize,
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+    },
+
+    /// Rebuild the file references of formal snapshots from their MANIFESTs, and report
+    /// referenced files that are missing and snapshot files that aren't referenced
+    #[command(name = "check-snapshot-references")]
+    CheckSnapshotReferences {
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+    },
+
+    /// Copy every object under a prefix from one object store to another, e.g. to move an
+    /// archive between clouds, verifying the checksum of every copy
+    #[command(name = "copy-prefix")]
+    CopyPrefix {
+        /// YAML object store config of the source store
+        #[arg(long = "src-config")]
+        src_config: PathBuf,
+        /// YAML object store config of the destination store
+        #[arg(long = "dest-config")]
+        dest_config: PathBuf,
+        #[arg(long = "prefix", default_value = "")]
+        prefix: String,
+        /// Local file recording the copied objects, to resume an interrupted copy
+        #[arg(long = "checkpoint-file")]
+        checkpoint_file: Option<PathBuf>,
+        #[arg(long = "concurrency", default_value = "16")]
+        concurrency: NonZeroUsize,
+    },
+
+    /// Write the objects of a formal snapshot, with allow-listed modifications, as genesis
+    /// objects for a private fork, to be passed to `sui genesis --with-objects`
+    #[command(name = "genesis-objects-from-snapshot")]
+    GenesisObjectsFromSnapshot {
+        #[arg(long = "epoch")]
+        epoch: u64,
+        /// Local directory to stage snapshot files in
+        #[arg(long = "path", default_value = "/tmp")]
+        path: PathBuf,
+        /// Number of parallel downloads to perform. Defaults to a reasonable
+        /// value based on number of available logical cores.
+        #[arg(long = "num-parallel-downloads")]
+        num_parallel_downloads: Option<usize>,
+        /// YAML file listing the modifications to make to the snapshot objects
+        #[arg(long = "modifications")]
+        modifications: Option<PathBuf>,
+        /// File to write the BCS encoded genesis objects to
+        #[arg(long = "output")]
+        output: PathBuf,
+        /// Snapshot store holding the epoch
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+    },
+
     /// Tool to sync the node from archive store
     #[command(name = "sync-from-archive")]
     SyncFromArchive {
@@ -155,6 +267,19 @@ pub enum ToolCommand {
         download_concurrency: usize,
     },
 
+    /// Tool to rewrite the files of the archive store in place with another compression. The
+    /// archive writer must be stopped while it runs. Interrupted conversions can be run again.
+    #[command(name = "convert-archive-compression")]
+    ConvertArchiveCompression {
+        #[command(flatten)]
+        object_store_config: ObjectStoreConfig,
+        /// Compression to rewrite the files with, none or zstd
+        #[arg(long = "compression")]
+        compression: FileCompression,
+        #[arg(long = "concurrency", default_value_t = 5)]
+        concurrency: usize,
+    },
+
     /// Tool to print archive contents in checkpoint range
     #[command(name = "dump-archive")]
     DumpArchiveByChecksum {
@@ -405,6 +530,104 @@ impl ToolCommand {
                     None => print_db_all_tables(path)?,
                 }
             }
+            ToolCommand::Store {
+                object_store_config,
+                cmd,
+            } => {
+                let store = object_store_config.make()?;
+                let deleter = object_store_config.make_batch_delete()?;
+                let versions = if cmd.reads_versions() {
+                    Some(object_store_config.make_versioned_reader()?)
+                } else {
+                    None
+                };
+                execute_store_tool_command(store, deleter, versions, cmd).await?;
+            }
+            ToolCommand::GcSnapshots {
+                retain_latest,
+                dry_run,
+                concurrency,
+                object_store_config,
+            } => {
+                gc_formal_snapshots(object_store_config, retain_latest, dry_run, concurrency)
+                    .await?;
+            }
+            ToolCommand::ValidateObjectStore {
+                object_store_config,
+            } => {
+                validate_object_store(object_store_config).await?;
+            }
+            ToolCommand::CheckSnapshotReferences {
+                object_store_config,
+            } => {
+                check_formal_snapshot_references(object_store_config).await?;
+            }
+            ToolCommand::CopyPrefix {
+                src_config,
+                dest_config,
+                prefix,
+                checkpoint_file,
+                concurrency,
+            } => {
+                copy_store_prefix(
+                    ObjectStoreConfig::load(src_config)?,
+                    ObjectStoreConfig::load(dest_config)?,
+                    &prefix,
+                    checkpoint_file.as_deref(),
+                    concurrency,
+                )
+                .await?;
+            }
+            ToolCommand::DiffSnapshots {
+                from_epoch,
+                to_epoch,
+                path,
+                num_parallel_downloads,
+                detail_output,
+                object_store_config,
+            } => {
+                let num_parallel_downloads = num_parallel_downloads.unwrap_or_else(|| {
+                    num_cpus::get()
+                        .checked_sub(1)
+                        .expect("Failed to get number of CPUs")
+                });
+                diff_formal_snapshots(
+                    &path,
+                    from_epoch,
+                    to_epoch,
+                    object_store_config,
+                    num_parallel_downloads,
+                    detail_output,
+                )
+                .await?;
+            }
+            ToolCommand::GenesisObjectsFromSnapshot {
+                epoch,
+                path,
+                num_parallel_downloads,
+                modifications,
+                output,
+                object_store_config,
+            } => {
+                let num_parallel_downloads = num_parallel_downloads.unwrap_or_else(|| {
+                    num_cpus::get()
+                        .checked_sub(1)
+                        .expect("Failed to get number of CPUs")
+                });
+                let modifications = match modifications {
+                    Some(path) => GenesisModifications::load(&path)?,
+                    None => GenesisModifications::default(),
+                };
+                genesis_objects_from_formal_snapshot(
+                    &path,
+                    epoch,
+                    object_store_config,
+                    num_parallel_downloads,
+                    &modifications,
+                    &output,
+                )
+                .await?;
+            }
             ToolCommand::DumpValidators { genesis, concise } => {
                 let genesis = Genesis::load(genesis).unwrap();
                 if !concise {
@@ -578,6 +801,7 @@ impl ToolCommand {
                         azure_storage_account: env::var("AZURE_SNAPSHOT_STORAGE_ACCOUNT").ok(),
                         azure_storage_access_key: env::var("AZURE_SNAPSHOT_STORAGE_ACCESS_KEY")
                             .ok(),
+                        azure_storage_sas_token: env::var("AZURE_SNAPSHOT_STORAGE_SAS_TOKEN").ok(),
                         object_store_connection_limit: 200,
                         no_sign_request,
                         ..Default::default()
@@ -595,6 +819,9 @@ impl ToolCommand {
                             );
                         }
                     }
+                    ObjectStoreType::Memory => {
+                        panic!("Download from an in-memory store is not supported")
+                    }
                 };
 
                 let archive_bucket = archive_bucket.or_else(|| match network {
@@ -644,6 +871,7 @@ impl ToolCommand {
                         bucket: archive_bucket,
                         azure_storage_account: env::var("AZURE_ARCHIVE_STORAGE_ACCOUNT").ok(),
                         azure_storage_access_key: env::var("AZURE_ARCHIVE_STORAGE_ACCESS_KEY").ok(),
+                        azure_storage_sas_token: env::var("AZURE_ARCHIVE_STORAGE_SAS_TOKEN").ok(),
                         object_store_connection_limit: 200,
                         no_sign_request,
                         ..Default::default()
@@ -651,6 +879,9 @@ impl ToolCommand {
                     ObjectStoreType::File => {
                         panic!("Download from local filesystem is not supported")
                     }
+                    ObjectStoreType::Memory => {
+                        panic!("Download from an in-memory store is not supported")
+                    }
                 };
 
                 if formal {
@@ -717,6 +948,13 @@ impl ToolCommand {
             } => {
                 verify_archive_by_checksum(object_store_config, download_concurrency).await?;
             }
+            ToolCommand::ConvertArchiveCompression {
+                object_store_config,
+                compression,
+                concurrency,
+            } => {
+                convert_archive_compression(object_store_config, compression, concurrency).await?;
+            }
             ToolCommand::DumpArchiveByChecksum {
                 object_store_config,
                 start,
@@ -9,6 +9,7 @@ use futures::future::AbortHandle;
 use itertools::Itertools;
 use std::collections::BTreeMap;
 use std::fmt::Write;
+use std::io::Write as _;
 use std::num::NonZeroUsize;
 use std::ops::Range;
 use std::path::{Path, PathBuf};
@@ -32,6 +33,7 @@ use tokio::time::Instant;
 
 use ::object_store::ObjectMeta;
 use anyhow::anyhow;
+use comfy_table::Table;
 use eyre::ContextCompat;
 use fastcrypto::hash::MultisetHash;
 use futures::{StreamExt, TryStreamExt};
@@ -46,11 +48,16 @@ use sui_core::checkpoints::CheckpointStore;
 use sui_core::db_checkpoint_handler::SUCCESS_MARKER;
 use sui_core::epoch::committee_store::CommitteeStore;
 use sui_core::storage::RocksDbStore;
+use sui_snapshot::diff::{DiffCounts, SnapshotDiff};
+use sui_snapshot::gc::{check_references, collect_garbage};
+use sui_snapshot::genesis::{genesis_objects_from_snapshot, GenesisModifications};
 use sui_snapshot::reader::StateSnapshotReaderV1;
 use sui_snapshot::setup_db_state;
+use sui_storage::object_store::checksum::{is_checksum_path, ChecksummedStore};
+use sui_storage::object_store::migrate::copy_prefix;
 use sui_storage::object_store::util::{copy_file, get_path};
 use sui_storage::object_store::{ObjectStoreConfig, ObjectStoreType};
-use sui_storage::verify_checkpoint_range;
+use sui_storage::{verify_checkpoint_range, FileCompression};
 use sui_types::messages_checkpoint::{CheckpointCommitment, ECMHLiveObjectSetDigest};
 use sui_types::messages_grpc::{
     ObjectInfoRequest, ObjectInfoRequestKind, ObjectInfoResponse, TransactionInfoRequest,
@@ -63,6 +70,7 @@ use typed_store::rocks::MetricConf;
 
 pub mod commands;
 pub mod db_tool;
+pub mod store_tool;
 
 // This functions requires at least one of genesis or fullnode_rpc to be `Some`.
 async fn make_clients(
@@ -671,6 +679,7 @@ fn start_summary_sync(
         // set up download of checkpoint summaries
         let config = ArchiveReaderConfig {
             remote_store_config: archive_store_config,
+            mirror_store_configs: vec![],
             download_concurrency: NonZeroUsize::new(num_parallel_downloads).unwrap(),
             use_for_pruning_watermark: false,
         };
@@ -793,6 +802,255 @@ fn start_summary_sync(
     })
 }
 
+/// Compare the formal snapshots taken at the end of `from_epoch` and `to_epoch`, printing
+/// added/removed/changed object counts by type and package. Per object differences are written
+/// as CSV to `detail_output`, if provided.
+pub async fn diff_formal_snapshots(
+    path: &Path,
+    from_epoch: EpochId,
+    to_epoch: EpochId,
+    snapshot_store_config: ObjectStoreConfig,
+    num_parallel_downloads: usize,
+    detail_output: Option<PathBuf>,
+) -> Result<(), anyhow::Error> {
+    let snapshot_dir = path.join("snapshot_diff");
+    if snapshot_dir.exists() {
+        fs::remove_dir_all(snapshot_dir.clone())?;
+    }
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(snapshot_dir.clone()),
+        ..Default::default()
+    };
+    let m = MultiProgress::new();
+    let mut readers = Vec::with_capacity(2);
+    for epoch in [from_epoch, to_epoch] {
+        readers.push(
+            StateSnapshotReaderV1::new(
+                epoch,
+                &snapshot_store_config,
+                &local_store_config,
+                usize::MAX,
+                NonZeroUsize::new(num_parallel_downloads).unwrap(),
+                m.clone(),
+            )
+            .await?,
+        );
+    }
+    let diff = SnapshotDiff::compute(&readers[0], &readers[1]).await?;
+    fs::remove_dir_all(snapshot_dir)?;
+
+    println!("Snapshot diff from epoch {from_epoch} to epoch {to_epoch}");
+    print_diff_counts("Total", [("all".to_string(), diff.total)]);
+    print_diff_counts("Type", diff.by_type.clone());
+    print_diff_counts("Package", diff.by_package.clone());
+
+    if let Some(detail_output) = detail_output {
+        let mut out = io::BufWriter::new(fs::File::create(&detail_output)?);
+        writeln!(
+            out,
+            "object_id,diff,from_version,from_digest,to_version,to_digest,type,package"
+        )?;
+        for object in &diff.objects {
+            let (type_, package) = object
+                .class
+                .as_ref()
+                .map(|class| (class.type_.as_str(), class.package.as_str()))
+                .unwrap_or_default();
+            let (from_version, from_digest) = object
+                .from
+                .map(|r| (r.1.value().to_string(), r.2.to_string()))
+                .unwrap_or_default();
+            let (to_version, to_digest) = object
+                .to
+                .map(|r| (r.1.value().to_string(), r.2.to_string()))
+                .unwrap_or_default();
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                object.object_id,
+                object.kind,
+                from_version,
+                from_digest,
+                to_version,
+                to_digest,
+                type_,
+                package
+            )?;
+        }
+        out.flush()?;
+        println!(
+            "Wrote {} object differences to {}",
+            diff.objects.len(),
+            detail_output.display()
+        );
+    }
+    Ok(())
+}
+
+/// Delete the formal snapshots of the store but the `retain_latest` latest ones, and the snapshot
+/// files no retained MANIFEST references.
+pub async fn gc_formal_snapshots(
+    snapshot_store_config: ObjectStoreConfig,
+    retain_latest: NonZeroUsize,
+    dry_run: bool,
+    concurrency: NonZeroUsize,
+) -> Result<(), anyhow::Error> {
+    let store = snapshot_store_config.make()?;
+    let deleter = snapshot_store_config.make_batch_delete()?;
+    let summary = collect_garbage(&store, &deleter, retain_latest, dry_run, concurrency).await?;
+    for path in &summary.garbage {
+        if dry_run {
+            println!("would delete {path}");
+        }
+    }
+    for (path, e) in &summary.failed {
+        eprintln!("failed to delete {path}: {e}");
+    }
+    println!(
+        "Retained epochs {:?}, expired epochs {:?}: {} files {}",
+        summary.retained_epochs,
+        summary.expired_epochs,
+        summary.garbage.len(),
+        if dry_run { "to delete" } else { "deleted" }
+    );
+    if !summary.failed.is_empty() {
+        return Err(anyhow!("Failed to delete {} files", summary.failed.len()));
+    }
+    Ok(())
+}
+
+/// Rebuild the references of the formal snapshots of the store from their MANIFESTs, and report
+/// the referenced files that are missing and the snapshot files that aren't referenced.
+pub async fn check_formal_snapshot_references(
+    snapshot_store_config: ObjectStoreConfig,
+) -> Result<(), anyhow::Error> {
+    let store = snapshot_store_config.make()?;
+    let report = check_references(&store).await?;
+    for path in &report.missing {
+        println!("missing {path}");
+    }
+    for path in &report.unreferenced {
+        println!("unreferenced {path}");
+    }
+    println!(
+        "{} referenced files, {} missing, {} unreferenced",
+        report.references.files().count(),
+        report.missing.len(),
+        report.unreferenced.len()
+    );
+    if !report.missing.is_empty() {
+        return Err(anyhow!(
+            "{} files referenced by snapshots are missing",
+            report.missing.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Check that the store can be written, read, listed and deleted from, reporting the step that
+/// failed and its likely cause otherwise.
+pub async fn validate_object_store(
+    object_store_config: ObjectStoreConfig,
+) -> Result<(), anyhow::Error> {
+    object_store_config
+        .validate()
+        .await
+        .map_err(|e| anyhow!("Object store config is invalid, {e}"))?;
+    println!("Object store config is valid");
+    Ok(())
+}
+
+/// Copy every object under `prefix` of the source store to the destination store, skipping the
+/// objects recorded in `checkpoint_file` by a previous run.
+pub async fn copy_store_prefix(
+    src_store_config: ObjectStoreConfig,
+    dest_store_config: ObjectStoreConfig,
+    prefix: &str,
+    checkpoint_file: Option<&Path>,
+    concurrency: NonZeroUsize,
+) -> Result<(), anyhow::Error> {
+    let src_store = src_store_config.make()?;
+    let dest_store = dest_store_config.make()?;
+    let progress_bar = ProgressBar::new(0).with_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {wide_bar} {pos}/{len} objects ({msg})")
+            .unwrap(),
+    );
+    let summary = copy_prefix(
+        &src_store,
+        &dest_store,
+        &get_path(prefix),
+        checkpoint_file,
+        concurrency,
+        Some(progress_bar.clone()),
+    )
+    .await?;
+    progress_bar.finish_and_clear();
+    println!(
+        "Copied {} objects ({} bytes) in {:?} at {:.2} MiB/s, {} already copied",
+        summary.copied,
+        summary.bytes,
+        summary.elapsed,
+        summary.bytes_per_second() / (1024.0 * 1024.0),
+        summary.skipped
+    );
+    Ok(())
+}
+
+/// Write the live objects of the formal snapshot taken at the end of `epoch`, with the
+/// `modifications` applied, to `output` as BCS encoded genesis objects.
+pub async fn genesis_objects_from_formal_snapshot(
+    path: &Path,
+    epoch: EpochId,
+    snapshot_store_config: ObjectStoreConfig,
+    num_parallel_downloads: usize,
+    modifications: &GenesisModifications,
+    output: &Path,
+) -> Result<(), anyhow::Error> {
+    let snapshot_dir = path.join("snapshot_genesis");
+    if snapshot_dir.exists() {
+        fs::remove_dir_all(snapshot_dir.clone())?;
+    }
+    let local_store_config = ObjectStoreConfig {
+        object_store: Some(ObjectStoreType::File),
+        directory: Some(snapshot_dir.clone()),
+        ..Default::default()
+    };
+    let reader = StateSnapshotReaderV1::new(
+        epoch,
+        &snapshot_store_config,
+        &local_store_config,
+        usize::MAX,
+        NonZeroUsize::new(num_parallel_downloads).unwrap(),
+        MultiProgress::new(),
+    )
+    .await?;
+    let objects = genesis_objects_from_snapshot(&reader, modifications).await?;
+    fs::remove_dir_all(snapshot_dir)?;
+
+    fs::write(output, bcs::to_bytes(&objects)?)?;
+    println!(
+        "Wrote {} genesis objects from epoch {epoch} to {}",
+        objects.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn print_diff_counts(header: &str, counts: impl IntoIterator<Item = (String, DiffCounts)>) {
+    let mut table = Table::new();
+    table.set_header(vec![header, "Added", "Removed", "Changed"]);
+    for (key, counts) in counts {
+        table.add_row(vec![
+            key,
+            counts.added.to_string(),
+            counts.removed.to_string(),
+            counts.changed.to_string(),
+        ]);
+    }
+    println!("{table}");
+}
+
 pub async fn download_formal_snapshot(
     path: &Path,
     epoch: EpochId,
@@ -1069,6 +1327,8 @@ pub async fn download_db_snapshot(
                 .objects,
         );
     }
+    // Checksums are not restored, but used to verify the files they were written along with.
+    files.retain(|f| !is_checksum_path(&f.location));
     let total_bytes: usize = files.iter().map(|f| f.size).sum();
     info!(
         "Total bytes to download: {}MiB",
@@ -1132,7 +1392,7 @@ pub async fn download_db_snapshot(
         futures::stream::iter(files.iter())
             .map(|file| {
                 let local_store = local_store.clone();
-                let remote_store = remote_store.clone();
+                let remote_store = ChecksummedStore::new(remote_store.clone());
                 let counter_cloned = file_counter.clone();
                 async move {
                     counter_cloned.fetch_add(1, Ordering::Relaxed);
@@ -1219,6 +1479,7 @@ pub async fn dump_checkpoints_from_archive(
     let metrics = ArchiveReaderMetrics::new(&Registry::default());
     let config = ArchiveReaderConfig {
         remote_store_config,
+        mirror_store_configs: vec![],
         download_concurrency: NonZeroUsize::new(1).unwrap(),
         use_for_pruning_watermark: false,
     };
@@ -1266,6 +1527,24 @@ pub async fn verify_archive_by_checksum(
     verify_archive_with_checksums(remote_store_config, concurrency).await
 }
 
+pub async fn convert_archive_compression(
+    remote_store_config: ObjectStoreConfig,
+    compression: FileCompression,
+    concurrency: usize,
+) -> Result<()> {
+    let summary = sui_archival::converter::convert_archive_compression(
+        remote_store_config,
+        compression,
+        concurrency,
+    )
+    .await?;
+    info!(
+        "Converted {} files to {:?}, {} were already converted",
+        summary.converted, compression, summary.skipped
+    );
+    Ok(())
+}
+
 pub async fn state_sync_from_archive(
     path: &Path,
     genesis: &Path,
@@ -1317,6 +1596,7 @@ pub async fn state_sync_from_archive(
     let state_sync_store = RocksDbStore::new(store, committee_store, checkpoint_store.clone());
     let archive_reader_config = ArchiveReaderConfig {
         remote_store_config,
+        mirror_store_configs: vec![],
         download_concurrency: NonZeroUsize::new(concurrency).unwrap(),
         use_for_pruning_watermark: false,
     };
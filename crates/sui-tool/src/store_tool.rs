@@ -0,0 +1,529 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use clap::Parser;
+use futures::TryStreamExt;
+use glob::{MatchOptions, Pattern};
+use indicatif::{ProgressBar, ProgressStyle};
+use object_store::path::Path;
+use object_store::{DynObjectStore, ObjectMeta, ObjectStore};
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use sui_storage::object_store::gc::gc_prefix;
+use sui_storage::object_store::inventory::inventory;
+use sui_storage::object_store::util::{
+    get, put, sync_dir_to_store, sync_store_to_dir, SyncSummary,
+};
+use sui_storage::object_store::versions::ObjectStoreVersionExt;
+use sui_storage::object_store::{ObjectStoreDeleteExt, ObjectStoreListExt};
+
+/// Characters that make a path segment a glob pattern rather than a literal.
+const GLOB_CHARS: &[char] = &['*', '?', '['];
+/// Glob wildcards only match within a path segment, as in a shell; `**` matches across them.
+const GLOB_MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+/// Entries printed per page of directory listings.
+const LIST_PAGE_SIZE: usize = 1000;
+
+#[derive(Parser)]
+#[command(rename_all = "kebab-case")]
+pub enum StoreToolCommand {
+    /// List objects under a prefix, or matching a glob (e.g. `epoch_10/*.obj`)
+    Ls {
+        /// Prefix or glob to list. Lists the whole store if omitted.
+        path: Option<String>,
+        /// Show size and last modified time of each object
+        #[arg(long, short)]
+        long: bool,
+        /// List the objects directly under the prefix and the prefixes of the objects nested
+        /// deeper, e.g. the epoch directories of a snapshot store, rather than every object
+        #[arg(long, short)]
+        directory: bool,
+    },
+    /// Show metadata of the objects matching a path or glob
+    Stat { path: String },
+    /// Write the contents of the objects matching a path or glob to stdout
+    Cat { path: String },
+    /// List the versions of an object, newest first, in an S3 or GCS bucket with object
+    /// versioning enabled
+    Versions { path: String },
+    /// Write the contents of a version of an object to stdout, e.g. to recover a manifest that
+    /// was overwritten
+    CatVersion {
+        path: String,
+        /// S3 version ID or GCS generation, as listed by `versions`
+        version: String,
+    },
+    /// Download the objects matching a path or glob into a local directory
+    Get {
+        path: String,
+        /// Local directory to download into. Object paths relative to the
+        /// non-glob prefix of `path` are preserved.
+        #[arg(default_value = ".")]
+        dest: PathBuf,
+    },
+    /// Upload the local files matching a path or glob
+    Put {
+        /// Local file, or glob over local files
+        src: String,
+        /// Destination path in the store. If `src` matches more than one file,
+        /// this is used as a prefix for the file names.
+        dest: String,
+    },
+    /// Delete the object at a path, or the objects matching a glob
+    Rm {
+        path: String,
+        /// Delete all objects under `path` as a prefix if there is no object at exactly that path
+        #[arg(long, short)]
+        recursive: bool,
+        /// Print the objects that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+        /// Number of delete requests in flight. S3 objects are deleted up to 1000 per request.
+        #[arg(long, default_value = "16")]
+        concurrency: NonZeroUsize,
+    },
+    /// Delete the objects under a prefix last modified longer ago than the retention, e.g. old
+    /// debug dumps or DB checkpoints
+    Gc {
+        prefix: String,
+        #[arg(long)]
+        retention_hours: u64,
+        /// Print the objects that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the object count, size and age of the objects under a prefix, in total and for
+    /// each epoch directory, and the missing epochs, as JSON
+    Inventory {
+        /// Prefix to inventory. Inventories the whole store if omitted.
+        prefix: Option<String>,
+    },
+    /// Upload the files of a local directory that are missing or changed under a prefix
+    SyncUp {
+        dir: PathBuf,
+        prefix: String,
+        /// Delete the objects under the prefix with no file in the directory
+        #[arg(long)]
+        delete: bool,
+        #[arg(long, default_value = "16")]
+        concurrency: NonZeroUsize,
+    },
+    /// Download the objects under a prefix that are missing or changed in a local directory
+    SyncDown {
+        prefix: String,
+        dir: PathBuf,
+        /// Delete the files in the directory with no object under the prefix
+        #[arg(long)]
+        delete: bool,
+        #[arg(long, default_value = "16")]
+        concurrency: NonZeroUsize,
+    },
+}
+
+impl StoreToolCommand {
+    /// Whether the command reads previous versions of objects, with a versioned reader of the
+    /// store.
+    pub fn reads_versions(&self) -> bool {
+        matches!(self, Self::Versions { .. } | Self::CatVersion { .. })
+    }
+}
+
+/// `versions` is only needed by the commands [reading versions](StoreToolCommand::reads_versions).
+pub async fn execute_store_tool_command(
+    store: Arc<DynObjectStore>,
+    deleter: Arc<dyn ObjectStoreDeleteExt>,
+    versions: Option<Arc<dyn ObjectStoreVersionExt>>,
+    cmd: StoreToolCommand,
+) -> Result<()> {
+    match cmd {
+        StoreToolCommand::Ls {
+            path,
+            long,
+            directory: true,
+        } => {
+            let prefix = match path.as_deref().map(split_glob) {
+                Some((_, Some(_))) => bail!("Directory listings don't support globs"),
+                Some((prefix, None)) => prefix,
+                None => None,
+            };
+            let page_size = NonZeroUsize::new(LIST_PAGE_SIZE).unwrap();
+            let mut token = None;
+            loop {
+                let page = store
+                    .list_objects_page(prefix.as_ref(), token.as_deref(), page_size)
+                    .await?;
+                for prefix in page.common_prefixes {
+                    println!("{prefix}/");
+                }
+                for object in page.objects {
+                    print_object(&object, long);
+                }
+                token = page.next_token;
+                if token.is_none() {
+                    break;
+                }
+            }
+        }
+        StoreToolCommand::Ls { path, long, .. } => {
+            let objects = match path {
+                Some(path) => find_objects(&store, &path).await?,
+                None => list_all(&store, None).await?,
+            };
+            for object in objects {
+                print_object(&object, long);
+            }
+        }
+        StoreToolCommand::Stat { path } => {
+            for object in find_objects_strict(&store, &path).await? {
+                println!("location:      {}", object.location);
+                println!("size:          {}", object.size);
+                println!("last modified: {}", object.last_modified.to_rfc3339());
+                println!(
+                    "e_tag:         {}",
+                    object.e_tag.as_deref().unwrap_or("<none>")
+                );
+            }
+        }
+        StoreToolCommand::Cat { path } => {
+            for object in find_objects_strict(&store, &path).await? {
+                let bytes = get(&store, &object.location).await?;
+                let mut stdout = std::io::stdout();
+                stdout.write_all(&bytes)?;
+                stdout.flush()?;
+            }
+        }
+        StoreToolCommand::Versions { path } => {
+            let versions = versions
+                .ok_or_else(|| anyhow!("No reader of object versions"))?
+                .list_versions(&Path::from(path.as_str()))
+                .await?;
+            for version in versions {
+                println!(
+                    "{}  {:>12}  {}{}",
+                    version.version,
+                    version.size,
+                    version
+                        .last_modified
+                        .map_or_else(|| "-".to_string(), |time| time.to_rfc3339()),
+                    if version.is_latest { "  (latest)" } else { "" }
+                );
+            }
+        }
+        StoreToolCommand::CatVersion { path, version } => {
+            let bytes = versions
+                .ok_or_else(|| anyhow!("No reader of object versions"))?
+                .get_bytes_version(&Path::from(path), &version)
+                .await?;
+            let mut stdout = std::io::stdout();
+            stdout.write_all(&bytes)?;
+            stdout.flush()?;
+        }
+        StoreToolCommand::Get { path, dest } => {
+            let (prefix, _) = split_glob(&path);
+            for object in find_objects_strict(&store, &path).await? {
+                let relative = relative_to(&object.location, prefix.as_ref())?;
+                let local_path = dest.join(relative);
+                if let Some(parent) = local_path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                let bytes = get(&store, &object.location).await?;
+                tokio::fs::write(&local_path, bytes).await?;
+                println!("{} -> {}", object.location, local_path.display());
+            }
+        }
+        StoreToolCommand::Put { src, dest } => {
+            let files = glob::glob(&src)?
+                .filter_map(|entry| match entry {
+                    Ok(path) if path.is_file() => Some(Ok(path)),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if files.is_empty() {
+                bail!("No local files match {src}");
+            }
+
+            let single = files.len() == 1;
+            for file in files {
+                let location = if single {
+                    Path::from(dest.as_str())
+                } else {
+                    let file_name = file
+                        .file_name()
+                        .ok_or_else(|| anyhow!("Invalid file name: {}", file.display()))?
+                        .to_string_lossy();
+                    Path::from(format!("{}/{}", dest.trim_end_matches('/'), file_name))
+                };
+                let bytes = Bytes::from(tokio::fs::read(&file).await?);
+                put(&store, &location, bytes).await?;
+                println!("{} -> {}", file.display(), location);
+            }
+        }
+        StoreToolCommand::Rm {
+            path,
+            recursive,
+            dry_run,
+            concurrency,
+        } => {
+            if path.trim_matches('/').is_empty() {
+                bail!("Refusing to delete every object in the store");
+            }
+            let objects = if recursive || path.contains(GLOB_CHARS) {
+                find_objects_strict(&store, &path).await?
+            } else {
+                let location = Path::from(path.trim_matches('/'));
+                match store.head(&location).await {
+                    Ok(meta) => vec![meta],
+                    Err(object_store::Error::NotFound { .. }) => {
+                        bail!(
+                            "No object at {path}, pass --recursive to delete the objects under it"
+                        )
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            if dry_run {
+                for object in objects {
+                    println!("would delete {}", object.location);
+                }
+                return Ok(());
+            }
+            let locations: Vec<Path> = objects.into_iter().map(|object| object.location).collect();
+            let progress_bar = ProgressBar::new(locations.len() as u64).with_style(
+                ProgressStyle::with_template("[{elapsed_precise}] {wide_bar} {pos}/{len} deleted")
+                    .unwrap(),
+            );
+            let result = deleter
+                .delete_objects(&locations, concurrency, Some(progress_bar.clone()))
+                .await;
+            progress_bar.finish_and_clear();
+            for (location, e) in &result.failed {
+                eprintln!("failed to delete {location}: {e}");
+            }
+            println!(
+                "deleted {} objects, {} failed",
+                result.deleted.len(),
+                result.failed.len()
+            );
+            if !result.is_complete() {
+                bail!("Failed to delete {} objects", result.failed.len());
+            }
+        }
+        StoreToolCommand::Gc {
+            prefix,
+            retention_hours,
+            dry_run,
+        } => {
+            let retention = Duration::from_secs(retention_hours * 3600);
+            let summary = gc_prefix(&store, &Path::from(prefix), retention, dry_run, None).await?;
+            for object in &summary.expired {
+                if dry_run {
+                    println!("would delete {}", object.location);
+                } else {
+                    println!("deleted {}", object.location);
+                }
+            }
+            for (location, e) in &summary.failed {
+                eprintln!("failed to delete {location}: {e}");
+            }
+            println!(
+                "{} objects ({} bytes) {}, {} retained, {} failed",
+                summary.expired.len(),
+                summary.expired_bytes(),
+                if dry_run { "to delete" } else { "deleted" },
+                summary.retained,
+                summary.failed.len()
+            );
+            if !summary.failed.is_empty() {
+                bail!("Failed to delete {} objects", summary.failed.len());
+            }
+        }
+        StoreToolCommand::Inventory { prefix } => {
+            let prefix = prefix.map(Path::from);
+            let inventory = inventory(&store, prefix.as_ref()).await?;
+            println!("{}", serde_json::to_string_pretty(&inventory)?);
+        }
+        StoreToolCommand::SyncUp {
+            dir,
+            prefix,
+            delete,
+            concurrency,
+        } => {
+            let summary =
+                sync_dir_to_store(&dir, &store, &Path::from(prefix), concurrency, delete).await?;
+            print_sync_summary(&summary);
+        }
+        StoreToolCommand::SyncDown {
+            prefix,
+            dir,
+            delete,
+            concurrency,
+        } => {
+            let summary =
+                sync_store_to_dir(&store, &Path::from(prefix), &dir, concurrency, delete).await?;
+            print_sync_summary(&summary);
+        }
+    }
+    Ok(())
+}
+
+fn print_sync_summary(summary: &SyncSummary) {
+    for path in &summary.copied {
+        println!("copied {path}");
+    }
+    for path in &summary.deleted {
+        println!("deleted {path}");
+    }
+    println!(
+        "{} copied, {} unchanged, {} deleted",
+        summary.copied.len(),
+        summary.unchanged,
+        summary.deleted.len()
+    );
+}
+
+fn print_object(object: &ObjectMeta, long: bool) {
+    if long {
+        println!(
+            "{:>12}  {}  {}",
+            object.size,
+            object.last_modified.to_rfc3339(),
+            object.location
+        );
+    } else {
+        println!("{}", object.location);
+    }
+}
+
+/// Split `path` into the literal prefix before its first glob segment, and a pattern over the
+/// full path if it contains any glob characters.
+fn split_glob(path: &str) -> (Option<Path>, Option<String>) {
+    let path = path.trim_matches('/');
+    if !path.contains(GLOB_CHARS) {
+        return ((!path.is_empty()).then(|| Path::from(path)), None);
+    }
+
+    let prefix = path
+        .split('/')
+        .take_while(|segment| !segment.contains(GLOB_CHARS))
+        .collect::<Vec<_>>()
+        .join("/");
+    (
+        (!prefix.is_empty()).then(|| Path::from(prefix)),
+        Some(path.to_owned()),
+    )
+}
+
+async fn list_all(store: &Arc<DynObjectStore>, prefix: Option<&Path>) -> Result<Vec<ObjectMeta>> {
+    let mut objects: Vec<ObjectMeta> = store.list_objects(prefix).await?.try_collect().await?;
+    objects.sort_by(|a, b| a.location.cmp(&b.location));
+    Ok(objects)
+}
+
+/// Find the objects matching `path`: the object at exactly that location if it exists, else
+/// all objects under it as a prefix. Globs are matched against the full object location.
+async fn find_objects(store: &Arc<DynObjectStore>, path: &str) -> Result<Vec<ObjectMeta>> {
+    let (prefix, pattern) = split_glob(path);
+    let Some(pattern) = pattern else {
+        if let Some(location) = &prefix {
+            match store.head(location).await {
+                Ok(meta) => return Ok(vec![meta]),
+                Err(object_store::Error::NotFound { .. }) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        return list_all(store, prefix.as_ref()).await;
+    };
+
+    let pattern = Pattern::new(&pattern)?;
+    Ok(list_all(store, prefix.as_ref())
+        .await?
+        .into_iter()
+        .filter(|object| pattern.matches_with(object.location.as_ref(), GLOB_MATCH_OPTIONS))
+        .collect())
+}
+
+/// Like [`find_objects`], but fails if nothing matches.
+async fn find_objects_strict(store: &Arc<DynObjectStore>, path: &str) -> Result<Vec<ObjectMeta>> {
+    let objects = find_objects(store, path).await?;
+    if objects.is_empty() {
+        bail!("No objects match {path}");
+    }
+    Ok(objects)
+}
+
+/// The local path an object is downloaded to, relative to the prefix it was matched under.
+fn relative_to(location: &Path, prefix: Option<&Path>) -> Result<PathBuf> {
+    let parts = match prefix.and_then(|prefix| location.prefix_match(prefix)) {
+        // The location is the prefix itself, keep its file name.
+        Some(mut parts) => match parts.next() {
+            Some(first) => std::iter::once(first).chain(parts).collect::<Vec<_>>(),
+            None => location.parts().last().into_iter().collect(),
+        },
+        None => location.parts().collect(),
+    };
+    if parts.is_empty() {
+        bail!("Cannot derive a local path for {location}");
+    }
+    Ok(parts.iter().map(|part| part.as_ref()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_glob() {
+        assert_eq!(split_glob(""), (None, None));
+        assert_eq!(
+            split_glob("epoch_10/1.obj"),
+            (Some(Path::from("epoch_10/1.obj")), None)
+        );
+        assert_eq!(
+            split_glob("epoch_10/*.obj"),
+            (
+                Some(Path::from("epoch_10")),
+                Some("epoch_10/*.obj".to_owned())
+            )
+        );
+        assert_eq!(
+            split_glob("/epoch_*/MANIFEST"),
+            (None, Some("epoch_*/MANIFEST".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_glob_match_options() {
+        let pattern = Pattern::new("epoch_*/MANIFEST").unwrap();
+        assert!(pattern.matches_with("epoch_10/MANIFEST", GLOB_MATCH_OPTIONS));
+        assert!(!pattern.matches_with("epoch_10/backup/MANIFEST", GLOB_MATCH_OPTIONS));
+        let pattern = Pattern::new("epoch_10/**/MANIFEST").unwrap();
+        assert!(pattern.matches_with("epoch_10/backup/MANIFEST", GLOB_MATCH_OPTIONS));
+    }
+
+    #[test]
+    fn test_relative_to() {
+        let location = Path::from("epoch_10/1_1.obj");
+        assert_eq!(
+            relative_to(&location, Some(&Path::from("epoch_10"))).unwrap(),
+            PathBuf::from("1_1.obj")
+        );
+        assert_eq!(
+            relative_to(&location, Some(&location)).unwrap(),
+            PathBuf::from("1_1.obj")
+        );
+        assert_eq!(
+            relative_to(&location, None).unwrap(),
+            PathBuf::from("epoch_10/1_1.obj")
+        );
+    }
+}
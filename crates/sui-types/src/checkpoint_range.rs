@@ -0,0 +1,227 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Ranges of checkpoint sequence numbers.
+//!
+//! A [`CheckpointRange`] includes its start and excludes its end, whichever way it was built, so
+//! that ranges handed between the archive, backfills and pruning can't be read with the wrong
+//! bounds. Ranges stored by their first and last checkpoints, e.g. in file names of the archive or
+//! rows of the indexer, are built with [`CheckpointRange::inclusive`] and read back with
+//! [`CheckpointRange::last`].
+
+use std::fmt::{Display, Formatter};
+use std::ops::{Range, RangeInclusive};
+
+use crate::messages_checkpoint::CheckpointSequenceNumber;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CheckpointRange {
+    start: CheckpointSequenceNumber,
+    end: CheckpointSequenceNumber,
+}
+
+impl CheckpointRange {
+    /// Checkpoints from `start` up to, but excluding, `end`. Empty if `end` isn't after `start`.
+    pub fn new(start: CheckpointSequenceNumber, end: CheckpointSequenceNumber) -> Self {
+        Self {
+            start,
+            end: end.max(start),
+        }
+    }
+
+    /// Checkpoints from `first` to `last`, both included. Empty if `last` is before `first`.
+    pub fn inclusive(first: CheckpointSequenceNumber, last: CheckpointSequenceNumber) -> Self {
+        Self::new(first, last.saturating_add(1))
+    }
+
+    pub fn start(&self) -> CheckpointSequenceNumber {
+        self.start
+    }
+
+    /// First checkpoint after the range.
+    pub fn end(&self) -> CheckpointSequenceNumber {
+        self.end
+    }
+
+    /// Last checkpoint of the range, `None` if it is empty.
+    pub fn last(&self) -> Option<CheckpointSequenceNumber> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.end - 1)
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, checkpoint: CheckpointSequenceNumber) -> bool {
+        self.start <= checkpoint && checkpoint < self.end
+    }
+
+    pub fn iter(&self) -> Range<CheckpointSequenceNumber> {
+        self.start..self.end
+    }
+
+    /// Consecutive ranges of `size` checkpoints covering this one, the last of which may be
+    /// shorter.
+    pub fn chunks(&self, size: u64) -> impl Iterator<Item = CheckpointRange> {
+        assert!(size > 0, "Chunks must have at least one checkpoint");
+        let end = self.end;
+        (self.start..end)
+            .step_by(size as usize)
+            .map(move |start| CheckpointRange::new(start, start.saturating_add(size).min(end)))
+    }
+
+    /// At most `count` consecutive ranges covering this one, whose lengths differ by at most one
+    /// checkpoint. There are fewer than `count` if the range has fewer checkpoints.
+    pub fn shards(&self, count: usize) -> Vec<CheckpointRange> {
+        assert!(count > 0, "There must be at least one shard");
+        let count = (count as u64).min(self.len());
+        let mut shards = Vec::with_capacity(count as usize);
+        let mut start = self.start;
+        for shard in 0..count {
+            // The first `len % count` shards take one of the remaining checkpoints each
+            let len = self.len() / count + u64::from(shard < self.len() % count);
+            shards.push(CheckpointRange::new(start, start + len));
+            start += len;
+        }
+        shards
+    }
+
+    /// Checkpoints in both ranges.
+    pub fn intersection(&self, other: &CheckpointRange) -> CheckpointRange {
+        CheckpointRange::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// Checkpoints of this range that aren't in `other`, as the non-empty ranges before and after
+    /// `other`, in order.
+    pub fn difference(&self, other: &CheckpointRange) -> Vec<CheckpointRange> {
+        if other.is_empty() {
+            return if self.is_empty() { vec![] } else { vec![*self] };
+        }
+        [
+            CheckpointRange::new(self.start, other.start.min(self.end)),
+            CheckpointRange::new(other.end.max(self.start), self.end),
+        ]
+        .into_iter()
+        .filter(|range| !range.is_empty())
+        .collect()
+    }
+}
+
+impl Display for CheckpointRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {})", self.start, self.end)
+    }
+}
+
+impl From<Range<CheckpointSequenceNumber>> for CheckpointRange {
+    fn from(range: Range<CheckpointSequenceNumber>) -> Self {
+        CheckpointRange::new(range.start, range.end)
+    }
+}
+
+impl From<RangeInclusive<CheckpointSequenceNumber>> for CheckpointRange {
+    fn from(range: RangeInclusive<CheckpointSequenceNumber>) -> Self {
+        CheckpointRange::inclusive(*range.start(), *range.end())
+    }
+}
+
+impl From<CheckpointRange> for Range<CheckpointSequenceNumber> {
+    fn from(range: CheckpointRange) -> Self {
+        range.iter()
+    }
+}
+
+impl IntoIterator for CheckpointRange {
+    type Item = CheckpointSequenceNumber;
+    type IntoIter = Range<CheckpointSequenceNumber>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CheckpointRange;
+
+    #[test]
+    fn test_bounds() {
+        let range = CheckpointRange::inclusive(10, 19);
+        assert_eq!(range, CheckpointRange::new(10, 20));
+        assert_eq!(range, CheckpointRange::from(10..=19));
+        assert_eq!(
+            (range.start(), range.end(), range.last()),
+            (10, 20, Some(19))
+        );
+        assert_eq!(range.len(), 10);
+        assert!(range.contains(10) && range.contains(19) && !range.contains(20));
+        assert_eq!(range.to_string(), "[10, 20)");
+
+        let empty = CheckpointRange::inclusive(10, 9);
+        assert!(empty.is_empty() && empty.last().is_none());
+        assert!(CheckpointRange::new(10, 5).is_empty());
+        assert_eq!(empty.into_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_chunks_and_shards() {
+        let range = CheckpointRange::new(10, 20);
+        let chunks: Vec<_> = range.chunks(4).collect();
+        assert_eq!(
+            chunks,
+            vec![
+                (10..14).into(),
+                (14..18).into(),
+                CheckpointRange::new(18, 20)
+            ]
+        );
+        assert_eq!(CheckpointRange::new(10, 10).chunks(4).count(), 0);
+
+        assert_eq!(
+            range.shards(3),
+            vec![
+                (10..14).into(),
+                (14..17).into(),
+                CheckpointRange::new(17, 20)
+            ]
+        );
+        assert_eq!(CheckpointRange::new(10, 12).shards(3).len(), 2);
+        for count in 1..12 {
+            let shards = range.shards(count);
+            assert_eq!(shards.first().map(|s| s.start()), Some(10));
+            assert_eq!(shards.iter().map(|s| s.len()).sum::<u64>(), 10);
+            assert!(shards.windows(2).all(|w| w[0].end() == w[1].start()));
+        }
+    }
+
+    #[test]
+    fn test_intersection_and_difference() {
+        let range = CheckpointRange::new(10, 20);
+        assert_eq!(
+            range.intersection(&(15..30).into()),
+            CheckpointRange::new(15, 20)
+        );
+        assert!(range.intersection(&(20..30).into()).is_empty());
+        assert!(range.intersection(&(0..5).into()).is_empty());
+
+        assert_eq!(
+            range.difference(&(12..15).into()),
+            vec![(10..12).into(), CheckpointRange::new(15, 20)]
+        );
+        assert_eq!(
+            range.difference(&(0..15).into()),
+            vec![CheckpointRange::new(15, 20)]
+        );
+        assert_eq!(range.difference(&(20..30).into()), vec![range]);
+        assert_eq!(range.difference(&(15..15).into()), vec![range]);
+        assert!(range.difference(&(0..30).into()).is_empty());
+    }
+}
@@ -267,6 +267,22 @@ where
     Ok(ObjectID::try_from(&hash.as_ref()[0..ObjectID::LENGTH]).unwrap())
 }
 
+/// ID of the `Field` object of the dynamic object field of `parent` with the given key, which is
+/// the ID of the dynamic field whose key is wrapped in `dynamic_object_field::Wrapper`.
+pub fn derive_dynamic_object_field_id<T>(
+    parent: T,
+    key_type_tag: &TypeTag,
+    key_bytes: &[u8],
+) -> Result<ObjectID, bcs::Error>
+where
+    T: Into<SuiAddress>,
+{
+    let wrapper_type_tag = TypeTag::Struct(Box::new(
+        DynamicFieldInfo::dynamic_object_field_wrapper(key_type_tag.clone()),
+    ));
+    derive_dynamic_field_id(parent, &wrapper_type_tag, key_bytes)
+}
+
 /// Given a parent object ID (e.g. a table), and a `key`, retrieve the corresponding dynamic field object
 /// from the `object_store`. The key type `K` must implement `MoveTypeTagTrait` which has an associated
 /// function that returns the Move type tag.
@@ -29,6 +29,7 @@ pub mod accumulator;
 pub mod authenticator_state;
 pub mod balance;
 pub mod base_types;
+pub mod checkpoint_range;
 pub mod clock;
 pub mod coin;
 pub mod collection_types;
@@ -14,6 +14,7 @@ use crate::signature::VerifyParams;
 use crate::transaction::VersionedProtocolMessage;
 use fastcrypto::traits::KeyPair;
 use once_cell::sync::OnceCell;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use shared_crypto::intent::{Intent, IntentScope};
 use std::fmt::{Debug, Display, Formatter};
@@ -539,6 +540,25 @@ impl<T: Message, S> DerefMut for Envelope<T, S> {
     }
 }
 
+/// The schema of an envelope mirrors its serialized form: the message and its signature, without
+/// the cached digest.
+impl<T: Message + JsonSchema, S: JsonSchema> JsonSchema for Envelope<T, S> {
+    fn schema_name() -> String {
+        format!("Envelope_for_{}_and_{}", T::schema_name(), S::schema_name())
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        #[allow(dead_code)]
+        #[derive(JsonSchema)]
+        struct SerializedEnvelope<T, S> {
+            data: T,
+            auth_signature: S,
+        }
+
+        SerializedEnvelope::<T, S>::json_schema(gen)
+    }
+}
+
 impl<T: Message, S> From<VerifiedEnvelope<T, S>> for Envelope<T, S> {
     fn from(v: VerifiedEnvelope<T, S>) -> Self {
         v.0 .0
@@ -36,6 +36,7 @@ use sui_swarm_config::network_config::NetworkConfig;
 use sui_swarm_config::network_config_builder::ConfigBuilder;
 use sui_swarm_config::node_config_builder::FullnodeConfigBuilder;
 use sui_types::crypto::{SignatureScheme, SuiKeyPair};
+use sui_types::object::Object;
 use tracing::info;
 
 #[allow(clippy::large_enum_variant)]
@@ -86,6 +87,11 @@ pub enum SuiCommand {
             help = "Creates an extra faucet configuration for sui-test-validator persisted runs."
         )]
         with_faucet: bool,
+        #[clap(
+            long,
+            help = "Add the BCS encoded objects in this file to genesis, e.g. as written by `sui-tool genesis-objects-from-snapshot`"
+        )]
+        with_objects: Option<PathBuf>,
     },
     GenesisCeremony(Ceremony),
     /// Sui keystore tool.
@@ -167,7 +173,7 @@ impl SuiCommand {
             } => {
                 // Auto genesis if path is none and sui directory doesn't exists.
                 if config.is_none() && !sui_config_dir()?.join(SUI_NETWORK_CONFIG).exists() {
-                    genesis(None, None, None, false, None, None, false).await?;
+                    genesis(None, None, None, false, None, None, false, None).await?;
                 }
 
                 // Load the config of the Sui authority.
@@ -246,6 +252,7 @@ impl SuiCommand {
                 epoch_duration_ms,
                 benchmark_ips,
                 with_faucet,
+                with_objects,
             } => {
                 genesis(
                     from_config,
@@ -255,6 +262,7 @@ impl SuiCommand {
                     epoch_duration_ms,
                     benchmark_ips,
                     with_faucet,
+                    with_objects,
                 )
                 .await
             }
@@ -332,6 +340,7 @@ async fn genesis(
     epoch_duration_ms: Option<u64>,
     benchmark_ips: Option<Vec<String>>,
     with_faucet: bool,
+    with_objects: Option<PathBuf>,
 ) -> Result<(), anyhow::Error> {
     let sui_config_dir = &match working_dir {
         // if a directory is specified, it must exist (it
@@ -434,7 +443,16 @@ async fn genesis(
     let validator_info = genesis_conf.validator_config_info.take();
     let ssfn_info = genesis_conf.ssfn_config_info.take();
 
-    let builder = ConfigBuilder::new(sui_config_dir);
+    let mut builder = ConfigBuilder::new(sui_config_dir);
+    if let Some(path) = with_objects {
+        let objects: Vec<Object> = bcs::from_bytes(&fs::read(&path)?)
+            .map_err(|err| anyhow!(err).context(format!("Cannot read objects from {path:?}")))?;
+        info!(
+            "Adding {} objects from {path:?} to genesis...",
+            objects.len()
+        );
+        builder = builder.with_objects(objects);
+    }
     if let Some(epoch_duration_ms) = epoch_duration_ms {
         genesis_conf.parameters.epoch_duration_ms = epoch_duration_ms;
     }
@@ -73,6 +73,7 @@ async fn test_genesis() -> Result<(), anyhow::Error> {
         epoch_duration_ms: None,
         benchmark_ips: None,
         with_faucet: false,
+        with_objects: None,
     }
     .execute()
     .await?;
@@ -112,6 +113,7 @@ async fn test_genesis() -> Result<(), anyhow::Error> {
         epoch_duration_ms: None,
         benchmark_ips: None,
         with_faucet: false,
+        with_objects: None,
     }
     .execute()
     .await;